@@ -0,0 +1,111 @@
+//! Throughput of the transaction generator itself, independent of any rollup node.
+//!
+//! Every benchmark processes one transaction per iteration on a single thread, so the
+//! reported element throughput is transactions per second per core. Compare it with the
+//! configured TPS before attributing a missed target to the rollup:
+//!
+//! ```sh
+//! cargo bench --bench tx_generation
+//! ```
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use num::BigUint;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use simulation_tool::config::Config;
+use simulation_tool::rollup::types::packing::{
+    closest_packable_fee_amount, closest_packable_token_amount, pack_fee_amount, pack_token_amount,
+};
+use simulation_tool::rollup::types::tx::{TimeRange, Transfer, TxSignature};
+use simulation_tool::rollup::types::{AccountId, Address, Nonce, TokenId};
+use simulation_tool::transaction::{AddressDenylist, Transaction};
+use simulation_tool::wallet::signing_key::SigningKey;
+
+fn transfer(nonce: u32) -> Transfer {
+    Transfer {
+        account_id: AccountId(1),
+        from: Address::from_low_u64_be(1),
+        to: Address::from_low_u64_be(2),
+        token: TokenId(0),
+        amount: BigUint::from(1_000_000u32),
+        fee: BigUint::from(1_000u32),
+        nonce: Nonce(nonce),
+        signature: TxSignature::default(),
+        time_range: TimeRange::default(),
+    }
+}
+
+fn construction(c: &mut Criterion) {
+    let config = Config::default().transaction;
+    let denylist = AddressDenylist::new(&config);
+    let recipients: Vec<Address> = (2..100).map(Address::from_low_u64_be).collect();
+    let mut rng = StdRng::seed_from_u64(42);
+
+    let mut group = c.benchmark_group("construction");
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("generate_transfer", |b| {
+        b.iter(|| {
+            Transaction::generate_transfer(
+                &mut rng,
+                &config,
+                &denylist,
+                Address::from_low_u64_be(1),
+                &recipients,
+                TokenId(0),
+                BigUint::from(1_000u32),
+                Nonce(0),
+            )
+        })
+    });
+    group.bench_function("transfer_bytes", |b| {
+        let transfer = transfer(0);
+        b.iter(|| black_box(&transfer).get_bytes())
+    });
+    group.finish();
+}
+
+fn packing(c: &mut Criterion) {
+    let amount = BigUint::from(123_456_789_000_000_000u64);
+    let fee = BigUint::from(12_345_000_000_000u64);
+
+    let mut group = c.benchmark_group("packing");
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("pack_token_amount", |b| {
+        b.iter(|| pack_token_amount(black_box(&amount)))
+    });
+    group.bench_function("pack_fee_amount", |b| {
+        b.iter(|| pack_fee_amount(black_box(&fee)))
+    });
+    group.bench_function("closest_packable_token_amount", |b| {
+        b.iter(|| closest_packable_token_amount(black_box(&amount)))
+    });
+    group.bench_function("closest_packable_fee_amount", |b| {
+        b.iter(|| closest_packable_fee_amount(black_box(&fee)))
+    });
+    group.finish();
+}
+
+fn signing(c: &mut Criterion) {
+    let signing_key = SigningKey::from_seed(&[7u8; 32]).expect("seed is long enough");
+    let message = transfer(0).get_bytes();
+
+    let mut group = c.benchmark_group("signing");
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("sign_transfer", |b| {
+        b.iter(|| signing_key.sign(black_box(&message)))
+    });
+    group.bench_function("build_and_sign_transfer", |b| {
+        let mut nonce = 0;
+        b.iter(|| {
+            nonce += 1;
+            let mut transfer = transfer(nonce);
+            transfer.signature = signing_key.sign(&transfer.get_bytes());
+            transfer
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, construction, packing, signing);
+criterion_main!(benches);
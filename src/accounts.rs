@@ -0,0 +1,1339 @@
+use std::collections::HashMap;
+
+use ethers::signers::LocalWallet;
+use ethers::types::{Address, U256};
+use num::BigUint;
+use rand::Rng;
+use thiserror::Error;
+use tracing::info;
+
+use crate::activation::{
+    AccountActivator, ActivationConfig, ActivationSubmitter, ActivationSummary, ActivationTarget,
+};
+use crate::checkpoint::{AccountCheckpoint, CheckpointError};
+use crate::config::TransactionConfig;
+use crate::funding::{
+    FundingConfig, FundingError, FundingOrchestrator, FundingSteps, FundingSummary, FundingTarget,
+};
+use crate::progress::PhaseProgress;
+use crate::rollup::provider::{ClientError, Provider, ResponseResult};
+use crate::rollup::types::packing::closest_packable_token_amount;
+use crate::rollup::types::serde_wrappers::BigUintSerdeWrapper;
+use crate::rollup::types::{AccountId, ChangePubKeyFeeType, Nonce, Token, NFT};
+use crate::tagging::RunTag;
+use crate::transaction::{AddressDenylist, GenerationInput, Transaction, TransactionKind};
+use crate::wallet::account_state::{CompactionStats, LocalAccount, StateDiscrepancy};
+use crate::wallet::derivation::{derive_wallet, DerivationError, KeysConfig};
+use crate::wallet::keystore::{AccountKeystore, KeystoreError};
+use crate::wallet::nonce::{NonceLease, NonceManager, NonceRejection};
+use crate::wallet::Wallet;
+
+#[derive(Debug, Error)]
+pub enum AccountsError {
+    #[error("Unable to derive account keys: {0}")]
+    Derivation(#[from] DerivationError),
+    #[error("Unable to create account wallet: {0}")]
+    Wallet(#[from] ClientError),
+    #[error("Unable to fund accounts: {0}")]
+    Funding(#[from] FundingError),
+    #[error("None of the {0} accounts could be funded")]
+    NoneFunded(usize),
+    #[error("{0}")]
+    Keystore(#[from] KeystoreError),
+}
+
+/// Simulated account: its keys together with the simulator's view of its state.
+pub struct PoolAccount {
+    pub wallet: Wallet,
+    pub state: LocalAccount,
+}
+
+impl PoolAccount {
+    pub fn new(wallet: Wallet) -> Self {
+        let state = LocalAccount::new(wallet.address());
+        Self { wallet, state }
+    }
+
+    pub fn address(&self) -> Address {
+        self.wallet.address()
+    }
+
+    fn balance(&self, token: &Token) -> BigUint {
+        self.state
+            .balances
+            .get(&token.symbol)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// Accounts the simulated traffic is sent from and to.
+///
+/// The pool keeps nonces and balances locally so transactions can be generated without
+/// asking the server first; `sync` replaces the local view by the committed state.
+/// Senders are handed out round-robin, so the load is spread over all funded accounts.
+///
+/// Every generated transaction reserves its nonce with the [`NonceManager`] and is handed
+/// back with `accept` or `reject` once the server answered, so a rejection rewinds the
+/// sender to the nonce it did not consume.
+pub struct AccountPool {
+    accounts: Vec<PoolAccount>,
+    index_by_address: HashMap<Address, usize>,
+    next_sender: usize,
+    tag: Option<RunTag>,
+    nonces: NonceManager,
+    /// Transactions waiting for the server's answer with the lease of their nonce.
+    leases: HashMap<(Address, Nonce), (NonceLease, Transaction)>,
+}
+
+impl AccountPool {
+    pub fn new(wallets: Vec<Wallet>) -> Self {
+        let accounts: Vec<PoolAccount> = wallets.into_iter().map(PoolAccount::new).collect();
+        let index_by_address = accounts
+            .iter()
+            .enumerate()
+            .map(|(index, account)| (account.address(), index))
+            .collect();
+        Self {
+            accounts,
+            index_by_address,
+            next_sender: 0,
+            tag: None,
+            nonces: NonceManager::new(),
+            leases: HashMap::new(),
+        }
+    }
+
+    /// Marks the content of the NFTs the accounts mint with the tag of the run.
+    pub fn with_run_tag(mut self, tag: RunTag) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    /// Creates `count` wallets, derived from the mnemonic when `[keys]` is configured and random otherwise.
+    pub async fn generate(
+        count: u32,
+        keys: Option<&KeysConfig>,
+        progress: PhaseProgress,
+    ) -> Result<Self, AccountsError> {
+        let mut wallets = Vec::with_capacity(count as usize);
+        for index in 0..count {
+            let wallet = match keys {
+                Some(keys) => {
+                    let eth_signer = derive_wallet(
+                        &keys.mnemonic,
+                        &keys.derivation_path,
+                        keys.first_index + index,
+                    )?;
+                    Wallet::new(eth_signer).await?
+                }
+                None => Wallet::random().await?,
+            };
+            wallets.push(wallet);
+            progress.inc();
+        }
+        progress.finish();
+        Ok(Self::new(wallets))
+    }
+
+    /// Creates `count` wallets from the keys of the keystore, generating and storing random
+    /// keys for the accounts it does not hold yet.
+    pub async fn load_or_generate(
+        count: u32,
+        keystore: &AccountKeystore,
+        progress: PhaseProgress,
+    ) -> Result<Self, AccountsError> {
+        let mut signers = keystore.load()?;
+        signers.truncate(count as usize);
+        let stored = signers.len();
+        let generated: Vec<LocalWallet> = (stored..count as usize)
+            .map(|_| LocalWallet::new(&mut rand::thread_rng()))
+            .collect();
+        if !generated.is_empty() {
+            keystore.store(stored, &generated)?;
+            info!(
+                "Stored the keys of {} new accounts in {}",
+                generated.len(),
+                keystore.path().display()
+            );
+        }
+        signers.extend(generated);
+
+        let mut wallets = Vec::with_capacity(signers.len());
+        for signer in signers {
+            wallets.push(Wallet::new(signer).await?);
+            progress.inc();
+        }
+        progress.finish();
+        Ok(Self::new(wallets))
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    pub fn addresses(&self) -> Vec<Address> {
+        self.accounts.iter().map(PoolAccount::address).collect()
+    }
+
+    pub fn get(&self, address: &Address) -> Option<&PoolAccount> {
+        self.index_by_address
+            .get(address)
+            .map(|index| &self.accounts[*index])
+    }
+
+    pub fn get_mut(&mut self, address: &Address) -> Option<&mut PoolAccount> {
+        self.index_by_address
+            .get(address)
+            .map(|index| &mut self.accounts[*index])
+    }
+
+    /// Funds every account from the master wallet through L1 transfers and deposits of RBTC
+    /// and the `[[funding.erc20]]` tokens, with the amounts of the `[funding]` section.
+    ///
+    /// The deposits stay pending, not spendable, until the rollup accepted them. Accounts whose
+    /// funding failed are removed from the pool; the run goes on with the rest unless none of
+    /// them could be funded.
+    pub async fn fund<S: FundingSteps>(
+        &mut self,
+        orchestrator: &FundingOrchestrator<S>,
+        config: &FundingConfig,
+    ) -> Result<FundingSummary, AccountsError> {
+        let assets = config.assets();
+        let targets = self
+            .accounts
+            .iter()
+            .map(|account| FundingTarget {
+                address: account.address(),
+                assets: assets.clone(),
+            })
+            .collect();
+        let summary = orchestrator.run(targets).await?;
+        if summary.funded.is_empty() && !self.accounts.is_empty() {
+            return Err(AccountsError::NoneFunded(self.accounts.len()));
+        }
+
+        for address in &summary.funded {
+            if let Some(account) = self.get_mut(address) {
+                for funding in &assets {
+                    account.state.record_deposit(
+                        funding.asset.symbol(),
+                        &u256_to_biguint(funding.deposit_amount),
+                    );
+                }
+            }
+        }
+        let failed: Vec<Address> = summary.failed.iter().map(|(address, _)| *address).collect();
+        self.remove(&failed);
+        Ok(summary)
+    }
+
+    /// Gives every account a rollup account id and the `[funding]` deposits as committed
+    /// balance, the state of a funded pool without asking the server, for dry runs.
+    pub fn assume_funded(&mut self, config: &FundingConfig) {
+        let assets = config.assets();
+        for (index, account) in self.accounts.iter_mut().enumerate() {
+            account.wallet.set_account_id(AccountId(index as u32 + 1));
+            for funding in &assets {
+                *account
+                    .state
+                    .balances
+                    .entry(funding.asset.symbol().to_string())
+                    .or_default() += u256_to_biguint(funding.deposit_amount);
+            }
+        }
+    }
+
+    /// Takes the last account out of the pool, no transaction is generated from or to it afterwards.
+    pub fn take_last(&mut self) -> Option<PoolAccount> {
+        let account = self.accounts.pop()?;
+        self.index_by_address.remove(&account.address());
+        self.next_sender = 0;
+        Some(account)
+    }
+
+    fn remove(&mut self, addresses: &[Address]) {
+        if addresses.is_empty() {
+            return;
+        }
+        self.accounts
+            .retain(|account| !addresses.contains(&account.address()));
+        self.index_by_address = self
+            .accounts
+            .iter()
+            .enumerate()
+            .map(|(index, account)| (account.address(), index))
+            .collect();
+        self.next_sender = 0;
+    }
+
+    /// Replaces the local view of every account by its committed state on the server,
+    /// returning the accounts whose local state had drifted.
+    pub async fn sync<P: Provider + Sync>(
+        &mut self,
+        provider: &P,
+    ) -> Result<Vec<(Address, Vec<StateDiscrepancy>)>, ClientError> {
+        let mut drifted = Vec::new();
+        for account in &mut self.accounts {
+            let info = provider.account_info(account.address()).await?;
+            if let Some(account_id) = info.id {
+                account.wallet.set_account_id(account_id);
+            }
+            let discrepancies = account.state.resync(&info.committed, &info.depositing);
+            if !discrepancies.is_empty() {
+                drifted.push((account.address(), discrepancies));
+            }
+            self.nonces
+                .set_nonce(account.address(), account.state.nonce);
+        }
+        // The committed state already tells what the transactions still in flight changed.
+        self.leases.clear();
+        Ok(drifted)
+    }
+
+    /// Drops the dust balances and transferred-away NFTs of every account.
+    pub fn compact(&mut self, dust_threshold: &BigUint) -> CompactionStats {
+        let mut total = CompactionStats::default();
+        for account in &mut self.accounts {
+            let stats = account.state.compact(dust_threshold);
+            total.balances_removed += stats.balances_removed;
+            total.nfts_removed += stats.nfts_removed;
+        }
+        total
+    }
+
+    /// Turns the accounts authorized with `CREATE2` into `CREATE2` accounts, which gives
+    /// them new addresses, so it has to happen before they are funded.
+    pub fn assign_create2(&mut self, config: &ActivationConfig) {
+        for (index, account) in self.accounts.iter_mut().enumerate() {
+            if config.auth_type(index) != ChangePubKeyFeeType::CREATE2 {
+                continue;
+            }
+            if let Some(create2) = config.create2_data(index) {
+                account.wallet.set_create2(create2);
+                account.state = LocalAccount::new(account.wallet.address());
+            }
+        }
+        self.index_by_address = self
+            .accounts
+            .iter()
+            .enumerate()
+            .map(|(index, account)| (account.address(), index))
+            .collect();
+    }
+
+    /// Sets the signing key of every account with a rollup account id and no key yet, meant
+    /// for the freshly funded accounts once `sync` picked up their ids.
+    ///
+    /// Every account uses the authorization of its position in the pool, see
+    /// `ActivationConfig::auth_type`. Activated accounts move on to their next nonce. The
+    /// fees are not deducted locally, the next `sync` picks them up.
+    pub async fn activate<S: ActivationSubmitter>(
+        &mut self,
+        submitter: &S,
+        activator: &AccountActivator<'_>,
+        fee_token: &Token,
+    ) -> ActivationSummary {
+        let targets = self
+            .accounts
+            .iter()
+            .enumerate()
+            .filter(|(_, account)| {
+                account.wallet.account_id().is_some() && !account.state.signing_key_set
+            })
+            .map(|(index, account)| ActivationTarget {
+                wallet: &account.wallet,
+                nonce: account.state.nonce,
+                auth_type: activator.config().auth_type(index),
+            })
+            .collect();
+        let summary = activator.run(submitter, targets, fee_token).await;
+        for address in summary.activated() {
+            if let Some(account) = self.get_mut(address) {
+                account.state.nonce = account
+                    .state
+                    .nonce
+                    .checked_next()
+                    .unwrap_or(account.state.nonce);
+                account.state.signing_key_set = true;
+            }
+        }
+        summary
+    }
+
+    /// Generates a transfer between two distinct accounts of the pool.
+    ///
+    /// The sender is the next account, in round-robin order, that has a rollup account id
+    /// and can cover the smallest configured amount plus the fee; larger amounts are
+    /// downsized to the largest packable amount the sender has left. Its nonce and balance
+    /// are updated right away, so the next transfer builds on this one without waiting for
+    /// it to be committed. `None` when no account can send.
+    ///
+    /// With `allow_overdraft` any account with an id sends and amounts are kept as drawn.
+    /// Transfers exceeding the balance are expected to be rejected and leave the local view
+    /// as it is.
+    pub fn generate_transfer<R: Rng>(
+        &mut self,
+        rng: &mut R,
+        config: &TransactionConfig,
+        denylist: &AddressDenylist,
+        token: &Token,
+        fee: BigUint,
+    ) -> Option<Transaction> {
+        if self.accounts.len() < 2 {
+            return None;
+        }
+        let required = BigUint::from(config.min_transfer_value) + &fee;
+        let sender = (0..self.accounts.len())
+            .map(|offset| (self.next_sender + offset) % self.accounts.len())
+            .find(|index| {
+                let account = &self.accounts[*index];
+                account.wallet.account_id().is_some()
+                    && (config.allow_overdraft || account.balance(token) >= required)
+            })?;
+        self.next_sender = (sender + 1) % self.accounts.len();
+
+        let from = self.accounts[sender].address();
+        let balance = self.accounts[sender].balance(token);
+        let recipients: Vec<Address> = self
+            .accounts
+            .iter()
+            .map(PoolAccount::address)
+            .filter(|address| *address != from)
+            .collect();
+        let nonce = self.accounts[sender].state.nonce;
+        let mut transaction = Transaction::generate_transfer(
+            rng,
+            config,
+            denylist,
+            from,
+            &recipients,
+            token.id,
+            fee,
+            nonce,
+        )?;
+        transaction.validate_addresses(denylist).ok()?;
+
+        if let Transaction::Transfer {
+            to, amount, fee, ..
+        } = &mut transaction
+        {
+            if !config.allow_overdraft && &*amount + &*fee > balance {
+                *amount = closest_packable_token_amount(&(&balance - &*fee))?;
+            }
+            if &*amount + &*fee > balance {
+                return Some(transaction);
+            }
+            let sender = &mut self.accounts[sender].state;
+            if let Some(balance) = sender.balances.get_mut(&token.symbol) {
+                *balance -= &*amount + &*fee;
+            }
+            if let Some(recipient) = self.get_mut(to) {
+                *recipient
+                    .state
+                    .balances
+                    .entry(token.symbol.clone())
+                    .or_default() += &*amount;
+            }
+            self.reserve_nonce(&from, &transaction);
+        }
+        Some(transaction)
+    }
+
+    /// Generates a `MintNFT` or `WithdrawNFT`, `None` for other kinds or when no account can
+    /// send one.
+    ///
+    /// The sender is the next account, in round-robin order, that has a rollup account id and
+    /// can pay the fee; withdrawals also need an NFT the sender still owns. Its nonce and fee
+    /// are taken locally right away and a withdrawn NFT is marked as transferred away, so it is
+    /// not withdrawn twice. Minted NFTs only show up locally once `sync` picks them up.
+    pub fn generate_nft<R: Rng>(
+        &mut self,
+        rng: &mut R,
+        kind: TransactionKind,
+        config: &TransactionConfig,
+        denylist: &AddressDenylist,
+        fee_token: &Token,
+        fee: BigUint,
+    ) -> Option<Transaction> {
+        let withdraw = match kind {
+            TransactionKind::MintNFT => false,
+            TransactionKind::WithdrawNFT => true,
+            _ => return None,
+        };
+        let sender = (0..self.accounts.len())
+            .map(|offset| (self.next_sender + offset) % self.accounts.len())
+            .find(|index| {
+                let account = &self.accounts[*index];
+                account.wallet.account_id().is_some()
+                    && account.balance(fee_token) >= fee
+                    && (!withdraw || account.state.owned_nft().is_some())
+            })?;
+        self.next_sender = (sender + 1) % self.accounts.len();
+
+        let recipients = self.addresses();
+        let account = &self.accounts[sender];
+        let balance = account.balance(fee_token);
+        let input = GenerationInput {
+            from: account.address(),
+            recipients: &recipients,
+            token: fee_token.id,
+            fee: fee.clone(),
+            nonce: account.state.nonce,
+            balance: &balance,
+            nft: account.state.owned_nft(),
+            tag: self.tag.as_ref(),
+        };
+        let transaction = Transaction::generate(rng, kind, config, denylist, input)?;
+        transaction.validate_addresses(denylist).ok()?;
+
+        self.reserve_nonce(&transaction.from(), &transaction);
+        let state = &mut self.accounts[sender].state;
+        if let Some(balance) = state.balances.get_mut(&fee_token.symbol) {
+            *balance -= &fee;
+        }
+        if let Transaction::WithdrawNFT { token, .. } = &transaction {
+            if let Some(entry) = state.nfts.get_mut(token) {
+                entry.transferred_away = true;
+            }
+        }
+        Some(transaction)
+    }
+
+    /// Accounts a forced exit of `token` can target: ones with a rollup account id and a
+    /// balance of the token that never set their signing key.
+    pub fn forced_exit_targets(&self, token: &Token) -> Vec<Address> {
+        self.accounts
+            .iter()
+            .filter(|account| {
+                account.wallet.account_id().is_some()
+                    && !account.state.signing_key_set
+                    && account.balance(token) > BigUint::default()
+            })
+            .map(PoolAccount::address)
+            .collect()
+    }
+
+    /// Generates a forced exit of one of the `forced_exit_targets`, `None` when there is no
+    /// target or no account can initiate it.
+    ///
+    /// The initiator is the next account, in round-robin order, that set its signing key and
+    /// can pay the fee, which is charged in the exited token. Its nonce and fee are taken
+    /// locally right away and the target's balance of the token is dropped, as the whole of
+    /// it is withdrawn, so the same target is not exited twice.
+    pub fn generate_forced_exit<R: Rng>(
+        &mut self,
+        rng: &mut R,
+        denylist: &AddressDenylist,
+        token: &Token,
+        fee: BigUint,
+    ) -> Option<Transaction> {
+        let targets = self.forced_exit_targets(token);
+        if targets.is_empty() {
+            return None;
+        }
+        let initiator = (0..self.accounts.len())
+            .map(|offset| (self.next_sender + offset) % self.accounts.len())
+            .find(|index| {
+                let account = &self.accounts[*index];
+                account.wallet.account_id().is_some()
+                    && account.state.signing_key_set
+                    && account.balance(token) >= fee
+            })?;
+        self.next_sender = (initiator + 1) % self.accounts.len();
+
+        let from = self.accounts[initiator].address();
+        let nonce = self.accounts[initiator].state.nonce;
+        let transaction = Transaction::generate_forced_exit(
+            rng,
+            denylist,
+            from,
+            &targets,
+            token.id,
+            fee.clone(),
+            nonce,
+        )?;
+        transaction.validate_addresses(denylist).ok()?;
+
+        self.reserve_nonce(&from, &transaction);
+        let initiator = &mut self.accounts[initiator].state;
+        if let Some(balance) = initiator.balances.get_mut(&token.symbol) {
+            *balance -= &fee;
+        }
+        if let Some(target) = transaction.to().and_then(|to| self.get_mut(&to)) {
+            target.state.balances.remove(&token.symbol);
+        }
+        Some(transaction)
+    }
+
+    /// Generates a `Deposit`, `TransferToNew`, `Withdraw` or `ChangePubKey` of the sender's own
+    /// funds, `None` for other kinds or when no account can send one.
+    ///
+    /// The sender is the next account, in round-robin order, that has a rollup account id and
+    /// can pay the fee in `token`; deposits are paid with L1 gas and only need the account.
+    /// Nonce, fee and the amount sent away are taken locally right away, deposits are recorded
+    /// as pending until the rollup accepts them. A `ChangePubKey` only marks the signing key as
+    /// set once it is confirmed, see [`Self::confirm_signing_key`].
+    pub fn generate_own<R: Rng>(
+        &mut self,
+        rng: &mut R,
+        kind: TransactionKind,
+        config: &TransactionConfig,
+        denylist: &AddressDenylist,
+        token: &Token,
+        fee: BigUint,
+    ) -> Option<Transaction> {
+        let deposit = match kind {
+            TransactionKind::Deposit => true,
+            TransactionKind::TransferToNew
+            | TransactionKind::Withdraw
+            | TransactionKind::ChangePubKey => false,
+            _ => return None,
+        };
+        let sender = (0..self.accounts.len())
+            .map(|offset| (self.next_sender + offset) % self.accounts.len())
+            .find(|index| {
+                let account = &self.accounts[*index];
+                deposit || (account.wallet.account_id().is_some() && account.balance(token) > fee)
+            })?;
+        self.next_sender = (sender + 1) % self.accounts.len();
+
+        let account = &self.accounts[sender];
+        let balance = account.balance(token);
+        let input = GenerationInput {
+            from: account.address(),
+            recipients: &[],
+            token: token.id,
+            fee: fee.clone(),
+            nonce: account.state.nonce,
+            balance: &balance,
+            nft: None,
+            tag: self.tag.as_ref(),
+        };
+        let transaction = Transaction::generate(rng, kind, config, denylist, input)?;
+        transaction.validate_addresses(denylist).ok()?;
+
+        if let Transaction::TransferToNew { amount, .. } | Transaction::Withdraw { amount, .. } =
+            &transaction
+        {
+            if &fee + amount > balance {
+                return None;
+            }
+        }
+        if !deposit {
+            self.reserve_nonce(&transaction.from(), &transaction);
+        }
+        let state = &mut self.accounts[sender].state;
+        match &transaction {
+            Transaction::Deposit { amount, .. } => state.record_deposit(&token.symbol, amount),
+            Transaction::TransferToNew { amount, .. } | Transaction::Withdraw { amount, .. } => {
+                state
+                    .balances
+                    .insert(token.symbol.clone(), balance - &fee - amount);
+            }
+            _ => {
+                state.balances.insert(token.symbol.clone(), balance - &fee);
+            }
+        }
+        Some(transaction)
+    }
+
+    /// Marks the signing key of `address` as set once its `ChangePubKey` was committed.
+    pub fn confirm_signing_key(&mut self, address: &Address) {
+        if let Some(account) = self.get_mut(address) {
+            account.state.signing_key_set = true;
+        }
+    }
+
+    /// NFTs known to the local view of the accounts, including the ones already sent on,
+    /// to resolve the ids of minted ones.
+    pub fn known_nfts(&self) -> impl Iterator<Item = &NFT> {
+        self.accounts
+            .iter()
+            .flat_map(|account| account.state.nfts.values().map(|entry| &entry.nft))
+    }
+
+    /// Reserves the local nonce of `address` for `transaction`, about to be sent, and moves
+    /// the account on to the next one.
+    pub fn reserve_nonce(&mut self, address: &Address, transaction: &Transaction) {
+        let Some(index) = self.index_by_address.get(address) else {
+            return;
+        };
+        let state = &mut self.accounts[*index].state;
+        // `restore` and activations move the local nonce as well, the manager follows it.
+        if self.nonces.next_nonce(address) != Some(state.nonce) {
+            self.nonces.set_nonce(*address, state.nonce);
+        }
+        let Ok(lease) = self.nonces.reserve(*address) else {
+            return;
+        };
+        state.nonce = lease.nonce.checked_next().unwrap_or(lease.nonce);
+        self.leases
+            .insert((*address, lease.nonce), (lease, transaction.clone()));
+    }
+
+    /// Lease of the nonce `transaction` reserved, `None` when it reserved none or the
+    /// nonce was reserved again since, by a later transaction.
+    fn release(&mut self, transaction: &Transaction) -> Option<NonceLease> {
+        let key = (transaction.from(), transaction.nonce()?);
+        let (_, reserved) = self.leases.get(&key)?;
+        if reserved != transaction {
+            return None;
+        }
+        self.leases.remove(&key).map(|(lease, _)| lease)
+    }
+
+    /// Hands back the nonce of a transaction the server accepted.
+    pub fn accept(&mut self, transaction: &Transaction) {
+        if let Some(lease) = self.release(transaction) {
+            self.nonces.accepted(lease);
+        }
+    }
+
+    /// Takes back a generated transaction the server rejected with `err`.
+    ///
+    /// Its balance changes are undone and, unless the nonce was already reset, the sender goes
+    /// back to the nonce it did not consume; the transactions reserved after it are taken back
+    /// as they are rejected in turn. `Mismatch` means the server disagreed on the nonce, see
+    /// [`Self::reconcile_nonce`]. A failed deposit only drops its pending amount.
+    ///
+    /// Transactions that never changed the local view, overdrafts or ones generated before
+    /// the last `sync`, are left alone and give `None`.
+    pub fn reject(
+        &mut self,
+        transaction: &Transaction,
+        token: &Token,
+        err: &ClientError,
+    ) -> Option<NonceRejection> {
+        if let Transaction::Deposit { from, amount, .. } = transaction {
+            if let Some(sender) = self.get_mut(from) {
+                sender.state.cancel_deposit(&token.symbol, amount);
+            }
+            return None;
+        }
+        let lease = self.release(transaction)?;
+        let rejection = self.nonces.rejected(lease, err);
+        if let NonceRejection::RolledBack { .. } = rejection {
+            if let Some(sender) = self.get_mut(&lease.address) {
+                sender.state.nonce = lease.nonce;
+            }
+        }
+        self.take_back(transaction, token);
+        Some(rejection)
+    }
+
+    /// Undoes the balance changes of a generated transaction. The target of a forced exit
+    /// only gets its balance back with the next `sync`.
+    fn take_back(&mut self, transaction: &Transaction, token: &Token) {
+        let from = transaction.from();
+        let mut refund = transaction.fee().cloned().unwrap_or_default();
+        match transaction {
+            Transaction::Transfer { to, amount, .. } => {
+                if let Some(recipient) = self.get_mut(to) {
+                    if let Some(balance) = recipient.state.balances.get_mut(&token.symbol) {
+                        *balance = if *balance >= *amount {
+                            &*balance - amount
+                        } else {
+                            BigUint::default()
+                        };
+                    }
+                }
+                refund += amount;
+            }
+            Transaction::TransferToNew { amount, .. } | Transaction::Withdraw { amount, .. } => {
+                refund += amount;
+            }
+            Transaction::WithdrawNFT { token: nft, .. } => {
+                let sender = self.get_mut(&from);
+                if let Some(entry) = sender.and_then(|sender| sender.state.nfts.get_mut(nft)) {
+                    entry.transferred_away = false;
+                }
+            }
+            _ => {}
+        }
+        if let Some(sender) = self.get_mut(&from) {
+            *sender
+                .state
+                .balances
+                .entry(token.symbol.clone())
+                .or_default() += refund;
+        }
+    }
+
+    /// Continues `address` from the nonce the server committed, after it disagreed on the
+    /// local one, never going back behind the transactions it accepted since.
+    pub async fn reconcile_nonce<P: Provider + Sync>(
+        &mut self,
+        provider: &P,
+        address: Address,
+    ) -> ResponseResult<Nonce> {
+        let nonce = self.nonces.reconcile_with(provider, address).await?;
+        if let Some(account) = self.get_mut(&address) {
+            account.state.nonce = nonce;
+        }
+        Ok(nonce)
+    }
+
+    /// Local nonces, balances and pending deposits of every account.
+    pub fn checkpoint(&self) -> Vec<AccountCheckpoint> {
+        self.addresses()
+            .iter()
+            .filter_map(|address| self.get(address))
+            .map(|account| AccountCheckpoint {
+                address: account.address(),
+                account_id: account.wallet.account_id(),
+                nonce: account.state.nonce,
+                balances: account
+                    .state
+                    .balances
+                    .iter()
+                    .map(|(symbol, balance)| (symbol.clone(), BigUintSerdeWrapper(balance.clone())))
+                    .collect(),
+                depositing: account
+                    .state
+                    .depositing
+                    .iter()
+                    .map(|(symbol, amount)| (symbol.clone(), BigUintSerdeWrapper(amount.clone())))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Replaces the local view of the accounts by the checkpointed one.
+    ///
+    /// Fails without changing anything when an account of the checkpoint is not in the pool,
+    /// e.g. because the wallets were not derived from the same mnemonic.
+    pub fn restore(&mut self, accounts: &[AccountCheckpoint]) -> Result<(), CheckpointError> {
+        let missing: Vec<Address> = accounts
+            .iter()
+            .map(|account| account.address)
+            .filter(|address| self.get(address).is_none())
+            .collect();
+        if !missing.is_empty() {
+            return Err(CheckpointError::MissingAccounts(missing));
+        }
+        for checkpoint in accounts {
+            let Some(account) = self.get_mut(&checkpoint.address) else {
+                continue;
+            };
+            if let Some(account_id) = checkpoint.account_id {
+                account.wallet.set_account_id(account_id);
+            }
+            account.state.nonce = checkpoint.nonce;
+            account.state.balances = checkpoint
+                .balances
+                .iter()
+                .map(|(symbol, balance)| (symbol.clone(), balance.0.clone()))
+                .collect();
+            account.state.depositing = checkpoint
+                .depositing
+                .iter()
+                .map(|(symbol, amount)| (symbol.clone(), amount.0.clone()))
+                .collect();
+        }
+        Ok(())
+    }
+
+    /// Next nonce of the account according to the local view.
+    pub fn nonce(&self, address: &Address) -> Option<Nonce> {
+        self.get(address).map(|account| account.state.nonce)
+    }
+}
+
+fn u256_to_biguint(value: U256) -> BigUint {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    BigUint::from_bytes_be(&bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use async_trait::async_trait;
+    use ethers::signers::LocalWallet;
+    use ethers::types::H256;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::config::Config;
+    use crate::funding::FundingAsset;
+    use crate::report::nfts::{NftMints, NftOperation};
+    use crate::rollup::mock::MockProvider;
+    use crate::rollup::types::tx::{ForcedExit, TimeRange, ZkSyncTx};
+    use crate::rollup::types::{TokenId, TokenKind, TxFeeTypes};
+    use crate::wallet::account_state::NftEntry;
+
+    struct FailingFirstDeposit {
+        rejected: Address,
+    }
+
+    #[async_trait]
+    impl FundingSteps for FailingFirstDeposit {
+        async fn master_nonce(&self) -> Result<U256, FundingError> {
+            Ok(U256::zero())
+        }
+
+        async fn send_l1_transfer(
+            &self,
+            nonce: U256,
+            _asset: &FundingAsset,
+            _to: Address,
+            _amount: U256,
+        ) -> Result<H256, FundingError> {
+            Ok(H256::from_low_u64_be(nonce.as_u64()))
+        }
+
+        async fn wait_l1_transfer(&self, _tx_hash: H256) -> Result<(), FundingError> {
+            Ok(())
+        }
+
+        async fn deposit(
+            &self,
+            from: Address,
+            _asset: &FundingAsset,
+            _amount: U256,
+        ) -> Result<(), FundingError> {
+            if from == self.rejected {
+                return Err(FundingError::Deposit("reverted".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    /// Tests that unfunded accounts leave the pool and transfers rotate senders with local nonces.
+    #[tokio::test]
+    async fn test_funded_pool_transfers() {
+        let mut wallets = Vec::new();
+        for seed in 1..=4u8 {
+            let eth_signer = LocalWallet::from_bytes(&[seed; 32]).unwrap();
+            wallets.push(Wallet::new(eth_signer).await.unwrap());
+        }
+        let mut pool = AccountPool::new(wallets);
+        let rejected = pool.addresses()[3];
+        let token = Token::new(TokenId(0), Address::zero(), "RBTC", 18, TokenKind::None);
+
+        let config = FundingConfig {
+            l1_amount_wei: 2_000,
+            deposit_amount_wei: 1_000,
+            ..Default::default()
+        };
+        let orchestrator = FundingOrchestrator::new(FailingFirstDeposit { rejected }, &config);
+        let summary = pool.fund(&orchestrator, &config).await.unwrap();
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(pool.len(), 3);
+        assert!(pool.get(&rejected).is_none());
+
+        for (id, address) in pool.addresses().into_iter().enumerate() {
+            let account = pool.get_mut(&address).unwrap();
+            assert_eq!(account.balance(&token), BigUint::default());
+            account
+                .state
+                .accept_deposit("RBTC", &BigUint::from(1_000u32));
+            account.wallet.set_account_id(AccountId(id as u32 + 1));
+        }
+
+        let config = Config::default().transaction;
+        let denylist = AddressDenylist::new(&config);
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut senders = Vec::new();
+        for _ in 0..6 {
+            let transfer = pool
+                .generate_transfer(&mut rng, &config, &denylist, &token, BigUint::from(5u32))
+                .unwrap();
+            let Transaction::Transfer { from, to, .. } = transfer else {
+                panic!("expected a transfer");
+            };
+            assert_ne!(from, to);
+            senders.push(from);
+        }
+
+        let addresses = pool.addresses();
+        assert_eq!(senders[..3], addresses[..]);
+        assert_eq!(senders[3..], addresses[..]);
+        for address in &addresses {
+            assert_eq!(pool.nonce(address), Some(Nonce(2)));
+        }
+        let total: BigUint = addresses
+            .iter()
+            .map(|address| pool.get(address).unwrap().balance(&token))
+            .sum();
+        assert_eq!(total, BigUint::from(3_000u32 - 6 * 5));
+    }
+
+    /// Tests that transfers are downsized to the sender's balance, overdrafts leave the local
+    /// view alone and rejected transfers are taken back with their nonce.
+    #[tokio::test]
+    async fn test_transfer_balance_model() {
+        let mut wallets = Vec::new();
+        for seed in 1..=2u8 {
+            let eth_signer = LocalWallet::from_bytes(&[seed; 32]).unwrap();
+            wallets.push(Wallet::new(eth_signer).await.unwrap());
+        }
+        let mut pool = AccountPool::new(wallets);
+        let token = Token::new(TokenId(0), Address::zero(), "RBTC", 18, TokenKind::None);
+        let [poor, rich] = [pool.addresses()[0], pool.addresses()[1]];
+        for (id, (address, balance)) in [(poor, 8u32), (rich, 0)].into_iter().enumerate() {
+            let account = pool.get_mut(&address).unwrap();
+            account.wallet.set_account_id(AccountId(id as u32 + 1));
+            account
+                .state
+                .balances
+                .insert(token.symbol.clone(), BigUint::from(balance));
+        }
+
+        let mut config = Config::default().transaction;
+        config.min_transfer_value = 1;
+        config.max_transfer_value = 100;
+        let denylist = AddressDenylist::new(&config);
+        let mut rng = StdRng::seed_from_u64(5);
+        let fee = BigUint::from(2u32);
+        let transfer = pool
+            .generate_transfer(&mut rng, &config, &denylist, &token, fee.clone())
+            .unwrap();
+        let Transaction::Transfer { from, amount, .. } = &transfer else {
+            panic!("expected a transfer");
+        };
+        assert_eq!((*from, amount), (poor, &BigUint::from(6u32)));
+        assert_eq!(pool.get(&poor).unwrap().balance(&token), BigUint::default());
+        assert_eq!(
+            pool.get(&rich).unwrap().balance(&token),
+            BigUint::from(6u32)
+        );
+        // The recipient can only send what it received, the sender nothing at all.
+        assert!(pool
+            .generate_transfer(&mut rng, &config, &denylist, &token, BigUint::from(7u32))
+            .is_none());
+
+        config.allow_overdraft = true;
+        config.min_transfer_value = 50;
+        let overdraft = pool
+            .generate_transfer(&mut rng, &config, &denylist, &token, fee.clone())
+            .unwrap();
+        let Transaction::Transfer { from, amount, .. } = &overdraft else {
+            panic!("expected a transfer");
+        };
+        assert_eq!(*from, rich);
+        assert!(*amount >= BigUint::from(50u32));
+        assert_eq!(pool.nonce(&rich), Some(Nonce(0)));
+        let rejected = ClientError::IncorrectInput;
+        assert_eq!(pool.reject(&overdraft, &token, &rejected), None);
+        assert_eq!(
+            pool.get(&rich).unwrap().balance(&token),
+            BigUint::from(6u32)
+        );
+
+        assert_eq!(
+            pool.reject(&transfer, &token, &rejected),
+            Some(NonceRejection::RolledBack { invalidated: 0 })
+        );
+        assert_eq!(
+            pool.get(&poor).unwrap().balance(&token),
+            BigUint::from(8u32)
+        );
+        assert_eq!(pool.get(&rich).unwrap().balance(&token), BigUint::default());
+        assert_eq!(pool.nonce(&poor), Some(Nonce(0)));
+    }
+
+    /// Tests NFT generation from the local view and resolving the ids of the minted NFTs.
+    #[tokio::test]
+    async fn test_nft_generation() {
+        let mut wallets = Vec::new();
+        for seed in 1..=2u8 {
+            let eth_signer = LocalWallet::from_bytes(&[seed; 32]).unwrap();
+            wallets.push(Wallet::new(eth_signer).await.unwrap());
+        }
+        let mut pool = AccountPool::new(wallets);
+        let token = Token::new(TokenId(0), Address::zero(), "RBTC", 18, TokenKind::None);
+        let [creator, owner] = [pool.addresses()[0], pool.addresses()[1]];
+        for (id, address) in [creator, owner].into_iter().enumerate() {
+            let account = pool.get_mut(&address).unwrap();
+            account.wallet.set_account_id(AccountId(id as u32 + 1));
+            account
+                .state
+                .balances
+                .insert(token.symbol.clone(), BigUint::from(10u32));
+        }
+
+        let config = Config::default().transaction;
+        let denylist = AddressDenylist::new(&config);
+        let mut rng = StdRng::seed_from_u64(7);
+        let fee = BigUint::from(4u32);
+        assert!(pool
+            .generate_nft(
+                &mut rng,
+                TransactionKind::WithdrawNFT,
+                &config,
+                &denylist,
+                &token,
+                fee.clone()
+            )
+            .is_none());
+        assert!(pool
+            .generate_nft(
+                &mut rng,
+                TransactionKind::Transfer,
+                &config,
+                &denylist,
+                &token,
+                fee.clone()
+            )
+            .is_none());
+        let mint = pool
+            .generate_nft(
+                &mut rng,
+                TransactionKind::MintNFT,
+                &config,
+                &denylist,
+                &token,
+                fee.clone(),
+            )
+            .unwrap();
+        let Transaction::MintNFT {
+            from, content_hash, ..
+        } = mint
+        else {
+            panic!("expected a mint");
+        };
+        assert_eq!(from, creator);
+        assert_eq!(pool.nonce(&creator), Some(Nonce(1)));
+        assert_eq!(
+            pool.get(&creator).unwrap().balance(&token),
+            BigUint::from(6u32)
+        );
+
+        let mut mints = NftMints::new();
+        mints.record(mint.nft_operation().unwrap(), true);
+        let nft = NFT {
+            id: TokenId(70_000),
+            symbol: "NFT-70000".to_string(),
+            creator_id: AccountId(1),
+            content_hash,
+        };
+        let entry = NftEntry {
+            nft,
+            transferred_away: false,
+        };
+        pool.get_mut(&owner)
+            .unwrap()
+            .state
+            .nfts
+            .insert(TokenId(70_000), entry);
+        assert_eq!(mints.record_owned(pool.known_nfts()), vec![TokenId(70_000)]);
+        assert_eq!(mints.minted_by(&creator), &[TokenId(70_000)]);
+
+        let withdraw = pool
+            .generate_nft(
+                &mut rng,
+                TransactionKind::WithdrawNFT,
+                &config,
+                &denylist,
+                &token,
+                fee.clone(),
+            )
+            .unwrap();
+        assert_eq!(withdraw.from(), owner);
+        assert_eq!(
+            withdraw.nft_operation(),
+            Some(NftOperation::Withdraw(TokenId(70_000)))
+        );
+        assert!(pool
+            .generate_nft(
+                &mut rng,
+                TransactionKind::WithdrawNFT,
+                &config,
+                &denylist,
+                &token,
+                fee
+            )
+            .is_none());
+    }
+
+    /// Tests that deposits need no account id and the other own operations take nonce, fee
+    /// and amount from the sender's local view, giving them back when rejected.
+    #[tokio::test]
+    async fn test_own_operation_generation() {
+        let mut wallets = Vec::new();
+        for seed in 1..=2u8 {
+            let eth_signer = LocalWallet::from_bytes(&[seed; 32]).unwrap();
+            wallets.push(Wallet::new(eth_signer).await.unwrap());
+        }
+        let mut pool = AccountPool::new(wallets);
+        let token = Token::new(TokenId(0), Address::zero(), "RBTC", 18, TokenKind::None);
+        let [sender, unregistered] = [pool.addresses()[0], pool.addresses()[1]];
+        let account = pool.get_mut(&sender).unwrap();
+        account.wallet.set_account_id(AccountId(1));
+        account
+            .state
+            .balances
+            .insert(token.symbol.clone(), BigUint::from(100u32));
+
+        let mut config = Config::default().transaction;
+        config.min_withdraw_value = 4;
+        let denylist = AddressDenylist::new(&config);
+        let mut rng = StdRng::seed_from_u64(11);
+        let fee = BigUint::from(2u32);
+        let generate = |pool: &mut AccountPool, rng: &mut StdRng, kind| {
+            pool.generate_own(rng, kind, &config, &denylist, &token, fee.clone())
+        };
+
+        assert!(generate(&mut pool, &mut rng, TransactionKind::Transfer).is_none());
+        let withdraw = generate(&mut pool, &mut rng, TransactionKind::Withdraw).unwrap();
+        let Transaction::Withdraw { from, amount, .. } = &withdraw else {
+            panic!("expected a withdrawal");
+        };
+        assert_eq!(*from, sender);
+        assert_eq!(pool.nonce(&sender), Some(Nonce(1)));
+        assert_eq!(
+            pool.get(&sender).unwrap().balance(&token),
+            BigUint::from(98u32) - amount
+        );
+
+        let change_pubkey = generate(&mut pool, &mut rng, TransactionKind::ChangePubKey).unwrap();
+        assert_eq!(change_pubkey.from(), sender);
+        assert!(!pool.get(&sender).unwrap().state.signing_key_set);
+        pool.confirm_signing_key(&sender);
+        assert!(pool.get(&sender).unwrap().state.signing_key_set);
+
+        // The rejected withdrawal rewinds the sender past the change of key sent after it.
+        let rejected = ClientError::IncorrectInput;
+        assert_eq!(
+            pool.reject(&withdraw, &token, &rejected),
+            Some(NonceRejection::RolledBack { invalidated: 1 })
+        );
+        assert_eq!(
+            pool.reject(&change_pubkey, &token, &rejected),
+            Some(NonceRejection::Stale)
+        );
+        assert_eq!(pool.nonce(&sender), Some(Nonce(0)));
+        assert_eq!(
+            pool.get(&sender).unwrap().balance(&token),
+            BigUint::from(100u32)
+        );
+
+        let deposits: Vec<Transaction> = (0..2)
+            .map(|_| generate(&mut pool, &mut rng, TransactionKind::Deposit).unwrap())
+            .collect();
+        assert!(deposits
+            .iter()
+            .any(|deposit| deposit.from() == unregistered));
+        let unregistered = pool.get(&unregistered).unwrap();
+        assert_eq!(unregistered.state.nonce, Nonce(0));
+        assert!(unregistered.state.depositing.contains_key("RBTC"));
+    }
+
+    /// Tests that only registered accounts without a signing key are activated, moving them
+    /// on to their next nonce.
+    #[tokio::test]
+    async fn test_activate_fresh_accounts() {
+        let mut wallets = Vec::new();
+        for seed in 1..=3u8 {
+            let eth_signer = LocalWallet::from_bytes(&[seed; 32]).unwrap();
+            wallets.push(Wallet::new(eth_signer).await.unwrap());
+        }
+        let mut pool = AccountPool::new(wallets);
+        let [fresh, activated, unregistered] = [
+            pool.addresses()[0],
+            pool.addresses()[1],
+            pool.addresses()[2],
+        ];
+        for (id, address) in [(1, fresh), (2, activated)] {
+            pool.get_mut(&address)
+                .unwrap()
+                .wallet
+                .set_account_id(AccountId(id));
+        }
+        pool.get_mut(&activated).unwrap().state.signing_key_set = true;
+
+        let activator = AccountActivator::new(ActivationConfig {
+            verify_timeout_secs: 0,
+            ..ActivationConfig::default()
+        });
+        let token = Token::new(TokenId(0), Address::zero(), "RBTC", 18, TokenKind::None);
+        let summary = pool
+            .activate(&MockProvider::new(), &activator, &token)
+            .await;
+
+        assert_eq!(summary.activated().collect::<Vec<_>>(), vec![&fresh]);
+        assert!(summary.failed.is_empty());
+        assert!(pool.get(&fresh).unwrap().state.signing_key_set);
+        assert_eq!(pool.nonce(&fresh), Some(Nonce(1)));
+        assert_eq!(pool.nonce(&activated), Some(Nonce(0)));
+        assert!(!pool.get(&unregistered).unwrap().state.signing_key_set);
+    }
+
+    /// Tests that forced exits target accounts without a signing key and are paid by one
+    /// that set it.
+    #[tokio::test]
+    async fn test_forced_exit_generation() {
+        let mut wallets = Vec::new();
+        for seed in 1..=3u8 {
+            let eth_signer = LocalWallet::from_bytes(&[seed; 32]).unwrap();
+            wallets.push(Wallet::new(eth_signer).await.unwrap());
+        }
+        let mut pool = AccountPool::new(wallets);
+        let token = Token::new(TokenId(0), Address::zero(), "RBTC", 18, TokenKind::None);
+        let [initiator, target, unregistered] = [
+            pool.addresses()[0],
+            pool.addresses()[1],
+            pool.addresses()[2],
+        ];
+        for address in [initiator, target, unregistered] {
+            pool.get_mut(&address)
+                .unwrap()
+                .state
+                .balances
+                .insert(token.symbol.clone(), BigUint::from(10u32));
+        }
+        for (id, address) in [initiator, target].into_iter().enumerate() {
+            pool.get_mut(&address)
+                .unwrap()
+                .wallet
+                .set_account_id(AccountId(id as u32 + 1));
+        }
+
+        let config = Config::default().transaction;
+        let denylist = AddressDenylist::new(&config);
+        let mut rng = StdRng::seed_from_u64(7);
+        let fee = BigUint::from(4u32);
+        assert_eq!(pool.forced_exit_targets(&token), vec![initiator, target]);
+        assert!(pool
+            .generate_forced_exit(&mut rng, &denylist, &token, fee.clone())
+            .is_none());
+
+        pool.get_mut(&initiator).unwrap().state.signing_key_set = true;
+        assert_eq!(pool.forced_exit_targets(&token), vec![target]);
+        let forced_exit = pool
+            .generate_forced_exit(&mut rng, &denylist, &token, fee.clone())
+            .unwrap();
+        assert_eq!(
+            (forced_exit.from(), forced_exit.to()),
+            (initiator, Some(target))
+        );
+        assert_eq!(forced_exit.fee_type(), Some(TxFeeTypes::Withdraw));
+        assert_eq!(pool.nonce(&initiator), Some(Nonce(1)));
+        assert_eq!(
+            pool.get(&initiator).unwrap().balance(&token),
+            BigUint::from(6u32)
+        );
+        assert!(pool.forced_exit_targets(&token).is_empty());
+        assert!(pool
+            .generate_forced_exit(&mut rng, &denylist, &token, fee.clone())
+            .is_none());
+
+        let wallet = &pool.get(&initiator).unwrap().wallet;
+        let (tx, eth_signature) = wallet
+            .sign_forced_exit(target, &token, fee, Nonce(0), TimeRange::default())
+            .await
+            .unwrap();
+        assert!(eth_signature.is_none());
+        let ZkSyncTx::ForcedExit(forced_exit) = tx else {
+            panic!("expected a forced exit");
+        };
+        assert_eq!(forced_exit.initiator_account_id, AccountId(1));
+        assert_eq!(forced_exit.get_bytes()[0], 255 - ForcedExit::TX_TYPE);
+    }
+}
@@ -0,0 +1,756 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use num::{BigUint, Zero};
+use serde::Deserialize;
+use tracing::{debug, warn};
+
+use crate::progress::PhaseProgress;
+use crate::report::batch_fees::BatchFeeQuote;
+use crate::report::change_pubkey::ChangePubKeyCoverage;
+use crate::rollup::provider::{ClientError, Provider, ResponseResult};
+use crate::rollup::types::pubkey_hash::PubKeyHash;
+use crate::rollup::types::tx::{ChangePubKeyCREATE2Data, TimeRange};
+use crate::rollup::types::{Address, ChangePubKeyFeeType, Nonce, Token, TxFeeTypes, TxHash, H256};
+use crate::sponsor::round_up_packable_fee;
+use crate::wallet::{SignedTx, Wallet};
+
+/// Activation of fresh accounts, configured in the `[activation]` section.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ActivationConfig {
+    /// Number of `ChangePubKey` transactions per `send_txs_batch` call, 1 sends them one by one.
+    #[serde(default = "ActivationConfig::default_batch_size")]
+    pub batch_size: usize,
+    /// Authorizations used by the accounts, assigned round-robin in pool order.
+    #[serde(default = "ActivationConfig::default_auth_types")]
+    pub auth_types: Vec<ChangePubKeyFeeType>,
+    /// Contract the `CREATE2` accounts belong to, required when they are used.
+    #[serde(default)]
+    pub create2: Option<Create2Config>,
+    /// How long to wait for the new keys to show up in the committed state, 0 skips the check.
+    #[serde(default = "ActivationConfig::default_verify_timeout_secs")]
+    pub verify_timeout_secs: u64,
+    #[serde(default = "ActivationConfig::default_verify_poll_interval_ms")]
+    pub verify_poll_interval_ms: u64,
+    /// Also quotes the members of every batch one by one, for the batch fee report.
+    #[serde(default)]
+    pub compare_fees: bool,
+}
+
+impl ActivationConfig {
+    fn default_batch_size() -> usize {
+        50
+    }
+
+    fn default_auth_types() -> Vec<ChangePubKeyFeeType> {
+        vec![ChangePubKeyFeeType::ECDSA]
+    }
+
+    fn default_verify_timeout_secs() -> u64 {
+        60
+    }
+
+    fn default_verify_poll_interval_ms() -> u64 {
+        1000
+    }
+
+    /// Authorization of the account at `index` of the pool.
+    pub fn auth_type(&self, index: usize) -> ChangePubKeyFeeType {
+        match self.auth_types.len() {
+            0 => ChangePubKeyFeeType::ECDSA,
+            len => self.auth_types[index % len],
+        }
+    }
+
+    /// Deployment data of the `CREATE2` account at `index` of the pool, salted with the index.
+    pub fn create2_data(&self, index: usize) -> Option<ChangePubKeyCREATE2Data> {
+        let create2 = self.create2.as_ref()?;
+        Some(ChangePubKeyCREATE2Data {
+            creator_address: create2.creator_address,
+            salt_arg: H256::from_low_u64_be(index as u64),
+            code_hash: create2.code_hash,
+        })
+    }
+}
+
+impl Default for ActivationConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: Self::default_batch_size(),
+            auth_types: Self::default_auth_types(),
+            create2: None,
+            verify_timeout_secs: Self::default_verify_timeout_secs(),
+            verify_poll_interval_ms: Self::default_verify_poll_interval_ms(),
+            compare_fees: false,
+        }
+    }
+}
+
+/// The `[activation.create2]` section: factory and code of the account contracts.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Create2Config {
+    pub creator_address: Address,
+    pub code_hash: H256,
+}
+
+/// Fee quotes and submissions the activator needs, implemented by every `Provider`.
+#[async_trait]
+pub trait ActivationSubmitter: Send + Sync {
+    /// Fee of a batch of `ChangePubKey` transactions from `addresses`, authorized with `auth_types`.
+    async fn batch_fee(
+        &self,
+        auth_types: Vec<ChangePubKeyFeeType>,
+        addresses: Vec<Address>,
+        token: &Token,
+    ) -> ResponseResult<BigUint>;
+
+    /// Fee of a single `ChangePubKey` transaction from `address`.
+    async fn fee(
+        &self,
+        auth_type: ChangePubKeyFeeType,
+        address: Address,
+        token: &Token,
+    ) -> ResponseResult<BigUint>;
+
+    async fn send_batch(&self, txs: Vec<SignedTx>) -> ResponseResult<Vec<TxHash>>;
+
+    async fn send(&self, tx: SignedTx) -> ResponseResult<TxHash>;
+
+    /// Signing key of `address` in the committed state.
+    async fn committed_pub_key_hash(&self, address: Address) -> ResponseResult<PubKeyHash>;
+}
+
+#[async_trait]
+impl<P: Provider + Send + Sync> ActivationSubmitter for P {
+    async fn batch_fee(
+        &self,
+        auth_types: Vec<ChangePubKeyFeeType>,
+        addresses: Vec<Address>,
+        token: &Token,
+    ) -> ResponseResult<BigUint> {
+        let tx_types = auth_types
+            .into_iter()
+            .map(TxFeeTypes::ChangePubKey)
+            .collect();
+        self.get_txs_batch_fee(tx_types, addresses, token).await
+    }
+
+    async fn fee(
+        &self,
+        auth_type: ChangePubKeyFeeType,
+        address: Address,
+        token: &Token,
+    ) -> ResponseResult<BigUint> {
+        let fee_type = TxFeeTypes::ChangePubKey(auth_type);
+        Ok(self.get_tx_fee(fee_type, address, token).await?.total_fee)
+    }
+
+    async fn send_batch(&self, txs: Vec<SignedTx>) -> ResponseResult<Vec<TxHash>> {
+        self.send_txs_batch(txs, None).await
+    }
+
+    async fn send(&self, (tx, eth_signature): SignedTx) -> ResponseResult<TxHash> {
+        self.send_tx(tx, eth_signature).await
+    }
+
+    async fn committed_pub_key_hash(&self, address: Address) -> ResponseResult<PubKeyHash> {
+        Ok(self.account_info(address).await?.committed.pub_key_hash)
+    }
+}
+
+/// Registers signing keys on the rollup contract, which `Onchain` authorization requires
+/// before the `ChangePubKey` is sent.
+#[async_trait]
+pub trait OnchainAuthorizer: Send + Sync {
+    /// Authorizes the signing key of `wallet` for its `ChangePubKey` with `nonce`.
+    async fn authorize(&self, wallet: &Wallet, nonce: Nonce) -> ResponseResult<()>;
+}
+
+/// Account whose signing key is set on the rollup, using its next nonce.
+pub struct ActivationTarget<'a> {
+    pub wallet: &'a Wallet,
+    pub nonce: Nonce,
+    pub auth_type: ChangePubKeyFeeType,
+}
+
+#[derive(Debug, Default)]
+pub struct ActivationSummary {
+    /// Accounts activated as part of an accepted batch.
+    pub batched: Vec<Address>,
+    /// Accounts activated with a transaction of their own, mostly members of rejected batches.
+    pub individual: Vec<Address>,
+    pub failed: Vec<(Address, ClientError)>,
+    /// Activated accounts whose new key was not committed within `verify_timeout_secs`.
+    pub unverified: Vec<Address>,
+    /// Submitted `ChangePubKey`s per authorization.
+    pub by_auth_type: HashMap<ChangePubKeyFeeType, usize>,
+    pub batches: usize,
+    pub rejected_batches: usize,
+    /// Batch fees next to the fees of their members, empty unless `compare_fees` is set.
+    pub batch_fees: Vec<BatchFeeQuote>,
+    /// Outcomes per authorization, an accepted `ChangePubKey` succeeding once its key is
+    /// committed, or right away when the keys are not verified.
+    pub change_pubkey: ChangePubKeyCoverage,
+    pub elapsed: Duration,
+    /// Accepted `ChangePubKey`s whose outcome is not known yet, by account.
+    submitted: HashMap<Address, (ChangePubKeyFeeType, BigUint, Instant)>,
+}
+
+impl ActivationSummary {
+    pub fn activated(&self) -> impl Iterator<Item = &Address> {
+        self.batched.iter().chain(&self.individual)
+    }
+
+    fn submitted(&mut self, address: Address, auth_type: ChangePubKeyFeeType, fee: BigUint) {
+        self.submitted
+            .insert(address, (auth_type, fee, Instant::now()));
+    }
+
+    /// Records the outcome of the `ChangePubKey` accepted from `address`.
+    fn resolve(&mut self, address: &Address, success: bool) {
+        if let Some((auth_type, fee, submitted_at)) = self.submitted.remove(address) {
+            self.change_pubkey
+                .record(auth_type, &fee, submitted_at.elapsed(), success);
+        }
+    }
+}
+
+/// Sets the signing keys of fresh accounts with `ChangePubKey` transactions before the
+/// workload starts.
+///
+/// Accounts are activated in batches of `batch_size`, one `send_txs_batch` call each, which
+/// takes a fraction of the time of one submission per account for large pools. Every member
+/// pays an equal share of the batch fee. The rollup executes a batch atomically, so a single
+/// bad member gets the whole batch rejected; its members are then submitted one by one with
+/// their own fee quote, and only the accounts rejected on their own are reported as failed.
+///
+/// `Onchain` targets have their key registered on the contract first, which needs an
+/// [`OnchainAuthorizer`]. Once submitted, the activator waits for the new keys to be
+/// committed and reports the accounts whose key did not show up.
+pub struct AccountActivator<'a> {
+    config: ActivationConfig,
+    time_range: TimeRange,
+    progress: PhaseProgress,
+    onchain: Option<&'a dyn OnchainAuthorizer>,
+}
+
+impl<'a> AccountActivator<'a> {
+    pub fn new(config: ActivationConfig) -> Self {
+        Self {
+            config,
+            time_range: TimeRange::default(),
+            progress: PhaseProgress::hidden(),
+            onchain: None,
+        }
+    }
+
+    /// Reports every activated or failed account to the given progress bar.
+    pub fn with_progress(mut self, progress: PhaseProgress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Registers the keys of `Onchain` targets through `authorizer`, without one they fail.
+    pub fn with_onchain_authorizer(mut self, authorizer: &'a dyn OnchainAuthorizer) -> Self {
+        self.onchain = Some(authorizer);
+        self
+    }
+
+    pub fn config(&self) -> &ActivationConfig {
+        &self.config
+    }
+
+    pub async fn run<S: ActivationSubmitter>(
+        &self,
+        submitter: &S,
+        targets: Vec<ActivationTarget<'_>>,
+        fee_token: &Token,
+    ) -> ActivationSummary {
+        let started = Instant::now();
+        let mut summary = ActivationSummary::default();
+        let targets = self.authorize_onchain(targets, &mut summary).await;
+        for target in &targets {
+            *summary.by_auth_type.entry(target.auth_type).or_default() += 1;
+        }
+        for chunk in targets.chunks(self.config.batch_size.max(1)) {
+            if chunk.len() == 1 {
+                self.activate_individually(submitter, chunk, fee_token, &mut summary)
+                    .await;
+                continue;
+            }
+            summary.batches += 1;
+            match self
+                .activate_batch(submitter, chunk, fee_token, &mut summary)
+                .await
+            {
+                Ok(()) => {
+                    summary
+                        .batched
+                        .extend(chunk.iter().map(|target| target.wallet.address()));
+                    self.progress.inc_by(chunk.len() as u64);
+                }
+                Err(err) => {
+                    warn!(
+                        "Activation batch of {} accounts rejected, activating them one by one: {}",
+                        chunk.len(),
+                        err
+                    );
+                    summary.rejected_batches += 1;
+                    self.activate_individually(submitter, chunk, fee_token, &mut summary)
+                        .await;
+                }
+            }
+        }
+        self.progress.finish();
+        self.verify(submitter, &targets, &mut summary).await;
+        summary.elapsed = started.elapsed();
+        summary
+    }
+
+    /// Registers the keys of the `Onchain` targets, leaving out the ones that could not be.
+    async fn authorize_onchain<'t>(
+        &self,
+        targets: Vec<ActivationTarget<'t>>,
+        summary: &mut ActivationSummary,
+    ) -> Vec<ActivationTarget<'t>> {
+        let mut authorized = Vec::with_capacity(targets.len());
+        for target in targets {
+            if target.auth_type != ChangePubKeyFeeType::Onchain {
+                authorized.push(target);
+                continue;
+            }
+            let address = target.wallet.address();
+            let result = match self.onchain {
+                Some(authorizer) => authorizer.authorize(target.wallet, target.nonce).await,
+                None => Err(ClientError::MissingRequiredField(
+                    "L1 connection for Onchain authorization".to_string(),
+                )),
+            };
+            match result {
+                Ok(()) => authorized.push(target),
+                Err(err) => {
+                    warn!(
+                        "Unable to authorize the key of {:?} on chain: {}",
+                        address, err
+                    );
+                    summary.failed.push((address, err));
+                    self.progress.inc();
+                }
+            }
+        }
+        authorized
+    }
+
+    /// Waits until the committed state of every activated account has its new key.
+    async fn verify<S: ActivationSubmitter>(
+        &self,
+        submitter: &S,
+        targets: &[ActivationTarget<'_>],
+        summary: &mut ActivationSummary,
+    ) {
+        if self.config.verify_timeout_secs == 0 {
+            let accepted: Vec<Address> = summary.submitted.keys().copied().collect();
+            for address in &accepted {
+                summary.resolve(address, true);
+            }
+            return;
+        }
+        let keys: HashMap<Address, PubKeyHash> = targets
+            .iter()
+            .map(|target| (target.wallet.address(), target.wallet.pub_key_hash()))
+            .collect();
+        let mut pending: Vec<Address> = summary.activated().copied().collect();
+        let deadline = Instant::now() + Duration::from_secs(self.config.verify_timeout_secs);
+        loop {
+            let mut unverified = Vec::new();
+            for address in pending {
+                match submitter.committed_pub_key_hash(address).await {
+                    Ok(committed) if keys.get(&address) == Some(&committed) => {
+                        summary.resolve(&address, true)
+                    }
+                    _ => unverified.push(address),
+                }
+            }
+            pending = unverified;
+            if pending.is_empty() || Instant::now() >= deadline {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(self.config.verify_poll_interval_ms)).await;
+        }
+        for address in &pending {
+            warn!("Signing key of {:?} was not committed in time", address);
+            summary.resolve(address, false);
+        }
+        summary.unverified = pending;
+    }
+
+    async fn activate_batch<S: ActivationSubmitter>(
+        &self,
+        submitter: &S,
+        targets: &[ActivationTarget<'_>],
+        fee_token: &Token,
+        summary: &mut ActivationSummary,
+    ) -> ResponseResult<()> {
+        let auth_types: Vec<ChangePubKeyFeeType> =
+            targets.iter().map(|target| target.auth_type).collect();
+        let addresses = targets
+            .iter()
+            .map(|target| target.wallet.address())
+            .collect();
+        let batch_fee = submitter
+            .batch_fee(auth_types.clone(), addresses, fee_token)
+            .await?;
+        if self.config.compare_fees {
+            match self.individual_fees(submitter, targets, fee_token).await {
+                Ok(individual_fees) => summary.batch_fees.push(BatchFeeQuote {
+                    token: fee_token.symbol.clone(),
+                    tx_types: auth_types
+                        .into_iter()
+                        .map(TxFeeTypes::ChangePubKey)
+                        .collect(),
+                    batch_fee: batch_fee.clone(),
+                    individual_fees,
+                }),
+                Err(err) => debug!("Unable to quote the batch members one by one: {}", err),
+            }
+        }
+        let members = BigUint::from(targets.len());
+        let share = (batch_fee + &members - 1u32) / &members;
+        let fee = round_up_packable_fee(&share).ok_or(ClientError::NotPackableValue)?;
+
+        let mut txs = Vec::with_capacity(targets.len());
+        for target in targets {
+            txs.push(self.sign(target, fee_token, fee.clone()).await?);
+        }
+        let hashes = submitter.send_batch(txs).await?;
+        debug!("Activated {} accounts in one batch", hashes.len());
+        for target in targets {
+            summary.submitted(target.wallet.address(), target.auth_type, fee.clone());
+        }
+        Ok(())
+    }
+
+    /// Sum of the fees the targets would pay with a transaction of their own.
+    async fn individual_fees<S: ActivationSubmitter>(
+        &self,
+        submitter: &S,
+        targets: &[ActivationTarget<'_>],
+        fee_token: &Token,
+    ) -> ResponseResult<BigUint> {
+        let mut total = BigUint::default();
+        for target in targets {
+            total += submitter
+                .fee(target.auth_type, target.wallet.address(), fee_token)
+                .await?;
+        }
+        Ok(total)
+    }
+
+    async fn activate_individually<S: ActivationSubmitter>(
+        &self,
+        submitter: &S,
+        targets: &[ActivationTarget<'_>],
+        fee_token: &Token,
+        summary: &mut ActivationSummary,
+    ) {
+        for target in targets {
+            let address = target.wallet.address();
+            let started = Instant::now();
+            let result = async {
+                let fee = submitter.fee(target.auth_type, address, fee_token).await?;
+                let fee = round_up_packable_fee(&fee).ok_or(ClientError::NotPackableValue)?;
+                let tx = self.sign(target, fee_token, fee.clone()).await?;
+                submitter.send(tx).await.map(|_| fee)
+            }
+            .await;
+            match result {
+                Ok(fee) => {
+                    summary.individual.push(address);
+                    summary.submitted(address, target.auth_type, fee);
+                }
+                Err(err) => {
+                    warn!("Unable to activate account {:?}: {}", address, err);
+                    summary.change_pubkey.record(
+                        target.auth_type,
+                        &BigUint::zero(),
+                        started.elapsed(),
+                        false,
+                    );
+                    summary.failed.push((address, err));
+                }
+            }
+            self.progress.inc();
+        }
+    }
+
+    async fn sign(
+        &self,
+        target: &ActivationTarget<'_>,
+        fee_token: &Token,
+        fee: BigUint,
+    ) -> ResponseResult<SignedTx> {
+        target
+            .wallet
+            .sign_change_pub_key(
+                fee_token,
+                fee,
+                target.nonce,
+                target.auth_type,
+                self.time_range,
+            )
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use ethers::signers::LocalWallet;
+
+    use super::*;
+    use crate::rollup::types::tx::ZkSyncTx;
+    use crate::rollup::types::{AccountId, TokenId, TokenKind};
+
+    /// Server rejecting every `ChangePubKey` of `bad`, and with it any batch it is part of,
+    /// and never committing the key of `lost`.
+    #[derive(Default)]
+    struct RejectingSubmitter {
+        bad: Address,
+        lost: Address,
+        fees: Mutex<Vec<BigUint>>,
+        auth_types: Mutex<Vec<ChangePubKeyFeeType>>,
+        committed: Mutex<HashMap<Address, PubKeyHash>>,
+    }
+
+    impl RejectingSubmitter {
+        fn check(&self, tx: &SignedTx) -> ResponseResult<TxHash> {
+            let ZkSyncTx::ChangePubKey(change_pub_key) = &tx.0 else {
+                return Err(ClientError::IncorrectInput);
+            };
+            self.fees.lock().unwrap().push(change_pub_key.fee.clone());
+            if change_pub_key.account == self.bad {
+                return Err(ClientError::IncorrectInput);
+            }
+            Ok(TxHash::default())
+        }
+
+        fn commit(&self, txs: &[SignedTx]) {
+            for (tx, _) in txs {
+                let ZkSyncTx::ChangePubKey(change_pub_key) = tx else {
+                    continue;
+                };
+                self.auth_types
+                    .lock()
+                    .unwrap()
+                    .push(change_pub_key.eth_auth_data.fee_type());
+                if change_pub_key.account != self.lost {
+                    self.committed
+                        .lock()
+                        .unwrap()
+                        .insert(change_pub_key.account, change_pub_key.new_pk_hash);
+                }
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ActivationSubmitter for RejectingSubmitter {
+        async fn batch_fee(
+            &self,
+            _: Vec<ChangePubKeyFeeType>,
+            _: Vec<Address>,
+            _: &Token,
+        ) -> ResponseResult<BigUint> {
+            Ok(BigUint::from(1_000u32))
+        }
+
+        async fn fee(
+            &self,
+            _: ChangePubKeyFeeType,
+            _: Address,
+            _: &Token,
+        ) -> ResponseResult<BigUint> {
+            Ok(BigUint::from(400u32))
+        }
+
+        async fn send_batch(&self, txs: Vec<SignedTx>) -> ResponseResult<Vec<TxHash>> {
+            let hashes = txs
+                .iter()
+                .map(|tx| self.check(tx))
+                .collect::<ResponseResult<_>>()?;
+            self.commit(&txs);
+            Ok(hashes)
+        }
+
+        async fn send(&self, tx: SignedTx) -> ResponseResult<TxHash> {
+            let hash = self.check(&tx)?;
+            self.commit(&[tx]);
+            Ok(hash)
+        }
+
+        async fn committed_pub_key_hash(&self, address: Address) -> ResponseResult<PubKeyHash> {
+            Ok(self
+                .committed
+                .lock()
+                .unwrap()
+                .get(&address)
+                .copied()
+                .unwrap_or_default())
+        }
+    }
+
+    /// Contract registering every key, remembering the accounts it registered.
+    #[derive(Default)]
+    struct RecordingAuthorizer {
+        authorized: Mutex<Vec<Address>>,
+    }
+
+    #[async_trait]
+    impl OnchainAuthorizer for RecordingAuthorizer {
+        async fn authorize(&self, wallet: &Wallet, _nonce: Nonce) -> ResponseResult<()> {
+            self.authorized.lock().unwrap().push(wallet.address());
+            Ok(())
+        }
+    }
+
+    async fn wallets(count: u8) -> Vec<Wallet> {
+        let mut wallets = Vec::new();
+        for seed in 1..=count {
+            let mut wallet = Wallet::new(LocalWallet::from_bytes(&[seed; 32]).unwrap())
+                .await
+                .unwrap();
+            wallet.set_account_id(AccountId(seed as u32));
+            wallets.push(wallet);
+        }
+        wallets
+    }
+
+    /// Tests that accounts are activated in batches and members of a rejected batch one by one.
+    #[tokio::test]
+    async fn test_batched_activation() {
+        let wallets = wallets(5).await;
+        let submitter = RejectingSubmitter {
+            bad: wallets[3].address(),
+            ..Default::default()
+        };
+        let token = Token::new(TokenId(0), Address::zero(), "RBTC", 18, TokenKind::None);
+        let targets = wallets
+            .iter()
+            .map(|wallet| ActivationTarget {
+                wallet,
+                nonce: Nonce(0),
+                auth_type: ChangePubKeyFeeType::ECDSA,
+            })
+            .collect();
+
+        let activator = AccountActivator::new(ActivationConfig {
+            batch_size: 3,
+            compare_fees: true,
+            ..Default::default()
+        });
+        let summary = activator.run(&submitter, targets, &token).await;
+        assert_eq!(
+            summary.batched,
+            wallets[..3].iter().map(Wallet::address).collect::<Vec<_>>()
+        );
+        assert_eq!(summary.individual, vec![wallets[4].address()]);
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(summary.failed[0].0, wallets[3].address());
+        assert_eq!((summary.batches, summary.rejected_batches), (2, 1));
+        assert_eq!(summary.batch_fees.len(), 2);
+        assert_eq!(
+            summary.batch_fees[0].individual_fees,
+            BigUint::from(1_200u32)
+        );
+        let rows = summary.change_pubkey.rows();
+        let ecdsa = rows
+            .iter()
+            .find(|row| row.fee_type == ChangePubKeyFeeType::ECDSA)
+            .unwrap();
+        assert_eq!((ecdsa.submitted, ecdsa.succeeded, ecdsa.failed), (5, 4, 1));
+        assert_eq!(summary.change_pubkey.untested().len(), 2);
+
+        // Batch members pay an equal share of the batch fee, the others their own quote.
+        let fees = submitter.fees.lock().unwrap();
+        assert!(fees[..3].iter().all(|fee| *fee == BigUint::from(334u32)));
+        assert!(fees[4..].iter().all(|fee| *fee == BigUint::from(400u32)));
+    }
+
+    /// Tests onboarding with every authorization and the check of the committed keys.
+    #[tokio::test]
+    async fn test_onboarding_auth_types() {
+        let config = ActivationConfig {
+            batch_size: 2,
+            auth_types: ChangePubKeyFeeType::ALL.to_vec(),
+            create2: Some(Create2Config {
+                creator_address: Address::from_low_u64_be(0xfac),
+                code_hash: H256::from_low_u64_be(0xc0de),
+            }),
+            verify_timeout_secs: 1,
+            verify_poll_interval_ms: 10,
+            compare_fees: false,
+        };
+        let mut wallets = wallets(4).await;
+        let auth_types: Vec<ChangePubKeyFeeType> = (0..wallets.len())
+            .map(|index| config.auth_type(index))
+            .collect();
+        let create2 = config.create2_data(2).unwrap();
+        let eth_address = wallets[2].address();
+        wallets[2].set_create2(create2.clone());
+        assert_eq!(
+            wallets[2].address(),
+            create2.get_address(&wallets[2].pub_key_hash())
+        );
+        assert_ne!(wallets[2].address(), eth_address);
+
+        let submitter = RejectingSubmitter {
+            lost: wallets[3].address(),
+            ..Default::default()
+        };
+        let token = Token::new(TokenId(0), Address::zero(), "RBTC", 18, TokenKind::None);
+        let targets = || {
+            wallets
+                .iter()
+                .zip(&auth_types)
+                .map(|(wallet, auth_type)| ActivationTarget {
+                    wallet,
+                    nonce: Nonce(0),
+                    auth_type: *auth_type,
+                })
+                .collect()
+        };
+
+        // Without an L1 connection the Onchain accounts can not be authorized.
+        let summary = AccountActivator::new(config.clone())
+            .run(&submitter, targets(), &token)
+            .await;
+        let failed: Vec<Address> = summary.failed.iter().map(|(address, _)| *address).collect();
+        assert_eq!(failed, vec![wallets[0].address(), wallets[3].address()]);
+
+        let authorizer = RecordingAuthorizer::default();
+        let summary = AccountActivator::new(config)
+            .with_onchain_authorizer(&authorizer)
+            .run(&submitter, targets(), &token)
+            .await;
+        assert!(summary.failed.is_empty());
+        assert_eq!(summary.activated().count(), 4);
+        assert_eq!(
+            *authorizer.authorized.lock().unwrap(),
+            vec![wallets[0].address(), wallets[3].address()]
+        );
+        assert_eq!(summary.by_auth_type[&ChangePubKeyFeeType::Onchain], 2);
+        assert_eq!(summary.by_auth_type[&ChangePubKeyFeeType::CREATE2], 1);
+        assert_eq!(submitter.auth_types.lock().unwrap()[2..], auth_types[..]);
+        assert_eq!(summary.unverified, vec![wallets[3].address()]);
+        assert!(summary.change_pubkey.untested().is_empty());
+        let failed: u64 = summary
+            .change_pubkey
+            .rows()
+            .iter()
+            .map(|row| row.failed)
+            .sum();
+        assert_eq!(failed, 1);
+    }
+}
@@ -0,0 +1,226 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tracing::warn;
+
+use crate::engine::{Submission, SubmissionObserver};
+use crate::paths;
+use crate::rollup::types::TxHash;
+
+#[derive(Debug, Error)]
+pub enum AuditError {
+    #[error("Unable to access audit log: {0}")]
+    Io(#[from] io::Error),
+    #[error("Malformed audit entry on line {0}: {1}")]
+    Malformed(usize, String),
+    #[error("Audit chain broken at entry {0}")]
+    BrokenChain(u64),
+}
+
+/// Single submission recorded in the audit log.
+///
+/// `entry_hash` commits to all other fields including `prev_hash`, so altering,
+/// removing or reordering any entry breaks the chain from that point on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub seq: u64,
+    pub timestamp_ms: u128,
+    pub tx_hash: TxHash,
+    /// Hex-encoded signed payload exactly as it was submitted.
+    pub payload: String,
+    pub prev_hash: String,
+    pub entry_hash: String,
+}
+
+impl AuditEntry {
+    fn compute_hash(
+        seq: u64,
+        timestamp_ms: u128,
+        tx_hash: &TxHash,
+        payload: &str,
+        prev_hash: &str,
+    ) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(seq.to_be_bytes());
+        hasher.update(timestamp_ms.to_be_bytes());
+        hasher.update(tx_hash.data);
+        hasher.update(payload.as_bytes());
+        hasher.update(prev_hash.as_bytes());
+        hex::encode(hasher.finalize())
+    }
+
+    fn is_valid(&self, prev_hash: &str) -> bool {
+        self.prev_hash == prev_hash
+            && self.entry_hash
+                == Self::compute_hash(
+                    self.seq,
+                    self.timestamp_ms,
+                    &self.tx_hash,
+                    &self.payload,
+                    &self.prev_hash,
+                )
+    }
+}
+
+/// Hash of the (non-existent) entry preceding the first one.
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Append-only, hash-chained log of every submitted transaction.
+pub struct AuditLog {
+    file: File,
+    seq: u64,
+    last_hash: String,
+}
+
+impl AuditLog {
+    /// Opens the audit log, continuing the chain of an already existing file.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, AuditError> {
+        let (seq, last_hash) = if path.as_ref().exists() {
+            let entries = Self::verify(&path)?;
+            (
+                entries.len() as u64,
+                entries
+                    .last()
+                    .map(|entry| entry.entry_hash.clone())
+                    .unwrap_or_else(|| GENESIS_HASH.to_string()),
+            )
+        } else {
+            (0, GENESIS_HASH.to_string())
+        };
+        let file = paths::open_private(path, true)?;
+
+        Ok(Self {
+            file,
+            seq,
+            last_hash,
+        })
+    }
+
+    /// Records the signed payload of a submitted transaction and the hash returned by the server.
+    pub fn append(&mut self, payload: &[u8], tx_hash: &TxHash) -> Result<AuditEntry, AuditError> {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let payload = hex::encode(payload);
+        let entry_hash =
+            AuditEntry::compute_hash(self.seq, timestamp_ms, tx_hash, &payload, &self.last_hash);
+        let entry = AuditEntry {
+            seq: self.seq,
+            timestamp_ms,
+            tx_hash: *tx_hash,
+            payload,
+            prev_hash: self.last_hash.clone(),
+            entry_hash,
+        };
+
+        let line = serde_json::to_string(&entry)
+            .map_err(|err| AuditError::Malformed(self.seq as usize + 1, err.to_string()))?;
+        writeln!(self.file, "{}", line)?;
+        self.file.flush()?;
+
+        self.seq += 1;
+        self.last_hash = entry.entry_hash.clone();
+        Ok(entry)
+    }
+
+    /// Reads the whole audit log and checks that the chain is intact.
+    pub fn verify(path: impl AsRef<Path>) -> Result<Vec<AuditEntry>, AuditError> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut entries: Vec<AuditEntry> = Vec::new();
+        for (index, line) in reader.lines().enumerate() {
+            let entry: AuditEntry = serde_json::from_str(&line?)
+                .map_err(|err| AuditError::Malformed(index + 1, err.to_string()))?;
+            let prev_hash = entries
+                .last()
+                .map(|prev| prev.entry_hash.as_str())
+                .unwrap_or(GENESIS_HASH);
+            if entry.seq != index as u64 || !entry.is_valid(prev_hash) {
+                return Err(AuditError::BrokenChain(index as u64));
+            }
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+}
+
+/// Appends every submission the server accepted, in the order of the answers.
+impl SubmissionObserver for Mutex<AuditLog> {
+    fn observe(&self, submission: &Submission) {
+        let (Ok(tx_hash), Some(payload)) = (submission.result, submission.payload) else {
+            return;
+        };
+        if let Err(err) = self.lock().unwrap().append(payload.as_bytes(), &tx_hash) {
+            warn!(%tx_hash, error = %err, "unable to append to the audit log");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::rollup::provider::ClientError;
+
+    /// Tests that modifying a recorded payload is detected.
+    #[test]
+    fn test_audit_chain_detects_tampering() {
+        let path = std::env::temp_dir().join(format!("audit-test-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut log = AuditLog::open(&path).unwrap();
+        for i in 0..3u8 {
+            log.append(&[i, i, i], &TxHash { data: [i; 32] }).unwrap();
+        }
+        drop(log);
+
+        // Reopening continues the same chain.
+        let mut log = AuditLog::open(&path).unwrap();
+        log.append(&[9], &TxHash { data: [9; 32] }).unwrap();
+        assert_eq!(AuditLog::verify(&path).unwrap().len(), 4);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, content.replacen("010101", "010102", 1)).unwrap();
+        assert!(matches!(
+            AuditLog::verify(&path),
+            Err(AuditError::BrokenChain(1))
+        ));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// Tests that submissions are appended only once the server accepted them.
+    #[test]
+    fn test_audit_log_observer() {
+        let path =
+            std::env::temp_dir().join(format!("audit-observer-test-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let log = Mutex::new(AuditLog::open(&path).unwrap());
+        let rejected = ClientError::IncorrectInput;
+        let submission = |result| Submission {
+            tx_type: "transfer",
+            account: None,
+            fee: None,
+            payload: Some("{}"),
+            op: None,
+            result,
+            started: Instant::now(),
+        };
+
+        log.observe(&submission(Ok(TxHash { data: [1; 32] })));
+        log.observe(&submission(Err(&rejected)));
+        let entries = AuditLog::verify(&path).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].payload, hex::encode("{}"));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
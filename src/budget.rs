@@ -0,0 +1,134 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use thiserror::Error;
+use tracing::warn;
+
+use crate::progress::SetupPhase;
+
+#[derive(Debug, Error, Clone, PartialEq)]
+#[error("The {} phase exceeded its time budget of {}s", .phase.label(), .budget.as_secs())]
+pub struct BudgetExceeded {
+    pub phase: SetupPhase,
+    pub budget: Duration,
+}
+
+/// What happens to a setup phase that is still running when its time budget is spent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverrunPolicy {
+    /// Warn and grant the phase another budget, as often as it needs.
+    #[default]
+    Extend,
+    /// Give up on the accounts that are not done and continue with a smaller pool.
+    Skip,
+    /// Fail the run.
+    Abort,
+}
+
+/// Time budget of a setup phase, e.g. `time_budget_secs = 600` and `on_overrun = "skip"`
+/// in the `[funding]` section.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub struct PhaseBudget {
+    /// Unlimited when not set.
+    #[serde(default)]
+    pub time_budget_secs: Option<u64>,
+    #[serde(default)]
+    pub on_overrun: OverrunPolicy,
+}
+
+impl PhaseBudget {
+    /// Runs a phase within the budget, `None` when the phase was cut short by the `skip` policy.
+    ///
+    /// `remaining` tells how many accounts the phase has left, it is only used for logging.
+    /// On skip the phase future is dropped, which cancels the work it still had in flight.
+    pub async fn run<T>(
+        &self,
+        phase: SetupPhase,
+        work: impl Future<Output = T>,
+        remaining: impl Fn() -> usize,
+    ) -> Result<Option<T>, BudgetExceeded> {
+        match self.time_budget_secs {
+            Some(secs) => {
+                within(
+                    Duration::from_secs(secs),
+                    self.on_overrun,
+                    phase,
+                    work,
+                    remaining,
+                )
+                .await
+            }
+            None => Ok(Some(work.await)),
+        }
+    }
+}
+
+async fn within<T>(
+    budget: Duration,
+    policy: OverrunPolicy,
+    phase: SetupPhase,
+    work: impl Future<Output = T>,
+    remaining: impl Fn() -> usize,
+) -> Result<Option<T>, BudgetExceeded> {
+    let mut work = Box::pin(work);
+    let mut deadline = Instant::now() + budget;
+    loop {
+        if let Ok(output) = tokio::time::timeout_at(deadline.into(), &mut work).await {
+            return Ok(Some(output));
+        }
+        match policy {
+            OverrunPolicy::Extend => {
+                warn!(
+                    "The {} phase exceeded its {}s budget with {} accounts left, extending",
+                    phase.label(),
+                    budget.as_secs(),
+                    remaining()
+                );
+                deadline += budget;
+            }
+            OverrunPolicy::Skip => {
+                warn!(
+                    "The {} phase exceeded its {}s budget, skipping the {} accounts left",
+                    phase.label(),
+                    budget.as_secs(),
+                    remaining()
+                );
+                return Ok(None);
+            }
+            OverrunPolicy::Abort => return Err(BudgetExceeded { phase, budget }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests that a phase overrunning its budget is extended, skipped or aborted by policy.
+    #[tokio::test]
+    async fn test_overrun_policies() {
+        let budget = Duration::from_millis(20);
+        let slow = || async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            "done"
+        };
+        let phase = SetupPhase::Funding;
+
+        let extended = within(budget, OverrunPolicy::Extend, phase, slow(), || 1).await;
+        assert_eq!(extended, Ok(Some("done")));
+
+        let skipped = within(budget, OverrunPolicy::Skip, phase, slow(), || 1).await;
+        assert_eq!(skipped, Ok(None));
+
+        let aborted = within(budget, OverrunPolicy::Abort, phase, slow(), || 1).await;
+        assert_eq!(aborted, Err(BudgetExceeded { phase, budget }));
+
+        let fast = within(budget, OverrunPolicy::Abort, phase, async { 1 }, || 0).await;
+        assert_eq!(fast, Ok(Some(1)));
+
+        let unlimited = PhaseBudget::default().run(phase, slow(), || 1).await;
+        assert_eq!(unlimited, Ok(Some("done")));
+    }
+}
@@ -0,0 +1,223 @@
+//! Capture of signed transactions for replay outside the simulator.
+//!
+//! A capture directory holds two files written side by side, one record per signed
+//! transaction in signing order:
+//!
+//! * `txs.jsonl` - one JSON object per line: `seq`, `timestampMs`, the `tx` and
+//!   `ethSignature` exactly as passed to `send_tx`, and the `offset` of the matching
+//!   record in `txs.bin`.
+//! * `txs.bin` - the raw bytes, big endian:
+//!
+//! ```text
+//! seq: u32 | len: u32 | tx bytes: [u8; len] | pub key: [u8; 32] | signature: [u8; 64]
+//!          | has eth signature: u8 | eth signature: [u8; 65] (only if the flag is 1)
+//! ```
+//!
+//! The tx bytes are the message signed by the L2 key (`ZkSyncTx::get_bytes`), so a
+//! node can be fed the exact byte stream the simulator produced under load.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::warn;
+
+use crate::engine::SubmissionObserver;
+use crate::paths;
+use crate::report::now_ms;
+use crate::rollup::types::tx::signature::{PACKED_PUBLIC_KEY_LEN, PACKED_SIGNATURE_LEN};
+use crate::rollup::types::tx::{PackedEthSignature, ZkSyncTx};
+use crate::wallet::SignedTx;
+
+const JSON_FILE: &str = "txs.jsonl";
+const BINARY_FILE: &str = "txs.bin";
+const ETH_SIGNATURE_LEN: usize = 65;
+
+#[derive(Debug, Error)]
+pub enum CaptureError {
+    #[error("Unable to access capture: {0}")]
+    Io(#[from] io::Error),
+    #[error("Malformed capture record on line {0}: {1}")]
+    Malformed(usize, String),
+    #[error("Binary record {0} does not match its JSON record")]
+    Mismatch(u32),
+}
+
+/// Signed transaction as recorded in `txs.jsonl`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturedTx {
+    pub seq: u32,
+    pub timestamp_ms: u128,
+    pub tx: ZkSyncTx,
+    pub eth_signature: Option<PackedEthSignature>,
+    /// Position of the binary record in `txs.bin`.
+    pub offset: u64,
+}
+
+impl CapturedTx {
+    /// Binary record as laid out in `txs.bin`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let tx_bytes = self.tx.get_bytes();
+        let signature = self.tx.signature();
+        let mut out = Vec::with_capacity(
+            9 + tx_bytes.len() + PACKED_PUBLIC_KEY_LEN + PACKED_SIGNATURE_LEN + ETH_SIGNATURE_LEN,
+        );
+        out.extend_from_slice(&self.seq.to_be_bytes());
+        out.extend_from_slice(&(tx_bytes.len() as u32).to_be_bytes());
+        out.extend(tx_bytes);
+        out.extend_from_slice(&signature.pub_key);
+        out.extend_from_slice(&signature.signature);
+        match &self.eth_signature {
+            Some(eth_signature) => {
+                out.push(1);
+                out.extend_from_slice(&eth_signature.serialize_packed());
+            }
+            None => out.push(0),
+        }
+        out
+    }
+}
+
+/// Writes every signed transaction of a run into a capture directory.
+pub struct CaptureWriter {
+    json: File,
+    binary: File,
+    seq: u32,
+    offset: u64,
+}
+
+impl CaptureWriter {
+    /// Creates the capture directory, replacing the files of a previous capture.
+    pub fn create(dir: impl AsRef<Path>) -> Result<Self, CaptureError> {
+        let (json_path, binary_path) = Self::files(dir);
+        Ok(Self {
+            json: paths::open_private(json_path, false)?,
+            binary: paths::open_private(binary_path, false)?,
+            seq: 0,
+            offset: 0,
+        })
+    }
+
+    fn files(dir: impl AsRef<Path>) -> (PathBuf, PathBuf) {
+        (dir.as_ref().join(JSON_FILE), dir.as_ref().join(BINARY_FILE))
+    }
+
+    /// Records a signed transaction, before it is submitted.
+    pub fn append(
+        &mut self,
+        tx: &ZkSyncTx,
+        eth_signature: Option<&PackedEthSignature>,
+    ) -> Result<CapturedTx, CaptureError> {
+        let captured = CapturedTx {
+            seq: self.seq,
+            timestamp_ms: now_ms(),
+            tx: tx.clone(),
+            eth_signature: eth_signature.copied(),
+            offset: self.offset,
+        };
+        let bytes = captured.to_bytes();
+        let line = serde_json::to_string(&captured)
+            .map_err(|err| CaptureError::Malformed(self.seq as usize + 1, err.to_string()))?;
+
+        self.binary.write_all(&bytes)?;
+        self.binary.flush()?;
+        writeln!(self.json, "{}", line)?;
+        self.json.flush()?;
+
+        self.seq += 1;
+        self.offset += bytes.len() as u64;
+        Ok(captured)
+    }
+
+    /// Reads a capture back, checking that both files describe the same transactions.
+    pub fn read(dir: impl AsRef<Path>) -> Result<Vec<CapturedTx>, CaptureError> {
+        let (json_path, binary_path) = Self::files(dir);
+        let binary = std::fs::read(binary_path)?;
+        let reader = BufReader::new(File::open(json_path)?);
+        let mut captured = Vec::new();
+        for (index, line) in reader.lines().enumerate() {
+            let record: CapturedTx = serde_json::from_str(&line?)
+                .map_err(|err| CaptureError::Malformed(index + 1, err.to_string()))?;
+            let expected = record.to_bytes();
+            let start = record.offset as usize;
+            if binary.get(start..start + expected.len()) != Some(expected.as_slice()) {
+                return Err(CaptureError::Mismatch(record.seq));
+            }
+            captured.push(record);
+        }
+        Ok(captured)
+    }
+}
+
+/// Captures every signed transaction before the engine sends it.
+impl SubmissionObserver for Mutex<CaptureWriter> {
+    fn sending(&self, (tx, eth_signature): &SignedTx) {
+        if let Err(err) = self.lock().unwrap().append(tx, eth_signature.as_ref()) {
+            warn!(error = %err, "unable to capture signed transaction");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::types::{Signature, U256};
+    use num::BigUint;
+
+    use super::*;
+    use crate::rollup::types::tx::{Transfer, TxSignature};
+    use crate::rollup::types::{AccountId, Address, Nonce, TokenId};
+
+    /// Tests that captured transactions read back identically and that edited binaries are detected.
+    #[test]
+    fn test_capture_round_trip() {
+        let dir = std::env::temp_dir().join(format!("capture-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let transfer = |nonce| {
+            ZkSyncTx::Transfer(Box::new(Transfer {
+                account_id: AccountId(1),
+                from: Address::from_low_u64_be(1),
+                to: Address::from_low_u64_be(2),
+                token: TokenId(0),
+                amount: BigUint::from(1_000u32),
+                fee: BigUint::from(10u32),
+                nonce: Nonce(nonce),
+                signature: TxSignature {
+                    pub_key: [nonce as u8; PACKED_PUBLIC_KEY_LEN],
+                    signature: [7; PACKED_SIGNATURE_LEN],
+                },
+                time_range: Default::default(),
+            }))
+        };
+        let eth_signature = PackedEthSignature(Signature {
+            r: U256::from(1),
+            s: U256::from(2),
+            v: 27,
+        });
+
+        let mut writer = CaptureWriter::create(&dir).unwrap();
+        let first = writer.append(&transfer(0), Some(&eth_signature)).unwrap();
+        let second = writer.append(&transfer(1), None).unwrap();
+        assert_eq!(second.offset, first.to_bytes().len() as u64);
+        drop(writer);
+
+        let captured = CaptureWriter::read(&dir).unwrap();
+        assert_eq!(captured, vec![first, second]);
+
+        let binary_path = dir.join(BINARY_FILE);
+        let mut binary = std::fs::read(&binary_path).unwrap();
+        let last = binary.len() - 1;
+        binary[last] ^= 1;
+        std::fs::write(&binary_path, binary).unwrap();
+        assert!(matches!(
+            CaptureWriter::read(&dir),
+            Err(CaptureError::Mismatch(1))
+        ));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
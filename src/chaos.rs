@@ -0,0 +1,384 @@
+//! Self-chaos mode: faults injected into the simulator itself while it runs.
+//!
+//! Week-long unattended soaks are only trustworthy if the simulator survives its
+//! own failures, so this mode periodically kills a worker task, suspends the
+//! confirmation tracker or corrupts a queued transaction, and then asserts that
+//! the simulator recovered within the configured time.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use crate::rng::{RngStream, RngStreams, StreamRng};
+
+/// Fault the simulator can inject into itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FaultKind {
+    /// Aborts one of the submission worker tasks.
+    KillWorker,
+    /// Stops the confirmation tracker for a while, dropping what it receives meanwhile.
+    DropTracker,
+    /// Replaces one queued transaction by garbage.
+    CorruptQueueEntry,
+}
+
+impl FaultKind {
+    pub const ALL: [FaultKind; 3] = [
+        FaultKind::KillWorker,
+        FaultKind::DropTracker,
+        FaultKind::CorruptQueueEntry,
+    ];
+}
+
+/// Self-chaos mode, enabled by the `[chaos]` configuration section.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChaosConfig {
+    /// Time between two injected faults.
+    pub interval_secs: u64,
+    /// Faults to choose from, all of them when empty.
+    #[serde(default)]
+    pub faults: Vec<FaultKind>,
+    /// How long the tracker stays down on `drop_tracker`.
+    #[serde(default = "ChaosConfig::default_tracker_downtime_secs")]
+    pub tracker_downtime_secs: u64,
+    /// Time the simulator has to recover once a fault is over.
+    #[serde(default = "ChaosConfig::default_recovery_timeout_secs")]
+    pub recovery_timeout_secs: u64,
+    /// Seed making the sequence of faults reproducible, takes precedence over the `chaos`
+    /// stream of the `[rng]` section.
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+impl ChaosConfig {
+    fn default_tracker_downtime_secs() -> u64 {
+        10
+    }
+
+    fn default_recovery_timeout_secs() -> u64 {
+        60
+    }
+}
+
+/// Parts of the simulator faults are injected into.
+#[async_trait]
+pub trait ChaosTarget: Send + Sync {
+    /// Aborts a worker task, returning which one.
+    async fn kill_worker(&self) -> Option<String>;
+
+    /// Suspends the confirmation tracker for the given time.
+    async fn suspend_tracker(&self, downtime: Duration);
+
+    /// Corrupts a queued transaction, returning which one.
+    async fn corrupt_queue_entry(&self) -> Option<String>;
+
+    /// Checks that the simulator recovered from the fault.
+    async fn check_recovered(&self, fault: FaultKind) -> Result<(), String>;
+}
+
+/// Record of a single injected fault.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaultRecord {
+    pub fault: FaultKind,
+    /// Worker or queue entry affected, if the fault targets one.
+    pub target: Option<String>,
+    pub recovery_ms: Option<f64>,
+    /// Last failed recovery check when the simulator did not recover in time.
+    pub failure: Option<String>,
+}
+
+impl FaultRecord {
+    pub fn recovered(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+pub struct ChaosMonkey {
+    config: ChaosConfig,
+    rng: StreamRng,
+}
+
+impl ChaosMonkey {
+    pub fn new(config: ChaosConfig) -> Self {
+        let rng = StreamRng::seed_from_u64(config.seed.unwrap_or_else(rand::random));
+        Self { config, rng }
+    }
+
+    /// Monkey drawing its faults from the `chaos` stream of the run unless a seed is configured.
+    pub fn from_streams(config: ChaosConfig, streams: &RngStreams) -> Self {
+        let rng = match config.seed {
+            Some(seed) => StreamRng::seed_from_u64(seed),
+            None => streams.stream(RngStream::Chaos),
+        };
+        Self { config, rng }
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.config.interval_secs)
+    }
+
+    fn pick_fault(&mut self) -> FaultKind {
+        let faults: &[FaultKind] = if self.config.faults.is_empty() {
+            &FaultKind::ALL
+        } else {
+            &self.config.faults
+        };
+        *faults
+            .choose(&mut self.rng)
+            .expect("fault list is never empty")
+    }
+
+    /// Injects a randomly chosen fault and waits until the simulator recovers from it.
+    pub async fn inject(&mut self, target: &dyn ChaosTarget) -> FaultRecord {
+        let fault = self.pick_fault();
+        let affected = match fault {
+            FaultKind::KillWorker => target.kill_worker().await,
+            FaultKind::DropTracker => {
+                target
+                    .suspend_tracker(Duration::from_secs(self.config.tracker_downtime_secs))
+                    .await;
+                None
+            }
+            FaultKind::CorruptQueueEntry => target.corrupt_queue_entry().await,
+        };
+
+        let started = Instant::now();
+        let timeout = Duration::from_secs(self.config.recovery_timeout_secs);
+        let poll_interval = Duration::from_secs(1).min(timeout.max(Duration::from_millis(1)));
+        loop {
+            match target.check_recovered(fault).await {
+                Ok(()) => {
+                    return FaultRecord {
+                        fault,
+                        target: affected,
+                        recovery_ms: Some(started.elapsed().as_secs_f64() * 1000.0),
+                        failure: None,
+                    }
+                }
+                Err(failure) if started.elapsed() >= timeout => {
+                    return FaultRecord {
+                        fault,
+                        target: affected,
+                        recovery_ms: None,
+                        failure: Some(failure),
+                    }
+                }
+                Err(_) => tokio::time::sleep(poll_interval).await,
+            }
+        }
+    }
+}
+
+/// Name of the worker following the confirmations, suspended on `drop_tracker`.
+pub const TRACKER_WORKER: &str = "confirmations";
+
+/// How often stopped workers are restarted.
+const SUPERVISE_INTERVAL: Duration = Duration::from_secs(1);
+
+struct Worker {
+    name: &'static str,
+    start: Box<dyn Fn() -> JoinHandle<()> + Send + Sync>,
+    handle: JoinHandle<()>,
+    suspended_until: Option<Instant>,
+}
+
+/// Background tasks of a run, restarted by [`Workers::supervise`] whenever they stop.
+///
+/// Transactions are generated right before they are sent, so there is no queue to corrupt:
+/// `corrupt_queue_entry` affects nothing.
+#[derive(Default)]
+pub struct Workers {
+    workers: Mutex<Vec<Worker>>,
+    kills: AtomicUsize,
+}
+
+impl Workers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts a worker, `start` is called again each time it has to be restarted.
+    pub fn spawn(
+        &self,
+        name: &'static str,
+        start: impl Fn() -> JoinHandle<()> + Send + Sync + 'static,
+    ) {
+        let handle = start();
+        self.workers.lock().unwrap().push(Worker {
+            name,
+            start: Box::new(start),
+            handle,
+            suspended_until: None,
+        });
+    }
+
+    /// Restarts the stopped workers that are not suspended.
+    fn restart_stopped(&self) {
+        let now = Instant::now();
+        for worker in self.workers.lock().unwrap().iter_mut() {
+            if worker.suspended_until.is_some_and(|until| until > now) {
+                continue;
+            }
+            worker.suspended_until = None;
+            if worker.handle.is_finished() {
+                warn!("Restarting stopped worker {}", worker.name);
+                worker.handle = (worker.start)();
+            }
+        }
+    }
+
+    /// Keeps restarting stopped workers until aborted.
+    pub async fn supervise(self: Arc<Self>) {
+        loop {
+            tokio::time::sleep(SUPERVISE_INTERVAL).await;
+            self.restart_stopped();
+        }
+    }
+
+    /// Stops all workers for good.
+    pub fn abort_all(&self) {
+        for worker in self.workers.lock().unwrap().iter() {
+            worker.handle.abort();
+        }
+    }
+}
+
+#[async_trait]
+impl ChaosTarget for Workers {
+    async fn kill_worker(&self) -> Option<String> {
+        let workers = self.workers.lock().unwrap();
+        if workers.is_empty() {
+            return None;
+        }
+        let worker = &workers[self.kills.fetch_add(1, Ordering::Relaxed) % workers.len()];
+        worker.handle.abort();
+        Some(worker.name.to_string())
+    }
+
+    async fn suspend_tracker(&self, downtime: Duration) {
+        {
+            let mut workers = self.workers.lock().unwrap();
+            let Some(tracker) = workers
+                .iter_mut()
+                .find(|worker| worker.name == TRACKER_WORKER)
+            else {
+                return;
+            };
+            tracker.handle.abort();
+            tracker.suspended_until = Some(Instant::now() + downtime);
+        }
+        tokio::time::sleep(downtime).await;
+    }
+
+    async fn corrupt_queue_entry(&self) -> Option<String> {
+        None
+    }
+
+    async fn check_recovered(&self, _fault: FaultKind) -> Result<(), String> {
+        let workers = self.workers.lock().unwrap();
+        match workers.iter().find(|worker| worker.handle.is_finished()) {
+            Some(worker) => Err(format!("worker {} is not running", worker.name)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct FakeSimulator;
+
+    #[async_trait]
+    impl ChaosTarget for FakeSimulator {
+        async fn kill_worker(&self) -> Option<String> {
+            Some("worker-3".to_string())
+        }
+
+        async fn suspend_tracker(&self, _downtime: Duration) {}
+
+        async fn corrupt_queue_entry(&self) -> Option<String> {
+            Some("queue[0]".to_string())
+        }
+
+        async fn check_recovered(&self, fault: FaultKind) -> Result<(), String> {
+            match fault {
+                FaultKind::CorruptQueueEntry => Err("corrupted entry still queued".to_string()),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    /// Tests that only configured faults are injected and missing recovery is reported.
+    #[tokio::test]
+    async fn test_recovery_assertions() {
+        let mut monkey = ChaosMonkey::new(ChaosConfig {
+            interval_secs: 1,
+            faults: vec![FaultKind::KillWorker, FaultKind::CorruptQueueEntry],
+            tracker_downtime_secs: 0,
+            recovery_timeout_secs: 0,
+            seed: Some(7),
+        });
+
+        for _ in 0..10 {
+            let record = monkey.inject(&FakeSimulator).await;
+            match record.fault {
+                FaultKind::KillWorker => {
+                    assert!(record.recovered());
+                    assert_eq!(record.target.as_deref(), Some("worker-3"));
+                }
+                FaultKind::CorruptQueueEntry => {
+                    assert_eq!(
+                        record.failure.as_deref(),
+                        Some("corrupted entry still queued")
+                    );
+                }
+                FaultKind::DropTracker => panic!("fault not configured"),
+            }
+        }
+    }
+
+    /// Tests that killed and suspended workers are restarted by the supervisor.
+    #[tokio::test]
+    async fn test_workers_recover() {
+        let workers = Workers::new();
+        workers.spawn(TRACKER_WORKER, || tokio::spawn(std::future::pending()));
+        workers.spawn("sampler", || tokio::spawn(std::future::pending()));
+
+        assert_eq!(workers.kill_worker().await.as_deref(), Some(TRACKER_WORKER));
+        assert_eq!(workers.kill_worker().await.as_deref(), Some("sampler"));
+        tokio::task::yield_now().await;
+        assert_eq!(
+            workers.check_recovered(FaultKind::KillWorker).await,
+            Err(format!("worker {} is not running", TRACKER_WORKER))
+        );
+        workers.restart_stopped();
+        assert!(workers.check_recovered(FaultKind::KillWorker).await.is_ok());
+
+        let downtime = Duration::from_millis(50);
+        workers.workers.lock().unwrap()[0].suspended_until = Some(Instant::now() + downtime);
+        workers.workers.lock().unwrap()[0].handle.abort();
+        tokio::task::yield_now().await;
+        workers.restart_stopped();
+        assert!(workers
+            .check_recovered(FaultKind::DropTracker)
+            .await
+            .is_err());
+        tokio::time::sleep(downtime).await;
+        workers.restart_stopped();
+        assert!(workers
+            .check_recovered(FaultKind::DropTracker)
+            .await
+            .is_ok());
+        assert_eq!(workers.corrupt_queue_entry().await, None);
+        workers.abort_all();
+    }
+}
@@ -0,0 +1,212 @@
+//! Checkpoint of an interrupted run, loaded with `--resume` to continue it.
+//!
+//! The checkpoint holds what the simulator knows that the server cannot tell it back:
+//! the local nonces and balances of every account and the operations that were still
+//! unconfirmed. Private keys are never written; accounts are matched by address, so a
+//! run can only be resumed when its wallets are derived from the `[keys]` mnemonic or
+//! reloaded from the `[keystore]`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::Path;
+
+use num::BigUint;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::paths;
+use crate::report::now_ms;
+use crate::rng::RngCheckpoint;
+use crate::rollup::confirmation::TrackedOp;
+use crate::rollup::types::serde_wrappers::BigUintSerdeWrapper;
+use crate::rollup::types::{AccountId, Address, Nonce};
+
+/// Name of the checkpoint in the report directory of a run.
+pub const CHECKPOINT_FILE: &str = "checkpoint.json";
+const CHECKPOINT_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum CheckpointError {
+    #[error("Unable to access checkpoint: {0}")]
+    Io(#[from] io::Error),
+    #[error("Malformed checkpoint: {0}")]
+    Malformed(#[from] serde_json::Error),
+    #[error("Checkpoint version {0} is not supported")]
+    Version(u32),
+    #[error("The transaction pipeline can not be resumed from a checkpoint")]
+    Unsupported,
+    #[error("Accounts of the checkpoint are missing from the pool: {0:?}")]
+    MissingAccounts(Vec<Address>),
+    #[error("The accounts are in use by transactions still being prepared")]
+    AccountsBusy,
+}
+
+/// Local view of an account at the time of the checkpoint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountCheckpoint {
+    pub address: Address,
+    pub account_id: Option<AccountId>,
+    pub nonce: Nonce,
+    /// Balances by token symbol.
+    pub balances: HashMap<String, BigUintSerdeWrapper>,
+    /// Deposits not accepted by the rollup yet, by token symbol.
+    #[serde(default)]
+    pub depositing: HashMap<String, BigUintSerdeWrapper>,
+}
+
+/// Operation that was neither confirmed nor given up on when the run stopped.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingCheckpoint {
+    pub op: TrackedOp,
+    pub tx_type: String,
+    /// How long the operation had been pending, for reference only.
+    pub pending_ms: u64,
+}
+
+/// State of the transaction pipeline needed to continue the run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PipelineState {
+    pub accounts: Vec<AccountCheckpoint>,
+    pub pending: Vec<PendingCheckpoint>,
+}
+
+impl PipelineState {
+    /// Balances of every token summed over the accounts.
+    pub fn token_totals(&self) -> BTreeMap<String, BigUint> {
+        let mut totals = BTreeMap::<String, BigUint>::new();
+        for account in &self.accounts {
+            for (token, balance) in &account.balances {
+                *totals.entry(token.clone()).or_default() += &balance.0;
+            }
+        }
+        totals
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Checkpoint {
+    pub version: u32,
+    pub run_id: String,
+    pub taken_at_ms: u128,
+    /// Time the run had been sending transactions, deducted from `duration_secs` on resume.
+    pub elapsed_secs: u64,
+    pub submitted: u64,
+    pub failed: u64,
+    pub state: PipelineState,
+    /// Random streams of the run, missing from checkpoints of runs without them.
+    #[serde(default)]
+    pub rng: Option<RngCheckpoint>,
+}
+
+impl Checkpoint {
+    pub fn new(
+        run_id: &str,
+        elapsed_secs: u64,
+        submitted: u64,
+        failed: u64,
+        state: PipelineState,
+    ) -> Self {
+        Self {
+            version: CHECKPOINT_VERSION,
+            run_id: run_id.to_string(),
+            taken_at_ms: now_ms(),
+            elapsed_secs,
+            submitted,
+            failed,
+            state,
+            rng: None,
+        }
+    }
+
+    pub fn with_rng(mut self, rng: RngCheckpoint) -> Self {
+        self.rng = Some(rng);
+        self
+    }
+
+    pub fn load_from_file(path: impl AsRef<Path>) -> Result<Self, CheckpointError> {
+        let checkpoint: Self = serde_json::from_slice(&std::fs::read(path)?)?;
+        if checkpoint.version != CHECKPOINT_VERSION {
+            return Err(CheckpointError::Version(checkpoint.version));
+        }
+        Ok(checkpoint)
+    }
+
+    /// Writes the checkpoint, only readable by its owner since it lists the run's accounts.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), CheckpointError> {
+        let mut file = paths::open_private(path, false)?;
+        serde_json::to_writer_pretty(&mut file, self)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::signers::LocalWallet;
+
+    use super::*;
+    use crate::accounts::AccountPool;
+    use crate::rng::RngStream;
+    use crate::rollup::types::TxHash;
+    use crate::wallet::Wallet;
+
+    /// Tests that account state survives a checkpoint written to disk and restored into a new pool.
+    #[tokio::test]
+    async fn test_checkpoint_round_trip() {
+        let wallets = || async {
+            let mut wallets = Vec::new();
+            for seed in 1..=2u8 {
+                let eth_signer = LocalWallet::from_bytes(&[seed; 32]).unwrap();
+                wallets.push(Wallet::new(eth_signer).await.unwrap());
+            }
+            wallets
+        };
+        let mut pool = AccountPool::new(wallets().await);
+        let address = pool.addresses()[0];
+        let account = pool.get_mut(&address).unwrap();
+        account.wallet.set_account_id(AccountId(7));
+        account.state.nonce = Nonce(12);
+        account
+            .state
+            .balances
+            .insert("RBTC".to_string(), BigUint::from(1_000u32));
+
+        let state = PipelineState {
+            accounts: pool.checkpoint(),
+            pending: vec![PendingCheckpoint {
+                op: TrackedOp::Tx(TxHash { data: [3; 32] }),
+                tx_type: "transfer".to_string(),
+                pending_ms: 1_500,
+            }],
+        };
+        assert_eq!(state.token_totals()["RBTC"], BigUint::from(1_000u32));
+        let checkpoint = Checkpoint::new("soak", 600, 3_000, 2, state).with_rng(RngCheckpoint {
+            master_seed: 42,
+            positions: BTreeMap::from([(RngStream::Amounts, 10)]),
+        });
+        let path = std::env::temp_dir()
+            .join(format!("checkpoint-test-{}", std::process::id()))
+            .join(CHECKPOINT_FILE);
+        checkpoint.write(&path).unwrap();
+        let loaded = Checkpoint::load_from_file(&path).unwrap();
+        assert_eq!(loaded, checkpoint);
+
+        let mut resumed = AccountPool::new(wallets().await);
+        resumed.restore(&loaded.state.accounts).unwrap();
+        let account = resumed.get(&address).unwrap();
+        assert_eq!(account.wallet.account_id(), Some(AccountId(7)));
+        assert_eq!(resumed.nonce(&address), Some(Nonce(12)));
+        assert_eq!(account.state.balances["RBTC"], BigUint::from(1_000u32));
+
+        let mut other = AccountPool::new(Vec::new());
+        assert!(matches!(
+            other.restore(&loaded.state.accounts),
+            Err(CheckpointError::MissingAccounts(missing)) if missing.len() == 2
+        ));
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
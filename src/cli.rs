@@ -1,69 +1,1571 @@
-use clap::{Command, Parser, arg};
-use rand::prelude::*;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use crate::{config::Config, transaction::Transaction, throttler::Throttler};
+use clap::{ArgAction, Args, Parser, Subcommand};
+use ethers::signers::Signer;
+use tracing::{debug, error, info, warn};
 
+use crate::{
+    accounts::AccountPool,
+    activation::AccountActivator,
+    audit::AuditLog,
+    capture::CaptureWriter,
+    chaos::{ChaosMonkey, Workers, TRACKER_WORKER},
+    checkpoint::{Checkpoint, CHECKPOINT_FILE},
+    config::{Config, ConfigOverride, DEFAULT_CONFIG_FILE},
+    config_migration::{self, CURRENT_SCHEMA_VERSION},
+    control::{self, fetch_live_export, ControlState},
+    dry_run::{mock_rollup, DryRun},
+    engine::{Engine, LoadMode, TxPipeline},
+    funding::FundingOrchestrator,
+    health::{self, Health, RunStatus},
+    l1::{auth::L1Authorizer, funding::L1FundingSteps, node::L1Node},
+    logging::{self, LOG_FILE},
+    metrics::{
+        pipeline::{QueueDepthHistory, SAMPLE_INTERVAL},
+        profile::STAGE_PROFILE_FILE,
+        prometheus, Metrics,
+    },
+    misbehavior::MisbehaviorInjector,
+    paths,
+    progress::{SetupPhase, SetupProgress},
+    report::{
+        baseline::{Baseline, BaselineError},
+        config_diff,
+        explorer_labels::{ExplorerLabels, EXPLORER_LABELS_FILE},
+        funding::FundingReport,
+        history_check::check_accounts,
+        html::{queue_depth_chart, HtmlReport, HTML_REPORT_FILE},
+        journey::JourneyObserver,
+        manifest::{ManifestError, RunManifest},
+        notify::{post_summary, RunOutcome, RunSummary},
+        redact::redact_export,
+        significance,
+        summary::RunReport,
+        units::AmountFormat,
+        RunRecorder,
+    },
+    resubmission::ResubmissionStudy,
+    rng::{RngStream, RngStreams},
+    rollup::{
+        confirmation::ConfirmationTracker,
+        events::EventListener,
+        failover::FailoverProvider,
+        http::HttpProvider,
+        mock::MockProvider,
+        network::Network,
+        provider::Provider,
+        retry::RetryProvider,
+        tokens::TokenRegistry,
+        types::{TokenId, TokenLike, TxHash},
+    },
+    scenario::{
+        cold_start::{ColdStart, RunningTraffic},
+        interleaving::{InterleavingConfig, InterleavingScenario},
+        merchant_payouts::{MerchantPayoutScenario, MerchantPayoutsConfig},
+        nft_interference::{self, NftInterference, NftInterferenceConfig},
+        pause::PauseControl,
+        reconnect_storm::{ConfirmationAudit, ReconnectStorm, Reconnectable, StormReport},
+        script::{EngineExecutor, ScenarioFile, ScenarioRunner},
+        templates::Template,
+        wait_for::BlockProgress,
+    },
+    shutdown::Shutdown,
+    submission::{InterleavingPool, MerchantPool, MixedPool, RollupPipeline},
+    tagging::RunTag,
+    wallet::{
+        derivation::derive_addresses,
+        keystore::{AccountKeystore, KeystoreConfig},
+    },
+};
+
+/// A CLI simulation tool for RIF Rollup.
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(name = "simulation-tool", version, about, long_about = None)]
 pub struct Cli {
-    pub verbose: bool,
-    pub config_file: Option<String>,
+    /// Turns on more verbose logging, -vv for trace output
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+    /// Overrides default configuration file
+    #[arg(short, long, value_name = "FILE", global = true)]
+    pub config: Option<PathBuf>,
+    /// Selects the network, `mainnet`, `testnet`, `localhost` or `custom:<chain id>`; overrides `network.chain`
+    #[arg(long, value_name = "NETWORK", global = true)]
+    pub network: Option<Network>,
+    /// Overrides a configuration value, e.g. `--set general.tps=100`; applied after the RIF_SIM_* environment variables
+    #[arg(long = "set", value_name = "PATH=VALUE", global = true)]
+    pub overrides: Vec<ConfigOverride>,
+    /// Runs the simulation when no command is given
+    #[command(subcommand)]
+    pub command: Option<Commands>,
 }
 
-fn create_cli() -> Command {
-    let app = Command::new("Simulation Tool")
-        .version("0.1")
-        .about("A CLI simulation tool for RIF Rollup");
-    let verbose_arg = arg!(-v --verbose "Turns on more verbose logging");
-    let config_arg = arg!(-c --config <FILE> "Overrides default configuration file");
-    app.arg(verbose_arg);
-    app.arg(config_arg);
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    /// Runs the simulation
+    Run(RunArgs),
+    /// Funds the simulated accounts from the master wallet without running the simulation
+    Fund(FundArgs),
+    /// Works with simulation reports
+    #[command(subcommand)]
+    Report(ReportCommand),
+    /// Checks the configuration file and exits
+    ValidateConfig,
+    /// Works with configuration files
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Lists the addresses of the accounts derived from the [keys] mnemonic
+    Accounts(AccountsArgs),
+}
 
-    app
+#[derive(Args, Debug, Default)]
+pub struct RunArgs {
+    /// Runs the phases of a scenario file instead of the random workload
+    #[arg(short, long, value_name = "FILE")]
+    pub scenario: Option<PathBuf>,
+    /// Runs a scenario shipped with the binary; `--set` adjusts the settings it brings
+    #[arg(long, value_name = "NAME", value_parser = Template::names(), conflicts_with_all = ["scenario", "resume"])]
+    pub template: Option<String>,
+    /// Fails the run when its metrics regressed against a baseline JSON file
+    #[arg(short, long, value_name = "FILE")]
+    pub baseline: Option<PathBuf>,
+    /// Continues an interrupted run from the checkpoint it wrote
+    #[arg(long, value_name = "CHECKPOINT", conflicts_with = "scenario")]
+    pub resume: Option<PathBuf>,
+    /// Generates and signs transactions against an in-process rollup from accounts assumed
+    /// funded, printing them instead of submitting them
+    #[arg(long)]
+    pub dry_run: bool,
 }
 
-impl Cli {
-    pub fn new() -> Self {
-        Cli {
-            verbose: false,
-            config_file: None
-        }
-    }
+#[derive(Args, Debug)]
+pub struct FundArgs {
+    /// Number of accounts to fund, `account_count` of the configuration and the fee sponsor by default
+    #[arg(short = 'n', long)]
+    pub accounts: Option<u32>,
+    /// Private key of the master wallet paying for the funding, `funding.master_key` by default;
+    /// prefer RIF_SIM_FUNDING__MASTER_KEY over the flag on shared machines
+    #[arg(long, value_name = "HEX")]
+    pub master_key: Option<String>,
+    /// Keeps the keys of the [keystore] in a plain JSON file instead of encrypted keystore files
+    #[arg(long)]
+    pub insecure_plain: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ReportCommand {
+    /// Exports the results of a simulation
+    Export(ExportArgs),
+    /// Checks the artifacts of a finished run against the hashes of its manifest
+    Verify(VerifyArgs),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Rewrites a configuration file written for an older schema version to the current one
+    Migrate(MigrateArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct MigrateArgs {
+    /// Writes the migrated configuration to a file instead of replacing the original
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+    /// Only reports whether the file needs migrating, failing when it does
+    #[arg(long, conflicts_with = "output")]
+    pub check: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// Exports aggregates and records collected so far by a running simulation
+    #[arg(long)]
+    pub live: bool,
+    /// Control API address of the running simulation
+    #[arg(long)]
+    pub address: Option<SocketAddr>,
+    /// Writes the export to a file instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+    /// Replaces addresses and hashes with stable pseudonyms for public sharing
+    #[arg(long)]
+    pub redact: bool,
+}
 
+#[derive(Args, Debug)]
+pub struct VerifyArgs {
+    /// Directory of the run, `<report_dir>/<run_id>`
+    #[arg(value_name = "DIR")]
+    pub run_dir: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct AccountsArgs {
+    /// Number of accounts to list, `account_count` of the configuration and the fee sponsor by default
+    #[arg(short = 'n', long)]
+    pub count: Option<u32>,
+}
+
+impl Cli {
     pub fn run(&self) {
-        let arguments = create_cli().get_matches();
-        
-        let config_file = arguments.get_one::<String>("config").unwrap_or(&String::from("config.toml"));
-        let config = match Config::load_from_file(config_file) {
+        let run_log = logging::init(self.verbose);
+        debug!(
+            "Built with features: {}",
+            crate::features::enabled().join(", ")
+        );
+
+        let config_file = self
+            .config
+            .as_ref()
+            .map(paths::expand_home)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_FILE));
+        if let Some(Commands::ValidateConfig) = &self.command {
+            if let Err(err) = self.validate_config(&config_file) {
+                error!(
+                    "Configuration {} is invalid: {}",
+                    config_file.display(),
+                    err
+                );
+                std::process::exit(1);
+            }
+            return;
+        }
+        if let Some(Commands::Config(ConfigCommand::Migrate(migrate))) = &self.command {
+            match self.migrate_config(&config_file, migrate) {
+                Ok(true) => return,
+                Ok(false) => std::process::exit(1),
+                Err(err) => {
+                    error!(
+                        "Error migrating configuration {}: {}",
+                        config_file.display(),
+                        err
+                    );
+                    std::process::exit(1);
+                }
+            }
+        }
+        let template = match &self.command {
+            Some(Commands::Run(RunArgs {
+                template: Some(name),
+                ..
+            })) => Template::find(name),
+            _ => None,
+        };
+        let loaded = match template {
+            Some(template) => template.load_config(&config_file, &self.overrides()),
+            None => Config::load_or_default(&config_file, &self.overrides()),
+        };
+        let mut config = match loaded {
             Ok(config) => config,
             Err(err) => {
-                eprintln!("Error loading configuration: {}", err);
+                error!(
+                    "Error loading configuration {}: {}",
+                    config_file.display(),
+                    err
+                );
+                std::process::exit(1);
+            }
+        };
+
+        if let Some(network) = self.network {
+            config.network.chain = network;
+        }
+
+        let default_run = RunArgs::default();
+        let run = match &self.command {
+            Some(Commands::Run(run)) => run,
+            None => &default_run,
+            Some(Commands::Report(ReportCommand::Export(export))) => {
+                if let Err(err) = self.export_report(&config, export) {
+                    error!("Error exporting report: {}", err);
+                }
                 return;
             }
+            Some(Commands::Report(ReportCommand::Verify(verify))) => {
+                if let Err(err) = self.verify_report(verify) {
+                    error!(
+                        "Run {} failed verification: {}",
+                        verify.run_dir.display(),
+                        err
+                    );
+                    std::process::exit(1);
+                }
+                return;
+            }
+            Some(Commands::Fund(fund)) => {
+                if let Err(err) = self.fund(&config, fund) {
+                    error!("Funding failed: {}", err);
+                    std::process::exit(1);
+                }
+                return;
+            }
+            Some(Commands::Accounts(accounts)) => {
+                if let Err(err) = self.list_accounts(&config, accounts) {
+                    error!("Error listing accounts: {}", err);
+                }
+                return;
+            }
+            Some(Commands::ValidateConfig) | Some(Commands::Config(_)) => return,
+        };
+
+        info!(
+            "Network {} (chain id {}), rollup server {}",
+            config.network.chain,
+            config.network.chain.chain_id(),
+            config.network.rollup_url()
+        );
+        if run.dry_run && !config.general.dry_run {
+            config.general.dry_run = true;
+            if let Err(err) = config.validate() {
+                error!("Error in configuration for a dry run: {}", err);
+                std::process::exit(1);
+            }
+        }
+        if config.general.dry_run {
+            info!("Dry run: transactions are printed instead of submitted");
+        }
+
+        // Checkpoints match accounts by address, random keys only come back from the keystore.
+        if let (Some(_), Err(err)) = (
+            &run.resume,
+            config.require_persistent_accounts("resume a run"),
+        ) {
+            error!("Error resuming run: {}", err);
+            return;
+        }
+        let checkpoint = match &run.resume {
+            Some(checkpoint_file) => {
+                match Checkpoint::load_from_file(paths::expand_home(checkpoint_file)) {
+                    Ok(checkpoint) => Some(checkpoint),
+                    Err(err) => {
+                        error!("Error loading checkpoint: {}", err);
+                        return;
+                    }
+                }
+            }
+            None => None,
+        };
+        let run_id = match &checkpoint {
+            // A resumed run keeps its id, so its reports and logs stay in one directory.
+            Some(checkpoint) => {
+                config.general.duration_secs = config
+                    .general
+                    .duration_secs
+                    .map(|duration| duration.saturating_sub(checkpoint.elapsed_secs));
+                checkpoint.run_id.clone()
+            }
+            None => config
+                .general
+                .run_id
+                .clone()
+                .unwrap_or_else(|| RunTag::generate().run_id().to_string()),
+        };
+        if config.general.log_json {
+            let log_path = config.general.report_dir.join(&run_id).join(LOG_FILE);
+            if let Err(err) = run_log.attach(&log_path) {
+                warn!(
+                    "Unable to write the JSON log to {}: {}",
+                    log_path.display(),
+                    err
+                );
+            }
+        }
+
+        let scenario = match (&run.scenario, template) {
+            (Some(scenario_file), _) => Some(ScenarioFile::load_from_file(paths::expand_home(
+                scenario_file,
+            ))),
+            (None, Some(template)) => {
+                info!("Template {}: {}", template.name, template.description());
+                Some(template.scenario())
+            }
+            (None, None) => None,
+        };
+        if let Some(scenario) = scenario {
+            let result = scenario.map_err(Into::into).and_then(|scenario| {
+                let runtime = tokio::runtime::Runtime::new()?;
+                let metrics = Arc::new(Metrics::new());
+                if config.general.dry_run {
+                    let pipeline = self.dry_run_pipeline(&config, &run_id, &runtime)?;
+                    self.start_scenario(&config, &scenario, runtime, metrics, DryRun::new(pipeline))
+                } else {
+                    let pipeline =
+                        self.rollup_pipeline(&config, &run_id, &runtime, metrics.clone())?;
+                    self.start_scenario(&config, &scenario, runtime, metrics, pipeline)
+                }
+            });
+            if let Err(err) = result {
+                error!("Scenario failed: {}", err);
+            }
+            return;
+        }
+        if let Some(payouts) = config.scenarios.merchant_payouts.clone() {
+            self.run_builtin(&config, &run_id, "Merchant payout", |runtime, pipeline| {
+                self.start_merchant_payouts(&config, payouts, runtime, pipeline)
+            });
+            return;
+        }
+        if let Some(interleaving) = config.scenarios.deposit_transfer_interleaving.clone() {
+            self.run_builtin(
+                &config,
+                &run_id,
+                "Deposit and transfer interleaving",
+                |runtime, pipeline| {
+                    self.start_interleaving(&config, interleaving, runtime, pipeline)
+                },
+            );
+            return;
+        }
+        if let Some(interference) = config.scenarios.nft_interference.clone() {
+            self.run_builtin(&config, &run_id, "NFT interference", |runtime, pipeline| {
+                self.start_nft_interference(&config, interference, runtime, pipeline)
+            });
+            return;
+        }
+
+        let baseline = match &run.baseline {
+            Some(baseline_file) => {
+                match Baseline::load_from_file(paths::expand_home(baseline_file)) {
+                    Ok(baseline) => Some(baseline),
+                    Err(err) => {
+                        error!("Error loading baseline: {}", err);
+                        return;
+                    }
+                }
+            }
+            None => None,
         };
 
         // Start the simulation based on the configuration
-        self.start_simulation(&config, &Client::new());
+        let result = tokio::runtime::Runtime::new()
+            .map_err(Into::into)
+            .and_then(|runtime| {
+                let metrics = Arc::new(Metrics::new());
+                if config.general.dry_run {
+                    let pipeline = self.dry_run_pipeline(&config, &run_id, &runtime)?;
+                    let provider = pipeline.provider().clone();
+                    self.start_simulation(
+                        &config,
+                        &run_id,
+                        runtime,
+                        metrics,
+                        provider,
+                        DryRun::new(pipeline),
+                        baseline.as_ref(),
+                        checkpoint.as_ref(),
+                    )
+                } else {
+                    let pipeline =
+                        self.rollup_pipeline(&config, &run_id, &runtime, metrics.clone())?;
+                    let provider = pipeline.provider().clone();
+                    self.start_simulation(
+                        &config,
+                        &run_id,
+                        runtime,
+                        metrics,
+                        provider,
+                        pipeline,
+                        baseline.as_ref(),
+                        checkpoint.as_ref(),
+                    )
+                }
+            });
+        if let Err(err) = result {
+            error!("Simulation failed: {}", err);
+            std::process::exit(1);
+        }
+    }
+
+    /// Overrides of the `RIF_SIM_*` environment variables followed by the `--set` flags.
+    fn overrides(&self) -> Vec<ConfigOverride> {
+        let mut overrides = ConfigOverride::from_env(std::env::vars());
+        overrides.extend(self.overrides.iter().cloned());
+        overrides
+    }
+
+    /// Loads the configuration without falling back to the defaults and checks it for mistakes parsing can not catch.
+    fn validate_config(&self, config_file: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        Config::load_with_overrides(config_file, &self.overrides())?.validate()?;
+        println!("Configuration {} is valid", config_file.display());
+        Ok(())
+    }
+
+    /// Migrates the configuration file, returning whether it is up to date afterwards.
+    fn migrate_config(
+        &self,
+        config_file: &Path,
+        args: &MigrateArgs,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut document: toml_edit::Document = std::fs::read_to_string(config_file)?.parse()?;
+        let migration = config_migration::migrate(&mut document)?;
+        for warning in &migration.warnings {
+            println!("  {}", warning);
+        }
+        if migration.is_current() {
+            println!(
+                "Configuration {} is up to date (schema version {})",
+                config_file.display(),
+                CURRENT_SCHEMA_VERSION
+            );
+            return Ok(true);
+        }
+        if args.check {
+            println!(
+                "Configuration {} needs migrating from schema version {} to {}",
+                config_file.display(),
+                migration.from_version,
+                CURRENT_SCHEMA_VERSION
+            );
+            return Ok(false);
+        }
+        let output = args.output.as_deref().unwrap_or(config_file);
+        std::fs::write(output, document.to_string())?;
+        println!(
+            "Migrated {} from schema version {} to {}, written to {}",
+            config_file.display(),
+            migration.from_version,
+            CURRENT_SCHEMA_VERSION,
+            output.display()
+        );
+        Ok(true)
+    }
+
+    fn list_accounts(
+        &self,
+        config: &Config,
+        args: &AccountsArgs,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let keys = config.require_keys("list accounts")?;
+        let count = args.count.unwrap_or(config.pool_size());
+        for (index, address) in derive_addresses(keys, count)?.iter().enumerate() {
+            println!("{:>6} {:?}", keys.first_index + index as u32, address);
+        }
+        Ok(())
+    }
+
+    /// Accounts of the run, derived from `[keys]`, else kept in the keystore, else random.
+    fn account_pool(
+        &self,
+        config: &Config,
+        count: u32,
+        insecure_plain: bool,
+        runtime: &tokio::runtime::Runtime,
+        progress: &SetupProgress,
+    ) -> Result<AccountPool, Box<dyn std::error::Error>> {
+        let generation = progress.phase(SetupPhase::AccountGeneration, count as u64);
+        let mut pool = match &config.keystore {
+            Some(keystore) if config.keys.is_none() => {
+                let keystore = KeystoreConfig {
+                    insecure_plain: keystore.insecure_plain || insecure_plain,
+                    ..keystore.clone()
+                };
+                runtime.block_on(AccountPool::load_or_generate(
+                    count,
+                    &AccountKeystore::from_config(&keystore)?,
+                    generation,
+                ))?
+            }
+            _ => runtime.block_on(AccountPool::generate(
+                count,
+                config.keys.as_ref(),
+                generation,
+            ))?,
+        };
+        // CREATE2 accounts get new addresses, which have to be known before anything is sent.
+        pool.assign_create2(&config.activation);
+        Ok(pool)
     }
 
-    fn start_simulation(&self, config: &Config, client: &Client) -> Result<(), Box<dyn std::error::Error>> {
-        // Generate transactions
-        let mut rng = rand::thread_rng();
-        let num_transactions = rng.gen_range(1..= config.general.tps);
-    
-        let throttler = Throttler::new(config.general.tps);
+    /// Pipeline sending from the run's accounts, synced with their state on the rollup; its
+    /// traffic carries the run id when `general.tag_traffic` is set.
+    fn rollup_pipeline(
+        &self,
+        config: &Config,
+        run_id: &str,
+        runtime: &tokio::runtime::Runtime,
+        metrics: Arc<Metrics>,
+    ) -> Result<RollupPipeline<RollupProvider>, Box<dyn std::error::Error>> {
+        let tag = config.general.tag_traffic.then(|| RunTag::new(run_id));
+        let mut failover = FailoverProvider::from_config(&config.network, metrics.clone());
+        if let Some(tag) = &tag {
+            failover = failover.with_run_tag(tag);
+        }
+        let provider = Arc::new(RetryProvider::new(
+            failover,
+            config.network.retry.clone(),
+            metrics,
+        ));
+        let mut pool = self.account_pool(
+            config,
+            config.pool_size(),
+            false,
+            runtime,
+            &SetupProgress::new(),
+        )?;
+        runtime.block_on(pool.sync(provider.as_ref()))?;
+        if let Some(tag) = &tag {
+            pool = pool.with_run_tag(tag.clone());
+        }
+        let mut pipeline = runtime.block_on(RollupPipeline::new(
+            provider,
+            pool,
+            &config.network,
+            &config.transaction,
+        ))?;
+        if let Some(tag) = tag {
+            pipeline = pipeline.with_run_tag(tag);
+        }
+        if let Some(sponsor) = &config.sponsor {
+            pipeline = pipeline.with_sponsor(sponsor.clone());
+        }
+        Ok(pipeline.with_l1(L1Node::connect(&config.network)?))
+    }
 
-        for _ in 0..num_transactions {
-            // Submit transaction
-            
-            // Throttle between transactions
-            if config.general.enable_throttling {
-                throttler.throttle();
+    /// Pipeline of dry runs, against the in-process rollup of [`mock_rollup`] from accounts
+    /// assumed funded, so neither the pool nor the pipeline asks a real server anything.
+    /// Deposits are not generated, they would need the Rootstock node.
+    fn dry_run_pipeline(
+        &self,
+        config: &Config,
+        run_id: &str,
+        runtime: &tokio::runtime::Runtime,
+    ) -> Result<RollupPipeline<MockProvider>, Box<dyn std::error::Error>> {
+        let tag = config.general.tag_traffic.then(|| RunTag::new(run_id));
+        let mut pool = self.account_pool(
+            config,
+            config.pool_size(),
+            false,
+            runtime,
+            &SetupProgress::new(),
+        )?;
+        pool.assume_funded(&config.funding);
+        if let Some(tag) = &tag {
+            pool = pool.with_run_tag(tag.clone());
+        }
+        let mut pipeline = runtime.block_on(RollupPipeline::new(
+            Arc::new(mock_rollup(&config.funding)),
+            pool,
+            &config.network,
+            &config.transaction,
+        ))?;
+        if let Some(tag) = tag {
+            pipeline = pipeline.with_run_tag(tag);
+        }
+        if let Some(sponsor) = &config.sponsor {
+            pipeline = pipeline.with_sponsor(sponsor.clone());
+        }
+        Ok(pipeline)
+    }
+
+    fn fund(&self, config: &Config, args: &FundArgs) -> Result<(), Box<dyn std::error::Error>> {
+        config.require_persistent_accounts("fund accounts")?;
+        let mut funding = config.funding.clone();
+        if let Some(master_key) = &args.master_key {
+            funding.master_key = Some(master_key.clone());
+        }
+        let master = funding
+            .master_wallet()?
+            .ok_or("a master wallet is required, set funding.master_key or pass --master-key")?;
+        info!("Funding from master wallet {:?}", master.address());
+        let count = args.accounts.unwrap_or(config.pool_size());
+        let runtime = tokio::runtime::Runtime::new()?;
+        let progress = SetupProgress::new();
+        let mut pool =
+            self.account_pool(config, count, args.insecure_plain, &runtime, &progress)?;
+        let provider = Arc::new(rollup_provider(config, Arc::new(Metrics::new())));
+        let tokens = runtime.block_on(TokenRegistry::fetch(provider.as_ref()))?;
+        let fee_token = tokens
+            .get(&TokenLike::Id(TokenId(0)))
+            .cloned()
+            .ok_or("the rollup lists no native token to pay the activation fees in")?;
+        let l1 = L1Node::connect(&config.network)?;
+        // Transfers of an interrupted earlier funding would hold back the new ones.
+        let replaced = runtime.block_on(l1.nonce_manager(&master).replace_stuck())?;
+        if replaced > 0 {
+            warn!(
+                "Replaced {} stuck L1 transactions of the master wallet",
+                replaced
+            );
+        }
+        let mut steps = L1FundingSteps::new(provider.clone(), l1.clone(), &master, tokens);
+        for address in pool.addresses() {
+            if let Some(account) = pool.get(&address) {
+                steps = steps.with_depositor(address, account.wallet.eth_signer().clone());
             }
         }
-    
+        let reverts = steps.reverts();
+        let orchestrator = FundingOrchestrator::new(steps, &funding)
+            .with_progress(progress.phase(SetupPhase::Funding, count as u64));
+        let summary = runtime.block_on(pool.fund(&orchestrator, &funding))?;
+        for (address, err) in &summary.failed {
+            warn!("Unable to fund {:?}: {}", address, err);
+        }
+        let mut units = AmountFormat::new();
+        units.set_raw(config.general.raw_amounts);
+        for erc20 in &funding.erc20 {
+            units.add_token(&erc20.token, erc20.decimals);
+        }
+        print!(
+            "{}",
+            FundingReport::new(master.address(), &summary, &funding.assets()).render_table(&units)
+        );
+        println!("Funded {} of {} accounts", summary.funded.len(), count);
+        let reverts = reverts.lock().unwrap().clone();
+        if reverts.total() > 0 {
+            println!(
+                "{} deposits reverted: {:?}",
+                reverts.total(),
+                reverts.counts
+            );
+        }
+
+        // The funded accounts get their rollup ids from the sync, activation needs them.
+        runtime.block_on(pool.sync(provider.as_ref()))?;
+        let authorizer = L1Authorizer::new(provider.as_ref(), l1.provider());
+        let activator = AccountActivator::new(config.activation.clone())
+            .with_progress(progress.phase(SetupPhase::Activation, count as u64))
+            .with_onchain_authorizer(&authorizer);
+        let mut activation =
+            runtime.block_on(pool.activate(provider.as_ref(), &activator, &fee_token));
+        for (address, err) in &activation.failed {
+            warn!("Unable to activate {:?}: {}", address, err);
+        }
+        for address in &activation.unverified {
+            warn!("The signing key of {:?} was not committed in time", address);
+        }
+        println!(
+            "Activated {} accounts in {:.1}s, {} batches of which {} rejected",
+            activation.activated().count(),
+            activation.elapsed.as_secs_f64(),
+            activation.batches,
+            activation.rejected_batches
+        );
+        units.add_token(&fee_token.symbol, fee_token.decimals);
+        activation.change_pubkey.set_fee_token(&fee_token.symbol);
+        print!("{}", activation.change_pubkey.render_table(&units));
         Ok(())
     }
-    
-    
+
+    fn export_report(
+        &self,
+        config: &Config,
+        export: &ExportArgs,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if !export.live {
+            return Err("only live exports (--live) of a running simulation are supported".into());
+        }
+        let address = export
+            .address
+            .unwrap_or_else(|| config.control.clone().unwrap_or_default().bind_address);
+
+        let runtime = tokio::runtime::Runtime::new()?;
+        let mut export_json = runtime.block_on(fetch_live_export(address))?;
+        if export.redact {
+            export_json = redact_export(&export_json)?;
+        }
+        match &export.output {
+            Some(output) => paths::write_file(paths::expand_home(output), export_json)?,
+            None => println!("{}", export_json),
+        }
+        Ok(())
+    }
+
+    fn verify_report(&self, args: &VerifyArgs) -> Result<(), ManifestError> {
+        let run_dir = paths::expand_home(&args.run_dir);
+        let manifest = RunManifest::load(&run_dir)?;
+        if let Err(err) = manifest.verify(&run_dir) {
+            if let ManifestError::Tampered(mismatches) = &err {
+                for mismatch in mismatches {
+                    warn!("Artifact {}", mismatch);
+                }
+            }
+            return Err(err);
+        }
+        println!(
+            "All {} artifacts of run {} match its manifest",
+            manifest.artifacts.len(),
+            manifest.run_id
+        );
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn start_simulation<R, P>(
+        &self,
+        config: &Config,
+        run_id: &str,
+        runtime: tokio::runtime::Runtime,
+        metrics: Arc<Metrics>,
+        provider: Arc<R>,
+        pipeline: P,
+        baseline: Option<&Baseline>,
+        resume: Option<&Checkpoint>,
+    ) -> Result<(), Box<dyn std::error::Error>>
+    where
+        R: Provider + Reconnectable + Send + Sync + 'static,
+        P: TxPipeline,
+    {
+        let recorder = Arc::new(RunRecorder::new());
+        recorder.with_amount_format(|units| units.set_raw(config.general.raw_amounts));
+        if let Some(window_secs) = config.general.latency_timeline_secs {
+            recorder.with_stage_latency(|latency| {
+                latency.start_timeline(Instant::now(), Duration::from_secs(window_secs))
+            });
+        }
+        let shutdown = Shutdown::new();
+        runtime.spawn(shutdown.clone().listen());
+        let l1 = L1Node::connect(&config.network)?;
+        let streams = match resume.and_then(|checkpoint| checkpoint.rng.as_ref()) {
+            Some(rng) => RngStreams::resume(&config.rng, rng),
+            None => RngStreams::new(&config.rng),
+        };
+        let tracker = ConfirmationTracker::from_config(
+            provider.clone(),
+            config.network.confirmation.clone(),
+            recorder.clone(),
+            metrics.clone(),
+        )?
+        .with_l1_receipts(Arc::new(l1.provider().clone()));
+        let events = match &config.network.confirmation.events_url {
+            Some(url) => {
+                match runtime.block_on(EventListener::connect(url, &config.network.tls_pins)) {
+                    Ok(listener) => Some(Arc::new(listener)),
+                    Err(err) => {
+                        warn!(
+                            "Unable to follow confirmations through {}, polling instead: {}",
+                            url, err
+                        );
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+        let tracker = match &events {
+            Some(listener) => tracker.with_events(listener.clone()),
+            None => tracker,
+        };
+        let audit = Arc::new(ConfirmationAudit::new());
+        let engine = Engine::new(pipeline, &config.general, recorder.clone(), metrics.clone())
+            .with_shutdown(shutdown)
+            .with_confirmation(config.network.confirmation.clone())
+            .with_account_gc(config.account_gc.clone());
+        let journeys = (!config.journeys.is_empty())
+            .then(|| Arc::new(JourneyObserver::new(recorder.clone(), &config.journeys)));
+        let tracker = match &journeys {
+            Some(journeys) => tracker.with_listener(journeys.clone()),
+            None => tracker,
+        };
+        let tracker = Arc::new(
+            tracker
+                .with_audit(audit.clone())
+                .with_listener(engine.shared_pipeline()),
+        );
+        let mut engine = engine.with_observer(tracker.clone());
+        if let Some(journeys) = journeys {
+            engine = engine.with_observer(journeys);
+        }
+        let stored = tracker.resume_stored();
+        if stored > 0 {
+            info!(
+                "Resuming the confirmation of {} operations left pending by the previous run",
+                stored
+            );
+        }
+        if let Some(audit_log) = &config.general.audit_log {
+            engine = engine.with_observer(Arc::new(Mutex::new(AuditLog::open(
+                paths::expand_home(audit_log),
+            )?)));
+        }
+        if let Some(capture_dir) = &config.general.capture_dir {
+            engine = engine.with_observer(Arc::new(Mutex::new(CaptureWriter::create(
+                paths::expand_home(capture_dir),
+            )?)));
+        }
+        if let Some(resubmission) = config.resubmission.clone() {
+            engine = engine.with_resubmission(ResubmissionStudy::new(
+                resubmission,
+                streams.stream(RngStream::Sampling),
+            ));
+        }
+        if let Some(misbehavior) = config.misbehavior.clone() {
+            engine = engine.with_misbehavior(MisbehaviorInjector::new(
+                misbehavior,
+                streams.stream(RngStream::Misbehavior),
+            ));
+        }
+        engine.pipeline().seed(&streams);
+        if let Some(tokens) = engine.pipeline().tokens() {
+            recorder.with_amount_format(|units| units.add_registry(&tokens));
+        }
+        if let Some(checkpoint) = resume {
+            engine.pipeline().restore(&checkpoint.state)?;
+            // The store holds the same operations, with their original submission times.
+            if config.network.confirmation.store_file.is_none() {
+                tracker.restore(&checkpoint.state.pending);
+            }
+            info!(
+                "Resuming run {} after {}s with {} accounts and {} unconfirmed operations",
+                checkpoint.run_id,
+                checkpoint.elapsed_secs,
+                checkpoint.state.accounts.len(),
+                checkpoint.state.pending.len()
+            );
+        }
+        // Written before sending starts so the accounts are recognizable on the explorer during the run.
+        let mut labels_path = None;
+        if config.general.explorer_labels {
+            match engine.pipeline().checkpoint() {
+                Some(state) => {
+                    let addresses: Vec<_> = state
+                        .accounts
+                        .iter()
+                        .map(|account| account.address)
+                        .collect();
+                    let labels = ExplorerLabels::new(run_id, &addresses);
+                    let path = config
+                        .general
+                        .report_dir
+                        .join(run_id)
+                        .join(EXPLORER_LABELS_FILE);
+                    paths::write_file(&path, labels.to_json()?)?;
+                    println!(
+                        "Labels of {} accounts written to {}",
+                        labels.len(),
+                        path.display()
+                    );
+                    labels_path = Some(path);
+                }
+                None => warn!(
+                    "Explorer labels are not written, the pipeline does not list its accounts"
+                ),
+            }
+        }
+        // Balances held when sending starts count as funded, resumed runs start from the checkpoint.
+        if let Some(state) = engine.pipeline().checkpoint() {
+            recorder.with_balances(|balances| {
+                state
+                    .token_totals()
+                    .iter()
+                    .for_each(|(token, total)| balances.record_funded(token, total))
+            });
+        }
+        let health = Health::new();
+        health.set_config_loaded();
+        if let Some(health_config) = config.health.clone() {
+            let interval = Duration::from_secs(health_config.probe_interval_secs);
+            runtime.spawn(health::watch_provider(
+                FailoverProvider::from_config(&config.network, metrics.clone()),
+                health.clone(),
+                interval,
+            ));
+            let health = health.clone();
+            runtime.spawn(async move {
+                if let Err(err) = health::serve(&health_config, health).await {
+                    error!("Health endpoint failed: {}", err);
+                }
+            });
+        }
+        let queue_depths = Arc::new(QueueDepthHistory::new());
+        let pause = PauseControl::new();
+        self.spawn_control(
+            config,
+            &runtime,
+            ControlState {
+                recorder: recorder.clone(),
+                metrics: metrics.clone(),
+                queue_depths: queue_depths.clone(),
+                pause: pause.clone(),
+            },
+        );
+        if let Some(metrics_config) = config.metrics.clone() {
+            let metrics = metrics.clone();
+            runtime.spawn(async move {
+                if let Err(err) = prometheus::serve(&metrics_config, metrics, Instant::now()).await
+                {
+                    error!("Metrics endpoint failed: {}", err);
+                }
+            });
+        }
+
+        health.set_run(RunStatus::Running);
+        let workers = Arc::new(Workers::new());
+        let handle = runtime.handle().clone();
+        workers.spawn(TRACKER_WORKER, {
+            let (handle, tracker) = (handle.clone(), tracker.clone());
+            move || {
+                let tracker = tracker.clone();
+                handle.spawn(async move { tracker.run().await })
+            }
+        });
+        workers.spawn("queue_depth_sampler", {
+            let (queue_depths, metrics) = (queue_depths.clone(), metrics.clone());
+            move || {
+                handle.spawn(
+                    queue_depths
+                        .clone()
+                        .run_sampler(metrics.clone(), SAMPLE_INTERVAL),
+                )
+            }
+        });
+        let supervisor = runtime.spawn(workers.clone().supervise());
+        let gas_price_watch = engine.pipeline().fee_cache().map(|fees| {
+            runtime.spawn(fees.watch_gas_price(l1.provider().clone(), metrics.clone()))
+        });
+        let chaos = config.chaos.clone().map(|chaos| {
+            let mut monkey = ChaosMonkey::from_streams(chaos, &streams);
+            let (workers, recorder) = (workers.clone(), recorder.clone());
+            runtime.spawn(async move {
+                loop {
+                    tokio::time::sleep(monkey.interval()).await;
+                    let record = monkey.inject(workers.as_ref()).await;
+                    match &record.failure {
+                        Some(failure) => warn!(
+                            "Simulator did not recover from {:?} in time: {}",
+                            record.fault, failure
+                        ),
+                        None => info!(
+                            "Simulator recovered from {:?} in {:.0}ms",
+                            record.fault,
+                            record.recovery_ms.unwrap_or_default()
+                        ),
+                    }
+                    recorder.record_fault(record);
+                }
+            })
+        });
+        let storms = Arc::new(Mutex::new(Vec::new()));
+        let reconnect_storm = config.scenarios.reconnect_storm.clone().map(|storm_config| {
+            let mut connections: Vec<Arc<dyn Reconnectable>> = vec![provider.clone()];
+            if let Some(listener) = &events {
+                connections.push(listener.clone());
+            }
+            let storm = ReconnectStorm::new(storm_config, connections);
+            let (tracker, provider, storms) = (tracker.clone(), provider.clone(), storms.clone());
+            runtime.spawn(async move {
+                loop {
+                    tokio::time::sleep(storm.interval()).await;
+                    let in_flight = tracker.pending_tx_hashes();
+                    audit.restart(in_flight.iter().copied());
+                    let report = storm.run(&audit, || confirmed_on_server(provider.as_ref(), in_flight)).await;
+                    for outcome in report.connections.iter().filter(|outcome| outcome.error.is_some()) {
+                        warn!("Reconnect storm: {} did not reconnect: {}", outcome.connection, outcome.error.as_deref().unwrap_or_default());
+                    }
+                    info!(
+                        "Reconnect storm: {} connections back in {:.0}ms, {} of {} in-flight confirmations missed",
+                        report.connections.len(),
+                        report.total_ms,
+                        report.missed_confirmations.len(),
+                        report.in_flight
+                    );
+                    storms.lock().unwrap().push(report);
+                }
+            })
+        });
+        // The traffic keeps running while the operator restarts the rollup node.
+        let cold_start = config
+            .scenarios
+            .cold_start
+            .clone()
+            .map(|cold_start_config| {
+                let recorder = recorder.clone();
+                runtime.spawn(async move {
+                    let traffic = RunningTraffic::new(recorder.clone());
+                    ColdStart::new(cold_start_config)
+                        .run(&pause, true, &traffic, || recorder.records())
+                        .await
+                })
+            });
+        let summary = runtime.block_on(engine.run());
+        match cold_start {
+            Some(cold_start) if cold_start.is_finished() => match runtime.block_on(cold_start) {
+                Ok(Ok(report)) => println!(
+                    "Rollup accepted transactions {:.0}ms after the restart, backlog peaked at {} and drained at {:.1} tx/s, latency recovered {}",
+                    report.time_to_first_tx_ms,
+                    report.peak_backlog,
+                    report.drain_rate_per_sec,
+                    report.recovered_after_ms.map_or("never".to_string(), |ms| format!("after {}s", ms / 1000))
+                ),
+                Ok(Err(err)) => warn!("Cold start scenario failed: {}", err),
+                Err(err) => warn!("Cold start scenario failed: {}", err),
+            },
+            Some(cold_start) => {
+                cold_start.abort();
+                warn!("Run ended before the cold start scenario completed");
+            }
+            None => {}
+        }
+        if let Some(chaos) = chaos {
+            chaos.abort();
+        }
+        if let Some(reconnect_storm) = reconnect_storm {
+            reconnect_storm.abort();
+            let storms = storms.lock().unwrap();
+            println!(
+                "{} reconnect storms, {} failed reconnects, {} missed confirmations",
+                storms.len(),
+                storms
+                    .iter()
+                    .map(StormReport::failed_reconnects)
+                    .sum::<usize>(),
+                storms
+                    .iter()
+                    .map(|storm| storm.missed_confirmations.len())
+                    .sum::<usize>()
+            );
+        }
+        supervisor.abort();
+        workers.abort_all();
+        if let Some(gas_price_watch) = gas_price_watch {
+            gas_price_watch.abort();
+        }
+        println!(
+            "Submitted {} transactions, {} failed, in {:.1}s ({:.1} TPS)",
+            summary.submitted,
+            summary.failed,
+            summary.elapsed.as_secs_f64(),
+            summary.achieved_tps()
+        );
+        if let Some(rate) = &summary.adaptive_rate {
+            println!(
+                "Adaptive rate backed off {} times and ramped up {} times, lowest at {:.0}% of the profile rate, ending at {:.0}%",
+                rate.decreases,
+                rate.increases,
+                rate.lowest_factor * 100.0,
+                rate.final_factor * 100.0
+            );
+        }
+        if config.general.load_mode == LoadMode::Closed {
+            println!(
+                "{} virtual users confirmed {} transactions ({:.1} TPS)",
+                config.general.virtual_users,
+                summary.confirmed,
+                summary.confirmed_tps()
+            );
+        }
+
+        if summary.interrupted {
+            health.set_run(RunStatus::Settling);
+            let grace = Duration::from_secs(config.general.shutdown_grace_secs);
+            runtime.block_on(engine.pipeline().settle(grace));
+            // Operations still pending after the grace period are carried over in the checkpoint.
+            let _ = runtime.block_on(tokio::time::timeout(grace, tracker.run_until_settled()));
+        } else if tracker.pending() > 0 {
+            health.set_run(RunStatus::Settling);
+            info!(
+                "Waiting for {} operations to be verified",
+                tracker.pending()
+            );
+            runtime.block_on(tracker.run_until_settled());
+        }
+        health.set_run(RunStatus::Finished);
+
+        if let Some(state) = engine.pipeline().checkpoint() {
+            recorder.with_balances(|balances| {
+                state
+                    .token_totals()
+                    .into_iter()
+                    .for_each(|(token, total)| balances.set_ending(&token, total))
+            });
+        }
+
+        let submitted = recorder.with_history(|history| history.submitted().clone());
+        if !submitted.is_empty() {
+            match runtime.block_on(check_accounts(provider.as_ref(), &submitted)) {
+                Ok(check) => recorder.with_history(|history| history.set_check(check)),
+                Err(err) => warn!(
+                    "Unable to check the submitted transactions against the account histories: {}",
+                    err
+                ),
+            }
+        }
+
+        let samples = queue_depths.samples();
+        let snapshot = recorder.snapshot(&metrics, samples.clone());
+        if !snapshot.stage_latency.is_empty() {
+            print!(
+                "{}",
+                recorder.with_stage_latency(|latency| latency.render_table())
+            );
+        }
+        if !snapshot.journeys.is_empty() {
+            print!(
+                "{}",
+                recorder.with_journeys(|journeys| journeys.render_table())
+            );
+        }
+        if !snapshot.balance_utilization.is_empty() {
+            let units = recorder.with_amount_format(|units| units.clone());
+            print!(
+                "{}",
+                recorder.with_balances(|balances| balances.render_table(&units))
+            );
+        }
+        if !snapshot.batch_fees.is_empty() {
+            let units = recorder.with_amount_format(|units| units.clone());
+            print!(
+                "{}",
+                recorder.with_batch_fees(|batch_fees| batch_fees.render_table(&units))
+            );
+        }
+        if !snapshot.bursts.is_empty() {
+            print!("{}", recorder.with_bursts(|bursts| bursts.render_table()));
+        }
+        if !snapshot.nfts.is_empty() {
+            print!("{}", recorder.with_nfts(|nfts| nfts.render_table()));
+        }
+        if !snapshot.resubmissions.is_empty() {
+            print!(
+                "{}",
+                recorder.with_resubmissions(|resubmissions| resubmissions.render_table())
+            );
+        }
+        if !snapshot.faults.is_empty() {
+            let recovered = snapshot
+                .faults
+                .iter()
+                .filter(|fault| fault.recovered())
+                .count();
+            println!(
+                "Simulator recovered from {} of {} injected faults",
+                recovered,
+                snapshot.faults.len()
+            );
+        }
+        if !snapshot.misbehaviors.is_empty() {
+            print!(
+                "{}",
+                recorder.with_misbehaviors(|misbehaviors| misbehaviors.render_table())
+            );
+            if snapshot.misbehaviors.gaps > 0 {
+                warn!(
+                    "The rollup accepted {} transactions of misbehaving wallets",
+                    snapshot.misbehaviors.gaps
+                );
+            }
+        }
+        if !snapshot.withdrawals.is_empty() {
+            print!(
+                "{}",
+                recorder.with_withdrawals(|withdrawals| withdrawals.render_table())
+            );
+        }
+        if let Some(check) = &snapshot.history_check {
+            print!("{}", check.render_table());
+            if !check.is_consistent() {
+                warn!(
+                    "{} submitted transactions are missing from or repeated in the account histories",
+                    check.missing.len() + check.duplicated.len()
+                );
+            }
+        }
+        if !snapshot.duplicate_hashes.is_empty() {
+            print!(
+                "{}",
+                recorder.with_duplicate_hashes(|duplicates| duplicates.render_table())
+            );
+        }
+        if !snapshot.confidence.is_empty() {
+            print!("{}", significance::render_table(&snapshot.confidence));
+        }
+        let notification = config.notify.as_ref().map(|notify| {
+            let outcome = if summary.interrupted {
+                RunOutcome::Aborted("shutdown requested".to_string())
+            } else {
+                RunOutcome::Completed
+            };
+            let run_summary = RunSummary::new(
+                run_id,
+                outcome,
+                summary.elapsed,
+                &snapshot,
+                notify.artifacts_url.clone(),
+            );
+            (notify, run_summary)
+        });
+        let actual = Baseline::from_run(summary.achieved_tps(), &snapshot)
+            .with_config(config.snapshot.clone());
+        if config.general.generate_reports {
+            let report = RunReport::new(run_id, snapshot);
+            let mut artifacts = report.write(&config.general.report_dir)?;
+            for path in &artifacts {
+                println!("Report written to {}", path.display());
+            }
+            // Metrics of this run in the format accepted by `--baseline`, ready to be committed.
+            let baseline_path = config.general.report_dir.join(run_id).join("baseline.json");
+            paths::write_file(&baseline_path, serde_json::to_string_pretty(&actual)?)?;
+            println!("Baseline written to {}", baseline_path.display());
+            artifacts.push(baseline_path);
+            let run_dir = config.general.report_dir.join(run_id);
+            if config.general.stage_profile {
+                let profile_path = run_dir.join(STAGE_PROFILE_FILE);
+                paths::write_file(&profile_path, metrics.profile.folded())?;
+                println!(
+                    "Pipeline stage profile written to {}",
+                    profile_path.display()
+                );
+                artifacts.push(profile_path);
+            }
+            let mut html = HtmlReport::new(&format!("Run {}", run_id));
+            html.add_section("Pipeline queue depths", queue_depth_chart(&samples));
+            let html_path = run_dir.join(HTML_REPORT_FILE);
+            html.write(&html_path)?;
+            println!("HTML report written to {}", html_path.display());
+            artifacts.push(html_path);
+            artifacts.extend(labels_path);
+            let manifest_path =
+                RunManifest::build(run_id, &run_dir, &artifacts)?.write(&run_dir)?;
+            println!("Artifact hashes written to {}", manifest_path.display());
+        }
+        // Posted before the checkpoint of an interrupted run, so aborted runs notify as well.
+        if let Some((notify, run_summary)) = notification {
+            match runtime.block_on(post_summary(notify, &run_summary, &config.network.tls_pins)) {
+                Ok(()) => info!("Run summary posted to the {:?} webhook", notify.kind),
+                Err(err) => warn!("Unable to post the run summary: {}", err),
+            }
+        }
+
+        if summary.interrupted {
+            match engine.pipeline().checkpoint() {
+                Some(mut state) => {
+                    state.pending.extend(tracker.checkpoint());
+                    let (elapsed_secs, submitted, failed) = resume
+                        .map(|previous| (previous.elapsed_secs, previous.submitted, previous.failed))
+                        .unwrap_or_default();
+                    let checkpoint = Checkpoint::new(
+                        run_id,
+                        elapsed_secs + summary.elapsed.as_secs(),
+                        submitted + summary.submitted,
+                        failed + summary.failed,
+                        state,
+                    )
+                    .with_rng(streams.checkpoint());
+                    let checkpoint_path = config.general.report_dir.join(run_id).join(CHECKPOINT_FILE);
+                    checkpoint.write(&checkpoint_path)?;
+                    println!("Checkpoint written to {}, continue with --resume", checkpoint_path.display());
+                }
+                None => warn!("The transaction pipeline does not support checkpoints, the run can not be resumed"),
+            }
+            // Metrics of a partial run say nothing about a regression.
+            return Ok(());
+        }
+
+        if let Some(baseline) = baseline {
+            // Settings changed since the baseline come first, so metric differences can be told apart from drift.
+            match (&baseline.config, &actual.config) {
+                (Some(before), Some(after)) => print!("Configuration changes since the baseline:\n{}", config_diff::render(&config_diff::diff(before, after))),
+                _ => println!("Configuration changes since the baseline: unknown, the settings of one of the runs were not recorded"),
+            }
+            let differences = significance::compare(&baseline.intervals, &actual.intervals);
+            if !differences.is_empty() {
+                println!(
+                    "Differences to the baseline ({:.0}% confidence intervals):",
+                    significance::CONFIDENCE * 100.0
+                );
+                for difference in &differences {
+                    println!("  {}", difference);
+                }
+            }
+            if let Err(err) = baseline.check(&actual, &config.baseline) {
+                if let BaselineError::Regressed(regressions) = &err {
+                    for regression in regressions {
+                        warn!("Regression {}", regression);
+                    }
+                }
+                return Err(err.into());
+            }
+            println!("All metrics are within the tolerances of the baseline");
+        }
+        Ok(())
+    }
+
+    /// Serves the control API in the background when `[control]` is configured.
+    fn spawn_control(
+        &self,
+        config: &Config,
+        runtime: &tokio::runtime::Runtime,
+        state: ControlState,
+    ) {
+        if let Some(control_config) = config.control.clone() {
+            runtime.spawn(async move {
+                if let Err(err) = control::serve(&control_config, state).await {
+                    error!("Control API failed: {}", err);
+                }
+            });
+        }
+    }
+
+    fn start_scenario<P: TxPipeline + BlockProgress>(
+        &self,
+        config: &Config,
+        scenario: &ScenarioFile,
+        runtime: tokio::runtime::Runtime,
+        metrics: Arc<Metrics>,
+        client: P,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let client = Arc::new(client);
+        let recorder = Arc::new(RunRecorder::new());
+        let pause = PauseControl::new();
+        self.spawn_control(
+            config,
+            &runtime,
+            ControlState {
+                recorder: recorder.clone(),
+                metrics: metrics.clone(),
+                queue_depths: Arc::new(QueueDepthHistory::new()),
+                pause: pause.clone(),
+            },
+        );
+        let mut executor = EngineExecutor::new(client.clone(), &config.general, recorder, metrics);
+        let runner = ScenarioRunner::new(pause, client.as_ref());
+
+        runtime.block_on(runner.run(scenario, &mut executor))?;
+        Ok(())
+    }
+
+    /// Runs a built-in scenario from the run's accounts instead of the random workload.
+    fn run_builtin<F>(&self, config: &Config, run_id: &str, name: &str, scenario: F)
+    where
+        F: FnOnce(
+            &tokio::runtime::Runtime,
+            RollupPipeline<RollupProvider>,
+        ) -> Result<(), Box<dyn std::error::Error>>,
+    {
+        let result = tokio::runtime::Runtime::new()
+            .map_err(Into::into)
+            .and_then(|runtime| {
+                let pipeline =
+                    self.rollup_pipeline(config, run_id, &runtime, Arc::new(Metrics::new()))?;
+                scenario(&runtime, pipeline)
+            });
+        if let Err(err) = result {
+            error!("{} scenario failed: {}", name, err);
+        }
+    }
+
+    fn start_merchant_payouts(
+        &self,
+        config: &Config,
+        payouts: MerchantPayoutsConfig,
+        runtime: &tokio::runtime::Runtime,
+        pipeline: RollupPipeline<RollupProvider>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let required = payouts.payers + payouts.merchants;
+        if (config.general.account_count as usize) < required {
+            return Err(format!(
+                "{} payers and {} merchants need {} accounts, general.account_count is {}",
+                payouts.payers, payouts.merchants, required, config.general.account_count
+            )
+            .into());
+        }
+        let shutdown = Shutdown::new();
+        runtime.spawn(shutdown.clone().listen());
+        let mut rng = RngStreams::new(&config.rng).stream(RngStream::Amounts);
+        let duration = config
+            .general
+            .duration_secs
+            .map_or(Duration::MAX, Duration::from_secs);
+        let accounts = MerchantPool::new(&pipeline, payouts.payers);
+        let mut scenario = MerchantPayoutScenario::new(payouts);
+        let report = runtime.block_on(scenario.run(
+            &accounts,
+            &mut rng,
+            config.general.tps,
+            duration,
+            &shutdown,
+        ));
+        println!(
+            "Sent {} payments ({} failed, median {:.0}ms) and {} payout batches ({} failed, median {:.0}ms) withdrawing {} {}",
+            report.payments,
+            report.failed_payments,
+            report.payment_response.p50,
+            report.payouts,
+            report.failed_payouts,
+            report.payout_response.p50,
+            report.paid_out,
+            pipeline.scenario_token().symbol
+        );
+        Ok(())
+    }
+
+    fn start_interleaving(
+        &self,
+        config: &Config,
+        interleaving: InterleavingConfig,
+        runtime: &tokio::runtime::Runtime,
+        pipeline: RollupPipeline<RollupProvider>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if (config.general.account_count as usize) < interleaving.accounts.max(2) {
+            return Err(format!(
+                "{} accounts are needed, general.account_count is {}",
+                interleaving.accounts.max(2),
+                config.general.account_count
+            )
+            .into());
+        }
+        let rounds = interleaving.rounds;
+        let mut scenario = InterleavingScenario::new(interleaving);
+        let anomalies = runtime.block_on(scenario.run(&InterleavingPool::new(&pipeline)))?;
+        for anomaly in &anomalies {
+            warn!("Anomaly in {}", anomaly);
+        }
+        println!(
+            "Interleaved {} deposits and transfers, {} anomalies",
+            rounds,
+            anomalies.len()
+        );
+        Ok(())
+    }
+
+    fn start_nft_interference(
+        &self,
+        config: &Config,
+        interference: NftInterferenceConfig,
+        runtime: &tokio::runtime::Runtime,
+        pipeline: RollupPipeline<RollupProvider>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let recorder = Arc::new(RunRecorder::new());
+        let mut workload = MixedPool::new(
+            Arc::new(pipeline),
+            &config.general,
+            recorder,
+            Arc::new(Metrics::new()),
+        );
+        let report = runtime.block_on(NftInterference::new(interference).run(&mut workload))?;
+        print!("{}", nft_interference::render_table(&report));
+        Ok(())
+    }
+}
+
+/// Rollup API of real runs, rotating over the configured servers and retrying transient errors.
+type RollupProvider = RetryProvider<FailoverProvider<HttpProvider>>;
+
+/// Transactions of `tx_hashes` the server reports as committed.
+async fn confirmed_on_server<R: Provider + Sync>(
+    provider: &R,
+    tx_hashes: Vec<TxHash>,
+) -> Vec<TxHash> {
+    let mut confirmed = Vec::new();
+    for tx_hash in tx_hashes {
+        match provider.tx_info(tx_hash).await {
+            Ok(info)
+                if info.executed && info.block.as_ref().is_some_and(|block| block.committed) =>
+            {
+                confirmed.push(tx_hash)
+            }
+            Ok(_) => {}
+            Err(err) => warn!("Unable to check the confirmation of {}: {}", tx_hash, err),
+        }
+    }
+    confirmed
+}
+
+fn rollup_provider(config: &Config, metrics: Arc<Metrics>) -> RollupProvider {
+    RetryProvider::new(
+        FailoverProvider::from_config(&config.network, metrics.clone()),
+        config.network.retry.clone(),
+        metrics,
+    )
 }
@@ -0,0 +1,76 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+
+/// Source of time for components pacing the simulation.
+///
+/// Production code runs on [`SystemClock`], tests on [`SimulatedClock`] whose
+/// time only moves when something sleeps on it or the test advances it.
+#[async_trait]
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+
+    /// Suspends the calling task for the given duration.
+    async fn sleep(&self, duration: Duration);
+
+    fn elapsed_since(&self, earlier: Instant) -> Duration {
+        self.now().saturating_duration_since(earlier)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+#[async_trait]
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+}
+
+/// Virtual clock advancing instantly on sleep, clones share the same time.
+#[derive(Debug, Clone)]
+pub struct SimulatedClock {
+    start: Instant,
+    offset: Arc<Mutex<Duration>>,
+}
+
+impl SimulatedClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            offset: Arc::new(Mutex::new(Duration::ZERO)),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.offset.lock().unwrap() += duration;
+    }
+
+    /// Total virtual time passed since the clock was created.
+    pub fn elapsed(&self) -> Duration {
+        *self.offset.lock().unwrap()
+    }
+}
+
+impl Default for SimulatedClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Clock for SimulatedClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    async fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
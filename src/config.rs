@@ -1,10 +1,165 @@
-use serde::Deserialize;
+use ethers::types::Address;
+use serde::{Deserialize, Deserializer};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+use toml_edit::{table, value, InlineTable, Value};
+use tracing::warn;
+
+use crate::activation::ActivationConfig;
+use crate::chaos::ChaosConfig;
+use crate::config_migration::{self, MigrationError, CURRENT_SCHEMA_VERSION};
+use crate::control::ControlConfig;
+use crate::engine::LoadMode;
+use crate::features::Feature;
+use crate::funding::FundingConfig;
+use crate::health::HealthConfig;
+use crate::l1::ethop_poll::EthOpPollConfig;
+use crate::l1::nonce::L1NonceConfig;
+use crate::load_profile::{BurstConfig, LoadShape};
+use crate::metrics::prometheus::PrometheusConfig;
+use crate::misbehavior::MisbehaviorConfig;
+use crate::rate_control::AdaptiveRateConfig;
+use crate::report::baseline::BaselineTolerances;
+use crate::report::journey::JourneyTemplate;
+use crate::report::notify::NotifyConfig;
+use crate::report::TxStatus;
+use crate::resubmission::ResubmissionConfig;
+use crate::rng::RngConfig;
+use crate::rollup::adapters::ApiVersion;
+use crate::rollup::confirmation::ConfirmationConfig;
+use crate::rollup::failover::FailoverConfig;
+use crate::rollup::fee_cache::FeeCacheConfig;
+use crate::rollup::network::Network;
+use crate::rollup::retry::RetryConfig;
+use crate::rollup::timeouts::TimeoutsConfig;
+use crate::rollup::tokens::WeightedToken;
+use crate::rollup::types::{ChangePubKeyFeeType, TokenLike};
+use crate::scenario::BuiltinScenarios;
+use crate::sponsor::SponsorConfig;
+use crate::tls::TlsPins;
+use crate::transaction::{parse_address, TransactionKind};
+use crate::wallet::account_state::AccountGcConfig;
+use crate::wallet::derivation::{derive_addresses, KeysConfig};
+use crate::wallet::keystore::KeystoreConfig;
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
+    pub network: NetworkConfig,
     pub general: GeneralConfig,
     pub transaction: TransactionConfig,
+    #[serde(default)]
+    pub funding: FundingConfig,
+    #[serde(default)]
+    pub scenarios: BuiltinScenarios,
+    #[serde(default)]
+    pub account_gc: AccountGcConfig,
+    #[serde(default)]
+    pub activation: ActivationConfig,
+    /// Control API of a running simulation, disabled when the section is missing.
+    pub control: Option<ControlConfig>,
+    /// Mnemonic based account keys, random keys are generated when the section is missing.
+    pub keys: Option<KeysConfig>,
+    /// Keys of random accounts kept between runs, fresh keys every run when the section is missing.
+    pub keystore: Option<KeystoreConfig>,
+    /// Faults injected into the simulator itself, disabled when the section is missing.
+    pub chaos: Option<ChaosConfig>,
+    /// Webhook the run summary is posted to when the run ends, disabled when the section is missing.
+    pub notify: Option<NotifyConfig>,
+    /// Prometheus endpoint of the running simulation, disabled when the section is missing.
+    pub metrics: Option<PrometheusConfig>,
+    /// Liveness and readiness probes for service deployments, disabled when the section is missing.
+    pub health: Option<HealthConfig>,
+    /// Resubmission study of the server's duplicate handling, disabled when the section is missing.
+    pub resubmission: Option<ResubmissionConfig>,
+    /// Wallet bugs emulated to check the rollup rejects them, disabled when the section is missing.
+    pub misbehavior: Option<MisbehaviorConfig>,
+    /// Batches whose fee is paid by a sponsor account, disabled when the section is missing.
+    pub sponsor: Option<SponsorConfig>,
+    /// Journeys the accounts are followed through, steps named after transaction types.
+    #[serde(default)]
+    pub journeys: Vec<JourneyTemplate>,
+    #[serde(default)]
+    pub baseline: BaselineTolerances,
+    /// Seeds of the random streams, drawn from entropy when the section is missing.
+    #[serde(default)]
+    pub rng: RngConfig,
+    /// Effective settings as loaded, overrides applied and secrets digested, `None` for the
+    /// built-in defaults.
+    #[serde(skip)]
+    pub snapshot: Option<toml::Table>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NetworkConfig {
+    /// Network the endpoints and the chain id default to, also set by `--network`.
+    #[serde(default)]
+    pub chain: Network,
+    /// Rollup server API, the one of `chain` when not set.
+    #[serde(default)]
+    pub rollup_url: Option<String>,
+    /// Rollup servers of a load-balanced deployment, requests are spread over them instead
+    /// of `rollup_url`, see `failover`.
+    #[serde(default)]
+    pub rollup_urls: Vec<String>,
+    /// JSON-RPC endpoint of a Rootstock node, the one of `chain` when not set.
+    #[serde(default)]
+    pub rpc_url: Option<String>,
+    /// Version of the rollup server API, selects the adapter used to talk to it.
+    #[serde(default)]
+    pub api_version: ApiVersion,
+    #[serde(default)]
+    pub timeouts: TimeoutsConfig,
+    #[serde(default)]
+    pub failover: FailoverConfig,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    #[serde(default)]
+    pub ethop_poll: EthOpPollConfig,
+    #[serde(default)]
+    pub fee_cache: FeeCacheConfig,
+    #[serde(default)]
+    pub confirmation: ConfirmationConfig,
+    #[serde(default)]
+    pub l1_nonce: L1NonceConfig,
+    /// Certificate pins of HTTPS hosts, keyed by host name, see `tls`.
+    #[serde(default)]
+    pub tls_pins: TlsPins,
+}
+
+impl NetworkConfig {
+    /// Rollup server API in use, empty for a custom network without `rollup_url`, which
+    /// validation refuses.
+    pub fn rollup_url(&self) -> &str {
+        self.rollup_url
+            .as_deref()
+            .or(self.chain.rollup_url())
+            .unwrap_or_default()
+    }
+
+    /// Rollup servers requests are spread over, `rollup_urls` when listed and the single
+    /// `rollup_url` otherwise.
+    pub fn rollup_urls(&self) -> Vec<&str> {
+        if self.rollup_urls.is_empty() {
+            vec![self.rollup_url()]
+        } else {
+            self.rollup_urls.iter().map(String::as_str).collect()
+        }
+    }
+
+    /// Rootstock node in use, empty for a custom network without `rpc_url`, which
+    /// validation refuses.
+    pub fn rpc_url(&self) -> &str {
+        self.rpc_url
+            .as_deref()
+            .or(self.chain.rpc_url())
+            .unwrap_or_default()
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -13,21 +168,1142 @@ pub struct GeneralConfig {
     pub enable_throttling: bool,
     pub generate_reports: bool,
     pub tps: u32,
+    /// Shape of the rate over the run, peaking at `tps`; constant when not set.
+    #[serde(default)]
+    pub load_profile: LoadShape,
+    /// Bursts sent on top of the load profile, disabled when not set.
+    #[serde(default)]
+    pub burst: Option<BurstConfig>,
+    /// Backs off from the load profile while the node shows backpressure, a fixed rate when
+    /// not set.
+    #[serde(default)]
+    pub adaptive_rate: Option<AdaptiveRateConfig>,
+    /// Prints the transactions signed against an in-process rollup instead of submitting them,
+    /// also set by `--dry-run`.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Length of the run in seconds, runs until interrupted when not set.
+    #[serde(default)]
+    pub duration_secs: Option<u64>,
+    /// File keeping a hash-chained record of every signed payload submitted during the run.
+    #[serde(default)]
+    pub audit_log: Option<String>,
+    /// Directory every signed transaction is dumped to for replay, see `capture` for the format.
+    #[serde(default)]
+    pub capture_dir: Option<PathBuf>,
+    /// Also write the log of the run as JSON lines to `<report_dir>/<run_id>/log.jsonl`.
+    #[serde(default)]
+    pub log_json: bool,
+    /// Identifier of the run, generated when not set.
+    #[serde(default)]
+    pub run_id: Option<String>,
+    /// Tag submitted traffic with the run identifier so operators can filter it out.
+    #[serde(default)]
+    pub tag_traffic: bool,
+    /// Directory generated reports are written to, created when missing.
+    #[serde(default = "GeneralConfig::default_report_dir")]
+    pub report_dir: PathBuf,
+    /// Width in seconds of the windows of `latency_timeline.csv`, written with the reports
+    /// when set.
+    #[serde(default)]
+    pub latency_timeline_secs: Option<u64>,
+    /// Also write the time spent per pipeline stage as `stages.folded`, the folded stack
+    /// format of flame graph tools.
+    #[serde(default)]
+    pub stage_profile: bool,
+    /// Write the addresses of the run's accounts with an alias each to
+    /// `explorer_labels.json` before sending starts, for import into the explorer tagging tool.
+    #[serde(default)]
+    pub explorer_labels: bool,
+    /// Write amounts in the text tables as raw integers of the smallest token units instead
+    /// of whole units such as `1.25 RBTC`.
+    #[serde(default)]
+    pub raw_amounts: bool,
+    /// Maximum number of submissions waiting for the server response at the same time.
+    #[serde(default = "GeneralConfig::default_max_in_flight")]
+    pub max_in_flight: usize,
+    /// Open loop sends at `tps`, closed loop lets `virtual_users` wait for each confirmation.
+    #[serde(default)]
+    pub load_mode: LoadMode,
+    /// Users sending one transaction at a time in the closed-loop mode.
+    #[serde(default = "GeneralConfig::default_virtual_users")]
+    pub virtual_users: usize,
+    /// How long an interrupted run waits for submitted transactions to be confirmed before
+    /// writing its checkpoint.
+    #[serde(default = "GeneralConfig::default_shutdown_grace_secs")]
+    pub shutdown_grace_secs: u64,
 }
 
-#[derive(Debug, Deserialize)]
+impl GeneralConfig {
+    fn default_report_dir() -> PathBuf {
+        PathBuf::from("reports")
+    }
+
+    fn default_max_in_flight() -> usize {
+        256
+    }
+
+    fn default_virtual_users() -> usize {
+        10
+    }
+
+    fn default_shutdown_grace_secs() -> u64 {
+        30
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct TransactionConfig {
     pub min_deposit_value: u32,
     pub max_deposit_value: u32,
     pub min_transfer_value: u32,
     pub max_transfer_value: u32,
+    /// Tokens used by generated transactions with their relative weights,
+    /// checked against the rollup token list at startup.
+    #[serde(default = "TransactionConfig::default_tokens")]
+    pub tokens: Vec<WeightedToken>,
+    pub min_transfer_to_new_value: u32,
+    pub max_transfer_to_new_value: u32,
+    #[serde(default = "TransactionConfig::default_min_withdraw_value")]
+    pub min_withdraw_value: u32,
+    #[serde(default = "TransactionConfig::default_max_withdraw_value")]
+    pub max_withdraw_value: u32,
+    /// Percentage of generated withdrawals requesting fast processing.
+    #[serde(default)]
+    pub fast_withdraw_percent: u32,
+    /// Addresses that must never be used as a transfer recipient or withdrawal target.
+    #[serde(default, deserialize_with = "deserialize_addresses")]
+    pub denylist: Vec<Address>,
+    /// Withdrawals whose fee exceeds this percentage of the withdrawn amount are not generated.
+    #[serde(default = "TransactionConfig::default_max_withdrawal_fee_percent")]
+    pub max_withdrawal_fee_percent: u32,
+    /// Relative weights of the generated operation types, deposits only when not set.
+    #[serde(default = "TransactionConfig::default_mix")]
+    pub mix: BTreeMap<TransactionKind, u32>,
+    /// Generate transfers exceeding the sender's balance as tracked locally, for negative
+    /// testing; otherwise they are downsized or not generated.
+    #[serde(default)]
+    pub allow_overdraft: bool,
+}
+
+/// Parses a list of addresses, rejecting malformed entries and mismatching checksums.
+fn deserialize_addresses<'de, D>(deserializer: D) -> Result<Vec<Address>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|value| {
+            parse_address(value).map_err(|err| serde::de::Error::custom(format!("{err}: {value}")))
+        })
+        .collect()
+}
+
+impl TransactionConfig {
+    fn default_tokens() -> Vec<WeightedToken> {
+        vec![TokenLike::from("RBTC").into()]
+    }
+
+    fn default_min_withdraw_value() -> u32 {
+        1
+    }
+
+    fn default_max_withdraw_value() -> u32 {
+        10
+    }
+
+    fn default_max_withdrawal_fee_percent() -> u32 {
+        50
+    }
+
+    fn default_mix() -> BTreeMap<TransactionKind, u32> {
+        BTreeMap::from([(TransactionKind::Deposit, 1)])
+    }
+}
+
+/// Configuration file looked up when none is given on the command line.
+pub const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+/// Configuration shipped with the repository, the base of scenario templates run without
+/// a configuration file.
+pub const EXAMPLE_CONFIG: &str = include_str!("../config.toml");
+
+/// Built-in configuration used when no configuration file exists:
+/// a local rollup node, 4 accounts and transfers at 5 TPS for 60 seconds.
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            network: NetworkConfig {
+                chain: Network::default(),
+                rollup_url: None,
+                rollup_urls: Vec::new(),
+                rpc_url: None,
+                api_version: ApiVersion::default(),
+                timeouts: TimeoutsConfig::default(),
+                failover: FailoverConfig::default(),
+                retry: RetryConfig::default(),
+                ethop_poll: EthOpPollConfig::default(),
+                fee_cache: FeeCacheConfig::default(),
+                confirmation: ConfirmationConfig::default(),
+                l1_nonce: L1NonceConfig::default(),
+                tls_pins: TlsPins::new(),
+            },
+            general: GeneralConfig {
+                account_count: 4,
+                enable_throttling: true,
+                generate_reports: false,
+                tps: 5,
+                load_profile: LoadShape::default(),
+                burst: None,
+                adaptive_rate: None,
+                dry_run: false,
+                duration_secs: Some(60),
+                audit_log: None,
+                capture_dir: None,
+                log_json: false,
+                run_id: None,
+                tag_traffic: false,
+                report_dir: GeneralConfig::default_report_dir(),
+                latency_timeline_secs: None,
+                stage_profile: false,
+                explorer_labels: false,
+                raw_amounts: false,
+                max_in_flight: GeneralConfig::default_max_in_flight(),
+                load_mode: LoadMode::default(),
+                virtual_users: GeneralConfig::default_virtual_users(),
+                shutdown_grace_secs: GeneralConfig::default_shutdown_grace_secs(),
+            },
+            transaction: TransactionConfig {
+                min_deposit_value: 10,
+                max_deposit_value: 100,
+                min_transfer_value: 1,
+                max_transfer_value: 10,
+                tokens: TransactionConfig::default_tokens(),
+                min_transfer_to_new_value: 1,
+                max_transfer_to_new_value: 10,
+                min_withdraw_value: TransactionConfig::default_min_withdraw_value(),
+                max_withdraw_value: TransactionConfig::default_max_withdraw_value(),
+                fast_withdraw_percent: 0,
+                denylist: Vec::new(),
+                max_withdrawal_fee_percent: TransactionConfig::default_max_withdrawal_fee_percent(),
+                mix: BTreeMap::from([(TransactionKind::Transfer, 1)]),
+                allow_overdraft: false,
+            },
+            funding: FundingConfig::default(),
+            scenarios: BuiltinScenarios::default(),
+            account_gc: AccountGcConfig::default(),
+            activation: ActivationConfig::default(),
+            control: None,
+            keys: None,
+            keystore: None,
+            chaos: None,
+            notify: None,
+            metrics: None,
+            health: None,
+            resubmission: None,
+            misbehavior: None,
+            sponsor: None,
+            journeys: Vec::new(),
+            baseline: BaselineTolerances::default(),
+            rng: RngConfig::default(),
+            snapshot: None,
+        }
+    }
+}
+
+/// Setting that parses but can not work, with the dotted path of the offending key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigViolation {
+    pub path: String,
+    pub message: String,
+}
+
+impl ConfigViolation {
+    fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Unable to read configuration: {0}")]
+    Io(#[from] io::Error),
+    #[error("Malformed configuration: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("Malformed configuration: {0}")]
+    Document(#[from] toml_edit::TomlError),
+    #[error("Unable to migrate configuration: {0}")]
+    Migration(#[from] MigrationError),
+    #[error("{}", render_violations(.0))]
+    Invalid(Vec<ConfigViolation>),
+}
+
+fn render_violations(violations: &[ConfigViolation]) -> String {
+    let mut rendered = format!("{} invalid settings", violations.len());
+    for violation in violations {
+        rendered.push_str("\n  ");
+        rendered.push_str(&violation.to_string());
+    }
+    rendered
+}
+
+/// Prefix of the environment variables overriding configuration values.
+pub const ENV_OVERRIDE_PREFIX: &str = "RIF_SIM_";
+
+/// Value set on top of the configuration file, e.g. `general.tps=100`.
+///
+/// Values are read as TOML, so `100`, `true` and `["RBTC", "RIF"]` keep their type;
+/// anything that is not valid TOML is taken as a string.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigOverride {
+    pub path: String,
+    pub value: toml::Value,
+}
+
+impl ConfigOverride {
+    pub fn new(path: &str, raw_value: &str) -> Self {
+        let value = toml::from_str::<toml::Table>(&format!("value = {raw_value}"))
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+            .unwrap_or_else(|| toml::Value::String(raw_value.to_string()));
+        Self {
+            path: path.to_string(),
+            value,
+        }
+    }
+
+    /// Overrides given as `RIF_SIM_<SECTION>__<KEY>` variables, `__` separating the levels,
+    /// e.g. `RIF_SIM_GENERAL__ACCOUNT_COUNT=50`.
+    pub fn from_env(vars: impl IntoIterator<Item = (String, String)>) -> Vec<Self> {
+        let mut overrides: Vec<Self> = vars
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let path = name.strip_prefix(ENV_OVERRIDE_PREFIX)?;
+                Some(Self::new(&path.to_lowercase().replace("__", "."), &value))
+            })
+            .collect();
+        // The environment is unordered, sorting keeps the outcome of conflicting variables stable.
+        overrides.sort_by(|a, b| a.path.cmp(&b.path));
+        overrides
+    }
+
+    fn apply(&self, root: &mut toml::Table) -> Result<(), ConfigViolation> {
+        let mut keys: Vec<&str> = self.path.split('.').collect();
+        let last = keys.pop().filter(|key| !key.is_empty());
+        let Some(last) = last else {
+            return Err(ConfigViolation::new(&self.path, "is not a valid key path"));
+        };
+        let mut table = root;
+        for key in keys {
+            let entry = table
+                .entry(key)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            table = entry.as_table_mut().ok_or_else(|| {
+                ConfigViolation::new(&self.path, format!("`{key}` is not a section"))
+            })?;
+        }
+        table.insert(last.to_string(), self.value.clone());
+        Ok(())
+    }
+}
+
+impl FromStr for ConfigOverride {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (path, raw_value) = value
+            .split_once('=')
+            .ok_or_else(|| format!("expected PATH=VALUE, got `{value}`"))?;
+        Ok(Self::new(path.trim(), raw_value.trim()))
+    }
+}
+
+/// Dotted paths of settings never written to reports as they are.
+const SECRET_KEYS: [&str; 4] = [
+    "keys.mnemonic",
+    "keystore.password",
+    "funding.master_key",
+    "notify.webhook_url",
+];
+
+/// Replaces secrets with a short digest, which still tells whether they changed between runs.
+fn digest_secrets(table: &mut toml::Table) {
+    for path in SECRET_KEYS {
+        let (section, key) = path.split_once('.').expect("dotted path");
+        let secret = table
+            .get_mut(section)
+            .and_then(toml::Value::as_table_mut)
+            .and_then(|section| section.get_mut(key));
+        if let Some(secret) = secret {
+            let digest = Sha256::digest(secret.to_string().as_bytes());
+            *secret = toml::Value::String(format!("sha256:{}", hex::encode(&digest[..4])));
+        }
+    }
 }
 
 impl Config {
-    pub fn load_from_file(file_path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let content = fs::read_to_string(file_path)?;
-        let config: Config = toml::from_str(&content)?;
+    pub fn load_from_file(file_path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Self::load_with_overrides(file_path, &[])
+    }
+
+    /// Loads the configuration file with `overrides` applied in order, the last one winning.
+    pub fn load_with_overrides(
+        file_path: impl AsRef<Path>,
+        overrides: &[ConfigOverride],
+    ) -> Result<Self, ConfigError> {
+        let content = fs::read_to_string(&file_path)?;
+        Self::parse_with_overrides(
+            &content,
+            &file_path.as_ref().display().to_string(),
+            overrides,
+        )
+    }
+
+    /// Parses the configuration `content` read from `source` with `overrides` applied in order.
+    pub fn parse_with_overrides(
+        content: &str,
+        source: &str,
+        overrides: &[ConfigOverride],
+    ) -> Result<Self, ConfigError> {
+        let mut document: toml_edit::Document = content.parse()?;
+        let migration = config_migration::migrate(&mut document)?;
+        if !migration.is_current() {
+            warn!(
+                "Configuration {} is written for schema version {}, migrated on the fly; \
+                 `config migrate` updates the file",
+                source, migration.from_version
+            );
+        }
+        for warning in &migration.warnings {
+            warn!("Deprecated setting {}", warning);
+        }
+        let mut table: toml::Table = toml::from_str(&document.to_string())?;
+        let violations: Vec<ConfigViolation> = overrides
+            .iter()
+            .filter_map(|config_override| config_override.apply(&mut table).err())
+            .collect();
+        if !violations.is_empty() {
+            return Err(ConfigError::Invalid(violations));
+        }
+        let mut snapshot = table.clone();
+        let mut config: Config = toml::Value::Table(table).try_into()?;
+        digest_secrets(&mut snapshot);
+        config.snapshot = Some(snapshot);
+
+        Ok(config)
+    }
+
+    /// Checks for mistakes parsing can not catch, such as empty value ranges, reporting all
+    /// of them at once.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut violations = Vec::new();
+        let general = &self.general;
+        if general.account_count == 0 {
+            violations.push(ConfigViolation::new(
+                "general.account_count",
+                "must be positive",
+            ));
+        }
+        if general.tps == 0 {
+            violations.push(ConfigViolation::new("general.tps", "must be positive"));
+        }
+        if let Some(min_tps) = general.load_profile.min_tps() {
+            if min_tps > general.tps {
+                violations.push(ConfigViolation::new(
+                    "general.load_profile",
+                    format!(
+                        "lowest rate {min_tps} exceeds the peak general.tps {}",
+                        general.tps
+                    ),
+                ));
+            }
+        }
+        match general.load_profile {
+            LoadShape::Burst {
+                period_secs,
+                burst_secs,
+                ..
+            } if burst_secs == 0 || burst_secs > period_secs => {
+                violations.push(ConfigViolation::new(
+                    "general.load_profile.burst_secs",
+                    format!("must be positive and at most period_secs {period_secs}"),
+                ));
+            }
+            LoadShape::Sine { period_secs: 0, .. } => {
+                violations.push(ConfigViolation::new(
+                    "general.load_profile.period_secs",
+                    "must be positive",
+                ));
+            }
+            _ => {}
+        }
+        if let Some(adaptive_rate) = &general.adaptive_rate {
+            if adaptive_rate.interval_ms == 0 {
+                violations.push(ConfigViolation::new(
+                    "general.adaptive_rate.interval_ms",
+                    "must be positive",
+                ));
+            }
+            if !(adaptive_rate.decrease_factor > 0.0 && adaptive_rate.decrease_factor < 1.0) {
+                violations.push(ConfigViolation::new(
+                    "general.adaptive_rate.decrease_factor",
+                    format!("{} is not between 0 and 1", adaptive_rate.decrease_factor),
+                ));
+            }
+            if !(adaptive_rate.min_factor > 0.0 && adaptive_rate.min_factor <= 1.0) {
+                violations.push(ConfigViolation::new(
+                    "general.adaptive_rate.min_factor",
+                    format!("{} is not above 0 and at most 1", adaptive_rate.min_factor),
+                ));
+            }
+            if adaptive_rate.increase_step <= 0.0 {
+                violations.push(ConfigViolation::new(
+                    "general.adaptive_rate.increase_step",
+                    "must be positive",
+                ));
+            }
+            if !general.enable_throttling || general.load_mode == LoadMode::Closed {
+                violations.push(ConfigViolation::new(
+                    "general.adaptive_rate",
+                    "only applies to throttled open-loop runs",
+                ));
+            }
+        }
+        if general.latency_timeline_secs == Some(0) {
+            violations.push(ConfigViolation::new(
+                "general.latency_timeline_secs",
+                "must be positive",
+            ));
+        }
+        if let Some(burst) = &general.burst {
+            if burst.txs == 0 {
+                violations.push(ConfigViolation::new(
+                    "general.burst.txs",
+                    "must be positive",
+                ));
+            }
+            if burst.every_secs == 0 {
+                violations.push(ConfigViolation::new(
+                    "general.burst.every_secs",
+                    "must be positive",
+                ));
+            }
+            if burst.within_ms == 0 || burst.within_ms > burst.every_secs.saturating_mul(1_000) {
+                violations.push(ConfigViolation::new(
+                    "general.burst.within_ms",
+                    "must be positive and at most every_secs",
+                ));
+            }
+        }
+        if general.duration_secs == Some(0) {
+            violations.push(ConfigViolation::new(
+                "general.duration_secs",
+                "must be positive, remove it to run until interrupted",
+            ));
+        }
+        if general.max_in_flight == 0 {
+            violations.push(ConfigViolation::new(
+                "general.max_in_flight",
+                "must be positive",
+            ));
+        }
+        if general.load_mode == LoadMode::Closed && general.virtual_users == 0 {
+            violations.push(ConfigViolation::new(
+                "general.virtual_users",
+                "must be positive with load_mode = \"closed\"",
+            ));
+        }
+
+        let transaction = &self.transaction;
+        for (name, min, max) in [
+            (
+                "deposit",
+                transaction.min_deposit_value,
+                transaction.max_deposit_value,
+            ),
+            (
+                "transfer",
+                transaction.min_transfer_value,
+                transaction.max_transfer_value,
+            ),
+            (
+                "transfer_to_new",
+                transaction.min_transfer_to_new_value,
+                transaction.max_transfer_to_new_value,
+            ),
+            (
+                "withdraw",
+                transaction.min_withdraw_value,
+                transaction.max_withdraw_value,
+            ),
+        ] {
+            if min > max {
+                violations.push(ConfigViolation::new(
+                    format!("transaction.min_{name}_value"),
+                    format!("{min} exceeds transaction.max_{name}_value {max}"),
+                ));
+            }
+        }
+        if transaction.tokens.is_empty() {
+            violations.push(ConfigViolation::new(
+                "transaction.tokens",
+                "must list at least one token",
+            ));
+        }
+        if transaction.mix.values().all(|weight| *weight == 0) {
+            violations.push(ConfigViolation::new(
+                "transaction.mix",
+                "needs at least one type with a positive weight",
+            ));
+        } else if self.general.dry_run
+            && transaction
+                .mix
+                .iter()
+                .all(|(kind, weight)| *weight == 0 || *kind == TransactionKind::Deposit)
+        {
+            violations.push(ConfigViolation::new(
+                "transaction.mix",
+                "only weights deposits, which a dry run does not generate",
+            ));
+        }
+        if transaction.fast_withdraw_percent > 100 {
+            violations.push(ConfigViolation::new(
+                "transaction.fast_withdraw_percent",
+                format!("{} exceeds 100", transaction.fast_withdraw_percent),
+            ));
+        }
+
+        let confirmation = &self.network.confirmation;
+        let mut sla_types: Vec<&String> = confirmation.sla.keys().collect();
+        sla_types.sort();
+        for tx_type in sla_types {
+            let timeout = confirmation.timeout_for(tx_type);
+            if timeout.is_zero() {
+                violations.push(ConfigViolation::new(
+                    format!("network.confirmation.sla.{tx_type}.timeout_secs"),
+                    "must be positive",
+                ));
+            }
+            if let Some(slo) = confirmation.slo_for(tx_type, TxStatus::Verified) {
+                if slo > timeout {
+                    violations.push(ConfigViolation::new(
+                        format!("network.confirmation.sla.{tx_type}.verify_slo_ms"),
+                        format!(
+                            "{} exceeds the timeout of {}s, breaches would be timeouts",
+                            slo.as_millis(),
+                            timeout.as_secs()
+                        ),
+                    ));
+                }
+            }
+        }
+        if let Some(events_url) = &confirmation.events_url {
+            let scheme = events_url.parse::<hyper::Uri>().ok();
+            if !matches!(
+                scheme.as_ref().and_then(|uri| uri.scheme_str()),
+                Some("ws" | "wss")
+            ) {
+                violations.push(ConfigViolation::new(
+                    "network.confirmation.events_url",
+                    format!("{events_url} is not a ws:// or wss:// URL"),
+                ));
+            }
+            if self.network.api_version != ApiVersion::V01 {
+                violations.push(ConfigViolation::new(
+                    "network.confirmation.events_url",
+                    "only the JSON-RPC API of v0.1 has subscriptions",
+                ));
+            }
+        }
+
+        if self.network.l1_nonce.gas_bump_percent < 10 {
+            violations.push(ConfigViolation::new(
+                "network.l1_nonce.gas_bump_percent",
+                "must be at least 10, nodes refuse smaller replacements",
+            ));
+        }
+        if self.network.rollup_url.is_some() && !self.network.rollup_urls.is_empty() {
+            violations.push(ConfigViolation::new(
+                "network.rollup_urls",
+                "replaces network.rollup_url, set only one of them",
+            ));
+        }
+        if self.network.failover.max_failures == 0 {
+            violations.push(ConfigViolation::new(
+                "network.failover.max_failures",
+                "must be at least 1",
+            ));
+        }
+        let retry = &self.network.retry;
+        if retry.max_attempts == 0 {
+            violations.push(ConfigViolation::new(
+                "network.retry.max_attempts",
+                "must be at least 1, 1 disables retries",
+            ));
+        }
+        if retry.base_delay_ms > retry.max_delay_ms {
+            violations.push(ConfigViolation::new(
+                "network.retry.base_delay_ms",
+                format!(
+                    "{} exceeds network.retry.max_delay_ms {}",
+                    retry.base_delay_ms, retry.max_delay_ms
+                ),
+            ));
+        }
+        if retry.jitter_percent > 100 {
+            violations.push(ConfigViolation::new(
+                "network.retry.jitter_percent",
+                "must be at most 100",
+            ));
+        }
+        if let Network::Custom { chain_id } = self.network.chain {
+            let rollup_set =
+                self.network.rollup_url.is_some() || !self.network.rollup_urls.is_empty();
+            for (key, set) in [
+                ("rollup_url", rollup_set),
+                ("rpc_url", self.network.rpc_url.is_some()),
+            ] {
+                if !set {
+                    violations.push(ConfigViolation::new(
+                        format!("network.{key}"),
+                        format!("must be set for the custom network of chain {chain_id}"),
+                    ));
+                }
+            }
+        }
+        let rollups: Vec<hyper::Uri> = self
+            .network
+            .rollup_urls()
+            .into_iter()
+            .filter_map(|url| url.parse().ok())
+            .collect();
+        for (host, pins) in &self.network.tls_pins {
+            if pins.is_empty() {
+                violations.push(ConfigViolation::new(
+                    format!("network.tls_pins.{host}"),
+                    "must list at least one pin, remove the host to leave it unpinned",
+                ));
+            }
+            let plain_rollup = rollups.iter().any(|uri| {
+                uri.scheme_str() != Some("https")
+                    && uri
+                        .host()
+                        .is_some_and(|rollup| rollup.eq_ignore_ascii_case(host))
+            });
+            if plain_rollup {
+                violations.push(ConfigViolation::new(
+                    format!("network.tls_pins.{host}"),
+                    "network.rollup_url reaches the host over plain HTTP, pins only apply to HTTPS",
+                ));
+            }
+        }
+
+        if self.funding.budget.time_budget_secs == Some(0) {
+            violations.push(ConfigViolation::new(
+                "funding.time_budget_secs",
+                "must be positive, remove it for an unlimited budget",
+            ));
+        }
+        if let Err(err) = self.funding.master_wallet() {
+            violations.push(ConfigViolation::new(
+                "funding.master_key",
+                format!("is not a private key: {err}"),
+            ));
+        }
+        for (index, erc20) in self.funding.erc20.iter().enumerate() {
+            if erc20.deposit_amount > erc20.l1_amount {
+                violations.push(ConfigViolation::new(
+                    format!("funding.erc20[{index}].deposit_amount"),
+                    format!(
+                        "{} exceeds the l1_amount {} sent to every account",
+                        erc20.deposit_amount, erc20.l1_amount
+                    ),
+                ));
+            }
+        }
+        if let Some(payouts) = &self.scenarios.merchant_payouts {
+            if payouts.min_payment > payouts.max_payment {
+                violations.push(ConfigViolation::new(
+                    "scenarios.merchant_payouts.min_payment",
+                    format!(
+                        "{} exceeds scenarios.merchant_payouts.max_payment {}",
+                        payouts.min_payment, payouts.max_payment
+                    ),
+                ));
+            }
+        }
+        if let Some(nft_interference) = &self.scenarios.nft_interference {
+            if nft_interference.transfer_tps == 0 {
+                violations.push(ConfigViolation::new(
+                    "scenarios.nft_interference.transfer_tps",
+                    "must be positive, transfers are what the scenario measures",
+                ));
+            }
+            if nft_interference.nft_tps_steps.is_empty() {
+                violations.push(ConfigViolation::new(
+                    "scenarios.nft_interference.nft_tps_steps",
+                    "must list at least one NFT rate",
+                ));
+            }
+        }
+        if let Some(chaos) = &self.chaos {
+            if chaos.interval_secs == 0 {
+                violations.push(ConfigViolation::new(
+                    "chaos.interval_secs",
+                    "must be positive",
+                ));
+            }
+        }
+        if self.activation.batch_size == 0 {
+            violations.push(ConfigViolation::new(
+                "activation.batch_size",
+                "must be positive, 1 activates accounts one by one",
+            ));
+        }
+        if self.activation.auth_types.is_empty() {
+            violations.push(ConfigViolation::new(
+                "activation.auth_types",
+                "must name at least one authorization",
+            ));
+        }
+        if self
+            .activation
+            .auth_types
+            .contains(&ChangePubKeyFeeType::CREATE2)
+            && self.activation.create2.is_none()
+        {
+            violations.push(ConfigViolation::new(
+                "activation.create2",
+                "section is required by the CREATE2 authorization",
+            ));
+        }
+        if let Some(sponsor) = &self.sponsor {
+            if sponsor.batch_size == 0 {
+                violations.push(ConfigViolation::new(
+                    "sponsor.batch_size",
+                    "must be positive",
+                ));
+            }
+        }
+        if let Some(resubmission) = &self.resubmission {
+            if !(0.0..=100.0).contains(&resubmission.sample_percent) {
+                violations.push(ConfigViolation::new(
+                    "resubmission.sample_percent",
+                    format!("{} is not between 0 and 100", resubmission.sample_percent),
+                ));
+            }
+        }
+        if let Some(misbehavior) = &self.misbehavior {
+            if !(0.0..=100.0).contains(&misbehavior.rate_percent) {
+                violations.push(ConfigViolation::new(
+                    "misbehavior.rate_percent",
+                    format!("{} is not between 0 and 100", misbehavior.rate_percent),
+                ));
+            }
+            if misbehavior.stale_fee_percent >= 100 {
+                violations.push(ConfigViolation::new(
+                    "misbehavior.stale_fee_percent",
+                    "must be below 100 for the fee to be stale",
+                ));
+            }
+        }
+        if self.notify.is_some() && !Feature::Webhook.is_enabled() {
+            violations.push(ConfigViolation::new(
+                "notify",
+                Feature::Webhook.unavailable(),
+            ));
+        }
+        if let Some(keys) = &self.keys {
+            if let Err(err) = derive_addresses(keys, 1) {
+                violations.push(ConfigViolation::new("keys", err.to_string()));
+            }
+            if self.keystore.is_some() {
+                violations.push(ConfigViolation::new(
+                    "keystore",
+                    "has no effect next to [keys], whose accounts are derived from the mnemonic",
+                ));
+            }
+        }
 
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(violations))
+        }
+    }
+
+    /// The `[keys]` section, which commands whose accounts must outlive the run depend on.
+    pub fn require_keys(&self, feature: &str) -> Result<&KeysConfig, ConfigError> {
+        self.keys.as_ref().ok_or_else(|| {
+            ConfigError::Invalid(vec![ConfigViolation::new(
+                "keys",
+                format!("section is required to {feature}, random accounts would be lost"),
+            )])
+        })
+    }
+
+    /// Accounts the simulation needs, the fee sponsor's on top of `general.account_count`.
+    pub fn pool_size(&self) -> u32 {
+        self.general.account_count + u32::from(self.sponsor.is_some())
+    }
+
+    /// Fails unless accounts come back on the next run, derived from `[keys]` or reloaded from
+    /// the `[keystore]`.
+    pub fn require_persistent_accounts(&self, feature: &str) -> Result<(), ConfigError> {
+        if self.keys.is_some() || self.keystore.is_some() {
+            return Ok(());
+        }
+        Err(ConfigError::Invalid(vec![ConfigViolation::new(
+            "keys",
+            format!(
+                "or the keystore section is required to {feature}, random accounts would be lost"
+            ),
+        )]))
+    }
+
+    /// The required settings of [`Config::default`] as a configuration file, for overrides to be
+    /// layered on when there is no file.
+    fn default_document() -> toml_edit::Document {
+        let defaults = Self::default();
+        let mut document = toml_edit::Document::new();
+        document["schema_version"] = value(i64::from(CURRENT_SCHEMA_VERSION));
+        document["network"] = table();
+
+        let general = &defaults.general;
+        document["general"] = table();
+        document["general"]["account_count"] = value(i64::from(general.account_count));
+        document["general"]["enable_throttling"] = value(general.enable_throttling);
+        document["general"]["generate_reports"] = value(general.generate_reports);
+        document["general"]["tps"] = value(i64::from(general.tps));
+        if let Some(duration_secs) = general.duration_secs {
+            document["general"]["duration_secs"] = value(duration_secs as i64);
+        }
+
+        let transaction = &defaults.transaction;
+        document["transaction"] = table();
+        for (key, amount) in [
+            ("min_deposit_value", transaction.min_deposit_value),
+            ("max_deposit_value", transaction.max_deposit_value),
+            ("min_transfer_value", transaction.min_transfer_value),
+            ("max_transfer_value", transaction.max_transfer_value),
+            (
+                "min_transfer_to_new_value",
+                transaction.min_transfer_to_new_value,
+            ),
+            (
+                "max_transfer_to_new_value",
+                transaction.max_transfer_to_new_value,
+            ),
+        ] {
+            document["transaction"][key] = value(i64::from(amount));
+        }
+        let mix: InlineTable = transaction
+            .mix
+            .iter()
+            .map(|(kind, weight)| (kind.name(), Value::from(i64::from(*weight))))
+            .collect();
+        document["transaction"]["mix"] = value(mix);
+        document
+    }
+
+    /// Loads and validates the configuration file with `overrides` applied, falling back to the
+    /// built-in defaults when it does not exist.
+    pub fn load_or_default(
+        file_path: impl AsRef<Path>,
+        overrides: &[ConfigOverride],
+    ) -> Result<Self, ConfigError> {
+        let config = match fs::metadata(&file_path) {
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                warn!(
+                    "Configuration file '{}' not found, using built-in defaults \
+                     (localhost network, 4 accounts, 5 TPS transfers for 60s)",
+                    file_path.as_ref().display()
+                );
+                if overrides.is_empty() {
+                    Self::default()
+                } else {
+                    Self::parse_with_overrides(
+                        &Self::default_document().to_string(),
+                        "built-in defaults",
+                        overrides,
+                    )?
+                }
+            }
+            _ => Self::load_with_overrides(file_path, overrides)?,
+        };
+        config.validate()?;
         Ok(config)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::funding::Erc20Funding;
+    use crate::rollup::confirmation::ConfirmationSla;
+
+    /// Tests that every violation is reported at once with the path of its key.
+    #[test]
+    fn test_validate_reports_all_violations() {
+        assert!(Config::default().validate().is_ok());
+
+        let mut config = Config::default();
+        config.general.account_count = 0;
+        config.general.tps = 0;
+        config.transaction.min_transfer_value = 20;
+        config.transaction.fast_withdraw_percent = 150;
+        config.network.confirmation.sla.insert(
+            "withdraw".to_string(),
+            ConfirmationSla {
+                timeout_secs: Some(60),
+                verify_slo_ms: Some(120_000),
+                ..Default::default()
+            },
+        );
+        config.network.confirmation.events_url = Some("http://localhost:3031".to_string());
+        config.network.chain = "custom:1337".parse().unwrap();
+        config.network.rollup_url = Some("http://10.0.0.2:5454".to_string());
+        config.network.rollup_urls = vec!["http://10.0.0.3:5454".to_string()];
+        config.funding.master_key = Some("not a key".to_string());
+        config.funding.erc20 = vec![Erc20Funding {
+            token: "RDOC".to_string(),
+            address: Address::zero(),
+            decimals: 18,
+            l1_amount: 1,
+            deposit_amount: 2,
+        }];
+        let Err(ConfigError::Invalid(violations)) = config.validate() else {
+            panic!("invalid configuration accepted");
+        };
+        let paths: Vec<&str> = violations
+            .iter()
+            .map(|violation| violation.path.as_str())
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "general.account_count",
+                "general.tps",
+                "transaction.min_transfer_value",
+                "transaction.fast_withdraw_percent",
+                "network.confirmation.sla.withdraw.verify_slo_ms",
+                "network.confirmation.events_url",
+                "network.rollup_urls",
+                "network.rpc_url",
+                "funding.master_key",
+                "funding.erc20[0].deposit_amount"
+            ]
+        );
+        assert!(matches!(
+            config.require_keys("fund accounts"),
+            Err(ConfigError::Invalid(violations)) if violations[0].path == "keys"
+        ));
+        assert!(config.require_persistent_accounts("resume a run").is_err());
+        config.keystore = Some(KeystoreConfig {
+            path: PathBuf::from("keys"),
+            password: None,
+            insecure_plain: true,
+        });
+        assert!(config.require_persistent_accounts("resume a run").is_ok());
+
+        let mut config = Config::default();
+        config.general.dry_run = true;
+        assert!(config.validate().is_ok());
+        config.transaction.mix = BTreeMap::from([
+            (TransactionKind::Deposit, 3),
+            (TransactionKind::Transfer, 0),
+        ]);
+        assert!(matches!(
+            config.validate(),
+            Err(ConfigError::Invalid(violations)) if violations[0].path == "transaction.mix"
+        ));
+    }
+
+    /// Tests that a section of a subsystem left out of the build is rejected, naming its feature.
+    #[test]
+    fn test_disabled_feature_sections() {
+        let config = Config {
+            notify: Some(NotifyConfig {
+                webhook_url: "https://hooks.example.com/run".to_string(),
+                kind: Default::default(),
+                artifacts_url: None,
+                timeout_secs: 10,
+            }),
+            ..Config::default()
+        };
+        match config.validate() {
+            Ok(()) => assert!(Feature::Webhook.is_enabled()),
+            Err(ConfigError::Invalid(violations)) => {
+                assert!(!Feature::Webhook.is_enabled());
+                assert_eq!(violations[0].path, "notify");
+                assert!(violations[0].message.contains("--features webhook"));
+            }
+            Err(err) => panic!("unexpected error: {err}"),
+        }
+    }
+
+    /// Tests that environment and command line overrides are layered on the configuration file.
+    #[test]
+    fn test_overrides() {
+        let env = ConfigOverride::from_env([
+            (
+                "RIF_SIM_GENERAL__ACCOUNT_COUNT".to_string(),
+                "50".to_string(),
+            ),
+            ("RIF_SIM_GENERAL__TPS".to_string(), "20".to_string()),
+            ("HOME".to_string(), "/root".to_string()),
+        ]);
+        assert_eq!(env.len(), 2);
+        let mut overrides = env;
+        overrides.push("general.tps = 100".parse().unwrap());
+        overrides.push("general.run_id=nightly".parse().unwrap());
+        overrides.push("keys.mnemonic=\"test test junk\"".parse().unwrap());
+        assert!("general.tps".parse::<ConfigOverride>().is_err());
+
+        let path = std::env::temp_dir().join(format!("config-test-{}.toml", std::process::id()));
+        fs::write(&path, include_str!("../config.toml")).unwrap();
+        let config = Config::load_with_overrides(&path, &overrides).unwrap();
+        assert_eq!(config.general.account_count, 50);
+        assert_eq!(config.general.tps, 100);
+        assert_eq!(config.general.run_id.as_deref(), Some("nightly"));
+        assert_eq!(config.keys.unwrap().mnemonic, "test test junk");
+
+        let nested = ["general.tps.value=1".parse().unwrap()];
+        assert!(matches!(
+            Config::load_with_overrides(&path, &nested),
+            Err(ConfigError::Invalid(violations)) if violations[0].path == "general.tps.value"
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+
+    /// Tests that overrides without a configuration file are layered on the built-in defaults.
+    #[test]
+    fn test_overrides_without_file() {
+        let path = std::env::temp_dir().join(format!("missing-{}.toml", std::process::id()));
+        let overrides = ["general.tps=20".parse().unwrap()];
+        let config = Config::load_or_default(&path, &overrides).unwrap();
+        assert_eq!(config.general.tps, 20);
+        let defaults = Config::default();
+        assert_eq!(config.general.account_count, defaults.general.account_count);
+        assert_eq!(config.general.duration_secs, defaults.general.duration_secs);
+        assert_eq!(config.transaction.mix, defaults.transaction.mix);
+
+        let mut builtin = Config::parse_with_overrides(
+            &Config::default_document().to_string(),
+            "built-in defaults",
+            &[],
+        )
+        .unwrap();
+        builtin.snapshot = None;
+        assert_eq!(format!("{builtin:?}"), format!("{defaults:?}"));
+    }
+}
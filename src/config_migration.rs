@@ -0,0 +1,182 @@
+//! Schema versions of configuration files.
+//!
+//! Files carry the version of the schema they were written for in a top level
+//! `schema_version`, files without one are version 0. Older files are migrated step by step
+//! when they are loaded, every step saying what it changed, and `config migrate` writes the
+//! migrated file back, comments and layout kept.
+
+use std::fmt;
+
+use thiserror::Error;
+use toml_edit::{value, Document, Item};
+
+/// Version of the schema this version of the tool reads.
+pub const CURRENT_SCHEMA_VERSION: u32 = 1;
+
+const VERSION_KEY: &str = "schema_version";
+
+#[derive(Debug, Error, PartialEq)]
+pub enum MigrationError {
+    #[error("{VERSION_KEY} must be a non-negative integer, got {0}")]
+    Malformed(String),
+    #[error("{VERSION_KEY} {0} is newer than {CURRENT_SCHEMA_VERSION}, the latest this version of the tool supports")]
+    Unsupported(u32),
+}
+
+/// Change made to a file while migrating it, to be shown to the user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationWarning {
+    pub path: String,
+    pub message: String,
+}
+
+impl MigrationWarning {
+    fn new(path: &str, message: impl Into<String>) -> Self {
+        Self {
+            path: path.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for MigrationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Outcome of migrating a file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationReport {
+    /// Version the file was written for.
+    pub from_version: u32,
+    pub warnings: Vec<MigrationWarning>,
+}
+
+impl MigrationReport {
+    /// Whether the file was already written for the current schema.
+    pub fn is_current(&self) -> bool {
+        self.from_version == CURRENT_SCHEMA_VERSION
+    }
+}
+
+/// Step migrating a file from the version at its index to the next one.
+type Migration = fn(&mut Document, &mut Vec<MigrationWarning>);
+
+const MIGRATIONS: [Migration; CURRENT_SCHEMA_VERSION as usize] = [v0_throttling];
+
+/// Version 1 replaced the throttling level with an on/off switch and dropped the account
+/// limits, which the generator never used.
+fn v0_throttling(document: &mut Document, warnings: &mut Vec<MigrationWarning>) {
+    let Some(general) = document
+        .get_mut("general")
+        .and_then(Item::as_table_like_mut)
+    else {
+        return;
+    };
+    if let Some(level) = general.remove("throttling_level") {
+        let enabled = level.as_integer().unwrap_or(0) > 0;
+        if general.contains_key("enable_throttling") {
+            warnings.push(MigrationWarning::new(
+                "general.throttling_level",
+                "is deprecated and was removed, general.enable_throttling is already set",
+            ));
+        } else {
+            general.insert("enable_throttling", value(enabled));
+            warnings.push(MigrationWarning::new(
+                "general.throttling_level",
+                format!("is deprecated, replaced by general.enable_throttling = {enabled}"),
+            ));
+        }
+    }
+    for key in [
+        "max_throttling_variance",
+        "max_self_created_accounts",
+        "max_unclaimed_accounts",
+    ] {
+        if general.remove(key).is_some() {
+            warnings.push(MigrationWarning::new(
+                &format!("general.{key}"),
+                "had no effect and was removed",
+            ));
+        }
+    }
+}
+
+/// Version the file was written for, 0 when it does not say.
+pub fn schema_version(document: &Document) -> Result<u32, MigrationError> {
+    match document.get(VERSION_KEY) {
+        None => Ok(0),
+        Some(version) => version
+            .as_integer()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| MigrationError::Malformed(version.to_string().trim().to_string())),
+    }
+}
+
+/// Migrates `document` to [`CURRENT_SCHEMA_VERSION`], leaving files already written for it as
+/// they are.
+pub fn migrate(document: &mut Document) -> Result<MigrationReport, MigrationError> {
+    let from_version = schema_version(document)?;
+    if from_version > CURRENT_SCHEMA_VERSION {
+        return Err(MigrationError::Unsupported(from_version));
+    }
+    let mut warnings = Vec::new();
+    for migration in &MIGRATIONS[from_version as usize..] {
+        migration(document, &mut warnings);
+    }
+    if from_version < CURRENT_SCHEMA_VERSION {
+        document.insert(VERSION_KEY, value(CURRENT_SCHEMA_VERSION as i64));
+    }
+    Ok(MigrationReport {
+        from_version,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests migrating a file of the original schema and leaving current files untouched.
+    #[test]
+    fn test_migrate() {
+        let original = "[general]\ntps = 100\nthrottling_level = 3 # 0 - disabled, 10 - max\n\
+                        max_throttling_variance = 0\nmax_unclaimed_accounts = 10\n";
+        let mut document: Document = original.parse().unwrap();
+        let report = migrate(&mut document).unwrap();
+        assert_eq!(report.from_version, 0);
+        assert!(!report.is_current());
+        let paths: Vec<&str> = report
+            .warnings
+            .iter()
+            .map(|warning| warning.path.as_str())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "general.throttling_level",
+                "general.max_throttling_variance",
+                "general.max_unclaimed_accounts"
+            ]
+        );
+        let migrated = document.to_string();
+        assert_eq!(schema_version(&migrated.parse().unwrap()), Ok(1));
+        assert!(migrated.contains("tps = 100\n"));
+        assert!(migrated.contains("enable_throttling = true"));
+        assert!(!migrated.contains("throttling_level"));
+
+        let mut current: Document = migrated.parse().unwrap();
+        let report = migrate(&mut current).unwrap();
+        assert!(report.is_current() && report.warnings.is_empty());
+        assert_eq!(current.to_string(), migrated);
+
+        let mut newer: Document = "schema_version = 2\n".parse().unwrap();
+        assert_eq!(migrate(&mut newer), Err(MigrationError::Unsupported(2)));
+        let mut malformed: Document = "schema_version = \"one\"\n".parse().unwrap();
+        assert_eq!(
+            migrate(&mut malformed),
+            Err(MigrationError::Malformed("\"one\"".to_string()))
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Client, Method, Request, Response, Server, StatusCode};
+use serde::Deserialize;
+
+use crate::metrics::pipeline::QueueDepthHistory;
+use crate::metrics::Metrics;
+use crate::report::RunRecorder;
+use crate::scenario::pause::PauseControl;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ControlConfig {
+    /// Address the control API listens on.
+    pub bind_address: SocketAddr,
+}
+
+impl Default for ControlConfig {
+    fn default() -> Self {
+        Self {
+            bind_address: SocketAddr::from(([127, 0, 0, 1], 9898)),
+        }
+    }
+}
+
+/// State of the running simulation exposed through the control API.
+#[derive(Debug, Clone)]
+pub struct ControlState {
+    pub recorder: Arc<RunRecorder>,
+    pub metrics: Arc<Metrics>,
+    pub queue_depths: Arc<QueueDepthHistory>,
+    pub pause: PauseControl,
+}
+
+/// Serves the control API until the future is dropped.
+///
+/// - `GET /export` returns a snapshot of the aggregates and raw records collected so far.
+/// - `POST /resume` releases the run from a manual pause point.
+pub async fn serve(config: &ControlConfig, state: ControlState) -> Result<(), hyper::Error> {
+    let state = Arc::new(state);
+    let make_service = make_service_fn(move |_| {
+        let state = state.clone();
+        async move { Ok::<_, Infallible>(service_fn(move |request| handle(state.clone(), request))) }
+    });
+
+    Server::bind(&config.bind_address).serve(make_service).await
+}
+
+async fn handle(
+    state: Arc<ControlState>,
+    request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/export") => {
+            let snapshot = state
+                .recorder
+                .snapshot(&state.metrics, state.queue_depths.samples());
+            match serde_json::to_string(&snapshot) {
+                Ok(json) => json_response(StatusCode::OK, json),
+                Err(err) => text_response(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
+            }
+        }
+        (&Method::POST, "/resume") => {
+            if state.pause.resume() {
+                text_response(StatusCode::OK, "resumed".to_string())
+            } else {
+                text_response(StatusCode::CONFLICT, "run is not paused".to_string())
+            }
+        }
+        _ => text_response(StatusCode::NOT_FOUND, "not found".to_string()),
+    };
+    Ok(response)
+}
+
+fn json_response(status: StatusCode, body: String) -> Response<Body> {
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+fn text_response(status: StatusCode, body: String) -> Response<Body> {
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    response
+}
+
+/// Downloads the live export of a simulation running with the control API at `address`.
+pub async fn fetch_live_export(address: SocketAddr) -> Result<String, Box<dyn std::error::Error>> {
+    let uri = format!("http://{}/export", address).parse()?;
+    let response = Client::new().get(uri).await?;
+    if !response.status().is_success() {
+        return Err(format!("control API responded with {}", response.status()).into());
+    }
+    let body = hyper::body::to_bytes(response.into_body()).await?;
+    Ok(String::from_utf8(body.to_vec())?)
+}
@@ -0,0 +1,277 @@
+//! Dry runs: the whole generation pipeline runs, accounts and signing included, against an
+//! in-process mock of the rollup server, and signed transactions are printed instead of
+//! submitted. Accounts are assumed funded with the `[funding]` deposits and fees are the
+//! mock's, nothing is asked from a real server.
+
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use num::BigUint;
+use sha2::{Digest, Sha256};
+
+use crate::checkpoint::{CheckpointError, PipelineState};
+use crate::engine::TxPipeline;
+use crate::funding::FundingConfig;
+use crate::misbehavior::WalletBug;
+use crate::report::nfts::NftOperation;
+use crate::rng::RngStreams;
+use crate::rollup::mock::MockProvider;
+use crate::rollup::provider::{ClientError, ResponseResult};
+use crate::rollup::tokens::TokenRegistry;
+use crate::rollup::types::{Address, BlockNumber, Token, TokenId, TokenKind, TxHash};
+use crate::scenario::wait_for::BlockProgress;
+use crate::wallet::SignedTx;
+
+/// In-process rollup server the pipeline of a dry run is built against, listing RBTC and the
+/// `[[funding.erc20]]` tokens.
+pub fn mock_rollup(funding: &FundingConfig) -> MockProvider {
+    let rbtc = Token::new(TokenId(0), Address::zero(), "RBTC", 18, TokenKind::None);
+    let erc20 = funding.erc20.iter().enumerate().map(|(index, erc20)| {
+        Token::new(
+            TokenId(index as u32 + 1),
+            erc20.address,
+            &erc20.token,
+            erc20.decimals,
+            TokenKind::ERC20,
+        )
+    });
+    MockProvider::new().with_tokens(std::iter::once(rbtc).chain(erc20))
+}
+
+/// Pipeline accepting every transaction of the wrapped one without sending it anywhere.
+///
+/// Accepted transactions count as confirmed at once, and the mock chain seals a committed
+/// and verified block whenever its progress is queried, so scenario boundaries pass
+/// right away.
+pub struct DryRun<P> {
+    inner: P,
+    accepted: AtomicU64,
+    blocks: AtomicU32,
+}
+
+impl<P: TxPipeline> DryRun<P> {
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            accepted: AtomicU64::new(0),
+            blocks: AtomicU32::new(0),
+        }
+    }
+
+    /// Number of transactions that would have been submitted so far.
+    pub fn accepted(&self) -> u64 {
+        self.accepted.load(Ordering::Relaxed)
+    }
+
+    /// Hash of the serialized transaction, or of its position when it can not be serialized.
+    fn tx_hash(&self, tx: &P::Tx, index: u64) -> TxHash {
+        let payload = P::tx_payload(tx).unwrap_or_else(|| format!("{}:{}", P::tx_type(tx), index));
+        TxHash {
+            data: Sha256::digest(payload.as_bytes()).into(),
+        }
+    }
+}
+
+#[async_trait]
+impl<P: TxPipeline> TxPipeline for DryRun<P> {
+    type Tx = P::Tx;
+
+    async fn prepare(&self) -> Option<Self::Tx> {
+        self.inner.prepare().await
+    }
+
+    fn tx_type(tx: &Self::Tx) -> &'static str {
+        P::tx_type(tx)
+    }
+
+    fn tx_fee(tx: &Self::Tx) -> Option<BigUint> {
+        P::tx_fee(tx)
+    }
+
+    fn tx_account(tx: &Self::Tx) -> Option<Address> {
+        P::tx_account(tx)
+    }
+
+    fn tx_amount(tx: &Self::Tx) -> Option<(String, BigUint)> {
+        P::tx_amount(tx)
+    }
+
+    fn tx_nft(tx: &Self::Tx) -> Option<NftOperation> {
+        P::tx_nft(tx)
+    }
+
+    fn tx_copy(tx: &Self::Tx) -> Option<Self::Tx> {
+        P::tx_copy(tx)
+    }
+
+    fn tx_payload(tx: &Self::Tx) -> Option<String> {
+        P::tx_payload(tx)
+    }
+
+    fn tx_signed(tx: &Self::Tx) -> Option<&SignedTx> {
+        P::tx_signed(tx)
+    }
+
+    async fn prepare_misbehaving(&self, bug: WalletBug) -> Option<Self::Tx> {
+        self.inner.prepare_misbehaving(bug).await
+    }
+
+    async fn submit(&self, tx: Self::Tx) -> Result<TxHash, ClientError> {
+        let index = self.accepted.fetch_add(1, Ordering::Relaxed);
+        let tx_hash = self.tx_hash(&tx, index);
+        let from = P::tx_account(&tx).map_or_else(|| "-".to_string(), |from| format!("{from:?}"));
+        let amount = P::tx_amount(&tx).map_or_else(
+            || "-".to_string(),
+            |(token, amount)| format!("{amount} {token}"),
+        );
+        let fee = P::tx_fee(&tx).map_or_else(|| "-".to_string(), |fee| fee.to_string());
+        println!(
+            "[dry run] {:>6} {:<16} from {} amount {} fee {} -> {}",
+            index + 1,
+            P::tx_type(&tx),
+            from,
+            amount,
+            fee,
+            tx_hash
+        );
+        Ok(tx_hash)
+    }
+
+    async fn settle(&self, _grace: Duration) {}
+
+    fn seed(&self, streams: &RngStreams) {
+        self.inner.seed(streams)
+    }
+
+    fn checkpoint(&self) -> Option<PipelineState> {
+        self.inner.checkpoint()
+    }
+
+    fn restore(&self, state: &PipelineState) -> Result<(), CheckpointError> {
+        self.inner.restore(state)
+    }
+
+    fn tokens(&self) -> Option<TokenRegistry> {
+        self.inner.tokens()
+    }
+}
+
+#[async_trait]
+impl<P: TxPipeline> BlockProgress for DryRun<P> {
+    async fn last_committed_block(&self) -> ResponseResult<BlockNumber> {
+        Ok(BlockNumber(self.blocks.fetch_add(1, Ordering::Relaxed) + 1))
+    }
+
+    async fn last_verified_block(&self) -> ResponseResult<BlockNumber> {
+        Ok(BlockNumber(self.blocks.load(Ordering::Relaxed)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use std::sync::Arc;
+
+    use ethers::signers::LocalWallet;
+
+    use super::*;
+    use crate::accounts::AccountPool;
+    use crate::config::Config;
+    use crate::funding::Erc20Funding;
+    use crate::rollup::provider::Provider;
+    use crate::scenario::wait_for::WaitFor;
+    use crate::submission::RollupPipeline;
+    use crate::transaction::TransactionKind;
+    use crate::wallet::Wallet;
+
+    /// Pipeline that fails every submission, so a dry run reaching it would show.
+    struct OfflinePipeline {
+        payloads: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl TxPipeline for OfflinePipeline {
+        type Tx = String;
+
+        async fn prepare(&self) -> Option<String> {
+            self.payloads.lock().unwrap().pop()
+        }
+
+        fn tx_type(_tx: &String) -> &'static str {
+            "transfer"
+        }
+
+        fn tx_payload(tx: &String) -> Option<String> {
+            Some(tx.clone())
+        }
+
+        async fn submit(&self, _tx: String) -> Result<TxHash, ClientError> {
+            Err(ClientError::NetworkError(
+                "submitted during a dry run".to_string(),
+            ))
+        }
+    }
+
+    /// Tests that a dry run accepts prepared transactions with payload hashes and passes block boundaries.
+    #[tokio::test]
+    async fn test_dry_run() {
+        let pipeline = OfflinePipeline {
+            payloads: Mutex::new(vec!["b".to_string(), "a".to_string(), "a".to_string()]),
+        };
+        let dry_run = DryRun::new(pipeline);
+
+        let mut hashes = Vec::new();
+        while let Some(tx) = dry_run.prepare().await {
+            hashes.push(dry_run.submit(tx).await.unwrap());
+        }
+        assert_eq!(dry_run.accepted(), 3);
+        assert_eq!(hashes[0], hashes[1]);
+        assert_ne!(hashes[1], hashes[2]);
+
+        let reached = WaitFor::CommittedBlocks(3)
+            .wait(&dry_run, Duration::ZERO, Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert_eq!(reached, BlockNumber(4));
+        WaitFor::VerifiedBlock
+            .wait(&dry_run, Duration::ZERO, Duration::from_secs(1))
+            .await
+            .unwrap();
+    }
+
+    /// Tests that a pool assumed funded sends from the tokens of the mock rollup without
+    /// anything reaching it.
+    #[tokio::test]
+    async fn test_mock_rollup() {
+        let mut config = Config::default();
+        config.funding.erc20.push(Erc20Funding {
+            token: "RIF".to_string(),
+            address: Address::repeat_byte(7),
+            decimals: 18,
+            l1_amount: 0,
+            deposit_amount: 5_000_000,
+        });
+        config.transaction.mix = [(TransactionKind::Transfer, 1)].into();
+        let mut wallets = Vec::new();
+        for seed in 1..=2u8 {
+            let eth_signer = LocalWallet::from_bytes(&[seed; 32]).unwrap();
+            wallets.push(Wallet::new(eth_signer).await.unwrap());
+        }
+        let mut pool = AccountPool::new(wallets);
+        pool.assume_funded(&config.funding);
+
+        let rollup = Arc::new(mock_rollup(&config.funding));
+        assert!(rollup.tokens().await.unwrap().contains_key("RIF"));
+        let pipeline =
+            RollupPipeline::new(rollup.clone(), pool, &config.network, &config.transaction)
+                .await
+                .unwrap();
+        pipeline.seed(&RngStreams::new(&config.rng));
+        let dry_run = DryRun::new(pipeline);
+        let tx = dry_run.prepare().await.unwrap();
+        dry_run.submit(tx).await.unwrap();
+        assert!(rollup.submitted().is_empty());
+    }
+}
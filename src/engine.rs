@@ -0,0 +1,1086 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use num::BigUint;
+use serde::Deserialize;
+use tokio::sync::Semaphore;
+use tokio::task::{JoinError, JoinSet};
+use tracing::{debug, info_span, warn, Instrument};
+
+use crate::checkpoint::{CheckpointError, PipelineState};
+use crate::clock::{Clock, SystemClock};
+use crate::config::GeneralConfig;
+use crate::load_profile::{BurstConfig, WithBursts};
+use crate::metrics::pipeline::PipelineStage;
+use crate::metrics::prometheus::SUBMISSION_LATENCY_METRIC;
+use crate::metrics::Metrics;
+use crate::misbehavior::{MisbehaviorInjector, WalletBug};
+use crate::rate_control::{AdaptiveRate, AdaptiveRateSummary};
+use crate::report::latency::TxTiming;
+use crate::report::nfts::NftOperation;
+use crate::report::{RunRecorder, TxStatus};
+use crate::resubmission::ResubmissionStudy;
+use crate::rng::RngStreams;
+use crate::rollup::confirmation::{ConfirmationConfig, ConfirmationListener, TrackedOp};
+use crate::rollup::fee_cache::FeeCache;
+use crate::rollup::provider::ClientError;
+use crate::rollup::tokens::TokenRegistry;
+use crate::rollup::types::{Address, ChangePubKeyFeeType, TxHash};
+use crate::shutdown::Shutdown;
+use crate::throttler::Throttler;
+use crate::wallet::account_state::{AccountGcAction, AccountGcConfig, AccountGcSchedule};
+use crate::wallet::SignedTx;
+
+/// Counter of transactions accepted by the server, labelled by type.
+pub const SUBMITTED_METRIC: &str = "txs_submitted_total";
+/// Counter of transactions the server rejected or that could not be sent, labelled by type.
+pub const FAILED_METRIC: &str = "txs_failed_total";
+/// Counter of hashes `send_tx` returned for more than one submission, labelled by type and kind.
+pub const DUPLICATE_HASH_METRIC: &str = "tx_duplicate_hashes_total";
+
+/// How often the closed-loop mode checks whether the run is over.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Pause of a virtual user that found no account ready to send.
+const IDLE_USER_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Submission handed to the observers of the engine once the server answered.
+pub struct Submission<'a> {
+    pub tx_type: &'static str,
+    pub account: Option<Address>,
+    pub fee: Option<&'a BigUint>,
+    pub payload: Option<&'a str>,
+    /// Operation to follow until it is verified, set when the server accepted a tracked transaction.
+    pub op: Option<TrackedOp>,
+    pub result: Result<TxHash, &'a ClientError>,
+    /// When the transaction was handed to the pipeline.
+    pub started: Instant,
+}
+
+/// Consumer of every submission of the run, such as the confirmation tracker.
+pub trait SubmissionObserver: Send + Sync {
+    /// Sees the signed L2 transaction right before it is sent.
+    fn sending(&self, _signed: &SignedTx) {}
+
+    fn observe(&self, _submission: &Submission) {}
+}
+
+/// Load model of a run, set with `load_mode` in the `[general]` section.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadMode {
+    /// Transactions arrive at the configured rate whatever the rollup's response,
+    /// answering how the rollup copes with a given demand.
+    #[default]
+    Open,
+    /// A fixed number of virtual users each send their next transaction once the
+    /// previous one is confirmed, answering how much the rollup can sustain.
+    Closed,
+}
+
+/// Source of signed transactions and the way they reach the rollup.
+#[async_trait]
+pub trait TxPipeline: Send + Sync + 'static {
+    type Tx: Send + 'static;
+
+    /// Generates and signs the next transaction, `None` when no account can send one right now.
+    async fn prepare(&self) -> Option<Self::Tx>;
+
+    /// Operation type used to label reports and metrics.
+    fn tx_type(tx: &Self::Tx) -> &'static str;
+
+    /// Fee paid by the transaction, reported when known.
+    fn tx_fee(_tx: &Self::Tx) -> Option<BigUint> {
+        None
+    }
+
+    /// Account sending the transaction, logged with its events when known.
+    fn tx_account(_tx: &Self::Tx) -> Option<Address> {
+        None
+    }
+
+    /// Token symbol and amount moved by the transaction, reported when known.
+    fn tx_amount(_tx: &Self::Tx) -> Option<(String, BigUint)> {
+        None
+    }
+
+    /// Mint or withdrawal of an NFT made by the transaction, tracked per creator.
+    fn tx_nft(_tx: &Self::Tx) -> Option<NftOperation> {
+        None
+    }
+
+    /// Authorization of a `ChangePubKey`, to report the coverage of its variants.
+    fn tx_change_pubkey(_tx: &Self::Tx) -> Option<ChangePubKeyFeeType> {
+        None
+    }
+
+    /// Identical copy of the signed transaction for the resubmission study, `None` when the
+    /// transaction can not be copied.
+    fn tx_copy(_tx: &Self::Tx) -> Option<Self::Tx> {
+        None
+    }
+
+    /// Serialized transaction, kept to analyse hashes the server returns twice.
+    fn tx_payload(_tx: &Self::Tx) -> Option<String> {
+        None
+    }
+
+    /// Signed L2 transaction exactly as it is sent, `None` for L1 operations.
+    fn tx_signed(_tx: &Self::Tx) -> Option<&SignedTx> {
+        None
+    }
+
+    /// How the transaction is followed once accepted, e.g. `TrackedOp::Withdrawal`, `None`
+    /// when its confirmation is not tracked.
+    fn tracked_as(_tx: &Self::Tx) -> Option<fn(TxHash) -> TrackedOp> {
+        None
+    }
+
+    /// Generates and signs a transaction with the wallet `bug`, `None` when the pipeline can not
+    /// emulate it. It must leave the nonces and balances of the accounts as they are, the
+    /// transaction being expected to be rejected.
+    async fn prepare_misbehaving(&self, _bug: WalletBug) -> Option<Self::Tx> {
+        None
+    }
+
+    async fn submit(&self, tx: Self::Tx) -> Result<TxHash, ClientError>;
+
+    /// Waits until a submitted transaction of `tx_type` is confirmed, paces the closed-loop mode.
+    ///
+    /// Pipelines that do not follow confirmations treat accepted transactions as confirmed.
+    async fn wait_confirmed(&self, _tx_hash: TxHash, _tx_type: &str) -> Result<(), ClientError> {
+        Ok(())
+    }
+
+    /// Learns that an accepted transaction reached `status`, as followed by the confirmation
+    /// tracker, for local state that has to wait until the rollup confirmed it.
+    async fn tx_reached(&self, _tx_hash: TxHash, _status: TxStatus) {}
+
+    /// Waits up to `grace` for accepted transactions to be confirmed, called once the run stopped.
+    async fn settle(&self, _grace: Duration) {}
+
+    /// Compacts or re-syncs the local model of the accounts, as scheduled by [`AccountGcSchedule`].
+    async fn collect_garbage(&self, _action: AccountGcAction, _dust_threshold: &BigUint) {}
+
+    /// Takes the random streams transactions are generated from, before the first one is prepared.
+    fn seed(&self, _streams: &RngStreams) {}
+
+    /// Reports what only the pipeline sees, e.g. what deposits cost on L1, to the recorder of
+    /// the engine running it.
+    fn report_to(&self, _recorder: Arc<RunRecorder>) {}
+
+    /// Accounts and unconfirmed operations to persist when the run is interrupted,
+    /// `None` when the pipeline can not be resumed.
+    fn checkpoint(&self) -> Option<PipelineState> {
+        None
+    }
+
+    /// Continues from the state of an interrupted run, before the first transaction is prepared.
+    fn restore(&self, _state: &PipelineState) -> Result<(), CheckpointError> {
+        Err(CheckpointError::Unsupported)
+    }
+
+    /// Tokens the pipeline sends, whose decimals the reports write amounts with.
+    fn tokens(&self) -> Option<TokenRegistry> {
+        None
+    }
+
+    /// Fees the pipeline quotes once and reuses, flushed by the run when the L1 gas price moves.
+    fn fee_cache(&self) -> Option<Arc<FeeCache>> {
+        None
+    }
+}
+
+#[async_trait]
+impl<P: TxPipeline> ConfirmationListener for P {
+    async fn reached(&self, tx_hash: TxHash, status: TxStatus) {
+        self.tx_reached(tx_hash, status).await
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct EngineSummary {
+    pub submitted: u64,
+    pub failed: u64,
+    /// Schedule slots left unused because the pipeline had nothing to send.
+    pub skipped: u64,
+    /// Transactions confirmed before the next one was sent, closed-loop mode only.
+    pub confirmed: u64,
+    pub elapsed: Duration,
+    /// The run was stopped by a shutdown request before its duration passed.
+    pub interrupted: bool,
+    /// Changes of the rate in the adaptive mode, `None` at a fixed rate.
+    pub adaptive_rate: Option<AdaptiveRateSummary>,
+}
+
+impl EngineSummary {
+    pub fn achieved_tps(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs == 0.0 {
+            return 0.0;
+        }
+        (self.submitted + self.failed) as f64 / secs
+    }
+
+    /// Throughput of confirmed transactions, the result of a closed-loop run.
+    pub fn confirmed_tps(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs == 0.0 {
+            return 0.0;
+        }
+        self.confirmed as f64 / secs
+    }
+
+    fn merge(&mut self, other: EngineSummary) {
+        self.submitted += other.submitted;
+        self.failed += other.failed;
+        self.skipped += other.skipped;
+        self.confirmed += other.confirmed;
+    }
+
+    fn record(&mut self, joined: Result<bool, JoinError>) {
+        match joined {
+            Ok(true) => self.submitted += 1,
+            Ok(false) | Err(_) => self.failed += 1,
+        }
+    }
+}
+
+/// Submits transactions at the configured rate from concurrent tokio tasks.
+///
+/// Transactions are prepared and paced on the calling task, so the schedule never
+/// depends on how long the server takes to respond. Each submission then runs in its
+/// own task; at most `max_in_flight` of them are pending at once; when the limit is
+/// reached the schedule falls behind, which shows up as response time in the report.
+pub struct Engine<P: TxPipeline, C: Clock = SystemClock> {
+    pipeline: Arc<P>,
+    throttler: Throttler<C>,
+    enable_throttling: bool,
+    duration: Option<Duration>,
+    max_in_flight: usize,
+    load_mode: LoadMode,
+    virtual_users: usize,
+    recorder: Arc<RunRecorder>,
+    metrics: Arc<Metrics>,
+    shutdown: Shutdown,
+    /// Timeouts of the confirmations closed-loop users wait for.
+    confirmation: ConfirmationConfig,
+    account_gc: Option<Mutex<AccountGcSchedule>>,
+    resubmission: Option<Arc<ResubmissionStudy>>,
+    misbehavior: Option<Arc<MisbehaviorInjector>>,
+    /// Bursts of the throttler's profile, probed for their drain times.
+    bursts: Option<BurstConfig>,
+    observers: Vec<Arc<dyn SubmissionObserver>>,
+}
+
+impl<P: TxPipeline> Engine<P> {
+    pub fn new(
+        pipeline: P,
+        config: &GeneralConfig,
+        recorder: Arc<RunRecorder>,
+        metrics: Arc<Metrics>,
+    ) -> Self {
+        let mut profile = config.load_profile.profile(config.tps);
+        if let Some(bursts) = config.burst {
+            profile = Box::new(WithBursts {
+                base: profile,
+                bursts,
+            });
+        }
+        let mut throttler = Throttler::with_profile(profile);
+        if let Some(adaptive_rate) = config.adaptive_rate {
+            throttler = throttler.with_adaptive_rate(adaptive_rate);
+        }
+        let mut engine =
+            Self::with_throttler(Arc::new(pipeline), config, throttler, recorder, metrics);
+        engine.bursts = config.burst;
+        engine
+    }
+
+    /// Engine running a single scenario phase at its own rate and length.
+    pub fn for_phase(
+        pipeline: Arc<P>,
+        config: &GeneralConfig,
+        tps: u32,
+        duration: Duration,
+        recorder: Arc<RunRecorder>,
+        metrics: Arc<Metrics>,
+    ) -> Self {
+        let mut engine =
+            Self::with_throttler(pipeline, config, Throttler::new(tps), recorder, metrics);
+        engine.duration = Some(duration);
+        engine
+    }
+}
+
+impl<P: TxPipeline, C: Clock> Engine<P, C> {
+    pub fn with_throttler(
+        pipeline: Arc<P>,
+        config: &GeneralConfig,
+        throttler: Throttler<C>,
+        recorder: Arc<RunRecorder>,
+        metrics: Arc<Metrics>,
+    ) -> Self {
+        pipeline.report_to(recorder.clone());
+        Self {
+            pipeline,
+            throttler,
+            enable_throttling: config.enable_throttling,
+            duration: config.duration_secs.map(Duration::from_secs),
+            max_in_flight: config.max_in_flight.max(1),
+            load_mode: config.load_mode,
+            virtual_users: config.virtual_users.max(1),
+            recorder,
+            metrics,
+            shutdown: Shutdown::new(),
+            confirmation: ConfirmationConfig::default(),
+            account_gc: None,
+            resubmission: None,
+            misbehavior: None,
+            bursts: None,
+            observers: Vec::new(),
+        }
+    }
+
+    /// Stops generating transactions once `shutdown` is requested.
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Gives up on the confirmation a closed-loop user waits for after the timeout of the
+    /// transaction's type in `config`.
+    pub fn with_confirmation(mut self, config: ConfirmationConfig) -> Self {
+        self.confirmation = config;
+        self
+    }
+
+    /// Has the pipeline compact and re-sync its accounts as scheduled by `config` while running.
+    pub fn with_account_gc(mut self, config: AccountGcConfig) -> Self {
+        self.account_gc = Some(Mutex::new(AccountGcSchedule::new(config)));
+        self
+    }
+
+    /// Sends a sample of the accepted transactions a second time, see [`ResubmissionStudy`].
+    pub fn with_resubmission(mut self, study: ResubmissionStudy) -> Self {
+        self.resubmission = Some(Arc::new(study));
+        self
+    }
+
+    /// Follows a small share of the transactions with one broken by a wallet bug, see
+    /// [`MisbehaviorInjector`].
+    pub fn with_misbehavior(mut self, injector: MisbehaviorInjector) -> Self {
+        self.misbehavior = Some(Arc::new(injector));
+        self
+    }
+
+    /// Hands every submission to `observer` once the server answered.
+    pub fn with_observer(mut self, observer: Arc<dyn SubmissionObserver>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    pub fn pipeline(&self) -> &P {
+        &self.pipeline
+    }
+
+    /// Pipeline shared with the tasks following its transactions, such as the confirmation
+    /// tracker telling it of the stages they reach.
+    pub fn shared_pipeline(&self) -> Arc<P> {
+        self.pipeline.clone()
+    }
+
+    fn is_finished(&self) -> bool {
+        self.throttler.is_finished(self.duration) || self.shutdown.is_requested()
+    }
+
+    /// Runs the account maintenance due after `elapsed` time of the run, if any.
+    async fn collect_garbage(&self, elapsed: Duration) {
+        let Some(schedule) = &self.account_gc else {
+            return;
+        };
+        let (due, dust_threshold) = {
+            let mut schedule = schedule.lock().unwrap();
+            (schedule.due(elapsed), schedule.dust_threshold())
+        };
+        if let Some(action) = due {
+            self.metrics
+                .profile
+                .time(
+                    &["schedule", "account_gc"],
+                    self.pipeline.collect_garbage(action, &dust_threshold),
+                )
+                .await;
+        }
+    }
+
+    /// Runs until the configured duration passes or a shutdown is requested, then waits for
+    /// the submissions still in flight.
+    pub async fn run(&self) -> EngineSummary {
+        let span = info_span!("run", mode = ?self.load_mode);
+        async {
+            let mut summary = match self.load_mode {
+                LoadMode::Open => self.run_open_loop().await,
+                LoadMode::Closed => self.run_closed_loop().await,
+            };
+            summary.interrupted = self.shutdown.is_requested();
+            if let Some(study) = &self.resubmission {
+                study.finish().await;
+            }
+            if let Some(injector) = &self.misbehavior {
+                injector.finish().await;
+            }
+            summary
+        }
+        .instrument(span)
+        .await
+    }
+
+    async fn run_open_loop(&self) -> EngineSummary {
+        let started = Instant::now();
+        let semaphore = Arc::new(Semaphore::new(self.max_in_flight));
+        let mut tasks = JoinSet::new();
+        let mut summary = EngineSummary::default();
+        let gauges = &self.metrics.pipeline;
+        let profile = &self.metrics.profile;
+        let adaptive_rate = self
+            .throttler
+            .adaptive_rate()
+            .filter(|_| self.enable_throttling);
+        if let (Some(bursts), true) = (self.bursts, self.enable_throttling) {
+            let started = self.throttler.started_at();
+            self.recorder
+                .with_bursts(|probe| probe.start(started, bursts));
+        }
+
+        while !self.is_finished() {
+            while let Some(joined) = tasks.try_join_next() {
+                summary.record(joined);
+            }
+            self.collect_garbage(started.elapsed()).await;
+
+            gauges.enter(PipelineStage::Generated);
+            let tx = profile
+                .time(&["schedule", "prepare"], self.pipeline.prepare())
+                .await;
+            gauges.advance(PipelineStage::Generated, PipelineStage::AwaitingRate);
+            let intended_start = if self.enable_throttling {
+                profile
+                    .time(&["schedule", "throttle"], self.throttler.throttle())
+                    .await
+            } else {
+                Instant::now()
+            };
+            let Some(tx) = tx else {
+                gauges.leave(PipelineStage::AwaitingRate);
+                summary.skipped += 1;
+                continue;
+            };
+            let slot = semaphore.clone().acquire_owned();
+            let permit = match profile.time(&["schedule", "in_flight_slot"], slot).await {
+                Ok(permit) => permit,
+                Err(_) => break,
+            };
+            gauges.advance(PipelineStage::AwaitingRate, PipelineStage::InFlight);
+
+            let pipeline = self.pipeline.clone();
+            let recorder = self.recorder.clone();
+            let metrics = self.metrics.clone();
+            let study = self.resubmission.clone();
+            let rate = adaptive_rate.clone();
+            let observers = self.observers.clone();
+            tasks.spawn(
+                async move {
+                    let _permit = permit;
+                    let study = study.as_deref();
+                    let rate = rate.as_deref();
+                    submit_sampled(
+                        &pipeline,
+                        &recorder,
+                        &metrics,
+                        &observers,
+                        study,
+                        rate,
+                        tx,
+                        intended_start,
+                    )
+                    .await
+                    .is_some()
+                }
+                .in_current_span(),
+            );
+            if let Some(injector) = &self.misbehavior {
+                misbehave(&self.pipeline, &self.recorder, &self.metrics, injector).await;
+            }
+        }
+
+        while let Some(joined) = tasks.join_next().await {
+            summary.record(joined);
+        }
+        summary.elapsed = started.elapsed();
+        summary.adaptive_rate = adaptive_rate.map(|rate| rate.summary());
+        summary
+    }
+
+    /// Runs `virtual_users` users sending one transaction at a time until the duration passes.
+    ///
+    /// Throttling does not apply: the rate follows from how fast the rollup confirms.
+    async fn run_closed_loop(&self) -> EngineSummary {
+        let started = Instant::now();
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut users = JoinSet::new();
+        for user in 0..self.virtual_users {
+            let pipeline = self.pipeline.clone();
+            let recorder = self.recorder.clone();
+            let metrics = self.metrics.clone();
+            let study = self.resubmission.clone();
+            let injector = self.misbehavior.clone();
+            let observers = self.observers.clone();
+            let stop = stop.clone();
+            let shutdown = self.shutdown.clone();
+            let confirmation = self.confirmation.clone();
+            users.spawn(
+                async move {
+                    let mut summary = EngineSummary::default();
+                    while !stop.load(Ordering::Relaxed) {
+                        metrics.pipeline.enter(PipelineStage::Generated);
+                        let tx = metrics
+                            .profile
+                            .time(&["user", "prepare"], pipeline.prepare())
+                            .await;
+                        metrics
+                            .pipeline
+                            .advance(PipelineStage::Generated, PipelineStage::InFlight);
+                        let Some(tx) = tx else {
+                            metrics.pipeline.leave(PipelineStage::InFlight);
+                            summary.skipped += 1;
+                            tokio::time::sleep(IDLE_USER_BACKOFF).await;
+                            continue;
+                        };
+                        let tx_type = P::tx_type(&tx);
+                        let submitted = submit_sampled(
+                            &pipeline,
+                            &recorder,
+                            &metrics,
+                            &observers,
+                            study.as_deref(),
+                            None,
+                            tx,
+                            Instant::now(),
+                        )
+                        .await;
+                        if let Some(injector) = &injector {
+                            misbehave(&pipeline, &recorder, &metrics, injector).await;
+                        }
+                        let Some(tx_hash) = submitted else {
+                            summary.failed += 1;
+                            continue;
+                        };
+                        summary.submitted += 1;
+                        let confirmed = metrics.profile.time(
+                            &["user", "wait_confirmed"],
+                            pipeline.wait_confirmed(tx_hash, tx_type),
+                        );
+                        let confirmed = tokio::select! {
+                            confirmed = tokio::time::timeout(
+                                confirmation.timeout_for(tx_type),
+                                confirmed,
+                            ) => confirmed,
+                            _ = shutdown.requested() => break,
+                        };
+                        match confirmed {
+                            Ok(Ok(())) => summary.confirmed += 1,
+                            Ok(Err(_)) => {}
+                            Err(_) => {
+                                debug!(%tx_hash, tx_type, "gave up waiting for the confirmation")
+                            }
+                        }
+                    }
+                    summary
+                }
+                .instrument(info_span!("user", user)),
+            );
+        }
+
+        while !self.is_finished() {
+            tokio::time::sleep(STOP_POLL_INTERVAL).await;
+            self.collect_garbage(started.elapsed()).await;
+        }
+        stop.store(true, Ordering::Relaxed);
+
+        let mut summary = EngineSummary::default();
+        while let Some(joined) = users.join_next().await {
+            if let Ok(user) = joined {
+                summary.merge(user);
+            }
+        }
+        summary.elapsed = started.elapsed();
+        summary
+    }
+}
+
+/// Sends a transaction broken by a wallet bug when one is picked, outside of the schedule.
+async fn misbehave<P: TxPipeline>(
+    pipeline: &Arc<P>,
+    recorder: &Arc<RunRecorder>,
+    metrics: &Arc<Metrics>,
+    injector: &MisbehaviorInjector,
+) {
+    let Some(bug) = injector.pick() else {
+        return;
+    };
+    match pipeline.prepare_misbehaving(bug).await {
+        Some(tx) => injector.send(pipeline.clone(), recorder.clone(), metrics.clone(), bug, tx),
+        None => debug!(
+            bug = bug.name(),
+            "no transaction to emulate the wallet bug on"
+        ),
+    }
+}
+
+/// Submits a transaction, scheduling an identical copy for the resubmission study when sampled
+/// and reporting the response to the adaptive `rate`.
+#[allow(clippy::too_many_arguments)]
+async fn submit_sampled<P: TxPipeline>(
+    pipeline: &Arc<P>,
+    recorder: &Arc<RunRecorder>,
+    metrics: &Arc<Metrics>,
+    observers: &[Arc<dyn SubmissionObserver>],
+    study: Option<&ResubmissionStudy>,
+    rate: Option<&AdaptiveRate>,
+    tx: P::Tx,
+    intended_start: Instant,
+) -> Option<TxHash> {
+    let copy = study
+        .filter(|study| study.sample())
+        .and_then(|_| P::tx_copy(&tx));
+    let result = submit_and_record(
+        &**pipeline,
+        recorder,
+        metrics,
+        observers,
+        tx,
+        intended_start,
+    )
+    .await;
+    if let Some(rate) = rate {
+        let backpressure = result.as_ref().is_err_and(ClientError::is_backpressure);
+        rate.record_response(intended_start.elapsed(), backpressure);
+    }
+    let tx_hash = result.ok()?;
+    if let (Some(study), Some(copy)) = (study, copy) {
+        study.schedule(
+            pipeline.clone(),
+            recorder.clone(),
+            metrics.clone(),
+            tx_hash,
+            copy,
+        );
+    }
+    Some(tx_hash)
+}
+
+/// Submits a prepared transaction and records its outcome, returning the hash when accepted.
+async fn submit_and_record<P: TxPipeline>(
+    pipeline: &P,
+    recorder: &RunRecorder,
+    metrics: &Metrics,
+    observers: &[Arc<dyn SubmissionObserver>],
+    tx: P::Tx,
+    intended_start: Instant,
+) -> Result<TxHash, ClientError> {
+    let tx_type = P::tx_type(&tx);
+    let fee = P::tx_fee(&tx);
+    let account = P::tx_account(&tx);
+    let amount = P::tx_amount(&tx);
+    let payload = P::tx_payload(&tx);
+    let nft = P::tx_nft(&tx);
+    let change_pubkey = P::tx_change_pubkey(&tx);
+    let tracked_as = P::tracked_as(&tx);
+    if let Some(signed) = P::tx_signed(&tx) {
+        for observer in observers {
+            observer.sending(signed);
+        }
+    }
+    let actual_start = Instant::now();
+    let result = pipeline.submit(tx).await;
+    let timing = TxTiming {
+        intended_start,
+        actual_start,
+        completed: Instant::now(),
+    };
+    metrics.pipeline.leave(PipelineStage::InFlight);
+    metrics
+        .profile
+        .record(&["submit", tx_type], timing.service_time());
+    metrics.observe(
+        SUBMISSION_LATENCY_METRIC,
+        &[("type", tx_type)],
+        timing.service_time(),
+    );
+
+    recorder.with_bursts(|bursts| {
+        bursts.record_response(
+            intended_start,
+            timing.response_time(),
+            result.as_ref().ok().copied(),
+        )
+    });
+    let submission = Submission {
+        tx_type,
+        account,
+        fee: fee.as_ref(),
+        payload: payload.as_deref(),
+        op: tracked_as
+            .zip(result.as_ref().ok())
+            .map(|(op, tx_hash)| op(*tx_hash)),
+        result: result.as_ref().copied(),
+        started: actual_start,
+    };
+    for observer in observers {
+        observer.observe(&submission);
+    }
+
+    match result {
+        Ok(tx_hash) => {
+            metrics.increment(SUBMITTED_METRIC, &[("type", tx_type)]);
+            if let Some((token, amount)) = &amount {
+                recorder
+                    .with_balances(|balances| balances.record_moved(token, amount, fee.as_ref()));
+            }
+            if let Some(nft) = nft {
+                recorder.with_nfts(|nfts| nfts.record(nft, true));
+            }
+            if let Some(fee_type) = change_pubkey {
+                let fee = fee.clone().unwrap_or_default();
+                recorder.with_change_pubkey(|coverage| coverage.submitted(tx_hash, fee_type, fee));
+            }
+            if let Some(account) = account.filter(|_| tracked_as.is_some()) {
+                recorder.with_history(|history| history.record(account, tx_hash));
+            }
+            recorder.record_tx(tx_type, Some(tx_hash), fee, &timing, TxStatus::Submitted);
+            debug!(
+                tx_type,
+                %tx_hash,
+                ?account,
+                service_time_ms = timing.service_time().as_secs_f64() * 1000.0,
+                "transaction accepted"
+            );
+            let duplicate = recorder.with_duplicate_hashes(|duplicates| {
+                duplicates
+                    .record(tx_hash, tx_type, payload)
+                    .map(|duplicate| duplicate.kind)
+            });
+            if let Some(kind) = duplicate {
+                let kind = kind.name();
+                metrics.increment(DUPLICATE_HASH_METRIC, &[("type", tx_type), ("kind", kind)]);
+                warn!(tx_type, %tx_hash, ?account, kind, "server returned a hash seen before");
+            }
+            Ok(tx_hash)
+        }
+        Err(err) => {
+            metrics.increment(FAILED_METRIC, &[("type", tx_type)]);
+            warn!(tx_type, ?account, error = %err, "transaction rejected");
+            if let Some(nft) = nft {
+                recorder.with_nfts(|nfts| nfts.record(nft, false));
+            }
+            if let Some(fee_type) = change_pubkey {
+                let fee = fee.clone().unwrap_or_default();
+                recorder.with_change_pubkey(|coverage| {
+                    coverage.record(fee_type, &fee, timing.service_time(), false)
+                });
+            }
+            recorder.record_rejected(tx_type, fee, &timing, err.to_string());
+            Err(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::clock::SimulatedClock;
+    use crate::config::Config;
+
+    #[derive(Default)]
+    struct SlowPipeline {
+        prepared: AtomicUsize,
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl TxPipeline for SlowPipeline {
+        type Tx = usize;
+
+        async fn prepare(&self) -> Option<usize> {
+            let index = self.prepared.fetch_add(1, Ordering::SeqCst);
+            // Every fifth slot has no account ready to send.
+            (index % 5 != 4).then_some(index)
+        }
+
+        fn tx_type(_tx: &usize) -> &'static str {
+            "transfer"
+        }
+
+        async fn submit(&self, tx: usize) -> Result<TxHash, ClientError> {
+            let current = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(current, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            if tx % 10 == 3 {
+                return Err(ClientError::IncorrectInput);
+            }
+            Ok(TxHash::default())
+        }
+    }
+
+    /// Tests that the schedule is kept, concurrency stays bounded and every outcome is recorded.
+    #[tokio::test]
+    async fn test_engine_bounded_submission() {
+        let mut config = Config::default().general;
+        config.tps = 10;
+        config.duration_secs = Some(2);
+        config.max_in_flight = 3;
+        let recorder = Arc::new(RunRecorder::new());
+        let metrics = Arc::new(Metrics::new());
+        let engine = Engine::with_throttler(
+            Arc::new(SlowPipeline::default()),
+            &config,
+            Throttler::with_clock(config.tps, SimulatedClock::new()),
+            recorder.clone(),
+            metrics.clone(),
+        );
+
+        let summary = engine.run().await;
+
+        assert_eq!(summary.skipped, 4);
+        assert_eq!(summary.failed, 2);
+        assert_eq!(summary.submitted, 14);
+        assert!(engine.pipeline.max_in_flight.load(Ordering::SeqCst) <= 3);
+        assert_eq!(
+            metrics.counter(SUBMITTED_METRIC, &[("type", "transfer")]),
+            14
+        );
+        assert_eq!(metrics.pipeline.depth(PipelineStage::InFlight), 0);
+        let snapshot = recorder.snapshot(&metrics, Vec::new());
+        assert_eq!(snapshot.records.len(), 16);
+        // 16 submissions of at least 5ms each.
+        let totals = metrics.profile.totals();
+        assert!(totals[&vec!["submit", "transfer"]] >= Duration::from_millis(80));
+        assert!(totals.contains_key(&vec!["schedule", "throttle"]));
+    }
+
+    /// Pipeline confirming every transaction 10ms of virtual time after it was accepted.
+    struct ConfirmingPipeline {
+        clock: SimulatedClock,
+        sent: AtomicUsize,
+        unconfirmed: AtomicUsize,
+        max_unconfirmed: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl TxPipeline for ConfirmingPipeline {
+        type Tx = usize;
+
+        async fn prepare(&self) -> Option<usize> {
+            Some(self.sent.fetch_add(1, Ordering::SeqCst))
+        }
+
+        fn tx_type(_tx: &usize) -> &'static str {
+            "transfer"
+        }
+
+        async fn submit(&self, tx: usize) -> Result<TxHash, ClientError> {
+            if tx % 10 == 3 {
+                return Err(ClientError::IncorrectInput);
+            }
+            let current = self.unconfirmed.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_unconfirmed.fetch_max(current, Ordering::SeqCst);
+            Ok(TxHash::default())
+        }
+
+        async fn wait_confirmed(
+            &self,
+            _tx_hash: TxHash,
+            _tx_type: &str,
+        ) -> Result<(), ClientError> {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            self.clock.sleep(Duration::from_millis(10)).await;
+            self.unconfirmed.fetch_sub(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    /// Tests that closed-loop users never have more than one unconfirmed transaction each.
+    #[tokio::test]
+    async fn test_engine_closed_loop() {
+        let mut config = Config::default().general;
+        config.duration_secs = Some(1);
+        config.load_mode = LoadMode::Closed;
+        config.virtual_users = 3;
+        let clock = SimulatedClock::new();
+        let pipeline = Arc::new(ConfirmingPipeline {
+            clock: clock.clone(),
+            sent: AtomicUsize::new(0),
+            unconfirmed: AtomicUsize::new(0),
+            max_unconfirmed: AtomicUsize::new(0),
+        });
+        let engine = Engine::with_throttler(
+            pipeline.clone(),
+            &config,
+            Throttler::with_clock(config.tps, clock.clone()),
+            Arc::new(RunRecorder::new()),
+            Arc::new(Metrics::new()),
+        );
+
+        let summary = engine.run().await;
+
+        assert!(clock.elapsed() >= Duration::from_secs(1));
+        assert!(pipeline.max_unconfirmed.load(Ordering::SeqCst) <= 3);
+        assert_eq!(summary.confirmed, summary.submitted);
+        assert!(summary.confirmed >= 100);
+        assert!(summary.failed > 0);
+        assert_eq!(
+            summary.submitted + summary.failed,
+            pipeline.sent.load(Ordering::SeqCst) as u64
+        );
+        assert!(summary.confirmed_tps() > 0.0);
+    }
+
+    /// Pipeline accepting every transaction and never confirming any.
+    #[derive(Default)]
+    struct UnconfirmedPipeline {
+        sent: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl TxPipeline for UnconfirmedPipeline {
+        type Tx = usize;
+
+        async fn prepare(&self) -> Option<usize> {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            Some(self.sent.fetch_add(1, Ordering::SeqCst))
+        }
+
+        fn tx_type(_tx: &usize) -> &'static str {
+            "transfer"
+        }
+
+        async fn submit(&self, _tx: usize) -> Result<TxHash, ClientError> {
+            Ok(TxHash::default())
+        }
+
+        async fn wait_confirmed(
+            &self,
+            _tx_hash: TxHash,
+            _tx_type: &str,
+        ) -> Result<(), ClientError> {
+            std::future::pending().await
+        }
+    }
+
+    /// Tests that closed-loop users give up on confirmations after their timeout and stop
+    /// waiting for them once a shutdown is requested.
+    #[tokio::test]
+    async fn test_closed_loop_unconfirmed() {
+        let mut config = Config::default().general;
+        config.duration_secs = None;
+        config.load_mode = LoadMode::Closed;
+        config.virtual_users = 2;
+        let run = |confirmation: ConfirmationConfig| {
+            let shutdown = Shutdown::new();
+            let engine = Engine::with_throttler(
+                Arc::new(UnconfirmedPipeline::default()),
+                &config,
+                Throttler::with_clock(config.tps, SimulatedClock::new()),
+                Arc::new(RunRecorder::new()),
+                Arc::new(Metrics::new()),
+            )
+            .with_shutdown(shutdown.clone())
+            .with_confirmation(confirmation);
+            async move {
+                let stopper = tokio::spawn(async move {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    shutdown.request();
+                });
+                let summary = engine.run().await;
+                stopper.await.unwrap();
+                summary
+            }
+        };
+
+        let waiting = run(ConfirmationConfig::default()).await;
+        assert!(waiting.interrupted);
+        assert_eq!(waiting.submitted, 2);
+        assert_eq!(waiting.confirmed, 0);
+
+        let timing_out = run(ConfirmationConfig {
+            timeout_secs: 0,
+            ..ConfirmationConfig::default()
+        })
+        .await;
+        assert!(timing_out.submitted > 2);
+        assert_eq!(timing_out.confirmed, 0);
+    }
+
+    /// Pipeline counting the account maintenance it is asked for.
+    #[derive(Default)]
+    struct CollectingPipeline {
+        compactions: AtomicUsize,
+        resyncs: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl TxPipeline for CollectingPipeline {
+        type Tx = usize;
+
+        async fn prepare(&self) -> Option<usize> {
+            Some(0)
+        }
+
+        fn tx_type(_tx: &usize) -> &'static str {
+            "transfer"
+        }
+
+        async fn submit(&self, _tx: usize) -> Result<TxHash, ClientError> {
+            Ok(TxHash::default())
+        }
+
+        async fn collect_garbage(&self, action: AccountGcAction, _dust_threshold: &BigUint) {
+            match action {
+                AccountGcAction::Compact => self.compactions.fetch_add(1, Ordering::SeqCst),
+                AccountGcAction::Resync => self.resyncs.fetch_add(1, Ordering::SeqCst),
+            };
+        }
+    }
+
+    /// Tests that the engine asks the pipeline for the account maintenance that is due.
+    #[tokio::test]
+    async fn test_engine_account_gc() {
+        let mut config = Config::default().general;
+        config.tps = 10;
+        config.duration_secs = Some(1);
+        let pipeline = Arc::new(CollectingPipeline::default());
+        let engine = Engine::with_throttler(
+            pipeline.clone(),
+            &config,
+            Throttler::with_clock(config.tps, SimulatedClock::new()),
+            Arc::new(RunRecorder::new()),
+            Arc::new(Metrics::new()),
+        )
+        .with_account_gc(AccountGcConfig {
+            compaction_interval_secs: 0,
+            resync_interval_secs: 3_600,
+            dust_threshold: 0,
+        });
+
+        engine.run().await;
+
+        assert!(pipeline.compactions.load(Ordering::SeqCst) >= 5);
+        assert_eq!(pipeline.resyncs.load(Ordering::SeqCst), 0);
+    }
+}
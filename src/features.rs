@@ -0,0 +1,52 @@
+//! Optional subsystems compiled in by cargo features.
+//!
+//! All of them are in the default features. CI builds that only run the simulation can
+//! leave them out with `--no-default-features`, which drops their dependencies; a
+//! configuration asking for a subsystem left out of the build is rejected when it is
+//! validated, naming the feature to rebuild with.
+
+/// Subsystem behind a cargo feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    /// Progress bars of the setup phases, built on `indicatif`.
+    ProgressBars,
+    /// Run summary posted to a chat webhook, built on `reqwest`.
+    Webhook,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 2] = [Feature::ProgressBars, Feature::Webhook];
+
+    /// Name of the cargo feature.
+    pub fn name(self) -> &'static str {
+        match self {
+            Feature::ProgressBars => "progress-bars",
+            Feature::Webhook => "webhook",
+        }
+    }
+
+    /// Whether this build includes the subsystem.
+    pub fn is_enabled(self) -> bool {
+        match self {
+            Feature::ProgressBars => cfg!(feature = "progress-bars"),
+            Feature::Webhook => cfg!(feature = "webhook"),
+        }
+    }
+
+    /// Explains how to get the subsystem, for errors about settings that need it.
+    pub fn unavailable(self) -> String {
+        format!(
+            "requires the `{0}` feature, which this build was made without; rebuild with `cargo build --features {0}`",
+            self.name()
+        )
+    }
+}
+
+/// Names of the features this build includes, e.g. for `--version`.
+pub fn enabled() -> Vec<&'static str> {
+    Feature::ALL
+        .into_iter()
+        .filter(|feature| feature.is_enabled())
+        .map(Feature::name)
+        .collect()
+}
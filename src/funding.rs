@@ -0,0 +1,443 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use ethers::signers::{LocalWallet, WalletError};
+use ethers::types::{Address, H256, U256};
+use serde::Deserialize;
+use thiserror::Error;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::budget::{BudgetExceeded, PhaseBudget};
+use crate::progress::{PhaseProgress, SetupPhase};
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum FundingError {
+    #[error("L1 transfer failed: {0}")]
+    L1Transfer(String),
+    #[error("Deposit failed: {0}")]
+    Deposit(String),
+    #[error("Funding task aborted: {0}")]
+    Aborted(String),
+    #[error("Skipped once funding exceeded its {0}s budget")]
+    Skipped(u64),
+    #[error("{0}")]
+    OverBudget(#[from] BudgetExceeded),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FundingConfig {
+    /// Maximum number of accounts being funded at the same time.
+    #[serde(default = "FundingConfig::default_max_concurrency")]
+    pub max_concurrency: usize,
+    /// Amount sent from the master wallet to every account on L1, in wei.
+    #[serde(default = "FundingConfig::default_l1_amount_wei")]
+    pub l1_amount_wei: u64,
+    /// Part of the L1 amount every account deposits to the rollup, in wei.
+    #[serde(default = "FundingConfig::default_deposit_amount_wei")]
+    pub deposit_amount_wei: u64,
+    /// Private key of the master wallet paying for the funding, as hex.
+    #[serde(default)]
+    pub master_key: Option<String>,
+    /// ERC20 tokens sent and deposited to every account next to RBTC.
+    #[serde(default)]
+    pub erc20: Vec<Erc20Funding>,
+    /// Time budget of the whole funding phase and what to do when it is spent.
+    #[serde(flatten)]
+    pub budget: PhaseBudget,
+}
+
+impl FundingConfig {
+    fn default_max_concurrency() -> usize {
+        16
+    }
+
+    fn default_l1_amount_wei() -> u64 {
+        10_000_000_000_000_000
+    }
+
+    fn default_deposit_amount_wei() -> u64 {
+        5_000_000_000_000_000
+    }
+
+    /// Master wallet of `master_key`, when one is set.
+    pub fn master_wallet(&self) -> Result<Option<LocalWallet>, WalletError> {
+        self.master_key
+            .as_deref()
+            .map(|key| key.trim_start_matches("0x").parse())
+            .transpose()
+    }
+
+    /// Amounts every account receives, RBTC first so accounts hold gas for the ERC20 deposits.
+    pub fn assets(&self) -> Vec<AssetFunding> {
+        let mut assets = vec![AssetFunding {
+            asset: FundingAsset::Rbtc,
+            l1_amount: U256::from(self.l1_amount_wei),
+            deposit_amount: U256::from(self.deposit_amount_wei),
+        }];
+        assets.extend(self.erc20.iter().map(|erc20| AssetFunding {
+            asset: FundingAsset::Erc20 {
+                symbol: erc20.token.clone(),
+                address: erc20.address,
+            },
+            l1_amount: U256::from(erc20.l1_amount),
+            deposit_amount: U256::from(erc20.deposit_amount),
+        }));
+        assets
+    }
+}
+
+impl Default for FundingConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrency: Self::default_max_concurrency(),
+            l1_amount_wei: Self::default_l1_amount_wei(),
+            deposit_amount_wei: Self::default_deposit_amount_wei(),
+            master_key: None,
+            erc20: Vec::new(),
+            budget: PhaseBudget::default(),
+        }
+    }
+}
+
+/// ERC20 token of a `[[funding.erc20]]` table, amounts in its smallest units.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Erc20Funding {
+    /// Symbol of the token on the rollup.
+    pub token: String,
+    /// Contract of the token on L1.
+    pub address: Address,
+    #[serde(default = "Erc20Funding::default_decimals")]
+    pub decimals: u8,
+    pub l1_amount: u64,
+    pub deposit_amount: u64,
+}
+
+impl Erc20Funding {
+    fn default_decimals() -> u8 {
+        18
+    }
+}
+
+/// Asset the master wallet sends on L1.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FundingAsset {
+    Rbtc,
+    Erc20 { symbol: String, address: Address },
+}
+
+impl FundingAsset {
+    pub fn symbol(&self) -> &str {
+        match self {
+            Self::Rbtc => "RBTC",
+            Self::Erc20 { symbol, .. } => symbol,
+        }
+    }
+}
+
+/// Amount of one asset sent to an account and the part of it deposited to the rollup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetFunding {
+    pub asset: FundingAsset,
+    pub l1_amount: U256,
+    pub deposit_amount: U256,
+}
+
+/// Account that should receive L1 funds from the master wallet and deposit them to the rollup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FundingTarget {
+    pub address: Address,
+    pub assets: Vec<AssetFunding>,
+}
+
+/// L1 operations the orchestrator is built from.
+#[async_trait]
+pub trait FundingSteps: Send + Sync + 'static {
+    /// Next nonce of the master wallet.
+    async fn master_nonce(&self) -> Result<U256, FundingError>;
+
+    /// Broadcasts a transfer of `asset` from the master wallet with the given nonce, without waiting for it to be mined.
+    async fn send_l1_transfer(
+        &self,
+        nonce: U256,
+        asset: &FundingAsset,
+        to: Address,
+        amount: U256,
+    ) -> Result<H256, FundingError>;
+
+    /// Waits until the L1 transfer is mined.
+    async fn wait_l1_transfer(&self, tx_hash: H256) -> Result<(), FundingError>;
+
+    /// Deposits `amount` of `asset` of the account from L1 to the rollup and waits for acceptance.
+    async fn deposit(
+        &self,
+        from: Address,
+        asset: &FundingAsset,
+        amount: U256,
+    ) -> Result<(), FundingError>;
+}
+
+#[derive(Debug, Default)]
+pub struct FundingSummary {
+    pub funded: Vec<Address>,
+    pub failed: Vec<(Address, FundingError)>,
+    pub elapsed: Duration,
+}
+
+/// Funds accounts in parallel while keeping master wallet transactions in nonce order.
+///
+/// Master wallet transfers are broadcast one after another with consecutive nonces,
+/// so they can never collide. An account whose transfer of one of its assets fails is
+/// reported as failed as a whole. Waiting for them to be mined and the deposits that
+/// depend on them run concurrently, bounded by `max_concurrency`.
+pub struct FundingOrchestrator<S: FundingSteps> {
+    steps: Arc<S>,
+    max_concurrency: usize,
+    budget: PhaseBudget,
+    progress: PhaseProgress,
+}
+
+impl<S: FundingSteps> FundingOrchestrator<S> {
+    pub fn new(steps: S, config: &FundingConfig) -> Self {
+        Self {
+            steps: Arc::new(steps),
+            max_concurrency: config.max_concurrency.max(1),
+            budget: config.budget,
+            progress: PhaseProgress::hidden(),
+        }
+    }
+
+    /// Reports every funded or failed account to the given progress bar.
+    pub fn with_progress(mut self, progress: PhaseProgress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Funds the targets within the time budget of the phase.
+    ///
+    /// With the `skip` policy, accounts still being funded when the budget is spent are
+    /// reported as failed and the run goes on with the others.
+    pub async fn run(&self, targets: Vec<FundingTarget>) -> Result<FundingSummary, FundingError> {
+        let started = Instant::now();
+        let addresses: Vec<Address> = targets.iter().map(|target| target.address).collect();
+        let summary = Mutex::new(FundingSummary::default());
+        let done = || {
+            let summary = summary.lock().unwrap();
+            summary.funded.len() + summary.failed.len()
+        };
+
+        let completed = self
+            .budget
+            .run(
+                SetupPhase::Funding,
+                self.fund_all(targets, &summary),
+                || addresses.len() - done(),
+            )
+            .await
+            .inspect_err(|err| self.progress.abandon(err.to_string()))?;
+        if let Some(result) = completed {
+            result?;
+        }
+
+        let mut summary = summary.into_inner().unwrap();
+        if let Some(secs) = self.budget.time_budget_secs {
+            let skipped: Vec<Address> = addresses
+                .into_iter()
+                .filter(|address| {
+                    !summary.funded.contains(address)
+                        && !summary.failed.iter().any(|(failed, _)| failed == address)
+                })
+                .collect();
+            for address in skipped {
+                summary.failed.push((address, FundingError::Skipped(secs)));
+                self.progress.inc();
+            }
+        }
+        self.progress.finish();
+        summary.elapsed = started.elapsed();
+        Ok(summary)
+    }
+
+    async fn fund_all(
+        &self,
+        targets: Vec<FundingTarget>,
+        summary: &Mutex<FundingSummary>,
+    ) -> Result<(), FundingError> {
+        let semaphore = Arc::new(Semaphore::new(self.max_concurrency));
+        let mut tasks = JoinSet::new();
+        let mut nonce = self.steps.master_nonce().await?;
+
+        for target in targets {
+            let permit = semaphore
+                .clone()
+                .acquire_owned()
+                .await
+                .map_err(|err| FundingError::Aborted(err.to_string()))?;
+
+            // Broadcasting stays sequential, a rejected transfer does not consume the nonce.
+            let mut tx_hashes = Vec::with_capacity(target.assets.len());
+            let mut rejected = None;
+            for funding in &target.assets {
+                match self
+                    .steps
+                    .send_l1_transfer(nonce, &funding.asset, target.address, funding.l1_amount)
+                    .await
+                {
+                    Ok(tx_hash) => {
+                        tx_hashes.push(tx_hash);
+                        nonce += U256::one();
+                    }
+                    Err(err) => {
+                        rejected = Some(err);
+                        break;
+                    }
+                }
+            }
+            if let Some(err) = rejected {
+                summary.lock().unwrap().failed.push((target.address, err));
+                self.progress.inc();
+                continue;
+            }
+
+            let steps = self.steps.clone();
+            tasks.spawn(async move {
+                let _permit = permit;
+                let result = async {
+                    for tx_hash in tx_hashes {
+                        steps.wait_l1_transfer(tx_hash).await?;
+                    }
+                    for funding in &target.assets {
+                        if !funding.deposit_amount.is_zero() {
+                            steps
+                                .deposit(target.address, &funding.asset, funding.deposit_amount)
+                                .await?;
+                        }
+                    }
+                    Ok(())
+                }
+                .await;
+                (target.address, result)
+            });
+        }
+
+        while let Some(joined) = tasks.join_next().await {
+            let mut summary = summary.lock().unwrap();
+            match joined {
+                Ok((address, Ok(()))) => summary.funded.push(address),
+                Ok((address, Err(err))) => summary.failed.push((address, err)),
+                Err(err) => {
+                    self.progress.abandon(err.to_string());
+                    return Err(FundingError::Aborted(err.to_string()));
+                }
+            }
+            self.progress.inc();
+            if !summary.failed.is_empty() {
+                self.progress
+                    .set_message(format!("{} failed", summary.failed.len()));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingSteps {
+        nonces: Mutex<Vec<U256>>,
+        deposits: Mutex<Vec<String>>,
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl FundingSteps for RecordingSteps {
+        async fn master_nonce(&self) -> Result<U256, FundingError> {
+            Ok(U256::from(7))
+        }
+
+        async fn send_l1_transfer(
+            &self,
+            nonce: U256,
+            asset: &FundingAsset,
+            to: Address,
+            _amount: U256,
+        ) -> Result<H256, FundingError> {
+            if to == Address::repeat_byte(0xff) && *asset != FundingAsset::Rbtc {
+                return Err(FundingError::L1Transfer("rejected".to_string()));
+            }
+            self.nonces.lock().unwrap().push(nonce);
+            Ok(H256::from_low_u64_be(nonce.as_u64()))
+        }
+
+        async fn wait_l1_transfer(&self, _tx_hash: H256) -> Result<(), FundingError> {
+            let current = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(current, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn deposit(
+            &self,
+            _from: Address,
+            asset: &FundingAsset,
+            _amount: U256,
+        ) -> Result<(), FundingError> {
+            self.deposits
+                .lock()
+                .unwrap()
+                .push(asset.symbol().to_string());
+            Ok(())
+        }
+    }
+
+    /// Tests that master nonces are consecutive across the assets of all accounts, that an
+    /// account fails as a whole, and that concurrency stays bounded.
+    #[tokio::test]
+    async fn test_parallel_funding_nonce_order() {
+        let config = FundingConfig {
+            max_concurrency: 3,
+            erc20: vec![Erc20Funding {
+                token: "RDOC".to_string(),
+                address: Address::repeat_byte(0xd0),
+                decimals: 18,
+                l1_amount: 100,
+                deposit_amount: 0,
+            }],
+            ..Default::default()
+        };
+        let mut targets: Vec<FundingTarget> = (1..=10u64)
+            .map(|i| FundingTarget {
+                address: Address::from_low_u64_be(i),
+                assets: config.assets(),
+            })
+            .collect();
+        targets.insert(
+            3,
+            FundingTarget {
+                address: Address::repeat_byte(0xff),
+                assets: config.assets(),
+            },
+        );
+
+        let orchestrator = FundingOrchestrator::new(RecordingSteps::default(), &config);
+        let summary = orchestrator.run(targets).await.unwrap();
+
+        assert_eq!(summary.funded.len(), 10);
+        assert_eq!(summary.failed.len(), 1);
+        // The RBTC transfer of the failed account was broadcast before its ERC20 one was rejected.
+        let nonces = orchestrator.steps.nonces.lock().unwrap().clone();
+        let expected: Vec<U256> = (7..28u64).map(U256::from).collect();
+        assert_eq!(nonces, expected);
+        let deposits = orchestrator.steps.deposits.lock().unwrap().clone();
+        assert_eq!(deposits, vec!["RBTC"; 10]);
+        assert!(orchestrator.steps.max_in_flight.load(Ordering::SeqCst) <= 3);
+    }
+}
@@ -0,0 +1,242 @@
+//! Liveness and readiness probes for simulators deployed as a long-running service.
+//!
+//! `GET /healthz` answers as long as the process serves requests, `GET /readyz` whether the
+//! simulation is doing useful work: configuration loaded, rollup server reachable and a
+//! run in progress. Both return the state behind the answer as JSON.
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::report::now_ms;
+use crate::rollup::provider::Provider;
+
+/// Health endpoint, enabled by the `[health]` section.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HealthConfig {
+    /// Address `/healthz` and `/readyz` are served on.
+    #[serde(default = "HealthConfig::default_bind_address")]
+    pub bind_address: SocketAddr,
+    /// How often the rollup server is probed.
+    #[serde(default = "HealthConfig::default_probe_interval_secs")]
+    pub probe_interval_secs: u64,
+}
+
+impl HealthConfig {
+    fn default_bind_address() -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], 9897))
+    }
+
+    fn default_probe_interval_secs() -> u64 {
+        10
+    }
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            bind_address: Self::default_bind_address(),
+            probe_interval_secs: Self::default_probe_interval_secs(),
+        }
+    }
+}
+
+/// Stage of the run, as seen by the probes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    #[default]
+    Starting,
+    Running,
+    /// Interrupted, waiting for the transactions in flight.
+    Settling,
+    Finished,
+}
+
+/// Outcome of the last probe of the rollup server.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderStatus {
+    pub reachable: bool,
+    pub checked_at_ms: u128,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthState {
+    pub config_loaded: bool,
+    /// `None` until the first probe completed.
+    pub provider: Option<ProviderStatus>,
+    pub run: RunStatus,
+}
+
+impl HealthState {
+    /// Reasons the simulator is not ready, empty when it is.
+    pub fn not_ready(&self) -> Vec<&'static str> {
+        let mut reasons = Vec::new();
+        if !self.config_loaded {
+            reasons.push("configuration not loaded");
+        }
+        if !self
+            .provider
+            .as_ref()
+            .is_some_and(|provider| provider.reachable)
+        {
+            reasons.push("rollup server not reachable");
+        }
+        if self.run != RunStatus::Running {
+            reasons.push("no run in progress");
+        }
+        reasons
+    }
+}
+
+/// Health of the simulator, updated by the run and read by the probes.
+#[derive(Debug, Clone, Default)]
+pub struct Health {
+    state: Arc<Mutex<HealthState>>,
+}
+
+impl Health {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_config_loaded(&self) {
+        self.state.lock().unwrap().config_loaded = true;
+    }
+
+    pub fn set_run(&self, run: RunStatus) {
+        self.state.lock().unwrap().run = run;
+    }
+
+    pub fn set_provider(&self, result: Result<(), String>) {
+        self.state.lock().unwrap().provider = Some(ProviderStatus {
+            reachable: result.is_ok(),
+            checked_at_ms: now_ms(),
+            error: result.err(),
+        });
+    }
+
+    pub fn state(&self) -> HealthState {
+        self.state.lock().unwrap().clone()
+    }
+}
+
+/// Cheap request telling whether the rollup server answers.
+#[async_trait]
+pub trait ProviderProbe: Send + Sync {
+    async fn probe(&self) -> Result<(), String>;
+}
+
+#[async_trait]
+impl<P: Provider + Send + Sync> ProviderProbe for P {
+    async fn probe(&self) -> Result<(), String> {
+        self.contract_address()
+            .await
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+}
+
+/// Probes the rollup server every `interval` until the future is dropped.
+pub async fn watch_provider(probe: impl ProviderProbe, health: Health, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let result = probe.probe().await;
+        if let Err(err) = &result {
+            warn!("Rollup server not reachable: {}", err);
+        }
+        health.set_provider(result);
+    }
+}
+
+/// Serves `GET /healthz` and `GET /readyz` until the future is dropped.
+pub async fn serve(config: &HealthConfig, health: Health) -> Result<(), hyper::Error> {
+    let make_service = make_service_fn(move |_| {
+        let health = health.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let response = handle(&health, &request);
+                async move { Ok::<_, Infallible>(response) }
+            }))
+        }
+    });
+
+    Server::bind(&config.bind_address).serve(make_service).await
+}
+
+fn handle(health: &Health, request: &Request<Body>) -> Response<Body> {
+    let state = health.state();
+    let (status, body) = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/healthz") => (StatusCode::OK, serde_json::json!({ "state": state })),
+        (&Method::GET, "/readyz") => {
+            let reasons = state.not_ready();
+            let status = if reasons.is_empty() {
+                StatusCode::OK
+            } else {
+                StatusCode::SERVICE_UNAVAILABLE
+            };
+            (
+                status,
+                serde_json::json!({ "state": state, "notReady": reasons }),
+            )
+        }
+        _ => (
+            StatusCode::NOT_FOUND,
+            serde_json::json!({ "error": "not found" }),
+        ),
+    };
+    let mut response = Response::new(Body::from(body.to_string()));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get(health: &Health, path: &str) -> StatusCode {
+        let request = Request::get(path).body(Body::empty()).unwrap();
+        handle(health, &request).status()
+    }
+
+    /// Tests that readiness follows the configuration, the rollup server and the run.
+    #[test]
+    fn test_probes() {
+        let health = Health::new();
+        assert_eq!(get(&health, "/healthz"), StatusCode::OK);
+        assert_eq!(get(&health, "/readyz"), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(health.state().not_ready().len(), 3);
+
+        health.set_config_loaded();
+        health.set_provider(Ok(()));
+        health.set_run(RunStatus::Running);
+        assert_eq!(get(&health, "/readyz"), StatusCode::OK);
+
+        health.set_provider(Err("connection refused".to_string()));
+        assert_eq!(
+            health.state().not_ready(),
+            vec!["rollup server not reachable"]
+        );
+        assert_eq!(get(&health, "/readyz"), StatusCode::SERVICE_UNAVAILABLE);
+
+        health.set_run(RunStatus::Finished);
+        assert_eq!(get(&health, "/healthz"), StatusCode::OK);
+        assert_eq!(get(&health, "/readyz"), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(get(&health, "/unknown"), StatusCode::NOT_FOUND);
+    }
+}
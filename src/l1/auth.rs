@@ -0,0 +1,91 @@
+use async_trait::async_trait;
+use ethers::abi::Token as AbiToken;
+use ethers::providers::Middleware;
+use ethers::signers::Signer;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, Bytes, TransactionRequest, U256};
+
+use super::deposit::calldata;
+use crate::activation::OnchainAuthorizer;
+use crate::rollup::provider::{ClientError, Provider, ResponseResult};
+use crate::rollup::types::pubkey_hash::PubKeyHash;
+use crate::rollup::types::Nonce;
+use crate::wallet::Wallet;
+
+/// `setAuthPubkeyHash(bytes _pubkeyHash, uint32 _nonce)`
+pub fn set_auth_pubkey_hash_calldata(pub_key_hash: &PubKeyHash, nonce: Nonce) -> Bytes {
+    calldata(
+        "setAuthPubkeyHash(bytes,uint32)",
+        &[
+            AbiToken::Bytes(pub_key_hash.data.to_vec()),
+            AbiToken::Uint(U256::from(nonce.0)),
+        ],
+    )
+}
+
+/// Registers signing keys on the main contract for `Onchain` authorized `ChangePubKey`s.
+///
+/// The contract only takes the key of the caller, so every call is signed with the
+/// Rootstock key of the account and pays its gas from the account's L1 balance.
+pub struct L1Authorizer<'a, P, M> {
+    provider: &'a P,
+    l1: &'a M,
+}
+
+impl<'a, P: Provider + Sync, M: Middleware> L1Authorizer<'a, P, M> {
+    pub fn new(provider: &'a P, l1: &'a M) -> Self {
+        Self { provider, l1 }
+    }
+
+    async fn main_contract(&self) -> ResponseResult<Address> {
+        let contract = self.provider.contract_address().await?.main_contract;
+        contract.parse().map_err(|_| ClientError::IncorrectAddress)
+    }
+}
+
+fn l1_error(err: impl ToString) -> ClientError {
+    ClientError::NetworkError(format!("L1 request failed: {}", err.to_string()))
+}
+
+#[async_trait]
+impl<'a, P: Provider + Sync, M: Middleware> OnchainAuthorizer for L1Authorizer<'a, P, M> {
+    async fn authorize(&self, wallet: &Wallet, nonce: Nonce) -> ResponseResult<()> {
+        let contract = self.main_contract().await?;
+        let chain_id = self.l1.get_chainid().await.map_err(l1_error)?.as_u64();
+        let mut tx: TypedTransaction = TransactionRequest::new()
+            .from(wallet.eth_signer().address())
+            .to(contract)
+            .data(set_auth_pubkey_hash_calldata(&wallet.pub_key_hash(), nonce))
+            .chain_id(chain_id)
+            .into();
+        self.l1
+            .fill_transaction(&mut tx, None)
+            .await
+            .map_err(l1_error)?;
+        let signature = wallet
+            .eth_signer()
+            .clone()
+            .with_chain_id(chain_id)
+            .sign_transaction(&tx)
+            .await
+            .map_err(|err| ClientError::SigningError(err.to_string()))?;
+
+        let pending = self
+            .l1
+            .send_raw_transaction(tx.rlp_signed(&signature))
+            .await
+            .map_err(l1_error)?;
+        let tx_hash = pending.tx_hash();
+        let receipt = pending
+            .await
+            .map_err(l1_error)?
+            .ok_or_else(|| l1_error(format!("transaction {:?} was dropped", tx_hash)))?;
+        if receipt.status != Some(1.into()) {
+            return Err(l1_error(format!(
+                "setAuthPubkeyHash {:?} reverted",
+                tx_hash
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,313 @@
+use ethers::abi::{self, ParamType, Token as AbiToken};
+use ethers::providers::Middleware;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, Bytes, Log, TransactionReceipt, TransactionRequest, H256, U256};
+use ethers::utils::{id, keccak256};
+use thiserror::Error;
+
+use super::ethop_poll::{EthOpPollConfig, EthOpPoller, EthOpStage, EthOpWaitError};
+use super::nonce::L1TxSender;
+use super::revert::DepositRevert;
+use crate::rollup::provider::{ClientError, Provider};
+use crate::rollup::types::{EthOpInfo, Token};
+use crate::tagging::RunTag;
+
+/// Largest amount `depositERC20` accepts, the contract takes a `uint104`.
+const MAX_ERC20_DEPOSIT_BITS: usize = 104;
+
+#[derive(Debug, Error)]
+pub enum DepositError {
+    #[error("Unable to query the rollup: {0}")]
+    Provider(#[from] ClientError),
+    #[error("L1 request failed: {0}")]
+    L1(String),
+    #[error("Malformed main contract address '{0}'")]
+    ContractAddress(String),
+    #[error("Deposit amount {0} does not fit into uint104")]
+    AmountTooLarge(U256),
+    #[error("Token approval {0:?} reverted")]
+    ApprovalReverted(H256),
+    #[error("Deposit {tx_hash:?} reverted: {revert:?}")]
+    Reverted {
+        tx_hash: H256,
+        revert: Box<DepositRevert>,
+    },
+    #[error("Deposit {0:?} did not emit a priority request")]
+    MissingPriorityRequest(H256),
+    #[error("Deposit was not accepted by the rollup: {0}")]
+    Wait(#[from] EthOpWaitError),
+}
+
+/// Deposit accepted by the rollup.
+#[derive(Debug, Clone)]
+pub struct CompletedDeposit {
+    pub tx_hash: H256,
+    pub serial_id: u64,
+    pub receipt: TransactionReceipt,
+    pub ethop: EthOpInfo,
+}
+
+impl CompletedDeposit {
+    /// Gas used by the deposit and the price paid for it, zero when the node left them out.
+    pub fn gas_cost(&self) -> (U256, U256) {
+        (
+            self.receipt.gas_used.unwrap_or_default(),
+            self.receipt.effective_gas_price.unwrap_or_default(),
+        )
+    }
+}
+
+/// Moves funds from L1 to the rollup by calling the main contract on Rootstock.
+///
+/// RBTC is sent with `depositRBTC`; ERC20 tokens are first approved for the main
+/// contract when its allowance is too low and then moved with `depositERC20`. A deposit
+/// is only done once the rollup executed the priority operation it created, so funds
+/// can be spent on L2 right after `deposit` returns.
+pub struct L1Depositor<'a, P, M> {
+    provider: &'a P,
+    l1: &'a M,
+    poll_config: EthOpPollConfig,
+    nonces: Option<&'a dyn L1TxSender>,
+    tag: Option<&'a RunTag>,
+}
+
+impl<'a, P: Provider + Sync, M: Middleware> L1Depositor<'a, P, M> {
+    pub fn new(provider: &'a P, l1: &'a M, poll_config: EthOpPollConfig) -> Self {
+        Self {
+            provider,
+            l1,
+            poll_config,
+            nonces: None,
+            tag: None,
+        }
+    }
+
+    /// Appends the tag of the run to the deposit calldata, which the contract ignores.
+    pub fn with_run_tag(mut self, tag: &'a RunTag) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    /// Sends the L1 transactions through the nonce manager of the middleware's sender, so
+    /// deposits do not collide with other setup phases sending from the same wallet.
+    pub fn with_nonce_manager(mut self, nonces: &'a dyn L1TxSender) -> Self {
+        self.nonces = Some(nonces);
+        self
+    }
+
+    /// Deposits `amount` of `token` from the L1 sender of the middleware to the rollup account `to`.
+    pub async fn deposit(
+        &self,
+        token: &Token,
+        amount: U256,
+        to: Address,
+    ) -> Result<CompletedDeposit, DepositError> {
+        let contract = self.main_contract().await?;
+        let (request, data) = if token.address.is_zero() {
+            let request = TransactionRequest::new().to(contract).value(amount);
+            (request, deposit_rbtc_calldata(to))
+        } else {
+            self.ensure_allowance(token.address, contract, amount)
+                .await?;
+            let request = TransactionRequest::new().to(contract);
+            (request, deposit_erc20_calldata(token.address, amount, to)?)
+        };
+        let request = match self.tag {
+            Some(tag) => request.data(tag.tag_calldata(&data)),
+            None => request.data(data),
+        };
+
+        let receipt = self.send(request).await?;
+        let tx_hash = receipt.transaction_hash;
+        if receipt.status != Some(1.into()) {
+            let revert = Box::new(DepositRevert::diagnose(None, receipt.gas_used, None));
+            return Err(DepositError::Reverted { tx_hash, revert });
+        }
+        let serial_id = priority_request_serial_id(&receipt.logs)
+            .ok_or(DepositError::MissingPriorityRequest(tx_hash))?;
+
+        let ethop = EthOpPoller::new(self.provider, self.l1, self.poll_config.clone())
+            .wait(serial_id as u32, EthOpStage::Executed)
+            .await?;
+        Ok(CompletedDeposit {
+            tx_hash,
+            serial_id,
+            receipt,
+            ethop,
+        })
+    }
+
+    async fn main_contract(&self) -> Result<Address, DepositError> {
+        let contract = self.provider.contract_address().await?.main_contract;
+        contract
+            .parse()
+            .map_err(|_| DepositError::ContractAddress(contract))
+    }
+
+    /// Approves the main contract to spend `amount` of the token unless it already may.
+    async fn ensure_allowance(
+        &self,
+        token: Address,
+        contract: Address,
+        amount: U256,
+    ) -> Result<(), DepositError> {
+        let owner = self.sender()?;
+        let call = TransactionRequest::new()
+            .to(token)
+            .data(calldata(
+                "allowance(address,address)",
+                &[AbiToken::Address(owner), AbiToken::Address(contract)],
+            ))
+            .into();
+        let allowance = self
+            .l1
+            .call(&call, None)
+            .await
+            .map_err(|err| DepositError::L1(err.to_string()))?;
+        if U256::from_big_endian(&allowance) >= amount {
+            return Ok(());
+        }
+
+        let approve = TransactionRequest::new()
+            .to(token)
+            .data(erc20_approve_calldata(contract, amount));
+        let receipt = self.send(approve).await?;
+        if receipt.status != Some(1.into()) {
+            return Err(DepositError::ApprovalReverted(receipt.transaction_hash));
+        }
+        Ok(())
+    }
+
+    fn sender(&self) -> Result<Address, DepositError> {
+        self.l1
+            .default_sender()
+            .ok_or_else(|| DepositError::L1("the L1 middleware has no sender".to_string()))
+    }
+
+    async fn send(&self, request: TransactionRequest) -> Result<TransactionReceipt, DepositError> {
+        let request = request.from(self.sender()?);
+        let tx: TypedTransaction = request.into();
+        if let Some(nonces) = self.nonces {
+            return nonces
+                .send_and_wait(tx)
+                .await
+                .map_err(|err| DepositError::L1(err.to_string()));
+        }
+        let pending = self
+            .l1
+            .send_transaction(tx, None)
+            .await
+            .map_err(|err| DepositError::L1(err.to_string()))?;
+        let tx_hash = pending.tx_hash();
+        pending
+            .await
+            .map_err(|err| DepositError::L1(err.to_string()))?
+            .ok_or_else(|| DepositError::L1(format!("transaction {:?} was dropped", tx_hash)))
+    }
+}
+
+pub(super) fn calldata(signature: &str, args: &[AbiToken]) -> Bytes {
+    let mut data = id(signature).to_vec();
+    data.extend(abi::encode(args));
+    data.into()
+}
+
+/// `depositRBTC(address _zkSyncAddress)`
+pub fn deposit_rbtc_calldata(to: Address) -> Bytes {
+    calldata("depositRBTC(address)", &[AbiToken::Address(to)])
+}
+
+/// `depositERC20(address _token, uint104 _amount, address _zkSyncAddress)`
+pub fn deposit_erc20_calldata(
+    token: Address,
+    amount: U256,
+    to: Address,
+) -> Result<Bytes, DepositError> {
+    if amount.bits() > MAX_ERC20_DEPOSIT_BITS {
+        return Err(DepositError::AmountTooLarge(amount));
+    }
+    Ok(calldata(
+        "depositERC20(address,uint104,address)",
+        &[
+            AbiToken::Address(token),
+            AbiToken::Uint(amount),
+            AbiToken::Address(to),
+        ],
+    ))
+}
+
+fn erc20_approve_calldata(spender: Address, amount: U256) -> Bytes {
+    calldata(
+        "approve(address,uint256)",
+        &[AbiToken::Address(spender), AbiToken::Uint(amount)],
+    )
+}
+
+/// Serial id of the priority operation created by a deposit, from its `NewPriorityRequest` event.
+pub fn priority_request_serial_id(logs: &[Log]) -> Option<u64> {
+    let topic = H256::from(keccak256(
+        "NewPriorityRequest(address,uint64,uint8,bytes,uint256)",
+    ));
+    let params = [
+        ParamType::Address,
+        ParamType::Uint(64),
+        ParamType::Uint(8),
+        ParamType::Bytes,
+        ParamType::Uint(256),
+    ];
+    logs.iter()
+        .filter(|log| log.topics.first() == Some(&topic))
+        .find_map(|log| match abi::decode(&params, &log.data).ok()?.get(1)? {
+            AbiToken::Uint(serial_id) => Some(serial_id.as_u64()),
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests deposit calldata encoding and reading the serial id of the created priority operation.
+    #[test]
+    fn test_deposit_calldata_and_priority_request() {
+        let to = Address::from_low_u64_be(0xa11ce);
+        let token = Address::from_low_u64_be(0xd0c);
+
+        let rbtc = deposit_rbtc_calldata(to);
+        assert_eq!(rbtc[..4], id("depositRBTC(address)"));
+        assert_eq!(rbtc.len(), 4 + 32);
+        assert_eq!(rbtc[16..36], to.0);
+
+        let erc20 = deposit_erc20_calldata(token, U256::from(1_000), to).unwrap();
+        assert_eq!(erc20.len(), 4 + 3 * 32);
+        assert_eq!(U256::from_big_endian(&erc20[36..68]), U256::from(1_000));
+        assert!(matches!(
+            deposit_erc20_calldata(token, U256::one() << 104, to),
+            Err(DepositError::AmountTooLarge(_))
+        ));
+
+        let event = Log {
+            topics: vec![H256::from(keccak256(
+                "NewPriorityRequest(address,uint64,uint8,bytes,uint256)",
+            ))],
+            data: abi::encode(&[
+                AbiToken::Address(to),
+                AbiToken::Uint(42.into()),
+                AbiToken::Uint(1.into()),
+                AbiToken::Bytes(vec![1, 2, 3]),
+                AbiToken::Uint(1_000.into()),
+            ])
+            .into(),
+            ..Default::default()
+        };
+        let unrelated = Log {
+            topics: vec![H256::from(keccak256("Transfer(address,address,uint256)"))],
+            ..Default::default()
+        };
+        assert_eq!(
+            priority_request_serial_id(&[unrelated.clone(), event]),
+            Some(42)
+        );
+        assert_eq!(priority_request_serial_id(&[unrelated]), None);
+    }
+}
@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use ethers::providers::Middleware;
+use ethers::types::U64;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::rollup::provider::{ClientError, Provider};
+use crate::rollup::types::EthOpInfo;
+
+#[derive(Debug, Error)]
+pub enum EthOpWaitError {
+    #[error("Unable to query priority operation: {0}")]
+    Provider(#[from] ClientError),
+    #[error("Unable to query L1 block number: {0}")]
+    L1(String),
+    #[error("Priority operation {serial_id} not processed within {blocks} L1 blocks")]
+    Timeout { serial_id: u32, blocks: u64 },
+}
+
+/// Polling of priority operations, configured in the `[network.ethop_poll]` section.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EthOpPollConfig {
+    /// How often the L1 block number is checked, in milliseconds.
+    #[serde(default = "EthOpPollConfig::default_block_poll_interval_ms")]
+    pub block_poll_interval_ms: u64,
+    /// Number of new L1 blocks after which waiting for an operation is given up.
+    #[serde(default = "EthOpPollConfig::default_max_blocks")]
+    pub max_blocks: u64,
+}
+
+impl EthOpPollConfig {
+    fn default_block_poll_interval_ms() -> u64 {
+        2_000
+    }
+
+    fn default_max_blocks() -> u64 {
+        40
+    }
+}
+
+impl Default for EthOpPollConfig {
+    fn default() -> Self {
+        Self {
+            block_poll_interval_ms: Self::default_block_poll_interval_ms(),
+            max_blocks: Self::default_max_blocks(),
+        }
+    }
+}
+
+/// Stage of a priority operation the poller waits for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EthOpStage {
+    Executed,
+    Verified,
+}
+
+impl EthOpStage {
+    fn is_reached(self, info: &EthOpInfo) -> bool {
+        match self {
+            EthOpStage::Executed => info.executed,
+            EthOpStage::Verified => info.is_verified(),
+        }
+    }
+}
+
+/// Waits for priority operations by re-checking `ethop_info` only once a new L1 block was produced.
+///
+/// The rollup can only pick up a priority operation from a newer L1 block, so querying
+/// it between Rootstock blocks (~30s apart) cannot change the answer. Checking the block
+/// number is a single cheap call against the L1 node.
+pub struct EthOpPoller<'a, P, M> {
+    provider: &'a P,
+    l1: &'a M,
+    config: EthOpPollConfig,
+}
+
+impl<'a, P: Provider + Sync, M: Middleware> EthOpPoller<'a, P, M> {
+    pub fn new(provider: &'a P, l1: &'a M, config: EthOpPollConfig) -> Self {
+        Self {
+            provider,
+            l1,
+            config,
+        }
+    }
+
+    /// Waits until the priority operation reaches the given stage.
+    pub async fn wait(
+        &self,
+        serial_id: u32,
+        stage: EthOpStage,
+    ) -> Result<EthOpInfo, EthOpWaitError> {
+        let interval = Duration::from_millis(self.config.block_poll_interval_ms);
+        let first_block = self.block_number().await?;
+        let mut checked_block = first_block;
+
+        let info = self.provider.ethop_info(serial_id).await?;
+        if stage.is_reached(&info) {
+            return Ok(info);
+        }
+
+        loop {
+            tokio::time::sleep(interval).await;
+            let block = self.block_number().await?;
+            if block <= checked_block {
+                continue;
+            }
+            checked_block = block;
+
+            let info = self.provider.ethop_info(serial_id).await?;
+            if stage.is_reached(&info) {
+                return Ok(info);
+            }
+            let waited = (block - first_block).as_u64();
+            if waited >= self.config.max_blocks {
+                return Err(EthOpWaitError::Timeout {
+                    serial_id,
+                    blocks: waited,
+                });
+            }
+        }
+    }
+
+    async fn block_number(&self) -> Result<U64, EthOpWaitError> {
+        self.l1
+            .get_block_number()
+            .await
+            .map_err(|err| EthOpWaitError::L1(err.to_string()))
+    }
+}
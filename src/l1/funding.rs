@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use ethers::abi::Token as AbiToken;
+use ethers::providers::Middleware;
+use ethers::signers::LocalWallet;
+use ethers::types::{Address, BlockNumber, Bytes, TransactionRequest, H256, U256};
+
+use super::deposit::{calldata, DepositError, L1Depositor};
+use super::node::{L1Node, L1Signer};
+use super::nonce::L1NonceManager;
+use super::revert::DepositRevertStats;
+use crate::funding::{FundingAsset, FundingError, FundingSteps};
+use crate::rollup::provider::Provider;
+use crate::rollup::tokens::TokenRegistry;
+use crate::rollup::types::TokenLike;
+
+/// How often a broadcast L1 transfer is checked for its receipt.
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// `FundingSteps` sending from the master wallet on Rootstock and depositing from the
+/// accounts' own keys.
+///
+/// The master wallet sends every account its L1 funds; each account then deposits part of
+/// them to the rollup itself, so the deposit is credited to the account that paid for it.
+/// The L1 transactions of a deposit go through the nonce manager of the depositing wallet.
+pub struct L1FundingSteps<P> {
+    provider: Arc<P>,
+    node: L1Node,
+    master: L1Signer,
+    depositors: HashMap<Address, (L1Signer, L1NonceManager<L1Signer>)>,
+    tokens: TokenRegistry,
+    reverts: Arc<Mutex<DepositRevertStats>>,
+}
+
+impl<P: Provider + Send + Sync + 'static> L1FundingSteps<P> {
+    pub fn new(
+        provider: Arc<P>,
+        node: L1Node,
+        master: &LocalWallet,
+        tokens: TokenRegistry,
+    ) -> Self {
+        Self {
+            provider,
+            master: node.signer(master),
+            node,
+            depositors: HashMap::new(),
+            tokens,
+            reverts: Arc::default(),
+        }
+    }
+
+    /// Reverted deposits by kind, shared so they can be read once the steps are handed over.
+    pub fn reverts(&self) -> Arc<Mutex<DepositRevertStats>> {
+        self.reverts.clone()
+    }
+
+    /// Deposits the funds of the rollup account `address` with the L1 key of `wallet`.
+    pub fn with_depositor(mut self, address: Address, wallet: LocalWallet) -> Self {
+        let depositor = (self.node.signer(&wallet), self.node.nonce_manager(&wallet));
+        self.depositors.insert(address, depositor);
+        self
+    }
+}
+
+#[async_trait]
+impl<P: Provider + Send + Sync + 'static> FundingSteps for L1FundingSteps<P> {
+    async fn master_nonce(&self) -> Result<U256, FundingError> {
+        self.master
+            .get_transaction_count(self.master.address(), Some(BlockNumber::Pending.into()))
+            .await
+            .map_err(|err| FundingError::L1Transfer(err.to_string()))
+    }
+
+    async fn send_l1_transfer(
+        &self,
+        nonce: U256,
+        asset: &FundingAsset,
+        to: Address,
+        amount: U256,
+    ) -> Result<H256, FundingError> {
+        let request = match asset {
+            FundingAsset::Rbtc => TransactionRequest::new().to(to).value(amount),
+            FundingAsset::Erc20 { address, .. } => TransactionRequest::new()
+                .to(*address)
+                .data(erc20_transfer_calldata(to, amount)),
+        }
+        .from(self.master.address())
+        .nonce(nonce);
+        let pending = self
+            .master
+            .send_transaction(request, None)
+            .await
+            .map_err(|err| FundingError::L1Transfer(err.to_string()))?;
+        Ok(pending.tx_hash())
+    }
+
+    async fn wait_l1_transfer(&self, tx_hash: H256) -> Result<(), FundingError> {
+        loop {
+            let receipt = self
+                .node
+                .provider()
+                .get_transaction_receipt(tx_hash)
+                .await
+                .map_err(|err| FundingError::L1Transfer(err.to_string()))?;
+            match receipt {
+                Some(receipt) if receipt.status == Some(1.into()) => return Ok(()),
+                Some(_) => {
+                    return Err(FundingError::L1Transfer(format!(
+                        "transfer {:?} reverted",
+                        tx_hash
+                    )))
+                }
+                None => tokio::time::sleep(RECEIPT_POLL_INTERVAL).await,
+            }
+        }
+    }
+
+    async fn deposit(
+        &self,
+        from: Address,
+        asset: &FundingAsset,
+        amount: U256,
+    ) -> Result<(), FundingError> {
+        let (l1, nonces) = self
+            .depositors
+            .get(&from)
+            .ok_or_else(|| FundingError::Deposit(format!("no L1 key of account {:?}", from)))?;
+        let token = self
+            .tokens
+            .get(&TokenLike::Symbol(asset.symbol().to_string()))
+            .ok_or_else(|| FundingError::Deposit(format!("unknown token {}", asset.symbol())))?;
+        L1Depositor::new(&*self.provider, l1, self.node.poll_config().clone())
+            .with_nonce_manager(nonces)
+            .deposit(token, amount, from)
+            .await
+            .map_err(|err| {
+                if let DepositError::Reverted { revert, .. } = &err {
+                    self.reverts.lock().unwrap().record(revert);
+                }
+                FundingError::Deposit(err.to_string())
+            })?;
+        Ok(())
+    }
+}
+
+/// `transfer(address _to, uint256 _value)`
+pub fn erc20_transfer_calldata(to: Address, amount: U256) -> Bytes {
+    calldata(
+        "transfer(address,uint256)",
+        &[AbiToken::Address(to), AbiToken::Uint(amount)],
+    )
+}
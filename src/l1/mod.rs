@@ -0,0 +1,7 @@
+pub mod auth;
+pub mod deposit;
+pub mod ethop_poll;
+pub mod funding;
+pub mod node;
+pub mod nonce;
+pub mod revert;
@@ -0,0 +1,63 @@
+use std::str::FromStr;
+
+use ethers::middleware::SignerMiddleware;
+use ethers::providers::{Http, Provider};
+use ethers::signers::{LocalWallet, Signer};
+
+use super::ethop_poll::EthOpPollConfig;
+use super::nonce::{L1NonceConfig, L1NonceManager};
+use crate::config::NetworkConfig;
+use crate::rollup::provider::ClientError;
+
+/// L1 middleware signing with the key of a single wallet.
+pub type L1Signer = SignerMiddleware<Provider<Http>, LocalWallet>;
+
+/// Rootstock node of the network, shared by everything sending L1 transactions.
+#[derive(Debug, Clone)]
+pub struct L1Node {
+    provider: Provider<Http>,
+    chain_id: u64,
+    poll_config: EthOpPollConfig,
+    nonce_config: L1NonceConfig,
+}
+
+impl L1Node {
+    /// Node at `network.rpc_url`, signing for the chain id of `network.chain`.
+    pub fn connect(config: &NetworkConfig) -> Result<Self, ClientError> {
+        let url = config.rpc_url();
+        let http = Http::from_str(url).map_err(|err| {
+            ClientError::NetworkError(format!("invalid Rootstock node URL '{}': {}", url, err))
+        })?;
+        Ok(Self {
+            provider: Provider::new(http),
+            chain_id: config.chain.chain_id(),
+            poll_config: config.ethop_poll.clone(),
+            nonce_config: config.l1_nonce.clone(),
+        })
+    }
+
+    pub fn provider(&self) -> &Provider<Http> {
+        &self.provider
+    }
+
+    /// Polling of the priority operations L1 transactions create on the rollup.
+    pub fn poll_config(&self) -> &EthOpPollConfig {
+        &self.poll_config
+    }
+
+    /// Middleware sending L1 transactions signed by `wallet`.
+    pub fn signer(&self, wallet: &LocalWallet) -> L1Signer {
+        let wallet = wallet.clone().with_chain_id(self.chain_id);
+        SignerMiddleware::new(self.provider.clone(), wallet)
+    }
+
+    /// Nonce manager of the L1 transactions signed by `wallet`, there should be a single one
+    /// per wallet.
+    pub fn nonce_manager(&self, wallet: &LocalWallet) -> L1NonceManager<L1Signer> {
+        L1NonceManager::new(
+            self.signer(wallet),
+            wallet.address(),
+            self.nonce_config.clone(),
+        )
+    }
+}
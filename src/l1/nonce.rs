@@ -0,0 +1,574 @@
+use std::collections::btree_map::{BTreeMap, Entry};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use ethers::providers::Middleware;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, TransactionReceipt, TransactionRequest, H256, U256};
+use serde::Deserialize;
+use thiserror::Error;
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+use crate::clock::{Clock, SystemClock};
+
+/// Gas limit of a plain value transfer, used by the transactions replacing stuck ones.
+const TRANSFER_GAS: u64 = 21_000;
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum L1NonceError {
+    #[error("L1 request failed: {0}")]
+    L1(String),
+    #[error("Transaction with nonce {nonce} rejected: {reason}")]
+    Rejected { nonce: U256, reason: String },
+    #[error("No transaction with nonce {0} is pending")]
+    Unknown(U256),
+    #[error("Nonce {0} was used by a transaction sent by someone else")]
+    Replaced(U256),
+    #[error("Transaction with nonce {0} was stuck and cancelled by an empty transfer")]
+    Cancelled(U256),
+    #[error("Transaction with nonce {nonce} still not mined after {bumps} gas price bumps")]
+    Stuck { nonce: U256, bumps: u32 },
+}
+
+/// Nonces of the master wallet on L1, configured in the `[network.l1_nonce]` section.
+#[derive(Debug, Clone, Deserialize)]
+pub struct L1NonceConfig {
+    /// How often pending transactions are checked for a receipt, in milliseconds.
+    #[serde(default = "L1NonceConfig::default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    /// Time without a receipt after which the gas price of a transaction is bumped, in seconds.
+    #[serde(default = "L1NonceConfig::default_stuck_after_secs")]
+    pub stuck_after_secs: u64,
+    /// Increase of the gas price per bump, in percent; nodes refuse replacements below 10.
+    #[serde(default = "L1NonceConfig::default_gas_bump_percent")]
+    pub gas_bump_percent: u64,
+    /// Bumps after which a transaction is replaced by an empty transfer to free its nonce.
+    #[serde(default = "L1NonceConfig::default_max_bumps")]
+    pub max_bumps: u32,
+}
+
+impl L1NonceConfig {
+    fn default_poll_interval_ms() -> u64 {
+        2_000
+    }
+
+    fn default_stuck_after_secs() -> u64 {
+        90
+    }
+
+    fn default_gas_bump_percent() -> u64 {
+        20
+    }
+
+    fn default_max_bumps() -> u32 {
+        3
+    }
+}
+
+impl Default for L1NonceConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_ms: Self::default_poll_interval_ms(),
+            stuck_after_secs: Self::default_stuck_after_secs(),
+            gas_bump_percent: Self::default_gas_bump_percent(),
+            max_bumps: Self::default_max_bumps(),
+        }
+    }
+}
+
+/// L1 requests the nonce manager needs, implemented by every ethers `Middleware`.
+#[async_trait]
+pub trait L1Transactions: Send + Sync {
+    /// Number of transactions sent from `address`, counting the ones in the mempool when `pending`.
+    async fn transaction_count(&self, address: Address, pending: bool) -> Result<U256, String>;
+
+    async fn gas_price(&self) -> Result<U256, String>;
+
+    /// Broadcasts a transaction with nonce and gas price set, without waiting for it to be mined.
+    async fn broadcast(&self, tx: TypedTransaction) -> Result<H256, String>;
+
+    async fn receipt(&self, tx_hash: H256) -> Result<Option<TransactionReceipt>, String>;
+}
+
+#[async_trait]
+impl<M: Middleware> L1Transactions for M {
+    async fn transaction_count(&self, address: Address, pending: bool) -> Result<U256, String> {
+        let block = if pending {
+            ethers::types::BlockNumber::Pending
+        } else {
+            ethers::types::BlockNumber::Latest
+        };
+        self.get_transaction_count(address, Some(block.into()))
+            .await
+            .map_err(|err| err.to_string())
+    }
+
+    async fn gas_price(&self) -> Result<U256, String> {
+        self.get_gas_price().await.map_err(|err| err.to_string())
+    }
+
+    async fn broadcast(&self, tx: TypedTransaction) -> Result<H256, String> {
+        self.send_transaction(tx, None)
+            .await
+            .map(|pending| pending.tx_hash())
+            .map_err(|err| err.to_string())
+    }
+
+    async fn receipt(&self, tx_hash: H256) -> Result<Option<TransactionReceipt>, String> {
+        self.get_transaction_receipt(tx_hash)
+            .await
+            .map_err(|err| err.to_string())
+    }
+}
+
+/// Transaction of the master wallet accepted by the L1 node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SentL1Tx {
+    pub nonce: U256,
+    pub tx_hash: H256,
+}
+
+#[derive(Debug)]
+struct PendingL1Tx {
+    tx: TypedTransaction,
+    /// Hashes of every version broadcast with this nonce, the latest last.
+    hashes: Vec<H256>,
+    gas_price: U256,
+    sent_at: Instant,
+    bumps: u32,
+    /// Whether the transaction was replaced by an empty transfer.
+    cancelled: bool,
+}
+
+#[derive(Debug, Default)]
+struct NonceState {
+    /// Nonce of the next transaction, read from the node when unknown.
+    next: Option<U256>,
+    pending: BTreeMap<U256, PendingL1Tx>,
+}
+
+/// Hands out nonces of the master wallet to funding, deposits and on-chain `ChangePubKey`.
+///
+/// Setup phases send from the master wallet concurrently; reading the nonce from the node
+/// for every transaction lets two of them pick the same one, and the loser then waits
+/// forever for a transaction that was never accepted. The manager assigns nonces under a
+/// lock and only consumes one when the node accepted the transaction. Transactions without
+/// a receipt for `stuck_after_secs` are re-broadcast with a higher gas price, and after
+/// `max_bumps` replaced by an empty transfer, so one underpriced transaction can not hold
+/// back all that follow it.
+pub struct L1NonceManager<S, C: Clock = SystemClock> {
+    l1: S,
+    address: Address,
+    config: L1NonceConfig,
+    clock: C,
+    state: Mutex<NonceState>,
+}
+
+impl<S: L1Transactions> L1NonceManager<S> {
+    pub fn new(l1: S, address: Address, config: L1NonceConfig) -> Self {
+        Self::with_clock(l1, address, config, SystemClock)
+    }
+}
+
+impl<S: L1Transactions, C: Clock> L1NonceManager<S, C> {
+    pub fn with_clock(l1: S, address: Address, config: L1NonceConfig, clock: C) -> Self {
+        Self {
+            l1,
+            address,
+            config,
+            clock,
+            state: Mutex::new(NonceState::default()),
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// Number of sent transactions without a receipt yet.
+    pub async fn pending(&self) -> usize {
+        self.state.lock().await.pending.len()
+    }
+
+    /// Sends the transaction with the next nonce of the master wallet and the current gas
+    /// price unless it has one.
+    ///
+    /// A rejected transaction does not consume its nonce, the next one is read from the node
+    /// again in case it was rejected because the nonce was used elsewhere.
+    pub async fn send(&self, mut tx: TypedTransaction) -> Result<SentL1Tx, L1NonceError> {
+        let mut state = self.state.lock().await;
+        let nonce = match state.next {
+            Some(nonce) => nonce,
+            None => self.sync_nonce(&state).await?,
+        };
+        let gas_price = match tx.gas_price() {
+            Some(gas_price) => gas_price,
+            None => self.l1.gas_price().await.map_err(L1NonceError::L1)?,
+        };
+        tx.set_from(self.address);
+        tx.set_nonce(nonce);
+        tx.set_gas_price(gas_price);
+
+        match self.l1.broadcast(tx.clone()).await {
+            Ok(tx_hash) => {
+                state.next = Some(nonce + 1);
+                state.pending.insert(
+                    nonce,
+                    PendingL1Tx {
+                        tx,
+                        hashes: vec![tx_hash],
+                        gas_price,
+                        sent_at: self.clock.now(),
+                        bumps: 0,
+                        cancelled: false,
+                    },
+                );
+                Ok(SentL1Tx { nonce, tx_hash })
+            }
+            Err(reason) => {
+                state.next = None;
+                Err(L1NonceError::Rejected { nonce, reason })
+            }
+        }
+    }
+
+    /// Waits until a transaction sent with `nonce` is mined, bumping its gas price while it is stuck.
+    pub async fn wait(&self, nonce: U256) -> Result<TransactionReceipt, L1NonceError> {
+        let interval = Duration::from_millis(self.config.poll_interval_ms);
+        loop {
+            if let Some(receipt) = self.find_receipt(nonce).await? {
+                return self.finish(nonce, receipt).await;
+            }
+            let mined = self
+                .l1
+                .transaction_count(self.address, false)
+                .await
+                .map_err(L1NonceError::L1)?;
+            if mined > nonce {
+                // Mined between the two requests, or by a transaction we do not know about.
+                if let Some(receipt) = self.find_receipt(nonce).await? {
+                    return self.finish(nonce, receipt).await;
+                }
+                self.state.lock().await.pending.remove(&nonce);
+                return Err(L1NonceError::Replaced(nonce));
+            }
+            self.bump_if_stuck(nonce).await?;
+            self.clock.sleep(interval).await;
+        }
+    }
+
+    /// Replaces transactions of the master wallet left in the mempool by someone else, such
+    /// as an interrupted earlier run, with empty transfers so new ones are not queued
+    /// behind them. Returns the number of replaced transactions.
+    pub async fn replace_stuck(&self) -> Result<usize, L1NonceError> {
+        let mut state = self.state.lock().await;
+        let mined = self
+            .l1
+            .transaction_count(self.address, false)
+            .await
+            .map_err(L1NonceError::L1)?;
+        let queued = self
+            .l1
+            .transaction_count(self.address, true)
+            .await
+            .map_err(L1NonceError::L1)?;
+        let gas_price = self.bumped(self.l1.gas_price().await.map_err(L1NonceError::L1)?);
+
+        let mut replaced = 0;
+        let mut nonce = mined;
+        while nonce < queued {
+            if let Entry::Vacant(entry) = state.pending.entry(nonce) {
+                let cancel = self.cancellation(nonce, gas_price);
+                let tx_hash = self
+                    .l1
+                    .broadcast(cancel.clone())
+                    .await
+                    .map_err(|reason| L1NonceError::Rejected { nonce, reason })?;
+                warn!("Replaced stuck L1 transaction with nonce {}", nonce);
+                entry.insert(PendingL1Tx {
+                    tx: cancel,
+                    hashes: vec![tx_hash],
+                    gas_price,
+                    sent_at: self.clock.now(),
+                    bumps: 0,
+                    cancelled: true,
+                });
+                replaced += 1;
+            }
+            nonce += U256::one();
+        }
+        state.next = state.next.max(Some(queued));
+        Ok(replaced)
+    }
+
+    /// Next nonce according to the node, never below the nonces already handed out.
+    async fn sync_nonce(&self, state: &NonceState) -> Result<U256, L1NonceError> {
+        let queued = self
+            .l1
+            .transaction_count(self.address, true)
+            .await
+            .map_err(L1NonceError::L1)?;
+        let after_pending = state
+            .pending
+            .keys()
+            .next_back()
+            .map(|nonce| nonce + 1)
+            .unwrap_or_default();
+        Ok(queued.max(after_pending))
+    }
+
+    async fn find_receipt(&self, nonce: U256) -> Result<Option<TransactionReceipt>, L1NonceError> {
+        let hashes = match self.state.lock().await.pending.get(&nonce) {
+            Some(pending) => pending.hashes.clone(),
+            None => return Err(L1NonceError::Unknown(nonce)),
+        };
+        for tx_hash in hashes.iter().rev() {
+            if let Some(receipt) = self.l1.receipt(*tx_hash).await.map_err(L1NonceError::L1)? {
+                return Ok(Some(receipt));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn finish(
+        &self,
+        nonce: U256,
+        receipt: TransactionReceipt,
+    ) -> Result<TransactionReceipt, L1NonceError> {
+        let pending = self.state.lock().await.pending.remove(&nonce);
+        match pending {
+            Some(pending) if pending.cancelled => Err(L1NonceError::Cancelled(nonce)),
+            _ => Ok(receipt),
+        }
+    }
+
+    /// Re-broadcasts the transaction with a higher gas price once it waited `stuck_after_secs`.
+    async fn bump_if_stuck(&self, nonce: U256) -> Result<(), L1NonceError> {
+        let mut state = self.state.lock().await;
+        let Some(pending) = state.pending.get_mut(&nonce) else {
+            return Err(L1NonceError::Unknown(nonce));
+        };
+        let stuck_after = Duration::from_secs(self.config.stuck_after_secs);
+        if self.clock.elapsed_since(pending.sent_at) < stuck_after {
+            return Ok(());
+        }
+        if pending.bumps >= self.config.max_bumps && pending.cancelled {
+            return Err(L1NonceError::Stuck {
+                nonce,
+                bumps: pending.bumps,
+            });
+        }
+
+        let network_price = self.l1.gas_price().await.map_err(L1NonceError::L1)?;
+        let gas_price = self.bumped(pending.gas_price).max(network_price);
+        let tx = if pending.bumps >= self.config.max_bumps {
+            warn!(
+                "L1 transaction with nonce {} stuck after {} bumps, cancelling it",
+                nonce, pending.bumps
+            );
+            pending.cancelled = true;
+            pending.bumps = 0;
+            self.cancellation(nonce, gas_price)
+        } else {
+            pending.bumps += 1;
+            let mut tx = pending.tx.clone();
+            tx.set_gas_price(gas_price);
+            tx
+        };
+        pending.sent_at = self.clock.now();
+        match self.l1.broadcast(tx.clone()).await {
+            Ok(tx_hash) => {
+                debug!("Bumped gas price of nonce {} to {}", nonce, gas_price);
+                pending.tx = tx;
+                pending.gas_price = gas_price;
+                pending.hashes.push(tx_hash);
+            }
+            // Usually the previous version was mined meanwhile, the next poll finds its receipt.
+            Err(reason) => debug!("Replacement of nonce {} rejected: {}", nonce, reason),
+        }
+        Ok(())
+    }
+
+    fn bumped(&self, gas_price: U256) -> U256 {
+        gas_price * (100 + self.config.gas_bump_percent) / 100 + 1
+    }
+
+    /// Empty transfer of the master wallet to itself, taking over the nonce of a stuck transaction.
+    fn cancellation(&self, nonce: U256, gas_price: U256) -> TypedTransaction {
+        TransactionRequest::new()
+            .from(self.address)
+            .to(self.address)
+            .value(0)
+            .gas(TRANSFER_GAS)
+            .gas_price(gas_price)
+            .nonce(nonce)
+            .into()
+    }
+}
+
+/// Sender of master wallet transactions, lets L1 helpers share one nonce manager whatever its node type.
+#[async_trait]
+pub trait L1TxSender: Send + Sync {
+    /// Sends the transaction with the next nonce and waits until it is mined.
+    async fn send_and_wait(&self, tx: TypedTransaction)
+        -> Result<TransactionReceipt, L1NonceError>;
+}
+
+#[async_trait]
+impl<S: L1Transactions, C: Clock> L1TxSender for L1NonceManager<S, C> {
+    async fn send_and_wait(
+        &self,
+        tx: TypedTransaction,
+    ) -> Result<TransactionReceipt, L1NonceError> {
+        let sent = self.send(tx).await?;
+        self.wait(sent.nonce).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex as StdMutex};
+
+    use super::*;
+    use crate::clock::SimulatedClock;
+
+    /// Node mining the queued transaction with the lowest nonce once it pays at least `min_gas_price`.
+    #[derive(Default)]
+    struct MockNode {
+        min_gas_price: U256,
+        mined: StdMutex<U256>,
+        /// Latest broadcast hash and gas price per nonce.
+        mempool: StdMutex<BTreeMap<U256, (H256, U256)>>,
+        receipts: StdMutex<HashMap<H256, TransactionReceipt>>,
+        broadcasts: StdMutex<Vec<(U256, U256, bool)>>,
+    }
+
+    impl MockNode {
+        fn mine(&self) {
+            let mut mined = self.mined.lock().unwrap();
+            let mut mempool = self.mempool.lock().unwrap();
+            while let Some((&nonce, &(tx_hash, gas_price))) = mempool.first_key_value() {
+                if nonce != *mined || gas_price < self.min_gas_price {
+                    break;
+                }
+                mempool.remove(&nonce);
+                let receipt = TransactionReceipt {
+                    transaction_hash: tx_hash,
+                    ..Default::default()
+                };
+                self.receipts.lock().unwrap().insert(tx_hash, receipt);
+                *mined += U256::one();
+            }
+        }
+    }
+
+    #[async_trait]
+    impl L1Transactions for MockNode {
+        async fn transaction_count(&self, _: Address, pending: bool) -> Result<U256, String> {
+            self.mine();
+            let mined = *self.mined.lock().unwrap();
+            let queued = self.mempool.lock().unwrap().len();
+            Ok(if pending { mined + queued } else { mined })
+        }
+
+        async fn gas_price(&self) -> Result<U256, String> {
+            Ok(U256::from(100))
+        }
+
+        async fn broadcast(&self, tx: TypedTransaction) -> Result<H256, String> {
+            let nonce = *tx.nonce().unwrap();
+            let gas_price = tx.gas_price().unwrap();
+            if nonce < *self.mined.lock().unwrap() {
+                return Err("nonce too low".to_string());
+            }
+            let empty = tx.value().is_none_or(U256::is_zero) && tx.to_addr() == tx.from();
+            self.broadcasts
+                .lock()
+                .unwrap()
+                .push((nonce, gas_price, empty));
+            let tx_hash = H256::from_low_u64_be((nonce.as_u64() << 32) | gas_price.as_u64());
+            self.mempool
+                .lock()
+                .unwrap()
+                .insert(nonce, (tx_hash, gas_price));
+            Ok(tx_hash)
+        }
+
+        async fn receipt(&self, tx_hash: H256) -> Result<Option<TransactionReceipt>, String> {
+            self.mine();
+            Ok(self.receipts.lock().unwrap().get(&tx_hash).cloned())
+        }
+    }
+
+    fn transfer(value: u64) -> TypedTransaction {
+        TransactionRequest::new()
+            .to(Address::from_low_u64_be(value))
+            .value(value)
+            .into()
+    }
+
+    /// Tests consecutive nonces for concurrent senders, replacement of foreign stuck
+    /// transactions and gas price bumps of underpriced ones.
+    #[tokio::test]
+    async fn test_nonce_manager() {
+        let master = Address::repeat_byte(0x11);
+        let node = MockNode {
+            min_gas_price: U256::from(130),
+            ..Default::default()
+        };
+        *node.mined.lock().unwrap() = U256::from(5);
+        // Left behind by an earlier run with a gas price the node never mines.
+        node.mempool
+            .lock()
+            .unwrap()
+            .insert(U256::from(5), (H256::repeat_byte(5), U256::from(1)));
+
+        let config = L1NonceConfig {
+            poll_interval_ms: 1_000,
+            stuck_after_secs: 10,
+            gas_bump_percent: 20,
+            max_bumps: 3,
+        };
+        let manager = Arc::new(L1NonceManager::with_clock(
+            node,
+            master,
+            config,
+            SimulatedClock::new(),
+        ));
+        assert_eq!(manager.replace_stuck().await.unwrap(), 1);
+        assert_eq!(
+            manager.wait(U256::from(5)).await,
+            Err(L1NonceError::Cancelled(U256::from(5)))
+        );
+
+        let mut sends = tokio::task::JoinSet::new();
+        for value in 1..=3 {
+            let manager = manager.clone();
+            sends.spawn(async move { manager.send(transfer(value)).await.unwrap().nonce });
+        }
+        let mut nonces = Vec::new();
+        while let Some(nonce) = sends.join_next().await {
+            nonces.push(nonce.unwrap().as_u64());
+        }
+        nonces.sort();
+        assert_eq!(nonces, vec![6, 7, 8]);
+
+        // 100 wei is below what the node mines, one bump to 121 is not enough, the second is.
+        for nonce in 6..=8u64 {
+            manager.wait(U256::from(nonce)).await.unwrap();
+        }
+        assert_eq!(manager.pending().await, 0);
+        let broadcasts = manager.l1.broadcasts.lock().unwrap();
+        let nonce_6: Vec<u64> = broadcasts
+            .iter()
+            .filter(|(nonce, _, _)| *nonce == U256::from(6))
+            .map(|(_, gas_price, _)| gas_price.as_u64())
+            .collect();
+        assert_eq!(nonce_6, vec![100, 121, 146]);
+        assert!(broadcasts
+            .iter()
+            .all(|(nonce, _, empty)| *empty == (*nonce == U256::from(5))));
+    }
+}
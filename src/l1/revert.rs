@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+
+use ethers::abi::{self, ParamType, Token};
+use ethers::types::U256;
+use serde::Serialize;
+
+/// Selector of `Error(string)`, used by `require` and `revert` with a message.
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+/// Selector of `Panic(uint256)`, used by failed asserts and arithmetic errors.
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Why an L1 deposit was reverted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DepositRevertKind {
+    /// ERC20 allowance of the main contract is lower than the deposited amount.
+    InsufficientAllowance,
+    /// Depositor does not hold the deposited amount.
+    InsufficientBalance,
+    /// The rollup contract is in exodus mode or otherwise paused.
+    ContractPaused,
+    /// Deposits of the token are paused by the governance.
+    TokenPaused,
+    /// Deposit amount or priority queue limit exceeded.
+    LimitExceeded,
+    /// Transaction ran out of gas, the gas limit is too low.
+    OutOfGas,
+    /// Reverted with a reason not known to the simulator.
+    Other,
+    /// Reverted without any revert data.
+    Unknown,
+}
+
+/// Decodes the revert reason from the return data of a failed call.
+pub fn decode_revert_reason(data: &[u8]) -> Option<String> {
+    if data.len() < 4 {
+        return None;
+    }
+    let (selector, payload) = data.split_at(4);
+    if selector == ERROR_SELECTOR {
+        match abi::decode(&[ParamType::String], payload).ok()?.pop()? {
+            Token::String(reason) => Some(reason),
+            _ => None,
+        }
+    } else if selector == PANIC_SELECTOR {
+        match abi::decode(&[ParamType::Uint(256)], payload).ok()?.pop()? {
+            Token::Uint(code) => Some(format!("panic code {:#x}", code)),
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
+/// Maps a revert reason to its kind.
+///
+/// The rollup contract reverts with short codes, ERC20 tokens with full sentences.
+pub fn classify_revert_reason(reason: &str) -> DepositRevertKind {
+    let lowercase = reason.to_lowercase();
+    match reason {
+        "L" => DepositRevertKind::ContractPaused,
+        "b" => DepositRevertKind::TokenPaused,
+        "C" | "e" => DepositRevertKind::LimitExceeded,
+        "c" => DepositRevertKind::InsufficientAllowance,
+        _ if lowercase.contains("allowance") => DepositRevertKind::InsufficientAllowance,
+        _ if lowercase.contains("exceeds balance")
+            || lowercase.contains("insufficient balance") =>
+        {
+            DepositRevertKind::InsufficientBalance
+        }
+        _ if lowercase.contains("paused") || lowercase.contains("exodus") => {
+            DepositRevertKind::ContractPaused
+        }
+        _ if lowercase.contains("limit") || lowercase.contains("exceed") => {
+            DepositRevertKind::LimitExceeded
+        }
+        _ => DepositRevertKind::Other,
+    }
+}
+
+/// Diagnosis of a single reverted deposit.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DepositRevert {
+    pub kind: DepositRevertKind,
+    pub reason: Option<String>,
+    pub gas_used: Option<U256>,
+    pub gas_limit: Option<U256>,
+}
+
+impl DepositRevert {
+    /// Diagnoses a revert from the call return data and the gas figures of the receipt.
+    pub fn diagnose(
+        revert_data: Option<&[u8]>,
+        gas_used: Option<U256>,
+        gas_limit: Option<U256>,
+    ) -> Self {
+        let reason = revert_data.and_then(decode_revert_reason);
+        let out_of_gas =
+            matches!((gas_used, gas_limit), (Some(used), Some(limit)) if used >= limit);
+        let kind = match &reason {
+            Some(reason) => classify_revert_reason(reason),
+            None if out_of_gas => DepositRevertKind::OutOfGas,
+            None => DepositRevertKind::Unknown,
+        };
+
+        Self {
+            kind,
+            reason,
+            gas_used,
+            gas_limit,
+        }
+    }
+}
+
+/// Per-kind counts of reverted deposits, keeping every distinct reason seen.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DepositRevertStats {
+    pub counts: BTreeMap<DepositRevertKind, u64>,
+    pub reasons: BTreeMap<String, u64>,
+}
+
+impl DepositRevertStats {
+    pub fn record(&mut self, revert: &DepositRevert) {
+        *self.counts.entry(revert.kind).or_default() += 1;
+        if let Some(reason) = &revert.reason {
+            *self.reasons.entry(reason.clone()).or_default() += 1;
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.values().sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn error_data(reason: &str) -> Vec<u8> {
+        let mut data = ERROR_SELECTOR.to_vec();
+        data.extend(abi::encode(&[Token::String(reason.to_string())]));
+        data
+    }
+
+    /// Tests that revert reasons are decoded and classified.
+    #[test]
+    fn test_deposit_revert_diagnosis() {
+        let revert = DepositRevert::diagnose(
+            Some(&error_data("ERC20: insufficient allowance")),
+            None,
+            None,
+        );
+        assert_eq!(revert.kind, DepositRevertKind::InsufficientAllowance);
+        assert_eq!(
+            revert.reason.as_deref(),
+            Some("ERC20: insufficient allowance")
+        );
+
+        let revert = DepositRevert::diagnose(Some(&error_data("L")), None, None);
+        assert_eq!(revert.kind, DepositRevertKind::ContractPaused);
+
+        let revert =
+            DepositRevert::diagnose(None, Some(U256::from(100_000)), Some(U256::from(100_000)));
+        assert_eq!(revert.kind, DepositRevertKind::OutOfGas);
+
+        let mut stats = DepositRevertStats::default();
+        stats.record(&revert);
+        stats.record(&DepositRevert::diagnose(None, None, None));
+        assert_eq!(stats.total(), 2);
+        assert_eq!(stats.counts[&DepositRevertKind::Unknown], 1);
+    }
+}
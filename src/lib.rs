@@ -0,0 +1,38 @@
+pub mod accounts;
+pub mod activation;
+pub mod audit;
+pub mod budget;
+pub mod capture;
+pub mod chaos;
+pub mod checkpoint;
+pub mod cli;
+pub mod clock;
+pub mod config;
+pub mod config_migration;
+pub mod control;
+pub mod dry_run;
+pub mod engine;
+pub mod features;
+pub mod funding;
+pub mod health;
+pub mod l1;
+pub mod load_profile;
+pub mod logging;
+pub mod metrics;
+pub mod misbehavior;
+pub mod paths;
+pub mod progress;
+pub mod rate_control;
+pub mod report;
+pub mod resubmission;
+pub mod rng;
+pub mod rollup;
+pub mod scenario;
+pub mod shutdown;
+pub mod sponsor;
+pub mod submission;
+pub mod tagging;
+pub mod throttler;
+pub mod tls;
+pub mod transaction;
+pub mod wallet;
@@ -0,0 +1,199 @@
+use std::f64::consts::PI;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// Target rate of a run over time.
+pub trait LoadProfile: Send + Sync {
+    /// Transactions per second wanted `elapsed` after the start of the run.
+    fn tps_at(&self, elapsed: Duration) -> f64;
+}
+
+/// Same rate during the whole run.
+#[derive(Debug, Clone, Copy)]
+pub struct ConstantLoad {
+    pub tps: f64,
+}
+
+impl LoadProfile for ConstantLoad {
+    fn tps_at(&self, _elapsed: Duration) -> f64 {
+        self.tps
+    }
+}
+
+/// Rate growing linearly from `start_tps` to `tps` over `ramp`, constant afterwards.
+#[derive(Debug, Clone, Copy)]
+pub struct RampLoad {
+    pub start_tps: f64,
+    pub tps: f64,
+    pub ramp: Duration,
+}
+
+impl LoadProfile for RampLoad {
+    fn tps_at(&self, elapsed: Duration) -> f64 {
+        if elapsed >= self.ramp {
+            return self.tps;
+        }
+        let progress = elapsed.as_secs_f64() / self.ramp.as_secs_f64();
+        self.start_tps + (self.tps - self.start_tps) * progress
+    }
+}
+
+/// Square wave: `tps` during the first `burst` of every `period`, `base_tps` for the rest.
+#[derive(Debug, Clone, Copy)]
+pub struct BurstLoad {
+    pub base_tps: f64,
+    pub tps: f64,
+    pub period: Duration,
+    pub burst: Duration,
+}
+
+impl LoadProfile for BurstLoad {
+    fn tps_at(&self, elapsed: Duration) -> f64 {
+        let in_period = elapsed.as_secs_f64() % self.period.as_secs_f64().max(f64::EPSILON);
+        if in_period < self.burst.as_secs_f64() {
+            self.tps
+        } else {
+            self.base_tps
+        }
+    }
+}
+
+/// Rate oscillating between `min_tps` and `tps`, starting at the minimum.
+#[derive(Debug, Clone, Copy)]
+pub struct SineLoad {
+    pub min_tps: f64,
+    pub tps: f64,
+    pub period: Duration,
+}
+
+impl LoadProfile for SineLoad {
+    fn tps_at(&self, elapsed: Duration) -> f64 {
+        let phase = 2.0 * PI * elapsed.as_secs_f64() / self.period.as_secs_f64().max(f64::EPSILON);
+        let amplitude = (self.tps - self.min_tps) / 2.0;
+        self.min_tps + amplitude * (1.0 - phase.cos())
+    }
+}
+
+/// Spikes of `txs` transactions sent within `within_ms` every `every_secs`, on top of the
+/// load profile, configured with `burst` in the `[general]` section.
+///
+/// The first burst comes `every_secs` into the run, so the rollup has settled at the base
+/// rate before it.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct BurstConfig {
+    pub txs: u32,
+    #[serde(default = "BurstConfig::default_within_ms")]
+    pub within_ms: u64,
+    #[serde(default = "BurstConfig::default_every_secs")]
+    pub every_secs: u64,
+}
+
+impl BurstConfig {
+    fn default_within_ms() -> u64 {
+        1_000
+    }
+
+    fn default_every_secs() -> u64 {
+        60
+    }
+
+    fn within(&self) -> Duration {
+        Duration::from_millis(self.within_ms)
+    }
+
+    /// Offset from the start of the run at which the burst `index` starts.
+    pub fn start(&self, index: u64) -> Duration {
+        Duration::from_secs(self.every_secs.saturating_mul(index + 1))
+    }
+
+    /// Index of the burst `elapsed` falls in, `None` between bursts.
+    pub fn window(&self, elapsed: Duration) -> Option<u64> {
+        let periods = elapsed.as_secs().checked_div(self.every_secs)?;
+        let index = periods.checked_sub(1)?;
+        (elapsed - self.start(index) < self.within()).then_some(index)
+    }
+}
+
+/// Load profile with bursts added on top of it.
+pub struct WithBursts {
+    pub base: Box<dyn LoadProfile>,
+    pub bursts: BurstConfig,
+}
+
+impl LoadProfile for WithBursts {
+    fn tps_at(&self, elapsed: Duration) -> f64 {
+        let base = self.base.tps_at(elapsed);
+        match self.bursts.window(elapsed) {
+            Some(_) => base + self.bursts.txs as f64 / self.bursts.within().as_secs_f64(),
+            None => base,
+        }
+    }
+}
+
+/// The `load_profile` of the `[general]` section, `tps` is the peak rate of every shape.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(tag = "shape", rename_all = "snake_case")]
+pub enum LoadShape {
+    #[default]
+    Constant,
+    Ramp {
+        start_tps: u32,
+        ramp_secs: u64,
+    },
+    Burst {
+        base_tps: u32,
+        period_secs: u64,
+        burst_secs: u64,
+    },
+    Sine {
+        min_tps: u32,
+        period_secs: u64,
+    },
+}
+
+impl LoadShape {
+    /// Profile of the shape peaking at `tps`.
+    pub fn profile(self, tps: u32) -> Box<dyn LoadProfile> {
+        let tps = tps as f64;
+        match self {
+            LoadShape::Constant => Box::new(ConstantLoad { tps }),
+            LoadShape::Ramp {
+                start_tps,
+                ramp_secs,
+            } => Box::new(RampLoad {
+                start_tps: start_tps as f64,
+                tps,
+                ramp: Duration::from_secs(ramp_secs),
+            }),
+            LoadShape::Burst {
+                base_tps,
+                period_secs,
+                burst_secs,
+            } => Box::new(BurstLoad {
+                base_tps: base_tps as f64,
+                tps,
+                period: Duration::from_secs(period_secs),
+                burst: Duration::from_secs(burst_secs),
+            }),
+            LoadShape::Sine {
+                min_tps,
+                period_secs,
+            } => Box::new(SineLoad {
+                min_tps: min_tps as f64,
+                tps,
+                period: Duration::from_secs(period_secs),
+            }),
+        }
+    }
+
+    /// Lowest rate of the shape, which must not exceed the peak.
+    pub fn min_tps(self) -> Option<u32> {
+        match self {
+            LoadShape::Constant => None,
+            LoadShape::Ramp { start_tps, .. } => Some(start_tps),
+            LoadShape::Burst { base_tps, .. } => Some(base_tps),
+            LoadShape::Sine { min_tps, .. } => Some(min_tps),
+        }
+    }
+}
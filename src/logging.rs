@@ -0,0 +1,95 @@
+//! Logging through `tracing`: human readable events on stderr and, when enabled, every
+//! event as a JSON line in the report directory of the run for post-run analysis.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use tracing::Level;
+use tracing_subscriber::filter::{filter_fn, LevelFilter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, Layer};
+
+use crate::paths;
+
+/// Name of the JSON log in the report directory of a run.
+pub const LOG_FILE: &str = "log.jsonl";
+
+/// Level shown on stderr for the number of `-v` flags: info, debug with `-v`, trace with `-vv`.
+pub fn verbosity_level(verbosity: u8) -> Level {
+    match verbosity {
+        0 => Level::INFO,
+        1 => Level::DEBUG,
+        _ => Level::TRACE,
+    }
+}
+
+/// JSON log of the run, attached once its report directory is known.
+///
+/// Events logged before a file is attached only reach stderr.
+#[derive(Debug, Clone, Default)]
+pub struct RunLog {
+    file: Arc<Mutex<Option<File>>>,
+}
+
+impl RunLog {
+    /// Starts writing the JSON log to `path`, appending to the log of a resumed run.
+    pub fn attach(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        *self.file.lock().unwrap() = Some(paths::open_private(path, true)?);
+        Ok(())
+    }
+
+    fn is_attached(&self) -> bool {
+        self.file.lock().unwrap().is_some()
+    }
+}
+
+impl Write for RunLog {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.file.lock().unwrap().as_mut() {
+            Some(file) => file.write(buf),
+            None => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.file.lock().unwrap().as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Installs the global subscriber, returning the handle the JSON log is attached through.
+///
+/// The JSON log records per-transaction events at debug level whatever the verbosity,
+/// so a quiet terminal still leaves a complete log behind.
+pub fn init(verbosity: u8) -> RunLog {
+    let level = verbosity_level(verbosity);
+    let run_log = RunLog::default();
+
+    let stderr = fmt::layer()
+        .with_writer(io::stderr)
+        .with_target(false)
+        .with_filter(LevelFilter::from_level(level));
+    let json_level = level.max(Level::DEBUG);
+    let attached = run_log.clone();
+    let writer = run_log.clone();
+    let json = fmt::layer()
+        .json()
+        .with_current_span(true)
+        .with_span_list(true)
+        .with_writer(move || writer.clone())
+        .with_filter(filter_fn(move |metadata| {
+            *metadata.level() <= json_level && attached.is_attached()
+        }));
+
+    // A subscriber installed earlier, e.g. by a test harness, keeps receiving the events.
+    let _ = tracing_subscriber::registry()
+        .with(stderr)
+        .with(json)
+        .try_init();
+    run_log
+}
@@ -1,15 +1,8 @@
-
-
-use crate::cli::Cli;
-
-pub mod cli;
-pub mod config;
-pub mod transaction;
-pub mod throttler;
-pub mod rollup;
+use clap::Parser;
+use simulation_tool::cli::Cli;
 
 fn main() {
-    let cli = Cli::new();
+    let cli = Cli::parse();
 
     cli.run();
 }
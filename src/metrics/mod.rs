@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+
+pub mod pipeline;
+pub mod profile;
+pub mod prometheus;
+
+use self::pipeline::PipelineGauges;
+use self::profile::StageProfile;
+
+/// Metric name together with its label values.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MetricKey {
+    pub name: &'static str,
+    pub labels: Vec<(&'static str, String)>,
+}
+
+impl MetricKey {
+    pub fn new(name: &'static str, labels: &[(&'static str, &str)]) -> Self {
+        Self {
+            name,
+            labels: labels
+                .iter()
+                .map(|(label, value)| (*label, value.to_string()))
+                .collect(),
+        }
+    }
+}
+
+/// Formats the key in the Prometheus notation, e.g. `provider_timeouts_total{method="send_tx"}`.
+impl fmt::Display for MetricKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.labels.is_empty() {
+            let labels: Vec<String> = self
+                .labels
+                .iter()
+                .map(|(label, value)| format!("{}=\"{}\"", label, value))
+                .collect();
+            write!(f, "{{{}}}", labels.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// Upper bounds of the latency histogram buckets, in seconds.
+///
+/// Submission latencies fall into the lower buckets, confirmation latencies span
+/// block times up to the verification of a block.
+pub const LATENCY_BUCKETS_SECS: [f64; 17] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0,
+    1800.0,
+];
+
+/// Latency distribution in the fixed `LATENCY_BUCKETS_SECS` buckets.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Histogram {
+    /// Observations per bucket, not cumulative; the last entry counts values above every bound.
+    pub buckets: [u64; LATENCY_BUCKETS_SECS.len() + 1],
+    pub sum_secs: f64,
+    pub count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: Duration) {
+        let secs = value.as_secs_f64();
+        let bucket = LATENCY_BUCKETS_SECS
+            .iter()
+            .position(|bound| secs <= *bound)
+            .unwrap_or(LATENCY_BUCKETS_SECS.len());
+        self.buckets[bucket] += 1;
+        self.sum_secs += secs;
+        self.count += 1;
+    }
+}
+
+/// Counters, histograms and gauges shared between all simulator tasks.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    counters: Mutex<BTreeMap<MetricKey, u64>>,
+    histograms: Mutex<BTreeMap<MetricKey, Histogram>>,
+    /// Number of transactions currently waiting in each pipeline stage.
+    pub pipeline: PipelineGauges,
+    /// Time spent in each pipeline stage over the run.
+    pub profile: StageProfile,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn increment(&self, name: &'static str, labels: &[(&'static str, &str)]) {
+        *self
+            .counters
+            .lock()
+            .unwrap()
+            .entry(MetricKey::new(name, labels))
+            .or_default() += 1;
+    }
+
+    pub fn counter(&self, name: &'static str, labels: &[(&'static str, &str)]) -> u64 {
+        self.counters
+            .lock()
+            .unwrap()
+            .get(&MetricKey::new(name, labels))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Snapshot of all counters.
+    pub fn counters(&self) -> BTreeMap<MetricKey, u64> {
+        self.counters.lock().unwrap().clone()
+    }
+
+    /// Adds a latency observation to the histogram.
+    pub fn observe(&self, name: &'static str, labels: &[(&'static str, &str)], value: Duration) {
+        self.histograms
+            .lock()
+            .unwrap()
+            .entry(MetricKey::new(name, labels))
+            .or_default()
+            .observe(value);
+    }
+
+    /// Snapshot of all histograms.
+    pub fn histograms(&self) -> BTreeMap<MetricKey, Histogram> {
+        self.histograms.lock().unwrap().clone()
+    }
+}
@@ -0,0 +1,114 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use super::Metrics;
+
+/// How often queue depths are sampled for the report.
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Stage of the transaction submission pipeline a transaction is waiting in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineStage {
+    Generated,
+    Signed,
+    AwaitingRate,
+    InFlight,
+    AwaitingConfirmation,
+}
+
+impl PipelineStage {
+    pub const ALL: [Self; 5] = [
+        Self::Generated,
+        Self::Signed,
+        Self::AwaitingRate,
+        Self::InFlight,
+        Self::AwaitingConfirmation,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Generated => "generated",
+            Self::Signed => "signed",
+            Self::AwaitingRate => "awaiting_rate",
+            Self::InFlight => "in_flight",
+            Self::AwaitingConfirmation => "awaiting_confirmation",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Queue depth gauge of every pipeline stage.
+#[derive(Debug, Default)]
+pub struct PipelineGauges {
+    depths: [AtomicI64; 5],
+}
+
+impl PipelineGauges {
+    pub fn enter(&self, stage: PipelineStage) {
+        self.depths[stage.index()].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn leave(&self, stage: PipelineStage) {
+        self.depths[stage.index()].fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Moves a transaction from one stage to the next one.
+    pub fn advance(&self, from: PipelineStage, to: PipelineStage) {
+        self.leave(from);
+        self.enter(to);
+    }
+
+    pub fn depth(&self, stage: PipelineStage) -> i64 {
+        self.depths[stage.index()].load(Ordering::Relaxed)
+    }
+
+    /// Current depths in the order of `PipelineStage::ALL`.
+    pub fn snapshot(&self) -> [i64; 5] {
+        PipelineStage::ALL.map(|stage| self.depth(stage))
+    }
+}
+
+/// Queue depths observed at one moment of the run.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueDepthSample {
+    pub elapsed_secs: f64,
+    /// Depths in the order of `PipelineStage::ALL`.
+    pub depths: [i64; 5],
+}
+
+/// Periodically samples the pipeline gauges so queue build-up can be plotted after the run.
+#[derive(Debug, Default)]
+pub struct QueueDepthHistory {
+    samples: Mutex<Vec<QueueDepthSample>>,
+}
+
+impl QueueDepthHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn samples(&self) -> Vec<QueueDepthSample> {
+        self.samples.lock().unwrap().clone()
+    }
+
+    /// Samples the gauges every `interval` until the task is dropped or aborted.
+    pub async fn run_sampler(self: Arc<Self>, metrics: Arc<Metrics>, interval: Duration) {
+        let started = Instant::now();
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            self.samples.lock().unwrap().push(QueueDepthSample {
+                elapsed_secs: started.elapsed().as_secs_f64(),
+                depths: metrics.pipeline.snapshot(),
+            });
+        }
+    }
+}
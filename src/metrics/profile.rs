@@ -0,0 +1,94 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// File of the run directory the profile is written to with `general.stage_profile`.
+pub const STAGE_PROFILE_FILE: &str = "stages.folded";
+
+/// Time spent per stage of the submission pipeline, summed over the run.
+///
+/// Stages are stacks of frames such as `schedule;throttle`. Pipelines may break their own
+/// stages down further, e.g. `schedule;prepare;sign` below the `schedule;prepare` recorded
+/// by the engine; the folded output then gives a frame only the time none of its children
+/// account for, as flame graph tools add the children back up.
+#[derive(Debug, Default)]
+pub struct StageProfile {
+    totals: Mutex<BTreeMap<Vec<&'static str>, Duration>>,
+}
+
+impl StageProfile {
+    pub fn record(&self, stack: &[&'static str], elapsed: Duration) {
+        *self
+            .totals
+            .lock()
+            .unwrap()
+            .entry(stack.to_vec())
+            .or_default() += elapsed;
+    }
+
+    /// Awaits `future`, recording the time it took under `stack`.
+    pub async fn time<F: Future>(&self, stack: &[&'static str], future: F) -> F::Output {
+        let started = Instant::now();
+        let output = future.await;
+        self.record(stack, started.elapsed());
+        output
+    }
+
+    /// Total time of every stage, children included.
+    pub fn totals(&self) -> BTreeMap<Vec<&'static str>, Duration> {
+        self.totals.lock().unwrap().clone()
+    }
+
+    /// Profile in the folded stack format of `flamegraph.pl` and `inferno-flamegraph`, one
+    /// `frame;frame <microseconds>` line per stage.
+    pub fn folded(&self) -> String {
+        let totals = self.totals();
+        let mut folded = String::new();
+        for (stack, total) in &totals {
+            let children: Duration = totals
+                .iter()
+                .filter(|(child, _)| child.len() == stack.len() + 1 && child.starts_with(stack))
+                .map(|(_, child)| *child)
+                .sum();
+            let own = total.saturating_sub(children).as_micros();
+            if own > 0 {
+                let _ = writeln!(folded, "{} {}", stack.join(";"), own);
+            }
+        }
+        folded
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests that stages sum up and parents keep only the time their children leave out.
+    #[tokio::test]
+    async fn test_stage_profile() {
+        let profile = StageProfile::default();
+        let ms = Duration::from_millis;
+        profile.record(&["schedule", "prepare"], ms(10));
+        profile.record(&["schedule", "prepare"], ms(20));
+        profile.record(&["schedule", "prepare", "sign"], ms(25));
+        profile.record(&["schedule", "throttle"], ms(5));
+        profile.record(&["submit", "transfer"], ms(40));
+        let answer = profile.time(&["submit", "withdraw"], async { 42 }).await;
+        assert_eq!(answer, 42);
+
+        assert_eq!(profile.totals()[&vec!["schedule", "prepare"]], ms(30));
+        let folded = profile.folded();
+        let lines: Vec<&str> = folded.lines().take(4).collect();
+        assert_eq!(
+            lines,
+            [
+                "schedule;prepare 5000",
+                "schedule;prepare;sign 25000",
+                "schedule;throttle 5000",
+                "submit;transfer 40000",
+            ]
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::BTreeSet;
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde::Deserialize;
+
+use super::pipeline::PipelineStage;
+use super::{MetricKey, Metrics, LATENCY_BUCKETS_SECS};
+use crate::engine::{FAILED_METRIC, SUBMITTED_METRIC};
+
+/// Histogram of the time `send_tx` took to answer, labelled by type.
+pub const SUBMISSION_LATENCY_METRIC: &str = "tx_submission_latency_seconds";
+/// Histogram of the time from submission to a committed or verified block, labelled by type and stage.
+pub const CONFIRMATION_LATENCY_METRIC: &str = "tx_confirmation_latency_seconds";
+
+/// Prometheus endpoint, enabled by the `[metrics]` section.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PrometheusConfig {
+    /// Address `GET /metrics` is served on.
+    #[serde(default = "PrometheusConfig::default_bind_address")]
+    pub bind_address: SocketAddr,
+}
+
+impl PrometheusConfig {
+    fn default_bind_address() -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], 9899))
+    }
+}
+
+impl Default for PrometheusConfig {
+    fn default() -> Self {
+        Self {
+            bind_address: Self::default_bind_address(),
+        }
+    }
+}
+
+/// Serves the metrics in the Prometheus text format on `GET /metrics` until the future is dropped.
+///
+/// The achieved TPS gauge counts every submission attempt since `started`.
+pub async fn serve(
+    config: &PrometheusConfig,
+    metrics: Arc<Metrics>,
+    started: Instant,
+) -> Result<(), hyper::Error> {
+    let make_service = make_service_fn(move |_| {
+        let metrics = metrics.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let metrics = metrics.clone();
+                async move {
+                    let mut response = match (request.method(), request.uri().path()) {
+                        (&Method::GET, "/metrics") => {
+                            Response::new(Body::from(render(&metrics, started)))
+                        }
+                        _ => {
+                            let mut response = Response::new(Body::from("not found"));
+                            *response.status_mut() = StatusCode::NOT_FOUND;
+                            response
+                        }
+                    };
+                    response.headers_mut().insert(
+                        hyper::header::CONTENT_TYPE,
+                        hyper::header::HeaderValue::from_static("text/plain; version=0.0.4"),
+                    );
+                    Ok::<_, Infallible>(response)
+                }
+            }))
+        }
+    });
+
+    Server::bind(&config.bind_address).serve(make_service).await
+}
+
+/// Renders counters, histograms, pipeline gauges and the achieved TPS in the Prometheus text format.
+pub fn render(metrics: &Metrics, started: Instant) -> String {
+    let mut out = String::new();
+
+    let counters = metrics.counters();
+    let names: BTreeSet<&str> = counters.keys().map(|key| key.name).collect();
+    for name in names {
+        let _ = writeln!(out, "# TYPE {} counter", name);
+        for (key, value) in counters.iter().filter(|(key, _)| key.name == name) {
+            let _ = writeln!(out, "{} {}", key, value);
+        }
+    }
+
+    let histograms = metrics.histograms();
+    let names: BTreeSet<&str> = histograms.keys().map(|key| key.name).collect();
+    for name in names {
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        for (key, histogram) in histograms.iter().filter(|(key, _)| key.name == name) {
+            let mut cumulative = 0;
+            let bounds = LATENCY_BUCKETS_SECS
+                .iter()
+                .map(|bound| bound.to_string())
+                .chain(["+Inf".to_string()]);
+            for (bound, count) in bounds.zip(histogram.buckets) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "{}_bucket{} {}",
+                    name,
+                    labels(key, Some(&bound)),
+                    cumulative
+                );
+            }
+            let _ = writeln!(
+                out,
+                "{}_sum{} {}",
+                name,
+                labels(key, None),
+                histogram.sum_secs
+            );
+            let _ = writeln!(
+                out,
+                "{}_count{} {}",
+                name,
+                labels(key, None),
+                histogram.count
+            );
+        }
+    }
+
+    let _ = writeln!(out, "# TYPE pipeline_queue_depth gauge");
+    for stage in PipelineStage::ALL {
+        let _ = writeln!(
+            out,
+            "pipeline_queue_depth{{stage=\"{}\"}} {}",
+            stage.name(),
+            metrics.pipeline.depth(stage)
+        );
+    }
+
+    let attempts: u64 = counters
+        .iter()
+        .filter(|(key, _)| key.name == SUBMITTED_METRIC || key.name == FAILED_METRIC)
+        .map(|(_, value)| value)
+        .sum();
+    let secs = started.elapsed().as_secs_f64();
+    let tps = if secs == 0.0 {
+        0.0
+    } else {
+        attempts as f64 / secs
+    };
+    let _ = writeln!(out, "# TYPE achieved_tps gauge");
+    let _ = writeln!(out, "achieved_tps {}", tps);
+    out
+}
+
+/// Label set of the key, with the `le` bucket bound appended when given.
+fn labels(key: &MetricKey, le: Option<&str>) -> String {
+    let mut labels: Vec<String> = key
+        .labels
+        .iter()
+        .map(|(label, value)| format!("{}=\"{}\"", label, value))
+        .collect();
+    if let Some(le) = le {
+        labels.push(format!("le=\"{}\"", le));
+    }
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", labels.join(","))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    /// Tests that histogram buckets are cumulative and every metric family is typed.
+    #[test]
+    fn test_render_prometheus() {
+        let metrics = Metrics::new();
+        metrics.increment(SUBMITTED_METRIC, &[("type", "transfer")]);
+        metrics.increment(SUBMITTED_METRIC, &[("type", "withdraw")]);
+        for millis in [3, 40, 40, 20_000] {
+            metrics.observe(
+                SUBMISSION_LATENCY_METRIC,
+                &[("type", "transfer")],
+                Duration::from_millis(millis),
+            );
+        }
+
+        let rendered = render(&metrics, Instant::now() - Duration::from_secs(1));
+        let lines: Vec<&str> = rendered.lines().collect();
+        for expected in [
+            "# TYPE txs_submitted_total counter",
+            "txs_submitted_total{type=\"withdraw\"} 1",
+            "# TYPE tx_submission_latency_seconds histogram",
+            "tx_submission_latency_seconds_bucket{type=\"transfer\",le=\"0.005\"} 1",
+            "tx_submission_latency_seconds_bucket{type=\"transfer\",le=\"0.05\"} 3",
+            "tx_submission_latency_seconds_bucket{type=\"transfer\",le=\"10\"} 3",
+            "tx_submission_latency_seconds_bucket{type=\"transfer\",le=\"30\"} 4",
+            "tx_submission_latency_seconds_bucket{type=\"transfer\",le=\"+Inf\"} 4",
+            "tx_submission_latency_seconds_count{type=\"transfer\"} 4",
+            "pipeline_queue_depth{stage=\"in_flight\"} 0",
+        ] {
+            assert!(lines.contains(&expected), "missing {expected}\n{rendered}");
+        }
+        let tps: f64 = lines
+            .iter()
+            .find_map(|line| line.strip_prefix("achieved_tps "))
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(tps > 1.0 && tps <= 2.0);
+    }
+}
@@ -0,0 +1,381 @@
+//! Wallet misbehavior catalogue: a small share of the run is sent as transactions broken the
+//! way buggy wallets break them. Each of them must be refused by the rollup; one accepted is a
+//! gap in its defenses, so a run doubles as a regression test of them.
+
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use num::BigUint;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinSet;
+use tracing::{debug, warn, Instrument};
+
+use crate::engine::TxPipeline;
+use crate::metrics::Metrics;
+use crate::report::misbehaviors::Misbehavior;
+use crate::report::RunRecorder;
+use crate::rng::StreamRng;
+use crate::transaction::Transaction;
+use crate::wallet::SignedTx;
+
+/// Counter of transactions sent with an emulated wallet bug, labelled by bug and outcome.
+pub const MISBEHAVIOR_METRIC: &str = "wallet_misbehaviors_total";
+
+/// Bug of a wallet emulated on a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WalletBug {
+    /// Signs with a fee quoted long ago, below what the server now asks.
+    StaleFee,
+    /// Signs with the nonce of a transaction already sent, as wallets restarting with a
+    /// stale nonce cache do.
+    ReusedNonce,
+    /// Signs the Rootstock message with a signer bound to another chain.
+    WrongChainId,
+    /// Sends the L2 signature cut short, its second half zeroed.
+    TruncatedSignature,
+}
+
+impl WalletBug {
+    pub const ALL: [WalletBug; 4] = [
+        WalletBug::StaleFee,
+        WalletBug::ReusedNonce,
+        WalletBug::WrongChainId,
+        WalletBug::TruncatedSignature,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WalletBug::StaleFee => "stale_fee",
+            WalletBug::ReusedNonce => "reused_nonce",
+            WalletBug::WrongChainId => "wrong_chain_id",
+            WalletBug::TruncatedSignature => "truncated_signature",
+        }
+    }
+
+    /// Applies the bug to a transaction about to be signed, `false` when it can not have it.
+    ///
+    /// Bugs of the signature leave the transaction as it is, see [`WalletBug::corrupt_signed`].
+    pub fn corrupt_unsigned(self, tx: &mut Transaction, config: &MisbehaviorConfig) -> bool {
+        match self {
+            WalletBug::StaleFee => match tx.fee_mut() {
+                Some(fee) if *fee > BigUint::default() => {
+                    *fee = &*fee * config.stale_fee_percent / 100u32;
+                    true
+                }
+                _ => false,
+            },
+            WalletBug::ReusedNonce => match tx.nonce_mut() {
+                Some(nonce) if **nonce > 0 => {
+                    **nonce -= 1;
+                    true
+                }
+                _ => false,
+            },
+            WalletBug::WrongChainId | WalletBug::TruncatedSignature => true,
+        }
+    }
+
+    /// Applies the bug to a signed transaction, `false` when it can not have it.
+    ///
+    /// Bugs of the transaction itself leave it as it is, see [`WalletBug::corrupt_unsigned`].
+    pub fn corrupt_signed(self, tx: &mut SignedTx, config: &MisbehaviorConfig) -> bool {
+        match self {
+            WalletBug::WrongChainId => {
+                let Some(eth_signature) = &mut tx.1 else {
+                    return false;
+                };
+                // EIP-155 `v` of the same signature made for `wrong_chain_id`.
+                let v = eth_signature.0.v;
+                let recovery_id = match v {
+                    0 | 1 => v,
+                    27 | 28 => v - 27,
+                    _ => (v - 35) % 2,
+                };
+                eth_signature.0.v = recovery_id + 35 + 2 * config.wrong_chain_id;
+                true
+            }
+            WalletBug::TruncatedSignature => {
+                let signature = &mut tx.0.signature_mut().signature;
+                let half = signature.len() / 2;
+                signature[half..].fill(0);
+                true
+            }
+            WalletBug::StaleFee | WalletBug::ReusedNonce => true,
+        }
+    }
+}
+
+/// The `[misbehavior]` section, no wallet bugs are emulated when it is missing.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MisbehaviorConfig {
+    /// Percentage of sent transactions followed by one with an emulated bug.
+    #[serde(default = "MisbehaviorConfig::default_rate_percent")]
+    pub rate_percent: f64,
+    /// Bugs to emulate, all of them when empty.
+    #[serde(default)]
+    pub bugs: Vec<WalletBug>,
+    /// Share of the quoted fee signed by `stale_fee`, in percent.
+    #[serde(default = "MisbehaviorConfig::default_stale_fee_percent")]
+    pub stale_fee_percent: u32,
+    /// Chain id `wrong_chain_id` signs for.
+    #[serde(default = "MisbehaviorConfig::default_wrong_chain_id")]
+    pub wrong_chain_id: u64,
+}
+
+impl MisbehaviorConfig {
+    fn default_rate_percent() -> f64 {
+        0.1
+    }
+
+    fn default_stale_fee_percent() -> u32 {
+        50
+    }
+
+    fn default_wrong_chain_id() -> u64 {
+        1
+    }
+
+    /// Bugs emulated in the run.
+    pub fn enabled_bugs(&self) -> &[WalletBug] {
+        if self.bugs.is_empty() {
+            &WalletBug::ALL
+        } else {
+            &self.bugs
+        }
+    }
+}
+
+impl Default for MisbehaviorConfig {
+    fn default() -> Self {
+        Self {
+            rate_percent: Self::default_rate_percent(),
+            bugs: Vec::new(),
+            stale_fee_percent: Self::default_stale_fee_percent(),
+            wrong_chain_id: Self::default_wrong_chain_id(),
+        }
+    }
+}
+
+/// Picks when to emulate which wallet bug and sends the broken transactions.
+///
+/// They are sent next to the scheduled transactions, without taking a slot of the schedule,
+/// and the outcome is checked against the expected rejection.
+#[derive(Debug)]
+pub struct MisbehaviorInjector {
+    config: MisbehaviorConfig,
+    rng: Mutex<StreamRng>,
+    pending: Mutex<JoinSet<()>>,
+}
+
+impl MisbehaviorInjector {
+    pub fn new(config: MisbehaviorConfig, rng: StreamRng) -> Self {
+        Self {
+            config,
+            rng: Mutex::new(rng),
+            pending: Mutex::new(JoinSet::new()),
+        }
+    }
+
+    pub fn config(&self) -> &MisbehaviorConfig {
+        &self.config
+    }
+
+    /// Bug to emulate after the next transaction, `None` for most of them.
+    pub fn pick(&self) -> Option<WalletBug> {
+        let fraction = (self.config.rate_percent / 100.0).clamp(0.0, 1.0);
+        let mut rng = self.rng.lock().unwrap();
+        if !rng.gen_bool(fraction) {
+            return None;
+        }
+        self.config.enabled_bugs().choose(&mut *rng).copied()
+    }
+
+    /// Sends `tx`, broken by `bug`, and records whether the rollup refused it.
+    pub fn send<P: TxPipeline>(
+        &self,
+        pipeline: Arc<P>,
+        recorder: Arc<RunRecorder>,
+        metrics: Arc<Metrics>,
+        bug: WalletBug,
+        tx: P::Tx,
+    ) {
+        let task = async move {
+            let tx_type = P::tx_type(&tx);
+            let started = Instant::now();
+            let result = pipeline.submit(tx).await;
+            let misbehavior = Misbehavior {
+                bug,
+                tx_type: tx_type.to_string(),
+                accepted_hash: result.as_ref().ok().copied(),
+                error: result.as_ref().err().map(ToString::to_string),
+                response_time_ms: started.elapsed().as_secs_f64() * 1000.0,
+            };
+            let outcome = if misbehavior.accepted_hash.is_some() {
+                "accepted"
+            } else {
+                "rejected"
+            };
+            metrics.increment(
+                MISBEHAVIOR_METRIC,
+                &[("bug", bug.name()), ("outcome", outcome)],
+            );
+            match &result {
+                Ok(tx_hash) => warn!(
+                    tx_type,
+                    %tx_hash,
+                    bug = bug.name(),
+                    "transaction of a misbehaving wallet accepted"
+                ),
+                Err(err) => debug!(
+                    tx_type,
+                    bug = bug.name(),
+                    error = %err,
+                    "transaction of a misbehaving wallet rejected"
+                ),
+            }
+            recorder.with_misbehaviors(|misbehaviors| misbehaviors.record(misbehavior));
+        };
+        self.pending.lock().unwrap().spawn(task.in_current_span());
+    }
+
+    /// Waits for the broken transactions still awaiting their response.
+    pub async fn finish(&self) {
+        let mut pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        while pending.join_next().await.is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::rollup::provider::ClientError;
+    use crate::rollup::types::tx::{PackedEthSignature, Transfer, TxSignature, ZkSyncTx};
+    use crate::rollup::types::{Address, Nonce, TxHash};
+
+    /// Pipeline accepting only transactions whose L2 signature is complete.
+    struct StrictPipeline;
+
+    #[async_trait]
+    impl TxPipeline for StrictPipeline {
+        type Tx = SignedTx;
+
+        async fn prepare(&self) -> Option<SignedTx> {
+            None
+        }
+
+        fn tx_type(_tx: &SignedTx) -> &'static str {
+            "transfer"
+        }
+
+        async fn submit(&self, tx: SignedTx) -> Result<TxHash, ClientError> {
+            if tx.0.signature().signature[63] == 0 {
+                return Err(ClientError::IncorrectInput);
+            }
+            Ok(TxHash { data: [1; 32] })
+        }
+    }
+
+    fn signed_transfer() -> SignedTx {
+        let transfer = Transfer {
+            account_id: Default::default(),
+            from: Address::zero(),
+            to: Address::zero(),
+            token: Default::default(),
+            amount: BigUint::from(10u32),
+            fee: BigUint::from(4u32),
+            nonce: Nonce(3),
+            signature: TxSignature {
+                pub_key: [7; 32],
+                signature: [9; 64],
+            },
+            time_range: Default::default(),
+        };
+        let eth_signature = ethers::types::Signature {
+            r: 1.into(),
+            s: 2.into(),
+            v: 28,
+        };
+        (
+            ZkSyncTx::Transfer(Box::new(transfer)),
+            Some(PackedEthSignature(eth_signature)),
+        )
+    }
+
+    /// Tests that every bug breaks the transaction it is emulated on, and that accepted
+    /// broken transactions are told apart from rejected ones.
+    #[tokio::test]
+    async fn test_wallet_bugs() {
+        let config = MisbehaviorConfig {
+            rate_percent: 100.0,
+            bugs: vec![WalletBug::WrongChainId],
+            ..MisbehaviorConfig::default()
+        };
+        let mut tx = Transaction::Transfer {
+            from: Address::zero(),
+            to: Address::zero(),
+            token: Default::default(),
+            amount: BigUint::from(10u32),
+            fee: BigUint::from(40u32),
+            nonce: Nonce(3),
+        };
+        assert!(WalletBug::StaleFee.corrupt_unsigned(&mut tx, &config));
+        assert!(WalletBug::ReusedNonce.corrupt_unsigned(&mut tx, &config));
+        assert_eq!(tx.fee(), Some(&BigUint::from(20u32)));
+        assert_eq!(tx.nonce(), Some(Nonce(2)));
+        *tx.nonce_mut().unwrap() = Nonce(0);
+        assert!(!WalletBug::ReusedNonce.corrupt_unsigned(&mut tx, &config));
+
+        let mut signed = signed_transfer();
+        assert!(WalletBug::WrongChainId.corrupt_signed(&mut signed, &config));
+        assert_eq!(signed.1.unwrap().0.v, 1 + 35 + 2);
+        assert!(WalletBug::TruncatedSignature.corrupt_signed(&mut signed, &config));
+        assert_eq!(signed.0.signature().signature[..32], [9; 32]);
+        assert_eq!(signed.0.signature().signature[32..], [0; 32]);
+        signed.1 = None;
+        assert!(!WalletBug::WrongChainId.corrupt_signed(&mut signed, &config));
+
+        let injector = MisbehaviorInjector::new(config, StreamRng::seed_from_u64(1));
+        assert_eq!(injector.pick(), Some(WalletBug::WrongChainId));
+        let pipeline = Arc::new(StrictPipeline);
+        let recorder = Arc::new(RunRecorder::new());
+        let metrics = Arc::new(Metrics::new());
+        let mut truncated = signed_transfer();
+        WalletBug::TruncatedSignature.corrupt_signed(&mut truncated, injector.config());
+        for (bug, tx) in [
+            (WalletBug::TruncatedSignature, truncated),
+            (WalletBug::WrongChainId, signed_transfer()),
+        ] {
+            injector.send(pipeline.clone(), recorder.clone(), metrics.clone(), bug, tx);
+        }
+        injector.finish().await;
+
+        let summary = recorder.with_misbehaviors(|misbehaviors| misbehaviors.summary());
+        assert_eq!(summary.gaps, 1);
+        let rows: Vec<(WalletBug, u64, u64)> = summary
+            .rows
+            .iter()
+            .map(|row| (row.bug, row.rejected, row.accepted))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                (WalletBug::WrongChainId, 0, 1),
+                (WalletBug::TruncatedSignature, 1, 0)
+            ]
+        );
+
+        let never = MisbehaviorInjector::new(
+            MisbehaviorConfig {
+                rate_percent: 0.0,
+                ..MisbehaviorConfig::default()
+            },
+            StreamRng::seed_from_u64(1),
+        );
+        assert_eq!(never.pick(), None);
+    }
+}
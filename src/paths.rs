@@ -0,0 +1,65 @@
+//! Platform-agnostic handling of user supplied file paths.
+//!
+//! Paths are always built with `Path`/`PathBuf` rather than by string
+//! concatenation, so both `/` and `\` separators work where the platform
+//! accepts them, and nothing here relies on unix-only calls except the
+//! permission tightening of private files, which is a no-op elsewhere.
+
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Home directory of the current user, `HOME` on unix and `USERPROFILE` on Windows.
+pub fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Expands a leading `~` to the home directory, leaving other paths untouched.
+pub fn expand_home(path: impl AsRef<Path>) -> PathBuf {
+    let path = path.as_ref();
+    match path.strip_prefix("~") {
+        Ok(rest) => match home_dir() {
+            Some(home) => home.join(rest),
+            None => path.to_path_buf(),
+        },
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// Creates the parent directory of a file that is about to be written.
+pub fn ensure_parent_dir(path: impl AsRef<Path>) -> io::Result<()> {
+    match path.as_ref().parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => fs::create_dir_all(parent),
+        _ => Ok(()),
+    }
+}
+
+/// Writes a file, creating its parent directory first.
+pub fn write_file(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    ensure_parent_dir(&path)?;
+    fs::write(path, contents)
+}
+
+/// Opens a file only its owner may read, such as a keystore or a file holding a mnemonic.
+///
+/// On unix the file is created with mode `0600`; on Windows files inherit the ACL of the
+/// user's directory, which already restricts access to the owner.
+pub fn open_private(path: impl AsRef<Path>, append: bool) -> io::Result<File> {
+    ensure_parent_dir(&path)?;
+    let mut options = OpenOptions::new();
+    options.create(true).write(true);
+    if append {
+        options.append(true);
+    } else {
+        options.truncate(true);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
@@ -0,0 +1,161 @@
+use std::io::IsTerminal;
+
+#[cfg(not(feature = "progress-bars"))]
+use hidden::{MultiProgress, ProgressBar, ProgressStyle};
+#[cfg(feature = "progress-bars")]
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+const BAR_TEMPLATE: &str =
+    "{prefix:>22} [{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} (ETA {eta}) {msg}";
+
+/// Setup phase that runs before the main workload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetupPhase {
+    AccountGeneration,
+    Funding,
+    DepositConfirmation,
+    Activation,
+}
+
+impl SetupPhase {
+    pub fn label(self) -> &'static str {
+        match self {
+            SetupPhase::AccountGeneration => "account generation",
+            SetupPhase::Funding => "funding",
+            SetupPhase::DepositConfirmation => "deposit confirmation",
+            SetupPhase::Activation => "activation",
+        }
+    }
+}
+
+/// Progress bars of the setup phases, hidden when stderr is not a terminal or the build does
+/// not include the `progress-bars` feature.
+pub struct SetupProgress {
+    bars: Option<MultiProgress>,
+}
+
+impl SetupProgress {
+    pub fn new() -> Self {
+        Self::with_enabled(std::io::stderr().is_terminal())
+    }
+
+    pub fn with_enabled(enabled: bool) -> Self {
+        Self {
+            bars: (enabled && cfg!(feature = "progress-bars")).then(MultiProgress::new),
+        }
+    }
+
+    /// Starts tracking a phase processing `total` accounts.
+    pub fn phase(&self, phase: SetupPhase, total: u64) -> PhaseProgress {
+        let bar = match &self.bars {
+            Some(bars) => {
+                let bar = bars.add(ProgressBar::new(total));
+                if let Ok(style) = ProgressStyle::with_template(BAR_TEMPLATE) {
+                    bar.set_style(style.progress_chars("=> "));
+                }
+                bar.set_prefix(phase.label());
+                bar
+            }
+            None => ProgressBar::hidden(),
+        };
+        PhaseProgress { bar }
+    }
+}
+
+impl Default for SetupProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Progress of a single setup phase, cheap to clone into worker tasks.
+#[derive(Debug, Clone)]
+pub struct PhaseProgress {
+    bar: ProgressBar,
+}
+
+impl PhaseProgress {
+    /// Progress that is never displayed.
+    pub fn hidden() -> Self {
+        Self {
+            bar: ProgressBar::hidden(),
+        }
+    }
+
+    /// Marks one more account as done.
+    pub fn inc(&self) {
+        self.bar.inc(1);
+    }
+
+    /// Marks `count` more accounts as done.
+    pub fn inc_by(&self, count: u64) {
+        self.bar.inc(count);
+    }
+
+    /// Shows a short status next to the bar, e.g. the number of failures so far.
+    pub fn set_message(&self, message: String) {
+        self.bar.set_message(message);
+    }
+
+    pub fn finish(&self) {
+        self.bar.finish_with_message("done");
+    }
+
+    pub fn abandon(&self, reason: String) {
+        self.bar.abandon_with_message(reason);
+    }
+}
+
+/// Stand-ins of the `indicatif` types for builds without the `progress-bars` feature, every
+/// bar is hidden.
+#[cfg(not(feature = "progress-bars"))]
+mod hidden {
+    #[derive(Debug, Clone)]
+    pub struct ProgressBar;
+
+    impl ProgressBar {
+        pub fn new(_len: u64) -> Self {
+            Self
+        }
+
+        pub fn hidden() -> Self {
+            Self
+        }
+
+        pub fn set_style(&self, _style: ProgressStyle) {}
+
+        pub fn set_prefix(&self, _prefix: &'static str) {}
+
+        pub fn inc(&self, _delta: u64) {}
+
+        pub fn set_message(&self, _message: String) {}
+
+        pub fn finish_with_message(&self, _message: &'static str) {}
+
+        pub fn abandon_with_message(&self, _message: String) {}
+    }
+
+    pub struct MultiProgress;
+
+    impl MultiProgress {
+        pub fn new() -> Self {
+            Self
+        }
+
+        pub fn add(&self, bar: ProgressBar) -> ProgressBar {
+            bar
+        }
+    }
+
+    pub struct ProgressStyle;
+
+    impl ProgressStyle {
+        pub fn with_template(_template: &str) -> Result<Self, ()> {
+            Ok(Self)
+        }
+
+        pub fn progress_chars(self, _chars: &str) -> Self {
+            self
+        }
+    }
+}
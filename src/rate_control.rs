@@ -0,0 +1,300 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Adaptive rate of the throttler, configured with `adaptive_rate` in the `[general]` section.
+///
+/// Every `interval_ms` the responses of the interval are judged: when more than
+/// `max_congested_ratio` of them failed with a network error or timeout, or took longer than
+/// `slow_response_ms`, the rate is multiplied by `decrease_factor`; otherwise
+/// `increase_step` of the profile rate is added back, up to the profile rate itself.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct AdaptiveRateConfig {
+    #[serde(default = "AdaptiveRateConfig::default_interval_ms")]
+    pub interval_ms: u64,
+    #[serde(default = "AdaptiveRateConfig::default_slow_response_ms")]
+    pub slow_response_ms: u64,
+    #[serde(default = "AdaptiveRateConfig::default_max_congested_ratio")]
+    pub max_congested_ratio: f64,
+    #[serde(default = "AdaptiveRateConfig::default_decrease_factor")]
+    pub decrease_factor: f64,
+    #[serde(default = "AdaptiveRateConfig::default_increase_step")]
+    pub increase_step: f64,
+    /// Lowest share of the profile rate the rate is cut to.
+    #[serde(default = "AdaptiveRateConfig::default_min_factor")]
+    pub min_factor: f64,
+}
+
+impl AdaptiveRateConfig {
+    fn default_interval_ms() -> u64 {
+        1_000
+    }
+
+    fn default_slow_response_ms() -> u64 {
+        2_000
+    }
+
+    fn default_max_congested_ratio() -> f64 {
+        0.1
+    }
+
+    fn default_decrease_factor() -> f64 {
+        0.5
+    }
+
+    fn default_increase_step() -> f64 {
+        0.05
+    }
+
+    fn default_min_factor() -> f64 {
+        0.05
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms)
+    }
+}
+
+impl Default for AdaptiveRateConfig {
+    fn default() -> Self {
+        Self {
+            interval_ms: Self::default_interval_ms(),
+            slow_response_ms: Self::default_slow_response_ms(),
+            max_congested_ratio: Self::default_max_congested_ratio(),
+            decrease_factor: Self::default_decrease_factor(),
+            increase_step: Self::default_increase_step(),
+            min_factor: Self::default_min_factor(),
+        }
+    }
+}
+
+/// How the rate was changed at the end of an interval.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RateAdjustment {
+    /// Kept at the profile rate, or while the requests of the interval await their response.
+    Hold,
+    Increase,
+    Decrease,
+}
+
+/// One judged interval of the run.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateInterval {
+    pub start_secs: f64,
+    /// Share of the profile rate the interval was sent at.
+    pub factor: f64,
+    pub sent: u64,
+    /// Transactions actually sent per second within the interval.
+    pub achieved_tps: f64,
+    pub responses: u64,
+    pub congested: u64,
+    pub adjustment: RateAdjustment,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdaptiveRateSummary {
+    pub decreases: usize,
+    pub increases: usize,
+    pub lowest_factor: f64,
+    pub final_factor: f64,
+    pub intervals: Vec<RateInterval>,
+}
+
+#[derive(Debug)]
+struct RateState {
+    factor: f64,
+    interval_start: Duration,
+    sent: u64,
+    intervals: Vec<RateInterval>,
+}
+
+/// AIMD controller scaling the rate of the load profile down when the node shows backpressure
+/// and back up once it recovers.
+///
+/// Submission tasks report their responses as they come; the throttler moves the controller
+/// along the schedule, so intervals are judged on the clock of the throttler.
+#[derive(Debug)]
+pub struct AdaptiveRate {
+    config: AdaptiveRateConfig,
+    responses: AtomicU64,
+    congested: AtomicU64,
+    state: Mutex<RateState>,
+}
+
+impl AdaptiveRate {
+    pub fn new(config: AdaptiveRateConfig) -> Self {
+        Self {
+            config,
+            responses: AtomicU64::new(0),
+            congested: AtomicU64::new(0),
+            state: Mutex::new(RateState {
+                factor: 1.0,
+                interval_start: Duration::ZERO,
+                sent: 0,
+                intervals: Vec::new(),
+            }),
+        }
+    }
+
+    /// Share of the profile rate transactions are currently sent at.
+    pub fn factor(&self) -> f64 {
+        self.state.lock().unwrap().factor
+    }
+
+    /// Counts the response to a submission, `backpressure` when it failed because the node
+    /// was unreachable or too slow to answer.
+    pub fn record_response(&self, response_time: Duration, backpressure: bool) {
+        self.responses.fetch_add(1, Ordering::Relaxed);
+        if backpressure || response_time.as_millis() > self.config.slow_response_ms as u128 {
+            self.congested.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Counts a transaction sent `elapsed` into the run, judging the intervals that ended
+    /// before it, and returns the factor to send it at.
+    pub fn on_send(&self, elapsed: Duration) -> f64 {
+        let mut state = self.state.lock().unwrap();
+        let interval = self.config.interval();
+        while elapsed >= state.interval_start + interval {
+            self.close_interval(&mut state);
+        }
+        state.sent += 1;
+        state.factor
+    }
+
+    fn close_interval(&self, state: &mut RateState) {
+        let interval = self.config.interval();
+        let responses = self.responses.swap(0, Ordering::Relaxed);
+        let congested = self.congested.swap(0, Ordering::Relaxed);
+        let adjustment = if responses > 0
+            && congested as f64 > responses as f64 * self.config.max_congested_ratio
+        {
+            RateAdjustment::Decrease
+        } else if state.factor >= 1.0 || (responses == 0 && state.sent > 0) {
+            RateAdjustment::Hold
+        } else {
+            RateAdjustment::Increase
+        };
+        state.intervals.push(RateInterval {
+            start_secs: state.interval_start.as_secs_f64(),
+            factor: state.factor,
+            sent: state.sent,
+            achieved_tps: state.sent as f64 / interval.as_secs_f64(),
+            responses,
+            congested,
+            adjustment,
+        });
+        state.factor = match adjustment {
+            RateAdjustment::Decrease => {
+                (state.factor * self.config.decrease_factor).max(self.config.min_factor)
+            }
+            RateAdjustment::Increase => (state.factor + self.config.increase_step).min(1.0),
+            RateAdjustment::Hold => state.factor,
+        };
+        state.interval_start += interval;
+        state.sent = 0;
+    }
+
+    /// Judged intervals and the changes of the rate over the run.
+    pub fn summary(&self) -> AdaptiveRateSummary {
+        let state = self.state.lock().unwrap();
+        let count = |adjustment| {
+            state
+                .intervals
+                .iter()
+                .filter(|interval| interval.adjustment == adjustment)
+                .count()
+        };
+        AdaptiveRateSummary {
+            decreases: count(RateAdjustment::Decrease),
+            increases: count(RateAdjustment::Increase),
+            lowest_factor: state
+                .intervals
+                .iter()
+                .map(|interval| interval.factor)
+                .fold(state.factor, f64::min),
+            final_factor: state.factor,
+            intervals: state.intervals.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::clock::{Clock, SimulatedClock};
+    use crate::throttler::Throttler;
+
+    /// Tests that the rate is cut on congestion, held while responses are awaited and ramped
+    /// back up once the node recovers.
+    #[tokio::test]
+    async fn test_adaptive_rate() {
+        let config = AdaptiveRateConfig {
+            min_factor: 0.2,
+            increase_step: 0.25,
+            ..AdaptiveRateConfig::default()
+        };
+        let clock = SimulatedClock::new();
+        let throttler = Throttler::with_clock(100, clock.clone()).with_adaptive_rate(config);
+        let rate = throttler.adaptive_rate().unwrap();
+
+        // The node fails every request of the first second, then answers them too slowly.
+        let until = clock.now() + Duration::from_secs(1);
+        while clock.now() < until {
+            throttler.throttle().await;
+            rate.record_response(Duration::from_millis(50), true);
+        }
+        for _ in 0..3 {
+            let until = clock.now() + Duration::from_secs(1);
+            while clock.now() < until {
+                throttler.throttle().await;
+                rate.record_response(Duration::from_secs(5), false);
+            }
+        }
+        let summary = rate.summary();
+        let factors: Vec<f64> = summary.intervals.iter().map(|i| i.factor).collect();
+        assert_eq!(factors, [1.0, 0.5, 0.25]);
+        assert_eq!(summary.intervals[1].sent, 50);
+        assert_eq!(summary.intervals[1].achieved_tps, 50.0);
+        assert_eq!(rate.factor(), 0.2);
+
+        // No responses arrive for a second, then every one is fast again.
+        let until = clock.now() + Duration::from_secs(1);
+        while clock.now() < until {
+            throttler.throttle().await;
+        }
+        for _ in 0..5 {
+            let until = clock.now() + Duration::from_secs(1);
+            while clock.now() < until {
+                throttler.throttle().await;
+                rate.record_response(Duration::from_millis(20), false);
+            }
+        }
+        throttler.throttle().await;
+        let summary = rate.summary();
+        let adjustments: Vec<RateAdjustment> = summary.intervals[3..]
+            .iter()
+            .map(|i| i.adjustment)
+            .collect();
+        assert_eq!(
+            adjustments,
+            [
+                RateAdjustment::Decrease,
+                RateAdjustment::Hold,
+                RateAdjustment::Increase,
+                RateAdjustment::Increase,
+                RateAdjustment::Increase,
+                RateAdjustment::Increase,
+                RateAdjustment::Hold,
+            ]
+        );
+        assert_eq!((summary.decreases, summary.increases), (4, 4));
+        assert_eq!(summary.lowest_factor, 0.2);
+        assert_eq!(summary.final_factor, 1.0);
+    }
+}
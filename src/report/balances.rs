@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use num::{BigUint, ToPrimitive};
+use serde::Serialize;
+
+use super::units::AmountFormat;
+use crate::rollup::types::serde_wrappers::{BigUintSerdeAsRadix10Str, BigUintSerdeWrapper};
+
+#[derive(Debug, Default)]
+struct TokenUsage {
+    funded: BigUint,
+    moved: BigUint,
+    fees: BigUint,
+    txs: u64,
+    ending: Option<BigUint>,
+}
+
+/// How much of the funded balance of a token the run actually used.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceUtilizationRow {
+    pub token: String,
+    /// Balance deposited to the simulated accounts before the run.
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    pub funded: BigUint,
+    /// Sum of the amounts of accepted transactions.
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    pub moved: BigUint,
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    pub fees: BigUint,
+    pub txs: u64,
+    /// Moved amount relative to the funded balance, `None` when nothing was funded.
+    pub turnover: Option<f64>,
+    /// Balance left in the simulated accounts once the run ended, when known.
+    pub ending: Option<BigUintSerdeWrapper>,
+    /// Share of the funded balance that was never needed, `None` until the ending balance is known.
+    pub idle_ratio: Option<f64>,
+}
+
+/// Per-token turnover of the funded balances, to size the funding of future runs.
+///
+/// Transfers between simulated accounts move the same funds around, so a turnover far
+/// above one with a high idle ratio means the scenario could run on less funding.
+#[derive(Debug, Default)]
+pub struct BalanceUtilization {
+    tokens: BTreeMap<String, TokenUsage>,
+}
+
+impl BalanceUtilization {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_funded(&mut self, token: &str, amount: &BigUint) {
+        self.usage(token).funded += amount;
+    }
+
+    /// Records an accepted transaction moving `amount` and paying `fee` in the token.
+    pub fn record_moved(&mut self, token: &str, amount: &BigUint, fee: Option<&BigUint>) {
+        let usage = self.usage(token);
+        usage.moved += amount;
+        if let Some(fee) = fee {
+            usage.fees += fee;
+        }
+        usage.txs += 1;
+    }
+
+    /// Sets the total balance of the token across the simulated accounts at the end of the run.
+    pub fn set_ending(&mut self, token: &str, balance: BigUint) {
+        self.usage(token).ending = Some(balance);
+    }
+
+    fn usage(&mut self, token: &str) -> &mut TokenUsage {
+        self.tokens.entry(token.to_string()).or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    pub fn rows(&self) -> Vec<BalanceUtilizationRow> {
+        self.tokens
+            .iter()
+            .map(|(token, usage)| {
+                let funded = usage.funded.to_f64().filter(|funded| *funded > 0.0);
+                let ratio = |value: &BigUint| {
+                    funded.map(|funded| value.to_f64().unwrap_or_default() / funded)
+                };
+                BalanceUtilizationRow {
+                    token: token.clone(),
+                    funded: usage.funded.clone(),
+                    moved: usage.moved.clone(),
+                    fees: usage.fees.clone(),
+                    txs: usage.txs,
+                    turnover: ratio(&usage.moved),
+                    ending: usage.ending.clone().map(BigUintSerdeWrapper),
+                    idle_ratio: usage.ending.as_ref().and_then(ratio),
+                }
+            })
+            .collect()
+    }
+
+    /// Renders the utilization of every token as a plain text table.
+    pub fn render_table(&self, units: &AmountFormat) -> String {
+        let ratio = |value: Option<f64>| match value {
+            Some(value) => format!("{:.2}", value),
+            None => "-".to_string(),
+        };
+        let mut table = format!(
+            "{:<10} {:>26} {:>26} {:>22} {:>9} {:>26} {:>6}\n",
+            "token", "funded", "moved", "fees", "turnover", "ending", "idle"
+        );
+        for row in self.rows() {
+            let _ = writeln!(
+                table,
+                "{:<10} {:>26} {:>26} {:>22} {:>9} {:>26} {:>6}",
+                row.token,
+                units.format(&row.funded, &row.token),
+                units.format(&row.moved, &row.token),
+                units.format(&row.fees, &row.token),
+                ratio(row.turnover),
+                row.ending
+                    .map(|ending| units.format(&ending.0, &row.token))
+                    .unwrap_or_else(|| "-".to_string()),
+                ratio(row.idle_ratio)
+            );
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests turnover and idle ratios, including tokens that moved without being funded.
+    #[test]
+    fn test_balance_utilization() {
+        let mut utilization = BalanceUtilization::new();
+        utilization.record_funded("RBTC", &BigUint::from(1_000u32));
+        utilization.record_moved("RBTC", &BigUint::from(700u32), Some(&BigUint::from(5u32)));
+        utilization.record_moved("RBTC", &BigUint::from(800u32), Some(&BigUint::from(5u32)));
+        utilization.set_ending("RBTC", BigUint::from(990u32));
+        utilization.record_moved("RIF", &BigUint::from(3u32), None);
+
+        let rows = utilization.rows();
+        assert_eq!(rows.len(), 2);
+        let rbtc = &rows[0];
+        assert_eq!(rbtc.token, "RBTC");
+        assert_eq!(rbtc.moved, BigUint::from(1_500u32));
+        assert_eq!(rbtc.fees, BigUint::from(10u32));
+        assert_eq!(rbtc.txs, 2);
+        assert_eq!(rbtc.turnover, Some(1.5));
+        assert_eq!(
+            rbtc.ending,
+            Some(BigUintSerdeWrapper(BigUint::from(990u32)))
+        );
+        assert_eq!(rbtc.idle_ratio, Some(0.99));
+
+        let rif = &rows[1];
+        assert_eq!((rif.turnover, rif.idle_ratio), (None, None));
+        let table = utilization.render_table(&AmountFormat::new());
+        assert_eq!(table.lines().count(), 3);
+        assert!(table.contains("0.00000000000000099 RBTC"));
+        assert!(table.contains(" 3 "));
+    }
+}
@@ -0,0 +1,245 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::significance::MetricInterval;
+use super::{RunSnapshot, TxStatus};
+
+/// Regressions tolerated when comparing a run with its baseline, configured in the `[baseline]` section.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BaselineTolerances {
+    /// Largest accepted drop of the achieved TPS, in percent of the baseline.
+    #[serde(default = "BaselineTolerances::default_max_tps_drop_percent")]
+    pub max_tps_drop_percent: f64,
+    /// Largest accepted increase of a response time percentile, in percent of the baseline.
+    #[serde(default = "BaselineTolerances::default_max_latency_increase_percent")]
+    pub max_latency_increase_percent: f64,
+    /// Largest accepted increase of the failure rate, in percentage points.
+    #[serde(default = "BaselineTolerances::default_max_failure_rate_increase")]
+    pub max_failure_rate_increase: f64,
+}
+
+impl BaselineTolerances {
+    fn default_max_tps_drop_percent() -> f64 {
+        10.0
+    }
+
+    fn default_max_latency_increase_percent() -> f64 {
+        20.0
+    }
+
+    fn default_max_failure_rate_increase() -> f64 {
+        1.0
+    }
+}
+
+impl Default for BaselineTolerances {
+    fn default() -> Self {
+        Self {
+            max_tps_drop_percent: Self::default_max_tps_drop_percent(),
+            max_latency_increase_percent: Self::default_max_latency_increase_percent(),
+            max_failure_rate_increase: Self::default_max_failure_rate_increase(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum BaselineError {
+    #[error("Unable to read the baseline file: {0}")]
+    Io(#[from] io::Error),
+    #[error("Unable to parse the baseline file: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("{} metric(s) regressed beyond the tolerances of the baseline", .0.len())]
+    Regressed(Vec<Regression>),
+}
+
+/// Expected metrics of a run, committed as `baseline.json` and passed with `--baseline`.
+///
+/// Latencies are response times in milliseconds, the failure rate is the percentage of
+/// submitted transactions that were rejected.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Baseline {
+    pub achieved_tps: f64,
+    pub response_p50_ms: f64,
+    pub response_p90_ms: f64,
+    pub response_p99_ms: f64,
+    pub failure_rate_percent: f64,
+    /// Settings of the run, compared with those of the next run to tell deliberate parameter
+    /// changes from environmental drift.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<toml::Table>,
+    /// Confidence intervals of the metrics, telling significant differences from noise.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub intervals: Vec<MetricInterval>,
+}
+
+/// Metric of a run that is worse than the baseline allows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regression {
+    pub metric: &'static str,
+    pub expected: f64,
+    pub actual: f64,
+    /// Worst value still accepted.
+    pub limit: f64,
+}
+
+impl fmt::Display for Regression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {:.3} (baseline {:.3}, limit {:.3})",
+            self.metric, self.actual, self.expected, self.limit
+        )
+    }
+}
+
+impl Baseline {
+    pub fn load_from_file(file_path: impl AsRef<Path>) -> Result<Self, BaselineError> {
+        let content = fs::read_to_string(file_path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Metrics of a finished run in the baseline format.
+    pub fn from_run(achieved_tps: f64, run: &RunSnapshot) -> Self {
+        let rejected = run
+            .records
+            .iter()
+            .filter(|record| record.status == TxStatus::Rejected)
+            .count();
+        let failure_rate_percent = if run.records.is_empty() {
+            0.0
+        } else {
+            rejected as f64 * 100.0 / run.records.len() as f64
+        };
+        let response_time = &run.latency.response_time;
+
+        Self {
+            achieved_tps,
+            response_p50_ms: response_time.p50,
+            response_p90_ms: response_time.p90,
+            response_p99_ms: response_time.p99,
+            failure_rate_percent,
+            config: None,
+            intervals: run.confidence.clone(),
+        }
+    }
+
+    /// Attaches the settings of the run, see [`Config::snapshot`](crate::config::Config::snapshot).
+    pub fn with_config(mut self, config: Option<toml::Table>) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Compares the metrics of a run with the baseline, failing when any of them regressed too much.
+    pub fn check(
+        &self,
+        actual: &Baseline,
+        tolerances: &BaselineTolerances,
+    ) -> Result<(), BaselineError> {
+        let latency_factor = 1.0 + tolerances.max_latency_increase_percent / 100.0;
+        let mut regressions = Vec::new();
+        let min_tps = self.achieved_tps * (1.0 - tolerances.max_tps_drop_percent / 100.0);
+        if actual.achieved_tps < min_tps {
+            regressions.push(Regression {
+                metric: "achieved_tps",
+                expected: self.achieved_tps,
+                actual: actual.achieved_tps,
+                limit: min_tps,
+            });
+        }
+        let mut at_most = |metric, expected: f64, actual: f64, limit: f64| {
+            if actual > limit {
+                regressions.push(Regression {
+                    metric,
+                    expected,
+                    actual,
+                    limit,
+                });
+            }
+        };
+
+        at_most(
+            "response_p50_ms",
+            self.response_p50_ms,
+            actual.response_p50_ms,
+            self.response_p50_ms * latency_factor,
+        );
+        at_most(
+            "response_p90_ms",
+            self.response_p90_ms,
+            actual.response_p90_ms,
+            self.response_p90_ms * latency_factor,
+        );
+        at_most(
+            "response_p99_ms",
+            self.response_p99_ms,
+            actual.response_p99_ms,
+            self.response_p99_ms * latency_factor,
+        );
+        at_most(
+            "failure_rate_percent",
+            self.failure_rate_percent,
+            actual.failure_rate_percent,
+            self.failure_rate_percent + tolerances.max_failure_rate_increase,
+        );
+
+        if regressions.is_empty() {
+            Ok(())
+        } else {
+            Err(BaselineError::Regressed(regressions))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests that only metrics worse than the baseline by more than the tolerance are reported.
+    #[test]
+    fn test_baseline_check() {
+        let baseline: Baseline = serde_json::from_str(
+            r#"{
+                "achievedTps": 50.0,
+                "responseP50Ms": 100.0,
+                "responseP90Ms": 200.0,
+                "responseP99Ms": 400.0,
+                "failureRatePercent": 0.5
+            }"#,
+        )
+        .unwrap();
+        let tolerances = BaselineTolerances::default();
+
+        let mut actual = Baseline {
+            achieved_tps: 46.0,
+            response_p50_ms: 110.0,
+            response_p90_ms: 150.0,
+            response_p99_ms: 480.0,
+            failure_rate_percent: 1.5,
+            config: None,
+            intervals: Vec::new(),
+        };
+        baseline.check(&actual, &tolerances).unwrap();
+
+        actual.achieved_tps = 44.0;
+        actual.response_p99_ms = 481.0;
+        actual.failure_rate_percent = 2.0;
+        let Err(BaselineError::Regressed(regressions)) = baseline.check(&actual, &tolerances)
+        else {
+            panic!("expected regressions");
+        };
+        assert_eq!(
+            regressions
+                .iter()
+                .map(|regression| regression.metric)
+                .collect::<Vec<_>>(),
+            vec!["achieved_tps", "response_p99_ms", "failure_rate_percent"]
+        );
+        assert_eq!(regressions[0].limit, 45.0);
+    }
+}
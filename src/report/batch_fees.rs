@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use num::{BigUint, ToPrimitive, Zero};
+use serde::Serialize;
+
+use super::units::AmountFormat;
+use crate::rollup::types::serde_wrappers::BigUintSerdeAsRadix10Str;
+use crate::rollup::types::TxFeeTypes;
+
+/// Fee quoted for a batch next to the fees of its transactions quoted one by one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchFeeQuote {
+    pub token: String,
+    /// Transactions of the batch, including any the batch needs only to carry its fee.
+    pub tx_types: Vec<TxFeeTypes>,
+    pub batch_fee: BigUint,
+    /// Sum of the fees of the transactions that would be sent without batching.
+    pub individual_fees: BigUint,
+}
+
+impl BatchFeeQuote {
+    /// Transaction types of the batch with their counts, e.g. `9 Transfer + 1 MintNFT`.
+    pub fn mix(&self) -> String {
+        let mut counts = BTreeMap::<String, usize>::new();
+        for tx_type in &self.tx_types {
+            *counts.entry(format!("{tx_type:?}")).or_default() += 1;
+        }
+        counts
+            .into_iter()
+            .map(|(name, count)| format!("{count} {name}"))
+            .collect::<Vec<_>>()
+            .join(" + ")
+    }
+}
+
+/// Batch fees against individual fees for batches of one size, mix and fee token.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchFeeRow {
+    pub token: String,
+    pub batch_size: usize,
+    pub mix: String,
+    pub batches: u64,
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    pub avg_batch_fee: BigUint,
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    pub avg_individual_fees: BigUint,
+    /// Share of the individual fees saved by batching, negative when batching costs more.
+    pub savings_percent: f64,
+}
+
+#[derive(Debug, Default)]
+struct MixTotals {
+    batches: u64,
+    batch_fees: BigUint,
+    individual_fees: BigUint,
+}
+
+/// Compares the quoted fee of every batch with the sum of the fees of its transactions
+/// sent one by one, which tells what batching saves, or costs, on this rollup.
+#[derive(Debug, Default)]
+pub struct BatchFeeFairness {
+    mixes: BTreeMap<(String, usize, String), MixTotals>,
+}
+
+impl BatchFeeFairness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, quote: &BatchFeeQuote) {
+        let key = (quote.token.clone(), quote.tx_types.len(), quote.mix());
+        let totals = self.mixes.entry(key).or_default();
+        totals.batches += 1;
+        totals.batch_fees += &quote.batch_fee;
+        totals.individual_fees += &quote.individual_fees;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mixes.is_empty()
+    }
+
+    /// One row per fee token, batch size and mix, by token and ascending batch size.
+    pub fn rows(&self) -> Vec<BatchFeeRow> {
+        self.mixes
+            .iter()
+            .map(|((token, batch_size, mix), totals)| {
+                let savings_percent = match totals.individual_fees.to_f64() {
+                    Some(individual) if !totals.individual_fees.is_zero() => {
+                        let batch = totals.batch_fees.to_f64().unwrap_or(f64::MAX);
+                        (individual - batch) / individual * 100.0
+                    }
+                    _ => 0.0,
+                };
+                BatchFeeRow {
+                    token: token.clone(),
+                    batch_size: *batch_size,
+                    mix: mix.clone(),
+                    batches: totals.batches,
+                    avg_batch_fee: &totals.batch_fees / totals.batches,
+                    avg_individual_fees: &totals.individual_fees / totals.batches,
+                    savings_percent,
+                }
+            })
+            .collect()
+    }
+
+    /// Renders the comparison as a plain text table.
+    pub fn render_table(&self, units: &AmountFormat) -> String {
+        let mut table = format!(
+            "{:>5} {:<36} {:>8} {:>24} {:>24} {:>9}\n",
+            "size", "mix", "batches", "batch fee", "individual fees", "saved %"
+        );
+        for row in self.rows() {
+            let _ = writeln!(
+                table,
+                "{:>5} {:<36} {:>8} {:>24} {:>24} {:>9.1}",
+                row.batch_size,
+                row.mix,
+                row.batches,
+                units.format(&row.avg_batch_fee, &row.token),
+                units.format(&row.avg_individual_fees, &row.token),
+                row.savings_percent
+            );
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rollup::types::ChangePubKeyFeeType;
+
+    /// Tests that batches are grouped by size and mix, and that savings turn negative when
+    /// a batch costs more than its transactions on their own.
+    #[test]
+    fn test_batch_fee_fairness() {
+        let mut fairness = BatchFeeFairness::new();
+        let transfers = |batch_fee: u32, individual_fees: u32| BatchFeeQuote {
+            token: "RBTC".to_string(),
+            tx_types: vec![TxFeeTypes::Transfer; 3],
+            batch_fee: BigUint::from(batch_fee),
+            individual_fees: BigUint::from(individual_fees),
+        };
+        fairness.record(&transfers(60, 100));
+        fairness.record(&transfers(100, 100));
+        fairness.record(&BatchFeeQuote {
+            token: "RBTC".to_string(),
+            tx_types: vec![
+                TxFeeTypes::ChangePubKey(ChangePubKeyFeeType::ECDSA),
+                TxFeeTypes::ChangePubKey(ChangePubKeyFeeType::Onchain),
+            ],
+            batch_fee: BigUint::from(110u32),
+            individual_fees: BigUint::from(100u32),
+        });
+
+        let rows = fairness.rows();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].batch_size, 2);
+        assert_eq!(
+            rows[0].mix,
+            "1 ChangePubKey(ECDSA) + 1 ChangePubKey(Onchain)"
+        );
+        assert!((rows[0].savings_percent + 10.0).abs() < 1e-9);
+        assert_eq!(rows[1].mix, "3 Transfer");
+        assert_eq!(rows[1].batches, 2);
+        assert_eq!(rows[1].avg_batch_fee, BigUint::from(80u32));
+        assert!((rows[1].savings_percent - 20.0).abs() < 1e-9);
+        assert_eq!(
+            fairness.render_table(&AmountFormat::new()).lines().count(),
+            3
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use super::latency::Percentiles;
+use crate::load_profile::BurstConfig;
+use crate::rollup::types::TxHash;
+
+#[derive(Debug, Default)]
+struct BurstWindow {
+    sent: u64,
+    rejected: u64,
+    response: Vec<Duration>,
+    /// Time from the burst start until the last response to one of its transactions.
+    responded_by: Duration,
+    committed: u64,
+    committed_by: Duration,
+}
+
+/// How one burst was absorbed, times in milliseconds from its start.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BurstRow {
+    pub index: u64,
+    pub start_secs: u64,
+    /// Transactions due within the burst, including the ones of the base rate.
+    pub sent: u64,
+    pub rejected: u64,
+    pub response: Percentiles,
+    /// Until the server responded to every transaction of the burst.
+    pub response_drain_ms: f64,
+    pub committed: u64,
+    /// Until every accepted transaction of the burst was committed, `None` while some are not.
+    pub commit_drain_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BurstSummary {
+    pub rows: Vec<BurstRow>,
+    /// Response times of the transactions sent between bursts, at the sustained rate.
+    pub sustained_response: Percentiles,
+}
+
+impl BurstSummary {
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+}
+
+/// Drain times of the bursts layered over the load profile.
+///
+/// Every transaction due within a burst window counts towards that burst. A burst is drained
+/// once the server responded to all of its transactions, and committed them; comparing the
+/// response times within bursts to the ones in between tells how the rollup absorbs spikes
+/// compared to sustained load.
+#[derive(Debug, Default)]
+pub struct BurstProbe {
+    schedule: Option<(Instant, BurstConfig)>,
+    bursts: BTreeMap<u64, BurstWindow>,
+    /// Accepted burst transactions awaiting their commit, with their burst and due offset
+    /// from its start.
+    pending: HashMap<TxHash, (u64, Duration)>,
+    sustained: Vec<Duration>,
+}
+
+impl BurstProbe {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts probing the bursts of a run whose schedule started at `started`.
+    pub fn start(&mut self, started: Instant, bursts: BurstConfig) {
+        self.schedule = Some((started, bursts));
+    }
+
+    /// Burst the transaction due at `due` falls in and its offset from the burst start.
+    fn burst_of(&self, due: Instant) -> Option<(u64, Duration)> {
+        let (started, bursts) = self.schedule?;
+        let elapsed = due.saturating_duration_since(started);
+        let index = bursts.window(elapsed)?;
+        Some((index, elapsed - bursts.start(index)))
+    }
+
+    /// Records the response to a transaction due at `due`, `tx_hash` is `None` when it was rejected.
+    pub fn record_response(
+        &mut self,
+        due: Instant,
+        response_time: Duration,
+        tx_hash: Option<TxHash>,
+    ) {
+        if self.schedule.is_none() {
+            return;
+        }
+        let Some((index, offset)) = self.burst_of(due) else {
+            if tx_hash.is_some() {
+                self.sustained.push(response_time);
+            }
+            return;
+        };
+        let burst = self.bursts.entry(index).or_default();
+        burst.sent += 1;
+        burst.responded_by = burst.responded_by.max(offset + response_time);
+        match tx_hash {
+            Some(tx_hash) => {
+                burst.response.push(response_time);
+                self.pending.insert(tx_hash, (index, offset));
+            }
+            None => burst.rejected += 1,
+        }
+    }
+
+    /// Records the commit of a transaction, `latency` measured from its submission.
+    pub fn record_commit(&mut self, tx_hash: &TxHash, latency: Duration) {
+        let Some((index, offset)) = self.pending.remove(tx_hash) else {
+            return;
+        };
+        if let Some(burst) = self.bursts.get_mut(&index) {
+            burst.committed += 1;
+            burst.committed_by = burst.committed_by.max(offset + latency);
+        }
+    }
+
+    pub fn summary(&self) -> BurstSummary {
+        let Some((_, bursts)) = self.schedule else {
+            return BurstSummary::default();
+        };
+        let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        let rows = self
+            .bursts
+            .iter()
+            .map(|(index, burst)| {
+                let accepted = burst.sent - burst.rejected;
+                BurstRow {
+                    index: *index,
+                    start_secs: bursts.start(*index).as_secs(),
+                    sent: burst.sent,
+                    rejected: burst.rejected,
+                    response: Percentiles::from_samples(&burst.response),
+                    response_drain_ms: millis(burst.responded_by),
+                    committed: burst.committed,
+                    commit_drain_ms: (burst.committed == accepted)
+                        .then(|| millis(burst.committed_by)),
+                }
+            })
+            .collect();
+        BurstSummary {
+            rows,
+            sustained_response: Percentiles::from_samples(&self.sustained),
+        }
+    }
+
+    /// Renders the drain times of every burst as a plain text table.
+    pub fn render_table(&self) -> String {
+        let summary = self.summary();
+        let mut table = format!(
+            "{:>6} {:>8} {:>6} {:>8} {:>10} {:>10} {:>16} {:>16}\n",
+            "burst",
+            "start_s",
+            "sent",
+            "rejected",
+            "resp_p50",
+            "resp_p99",
+            "response_drain",
+            "commit_drain"
+        );
+        for row in &summary.rows {
+            let commit_drain = row
+                .commit_drain_ms
+                .map_or_else(|| "-".to_string(), |drain| format!("{:.1}", drain));
+            let _ = writeln!(
+                table,
+                "{:>6} {:>8} {:>6} {:>8} {:>10.1} {:>10.1} {:>16.1} {:>16}",
+                row.index,
+                row.start_secs,
+                row.sent,
+                row.rejected,
+                row.response.p50,
+                row.response.p99,
+                row.response_drain_ms,
+                commit_drain
+            );
+        }
+        let _ = writeln!(
+            table,
+            "Sustained load between bursts: response p50 {:.1} ms, p99 {:.1} ms",
+            summary.sustained_response.p50, summary.sustained_response.p99
+        );
+        table
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests assigning transactions to burst windows and measuring their drain times.
+    #[test]
+    fn test_burst_drains() {
+        let bursts = BurstConfig {
+            txs: 500,
+            within_ms: 1_000,
+            every_secs: 60,
+        };
+        assert_eq!(bursts.window(Duration::from_secs(30)), None);
+        assert_eq!(bursts.window(Duration::from_millis(60_500)), Some(0));
+        assert_eq!(bursts.window(Duration::from_millis(121_000)), None);
+        assert_eq!(bursts.window(Duration::from_millis(120_999)), Some(1));
+
+        let started = Instant::now();
+        let at = |ms: u64| started + Duration::from_millis(ms);
+        let hash = |byte: u8| TxHash { data: [byte; 32] };
+        let mut probe = BurstProbe::new();
+        probe.record_response(at(60_000), Duration::from_millis(10), Some(hash(1)));
+        assert!(probe.summary().is_empty());
+
+        probe.start(started, bursts);
+        probe.record_response(at(30_000), Duration::from_millis(20), Some(hash(1)));
+        probe.record_response(at(60_000), Duration::from_millis(100), Some(hash(2)));
+        probe.record_response(at(60_900), Duration::from_millis(300), Some(hash(3)));
+        probe.record_response(at(60_950), Duration::from_millis(50), None);
+        probe.record_response(at(120_100), Duration::from_millis(40), Some(hash(4)));
+        probe.record_commit(&hash(1), Duration::from_secs(5));
+        probe.record_commit(&hash(2), Duration::from_secs(2));
+        probe.record_commit(&hash(3), Duration::from_secs(3));
+
+        let summary = probe.summary();
+        assert_eq!(summary.sustained_response.p50, 20.0);
+        let first = &summary.rows[0];
+        assert_eq!((first.start_secs, first.sent, first.rejected), (60, 3, 1));
+        assert_eq!(first.response_drain_ms, 1_200.0);
+        assert_eq!(first.commit_drain_ms, Some(3_900.0));
+        let second = &summary.rows[1];
+        assert_eq!((second.index, second.start_secs), (1, 120));
+        assert_eq!(second.commit_drain_ms, None);
+        assert_eq!(probe.render_table().lines().count(), 4);
+    }
+}
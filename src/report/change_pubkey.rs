@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use num::{BigUint, Zero};
+use serde::Serialize;
+
+use super::units::AmountFormat;
+use crate::rollup::types::serde_wrappers::BigUintSerdeAsRadix10Str;
+use crate::rollup::types::{ChangePubKeyFeeType, TxHash};
+
+#[derive(Debug, Default)]
+struct VariantStats {
+    succeeded: u64,
+    failed: u64,
+    total_fee: BigUint,
+    total_latency: Duration,
+    max_latency: Duration,
+}
+
+/// One row of the `ChangePubKey` coverage table.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangePubKeyCoverageRow {
+    pub fee_type: ChangePubKeyFeeType,
+    pub submitted: u64,
+    pub succeeded: u64,
+    pub failed: u64,
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    pub avg_fee: BigUint,
+    pub avg_latency_ms: f64,
+    pub max_latency_ms: f64,
+}
+
+/// Tracks which `ChangePubKeyFeeType` variants were exercised during a run.
+///
+/// The Onchain and CREATE2 paths differ substantially from the common ECDSA one,
+/// so variants that were never submitted are reported explicitly. Accepted transactions
+/// count once their outcome is known, their latency running until then.
+#[derive(Debug, Default)]
+pub struct ChangePubKeyCoverage {
+    variants: HashMap<ChangePubKeyFeeType, VariantStats>,
+    pending: HashMap<TxHash, (ChangePubKeyFeeType, BigUint, Instant)>,
+    fee_token: Option<String>,
+}
+
+impl ChangePubKeyCoverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the token the `ChangePubKey` fees are paid in, to write them in whole units.
+    pub fn set_fee_token(&mut self, token: &str) {
+        self.fee_token = Some(token.to_string());
+    }
+
+    pub fn record(
+        &mut self,
+        fee_type: ChangePubKeyFeeType,
+        fee: &BigUint,
+        latency: Duration,
+        success: bool,
+    ) {
+        let stats = self.variants.entry(fee_type).or_default();
+        if success {
+            stats.succeeded += 1;
+        } else {
+            stats.failed += 1;
+        }
+        stats.total_fee += fee;
+        stats.total_latency += latency;
+        stats.max_latency = stats.max_latency.max(latency);
+    }
+
+    /// Remembers a `ChangePubKey` accepted by the server until [`Self::resolve`] learns its outcome.
+    pub fn submitted(&mut self, tx_hash: TxHash, fee_type: ChangePubKeyFeeType, fee: BigUint) {
+        self.pending
+            .insert(tx_hash, (fee_type, fee, Instant::now()));
+    }
+
+    /// Records the outcome of an accepted `ChangePubKey`, ignoring hashes that are not one.
+    pub fn resolve(&mut self, tx_hash: &TxHash, success: bool) {
+        if let Some((fee_type, fee, submitted_at)) = self.pending.remove(tx_hash) {
+            self.record(fee_type, &fee, submitted_at.elapsed(), success);
+        }
+    }
+
+    /// Variants that were not submitted a single time.
+    pub fn untested(&self) -> Vec<ChangePubKeyFeeType> {
+        ChangePubKeyFeeType::ALL
+            .into_iter()
+            .filter(|fee_type| !self.variants.contains_key(fee_type))
+            .collect()
+    }
+
+    /// Per-variant statistics, including the variants that were never exercised.
+    pub fn rows(&self) -> Vec<ChangePubKeyCoverageRow> {
+        ChangePubKeyFeeType::ALL
+            .into_iter()
+            .map(|fee_type| {
+                let empty = VariantStats::default();
+                let stats = self.variants.get(&fee_type).unwrap_or(&empty);
+                let submitted = stats.succeeded + stats.failed;
+                let (avg_fee, avg_latency_ms) = if submitted == 0 {
+                    (BigUint::zero(), 0.0)
+                } else {
+                    (
+                        &stats.total_fee / submitted,
+                        stats.total_latency.as_secs_f64() * 1000.0 / submitted as f64,
+                    )
+                };
+
+                ChangePubKeyCoverageRow {
+                    fee_type,
+                    submitted,
+                    succeeded: stats.succeeded,
+                    failed: stats.failed,
+                    avg_fee,
+                    avg_latency_ms,
+                    max_latency_ms: stats.max_latency.as_secs_f64() * 1000.0,
+                }
+            })
+            .collect()
+    }
+
+    /// Renders the coverage as a plain text table.
+    pub fn render_table(&self, units: &AmountFormat) -> String {
+        let mut table = format!(
+            "{:<8} {:>9} {:>9} {:>7} {:>24} {:>12} {:>12}\n",
+            "variant", "submitted", "succeeded", "failed", "avg fee", "avg ms", "max ms"
+        );
+        for row in self.rows() {
+            let _ = writeln!(
+                table,
+                "{:<8} {:>9} {:>9} {:>7} {:>24} {:>12.1} {:>12.1}",
+                format!("{:?}", row.fee_type),
+                row.submitted,
+                row.succeeded,
+                row.failed,
+                match &self.fee_token {
+                    Some(token) => units.format(&row.avg_fee, token),
+                    None => row.avg_fee.to_string(),
+                },
+                row.avg_latency_ms,
+                row.max_latency_ms
+            );
+        }
+        for fee_type in self.untested() {
+            let _ = writeln!(
+                table,
+                "WARNING: {:?} ChangePubKey was never exercised",
+                fee_type
+            );
+        }
+        table
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use toml::{Table, Value};
+
+/// Setting whose value differs between two runs, `None` when it is not set in that run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigChange {
+    /// Dotted path of the key, e.g. `general.tps`.
+    pub path: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+impl fmt::Display for ConfigChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |value: &Option<Value>| match value {
+            Some(value) => value.to_string(),
+            None => "(unset)".to_string(),
+        };
+        write!(
+            f,
+            "{}: {} -> {}",
+            self.path,
+            show(&self.before),
+            show(&self.after)
+        )
+    }
+}
+
+/// Keys of two configuration snapshots whose values differ, sorted by path.
+///
+/// Tables are compared key by key, so a changed setting is reported on its own rather
+/// than as a change of its whole section; arrays are compared as a single value.
+pub fn diff(before: &Table, after: &Table) -> Vec<ConfigChange> {
+    let mut changes = Vec::new();
+    diff_tables("", before, after, &mut changes);
+    changes
+}
+
+fn diff_tables(prefix: &str, before: &Table, after: &Table, changes: &mut Vec<ConfigChange>) {
+    let keys: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    for key in keys {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match (before.get(key), after.get(key)) {
+            (Some(Value::Table(before)), Some(Value::Table(after))) => {
+                diff_tables(&path, before, after, changes)
+            }
+            (before, after) if before != after => changes.push(ConfigChange {
+                path,
+                before: before.cloned(),
+                after: after.cloned(),
+            }),
+            _ => {}
+        }
+    }
+}
+
+/// Renders the changes one per line, or a note that the configurations are identical.
+pub fn render(changes: &[ConfigChange]) -> String {
+    if changes.is_empty() {
+        return "  (no changes)\n".to_string();
+    }
+    changes
+        .iter()
+        .map(|change| format!("  {change}\n"))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests that only changed, added and removed keys are reported, with their dotted path.
+    #[test]
+    fn test_config_diff() {
+        let before: Table = toml::from_str(
+            r#"
+            [general]
+            tps = 100
+            account_count = 10
+            [transaction]
+            tokens = ["RBTC"]
+            [chaos]
+            drop_percent = 5
+            "#,
+        )
+        .unwrap();
+        let after: Table = toml::from_str(
+            r#"
+            [general]
+            tps = 200
+            account_count = 10
+            dry_run = true
+            [transaction]
+            tokens = ["RBTC", "RIF"]
+            "#,
+        )
+        .unwrap();
+
+        let changes = diff(&before, &after);
+        assert_eq!(
+            changes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec![
+                "chaos: { drop_percent = 5 } -> (unset)",
+                "general.dry_run: (unset) -> true",
+                "general.tps: 100 -> 200",
+                r#"transaction.tokens: ["RBTC"] -> ["RBTC", "RIF"]"#,
+            ]
+        );
+        assert!(diff(&after, &after).is_empty());
+    }
+}
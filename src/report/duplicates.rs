@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::rollup::types::TxHash;
+
+/// How a repeated transaction hash relates to the transaction first seen with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DuplicateKind {
+    /// The same payload was answered with the same hash again, the server deduplicated a resend.
+    Repeated,
+    /// Different payloads were answered with the same hash, a serialization or hashing bug on
+    /// one side.
+    Collision,
+    /// The pipeline does not expose payloads, so the two submissions cannot be compared.
+    Unknown,
+}
+
+impl DuplicateKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            DuplicateKind::Repeated => "repeated",
+            DuplicateKind::Collision => "collision",
+            DuplicateKind::Unknown => "unknown",
+        }
+    }
+}
+
+/// Transaction hash that `send_tx` returned more than once in the run.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateHash {
+    pub tx_hash: TxHash,
+    pub kind: DuplicateKind,
+    pub first_type: String,
+    pub tx_type: String,
+    /// Payload first submitted with the hash.
+    pub first_payload: Option<String>,
+    /// Payload submitted when the hash came back again.
+    pub payload: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateHashSummary {
+    pub repeated: u64,
+    pub collisions: u64,
+    pub unknown: u64,
+    pub duplicates: Vec<DuplicateHash>,
+}
+
+impl DuplicateHashSummary {
+    pub fn is_empty(&self) -> bool {
+        self.duplicates.is_empty()
+    }
+}
+
+#[derive(Debug)]
+struct SeenTx {
+    tx_type: String,
+    payload: Option<String>,
+}
+
+/// Remembers every hash `send_tx` returned and flags the ones it returns again.
+///
+/// Every signed transaction carries a fresh nonce, so a hash seen twice means either the
+/// simulator sent the same bytes twice or the server hashed two different transactions
+/// alike. Both payloads are kept so the colliding pair can be analysed after the run.
+#[derive(Debug, Default)]
+pub struct DuplicateHashes {
+    seen: HashMap<TxHash, SeenTx>,
+    duplicates: Vec<DuplicateHash>,
+}
+
+impl DuplicateHashes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a hash returned by the server, returning the duplicate when it was already seen.
+    pub fn record(
+        &mut self,
+        tx_hash: TxHash,
+        tx_type: &str,
+        payload: Option<String>,
+    ) -> Option<&DuplicateHash> {
+        let Some(first) = self.seen.get(&tx_hash) else {
+            self.seen.insert(
+                tx_hash,
+                SeenTx {
+                    tx_type: tx_type.to_string(),
+                    payload,
+                },
+            );
+            return None;
+        };
+        let kind = match (&first.payload, &payload) {
+            (Some(first), Some(payload)) if first == payload => DuplicateKind::Repeated,
+            (Some(_), Some(_)) => DuplicateKind::Collision,
+            _ => DuplicateKind::Unknown,
+        };
+        self.duplicates.push(DuplicateHash {
+            tx_hash,
+            kind,
+            first_type: first.tx_type.clone(),
+            tx_type: tx_type.to_string(),
+            first_payload: first.payload.clone(),
+            payload,
+        });
+        self.duplicates.last()
+    }
+
+    pub fn summary(&self) -> DuplicateHashSummary {
+        let count = |kind| {
+            self.duplicates
+                .iter()
+                .filter(|duplicate| duplicate.kind == kind)
+                .count() as u64
+        };
+        DuplicateHashSummary {
+            repeated: count(DuplicateKind::Repeated),
+            collisions: count(DuplicateKind::Collision),
+            unknown: count(DuplicateKind::Unknown),
+            duplicates: self.duplicates.clone(),
+        }
+    }
+
+    /// Renders the duplicated hashes as a plain text table, payloads are left to the JSON report.
+    pub fn render_table(&self) -> String {
+        let mut table = format!(
+            "{:<74} {:<10} {:<16} {:<16}\n",
+            "duplicate tx hash", "kind", "first type", "type"
+        );
+        for duplicate in &self.duplicates {
+            let _ = writeln!(
+                table,
+                "{:<74} {:<10} {:<16} {:<16}",
+                duplicate.tx_hash.to_string(),
+                duplicate.kind.name(),
+                duplicate.first_type,
+                duplicate.tx_type
+            );
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests that repeated hashes are classified by comparing the payloads submitted with them.
+    #[test]
+    fn test_duplicate_hashes() {
+        let hash = |byte| TxHash { data: [byte; 32] };
+        let mut duplicates = DuplicateHashes::new();
+        assert!(duplicates
+            .record(hash(1), "transfer", Some("a".to_string()))
+            .is_none());
+        assert!(duplicates
+            .record(hash(2), "withdraw", Some("b".to_string()))
+            .is_none());
+        assert!(duplicates.record(hash(3), "transfer", None).is_none());
+
+        let repeated = duplicates
+            .record(hash(1), "transfer", Some("a".to_string()))
+            .unwrap();
+        assert_eq!(repeated.kind, DuplicateKind::Repeated);
+
+        let collision = duplicates
+            .record(hash(2), "transfer", Some("c".to_string()))
+            .unwrap()
+            .clone();
+        assert_eq!(collision.kind, DuplicateKind::Collision);
+        assert_eq!(collision.first_type, "withdraw");
+        assert_eq!(collision.first_payload.as_deref(), Some("b"));
+        assert_eq!(collision.payload.as_deref(), Some("c"));
+
+        assert_eq!(
+            duplicates
+                .record(hash(3), "transfer", Some("d".to_string()))
+                .unwrap()
+                .kind,
+            DuplicateKind::Unknown
+        );
+
+        let summary = duplicates.summary();
+        assert_eq!(
+            (summary.repeated, summary.collisions, summary.unknown),
+            (1, 1, 1)
+        );
+        assert_eq!(duplicates.render_table().lines().count(), 4);
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+
+use ethers::types::Address;
+use serde::Serialize;
+
+use crate::tagging::TAG_MAGIC;
+
+/// File the account labels of a run are written to, in the run's report directory.
+pub const EXPLORER_LABELS_FILE: &str = "explorer_labels.json";
+
+/// Aliases of the simulator accounts of one run, written as
+/// `{ "<run id>": { "<address>": "<alias>" } }` for the explorer tagging tool, so the
+/// accounts can be told apart from organic ones while the run is going on.
+///
+/// Aliases are `rsim-<run id>-<account index>`, which keeps the labels of different runs
+/// apart when the same accounts are reused.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct ExplorerLabels {
+    runs: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl ExplorerLabels {
+    pub fn new(run_id: &str, addresses: &[Address]) -> Self {
+        let prefix = String::from_utf8_lossy(TAG_MAGIC);
+        let labels = addresses
+            .iter()
+            .enumerate()
+            .map(|(index, address)| {
+                (
+                    format!("{address:?}"),
+                    format!("{prefix}-{run_id}-{index:04}"),
+                )
+            })
+            .collect();
+        Self {
+            runs: BTreeMap::from([(run_id.to_string(), labels)]),
+        }
+    }
+
+    /// Number of labeled accounts.
+    pub fn len(&self) -> usize {
+        self.runs.values().map(BTreeMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests that every address is labeled with the run and its account index, under the run.
+    #[test]
+    fn test_explorer_labels() {
+        let addresses = [Address::from_low_u64_be(7), Address::from_low_u64_be(3)];
+        let labels = ExplorerLabels::new("nightly", &addresses);
+        assert_eq!(labels.len(), 2);
+
+        let json: serde_json::Value = serde_json::from_str(&labels.to_json().unwrap()).unwrap();
+        let run = json["nightly"].as_object().unwrap();
+        assert_eq!(run.len(), 2);
+        assert_eq!(
+            run[&format!("{:?}", addresses[0])],
+            serde_json::json!("rsim-nightly-0000")
+        );
+        assert_eq!(
+            run[&format!("{:?}", addresses[1])],
+            serde_json::json!("rsim-nightly-0001")
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+use ethers::types::{Address, U256};
+use num::BigUint;
+
+use super::units::AmountFormat;
+use crate::funding::{AssetFunding, FundingSummary};
+
+/// Totals of one asset over the funded accounts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FundingReportRow {
+    pub asset: String,
+    pub accounts: usize,
+    pub l1_sent: BigUint,
+    pub deposited: BigUint,
+}
+
+/// Outcome of the `fund` command: what every asset cost the master wallet and which
+/// accounts could not be funded.
+#[derive(Debug, Clone)]
+pub struct FundingReport {
+    pub master: Address,
+    pub rows: Vec<FundingReportRow>,
+    pub failed: Vec<(Address, String)>,
+    pub elapsed: Duration,
+}
+
+impl FundingReport {
+    pub fn new(master: Address, summary: &FundingSummary, assets: &[AssetFunding]) -> Self {
+        let accounts = summary.funded.len();
+        let rows = assets
+            .iter()
+            .map(|funding| FundingReportRow {
+                asset: funding.asset.symbol().to_string(),
+                accounts,
+                l1_sent: u256_to_biguint(funding.l1_amount) * accounts,
+                deposited: u256_to_biguint(funding.deposit_amount) * accounts,
+            })
+            .collect();
+        Self {
+            master,
+            rows,
+            failed: summary
+                .failed
+                .iter()
+                .map(|(address, err)| (*address, err.to_string()))
+                .collect(),
+            elapsed: summary.elapsed,
+        }
+    }
+
+    /// Renders the report as a plain text table followed by the failed accounts.
+    pub fn render_table(&self, units: &AmountFormat) -> String {
+        let mut table = format!(
+            "Funded by {:?} in {:.1}s\n{:<10} {:>9} {:>28} {:>28}\n",
+            self.master,
+            self.elapsed.as_secs_f64(),
+            "asset",
+            "accounts",
+            "sent on L1",
+            "deposited"
+        );
+        for row in &self.rows {
+            let _ = writeln!(
+                table,
+                "{:<10} {:>9} {:>28} {:>28}",
+                row.asset,
+                row.accounts,
+                units.format(&row.l1_sent, &row.asset),
+                units.format(&row.deposited, &row.asset)
+            );
+        }
+        if !self.failed.is_empty() {
+            let _ = writeln!(table, "{} accounts failed:", self.failed.len());
+            for (address, err) in &self.failed {
+                let _ = writeln!(table, "  {:?}: {}", address, err);
+            }
+        }
+        table
+    }
+}
+
+fn u256_to_biguint(value: U256) -> BigUint {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    BigUint::from_bytes_be(&bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::funding::{Erc20Funding, FundingConfig, FundingError};
+
+    /// Tests that the totals of every asset only count the funded accounts and are written
+    /// in whole units of their token.
+    #[test]
+    fn test_funding_report() {
+        let config = FundingConfig {
+            l1_amount_wei: 2_000_000_000_000_000_000,
+            deposit_amount_wei: 500_000_000_000_000_000,
+            erc20: vec![Erc20Funding {
+                token: "RDOC".to_string(),
+                address: Address::repeat_byte(0xd0),
+                decimals: 2,
+                l1_amount: 150,
+                deposit_amount: 100,
+            }],
+            ..Default::default()
+        };
+        let summary = FundingSummary {
+            funded: vec![Address::from_low_u64_be(1), Address::from_low_u64_be(2)],
+            failed: vec![(
+                Address::from_low_u64_be(3),
+                FundingError::Deposit("reverted".to_string()),
+            )],
+            elapsed: Duration::from_secs(3),
+        };
+        let report = FundingReport::new(Address::zero(), &summary, &config.assets());
+        assert_eq!(report.rows[1].l1_sent, BigUint::from(300u32));
+
+        let mut units = AmountFormat::new();
+        units.add_token("RDOC", 2);
+        let table = report.render_table(&units);
+        assert!(table.contains("4 RBTC"), "{table}");
+        assert!(table.contains("1 RBTC"), "{table}");
+        assert!(table.contains("3 RDOC"), "{table}");
+        assert!(table.contains("1 accounts failed"), "{table}");
+        assert!(table.contains("Deposit failed: reverted"), "{table}");
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// Buckets recorded exactly, above them every power of two is split into `SUB_BUCKETS / 2`
+/// buckets, which keeps three significant digits.
+const SUB_BUCKETS: u64 = 2_048;
+const HALF_SUB_BUCKETS: u64 = SUB_BUCKETS / 2;
+
+fn bucket_of(value: u64) -> u32 {
+    if value < SUB_BUCKETS {
+        return value as u32;
+    }
+    // Shifted right by `shift`, the value falls in the upper half of the sub-buckets.
+    let shift = 64 - value.leading_zeros() as u64 - SUB_BUCKETS.trailing_zeros() as u64;
+    (shift * HALF_SUB_BUCKETS + (value >> shift)) as u32
+}
+
+/// Lowest and highest value counted in a bucket.
+fn bucket_range(bucket: u32) -> (u64, u64) {
+    let bucket = bucket as u64;
+    if bucket < SUB_BUCKETS {
+        return (bucket, bucket);
+    }
+    let shift = bucket / HALF_SUB_BUCKETS - 1;
+    let lowest = (bucket - shift * HALF_SUB_BUCKETS) << shift;
+    (lowest, lowest + ((1 << shift) - 1))
+}
+
+/// Latency distribution with three significant digits, in the manner of an HDR histogram.
+///
+/// Latencies are counted in microsecond buckets whose width grows with the value, so the
+/// memory used depends on the range of latencies and not on how many were recorded. A
+/// percentile is the highest latency of the bucket holding it, off by at most 0.1%.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistogram {
+    buckets: BTreeMap<u32, u64>,
+    count: u64,
+    total_micros: u128,
+    max_micros: u64,
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, latency: Duration) {
+        let micros = u64::try_from(latency.as_micros()).unwrap_or(u64::MAX);
+        *self.buckets.entry(bucket_of(micros)).or_default() += 1;
+        self.count += 1;
+        self.total_micros += micros as u128;
+        self.max_micros = self.max_micros.max(micros);
+    }
+
+    pub fn len(&self) -> u64 {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Latency `quantile` of the recorded ones are at or below, zero when nothing was recorded.
+    pub fn value_at_quantile(&self, quantile: f64) -> Duration {
+        let rank = ((self.count as f64) * quantile).ceil() as u64;
+        let rank = rank.clamp(1, self.count.max(1));
+        let mut seen = 0;
+        for (bucket, count) in &self.buckets {
+            seen += count;
+            if seen >= rank {
+                let (_, highest) = bucket_range(*bucket);
+                return Duration::from_micros(highest.min(self.max_micros));
+            }
+        }
+        Duration::ZERO
+    }
+
+    pub fn quantiles(&self) -> LatencyQuantiles {
+        if self.is_empty() {
+            return LatencyQuantiles::default();
+        }
+        let millis = |latency: Duration| latency.as_secs_f64() * 1000.0;
+        LatencyQuantiles {
+            count: self.count,
+            mean: self.total_micros as f64 / self.count as f64 / 1000.0,
+            p50: millis(self.value_at_quantile(0.50)),
+            p90: millis(self.value_at_quantile(0.90)),
+            p99: millis(self.value_at_quantile(0.99)),
+            p999: millis(self.value_at_quantile(0.999)),
+            max: self.max_micros as f64 / 1000.0,
+        }
+    }
+}
+
+/// Latency distribution summary of a histogram, all values in milliseconds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LatencyQuantiles {
+    pub count: u64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub p999: f64,
+    pub max: f64,
+}
+
+/// Point in the life of an operation latency is measured up to, always from its submission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LatencyStage {
+    /// Response of the server to the submission, including any delay of a late schedule.
+    Submission,
+    Commit,
+    Verify,
+}
+
+impl LatencyStage {
+    pub fn label(self) -> &'static str {
+        match self {
+            LatencyStage::Submission => "submission",
+            LatencyStage::Commit => "commit",
+            LatencyStage::Verify => "verify",
+        }
+    }
+}
+
+/// Latencies of one operation type up to one stage.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StageLatencyRow {
+    pub tx_type: String,
+    pub stage: LatencyStage,
+    pub latency: LatencyQuantiles,
+}
+
+/// Latencies of all operation types up to one stage, recorded within one window of the run.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyWindowRow {
+    /// Start of the window, in seconds since the run started.
+    pub start_secs: u64,
+    pub stage: LatencyStage,
+    pub latency: LatencyQuantiles,
+}
+
+#[derive(Debug)]
+struct Timeline {
+    started: Instant,
+    window: Duration,
+    windows: BTreeMap<(u64, LatencyStage), LatencyHistogram>,
+}
+
+/// Histograms of the submission, commit and verify latencies per operation type.
+///
+/// With a timeline started, latencies are also grouped by the window of the run they were
+/// observed in, over all operation types, to show how latency drifts during the run.
+#[derive(Debug, Default)]
+pub struct StageLatencies {
+    by_type: BTreeMap<(String, LatencyStage), LatencyHistogram>,
+    timeline: Option<Timeline>,
+}
+
+impl StageLatencies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Groups latencies observed from `started` on into windows of `window`.
+    pub fn start_timeline(&mut self, started: Instant, window: Duration) {
+        self.timeline = Some(Timeline {
+            started,
+            window,
+            windows: BTreeMap::new(),
+        });
+    }
+
+    /// Records the latency of an operation that reached `stage` at `observed`.
+    pub fn record(
+        &mut self,
+        tx_type: &str,
+        stage: LatencyStage,
+        latency: Duration,
+        observed: Instant,
+    ) {
+        self.by_type
+            .entry((tx_type.to_string(), stage))
+            .or_default()
+            .record(latency);
+        if let Some(timeline) = &mut self.timeline {
+            let elapsed = observed.saturating_duration_since(timeline.started);
+            let index = (elapsed.as_secs_f64() / timeline.window.as_secs_f64()) as u64;
+            timeline
+                .windows
+                .entry((index, stage))
+                .or_default()
+                .record(latency);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_type.is_empty()
+    }
+
+    pub fn rows(&self) -> Vec<StageLatencyRow> {
+        self.by_type
+            .iter()
+            .map(|((tx_type, stage), histogram)| StageLatencyRow {
+                tx_type: tx_type.clone(),
+                stage: *stage,
+                latency: histogram.quantiles(),
+            })
+            .collect()
+    }
+
+    /// Latencies per window of the timeline, empty when it was not started.
+    pub fn timeline(&self) -> Vec<LatencyWindowRow> {
+        let Some(timeline) = &self.timeline else {
+            return Vec::new();
+        };
+        timeline
+            .windows
+            .iter()
+            .map(|((index, stage), histogram)| LatencyWindowRow {
+                start_secs: timeline.window.as_secs() * index,
+                stage: *stage,
+                latency: histogram.quantiles(),
+            })
+            .collect()
+    }
+
+    /// Renders the latencies of every operation type and stage as a plain text table.
+    pub fn render_table(&self) -> String {
+        let mut table = format!(
+            "{:<16} {:<10} {:>8} {:>10} {:>10} {:>10} {:>10} {:>10}\n",
+            "tx_type", "stage", "count", "p50_ms", "p90_ms", "p99_ms", "p999_ms", "max_ms"
+        );
+        for row in self.rows() {
+            let _ = writeln!(
+                table,
+                "{:<16} {:<10} {:>8} {:>10.1} {:>10.1} {:>10.1} {:>10.1} {:>10.1}",
+                row.tx_type,
+                row.stage.label(),
+                row.latency.count,
+                row.latency.p50,
+                row.latency.p90,
+                row.latency.p99,
+                row.latency.p999,
+                row.latency.max
+            );
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests the bucket precision, the quantiles and the grouping by type, stage and window.
+    #[test]
+    fn test_stage_latencies() {
+        for value in [0, 1, 2_047, 2_048, 4_095, 4_096, 1_234_567, u64::MAX] {
+            let (lowest, highest) = bucket_range(bucket_of(value));
+            assert!(lowest <= value && value <= highest, "{value}");
+            assert!(highest - lowest <= value / HALF_SUB_BUCKETS, "{value}");
+        }
+
+        let mut histogram = LatencyHistogram::new();
+        assert_eq!(histogram.quantiles(), LatencyQuantiles::default());
+        for millis in 1..=1_000u64 {
+            histogram.record(Duration::from_millis(millis));
+        }
+        let quantiles = histogram.quantiles();
+        assert_eq!(quantiles.count, 1_000);
+        assert_eq!(quantiles.mean, 500.5);
+        assert!((quantiles.p50 - 500.0).abs() <= 0.5, "{}", quantiles.p50);
+        assert!((quantiles.p999 - 999.0).abs() <= 1.0, "{}", quantiles.p999);
+        assert_eq!(quantiles.max, 1_000.0);
+
+        let started = Instant::now();
+        let at = |secs: u64| started + Duration::from_secs(secs);
+        let mut latencies = StageLatencies::new();
+        latencies.record(
+            "transfer",
+            LatencyStage::Submission,
+            Duration::from_millis(5),
+            at(0),
+        );
+        assert!(latencies.timeline().is_empty());
+
+        latencies.start_timeline(started, Duration::from_secs(10));
+        latencies.record(
+            "transfer",
+            LatencyStage::Submission,
+            Duration::from_millis(15),
+            at(3),
+        );
+        latencies.record(
+            "withdraw",
+            LatencyStage::Submission,
+            Duration::from_millis(40),
+            at(12),
+        );
+        latencies.record(
+            "transfer",
+            LatencyStage::Commit,
+            Duration::from_secs(2),
+            at(14),
+        );
+        let rows = latencies.rows();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            (rows[0].stage, rows[0].latency.count),
+            (LatencyStage::Submission, 2)
+        );
+        assert_eq!(
+            (rows[1].stage, rows[1].latency.max),
+            (LatencyStage::Commit, 2_000.0)
+        );
+        assert_eq!(rows[2].tx_type, "withdraw");
+
+        let timeline = latencies.timeline();
+        let windows: Vec<(u64, LatencyStage)> = timeline
+            .iter()
+            .map(|row| (row.start_secs, row.stage))
+            .collect();
+        assert_eq!(
+            windows,
+            [
+                (0, LatencyStage::Submission),
+                (10, LatencyStage::Submission),
+                (10, LatencyStage::Commit)
+            ]
+        );
+        assert_eq!(latencies.render_table().lines().count(), 4);
+    }
+}
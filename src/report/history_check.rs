@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::rollup::adapters::v02::MAX_PAGE_LIMIT;
+use crate::rollup::provider::{Provider, ResponseResult};
+use crate::rollup::types::pagination::TxHistoryCursor;
+use crate::rollup::types::{AccountTx, Address, PaginationQuery, TxHash};
+
+/// Result of cross-checking submitted transactions against the history reported by the server.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryCheck {
+    pub checked: usize,
+    /// Submitted transactions missing from the history.
+    pub missing: Vec<TxHash>,
+    /// Submitted transactions listed more than once, with the number of occurrences.
+    pub duplicated: Vec<(TxHash, usize)>,
+}
+
+impl HistoryCheck {
+    pub fn is_consistent(&self) -> bool {
+        self.missing.is_empty() && self.duplicated.is_empty()
+    }
+
+    /// Adds the outcome of checking another account.
+    fn merge(&mut self, other: HistoryCheck) {
+        self.checked += other.checked;
+        self.missing.extend(other.missing);
+        self.duplicated.extend(other.duplicated);
+    }
+
+    /// Renders the submitted transactions missing from or repeated in the histories as a plain text table.
+    pub fn render_table(&self) -> String {
+        let mut table = format!(
+            "History check: {} submitted transactions, {} missing, {} listed more than once\n",
+            self.checked,
+            self.missing.len(),
+            self.duplicated.len()
+        );
+        if self.is_consistent() {
+            return table;
+        }
+        let _ = writeln!(table, "{:<74} {:>11}", "tx hash", "occurrences");
+        for tx_hash in &self.missing {
+            let _ = writeln!(table, "{:<74} {:>11}", tx_hash.to_string(), 0);
+        }
+        for (tx_hash, occurrences) in &self.duplicated {
+            let _ = writeln!(table, "{:<74} {:>11}", tx_hash.to_string(), occurrences);
+        }
+        table
+    }
+}
+
+/// L2 transactions accepted from each account, cross-checked against the accounts'
+/// histories once the run settled.
+#[derive(Debug, Default)]
+pub struct SubmittedHistory {
+    submitted: HashMap<Address, Vec<TxHash>>,
+    check: Option<HistoryCheck>,
+}
+
+impl SubmittedHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, account: Address, tx_hash: TxHash) {
+        self.submitted.entry(account).or_default().push(tx_hash);
+    }
+
+    pub fn submitted(&self) -> &HashMap<Address, Vec<TxHash>> {
+        &self.submitted
+    }
+
+    pub fn set_check(&mut self, check: HistoryCheck) {
+        self.check = Some(check);
+    }
+
+    /// Outcome of the cross-check, `None` until it ran.
+    pub fn check(&self) -> Option<&HistoryCheck> {
+        self.check.as_ref()
+    }
+}
+
+/// Checks that every submitted transaction appears in the account history exactly once.
+pub fn check_history(submitted: &[TxHash], history: &[AccountTx]) -> HistoryCheck {
+    let mut occurrences: HashMap<TxHash, usize> = HashMap::new();
+    for tx in history {
+        *occurrences.entry(tx.tx_hash).or_default() += 1;
+    }
+
+    let mut check = HistoryCheck {
+        checked: submitted.len(),
+        ..Default::default()
+    };
+    for tx_hash in submitted {
+        match occurrences.get(tx_hash).copied().unwrap_or_default() {
+            0 => check.missing.push(*tx_hash),
+            1 => {}
+            count => check.duplicated.push((*tx_hash, count)),
+        }
+    }
+    check
+}
+
+/// Fetches the whole transaction history of an account, newest first.
+///
+/// Pages start at the transaction given as cursor inclusively, so the cursor
+/// repeated at the top of the next page is skipped rather than counted twice.
+pub async fn fetch_account_history<P: Provider + Sync>(
+    provider: &P,
+    address: Address,
+) -> ResponseResult<Vec<AccountTx>> {
+    let mut history: Vec<AccountTx> = Vec::new();
+    let mut query = PaginationQuery::latest(MAX_PAGE_LIMIT);
+    loop {
+        let page = provider.account_txs(address, query).await?;
+        let page_len = page.list.len();
+        let mut list = page.list.into_iter().peekable();
+        if let TxHistoryCursor::Tx(cursor) = query.from {
+            list.next_if(|tx| tx.tx_hash == cursor);
+        }
+        history.extend(list);
+
+        match history.last() {
+            Some(last) if page_len as u32 >= query.limit => {
+                query.from = TxHistoryCursor::Tx(last.tx_hash);
+            }
+            _ => return Ok(history),
+        }
+    }
+}
+
+/// Cross-checks the transactions each account submitted against the account's history.
+pub async fn check_accounts<P: Provider + Sync>(
+    provider: &P,
+    submitted: &HashMap<Address, Vec<TxHash>>,
+) -> ResponseResult<HistoryCheck> {
+    let mut check = HistoryCheck::default();
+    for (address, tx_hashes) in submitted {
+        let history = fetch_account_history(provider, *address).await?;
+        check.merge(check_history(tx_hashes, &history));
+    }
+    Ok(check)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tx(byte: u8) -> AccountTx {
+        AccountTx {
+            tx_hash: TxHash { data: [byte; 32] },
+            block_number: None,
+            status: "committed".to_string(),
+            fail_reason: None,
+            created_at: None,
+        }
+    }
+
+    /// Tests that missing and duplicated submissions are reported, foreign transactions ignored.
+    #[test]
+    fn test_check_history() {
+        let submitted: Vec<TxHash> = [1, 2, 3].iter().map(|byte| tx(*byte).tx_hash).collect();
+        let history = vec![tx(9), tx(3), tx(1), tx(3)];
+
+        let check = check_history(&submitted, &history);
+        assert_eq!(check.checked, 3);
+        assert_eq!(check.missing, vec![submitted[1]]);
+        assert_eq!(check.duplicated, vec![(submitted[2], 2)]);
+        assert!(!check.is_consistent());
+    }
+}
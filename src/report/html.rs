@@ -0,0 +1,124 @@
+use std::fmt::Write;
+use std::io;
+use std::path::Path;
+
+use crate::metrics::pipeline::{PipelineStage, QueueDepthSample};
+use crate::paths;
+
+/// Report of a run directory viewable in a browser.
+pub const HTML_REPORT_FILE: &str = "report.html";
+
+const CHART_WIDTH: f64 = 800.0;
+const CHART_HEIGHT: f64 = 240.0;
+const STAGE_COLORS: [&str; 5] = ["#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f"];
+
+/// Self-contained HTML report assembled from independent sections.
+#[derive(Debug, Default)]
+pub struct HtmlReport {
+    title: String,
+    sections: Vec<(String, String)>,
+}
+
+impl HtmlReport {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            sections: Vec::new(),
+        }
+    }
+
+    /// Adds a section with an already rendered HTML body.
+    pub fn add_section(&mut self, heading: &str, body: String) {
+        self.sections.push((heading.to_string(), body));
+    }
+
+    pub fn render(&self) -> String {
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n\
+             <style>body {{ font-family: sans-serif; margin: 2em; }} \
+             table {{ border-collapse: collapse; }} td, th {{ padding: 2px 8px; }}</style>\n\
+             </head>\n<body>\n<h1>{0}</h1>\n",
+            escape(&self.title)
+        );
+        for (heading, body) in &self.sections {
+            let _ = writeln!(html, "<h2>{}</h2>\n{}", escape(heading), body);
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        paths::write_file(path, self.render())
+    }
+}
+
+/// Renders the pipeline queue depths over time as an SVG line chart.
+pub fn queue_depth_chart(samples: &[QueueDepthSample]) -> String {
+    if samples.is_empty() {
+        return "<p>No queue depth samples were recorded.</p>".to_string();
+    }
+    let max_time = samples
+        .last()
+        .map(|sample| sample.elapsed_secs)
+        .unwrap_or_default()
+        .max(1.0);
+    let max_depth = samples
+        .iter()
+        .flat_map(|sample| sample.depths)
+        .max()
+        .unwrap_or_default()
+        .max(1) as f64;
+
+    let mut svg = format!(
+        "<svg width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+        CHART_WIDTH,
+        CHART_HEIGHT + 20.0,
+        CHART_WIDTH,
+        CHART_HEIGHT + 20.0
+    );
+    for (index, stage) in PipelineStage::ALL.iter().enumerate() {
+        let points: Vec<String> = samples
+            .iter()
+            .map(|sample| {
+                let x = sample.elapsed_secs / max_time * CHART_WIDTH;
+                let y =
+                    CHART_HEIGHT - sample.depths[index].max(0) as f64 / max_depth * CHART_HEIGHT;
+                format!("{:.1},{:.1}", x, y)
+            })
+            .collect();
+        let _ = writeln!(
+            svg,
+            "<polyline fill=\"none\" stroke=\"{}\" stroke-width=\"1.5\" points=\"{}\"><title>{}</title></polyline>",
+            STAGE_COLORS[index],
+            points.join(" "),
+            stage.name()
+        );
+    }
+    let _ = writeln!(
+        svg,
+        "<text x=\"0\" y=\"{}\" font-size=\"12\">0s .. {:.0}s, max depth {}</text>\n</svg>",
+        CHART_HEIGHT + 15.0,
+        max_time,
+        max_depth
+    );
+
+    let legend: Vec<String> = PipelineStage::ALL
+        .iter()
+        .enumerate()
+        .map(|(index, stage)| {
+            format!(
+                "<span style=\"color: {}\">&#9632; {}</span>",
+                STAGE_COLORS[index],
+                stage.name()
+            )
+        })
+        .collect();
+    format!("{}<p>{}</p>", svg, legend.join(" &nbsp; "))
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::{RunRecorder, TxStatus};
+use crate::engine::{Submission, SubmissionObserver};
+use crate::rollup::confirmation::ConfirmationListener;
+use crate::rollup::types::{Address, TxHash};
+
+/// Ordered steps every account following the journey goes through,
+/// e.g. deposit, change_pubkey, transfer and withdraw.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct JourneyTemplate {
+    pub name: String,
+    pub steps: Vec<String>,
+}
+
+#[derive(Debug)]
+struct AccountJourney {
+    started: Instant,
+    /// Number of steps completed, in template order.
+    completed_steps: usize,
+    finished: Option<Instant>,
+}
+
+/// Accounts that stopped before the given step of a journey.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StalledStep {
+    pub step: String,
+    pub accounts: u64,
+}
+
+/// Outcome of one journey template across all accounts that started it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JourneySummary {
+    pub journey: String,
+    pub started: u64,
+    pub completed: u64,
+    /// Incomplete journeys by the first step that was not completed.
+    pub stalled: Vec<StalledStep>,
+    /// Median time from the start to the last step of completed journeys.
+    pub median_duration_ms: Option<f64>,
+}
+
+/// Follows accounts through template-based journeys.
+///
+/// Transaction statistics say how the rollup coped with each operation, journeys say
+/// how many simulated users managed to do everything they came for. Steps must be
+/// completed in template order; a step reported out of order is ignored, so the
+/// account shows as stalled at the step it skipped.
+#[derive(Debug, Default)]
+pub struct Journeys {
+    templates: Vec<JourneyTemplate>,
+    accounts: HashMap<(String, Address), AccountJourney>,
+}
+
+impl Journeys {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a template, journeys of unknown templates are not tracked.
+    pub fn register(&mut self, template: JourneyTemplate) {
+        if !self
+            .templates
+            .iter()
+            .any(|known| known.name == template.name)
+        {
+            self.templates.push(template);
+        }
+    }
+
+    pub fn start(&mut self, journey: &str, account: Address, at: Instant) {
+        if self.template(journey).is_none() {
+            return;
+        }
+        self.accounts
+            .entry((journey.to_string(), account))
+            .or_insert(AccountJourney {
+                started: at,
+                completed_steps: 0,
+                finished: None,
+            });
+    }
+
+    /// Marks a step of the account's journey as completed, finishing the journey on its last step.
+    pub fn complete_step(&mut self, journey: &str, account: Address, step: &str, at: Instant) {
+        let Some(steps) = self
+            .template(journey)
+            .map(|template| template.steps.clone())
+        else {
+            return;
+        };
+        let Some(progress) = self.accounts.get_mut(&(journey.to_string(), account)) else {
+            return;
+        };
+        if steps.get(progress.completed_steps).map(String::as_str) != Some(step) {
+            return;
+        }
+        progress.completed_steps += 1;
+        if progress.completed_steps == steps.len() {
+            progress.finished = Some(at);
+        }
+    }
+
+    fn template(&self, journey: &str) -> Option<&JourneyTemplate> {
+        self.templates
+            .iter()
+            .find(|template| template.name == journey)
+    }
+
+    /// Per-template completion, in registration order.
+    pub fn summary(&self) -> Vec<JourneySummary> {
+        self.templates
+            .iter()
+            .map(|template| {
+                let mut stalled = vec![0u64; template.steps.len()];
+                let mut durations = Vec::new();
+                let mut started = 0;
+                for ((journey, _), progress) in &self.accounts {
+                    if *journey != template.name {
+                        continue;
+                    }
+                    started += 1;
+                    match progress.finished {
+                        Some(finished) => durations.push(finished - progress.started),
+                        None => stalled[progress.completed_steps] += 1,
+                    }
+                }
+
+                JourneySummary {
+                    journey: template.name.clone(),
+                    started,
+                    completed: durations.len() as u64,
+                    stalled: template
+                        .steps
+                        .iter()
+                        .zip(stalled)
+                        .filter(|(_, accounts)| *accounts > 0)
+                        .map(|(step, accounts)| StalledStep {
+                            step: step.clone(),
+                            accounts,
+                        })
+                        .collect(),
+                    median_duration_ms: median(&mut durations)
+                        .map(|median| median.as_secs_f64() * 1000.0),
+                }
+            })
+            .collect()
+    }
+
+    /// Renders completion and stalls per journey as a plain text table.
+    pub fn render_table(&self) -> String {
+        let mut table = format!(
+            "{:<20} {:>8} {:>10} {:>12}  {}\n",
+            "journey", "started", "completed", "median ms", "stalled at"
+        );
+        for summary in self.summary() {
+            let stalled: Vec<String> = summary
+                .stalled
+                .iter()
+                .map(|stalled| format!("{} ({})", stalled.step, stalled.accounts))
+                .collect();
+            let median = summary
+                .median_duration_ms
+                .map(|median| format!("{median:.1}"))
+                .unwrap_or_else(|| "-".to_string());
+            let _ = writeln!(
+                table,
+                "{:<20} {:>8} {:>10} {:>12}  {}",
+                summary.journey,
+                summary.started,
+                summary.completed,
+                median,
+                stalled.join(", ")
+            );
+        }
+        table
+    }
+}
+
+/// Follows the accounts of a run through the configured journeys, steps being named after
+/// the transaction types.
+///
+/// An account starts every journey whose first step it submits. A step is completed once its
+/// transaction is committed, or as soon as it is accepted when its confirmation is not
+/// tracked, as for deposits.
+pub struct JourneyObserver {
+    recorder: Arc<RunRecorder>,
+    /// Name and first step of every journey.
+    journeys: Vec<(String, String)>,
+    /// Accepted transactions waiting for their commit, with their sender and type.
+    pending: Mutex<HashMap<TxHash, (Address, &'static str)>>,
+}
+
+impl JourneyObserver {
+    pub fn new(recorder: Arc<RunRecorder>, templates: &[JourneyTemplate]) -> Self {
+        recorder.with_journeys(|journeys| {
+            for template in templates {
+                journeys.register(template.clone());
+            }
+        });
+        let journeys = templates
+            .iter()
+            .filter_map(|template| Some((template.name.clone(), template.steps.first()?.clone())))
+            .collect();
+        Self {
+            recorder,
+            journeys,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn complete(&self, account: Address, step: &str) {
+        let now = Instant::now();
+        self.recorder.with_journeys(|journeys| {
+            for (journey, _) in &self.journeys {
+                journeys.complete_step(journey, account, step, now);
+            }
+        });
+    }
+}
+
+impl SubmissionObserver for JourneyObserver {
+    fn observe(&self, submission: &Submission) {
+        let (Some(account), Ok(tx_hash)) = (submission.account, submission.result) else {
+            return;
+        };
+        self.recorder.with_journeys(|journeys| {
+            for (journey, first_step) in &self.journeys {
+                if first_step == submission.tx_type {
+                    journeys.start(journey, account, submission.started);
+                }
+            }
+        });
+        match submission.op {
+            Some(_) => {
+                let step = (account, submission.tx_type);
+                self.pending.lock().unwrap().insert(tx_hash, step);
+            }
+            None => self.complete(account, submission.tx_type),
+        }
+    }
+}
+
+#[async_trait]
+impl ConfirmationListener for JourneyObserver {
+    async fn reached(&self, tx_hash: TxHash, status: TxStatus) {
+        if status == TxStatus::Submitted {
+            return;
+        }
+        let Some((account, step)) = self.pending.lock().unwrap().remove(&tx_hash) else {
+            return;
+        };
+        if status != TxStatus::Rejected {
+            self.complete(account, step);
+        }
+    }
+}
+
+fn median(durations: &mut [Duration]) -> Option<Duration> {
+    if durations.is_empty() {
+        return None;
+    }
+    durations.sort_unstable();
+    let middle = durations.len() / 2;
+    Some(if durations.len().is_multiple_of(2) {
+        (durations[middle - 1] + durations[middle]) / 2
+    } else {
+        durations[middle]
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rollup::confirmation::TrackedOp;
+
+    /// Tests that accounts are counted as completed or stalled at the first step they missed.
+    #[test]
+    fn test_journey_completion() {
+        let mut journeys = Journeys::new();
+        journeys.register(JourneyTemplate {
+            name: "onboarding".to_string(),
+            steps: vec![
+                "deposit".to_string(),
+                "change_pubkey".to_string(),
+                "transfer".to_string(),
+            ],
+        });
+        let start = Instant::now();
+        let secs = |secs| start + Duration::from_secs(secs);
+        let account = Address::from_low_u64_be;
+
+        for (index, completed_at) in [(1, 10), (2, 20), (3, 60)] {
+            journeys.start("onboarding", account(index), start);
+            for step in ["deposit", "change_pubkey", "transfer"] {
+                journeys.complete_step("onboarding", account(index), step, secs(completed_at));
+            }
+        }
+        journeys.start("onboarding", account(4), start);
+        journeys.complete_step("onboarding", account(4), "deposit", secs(1));
+        journeys.complete_step("onboarding", account(4), "transfer", secs(2));
+        journeys.start("onboarding", account(5), start);
+        journeys.start("unknown", account(6), start);
+
+        assert_eq!(
+            journeys.summary(),
+            vec![JourneySummary {
+                journey: "onboarding".to_string(),
+                started: 5,
+                completed: 3,
+                stalled: vec![
+                    StalledStep {
+                        step: "deposit".to_string(),
+                        accounts: 1,
+                    },
+                    StalledStep {
+                        step: "change_pubkey".to_string(),
+                        accounts: 1,
+                    },
+                ],
+                median_duration_ms: Some(20_000.0),
+            }]
+        );
+    }
+
+    /// Tests that submissions start journeys and complete their steps once committed.
+    #[tokio::test]
+    async fn test_journey_observer() {
+        let recorder = Arc::new(RunRecorder::new());
+        let observer = JourneyObserver::new(
+            recorder.clone(),
+            &[JourneyTemplate {
+                name: "onboarding".to_string(),
+                steps: vec!["deposit".to_string(), "transfer".to_string()],
+            }],
+        );
+        let hash = |byte: u8| TxHash { data: [byte; 32] };
+        let submit = |account: u64, tx_type, op: Option<TrackedOp>, byte| {
+            observer.observe(&Submission {
+                tx_type,
+                account: Some(Address::from_low_u64_be(account)),
+                fee: None,
+                payload: None,
+                op,
+                result: Ok(hash(byte)),
+                started: Instant::now(),
+            })
+        };
+
+        // Deposits are not tracked, their step is completed once they are accepted.
+        submit(1, "deposit", None, 1);
+        submit(1, "transfer", Some(TrackedOp::Tx(hash(2))), 2);
+        submit(2, "deposit", None, 3);
+        submit(2, "transfer", Some(TrackedOp::Tx(hash(4))), 4);
+        submit(3, "transfer", Some(TrackedOp::Tx(hash(5))), 5);
+        observer.reached(hash(2), TxStatus::Committed).await;
+        observer.reached(hash(2), TxStatus::Verified).await;
+        observer.reached(hash(4), TxStatus::Rejected).await;
+        observer.reached(hash(5), TxStatus::Committed).await;
+
+        let summary = recorder.with_journeys(|journeys| journeys.summary());
+        assert_eq!(summary[0].started, 2);
+        assert_eq!(summary[0].completed, 1);
+        assert_eq!(
+            summary[0].stalled,
+            vec![StalledStep {
+                step: "transfer".to_string(),
+                accounts: 1,
+            }]
+        );
+    }
+}
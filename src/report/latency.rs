@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// Timestamps of a single transaction submission.
+///
+/// `intended_start` is the moment the load schedule wanted the transaction to be sent,
+/// `actual_start` is when it was really handed to the provider. When the generator falls
+/// behind schedule the two drift apart, and measuring only from `actual_start` hides the
+/// stall (coordinated omission).
+#[derive(Debug, Clone, Copy)]
+pub struct TxTiming {
+    pub intended_start: Instant,
+    pub actual_start: Instant,
+    pub completed: Instant,
+}
+
+impl TxTiming {
+    /// Time the server spent handling the request.
+    pub fn service_time(&self) -> Duration {
+        self.completed.saturating_duration_since(self.actual_start)
+    }
+
+    /// Time the user would have observed, including the delay caused by a late schedule.
+    pub fn response_time(&self) -> Duration {
+        self.completed
+            .saturating_duration_since(self.intended_start)
+    }
+
+    /// How late the submission started compared to the schedule.
+    pub fn schedule_delay(&self) -> Duration {
+        self.actual_start
+            .saturating_duration_since(self.intended_start)
+    }
+}
+
+/// Latency distribution summary, all values in milliseconds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Percentiles {
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Percentiles {
+    pub(crate) fn from_samples(samples: &[Duration]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+        let millis = |d: Duration| d.as_secs_f64() * 1000.0;
+        let at = |q: f64| {
+            let rank = ((sorted.len() as f64) * q).ceil() as usize;
+            millis(sorted[rank.clamp(1, sorted.len()) - 1])
+        };
+        let total: Duration = sorted.iter().sum();
+
+        Self {
+            mean: millis(total) / sorted.len() as f64,
+            p50: at(0.50),
+            p90: at(0.90),
+            p99: at(0.99),
+            max: millis(sorted[sorted.len() - 1]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LatencySummary {
+    pub count: usize,
+    /// Latency measured from the actual submission start.
+    pub service_time: Percentiles,
+    /// Latency measured from the scheduled submission start (coordinated omission corrected).
+    pub response_time: Percentiles,
+    /// Number of submissions that started later than scheduled.
+    pub late_starts: usize,
+    pub max_schedule_delay_ms: f64,
+}
+
+/// Collects per-transaction timings and reports both service and response times.
+#[derive(Debug, Default)]
+pub struct LatencyRecorder {
+    service_times: Vec<Duration>,
+    response_times: Vec<Duration>,
+    late_starts: usize,
+    max_schedule_delay: Duration,
+}
+
+impl LatencyRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, timing: &TxTiming) {
+        self.service_times.push(timing.service_time());
+        self.response_times.push(timing.response_time());
+
+        let delay = timing.schedule_delay();
+        if !delay.is_zero() {
+            self.late_starts += 1;
+        }
+        self.max_schedule_delay = self.max_schedule_delay.max(delay);
+    }
+
+    pub fn summary(&self) -> LatencySummary {
+        LatencySummary {
+            count: self.service_times.len(),
+            service_time: Percentiles::from_samples(&self.service_times),
+            response_time: Percentiles::from_samples(&self.response_times),
+            late_starts: self.late_starts,
+            max_schedule_delay_ms: self.max_schedule_delay.as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+/// Time from submission until operations of one type were seen in a committed and in a verified block.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ConfirmationLatencyRow {
+    pub tx_type: String,
+    pub committed: usize,
+    pub commit_latency: Percentiles,
+    pub verified: usize,
+    pub verify_latency: Percentiles,
+    /// Operations given up on before they were verified.
+    pub timed_out: usize,
+    /// Operations committed later than the commit objective of their type.
+    pub commit_slo_breaches: usize,
+    /// Operations verified later than the verify objective of their type.
+    pub verify_slo_breaches: usize,
+}
+
+/// Collects commit and verify latencies per operation type.
+///
+/// Latencies are observed by polling, so they are upper bounds with the precision of the
+/// poll interval.
+#[derive(Debug, Default)]
+pub struct ConfirmationLatencyRecorder {
+    commit_latencies: BTreeMap<String, Vec<Duration>>,
+    verify_latencies: BTreeMap<String, Vec<Duration>>,
+    timeouts: BTreeMap<String, usize>,
+    commit_slo_breaches: BTreeMap<String, usize>,
+    verify_slo_breaches: BTreeMap<String, usize>,
+}
+
+impl ConfirmationLatencyRecorder {
+    pub fn record_commit(&mut self, tx_type: &str, latency: Duration) {
+        self.commit_latencies
+            .entry(tx_type.to_string())
+            .or_default()
+            .push(latency);
+    }
+
+    pub fn record_verify(&mut self, tx_type: &str, latency: Duration) {
+        self.verify_latencies
+            .entry(tx_type.to_string())
+            .or_default()
+            .push(latency);
+    }
+
+    pub fn record_timeout(&mut self, tx_type: &str) {
+        *self.timeouts.entry(tx_type.to_string()).or_default() += 1;
+    }
+
+    pub fn record_commit_slo_breach(&mut self, tx_type: &str) {
+        *self
+            .commit_slo_breaches
+            .entry(tx_type.to_string())
+            .or_default() += 1;
+    }
+
+    pub fn record_verify_slo_breach(&mut self, tx_type: &str) {
+        *self
+            .verify_slo_breaches
+            .entry(tx_type.to_string())
+            .or_default() += 1;
+    }
+
+    pub fn rows(&self) -> Vec<ConfirmationLatencyRow> {
+        let empty = Vec::new();
+        let mut tx_types: Vec<&String> = self
+            .commit_latencies
+            .keys()
+            .chain(self.verify_latencies.keys())
+            .chain(self.timeouts.keys())
+            .collect();
+        tx_types.sort();
+        tx_types.dedup();
+
+        tx_types
+            .into_iter()
+            .map(|tx_type| {
+                let committed = self.commit_latencies.get(tx_type).unwrap_or(&empty);
+                let verified = self.verify_latencies.get(tx_type).unwrap_or(&empty);
+                ConfirmationLatencyRow {
+                    tx_type: tx_type.clone(),
+                    committed: committed.len(),
+                    commit_latency: Percentiles::from_samples(committed),
+                    verified: verified.len(),
+                    verify_latency: Percentiles::from_samples(verified),
+                    timed_out: self.timeouts.get(tx_type).copied().unwrap_or(0),
+                    commit_slo_breaches: self
+                        .commit_slo_breaches
+                        .get(tx_type)
+                        .copied()
+                        .unwrap_or(0),
+                    verify_slo_breaches: self
+                        .verify_slo_breaches
+                        .get(tx_type)
+                        .copied()
+                        .unwrap_or(0),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests that a stalled generator shows up in the response time but not in the service time.
+    #[test]
+    fn test_coordinated_omission_correction() {
+        let start = Instant::now();
+        let interval = Duration::from_millis(10);
+        let mut recorder = LatencyRecorder::new();
+
+        // The first tx stalls the generator for 100ms; the following ones are sent late.
+        let mut now = start;
+        for i in 0..10u32 {
+            let intended_start = start + interval * i;
+            let actual_start = now.max(intended_start);
+            let service = if i == 0 {
+                Duration::from_millis(100)
+            } else {
+                Duration::from_millis(1)
+            };
+            now = actual_start + service;
+            recorder.record(&TxTiming {
+                intended_start,
+                actual_start,
+                completed: now,
+            });
+        }
+
+        let summary = recorder.summary();
+        assert_eq!(summary.count, 10);
+        assert_eq!(summary.late_starts, 9);
+        assert!(summary.service_time.p50 < 2.0);
+        assert!(summary.response_time.p50 > 50.0);
+        assert!(summary.response_time.max >= summary.service_time.max);
+    }
+}
@@ -0,0 +1,193 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use super::now_ms;
+use crate::paths;
+
+/// File name of the manifest, next to the artifacts it lists.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Debug, Error)]
+pub enum ManifestError {
+    #[error("Unable to access the run manifest: {0}")]
+    Io(#[from] io::Error),
+    #[error("Unable to parse the run manifest: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("Artifact {0} lies outside the directory of the run")]
+    OutsideRun(String),
+    #[error("{} artifact(s) no longer match the run manifest", .0.len())]
+    Tampered(Vec<ArtifactMismatch>),
+}
+
+/// SHA-256 digest and size of one artifact, its path relative to the run directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtifactHash {
+    pub file: String,
+    pub sha256: String,
+    pub bytes: u64,
+}
+
+impl ArtifactHash {
+    fn of(file: String, content: &[u8]) -> Self {
+        Self {
+            file,
+            sha256: hex::encode(Sha256::digest(content)),
+            bytes: content.len() as u64,
+        }
+    }
+}
+
+/// Artifact whose content differs from the digest recorded at finalization.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArtifactMismatch {
+    Missing(String),
+    Modified(String),
+}
+
+impl fmt::Display for ArtifactMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArtifactMismatch::Missing(file) => write!(f, "{file} is missing"),
+            ArtifactMismatch::Modified(file) => write!(f, "{file} was modified"),
+        }
+    }
+}
+
+/// Digests of the report artifacts of a run, written as `manifest.json` when the run is
+/// finalized so archived results can later be checked as untampered.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunManifest {
+    pub run_id: String,
+    pub finalized_at_ms: u128,
+    pub artifacts: Vec<ArtifactHash>,
+}
+
+impl RunManifest {
+    /// Hashes `artifacts`, which must lie in the run directory `dir`.
+    pub fn build(
+        run_id: &str,
+        dir: impl AsRef<Path>,
+        artifacts: &[PathBuf],
+    ) -> Result<Self, ManifestError> {
+        let dir = dir.as_ref();
+        let mut hashes = artifacts
+            .iter()
+            .map(|path| {
+                let file = relative_name(dir, path)?;
+                Ok(ArtifactHash::of(file, &fs::read(path)?))
+            })
+            .collect::<Result<Vec<_>, ManifestError>>()?;
+        hashes.sort_by(|a, b| a.file.cmp(&b.file));
+        hashes.dedup_by(|a, b| a.file == b.file);
+        Ok(Self {
+            run_id: run_id.to_string(),
+            finalized_at_ms: now_ms(),
+            artifacts: hashes,
+        })
+    }
+
+    /// Writes the manifest to `<dir>/manifest.json`, returning its path.
+    pub fn write(&self, dir: impl AsRef<Path>) -> Result<PathBuf, ManifestError> {
+        let path = dir.as_ref().join(MANIFEST_FILE);
+        paths::write_file(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, ManifestError> {
+        let content = fs::read_to_string(dir.as_ref().join(MANIFEST_FILE))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Checks the artifacts in `dir` against their recorded digests.
+    pub fn verify(&self, dir: impl AsRef<Path>) -> Result<(), ManifestError> {
+        let dir = dir.as_ref();
+        let mut mismatches = Vec::new();
+        for artifact in &self.artifacts {
+            let path = dir.join(&artifact.file);
+            if !path.starts_with(dir) || artifact.file.split('/').any(|part| part == "..") {
+                return Err(ManifestError::OutsideRun(artifact.file.clone()));
+            }
+            match fs::read(&path) {
+                Ok(content) if ArtifactHash::of(artifact.file.clone(), &content) == *artifact => {}
+                Ok(_) => mismatches.push(ArtifactMismatch::Modified(artifact.file.clone())),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                    mismatches.push(ArtifactMismatch::Missing(artifact.file.clone()))
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(ManifestError::Tampered(mismatches))
+        }
+    }
+}
+
+/// Path of `path` relative to `dir` with `/` separators, so manifests verify on any platform.
+fn relative_name(dir: &Path, path: &Path) -> Result<String, ManifestError> {
+    let relative = path
+        .strip_prefix(dir)
+        .map_err(|_| ManifestError::OutsideRun(path.display().to_string()))?;
+    let parts = relative
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>();
+    Ok(parts.join("/"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests that the manifest verifies untouched artifacts and reports altered or removed ones.
+    #[test]
+    fn test_run_manifest() {
+        let dir = std::env::temp_dir().join(format!("manifest-test-{}", now_ms()));
+        let report = dir.join("report.json");
+        let timeline = dir.join("timelines").join("latency.csv");
+        paths::write_file(&report, "{}").unwrap();
+        paths::write_file(&timeline, "ms,latency\n").unwrap();
+
+        let manifest =
+            RunManifest::build("run", &dir, &[timeline.clone(), report.clone()]).unwrap();
+        assert_eq!(
+            manifest
+                .artifacts
+                .iter()
+                .map(|artifact| artifact.file.as_str())
+                .collect::<Vec<_>>(),
+            ["report.json", "timelines/latency.csv"]
+        );
+        manifest.write(&dir).unwrap();
+        let loaded = RunManifest::load(&dir).unwrap();
+        assert_eq!(loaded, manifest);
+        assert!(loaded.verify(&dir).is_ok());
+
+        fs::write(&report, "{\"tps\":1000}").unwrap();
+        fs::remove_file(&timeline).unwrap();
+        match loaded.verify(&dir) {
+            Err(ManifestError::Tampered(mismatches)) => assert_eq!(
+                mismatches,
+                [
+                    ArtifactMismatch::Modified("report.json".to_string()),
+                    ArtifactMismatch::Missing("timelines/latency.csv".to_string()),
+                ]
+            ),
+            other => panic!("unexpected verification result {other:?}"),
+        }
+        assert!(matches!(
+            RunManifest::build("run", &dir, &[std::env::temp_dir().join("elsewhere")]),
+            Err(ManifestError::OutsideRun(_))
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
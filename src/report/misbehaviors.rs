@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::misbehavior::WalletBug;
+use crate::rollup::types::TxHash;
+
+/// Transaction sent with an emulated wallet bug, which the rollup is expected to refuse.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Misbehavior {
+    pub bug: WalletBug,
+    pub tx_type: String,
+    /// Hash returned when the rollup accepted the transaction, a gap in its defenses.
+    pub accepted_hash: Option<TxHash>,
+    pub error: Option<String>,
+    pub response_time_ms: f64,
+}
+
+/// Transactions of one wallet bug, with the rejection errors grouped by message.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MisbehaviorRow {
+    pub bug: WalletBug,
+    pub rejected: u64,
+    pub accepted: u64,
+    pub errors: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MisbehaviorSummary {
+    pub rows: Vec<MisbehaviorRow>,
+    /// Broken transactions the rollup accepted.
+    pub gaps: u64,
+    pub misbehaviors: Vec<Misbehavior>,
+}
+
+impl MisbehaviorSummary {
+    pub fn is_empty(&self) -> bool {
+        self.misbehaviors.is_empty()
+    }
+}
+
+/// Outcomes of the emulated wallet bugs, telling which defenses of the rollup held.
+#[derive(Debug, Default)]
+pub struct Misbehaviors {
+    misbehaviors: Vec<Misbehavior>,
+}
+
+impl Misbehaviors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, misbehavior: Misbehavior) {
+        self.misbehaviors.push(misbehavior);
+    }
+
+    fn rows(&self) -> Vec<MisbehaviorRow> {
+        let mut rows = BTreeMap::<WalletBug, MisbehaviorRow>::new();
+        for misbehavior in &self.misbehaviors {
+            let row = rows
+                .entry(misbehavior.bug)
+                .or_insert_with(|| MisbehaviorRow {
+                    bug: misbehavior.bug,
+                    rejected: 0,
+                    accepted: 0,
+                    errors: BTreeMap::new(),
+                });
+            match &misbehavior.error {
+                Some(error) => {
+                    row.rejected += 1;
+                    *row.errors.entry(error.clone()).or_default() += 1;
+                }
+                None => row.accepted += 1,
+            }
+        }
+        rows.into_values().collect()
+    }
+
+    pub fn summary(&self) -> MisbehaviorSummary {
+        MisbehaviorSummary {
+            rows: self.rows(),
+            gaps: self
+                .misbehaviors
+                .iter()
+                .filter(|misbehavior| misbehavior.accepted_hash.is_some())
+                .count() as u64,
+            misbehaviors: self.misbehaviors.clone(),
+        }
+    }
+
+    /// Renders the outcomes per wallet bug as a plain text table, with the most frequent
+    /// rejection error.
+    pub fn render_table(&self) -> String {
+        let mut table = format!(
+            "{:<20} {:>10} {:>10}  {}\n",
+            "wallet bug", "rejected", "accepted", "most frequent error"
+        );
+        for row in self.rows() {
+            let error = row
+                .errors
+                .iter()
+                .max_by_key(|(_, count)| **count)
+                .map(|(error, _)| error.as_str())
+                .unwrap_or("-");
+            let _ = writeln!(
+                table,
+                "{:<20} {:>10} {:>10}  {}",
+                row.bug.name(),
+                row.rejected,
+                row.accepted,
+                error
+            );
+        }
+        table
+    }
+}
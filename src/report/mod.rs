@@ -0,0 +1,451 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use num::BigUint;
+use serde::Serialize;
+
+pub mod balances;
+pub mod baseline;
+pub mod batch_fees;
+pub mod bursts;
+pub mod change_pubkey;
+pub mod config_diff;
+pub mod duplicates;
+pub mod explorer_labels;
+pub mod funding;
+pub mod histogram;
+pub mod history_check;
+pub mod html;
+pub mod journey;
+pub mod latency;
+pub mod manifest;
+pub mod misbehaviors;
+pub mod nfts;
+pub mod notify;
+pub mod onboarding_cost;
+pub mod redact;
+pub mod resubmissions;
+pub mod significance;
+pub mod sponsor;
+pub mod summary;
+pub mod units;
+pub mod withdrawals;
+
+use self::balances::{BalanceUtilization, BalanceUtilizationRow};
+use self::batch_fees::{BatchFeeFairness, BatchFeeRow};
+use self::bursts::{BurstProbe, BurstSummary};
+use self::change_pubkey::{ChangePubKeyCoverage, ChangePubKeyCoverageRow};
+use self::duplicates::{DuplicateHashSummary, DuplicateHashes};
+use self::histogram::{LatencyStage, LatencyWindowRow, StageLatencies, StageLatencyRow};
+use self::history_check::{HistoryCheck, SubmittedHistory};
+use self::journey::{JourneySummary, Journeys};
+use self::latency::{
+    ConfirmationLatencyRecorder, ConfirmationLatencyRow, LatencyRecorder, LatencySummary, TxTiming,
+};
+use self::misbehaviors::{MisbehaviorSummary, Misbehaviors};
+use self::nfts::{NftCreatorRow, NftMints};
+use self::onboarding_cost::{OnboardingCostSummary, OnboardingCosts};
+use self::resubmissions::{ResubmissionSummary, Resubmissions};
+use self::significance::MetricInterval;
+use self::sponsor::{SponsorLedger, SponsorSummary};
+use self::units::AmountFormat;
+use self::withdrawals::{WithdrawalLifecycle, WithdrawalSummary};
+use crate::chaos::FaultRecord;
+use crate::l1::revert::{DepositRevert, DepositRevertStats};
+use crate::metrics::pipeline::QueueDepthSample;
+use crate::metrics::Metrics;
+use crate::rollup::types::serde_wrappers::BigUintSerdeWrapper;
+use crate::rollup::types::TxHash;
+
+/// Outcome of a submitted transaction as far as the simulator knows it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TxStatus {
+    Submitted,
+    Committed,
+    Verified,
+    Rejected,
+}
+
+/// Raw record of a single submitted transaction.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxRecord {
+    pub tx_hash: Option<TxHash>,
+    pub tx_type: String,
+    pub submitted_at_ms: u128,
+    pub service_time_ms: f64,
+    pub response_time_ms: f64,
+    pub fee: Option<BigUintSerdeWrapper>,
+    pub status: TxStatus,
+    /// Why the transaction was rejected, as reported by the server.
+    pub fail_reason: Option<String>,
+    /// Time from submission until the transaction was seen in a committed block.
+    pub commit_latency_ms: Option<f64>,
+    /// Time from submission until the transaction was seen in a verified block.
+    pub verify_latency_ms: Option<f64>,
+}
+
+#[derive(Debug, Default)]
+struct RunData {
+    records: Vec<TxRecord>,
+    latency: LatencyRecorder,
+    confirmation_latency: ConfirmationLatencyRecorder,
+    stage_latency: StageLatencies,
+    change_pubkey: ChangePubKeyCoverage,
+    deposit_reverts: DepositRevertStats,
+    onboarding_costs: OnboardingCosts,
+    journeys: Journeys,
+    sponsor: SponsorLedger,
+    batch_fees: BatchFeeFairness,
+    duplicate_hashes: DuplicateHashes,
+    history: SubmittedHistory,
+    resubmissions: Resubmissions,
+    misbehaviors: Misbehaviors,
+    withdrawals: WithdrawalLifecycle,
+    balances: BalanceUtilization,
+    nfts: NftMints,
+    bursts: BurstProbe,
+    amount_format: AmountFormat,
+    faults: Vec<FaultRecord>,
+}
+
+/// Collects raw records and aggregates of a run; shared between the
+/// simulation tasks and the control API so results can be exported while running.
+#[derive(Debug, Default)]
+pub struct RunRecorder {
+    data: Mutex<RunData>,
+}
+
+/// Point-in-time view of a run: aggregates plus every raw record collected so far.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunSnapshot {
+    pub taken_at_ms: u128,
+    pub counters: Vec<(String, u64)>,
+    pub latency: LatencySummary,
+    pub confirmation_latency: Vec<ConfirmationLatencyRow>,
+    /// Submission, commit and verify latency histograms per operation type.
+    pub stage_latency: Vec<StageLatencyRow>,
+    /// Latencies per window of the run, empty unless `general.latency_timeline_secs` is set.
+    pub latency_timeline: Vec<LatencyWindowRow>,
+    pub change_pubkey: Vec<ChangePubKeyCoverageRow>,
+    pub deposit_reverts: DepositRevertStats,
+    pub onboarding_cost: OnboardingCostSummary,
+    pub journeys: Vec<JourneySummary>,
+    pub sponsor: SponsorSummary,
+    /// Quoted batch fees against the individual fees of their transactions, empty unless
+    /// `compare_fees` is set in `[activation]` or `[sponsor]`.
+    pub batch_fees: Vec<BatchFeeRow>,
+    pub duplicate_hashes: DuplicateHashSummary,
+    /// Submitted transactions cross-checked against the accounts' histories, `None` until
+    /// the run settled or when the server does not list histories.
+    pub history_check: Option<HistoryCheck>,
+    pub resubmissions: ResubmissionSummary,
+    /// Transactions sent with emulated wallet bugs, empty unless `[misbehavior]` is set.
+    pub misbehaviors: MisbehaviorSummary,
+    pub withdrawals: WithdrawalSummary,
+    pub balance_utilization: Vec<BalanceUtilizationRow>,
+    pub nfts: Vec<NftCreatorRow>,
+    pub bursts: BurstSummary,
+    /// Faults injected into the simulator itself, empty unless `[chaos]` is set.
+    pub faults: Vec<FaultRecord>,
+    pub queue_depths: Vec<QueueDepthSample>,
+    /// Confidence intervals of the baseline metrics, empty for runs of less than three minutes.
+    pub confidence: Vec<MetricInterval>,
+    pub records: Vec<TxRecord>,
+}
+
+pub fn now_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+impl RunData {
+    /// Latest record of the transaction, a resubmitted transaction has several.
+    fn find_record(&mut self, tx_hash: &TxHash) -> Option<&mut TxRecord> {
+        self.records
+            .iter_mut()
+            .rev()
+            .find(|record| record.tx_hash.as_ref() == Some(tx_hash))
+    }
+}
+
+impl RunRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_tx(
+        &self,
+        tx_type: &str,
+        tx_hash: Option<TxHash>,
+        fee: Option<BigUint>,
+        timing: &TxTiming,
+        status: TxStatus,
+    ) {
+        self.push_record(tx_type, tx_hash, fee, timing, status, None);
+    }
+
+    /// Records a transaction the server refused to accept.
+    pub fn record_rejected(
+        &self,
+        tx_type: &str,
+        fee: Option<BigUint>,
+        timing: &TxTiming,
+        fail_reason: String,
+    ) {
+        self.push_record(
+            tx_type,
+            None,
+            fee,
+            timing,
+            TxStatus::Rejected,
+            Some(fail_reason),
+        );
+    }
+
+    fn push_record(
+        &self,
+        tx_type: &str,
+        tx_hash: Option<TxHash>,
+        fee: Option<BigUint>,
+        timing: &TxTiming,
+        status: TxStatus,
+        fail_reason: Option<String>,
+    ) {
+        let mut data = self.data.lock().unwrap();
+        data.latency.record(timing);
+        data.stage_latency.record(
+            tx_type,
+            LatencyStage::Submission,
+            timing.response_time(),
+            timing.completed,
+        );
+        data.records.push(TxRecord {
+            tx_hash,
+            tx_type: tx_type.to_string(),
+            submitted_at_ms: now_ms(),
+            service_time_ms: timing.service_time().as_secs_f64() * 1000.0,
+            response_time_ms: timing.response_time().as_secs_f64() * 1000.0,
+            fee: fee.map(BigUintSerdeWrapper),
+            status,
+            fail_reason,
+            commit_latency_ms: None,
+            verify_latency_ms: None,
+        });
+    }
+
+    /// Updates the status of an already recorded transaction.
+    pub fn update_status(&self, tx_hash: &TxHash, status: TxStatus, fail_reason: Option<String>) {
+        let mut data = self.data.lock().unwrap();
+        if status == TxStatus::Rejected {
+            data.change_pubkey.resolve(tx_hash, false);
+        }
+        if let Some(record) = data.find_record(tx_hash) {
+            record.status = status;
+            record.fail_reason = fail_reason;
+        }
+    }
+
+    /// Records that a submitted operation was seen in a committed or verified block,
+    /// `latency` measured from its submission.
+    ///
+    /// Priority operations have no record of their own, `tx_hash` is `None` for them and
+    /// only the per-type latency aggregates are updated.
+    pub fn record_confirmation(
+        &self,
+        tx_type: &str,
+        tx_hash: Option<&TxHash>,
+        status: TxStatus,
+        latency: Duration,
+    ) {
+        let mut data = self.data.lock().unwrap();
+        let stage = match status {
+            TxStatus::Committed => {
+                data.confirmation_latency.record_commit(tx_type, latency);
+                LatencyStage::Commit
+            }
+            TxStatus::Verified => {
+                data.confirmation_latency.record_verify(tx_type, latency);
+                LatencyStage::Verify
+            }
+            TxStatus::Submitted | TxStatus::Rejected => return,
+        };
+        data.stage_latency
+            .record(tx_type, stage, latency, Instant::now());
+        if let (TxStatus::Committed, Some(tx_hash)) = (status, tx_hash) {
+            data.bursts.record_commit(tx_hash, latency);
+            data.change_pubkey.resolve(tx_hash, true);
+        }
+        let Some(record) = tx_hash.and_then(|tx_hash| data.find_record(tx_hash)) else {
+            return;
+        };
+        let latency_ms = Some(latency.as_secs_f64() * 1000.0);
+        match status {
+            TxStatus::Committed => record.commit_latency_ms = latency_ms,
+            _ => record.verify_latency_ms = latency_ms,
+        }
+        record.status = status;
+    }
+
+    /// Counts an operation given up on before it was verified.
+    pub fn record_confirmation_timeout(&self, tx_type: &str) {
+        let mut data = self.data.lock().unwrap();
+        data.confirmation_latency.record_timeout(tx_type);
+    }
+
+    /// Counts an operation that reached `status` later than the objective of its type.
+    pub fn record_slo_breach(&self, tx_type: &str, status: TxStatus) {
+        let mut data = self.data.lock().unwrap();
+        match status {
+            TxStatus::Committed => data.confirmation_latency.record_commit_slo_breach(tx_type),
+            TxStatus::Verified => data.confirmation_latency.record_verify_slo_breach(tx_type),
+            TxStatus::Submitted | TxStatus::Rejected => {}
+        }
+    }
+
+    /// Gives access to the `ChangePubKey` coverage tracker.
+    pub fn with_change_pubkey<T>(&self, f: impl FnOnce(&mut ChangePubKeyCoverage) -> T) -> T {
+        f(&mut self.data.lock().unwrap().change_pubkey)
+    }
+
+    pub fn record_deposit_revert(&self, revert: &DepositRevert) {
+        self.data.lock().unwrap().deposit_reverts.record(revert);
+    }
+
+    /// Gives access to the comparison of L1 deposit costs and L2 fees.
+    pub fn with_onboarding_costs<T>(&self, f: impl FnOnce(&mut OnboardingCosts) -> T) -> T {
+        f(&mut self.data.lock().unwrap().onboarding_costs)
+    }
+
+    /// Gives access to the per-account journey tracker.
+    pub fn with_journeys<T>(&self, f: impl FnOnce(&mut Journeys) -> T) -> T {
+        f(&mut self.data.lock().unwrap().journeys)
+    }
+
+    /// Gives access to the fees paid by the batch fee sponsor.
+    pub fn with_sponsor<T>(&self, f: impl FnOnce(&mut SponsorLedger) -> T) -> T {
+        f(&mut self.data.lock().unwrap().sponsor)
+    }
+
+    /// Gives access to the comparison of batch fees with individual fees.
+    pub fn with_batch_fees<T>(&self, f: impl FnOnce(&mut BatchFeeFairness) -> T) -> T {
+        f(&mut self.data.lock().unwrap().batch_fees)
+    }
+
+    /// Gives access to the hashes returned by `send_tx`, to flag the ones returned twice.
+    pub fn with_duplicate_hashes<T>(&self, f: impl FnOnce(&mut DuplicateHashes) -> T) -> T {
+        f(&mut self.data.lock().unwrap().duplicate_hashes)
+    }
+
+    /// Gives access to the transactions accepted from each account, to check their histories.
+    pub fn with_history<T>(&self, f: impl FnOnce(&mut SubmittedHistory) -> T) -> T {
+        f(&mut self.data.lock().unwrap().history)
+    }
+
+    /// Gives access to the outcomes of the resubmission study.
+    pub fn with_resubmissions<T>(&self, f: impl FnOnce(&mut Resubmissions) -> T) -> T {
+        f(&mut self.data.lock().unwrap().resubmissions)
+    }
+
+    /// Gives access to the outcomes of the emulated wallet bugs.
+    pub fn with_misbehaviors<T>(&self, f: impl FnOnce(&mut Misbehaviors) -> T) -> T {
+        f(&mut self.data.lock().unwrap().misbehaviors)
+    }
+
+    /// Gives access to the end-to-end withdrawal latencies.
+    pub fn with_withdrawals<T>(&self, f: impl FnOnce(&mut WithdrawalLifecycle) -> T) -> T {
+        f(&mut self.data.lock().unwrap().withdrawals)
+    }
+
+    /// Gives access to the per-token utilization of the funded balances.
+    pub fn with_balances<T>(&self, f: impl FnOnce(&mut BalanceUtilization) -> T) -> T {
+        f(&mut self.data.lock().unwrap().balances)
+    }
+
+    /// Gives access to the drain times of the load bursts.
+    pub fn with_bursts<T>(&self, f: impl FnOnce(&mut BurstProbe) -> T) -> T {
+        f(&mut self.data.lock().unwrap().bursts)
+    }
+
+    /// Gives access to the token decimals the text tables write amounts with.
+    pub fn with_amount_format<T>(&self, f: impl FnOnce(&mut AmountFormat) -> T) -> T {
+        f(&mut self.data.lock().unwrap().amount_format)
+    }
+
+    /// Gives access to the latency histograms of every stage.
+    pub fn with_stage_latency<T>(&self, f: impl FnOnce(&mut StageLatencies) -> T) -> T {
+        f(&mut self.data.lock().unwrap().stage_latency)
+    }
+
+    /// Gives access to the NFTs minted by the simulated accounts.
+    pub fn with_nfts<T>(&self, f: impl FnOnce(&mut NftMints) -> T) -> T {
+        f(&mut self.data.lock().unwrap().nfts)
+    }
+
+    /// Records a fault injected by self-chaos mode and whether the simulator recovered.
+    pub fn record_fault(&self, record: FaultRecord) {
+        self.data.lock().unwrap().faults.push(record);
+    }
+
+    /// Records of every transaction submitted so far.
+    pub fn records(&self) -> Vec<TxRecord> {
+        self.data.lock().unwrap().records.clone()
+    }
+
+    /// Whether a transaction recorded at or after `since_ms` was accepted by the server.
+    pub fn accepted_since(&self, since_ms: u128) -> bool {
+        let data = self.data.lock().unwrap();
+        data.records
+            .iter()
+            .rev()
+            .take_while(|record| record.submitted_at_ms >= since_ms)
+            .any(|record| record.status != TxStatus::Rejected)
+    }
+
+    /// Accepted transactions not seen in a committed block yet.
+    pub fn uncommitted(&self) -> usize {
+        let data = self.data.lock().unwrap();
+        data.records
+            .iter()
+            .filter(|record| record.status == TxStatus::Submitted)
+            .count()
+    }
+
+    pub fn snapshot(&self, metrics: &Metrics, queue_depths: Vec<QueueDepthSample>) -> RunSnapshot {
+        let data = self.data.lock().unwrap();
+        RunSnapshot {
+            taken_at_ms: now_ms(),
+            counters: metrics
+                .counters()
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+            latency: data.latency.summary(),
+            confirmation_latency: data.confirmation_latency.rows(),
+            stage_latency: data.stage_latency.rows(),
+            latency_timeline: data.stage_latency.timeline(),
+            change_pubkey: data.change_pubkey.rows(),
+            deposit_reverts: data.deposit_reverts.clone(),
+            onboarding_cost: data.onboarding_costs.summary(),
+            journeys: data.journeys.summary(),
+            sponsor: data.sponsor.summary(),
+            batch_fees: data.batch_fees.rows(),
+            duplicate_hashes: data.duplicate_hashes.summary(),
+            history_check: data.history.check().cloned(),
+            resubmissions: data.resubmissions.summary(),
+            misbehaviors: data.misbehaviors.summary(),
+            withdrawals: data.withdrawals.summary(),
+            balance_utilization: data.balances.rows(),
+            nfts: data.nfts.rows(),
+            bursts: data.bursts.summary(),
+            faults: data.faults.clone(),
+            queue_depths,
+            confidence: significance::confidence_intervals(&data.records),
+            records: data.records.clone(),
+        }
+    }
+}
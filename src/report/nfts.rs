@@ -0,0 +1,205 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::rollup::types::{Address, TokenId, H256, NFT};
+
+/// NFT side of a submitted transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NftOperation {
+    Mint {
+        creator: Address,
+        content_hash: H256,
+    },
+    Withdraw(TokenId),
+}
+
+#[derive(Debug, Default)]
+struct CreatorNfts {
+    submitted: u64,
+    rejected: u64,
+    minted: Vec<TokenId>,
+    withdrawn: u64,
+}
+
+/// NFTs minted by one simulated account.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NftCreatorRow {
+    pub creator: Address,
+    /// `MintNFT` transactions accepted by the server.
+    pub submitted: u64,
+    pub rejected: u64,
+    /// Ids the rollup assigned to the minted NFTs, in the order they were seen.
+    pub minted: Vec<TokenId>,
+    /// Accepted mints whose NFT was not seen in any account yet.
+    pub pending: u64,
+    /// Minted NFTs withdrawn to Rootstock.
+    pub withdrawn: u64,
+}
+
+/// NFTs minted during the run, grouped by the account that created them.
+///
+/// The id of an NFT is only assigned once the mint is executed, so mints are kept by content
+/// hash until the NFT shows up in the state of its recipient. Content hashes are random, one
+/// hash stands for one mint.
+#[derive(Debug, Default)]
+pub struct NftMints {
+    pending: HashMap<H256, Address>,
+    creators: BTreeMap<Address, CreatorNfts>,
+    creator_by_id: HashMap<TokenId, Address>,
+}
+
+impl NftMints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a `MintNFT` of `creator` accepted by the server.
+    pub fn record_mint(&mut self, creator: Address, content_hash: H256) {
+        self.pending.insert(content_hash, creator);
+        self.creators.entry(creator).or_default().submitted += 1;
+    }
+
+    /// Records a `MintNFT` of `creator` the server refused.
+    pub fn record_rejected(&mut self, creator: Address) {
+        self.creators.entry(creator).or_default().rejected += 1;
+    }
+
+    /// Assigns ids to the pending mints among `nfts`, as found in the state of any account,
+    /// returning the ids seen for the first time.
+    pub fn record_owned<'a>(&mut self, nfts: impl IntoIterator<Item = &'a NFT>) -> Vec<TokenId> {
+        let mut resolved = Vec::new();
+        for nft in nfts {
+            let Some(creator) = self.pending.remove(&nft.content_hash) else {
+                continue;
+            };
+            self.creators
+                .entry(creator)
+                .or_default()
+                .minted
+                .push(nft.id);
+            self.creator_by_id.insert(nft.id, creator);
+            resolved.push(nft.id);
+        }
+        resolved
+    }
+
+    /// Records an accepted `WithdrawNFT`, NFTs minted before the run are not counted.
+    pub fn record_withdrawal(&mut self, token: TokenId) {
+        if let Some(creator) = self.creator_by_id.get(&token) {
+            if let Some(nfts) = self.creators.get_mut(creator) {
+                nfts.withdrawn += 1;
+            }
+        }
+    }
+
+    /// Records the outcome of a submitted NFT operation, `accepted` by the server or not.
+    pub fn record(&mut self, operation: NftOperation, accepted: bool) {
+        match (operation, accepted) {
+            (
+                NftOperation::Mint {
+                    creator,
+                    content_hash,
+                },
+                true,
+            ) => self.record_mint(creator, content_hash),
+            (NftOperation::Mint { creator, .. }, false) => self.record_rejected(creator),
+            (NftOperation::Withdraw(token), true) => self.record_withdrawal(token),
+            (NftOperation::Withdraw(_), false) => {}
+        }
+    }
+
+    /// Ids of the NFTs minted by `creator` so far.
+    pub fn minted_by(&self, creator: &Address) -> &[TokenId] {
+        self.creators
+            .get(creator)
+            .map_or(&[], |nfts| nfts.minted.as_slice())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.creators.is_empty()
+    }
+
+    pub fn rows(&self) -> Vec<NftCreatorRow> {
+        self.creators
+            .iter()
+            .map(|(creator, nfts)| NftCreatorRow {
+                creator: *creator,
+                submitted: nfts.submitted,
+                rejected: nfts.rejected,
+                minted: nfts.minted.clone(),
+                pending: nfts.submitted - nfts.minted.len() as u64,
+                withdrawn: nfts.withdrawn,
+            })
+            .collect()
+    }
+
+    /// Renders the mints of every creator as a plain text table.
+    pub fn render_table(&self) -> String {
+        let mut table = format!(
+            "{:<42} {:>9} {:>8} {:>7} {:>7} {:>9}\n",
+            "creator", "submitted", "rejected", "minted", "pending", "withdrawn"
+        );
+        for row in self.rows() {
+            let _ = writeln!(
+                table,
+                "{:<42} {:>9} {:>8} {:>7} {:>7} {:>9}",
+                format!("{:?}", row.creator),
+                row.submitted,
+                row.rejected,
+                row.minted.len(),
+                row.pending,
+                row.withdrawn
+            );
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rollup::types::AccountId;
+
+    /// Tests resolving pending mints to ids per creator and counting their withdrawals.
+    #[test]
+    fn test_nft_mints() {
+        let nft = |id: u32, content_hash: H256| NFT {
+            id: TokenId(id),
+            symbol: format!("NFT-{}", id),
+            creator_id: AccountId(1),
+            content_hash,
+        };
+        let (alice, bob) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let mut mints = NftMints::new();
+        mints.record_mint(alice, H256::repeat_byte(1));
+        mints.record_mint(alice, H256::repeat_byte(2));
+        mints.record_mint(bob, H256::repeat_byte(3));
+        mints.record_rejected(bob);
+
+        let owned = [
+            nft(70_000, H256::repeat_byte(2)),
+            nft(70_001, H256::repeat_byte(3)),
+            nft(65_536, H256::repeat_byte(9)),
+        ];
+        assert_eq!(
+            mints.record_owned(&owned),
+            vec![TokenId(70_000), TokenId(70_001)]
+        );
+        assert!(mints.record_owned(&owned).is_empty());
+        assert_eq!(mints.minted_by(&alice), &[TokenId(70_000)]);
+        assert!(mints.minted_by(&Address::zero()).is_empty());
+
+        mints.record_withdrawal(TokenId(70_001));
+        mints.record_withdrawal(TokenId(65_536));
+        let rows = mints.rows();
+        assert_eq!((rows[0].creator, rows[0].pending), (alice, 1));
+        assert_eq!(
+            (rows[1].submitted, rows[1].rejected, rows[1].withdrawn),
+            (1, 1, 1)
+        );
+        assert_eq!(mints.render_table().lines().count(), 3);
+    }
+}
@@ -0,0 +1,314 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use thiserror::Error;
+
+use super::{RunSnapshot, TxStatus};
+#[cfg(not(feature = "webhook"))]
+use crate::features::Feature;
+#[cfg(feature = "webhook")]
+use crate::tls;
+use crate::tls::TlsPins;
+
+/// Chat service receiving the run summary.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookKind {
+    /// Slack incoming webhook.
+    #[default]
+    Slack,
+    /// Matrix hookshot generic webhook.
+    Matrix,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NotifyConfig {
+    pub webhook_url: String,
+    #[serde(default)]
+    pub kind: WebhookKind,
+    /// Link to the published artifacts of the run, included in the summary.
+    #[serde(default)]
+    pub artifacts_url: Option<String>,
+    #[serde(default = "NotifyConfig::default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl NotifyConfig {
+    fn default_timeout_secs() -> u64 {
+        10
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum NotifyError {
+    #[cfg(feature = "webhook")]
+    #[error("Webhook request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("Webhook responded with status {0}")]
+    Status(u16),
+    #[error("Posting the run summary {0}")]
+    Unavailable(String),
+}
+
+/// How the run ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunOutcome {
+    Completed,
+    Aborted(String),
+}
+
+/// Headline figures of a finished run posted to the team chat.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunSummary {
+    pub run_id: String,
+    pub outcome: RunOutcome,
+    pub elapsed: Duration,
+    pub submitted: usize,
+    pub verified: usize,
+    pub rejected: usize,
+    pub response_p50_ms: f64,
+    pub response_p99_ms: f64,
+    pub artifacts_url: Option<String>,
+}
+
+impl RunSummary {
+    pub fn new(
+        run_id: &str,
+        outcome: RunOutcome,
+        elapsed: Duration,
+        snapshot: &RunSnapshot,
+        artifacts_url: Option<String>,
+    ) -> Self {
+        let count = |status| {
+            snapshot
+                .records
+                .iter()
+                .filter(|record| record.status == status)
+                .count()
+        };
+        Self {
+            run_id: run_id.to_string(),
+            outcome,
+            elapsed,
+            submitted: snapshot.records.len(),
+            verified: count(TxStatus::Verified),
+            rejected: count(TxStatus::Rejected),
+            response_p50_ms: snapshot.latency.response_time.p50,
+            response_p99_ms: snapshot.latency.response_time.p99,
+            artifacts_url,
+        }
+    }
+
+    fn status(&self) -> String {
+        match &self.outcome {
+            RunOutcome::Completed => "completed".to_string(),
+            RunOutcome::Aborted(reason) => format!("aborted ({})", reason),
+        }
+    }
+
+    fn metrics(&self) -> String {
+        format!(
+            "{} submitted, {} verified, {} rejected; response time p50 {:.0} ms, p99 {:.0} ms",
+            self.submitted,
+            self.verified,
+            self.rejected,
+            self.response_p50_ms,
+            self.response_p99_ms
+        )
+    }
+
+    /// Plain text version of the summary.
+    pub fn text(&self) -> String {
+        let mut text = format!(
+            "Simulation run {} {} after {}s\n{}",
+            self.run_id,
+            self.status(),
+            self.elapsed.as_secs(),
+            self.metrics()
+        );
+        if let Some(url) = &self.artifacts_url {
+            let _ = write!(text, "\nArtifacts: {}", url);
+        }
+        text
+    }
+
+    /// Body of the webhook request in the format expected by the service.
+    pub fn payload(&self, kind: WebhookKind) -> Value {
+        match kind {
+            WebhookKind::Slack => {
+                let mut text = format!(
+                    "*Simulation run `{}` {}* after {}s\n{}",
+                    self.run_id,
+                    self.status(),
+                    self.elapsed.as_secs(),
+                    self.metrics()
+                );
+                if let Some(url) = &self.artifacts_url {
+                    let _ = write!(text, "\n<{}|Artifacts>", url);
+                }
+                json!({ "text": text })
+            }
+            WebhookKind::Matrix => {
+                let mut html = format!(
+                    "<b>Simulation run <code>{}</code> {}</b> after {}s<br>{}",
+                    self.run_id,
+                    self.status(),
+                    self.elapsed.as_secs(),
+                    self.metrics()
+                );
+                if let Some(url) = &self.artifacts_url {
+                    let _ = write!(html, "<br><a href=\"{}\">Artifacts</a>", url);
+                }
+                json!({ "text": self.text(), "html": html })
+            }
+        }
+    }
+}
+
+/// Posts the summary to the configured webhook, whose host must match its `pins` if any.
+#[cfg(feature = "webhook")]
+pub async fn post_summary(
+    config: &NotifyConfig,
+    summary: &RunSummary,
+    pins: &TlsPins,
+) -> Result<(), NotifyError> {
+    let response = reqwest::Client::builder()
+        .use_preconfigured_tls(tls::client_config(pins))
+        .build()?
+        .post(&config.webhook_url)
+        .timeout(Duration::from_secs(config.timeout_secs))
+        .json(&summary.payload(config.kind))
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(NotifyError::Status(response.status().as_u16()));
+    }
+    Ok(())
+}
+
+/// Refuses to post the summary in builds without the `webhook` feature.
+#[cfg(not(feature = "webhook"))]
+pub async fn post_summary(
+    _config: &NotifyConfig,
+    _summary: &RunSummary,
+    _pins: &TlsPins,
+) -> Result<(), NotifyError> {
+    Err(NotifyError::Unavailable(Feature::Webhook.unavailable()))
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::metrics::Metrics;
+    use crate::report::latency::TxTiming;
+    use crate::report::RunRecorder;
+
+    /// Tests that the summary counts records by status and renders the service specific payloads.
+    #[test]
+    fn test_summary_payloads() {
+        let recorder = RunRecorder::new();
+        let now = Instant::now();
+        let timing = TxTiming {
+            intended_start: now,
+            actual_start: now,
+            completed: now,
+        };
+        recorder.record_tx("transfer", None, None, &timing, TxStatus::Verified);
+        recorder.record_tx("transfer", None, None, &timing, TxStatus::Verified);
+        recorder.record_rejected("withdraw", None, &timing, "Nonce mismatch".to_string());
+        let snapshot = recorder.snapshot(&Metrics::new(), Vec::new());
+
+        let summary = RunSummary::new(
+            "nightly",
+            RunOutcome::Aborted("interrupted".to_string()),
+            Duration::from_secs(90),
+            &snapshot,
+            Some("https://ci.example/artifacts/1".to_string()),
+        );
+        assert_eq!(
+            (summary.submitted, summary.verified, summary.rejected),
+            (3, 2, 1)
+        );
+
+        let slack = summary.payload(WebhookKind::Slack);
+        let text = slack["text"].as_str().unwrap();
+        assert!(text.starts_with("*Simulation run `nightly` aborted (interrupted)* after 90s"));
+        assert!(text.ends_with("<https://ci.example/artifacts/1|Artifacts>"));
+
+        let matrix = summary.payload(WebhookKind::Matrix);
+        assert_eq!(matrix["text"].as_str().unwrap(), summary.text());
+        assert!(matrix["html"]
+            .as_str()
+            .unwrap()
+            .contains("<a href=\"https://ci.example/artifacts/1\">Artifacts</a>"));
+    }
+
+    /// Tests that the payload reaches the webhook endpoint and refusals are reported with their status.
+    #[cfg(feature = "webhook")]
+    #[tokio::test]
+    async fn test_post_summary() {
+        use std::convert::Infallible;
+        use std::net::SocketAddr;
+
+        use hyper::service::{make_service_fn, service_fn};
+        use hyper::{Body, Request, Response, Server, StatusCode};
+        use tokio::sync::mpsc;
+
+        let (sender, mut received) = mpsc::unbounded_channel();
+        let service = make_service_fn(move |_| {
+            let sender = sender.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    let sender = sender.clone();
+                    async move {
+                        let refused = request.uri().path().ends_with("/refused");
+                        let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                        sender
+                            .send(serde_json::from_slice::<Value>(&body).unwrap())
+                            .unwrap();
+                        let mut response = Response::new(Body::empty());
+                        if refused {
+                            *response.status_mut() = StatusCode::FORBIDDEN;
+                        }
+                        Ok::<_, Infallible>(response)
+                    }
+                }))
+            }
+        });
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(service);
+        let address = server.local_addr();
+        tokio::spawn(server);
+
+        let snapshot = RunRecorder::new().snapshot(&Metrics::new(), Vec::new());
+        let summary = RunSummary::new(
+            "nightly",
+            RunOutcome::Completed,
+            Duration::from_secs(60),
+            &snapshot,
+            None,
+        );
+        let mut config = NotifyConfig {
+            webhook_url: format!("http://{address}/hooks/run"),
+            kind: WebhookKind::Matrix,
+            artifacts_url: None,
+            timeout_secs: 5,
+        };
+        post_summary(&config, &summary, &TlsPins::new())
+            .await
+            .unwrap();
+        assert_eq!(
+            received.recv().await.unwrap(),
+            summary.payload(WebhookKind::Matrix)
+        );
+
+        config.webhook_url = format!("http://{address}/hooks/refused");
+        assert!(matches!(
+            post_summary(&config, &summary, &TlsPins::new()).await,
+            Err(NotifyError::Status(403))
+        ));
+    }
+}
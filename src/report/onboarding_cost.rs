@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use num::{BigUint, Zero};
+use serde::Serialize;
+
+use super::units::AmountFormat;
+use crate::rollup::types::serde_wrappers::BigUintSerdeAsRadix10Str;
+use crate::rollup::types::{Address, H256, U256};
+
+#[derive(Debug, Default)]
+struct AccountCosts {
+    deposit_tx: Option<H256>,
+    l1_cost: BigUint,
+    l2_fees: BigUint,
+    l2_txs: u64,
+}
+
+/// L1 cost of onboarding a single account compared with the L2 fees it paid afterwards.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OnboardingCostRow {
+    pub address: Address,
+    pub deposit_tx: Option<H256>,
+    /// Gas used by the deposit multiplied by its gas price, in wei.
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    pub l1_cost: BigUint,
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    pub l2_fees: BigUint,
+    pub l2_txs: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OnboardingCostSummary {
+    pub onboarded_accounts: u64,
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    pub total_l1_cost: BigUint,
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    pub total_l2_fees: BigUint,
+    /// Average L1 deposit cost plus L2 fees per onboarded account, in wei.
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    pub cost_per_user: BigUint,
+    pub rows: Vec<OnboardingCostRow>,
+}
+
+/// Compares what every deposit cost on L1 with the L2 fees its account incurred later.
+///
+/// Only accounts that were onboarded by a deposit during the run are reported,
+/// fees of pre-existing accounts are not attributed to any onboarding.
+#[derive(Debug, Default)]
+pub struct OnboardingCosts {
+    accounts: HashMap<Address, AccountCosts>,
+}
+
+impl OnboardingCosts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_deposit(
+        &mut self,
+        address: Address,
+        tx_hash: H256,
+        gas_used: U256,
+        gas_price: U256,
+    ) {
+        let costs = self.accounts.entry(address).or_default();
+        costs.deposit_tx.get_or_insert(tx_hash);
+        costs.l1_cost += u256_to_biguint(gas_used) * u256_to_biguint(gas_price);
+    }
+
+    pub fn record_l2_fee(&mut self, address: Address, fee: &BigUint) {
+        let costs = self.accounts.entry(address).or_default();
+        costs.l2_fees += fee;
+        costs.l2_txs += 1;
+    }
+
+    pub fn summary(&self) -> OnboardingCostSummary {
+        let mut rows: Vec<OnboardingCostRow> = self
+            .accounts
+            .iter()
+            .filter(|(_, costs)| costs.deposit_tx.is_some())
+            .map(|(address, costs)| OnboardingCostRow {
+                address: *address,
+                deposit_tx: costs.deposit_tx,
+                l1_cost: costs.l1_cost.clone(),
+                l2_fees: costs.l2_fees.clone(),
+                l2_txs: costs.l2_txs,
+            })
+            .collect();
+        rows.sort_by_key(|row| row.address);
+
+        let total_l1_cost: BigUint = rows.iter().map(|row| &row.l1_cost).sum();
+        let total_l2_fees: BigUint = rows.iter().map(|row| &row.l2_fees).sum();
+        let onboarded_accounts = rows.len() as u64;
+        let cost_per_user = if onboarded_accounts == 0 {
+            BigUint::zero()
+        } else {
+            (&total_l1_cost + &total_l2_fees) / onboarded_accounts
+        };
+
+        OnboardingCostSummary {
+            onboarded_accounts,
+            total_l1_cost,
+            total_l2_fees,
+            cost_per_user,
+            rows,
+        }
+    }
+
+    /// Renders the totals and the cost per onboarded user as a plain text table.
+    pub fn render_table(&self, units: &AmountFormat) -> String {
+        let summary = self.summary();
+        let mut table = format!(
+            "{:<20} {:>9} {:>28} {:>28} {:>28}\n",
+            "", "accounts", "L1 deposit gas", "L2 fees", "per user"
+        );
+        let _ = writeln!(
+            table,
+            "{:<20} {:>9} {:>28} {:>28} {:>28}",
+            "onboarding cost",
+            summary.onboarded_accounts,
+            units.format_native(&summary.total_l1_cost),
+            units.format_native(&summary.total_l2_fees),
+            units.format_native(&summary.cost_per_user)
+        );
+        table
+    }
+}
+
+fn u256_to_biguint(value: U256) -> BigUint {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    BigUint::from_bytes_be(&bytes)
+}
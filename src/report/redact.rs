@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+/// Length in hex digits of an address.
+const ADDRESS_HEX_LEN: usize = 40;
+/// Length in hex digits of a transaction or block hash.
+const HASH_HEX_LEN: usize = 64;
+
+/// Replaces addresses and hashes in reports with stable pseudonyms.
+///
+/// Every distinct address becomes `account-N` and every distinct hash `hash-N`, numbered
+/// in order of first appearance, so the same wallet or transaction keeps its pseudonym
+/// throughout the report while nothing about the real value is revealed.
+#[derive(Debug, Default)]
+pub struct Redactor {
+    pseudonyms: HashMap<String, String>,
+    accounts: usize,
+    hashes: usize,
+}
+
+impl Redactor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Redacts all strings and object keys of a JSON document in place.
+    pub fn redact_value(&mut self, value: &mut Value) {
+        match value {
+            Value::String(text) => *text = self.redact_str(text),
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact_value(item)),
+            Value::Object(fields) => {
+                let redacted: Map<String, Value> = std::mem::take(fields)
+                    .into_iter()
+                    .map(|(key, mut value)| {
+                        self.redact_value(&mut value);
+                        (self.redact_str(&key), value)
+                    })
+                    .collect();
+                *fields = redacted;
+            }
+            _ => {}
+        }
+    }
+
+    /// Replaces every `0x`/`sync-tx:` prefixed address or hash found in the text.
+    pub fn redact_str(&mut self, text: &str) -> String {
+        let mut redacted = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some((start, prefix_len)) = find_prefix(rest) {
+            let hex_len = rest[start + prefix_len..]
+                .bytes()
+                .take_while(u8::is_ascii_hexdigit)
+                .count();
+            let end = start + prefix_len + hex_len;
+            let preceded_by_word = rest[..start]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_ascii_alphanumeric());
+            let followed_by_word = rest[end..]
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphanumeric());
+
+            redacted.push_str(&rest[..start]);
+            if (hex_len == ADDRESS_HEX_LEN || hex_len == HASH_HEX_LEN)
+                && !preceded_by_word
+                && !followed_by_word
+            {
+                let hex = rest[start + prefix_len..end].to_lowercase();
+                redacted.push_str(&self.pseudonym(hex));
+            } else {
+                redacted.push_str(&rest[start..end]);
+            }
+            rest = &rest[end..];
+        }
+        redacted.push_str(rest);
+        redacted
+    }
+
+    fn pseudonym(&mut self, hex: String) -> String {
+        let Self {
+            pseudonyms,
+            accounts,
+            hashes,
+        } = self;
+        pseudonyms
+            .entry(hex)
+            .or_insert_with_key(|hex| {
+                if hex.len() == ADDRESS_HEX_LEN {
+                    *accounts += 1;
+                    format!("account-{}", accounts)
+                } else {
+                    *hashes += 1;
+                    format!("hash-{}", hashes)
+                }
+            })
+            .clone()
+    }
+}
+
+/// Finds the first address or hash prefix, returning its position and length.
+fn find_prefix(text: &str) -> Option<(usize, usize)> {
+    ["0x", "sync-tx:", "sync-block:"]
+        .iter()
+        .filter_map(|prefix| text.find(prefix).map(|start| (start, prefix.len())))
+        .min_by_key(|(start, _)| *start)
+}
+
+/// Redacts a JSON export, keeping it pretty-printed.
+pub fn redact_export(export_json: &str) -> Result<String, serde_json::Error> {
+    let mut export: Value = serde_json::from_str(export_json)?;
+    Redactor::new().redact_value(&mut export);
+    serde_json::to_string_pretty(&export)
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Tests that pseudonyms are stable and only whole addresses and hashes are replaced.
+    #[test]
+    fn test_redact_export() {
+        let address = format!("0x{}", "aB".repeat(20));
+        let hash = "1f".repeat(32);
+        let mut report = json!({
+            "records": [
+                { "txHash": format!("sync-tx:{}", hash), "txType": "Transfer" },
+                { "txHash": format!("0x{}", hash), "amount": "0x10" },
+            ],
+            "reasons": {
+                format!("transfer to {} rejected", address.to_lowercase()): 2,
+            },
+            "sender": address,
+            "checksum": format!("0x{}", "0".repeat(41)),
+        });
+
+        Redactor::new().redact_value(&mut report);
+
+        assert_eq!(
+            report,
+            json!({
+                "records": [
+                    { "txHash": "hash-1", "txType": "Transfer" },
+                    { "txHash": "hash-1", "amount": "0x10" },
+                ],
+                "reasons": { "transfer to account-1 rejected": 2 },
+                "sender": "account-1",
+                "checksum": format!("0x{}", "0".repeat(41)),
+            })
+        );
+    }
+}
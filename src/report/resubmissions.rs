@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::rollup::types::TxHash;
+
+/// How the server answered a signed transaction sent a second time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ResubmissionOutcome {
+    /// Accepted with the hash of the first submission, the server is idempotent.
+    SameHash,
+    /// Accepted with another hash, the transaction may be executed twice.
+    NewHash,
+    /// Refused, see the error for how the server recognized the duplicate.
+    Rejected,
+}
+
+impl ResubmissionOutcome {
+    pub fn name(self) -> &'static str {
+        match self {
+            ResubmissionOutcome::SameHash => "same_hash",
+            ResubmissionOutcome::NewHash => "new_hash",
+            ResubmissionOutcome::Rejected => "rejected",
+        }
+    }
+}
+
+/// Second submission of a sampled transaction.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Resubmission {
+    pub tx_type: String,
+    pub tx_hash: TxHash,
+    pub outcome: ResubmissionOutcome,
+    /// Hash returned for the resubmission when it was accepted.
+    pub resubmitted_hash: Option<TxHash>,
+    pub error: Option<String>,
+    /// Time between the first submission being accepted and the resubmission.
+    pub delay_ms: u64,
+}
+
+/// Resubmissions of one transaction type, with the rejection errors grouped by message.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResubmissionRow {
+    pub tx_type: String,
+    pub same_hash: u64,
+    pub new_hash: u64,
+    pub rejected: u64,
+    pub errors: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResubmissionSummary {
+    pub rows: Vec<ResubmissionRow>,
+    pub resubmissions: Vec<Resubmission>,
+}
+
+impl ResubmissionSummary {
+    pub fn is_empty(&self) -> bool {
+        self.resubmissions.is_empty()
+    }
+}
+
+/// Outcomes of the resubmission study, telling which duplicate handling wallets can rely on.
+#[derive(Debug, Default)]
+pub struct Resubmissions {
+    resubmissions: Vec<Resubmission>,
+}
+
+impl Resubmissions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, resubmission: Resubmission) {
+        self.resubmissions.push(resubmission);
+    }
+
+    fn rows(&self) -> Vec<ResubmissionRow> {
+        let mut rows = BTreeMap::<&str, ResubmissionRow>::new();
+        for resubmission in &self.resubmissions {
+            let row = rows
+                .entry(&resubmission.tx_type)
+                .or_insert_with(|| ResubmissionRow {
+                    tx_type: resubmission.tx_type.clone(),
+                    ..Default::default()
+                });
+            match resubmission.outcome {
+                ResubmissionOutcome::SameHash => row.same_hash += 1,
+                ResubmissionOutcome::NewHash => row.new_hash += 1,
+                ResubmissionOutcome::Rejected => row.rejected += 1,
+            }
+            if let Some(error) = &resubmission.error {
+                *row.errors.entry(error.clone()).or_default() += 1;
+            }
+        }
+        rows.into_values().collect()
+    }
+
+    pub fn summary(&self) -> ResubmissionSummary {
+        ResubmissionSummary {
+            rows: self.rows(),
+            resubmissions: self.resubmissions.clone(),
+        }
+    }
+
+    /// Renders the outcomes per transaction type as a plain text table, with the most
+    /// frequent rejection error.
+    pub fn render_table(&self) -> String {
+        let mut table = format!(
+            "{:<16} {:>10} {:>10} {:>10}  {}\n",
+            "resubmitted", "same hash", "new hash", "rejected", "most frequent error"
+        );
+        for row in self.rows() {
+            let error = row
+                .errors
+                .iter()
+                .max_by_key(|(_, count)| **count)
+                .map(|(error, _)| error.as_str())
+                .unwrap_or("-");
+            let _ = writeln!(
+                table,
+                "{:<16} {:>10} {:>10} {:>10}  {}",
+                row.tx_type, row.same_hash, row.new_hash, row.rejected, error
+            );
+        }
+        table
+    }
+}
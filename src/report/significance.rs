@@ -0,0 +1,305 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::time::Duration;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::latency::Percentiles;
+use super::{TxRecord, TxStatus};
+use crate::rng::StreamRng;
+
+/// Length of the buckets a run is split into for the bootstrap.
+pub const BUCKET: Duration = Duration::from_secs(60);
+/// Fewer complete buckets say nothing about the spread of a metric.
+const MIN_BUCKETS: usize = 3;
+const RESAMPLES: usize = 1_000;
+/// Two-sided confidence level of the intervals.
+pub const CONFIDENCE: f64 = 0.95;
+/// Fixed seed, so the intervals of a run are the same every time they are computed.
+const BOOTSTRAP_SEED: u64 = 0x5eed;
+
+/// Range a metric of the run lies in with [`CONFIDENCE`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ConfidenceInterval {
+    pub estimate: f64,
+    pub low: f64,
+    pub high: f64,
+}
+
+impl ConfidenceInterval {
+    pub fn overlaps(&self, other: &ConfidenceInterval) -> bool {
+        self.low <= other.high && other.low <= self.high
+    }
+}
+
+impl fmt::Display for ConfidenceInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.3} [{:.3}, {:.3}]",
+            self.estimate, self.low, self.high
+        )
+    }
+}
+
+/// Confidence interval of one of the metrics compared with a baseline.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricInterval {
+    /// Name of the metric as in the baseline file, e.g. `response_p90_ms`.
+    pub metric: String,
+    /// Number of buckets the interval was bootstrapped from.
+    pub buckets: usize,
+    #[serde(flatten)]
+    pub interval: ConfidenceInterval,
+}
+
+/// Values of the metrics in every complete bucket of a run.
+#[derive(Debug, Default)]
+struct BucketValues {
+    achieved_tps: Vec<f64>,
+    response_p50_ms: Vec<f64>,
+    response_p90_ms: Vec<f64>,
+    response_p99_ms: Vec<f64>,
+    failure_rate_percent: Vec<f64>,
+}
+
+impl BucketValues {
+    fn from_records(records: &[TxRecord]) -> Self {
+        let mut values = Self::default();
+        let (Some(first), Some(last)) = (
+            records.iter().map(|record| record.submitted_at_ms).min(),
+            records.iter().map(|record| record.submitted_at_ms).max(),
+        ) else {
+            return values;
+        };
+        let bucket_ms = BUCKET.as_millis();
+        // The last bucket usually ends with the run and would understate the rate.
+        let complete = ((last - first) / bucket_ms) as usize;
+        let mut buckets: BTreeMap<usize, Vec<&TxRecord>> =
+            (0..complete).map(|bucket| (bucket, Vec::new())).collect();
+        for record in records {
+            let bucket = ((record.submitted_at_ms - first) / bucket_ms) as usize;
+            if let Some(members) = buckets.get_mut(&bucket) {
+                members.push(record);
+            }
+        }
+
+        for members in buckets.values() {
+            values
+                .achieved_tps
+                .push(members.len() as f64 / BUCKET.as_secs_f64());
+            if members.is_empty() {
+                continue;
+            }
+            let response_times: Vec<Duration> = members
+                .iter()
+                .map(|record| Duration::from_secs_f64(record.response_time_ms / 1000.0))
+                .collect();
+            let percentiles = Percentiles::from_samples(&response_times);
+            values.response_p50_ms.push(percentiles.p50);
+            values.response_p90_ms.push(percentiles.p90);
+            values.response_p99_ms.push(percentiles.p99);
+            let rejected = members
+                .iter()
+                .filter(|record| record.status == TxStatus::Rejected)
+                .count();
+            values
+                .failure_rate_percent
+                .push(rejected as f64 * 100.0 / members.len() as f64);
+        }
+        values
+    }
+
+    fn metrics(&self) -> [(&'static str, &[f64]); 5] {
+        [
+            ("achieved_tps", &self.achieved_tps),
+            ("response_p50_ms", &self.response_p50_ms),
+            ("response_p90_ms", &self.response_p90_ms),
+            ("response_p99_ms", &self.response_p99_ms),
+            ("failure_rate_percent", &self.failure_rate_percent),
+        ]
+    }
+}
+
+/// Percentile bootstrap interval of the mean of `values`.
+fn bootstrap(values: &[f64], rng: &mut StreamRng) -> ConfidenceInterval {
+    let mean = |sample: &mut dyn Iterator<Item = f64>| sample.sum::<f64>() / values.len() as f64;
+    let mut means: Vec<f64> = (0..RESAMPLES)
+        .map(|_| mean(&mut (0..values.len()).map(|_| values[rng.gen_range(0..values.len())])))
+        .collect();
+    means.sort_by(f64::total_cmp);
+    let tail = (1.0 - CONFIDENCE) / 2.0;
+    let at = |q: f64| means[((RESAMPLES as f64 * q) as usize).min(RESAMPLES - 1)];
+    ConfidenceInterval {
+        estimate: mean(&mut values.iter().copied()),
+        low: at(tail),
+        high: at(1.0 - tail),
+    }
+}
+
+/// Confidence intervals of the baseline metrics, bootstrapped over the one minute buckets of the run.
+///
+/// Latency percentiles and the failure rate are computed per bucket and their mean is
+/// resampled, so the intervals reflect how much the metrics moved during the run. Runs
+/// shorter than three complete buckets get no intervals.
+pub fn confidence_intervals(records: &[TxRecord]) -> Vec<MetricInterval> {
+    let values = BucketValues::from_records(records);
+    let mut rng = StreamRng::seed_from_u64(BOOTSTRAP_SEED);
+    values
+        .metrics()
+        .into_iter()
+        .filter(|(_, samples)| samples.len() >= MIN_BUCKETS)
+        .map(|(metric, samples)| MetricInterval {
+            metric: metric.to_string(),
+            buckets: samples.len(),
+            interval: bootstrap(samples, &mut rng),
+        })
+        .collect()
+}
+
+/// Metric of a run next to the same metric of its baseline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricDifference {
+    pub metric: String,
+    pub baseline: ConfidenceInterval,
+    pub actual: ConfidenceInterval,
+}
+
+impl MetricDifference {
+    /// Whether the difference is beyond the noise of both runs, the intervals do not overlap.
+    pub fn is_significant(&self) -> bool {
+        !self.baseline.overlaps(&self.actual)
+    }
+}
+
+impl fmt::Display for MetricDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} (baseline {}), {}",
+            self.metric,
+            self.actual,
+            self.baseline,
+            if self.is_significant() {
+                "significant"
+            } else {
+                "within noise"
+            }
+        )
+    }
+}
+
+/// Pairs the intervals of a run with those of its baseline, metrics missing on either side are left out.
+pub fn compare(baseline: &[MetricInterval], actual: &[MetricInterval]) -> Vec<MetricDifference> {
+    actual
+        .iter()
+        .filter_map(|actual| {
+            let baseline = baseline
+                .iter()
+                .find(|baseline| baseline.metric == actual.metric)?;
+            Some(MetricDifference {
+                metric: actual.metric.clone(),
+                baseline: baseline.interval,
+                actual: actual.interval,
+            })
+        })
+        .collect()
+}
+
+/// Renders the intervals as a plain text table.
+pub fn render_table(intervals: &[MetricInterval]) -> String {
+    let mut table = format!(
+        "{:<22} {:>8} {:>12} {:>12} {:>12}\n",
+        "metric",
+        "buckets",
+        "estimate",
+        format!("{:.0}% low", CONFIDENCE * 100.0),
+        format!("{:.0}% high", CONFIDENCE * 100.0)
+    );
+    for row in intervals {
+        let _ = writeln!(
+            table,
+            "{:<22} {:>8} {:>12.3} {:>12.3} {:>12.3}",
+            row.metric, row.buckets, row.interval.estimate, row.interval.low, row.interval.high
+        );
+    }
+    table
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn records(minutes: u64, per_minute: u64, response_ms: impl Fn(u64) -> f64) -> Vec<TxRecord> {
+        (0..minutes * per_minute)
+            .map(|i| TxRecord {
+                tx_hash: None,
+                tx_type: "transfer".to_string(),
+                submitted_at_ms: (i * 60_000 / per_minute) as u128,
+                service_time_ms: 0.0,
+                response_time_ms: response_ms(i),
+                fee: None,
+                status: if i.is_multiple_of(10) {
+                    TxStatus::Rejected
+                } else {
+                    TxStatus::Submitted
+                },
+                fail_reason: None,
+                commit_latency_ms: None,
+                verify_latency_ms: None,
+            })
+            .collect()
+    }
+
+    /// Tests intervals over complete buckets and telling noise apart from a real shift.
+    #[test]
+    fn test_bootstrap_significance() {
+        assert!(confidence_intervals(&records(2, 60, |_| 100.0)).is_empty());
+
+        // Response times wobble by ±10 ms from minute to minute.
+        let wobble = |i: u64| {
+            100.0
+                + if (i / 60).is_multiple_of(2) {
+                    10.0
+                } else {
+                    -10.0
+                }
+        };
+        let baseline = confidence_intervals(&records(11, 60, wobble));
+        let p50 = baseline
+            .iter()
+            .find(|row| row.metric == "response_p50_ms")
+            .unwrap();
+        assert_eq!(p50.buckets, 10);
+        assert!(p50.interval.low < 100.0 && 100.0 < p50.interval.high);
+        assert!(p50.interval.low >= 90.0 && p50.interval.high <= 110.0);
+        let tps = baseline
+            .iter()
+            .find(|row| row.metric == "achieved_tps")
+            .unwrap();
+        assert_eq!(tps.interval.estimate, 1.0);
+        assert_eq!(confidence_intervals(&records(11, 60, wobble)), baseline);
+
+        let same = compare(
+            &baseline,
+            &confidence_intervals(&records(11, 60, |i| wobble(i + 60))),
+        );
+        assert!(same.iter().all(|difference| !difference.is_significant()));
+
+        let slower = compare(
+            &baseline,
+            &confidence_intervals(&records(11, 60, |i| wobble(i) + 50.0)),
+        );
+        let significant: Vec<&str> = slower
+            .iter()
+            .filter(|difference| difference.is_significant())
+            .map(|difference| difference.metric.as_str())
+            .collect();
+        assert_eq!(
+            significant,
+            vec!["response_p50_ms", "response_p90_ms", "response_p99_ms"]
+        );
+    }
+}
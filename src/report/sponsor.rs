@@ -0,0 +1,112 @@
+use std::fmt::Write;
+
+use num::{BigUint, Zero};
+use serde::Serialize;
+
+use super::units::AmountFormat;
+use crate::rollup::types::serde_wrappers::BigUintSerdeAsRadix10Str;
+use crate::rollup::types::Address;
+
+/// Fees paid by the sponsor of fee-sponsored batches.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SponsorSummary {
+    pub sponsor: Option<Address>,
+    /// Token the sponsor pays the batch fees in.
+    pub fee_token: Option<String>,
+    pub batches: u64,
+    pub rejected_batches: u64,
+    /// Zero-fee transactions whose fee the sponsor paid.
+    pub sponsored_txs: u64,
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    pub fees_paid: BigUint,
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    pub avg_fee_per_tx: BigUint,
+    /// Sponsor balance as tracked locally, in the smallest units of the fee token.
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    pub remaining_balance: BigUint,
+}
+
+/// Keeps track of what the fee sponsor paid and what it has left.
+#[derive(Debug, Default)]
+pub struct SponsorLedger {
+    sponsor: Option<Address>,
+    fee_token: Option<String>,
+    balance: BigUint,
+    batches: u64,
+    rejected_batches: u64,
+    sponsored_txs: u64,
+    fees_paid: BigUint,
+}
+
+impl SponsorLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts tracking the sponsor with its balance of the fee token at the start of the run.
+    pub fn set_sponsor(&mut self, sponsor: Address, fee_token: &str, balance: BigUint) {
+        self.sponsor = Some(sponsor);
+        self.fee_token = Some(fee_token.to_string());
+        self.balance = balance;
+    }
+
+    pub fn record_batch(&mut self, sponsored_txs: usize, fee: &BigUint) {
+        self.batches += 1;
+        self.sponsored_txs += sponsored_txs as u64;
+        self.fees_paid += fee;
+        self.balance = if self.balance >= *fee {
+            &self.balance - fee
+        } else {
+            BigUint::zero()
+        };
+    }
+
+    /// Records a batch the server refused, no fee was charged for it.
+    pub fn record_rejected(&mut self) {
+        self.rejected_batches += 1;
+    }
+
+    pub fn summary(&self) -> SponsorSummary {
+        let avg_fee_per_tx = if self.sponsored_txs == 0 {
+            BigUint::zero()
+        } else {
+            &self.fees_paid / self.sponsored_txs
+        };
+        SponsorSummary {
+            sponsor: self.sponsor,
+            fee_token: self.fee_token.clone(),
+            batches: self.batches,
+            rejected_batches: self.rejected_batches,
+            sponsored_txs: self.sponsored_txs,
+            fees_paid: self.fees_paid.clone(),
+            avg_fee_per_tx,
+            remaining_balance: self.balance.clone(),
+        }
+    }
+
+    /// Renders the sponsor section as a plain text table.
+    pub fn render_table(&self, units: &AmountFormat) -> String {
+        let summary = self.summary();
+        let amount = |value: &BigUint| match &summary.fee_token {
+            Some(token) => units.format(value, token),
+            None => value.to_string(),
+        };
+        let mut table = format!(
+            "{:<16} {:>8} {:>9} {:>10} {:>24} {:>24} {:>24}\n",
+            "", "batches", "rejected", "sponsored", "fees paid", "fee per tx", "remaining"
+        );
+        let _ = writeln!(
+            table,
+            "{:<16} {:>8} {:>9} {:>10} {:>24} {:>24} {:>24}",
+            "fee sponsor",
+            summary.batches,
+            summary.rejected_batches,
+            summary.sponsored_txs,
+            amount(&summary.fees_paid),
+            amount(&summary.avg_fee_per_tx),
+            amount(&summary.remaining_balance)
+        );
+        table
+    }
+}
@@ -0,0 +1,358 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use num::BigUint;
+use serde::Serialize;
+
+use super::latency::Percentiles;
+use super::{now_ms, RunSnapshot, TxRecord, TxStatus};
+use crate::paths;
+use crate::rollup::types::serde_wrappers::BigUintSerdeWrapper;
+
+/// Statistics of all transactions of one type.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxTypeStats {
+    pub tx_type: String,
+    pub total: usize,
+    /// Accepted by the server but not yet seen in a block.
+    pub pending: usize,
+    pub committed: usize,
+    pub verified: usize,
+    pub rejected: usize,
+    pub response_time: Percentiles,
+    pub total_fee: BigUintSerdeWrapper,
+}
+
+/// Number of rejections of one transaction type for the same reason.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailureCount {
+    pub tx_type: String,
+    pub fail_reason: String,
+    pub count: usize,
+}
+
+/// End of run report written when `generate_reports` is enabled.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunReport {
+    pub run_id: String,
+    pub generated_at_ms: u128,
+    pub per_type: Vec<TxTypeStats>,
+    pub failures: Vec<FailureCount>,
+    pub run: RunSnapshot,
+}
+
+impl RunReport {
+    pub fn new(run_id: &str, run: RunSnapshot) -> Self {
+        Self {
+            run_id: run_id.to_string(),
+            generated_at_ms: now_ms(),
+            per_type: per_type_stats(&run.records),
+            failures: failure_breakdown(&run.records),
+            run,
+        }
+    }
+
+    /// One row per submitted transaction.
+    pub fn transactions_csv(&self) -> String {
+        let mut csv = String::from(
+            "tx_hash,tx_type,submitted_at_ms,service_time_ms,response_time_ms,fee,status,fail_reason\n",
+        );
+        for record in &self.run.records {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{:.3},{:.3},{},{},{}",
+                record
+                    .tx_hash
+                    .as_ref()
+                    .map(ToString::to_string)
+                    .unwrap_or_default(),
+                csv_field(&record.tx_type),
+                record.submitted_at_ms,
+                record.service_time_ms,
+                record.response_time_ms,
+                record
+                    .fee
+                    .as_ref()
+                    .map(|fee| fee.0.to_string())
+                    .unwrap_or_default(),
+                status_name(record.status),
+                csv_field(record.fail_reason.as_deref().unwrap_or_default()),
+            );
+        }
+        csv
+    }
+
+    /// One row per transaction type.
+    pub fn summary_csv(&self) -> String {
+        let mut csv = String::from(
+            "tx_type,total,pending,committed,verified,rejected,\
+             response_p50_ms,response_p90_ms,response_p99_ms,total_fee\n",
+        );
+        for stats in &self.per_type {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{:.3},{:.3},{:.3},{}",
+                csv_field(&stats.tx_type),
+                stats.total,
+                stats.pending,
+                stats.committed,
+                stats.verified,
+                stats.rejected,
+                stats.response_time.p50,
+                stats.response_time.p90,
+                stats.response_time.p99,
+                stats.total_fee.0,
+            );
+        }
+        csv
+    }
+
+    /// One row per transaction type and latency stage.
+    pub fn latency_csv(&self) -> String {
+        let mut csv =
+            String::from("tx_type,stage,count,mean_ms,p50_ms,p90_ms,p99_ms,p999_ms,max_ms\n");
+        for row in &self.run.stage_latency {
+            let latency = &row.latency;
+            let _ = writeln!(
+                csv,
+                "{},{},{},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3}",
+                csv_field(&row.tx_type),
+                row.stage.label(),
+                latency.count,
+                latency.mean,
+                latency.p50,
+                latency.p90,
+                latency.p99,
+                latency.p999,
+                latency.max,
+            );
+        }
+        csv
+    }
+
+    /// One row per window of the run and latency stage, over all transaction types.
+    pub fn latency_timeline_csv(&self) -> String {
+        let mut csv =
+            String::from("start_secs,stage,count,mean_ms,p50_ms,p90_ms,p99_ms,p999_ms,max_ms\n");
+        for row in &self.run.latency_timeline {
+            let latency = &row.latency;
+            let _ = writeln!(
+                csv,
+                "{},{},{},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3}",
+                row.start_secs,
+                row.stage.label(),
+                latency.count,
+                latency.mean,
+                latency.p50,
+                latency.p90,
+                latency.p99,
+                latency.p999,
+                latency.max,
+            );
+        }
+        csv
+    }
+
+    pub fn failures_csv(&self) -> String {
+        let mut csv = String::from("tx_type,fail_reason,count\n");
+        for failure in &self.failures {
+            let _ = writeln!(
+                csv,
+                "{},{},{}",
+                csv_field(&failure.tx_type),
+                csv_field(&failure.fail_reason),
+                failure.count
+            );
+        }
+        csv
+    }
+
+    /// Writes the JSON and CSV reports to `<report_dir>/<run_id>/`, returning the written files.
+    ///
+    /// The latency timeline is only written when it was recorded.
+    pub fn write(&self, report_dir: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+        let dir = report_dir.as_ref().join(&self.run_id);
+        let json = serde_json::to_string_pretty(self)?;
+        let mut files = vec![
+            ("report.json", json),
+            ("transactions.csv", self.transactions_csv()),
+            ("summary.csv", self.summary_csv()),
+            ("latency.csv", self.latency_csv()),
+            ("failures.csv", self.failures_csv()),
+        ];
+        if !self.run.latency_timeline.is_empty() {
+            files.push(("latency_timeline.csv", self.latency_timeline_csv()));
+        }
+
+        let mut written = Vec::with_capacity(files.len());
+        for (name, content) in files {
+            let path = dir.join(name);
+            paths::write_file(&path, content)?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+}
+
+fn per_type_stats(records: &[TxRecord]) -> Vec<TxTypeStats> {
+    let mut by_type: BTreeMap<&str, Vec<&TxRecord>> = BTreeMap::new();
+    for record in records {
+        by_type.entry(&record.tx_type).or_default().push(record);
+    }
+
+    by_type
+        .into_iter()
+        .map(|(tx_type, records)| {
+            let count = |status| {
+                records
+                    .iter()
+                    .filter(|record| record.status == status)
+                    .count()
+            };
+            let response_times: Vec<Duration> = records
+                .iter()
+                .map(|record| Duration::from_secs_f64(record.response_time_ms / 1000.0))
+                .collect();
+            let total_fee: BigUint = records
+                .iter()
+                .filter(|record| record.status != TxStatus::Rejected)
+                .filter_map(|record| record.fee.as_ref())
+                .map(|fee| &fee.0)
+                .sum();
+
+            TxTypeStats {
+                tx_type: tx_type.to_string(),
+                total: records.len(),
+                pending: count(TxStatus::Submitted),
+                committed: count(TxStatus::Committed),
+                verified: count(TxStatus::Verified),
+                rejected: count(TxStatus::Rejected),
+                response_time: Percentiles::from_samples(&response_times),
+                total_fee: BigUintSerdeWrapper(total_fee),
+            }
+        })
+        .collect()
+}
+
+/// Rejections grouped by type and reason, most frequent first.
+fn failure_breakdown(records: &[TxRecord]) -> Vec<FailureCount> {
+    let mut counts: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+    for record in records
+        .iter()
+        .filter(|record| record.status == TxStatus::Rejected)
+    {
+        let reason = record.fail_reason.as_deref().unwrap_or("unknown");
+        *counts.entry((&record.tx_type, reason)).or_default() += 1;
+    }
+
+    let mut failures: Vec<FailureCount> = counts
+        .into_iter()
+        .map(|((tx_type, fail_reason), count)| FailureCount {
+            tx_type: tx_type.to_string(),
+            fail_reason: fail_reason.to_string(),
+            count,
+        })
+        .collect();
+    failures.sort_by_key(|failure| std::cmp::Reverse(failure.count));
+    failures
+}
+
+fn status_name(status: TxStatus) -> &'static str {
+    match status {
+        TxStatus::Submitted => "submitted",
+        TxStatus::Committed => "committed",
+        TxStatus::Verified => "verified",
+        TxStatus::Rejected => "rejected",
+    }
+}
+
+/// Quotes a CSV field when it contains a separator, a quote or a line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::metrics::Metrics;
+    use crate::report::latency::TxTiming;
+    use crate::report::RunRecorder;
+    use crate::rollup::types::TxHash;
+
+    /// Tests per-type aggregation, the failure breakdown and CSV quoting of fail reasons.
+    #[test]
+    fn test_run_report() {
+        let recorder = RunRecorder::new();
+        let now = Instant::now();
+        let timing = TxTiming {
+            intended_start: now,
+            actual_start: now,
+            completed: now + Duration::from_millis(20),
+        };
+        let hash = TxHash::default();
+        let fee = || Some(BigUint::from(10u32));
+        recorder.record_tx("transfer", Some(hash), fee(), &timing, TxStatus::Submitted);
+        recorder.update_status(&hash, TxStatus::Verified, None);
+        recorder.record_tx("transfer", None, fee(), &timing, TxStatus::Committed);
+        for _ in 0..2 {
+            recorder.record_rejected("transfer", fee(), &timing, "Nonce mismatch".to_string());
+        }
+        recorder.record_rejected(
+            "withdraw",
+            None,
+            &timing,
+            "Not enough balance, \"RBTC\"".to_string(),
+        );
+
+        let report = RunReport::new("run", recorder.snapshot(&Metrics::new(), Vec::new()));
+
+        let transfer = &report.per_type[0];
+        assert_eq!(transfer.tx_type, "transfer");
+        assert_eq!(
+            (
+                transfer.total,
+                transfer.verified,
+                transfer.committed,
+                transfer.rejected
+            ),
+            (4, 1, 1, 2)
+        );
+        assert_eq!(transfer.total_fee.0, BigUint::from(20u32));
+        assert_eq!(transfer.response_time.p50, 20.0);
+        assert_eq!(
+            report.failures,
+            vec![
+                FailureCount {
+                    tx_type: "transfer".to_string(),
+                    fail_reason: "Nonce mismatch".to_string(),
+                    count: 2,
+                },
+                FailureCount {
+                    tx_type: "withdraw".to_string(),
+                    fail_reason: "Not enough balance, \"RBTC\"".to_string(),
+                    count: 1,
+                },
+            ]
+        );
+        assert!(report
+            .failures_csv()
+            .ends_with("withdraw,\"Not enough balance, \"\"RBTC\"\"\",1\n"));
+        assert_eq!(report.transactions_csv().lines().count(), 6);
+        let latency_csv = report.latency_csv();
+        assert_eq!(latency_csv.lines().count(), 3);
+        assert!(latency_csv.contains("\ntransfer,submission,4,20.000,20.000,"));
+        assert_eq!(report.latency_timeline_csv().lines().count(), 1);
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use num::BigUint;
+
+use crate::rollup::tokens::TokenRegistry;
+
+/// Native token of Rootstock, whose decimals are known without the registry.
+const NATIVE_TOKEN: (&str, u8) = ("RBTC", 18);
+
+/// Writes `amount` of smallest units in whole units, e.g. `1250000000000000000` with 18
+/// decimals as `1.25`.
+pub fn format_units(amount: &BigUint, decimals: u8) -> String {
+    let digits = amount.to_str_radix(10);
+    let decimals = decimals as usize;
+    if decimals == 0 {
+        return digits;
+    }
+    let padded = format!("{:0>width$}", digits, width = decimals + 1);
+    let (whole, fraction) = padded.split_at(padded.len() - decimals);
+    match fraction.trim_end_matches('0') {
+        "" => whole.to_string(),
+        fraction => format!("{whole}.{fraction}"),
+    }
+}
+
+/// How the text tables write token amounts: in whole units with the token symbol, e.g.
+/// `1.25 RBTC`, or as the raw integers of smallest units with `general.raw_amounts`.
+///
+/// Decimals come from the token registry; amounts of tokens it does not list stay raw.
+#[derive(Debug, Clone)]
+pub struct AmountFormat {
+    decimals: HashMap<String, u8>,
+    raw: bool,
+}
+
+impl Default for AmountFormat {
+    fn default() -> Self {
+        let (symbol, decimals) = NATIVE_TOKEN;
+        Self {
+            decimals: HashMap::from([(symbol.to_string(), decimals)]),
+            raw: false,
+        }
+    }
+}
+
+impl AmountFormat {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Learns the decimals of every token of the registry.
+    pub fn add_registry(&mut self, registry: &TokenRegistry) {
+        for token in registry.tokens() {
+            self.decimals.insert(token.symbol.clone(), token.decimals);
+        }
+    }
+
+    /// Learns the decimals of a token the registry does not list, e.g. an L1 ERC20.
+    pub fn add_token(&mut self, symbol: &str, decimals: u8) {
+        self.decimals.insert(symbol.to_string(), decimals);
+    }
+
+    pub fn set_raw(&mut self, raw: bool) {
+        self.raw = raw;
+    }
+
+    /// Amount of `token` in smallest units, formatted for a report.
+    pub fn format(&self, amount: &BigUint, token: &str) -> String {
+        match self.decimals.get(token) {
+            Some(decimals) if !self.raw => format!("{} {}", format_units(amount, *decimals), token),
+            _ => amount.to_string(),
+        }
+    }
+
+    /// Amount of RBTC in wei, such as gas costs.
+    pub fn format_native(&self, amount: &BigUint) -> String {
+        self.format(amount, NATIVE_TOKEN.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rollup::types::{Address, Token, TokenId, TokenKind, Tokens};
+
+    /// Tests whole unit formatting, registry decimals and the raw toggle.
+    #[test]
+    fn test_amount_format() {
+        let wei = |value: u64| BigUint::from(value);
+        assert_eq!(format_units(&wei(1_250_000_000_000_000_000), 18), "1.25");
+        assert_eq!(format_units(&wei(5), 18), "0.000000000000000005");
+        assert_eq!(format_units(&wei(3_000_000), 6), "3");
+        assert_eq!(format_units(&wei(0), 2), "0");
+        assert_eq!(format_units(&wei(42), 0), "42");
+
+        let usdt = Token::new(TokenId(2), Address::zero(), "USDT", 6, TokenKind::ERC20);
+        let mut units = AmountFormat::new();
+        units.add_registry(&TokenRegistry::new(Tokens::from([(
+            "USDT".to_string(),
+            usdt,
+        )])));
+        assert_eq!(units.format_native(&wei(10u64.pow(17))), "0.1 RBTC");
+        assert_eq!(units.format(&wei(1_500_000), "USDT"), "1.5 USDT");
+        assert_eq!(units.format(&wei(1_500_000), "RIF"), "1500000");
+        units.set_raw(true);
+        assert_eq!(units.format(&wei(1_500_000), "USDT"), "1500000");
+    }
+}
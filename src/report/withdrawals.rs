@@ -0,0 +1,184 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use super::latency::Percentiles;
+use crate::rollup::types::TxHash;
+
+/// Stage a withdrawal reached on its way to Rootstock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WithdrawalStage {
+    Committed,
+    Verified,
+    /// The server sent the Rootstock transaction paying the withdrawal out.
+    L1Sent,
+    /// The Rootstock transaction was mined.
+    L1Confirmed,
+}
+
+impl WithdrawalStage {
+    pub fn name(self) -> &'static str {
+        match self {
+            WithdrawalStage::Committed => "committed",
+            WithdrawalStage::Verified => "verified",
+            WithdrawalStage::L1Sent => "l1_sent",
+            WithdrawalStage::L1Confirmed => "l1_confirmed",
+        }
+    }
+}
+
+/// Withdrawal followed from its submission to its Rootstock transaction.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Withdrawal {
+    pub tx_hash: TxHash,
+    pub tx_type: String,
+    pub eth_tx_hash: Option<String>,
+    /// Whether the Rootstock transaction was mined but reverted.
+    pub l1_failed: bool,
+    /// Time from submission until each stage was reached, in milliseconds.
+    pub stages: BTreeMap<WithdrawalStage, f64>,
+}
+
+/// Withdrawals that reached one stage and how long after submission they did.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WithdrawalStageRow {
+    pub stage: WithdrawalStage,
+    pub reached: usize,
+    pub latency: Percentiles,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WithdrawalSummary {
+    pub rows: Vec<WithdrawalStageRow>,
+    pub l1_failed: usize,
+    pub withdrawals: Vec<Withdrawal>,
+}
+
+impl WithdrawalSummary {
+    pub fn is_empty(&self) -> bool {
+        self.withdrawals.is_empty()
+    }
+}
+
+/// End-to-end latencies of withdrawals, from submission until the funds arrived on L1.
+///
+/// The L2 stages only tell that the rollup accepted the withdrawal; users wait until the
+/// server sent the Rootstock transaction and it was mined.
+#[derive(Debug, Default)]
+pub struct WithdrawalLifecycle {
+    withdrawals: Vec<Withdrawal>,
+    index: HashMap<TxHash, usize>,
+    latencies: BTreeMap<WithdrawalStage, Vec<Duration>>,
+}
+
+impl WithdrawalLifecycle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn withdrawal(&mut self, tx_hash: TxHash, tx_type: &str) -> &mut Withdrawal {
+        let withdrawals = &mut self.withdrawals;
+        let position = *self.index.entry(tx_hash).or_insert_with(|| {
+            withdrawals.push(Withdrawal {
+                tx_hash,
+                tx_type: tx_type.to_string(),
+                eth_tx_hash: None,
+                l1_failed: false,
+                stages: BTreeMap::new(),
+            });
+            withdrawals.len() - 1
+        });
+        &mut self.withdrawals[position]
+    }
+
+    /// Records that the withdrawal reached `stage` `latency` after it was submitted.
+    pub fn record_stage(
+        &mut self,
+        tx_hash: TxHash,
+        tx_type: &str,
+        stage: WithdrawalStage,
+        latency: Duration,
+    ) {
+        let withdrawal = self.withdrawal(tx_hash, tx_type);
+        if withdrawal.stages.contains_key(&stage) {
+            return;
+        }
+        withdrawal
+            .stages
+            .insert(stage, latency.as_secs_f64() * 1000.0);
+        self.latencies.entry(stage).or_default().push(latency);
+    }
+
+    /// Records the Rootstock transaction the server sent for the withdrawal.
+    pub fn record_eth_tx(
+        &mut self,
+        tx_hash: TxHash,
+        tx_type: &str,
+        eth_tx_hash: String,
+        latency: Duration,
+    ) {
+        self.withdrawal(tx_hash, tx_type).eth_tx_hash = Some(eth_tx_hash);
+        self.record_stage(tx_hash, tx_type, WithdrawalStage::L1Sent, latency);
+    }
+
+    /// Records the mined Rootstock transaction of the withdrawal and whether it succeeded.
+    pub fn record_receipt(
+        &mut self,
+        tx_hash: TxHash,
+        tx_type: &str,
+        success: bool,
+        latency: Duration,
+    ) {
+        self.withdrawal(tx_hash, tx_type).l1_failed = !success;
+        self.record_stage(tx_hash, tx_type, WithdrawalStage::L1Confirmed, latency);
+    }
+
+    fn rows(&self) -> Vec<WithdrawalStageRow> {
+        self.latencies
+            .iter()
+            .map(|(stage, latencies)| WithdrawalStageRow {
+                stage: *stage,
+                reached: latencies.len(),
+                latency: Percentiles::from_samples(latencies),
+            })
+            .collect()
+    }
+
+    pub fn summary(&self) -> WithdrawalSummary {
+        WithdrawalSummary {
+            rows: self.rows(),
+            l1_failed: self
+                .withdrawals
+                .iter()
+                .filter(|withdrawal| withdrawal.l1_failed)
+                .count(),
+            withdrawals: self.withdrawals.clone(),
+        }
+    }
+
+    /// Renders the time from submission until each stage as a plain text table, in milliseconds.
+    pub fn render_table(&self) -> String {
+        let mut table = format!(
+            "{:<16} {:>10} {:>12} {:>12} {:>12}\n",
+            "withdrawal stage", "reached", "p50 ms", "p90 ms", "max ms"
+        );
+        for row in self.rows() {
+            let _ = writeln!(
+                table,
+                "{:<16} {:>10} {:>12.0} {:>12.0} {:>12.0}",
+                row.stage.name(),
+                row.reached,
+                row.latency.p50,
+                row.latency.p90,
+                row.latency.max
+            );
+        }
+        table
+    }
+}
@@ -0,0 +1,206 @@
+//! Resubmission study: a sampled share of accepted transactions is sent again, byte for
+//! byte, after a fixed delay whatever became of the first submission. How the server
+//! answers tells which idempotency guarantees wallet SDKs retrying a send can rely on.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rand::Rng;
+use serde::Deserialize;
+use tokio::task::JoinSet;
+use tracing::{debug, warn, Instrument};
+
+use crate::engine::TxPipeline;
+use crate::metrics::Metrics;
+use crate::report::resubmissions::{Resubmission, ResubmissionOutcome};
+use crate::report::RunRecorder;
+use crate::rng::StreamRng;
+use crate::rollup::types::TxHash;
+
+/// Counter of resubmitted transactions, labelled by type and outcome.
+pub const RESUBMISSION_METRIC: &str = "tx_resubmissions_total";
+
+/// The `[resubmission]` section, the study is disabled when it is missing.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResubmissionConfig {
+    /// Percentage of accepted transactions sent again.
+    #[serde(default = "ResubmissionConfig::default_sample_percent")]
+    pub sample_percent: f64,
+    /// Time between the first submission being accepted and the resubmission.
+    #[serde(default = "ResubmissionConfig::default_delay_secs")]
+    pub delay_secs: u64,
+}
+
+impl ResubmissionConfig {
+    fn default_sample_percent() -> f64 {
+        1.0
+    }
+
+    fn default_delay_secs() -> u64 {
+        5
+    }
+}
+
+impl Default for ResubmissionConfig {
+    fn default() -> Self {
+        Self {
+            sample_percent: Self::default_sample_percent(),
+            delay_secs: Self::default_delay_secs(),
+        }
+    }
+}
+
+/// Picks the transactions to resubmit and sends them again once their delay passed.
+#[derive(Debug)]
+pub struct ResubmissionStudy {
+    config: ResubmissionConfig,
+    rng: Mutex<StreamRng>,
+    pending: Mutex<JoinSet<()>>,
+}
+
+impl ResubmissionStudy {
+    pub fn new(config: ResubmissionConfig, rng: StreamRng) -> Self {
+        Self {
+            config,
+            rng: Mutex::new(rng),
+            pending: Mutex::new(JoinSet::new()),
+        }
+    }
+
+    /// Whether the next transaction is part of the sample.
+    pub fn sample(&self) -> bool {
+        let fraction = (self.config.sample_percent / 100.0).clamp(0.0, 1.0);
+        self.rng.lock().unwrap().gen_bool(fraction)
+    }
+
+    /// Sends `copy` of the transaction accepted as `tx_hash` again after the delay.
+    pub fn schedule<P: TxPipeline>(
+        &self,
+        pipeline: Arc<P>,
+        recorder: Arc<RunRecorder>,
+        metrics: Arc<Metrics>,
+        tx_hash: TxHash,
+        copy: P::Tx,
+    ) {
+        let delay = Duration::from_secs(self.config.delay_secs);
+        let task = async move {
+            tokio::time::sleep(delay).await;
+            let tx_type = P::tx_type(&copy);
+            let resubmission = resubmit(&*pipeline, tx_hash, copy, delay).await;
+            let outcome = resubmission.outcome.name();
+            metrics.increment(
+                RESUBMISSION_METRIC,
+                &[("type", tx_type), ("outcome", outcome)],
+            );
+            match resubmission.outcome {
+                ResubmissionOutcome::NewHash => {
+                    warn!(tx_type, %tx_hash, "resubmitted transaction accepted with a new hash")
+                }
+                _ => debug!(tx_type, %tx_hash, outcome, "transaction resubmitted"),
+            }
+            recorder.with_resubmissions(|resubmissions| resubmissions.record(resubmission));
+        };
+        self.pending.lock().unwrap().spawn(task.in_current_span());
+    }
+
+    /// Waits for the scheduled resubmissions, at most one delay once the run stopped.
+    pub async fn finish(&self) {
+        let mut pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        while pending.join_next().await.is_some() {}
+    }
+}
+
+async fn resubmit<P: TxPipeline>(
+    pipeline: &P,
+    tx_hash: TxHash,
+    copy: P::Tx,
+    delay: Duration,
+) -> Resubmission {
+    let tx_type = P::tx_type(&copy).to_string();
+    let (outcome, resubmitted_hash, error) = match pipeline.submit(copy).await {
+        Ok(hash) if hash == tx_hash => (ResubmissionOutcome::SameHash, Some(hash), None),
+        Ok(hash) => (ResubmissionOutcome::NewHash, Some(hash), None),
+        Err(err) => (ResubmissionOutcome::Rejected, None, Some(err.to_string())),
+    };
+    Resubmission {
+        tx_type,
+        tx_hash,
+        outcome,
+        resubmitted_hash,
+        error,
+        delay_ms: delay.as_millis() as u64,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::rollup::provider::ClientError;
+
+    /// Pipeline answering with the same hash, a new hash or an error depending on the transaction.
+    struct DedupPipeline;
+
+    #[async_trait]
+    impl TxPipeline for DedupPipeline {
+        type Tx = u8;
+
+        async fn prepare(&self) -> Option<u8> {
+            None
+        }
+
+        fn tx_type(_tx: &u8) -> &'static str {
+            "transfer"
+        }
+
+        async fn submit(&self, tx: u8) -> Result<TxHash, ClientError> {
+            match tx {
+                0 => Ok(TxHash { data: [0; 32] }),
+                1 => Ok(TxHash { data: [9; 32] }),
+                _ => Err(ClientError::IncorrectInput),
+            }
+        }
+    }
+
+    /// Tests that resubmissions are classified and recorded once the study finishes.
+    #[tokio::test]
+    async fn test_resubmission_outcomes() {
+        let config = ResubmissionConfig {
+            sample_percent: 100.0,
+            delay_secs: 0,
+        };
+        let study = ResubmissionStudy::new(config, StreamRng::seed_from_u64(1));
+        assert!(study.sample());
+
+        let pipeline = Arc::new(DedupPipeline);
+        let recorder = Arc::new(RunRecorder::new());
+        let metrics = Arc::new(Metrics::new());
+        for copy in 0..3 {
+            let tx_hash = TxHash { data: [0; 32] };
+            study.schedule(
+                pipeline.clone(),
+                recorder.clone(),
+                metrics.clone(),
+                tx_hash,
+                copy,
+            );
+        }
+        study.finish().await;
+
+        let summary = recorder.with_resubmissions(|resubmissions| resubmissions.summary());
+        assert_eq!(summary.resubmissions.len(), 3);
+        let row = &summary.rows[0];
+        assert_eq!((row.same_hash, row.new_hash, row.rejected), (1, 1, 1));
+        assert_eq!(row.errors.values().sum::<u64>(), 1);
+
+        let never = ResubmissionStudy::new(
+            ResubmissionConfig {
+                sample_percent: 0.0,
+                delay_secs: 0,
+            },
+            StreamRng::seed_from_u64(1),
+        );
+        assert!(!never.sample());
+    }
+}
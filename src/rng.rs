@@ -0,0 +1,262 @@
+//! Named random streams derived from one master seed.
+//!
+//! Every source of randomness draws from its own stream, so a run can be reproduced in
+//! part: keeping the master seed while overriding the `timing` stream replays the same
+//! amounts and recipients with a different schedule. Streams count the 32-bit words they
+//! hand out, which is enough to fast-forward a fresh stream to the same position.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use rand::rngs::StdRng;
+use rand::{Error, RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::info;
+
+/// Purpose a random stream is drawn for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RngStream {
+    Amounts,
+    Recipients,
+    Timing,
+    Chaos,
+    /// Picks the transactions studied more closely, such as the resubmission sample.
+    Sampling,
+    /// Picks the wallet bugs emulated and when.
+    Misbehavior,
+}
+
+impl RngStream {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Amounts => "amounts",
+            Self::Recipients => "recipients",
+            Self::Timing => "timing",
+            Self::Chaos => "chaos",
+            Self::Sampling => "sampling",
+            Self::Misbehavior => "misbehavior",
+        }
+    }
+}
+
+/// The `[rng]` section, e.g. `seed = 42` with `streams = { timing = 7 }`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RngConfig {
+    /// Master seed every stream is derived from, drawn from entropy and logged when not set.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Seeds replacing the derived seed of single streams.
+    #[serde(default)]
+    pub streams: BTreeMap<RngStream, u64>,
+}
+
+/// Master seed and stream positions, written to the checkpoint of an interrupted run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RngCheckpoint {
+    pub master_seed: u64,
+    /// Words drawn from each stream handed out so far.
+    pub positions: BTreeMap<RngStream, u64>,
+}
+
+/// Random generator of one stream, counting the words drawn from it.
+#[derive(Debug)]
+pub struct StreamRng {
+    rng: StdRng,
+    position: Arc<AtomicU64>,
+}
+
+impl StreamRng {
+    fn new(seed: [u8; 32]) -> Self {
+        Self {
+            rng: StdRng::from_seed(seed),
+            position: Arc::default(),
+        }
+    }
+
+    /// Generator of a stream used on its own, outside of [`RngStreams`].
+    pub fn seed_from_u64(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            position: Arc::default(),
+        }
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position.load(Ordering::Relaxed)
+    }
+
+    /// Skips ahead until `position` words were drawn.
+    pub fn advance_to(&mut self, position: u64) {
+        while self.position() < position {
+            self.next_u32();
+        }
+    }
+
+    fn consumed(&self, words: usize) {
+        self.position.fetch_add(words as u64, Ordering::Relaxed);
+    }
+}
+
+impl RngCore for StreamRng {
+    fn next_u32(&mut self) -> u32 {
+        self.consumed(1);
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.consumed(2);
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        // The generator discards the unused bytes of the last word.
+        self.consumed(dest.len().div_ceil(4));
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Hands out the random streams of a run and keeps track of their positions.
+#[derive(Debug, Clone)]
+pub struct RngStreams {
+    master_seed: u64,
+    overrides: BTreeMap<RngStream, u64>,
+    /// Positions a stream is fast-forwarded to when handed out, on resume.
+    resume_at: BTreeMap<RngStream, u64>,
+    positions: Arc<Mutex<BTreeMap<RngStream, Arc<AtomicU64>>>>,
+}
+
+impl RngStreams {
+    pub fn new(config: &RngConfig) -> Self {
+        let master_seed = config.seed.unwrap_or_else(rand::random);
+        info!(master_seed, "Random streams seeded");
+        Self {
+            master_seed,
+            overrides: config.streams.clone(),
+            resume_at: BTreeMap::new(),
+            positions: Arc::default(),
+        }
+    }
+
+    /// Streams of an interrupted run, continuing where the checkpoint left them.
+    ///
+    /// Overridden streams keep their configured seed but still skip the drawn words.
+    pub fn resume(config: &RngConfig, checkpoint: &RngCheckpoint) -> Self {
+        info!(
+            master_seed = checkpoint.master_seed,
+            positions = ?checkpoint.positions,
+            "Random streams resumed"
+        );
+        Self {
+            master_seed: checkpoint.master_seed,
+            overrides: config.streams.clone(),
+            resume_at: checkpoint.positions.clone(),
+            positions: Arc::default(),
+        }
+    }
+
+    pub fn master_seed(&self) -> u64 {
+        self.master_seed
+    }
+
+    /// Generator of a stream; every stream is meant to have a single owner, asking for it
+    /// again starts it over.
+    pub fn stream(&self, stream: RngStream) -> StreamRng {
+        let mut rng = match self.overrides.get(&stream) {
+            Some(seed) => StreamRng::seed_from_u64(*seed),
+            None => StreamRng::new(derive_seed(self.master_seed, stream)),
+        };
+        if let Some(position) = self.resume_at.get(&stream) {
+            rng.advance_to(*position);
+        }
+        self.positions
+            .lock()
+            .unwrap()
+            .insert(stream, rng.position.clone());
+        rng
+    }
+
+    /// Current positions of the streams handed out, logged so a run can be audited.
+    pub fn checkpoint(&self) -> RngCheckpoint {
+        let positions: BTreeMap<RngStream, u64> = self
+            .positions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(stream, position)| (*stream, position.load(Ordering::Relaxed)))
+            .collect();
+        info!(
+            master_seed = self.master_seed,
+            ?positions,
+            "Random stream positions"
+        );
+        RngCheckpoint {
+            master_seed: self.master_seed,
+            positions,
+        }
+    }
+}
+
+fn derive_seed(master_seed: u64, stream: RngStream) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(master_seed.to_le_bytes());
+    hasher.update(stream.name().as_bytes());
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod test {
+    use rand::Rng;
+
+    use super::*;
+
+    /// Tests that streams are independent and can be resumed from their recorded positions.
+    #[test]
+    fn test_streams_resume_from_positions() {
+        let config = RngConfig {
+            seed: Some(42),
+            streams: BTreeMap::new(),
+        };
+        let streams = RngStreams::new(&config);
+        let mut amounts = streams.stream(RngStream::Amounts);
+        let mut timing = streams.stream(RngStream::Timing);
+        assert_ne!(amounts.next_u64(), timing.next_u64());
+        let _: u8 = amounts.gen();
+        let mut bytes = [0u8; 5];
+        amounts.fill_bytes(&mut bytes);
+        let _ = amounts.gen_range(0..1_000u32);
+
+        let checkpoint = streams.checkpoint();
+        assert_eq!(
+            checkpoint.positions[&RngStream::Amounts],
+            amounts.position()
+        );
+        assert_eq!(checkpoint.positions[&RngStream::Timing], 2);
+
+        let resumed = RngStreams::resume(&config, &checkpoint);
+        let mut resumed_amounts = resumed.stream(RngStream::Amounts);
+        assert_eq!(resumed_amounts.next_u64(), amounts.next_u64());
+
+        let reseeded = RngStreams::new(&RngConfig {
+            seed: Some(42),
+            streams: BTreeMap::from([(RngStream::Timing, 7)]),
+        });
+        let mut fresh_amounts = streams.stream(RngStream::Amounts);
+        assert_eq!(
+            reseeded.stream(RngStream::Amounts).next_u64(),
+            fresh_amounts.next_u64()
+        );
+        assert_ne!(
+            reseeded.stream(RngStream::Timing).next_u64(),
+            streams.stream(RngStream::Timing).next_u64()
+        );
+    }
+}
@@ -0,0 +1,162 @@
+//! Adapters hiding the differences between rollup server API versions.
+//!
+//! Every adapter knows how to turn a provider call into a wire request for its
+//! API version and how to translate the server response back into the crate's
+//! internal types, so the rest of the simulator never sees version specifics.
+
+use num::BigUint;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::provider::{ClientError, ProviderMethod, ResponseResult};
+use super::types::{
+    AccountInfo, AccountTx, Address, ContractAddress, EthOpInfo, Fee, Paginated, PaginationQuery,
+    TokenLike, Tokens, TransactionInfo, TxFeeTypes, TxHash,
+};
+
+pub mod v01;
+pub mod v02;
+
+/// Version of the rollup server API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+pub enum ApiVersion {
+    /// JSON-RPC API.
+    #[default]
+    #[serde(rename = "v0.1")]
+    V01,
+    /// REST API.
+    #[serde(rename = "v0.2")]
+    V02,
+}
+
+impl ApiVersion {
+    /// Label of the version in metrics and logs.
+    pub fn label(self) -> &'static str {
+        match self {
+            ApiVersion::V01 => "v0.1",
+            ApiVersion::V02 => "v0.2",
+        }
+    }
+}
+
+/// Provider call expressed independently of the API version.
+#[derive(Debug, Clone)]
+pub enum ApiCall {
+    AccountInfo(Address),
+    AccountTxs {
+        address: Address,
+        query: PaginationQuery,
+    },
+    Tokens,
+    TxInfo(TxHash),
+    EthOpInfo(u32),
+    TxFee {
+        tx_type: TxFeeTypes,
+        address: Address,
+        token: TokenLike,
+    },
+    TxsBatchFee {
+        tx_types: Vec<TxFeeTypes>,
+        addresses: Vec<Address>,
+        token: TokenLike,
+    },
+    EthTxForWithdrawal(TxHash),
+    ContractAddress,
+    SendTx {
+        tx: Value,
+        eth_signature: Option<Value>,
+    },
+    SendTxsBatch {
+        txs: Vec<(Value, Option<Value>)>,
+        eth_signature: Option<Value>,
+    },
+}
+
+impl ApiCall {
+    /// Provider method this call is made on behalf of.
+    pub fn method(&self) -> ProviderMethod {
+        match self {
+            ApiCall::AccountInfo(_) => ProviderMethod::AccountInfo,
+            ApiCall::AccountTxs { .. } => ProviderMethod::AccountTxs,
+            ApiCall::Tokens => ProviderMethod::Tokens,
+            ApiCall::TxInfo(_) => ProviderMethod::TxInfo,
+            ApiCall::EthOpInfo(_) => ProviderMethod::EthOpInfo,
+            ApiCall::TxFee { .. } => ProviderMethod::GetTxFee,
+            ApiCall::TxsBatchFee { .. } => ProviderMethod::GetTxsBatchFee,
+            ApiCall::EthTxForWithdrawal(_) => ProviderMethod::GetEthTxForWithdrawal,
+            ApiCall::ContractAddress => ProviderMethod::ContractAddress,
+            ApiCall::SendTx { .. } => ProviderMethod::SendTx,
+            ApiCall::SendTxsBatch { .. } => ProviderMethod::SendTxsBatch,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpMethod {
+    Get,
+    Post,
+}
+
+/// Wire-level request produced by an adapter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiRequest {
+    JsonRpc {
+        method: &'static str,
+        params: Value,
+    },
+    Rest {
+        method: HttpMethod,
+        path: String,
+        body: Option<Value>,
+    },
+}
+
+/// Translates provider calls to the wire format of one API version and back.
+///
+/// Responses passed to the `parse_*` methods are the payload of a successful
+/// call, i.e. the JSON-RPC `result` or the REST response body.
+pub trait ApiAdapter: Send + Sync {
+    /// API version handled by this adapter.
+    fn version(&self) -> ApiVersion;
+
+    /// Builds the wire request for the given call.
+    fn request(&self, call: &ApiCall) -> ResponseResult<ApiRequest>;
+
+    /// Extracts the payload from a response body, failing with the error the server reported.
+    fn unwrap_response(&self, body: Value) -> ResponseResult<Value>;
+
+    fn parse_account_info(&self, response: Value) -> ResponseResult<AccountInfo>;
+
+    fn parse_account_txs(&self, response: Value) -> ResponseResult<Paginated<AccountTx>>;
+
+    fn parse_tokens(&self, response: Value) -> ResponseResult<Tokens>;
+
+    fn parse_tx_info(&self, response: Value) -> ResponseResult<TransactionInfo>;
+
+    fn parse_ethop_info(&self, response: Value) -> ResponseResult<EthOpInfo>;
+
+    fn parse_tx_fee(&self, tx_type: TxFeeTypes, response: Value) -> ResponseResult<Fee>;
+
+    fn parse_txs_batch_fee(&self, response: Value) -> ResponseResult<BigUint>;
+
+    fn parse_eth_tx_for_withdrawal(&self, response: Value) -> ResponseResult<Option<String>>;
+
+    fn parse_contract_address(&self, response: Value) -> ResponseResult<ContractAddress>;
+
+    fn parse_tx_hash(&self, response: Value) -> ResponseResult<TxHash>;
+
+    fn parse_tx_hashes(&self, response: Value) -> ResponseResult<Vec<TxHash>>;
+}
+
+/// Returns the adapter for the given API version.
+pub fn adapter_for(version: ApiVersion) -> Box<dyn ApiAdapter> {
+    match version {
+        ApiVersion::V01 => Box::new(v01::JsonRpcAdapter),
+        ApiVersion::V02 => Box::new(v02::RestAdapter),
+    }
+}
+
+/// Deserializes a response payload into the given internal type.
+pub(crate) fn decode<T: serde::de::DeserializeOwned>(response: Value) -> ResponseResult<T> {
+    serde_json::from_value(response).map_err(|err| ClientError::MalformedResponse(err.to_string()))
+}
@@ -0,0 +1,132 @@
+use num::BigUint;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::{decode, ApiAdapter, ApiCall, ApiRequest, ApiVersion};
+use crate::rollup::provider::{ClientError, ResponseResult};
+use crate::rollup::types::{
+    AccountInfo, AccountTx, BatchFee, ContractAddress, EthOpInfo, Fee, Paginated, Tokens,
+    TransactionInfo, TxFeeTypes, TxHash,
+};
+
+#[derive(Debug, Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+/// JSON-RPC 2.0 response object.
+#[derive(Debug, Deserialize)]
+struct RpcResponse {
+    result: Option<Value>,
+    error: Option<RpcError>,
+}
+
+/// Extracts the `result` from a JSON-RPC response.
+pub fn unwrap_rpc_response(response: Value) -> ResponseResult<Value> {
+    let response: RpcResponse = decode(response)?;
+    match response.error {
+        Some(err) => Err(ClientError::NetworkError(format!(
+            "{} (code {})",
+            err.message, err.code
+        ))),
+        None => Ok(response.result.unwrap_or(Value::Null)),
+    }
+}
+
+/// Adapter for the v0.1 JSON-RPC API.
+///
+/// Internal types mirror the v0.1 responses, so translation is plain deserialization.
+pub struct JsonRpcAdapter;
+
+impl ApiAdapter for JsonRpcAdapter {
+    fn version(&self) -> ApiVersion {
+        ApiVersion::V01
+    }
+
+    fn request(&self, call: &ApiCall) -> ResponseResult<ApiRequest> {
+        let (method, params) = match call {
+            ApiCall::AccountInfo(address) => ("account_info", json!([address])),
+            ApiCall::AccountTxs { .. } => {
+                // The JSON-RPC API has no access to the transaction history.
+                return Err(ClientError::UnsupportedMethod("account_txs".to_string()));
+            }
+            ApiCall::Tokens => ("tokens", json!([])),
+            ApiCall::TxInfo(tx_hash) => ("tx_info", json!([tx_hash])),
+            ApiCall::EthOpInfo(serial_id) => ("ethop_info", json!([serial_id])),
+            ApiCall::TxFee {
+                tx_type,
+                address,
+                token,
+            } => ("get_tx_fee", json!([tx_type, address, token])),
+            ApiCall::TxsBatchFee {
+                tx_types,
+                addresses,
+                token,
+            } => (
+                "get_txs_batch_fee_in_wei",
+                json!([tx_types, addresses, token]),
+            ),
+            ApiCall::EthTxForWithdrawal(tx_hash) => ("get_eth_tx_for_withdrawal", json!([tx_hash])),
+            ApiCall::ContractAddress => ("contract_address", json!([])),
+            ApiCall::SendTx { tx, eth_signature } => ("tx_submit", json!([tx, eth_signature])),
+            ApiCall::SendTxsBatch { txs, eth_signature } => {
+                let txs: Vec<Value> = txs
+                    .iter()
+                    .map(|(tx, signature)| json!({ "tx": tx, "signature": signature }))
+                    .collect();
+                ("submit_txs_batch", json!([txs, eth_signature]))
+            }
+        };
+
+        Ok(ApiRequest::JsonRpc { method, params })
+    }
+
+    fn unwrap_response(&self, body: Value) -> ResponseResult<Value> {
+        unwrap_rpc_response(body)
+    }
+
+    fn parse_account_info(&self, response: Value) -> ResponseResult<AccountInfo> {
+        decode(response)
+    }
+
+    fn parse_account_txs(&self, _response: Value) -> ResponseResult<Paginated<AccountTx>> {
+        Err(ClientError::UnsupportedMethod("account_txs".to_string()))
+    }
+
+    fn parse_tokens(&self, response: Value) -> ResponseResult<Tokens> {
+        decode(response)
+    }
+
+    fn parse_tx_info(&self, response: Value) -> ResponseResult<TransactionInfo> {
+        decode(response)
+    }
+
+    fn parse_ethop_info(&self, response: Value) -> ResponseResult<EthOpInfo> {
+        decode(response)
+    }
+
+    fn parse_tx_fee(&self, _tx_type: TxFeeTypes, response: Value) -> ResponseResult<Fee> {
+        decode(response)
+    }
+
+    fn parse_txs_batch_fee(&self, response: Value) -> ResponseResult<BigUint> {
+        decode::<BatchFee>(response).map(|fee| fee.total_fee)
+    }
+
+    fn parse_eth_tx_for_withdrawal(&self, response: Value) -> ResponseResult<Option<String>> {
+        decode(response)
+    }
+
+    fn parse_contract_address(&self, response: Value) -> ResponseResult<ContractAddress> {
+        decode(response)
+    }
+
+    fn parse_tx_hash(&self, response: Value) -> ResponseResult<TxHash> {
+        decode(response)
+    }
+
+    fn parse_tx_hashes(&self, response: Value) -> ResponseResult<Vec<TxHash>> {
+        decode(response)
+    }
+}
@@ -0,0 +1,473 @@
+use std::collections::HashMap;
+
+use num::BigUint;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::{decode, ApiAdapter, ApiCall, ApiRequest, ApiVersion, HttpMethod};
+use crate::rollup::provider::{ClientError, ResponseResult};
+use crate::rollup::types::pubkey_hash::PubKeyHash;
+use crate::rollup::types::serde_wrappers::{BigUintSerdeAsRadix10Str, BigUintSerdeWrapper};
+use crate::rollup::types::{
+    AccountId, AccountInfo, AccountState, AccountTx, Address, BlockInfo, ContractAddress,
+    EthOpInfo, Fee, Nonce, OutputFeeType, Paginated, Token, TokenId, TokenKind, Tokens,
+    TransactionInfo, TxFeeTypes, TxHash, NFT,
+};
+
+/// Prefix of all v0.2 REST endpoints.
+pub const API_PREFIX: &str = "/api/v0.2";
+
+/// Maximum page size accepted by the v0.2 list endpoints.
+pub const MAX_PAGE_LIMIT: u32 = 100;
+
+/// Adapter for the v0.2 REST API.
+pub struct RestAdapter;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ResponseStatus {
+    Success,
+    Error,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiError {
+    message: String,
+}
+
+/// Envelope wrapping every v0.2 response.
+#[derive(Debug, Deserialize)]
+struct Response {
+    status: ResponseStatus,
+    result: Option<Value>,
+    error: Option<ApiError>,
+}
+
+/// Extracts the payload from a v0.2 response envelope.
+pub fn unwrap_envelope(response: Value) -> ResponseResult<Value> {
+    let response: Response = decode(response)?;
+    match response.status {
+        ResponseStatus::Success => Ok(response.result.unwrap_or(Value::Null)),
+        ResponseStatus::Error => Err(ClientError::NetworkError(
+            response
+                .error
+                .map(|err| err.message)
+                .unwrap_or_else(|| "unknown server error".to_string()),
+        )),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiAccount {
+    account_id: AccountId,
+    address: Address,
+    nonce: Nonce,
+    pub_key_hash: PubKeyHash,
+    balances: HashMap<String, BigUintSerdeWrapper>,
+    #[serde(default)]
+    nfts: HashMap<TokenId, NFT>,
+}
+
+impl From<ApiAccount> for AccountState {
+    fn from(account: ApiAccount) -> Self {
+        Self {
+            balances: account.balances,
+            nfts: account.nfts,
+            nonce: account.nonce,
+            pub_key_hash: account.pub_key_hash,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiAccountFullInfo {
+    committed: Option<ApiAccount>,
+    finalized: Option<ApiAccount>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiToken {
+    id: TokenId,
+    address: Address,
+    symbol: String,
+    decimals: u8,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiPaginated<T> {
+    list: Vec<T>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+enum ApiTxStatus {
+    Queued,
+    Committed,
+    Finalized,
+    Rejected,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiTxReceipt {
+    status: ApiTxStatus,
+    fail_reason: Option<String>,
+    rollup_block: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiFee {
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    gas_fee: BigUint,
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    zkp_fee: BigUint,
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    total_fee: BigUint,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiConfig {
+    contract: Address,
+    gov_contract: Address,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiTransaction {
+    eth_tx_hash: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiTxData {
+    tx: ApiTransaction,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiSubmitBatchResponse {
+    transaction_hashes: Vec<TxHash>,
+}
+
+/// Maps the fee type requested by the user to the one reported by the server.
+fn output_fee_type(tx_type: TxFeeTypes) -> OutputFeeType {
+    match tx_type {
+        TxFeeTypes::Withdraw => OutputFeeType::Withdraw,
+        TxFeeTypes::FastWithdraw => OutputFeeType::FastWithdraw,
+        TxFeeTypes::Transfer => OutputFeeType::Transfer,
+        TxFeeTypes::ChangePubKey(fee_type) => OutputFeeType::ChangePubKey(fee_type),
+        TxFeeTypes::MintNFT => OutputFeeType::MintNFT,
+        TxFeeTypes::WithdrawNFT => OutputFeeType::WithdrawNFT,
+        TxFeeTypes::FastWithdrawNFT => OutputFeeType::FastWithdrawNFT,
+    }
+}
+
+fn path(suffix: impl AsRef<str>) -> String {
+    format!("{}{}", API_PREFIX, suffix.as_ref())
+}
+
+impl ApiAdapter for RestAdapter {
+    fn version(&self) -> ApiVersion {
+        ApiVersion::V02
+    }
+
+    fn request(&self, call: &ApiCall) -> ResponseResult<ApiRequest> {
+        let (method, path, body) = match call {
+            ApiCall::AccountInfo(address) => (
+                HttpMethod::Get,
+                path(format!("/accounts/{:?}/full", address)),
+                None,
+            ),
+            ApiCall::AccountTxs { address, query } => (
+                HttpMethod::Get,
+                path(format!(
+                    "/accounts/{:?}/transactions?from={}&limit={}&direction={}",
+                    address,
+                    query.from,
+                    query.limit.min(MAX_PAGE_LIMIT),
+                    query.direction
+                )),
+                None,
+            ),
+            ApiCall::Tokens => (
+                HttpMethod::Get,
+                path(format!(
+                    "/tokens?from=latest&limit={}&direction=older",
+                    MAX_PAGE_LIMIT
+                )),
+                None,
+            ),
+            ApiCall::TxInfo(tx_hash) => (
+                HttpMethod::Get,
+                path(format!("/transactions/{}", tx_hash)),
+                None,
+            ),
+            ApiCall::EthOpInfo(_) => {
+                // v0.2 only looks priority operations up by their hash.
+                return Err(ClientError::UnsupportedMethod("ethop_info".to_string()));
+            }
+            ApiCall::TxFee {
+                tx_type,
+                address,
+                token,
+            } => (
+                HttpMethod::Post,
+                path("/fee"),
+                Some(json!({ "txType": tx_type, "address": address, "tokenLike": token })),
+            ),
+            ApiCall::TxsBatchFee {
+                tx_types,
+                addresses,
+                token,
+            } => {
+                let transactions: Vec<Value> = tx_types
+                    .iter()
+                    .zip(addresses)
+                    .map(|(tx_type, address)| json!({ "txType": tx_type, "address": address }))
+                    .collect();
+                (
+                    HttpMethod::Post,
+                    path("/fee/batch"),
+                    Some(json!({ "transactions": transactions, "tokenLike": token })),
+                )
+            }
+            ApiCall::EthTxForWithdrawal(tx_hash) => (
+                HttpMethod::Get,
+                path(format!("/transactions/{}/data", tx_hash)),
+                None,
+            ),
+            ApiCall::ContractAddress => (HttpMethod::Get, path("/config"), None),
+            ApiCall::SendTx { tx, eth_signature } => (
+                HttpMethod::Post,
+                path("/transactions"),
+                Some(json!({ "tx": tx, "signature": eth_signature })),
+            ),
+            ApiCall::SendTxsBatch { txs, eth_signature } => {
+                let txs: Vec<Value> = txs
+                    .iter()
+                    .map(|(tx, signature)| json!({ "tx": tx, "signature": signature }))
+                    .collect();
+                (
+                    HttpMethod::Post,
+                    path("/transactions/batches"),
+                    Some(json!({ "txs": txs, "signature": eth_signature })),
+                )
+            }
+        };
+
+        Ok(ApiRequest::Rest { method, path, body })
+    }
+
+    fn unwrap_response(&self, body: Value) -> ResponseResult<Value> {
+        unwrap_envelope(body)
+    }
+
+    fn parse_account_info(&self, response: Value) -> ResponseResult<AccountInfo> {
+        let info: ApiAccountFullInfo = decode(response)?;
+        let address = info
+            .committed
+            .as_ref()
+            .or(info.finalized.as_ref())
+            .map(|account| account.address)
+            .unwrap_or_default();
+        let id = info
+            .committed
+            .as_ref()
+            .or(info.finalized.as_ref())
+            .map(|account| account.account_id);
+
+        Ok(AccountInfo {
+            address,
+            id,
+            depositing: Default::default(),
+            committed: info.committed.map(Into::into).unwrap_or_default(),
+            verified: info.finalized.map(Into::into).unwrap_or_default(),
+        })
+    }
+
+    fn parse_account_txs(&self, response: Value) -> ResponseResult<Paginated<AccountTx>> {
+        decode(response)
+    }
+
+    fn parse_tokens(&self, response: Value) -> ResponseResult<Tokens> {
+        let page: ApiPaginated<ApiToken> = decode(response)?;
+        Ok(page
+            .list
+            .into_iter()
+            .map(|token| {
+                let token = Token::new(
+                    token.id,
+                    token.address,
+                    &token.symbol,
+                    token.decimals,
+                    TokenKind::ERC20,
+                );
+                (token.symbol.clone(), token)
+            })
+            .collect())
+    }
+
+    fn parse_tx_info(&self, response: Value) -> ResponseResult<TransactionInfo> {
+        let receipt: Option<ApiTxReceipt> = decode(response)?;
+        let receipt = match receipt {
+            Some(receipt) => receipt,
+            None => {
+                return Ok(TransactionInfo {
+                    executed: false,
+                    success: None,
+                    fail_reason: None,
+                    block: None,
+                })
+            }
+        };
+
+        let block = receipt.rollup_block.map(|block_number| BlockInfo {
+            block_number,
+            committed: matches!(
+                receipt.status,
+                ApiTxStatus::Committed | ApiTxStatus::Finalized
+            ),
+            verified: receipt.status == ApiTxStatus::Finalized,
+        });
+
+        Ok(TransactionInfo {
+            executed: receipt.status != ApiTxStatus::Queued,
+            success: match receipt.status {
+                ApiTxStatus::Queued => None,
+                ApiTxStatus::Rejected => Some(false),
+                ApiTxStatus::Committed | ApiTxStatus::Finalized => Some(true),
+            },
+            fail_reason: receipt.fail_reason,
+            block,
+        })
+    }
+
+    fn parse_ethop_info(&self, _response: Value) -> ResponseResult<EthOpInfo> {
+        Err(ClientError::UnsupportedMethod("ethop_info".to_string()))
+    }
+
+    fn parse_tx_fee(&self, tx_type: TxFeeTypes, response: Value) -> ResponseResult<Fee> {
+        let fee: ApiFee = decode(response)?;
+        Ok(Fee {
+            fee_type: output_fee_type(tx_type),
+            // v0.2 does not report the gas estimation the fee was derived from.
+            gas_tx_amount: BigUint::default(),
+            gas_price_wei: BigUint::default(),
+            gas_fee: fee.gas_fee,
+            zkp_fee: fee.zkp_fee,
+            total_fee: fee.total_fee,
+        })
+    }
+
+    fn parse_txs_batch_fee(&self, response: Value) -> ResponseResult<BigUint> {
+        decode::<ApiFee>(response).map(|fee| fee.total_fee)
+    }
+
+    fn parse_eth_tx_for_withdrawal(&self, response: Value) -> ResponseResult<Option<String>> {
+        let data: Option<ApiTxData> = decode(response)?;
+        Ok(data.and_then(|data| data.tx.eth_tx_hash))
+    }
+
+    fn parse_contract_address(&self, response: Value) -> ResponseResult<ContractAddress> {
+        let config: ApiConfig = decode(response)?;
+        Ok(ContractAddress {
+            main_contract: format!("{:?}", config.contract),
+            gov_contract: format!("{:?}", config.gov_contract),
+        })
+    }
+
+    fn parse_tx_hash(&self, response: Value) -> ResponseResult<TxHash> {
+        decode(response)
+    }
+
+    fn parse_tx_hashes(&self, response: Value) -> ResponseResult<Vec<TxHash>> {
+        decode::<ApiSubmitBatchResponse>(response).map(|response| response.transaction_hashes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rollup::types::pagination::TxHistoryCursor;
+    use crate::rollup::types::{BlockNumber, PaginationDirection, PaginationQuery};
+
+    /// Tests that v0.2 receipts are translated into the internal `TransactionInfo`.
+    #[test]
+    fn test_tx_receipt_translation() {
+        let finalized = json!({ "status": "finalized", "failReason": null, "rollupBlock": 12 });
+        let info = RestAdapter.parse_tx_info(finalized).unwrap();
+        assert!(info.is_verified());
+        assert_eq!(info.success, Some(true));
+
+        let rejected =
+            json!({ "status": "rejected", "failReason": "Nonce mismatch", "rollupBlock": null });
+        let info = RestAdapter.parse_tx_info(rejected).unwrap();
+        assert!(info.executed);
+        assert_eq!(info.success, Some(false));
+        assert_eq!(info.fail_reason.as_deref(), Some("Nonce mismatch"));
+
+        let unknown = RestAdapter.parse_tx_info(Value::Null).unwrap();
+        assert!(!unknown.executed);
+    }
+
+    /// Tests that pages of the account history are requested and decoded.
+    #[test]
+    fn test_account_txs_page() {
+        let tx_hash = TxHash { data: [7; 32] };
+        let call = ApiCall::AccountTxs {
+            address: Address::repeat_byte(1),
+            query: PaginationQuery {
+                from: TxHistoryCursor::Tx(tx_hash),
+                limit: 500,
+                direction: PaginationDirection::Older,
+            },
+        };
+        let expected_path = format!(
+            "/api/v0.2/accounts/{:?}/transactions?from={}&limit=100&direction=older",
+            Address::repeat_byte(1),
+            tx_hash
+        );
+        assert_eq!(
+            RestAdapter.request(&call).unwrap(),
+            ApiRequest::Rest {
+                method: HttpMethod::Get,
+                path: expected_path,
+                body: None
+            }
+        );
+
+        let page = json!({
+            "pagination": { "from": "latest", "limit": 100, "direction": "older", "count": 1 },
+            "list": [{
+                "txHash": tx_hash.to_string(),
+                "blockIndex": 0,
+                "blockNumber": 12,
+                "op": { "type": "Transfer" },
+                "status": "committed",
+                "failReason": null,
+                "createdAt": "2024-01-01T00:00:00Z"
+            }]
+        });
+        let page = RestAdapter.parse_account_txs(page).unwrap();
+        assert_eq!(page.pagination.from, TxHistoryCursor::Latest);
+        assert_eq!(page.list[0].tx_hash, tx_hash);
+        assert_eq!(page.list[0].block_number, Some(BlockNumber(12)));
+    }
+
+    /// Tests that error envelopes are surfaced as client errors.
+    #[test]
+    fn test_unwrap_envelope() {
+        let ok = json!({ "request": {}, "status": "success", "result": 5, "error": null });
+        assert_eq!(unwrap_envelope(ok).unwrap(), json!(5));
+
+        let err = json!({ "request": {}, "status": "error", "result": null, "error": { "message": "boom" } });
+        assert_eq!(
+            unwrap_envelope(err),
+            Err(ClientError::NetworkError("boom".to_string()))
+        );
+    }
+}
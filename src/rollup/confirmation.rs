@@ -0,0 +1,1143 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use ethers::providers::Middleware;
+use ethers::types::H256;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::events::{StatusEvent, StatusFeed};
+use super::pending_store::{PendingStore, PendingStoreError, StoredOp};
+use super::provider::{Provider, ResponseResult};
+use super::types::{EthOpInfo, TransactionInfo, TxHash};
+use crate::checkpoint::PendingCheckpoint;
+use crate::engine::{Submission, SubmissionObserver};
+use crate::metrics::prometheus::CONFIRMATION_LATENCY_METRIC;
+use crate::metrics::Metrics;
+use crate::paths;
+use crate::report::withdrawals::WithdrawalStage;
+use crate::report::{RunRecorder, TxStatus};
+use crate::scenario::reconnect_storm::ConfirmationAudit;
+
+/// Counter of operations given up on before they were verified, labelled by type.
+pub const CONFIRMATION_TIMEOUTS_METRIC: &str = "tx_confirmation_timeouts_total";
+/// Counter of operations that reached a stage later than their objective, labelled by type and stage.
+pub const CONFIRMATION_SLO_BREACHES_METRIC: &str = "tx_confirmation_slo_breaches_total";
+/// Type label of operations carried over from a checkpoint; their latencies are measured
+/// from the resume and would skew the per-type statistics.
+pub const RESUMED_TX_TYPE: &str = "resumed";
+
+/// Polling of submitted operations, configured in the `[network.confirmation]` section.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConfirmationConfig {
+    /// How often the status of every pending operation is queried, in milliseconds.
+    #[serde(default = "ConfirmationConfig::default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    /// Time after submission at which an operation that is still not verified is given up on.
+    #[serde(default = "ConfirmationConfig::default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Objective for the time until an operation is committed, none when missing.
+    #[serde(default)]
+    pub commit_slo_ms: Option<u64>,
+    /// Objective for the time until an operation is verified, none when missing.
+    #[serde(default)]
+    pub verify_slo_ms: Option<u64>,
+    /// Overrides per operation type, e.g. `[network.confirmation.sla.withdraw]`.
+    #[serde(default)]
+    pub sla: HashMap<String, ConfirmationSla>,
+    /// File the unconfirmed operations are kept in, so a restarted run keeps tracking them;
+    /// in memory only when not set.
+    #[serde(default)]
+    pub store_file: Option<PathBuf>,
+    /// WebSocket endpoint of the JSON-RPC API; operations are followed through its
+    /// notifications instead of polled when set, see [`super::events`].
+    #[serde(default)]
+    pub events_url: Option<String>,
+}
+
+/// Timeout and objectives of one operation type; missing values fall back to the
+/// `[network.confirmation]` ones.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ConfirmationSla {
+    pub timeout_secs: Option<u64>,
+    pub commit_slo_ms: Option<u64>,
+    pub verify_slo_ms: Option<u64>,
+}
+
+impl ConfirmationConfig {
+    fn default_poll_interval_ms() -> u64 {
+        1_000
+    }
+
+    fn default_timeout_secs() -> u64 {
+        1_800
+    }
+
+    /// Time after which a still unverified operation of the type is given up on.
+    pub fn timeout_for(&self, tx_type: &str) -> Duration {
+        let timeout_secs = self.sla.get(tx_type).and_then(|sla| sla.timeout_secs);
+        Duration::from_secs(timeout_secs.unwrap_or(self.timeout_secs))
+    }
+
+    /// Objective for operations of the type reaching the `Committed` or `Verified` stage.
+    pub fn slo_for(&self, tx_type: &str, status: TxStatus) -> Option<Duration> {
+        let sla = self.sla.get(tx_type);
+        let slo_ms = match status {
+            TxStatus::Committed => sla.and_then(|sla| sla.commit_slo_ms).or(self.commit_slo_ms),
+            TxStatus::Verified => sla.and_then(|sla| sla.verify_slo_ms).or(self.verify_slo_ms),
+            TxStatus::Submitted | TxStatus::Rejected => None,
+        };
+        slo_ms.map(Duration::from_millis)
+    }
+}
+
+impl Default for ConfirmationConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_ms: Self::default_poll_interval_ms(),
+            timeout_secs: Self::default_timeout_secs(),
+            commit_slo_ms: None,
+            verify_slo_ms: None,
+            sla: HashMap::new(),
+            store_file: None,
+            events_url: None,
+        }
+    }
+}
+
+/// Status queries the tracker needs, implemented by every `Provider`.
+#[async_trait]
+pub trait ConfirmationSource: Send + Sync {
+    async fn tx_info(&self, tx_hash: TxHash) -> ResponseResult<TransactionInfo>;
+
+    async fn ethop_info(&self, serial_id: u32) -> ResponseResult<EthOpInfo>;
+
+    async fn eth_tx_for_withdrawal(&self, tx_hash: TxHash) -> ResponseResult<Option<String>>;
+}
+
+#[async_trait]
+impl<P: Provider + Send + Sync> ConfirmationSource for P {
+    async fn tx_info(&self, tx_hash: TxHash) -> ResponseResult<TransactionInfo> {
+        Provider::tx_info(self, tx_hash).await
+    }
+
+    async fn ethop_info(&self, serial_id: u32) -> ResponseResult<EthOpInfo> {
+        Provider::ethop_info(self, serial_id).await
+    }
+
+    async fn eth_tx_for_withdrawal(&self, tx_hash: TxHash) -> ResponseResult<Option<String>> {
+        Provider::get_eth_tx_for_withdrawal(self, tx_hash).await
+    }
+}
+
+/// Receipt lookups on Rootstock, implemented by every ethers `Middleware`.
+#[async_trait]
+pub trait L1Receipts: Send + Sync {
+    /// Whether the transaction succeeded, `None` while it is not mined.
+    async fn receipt_status(&self, tx_hash: H256) -> Result<Option<bool>, String>;
+}
+
+#[async_trait]
+impl<M: Middleware> L1Receipts for M {
+    async fn receipt_status(&self, tx_hash: H256) -> Result<Option<bool>, String> {
+        let receipt = self
+            .get_transaction_receipt(tx_hash)
+            .await
+            .map_err(|err| err.to_string())?;
+        Ok(receipt.map(|receipt| receipt.status.is_none_or(|status| status.as_u64() == 1)))
+    }
+}
+
+/// Told of every stage a tracked transaction reaches, such as the pipeline whose local view
+/// of the accounts waits for confirmations.
+#[async_trait]
+pub trait ConfirmationListener: Send + Sync {
+    async fn reached(&self, tx_hash: TxHash, status: TxStatus);
+}
+
+/// Operation whose progress is tracked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TrackedOp {
+    /// L2 transaction accepted by `send_tx`.
+    Tx(TxHash),
+    /// Priority operation such as a deposit, identified by its serial id.
+    PriorityOp(u32),
+    /// L2 withdrawal, followed past verification until its Rootstock transaction.
+    Withdrawal(TxHash),
+}
+
+impl TrackedOp {
+    fn tx_hash(&self) -> Option<&TxHash> {
+        match self {
+            TrackedOp::Tx(tx_hash) | TrackedOp::Withdrawal(tx_hash) => Some(tx_hash),
+            TrackedOp::PriorityOp(_) => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PendingOp {
+    op: TrackedOp,
+    tx_type: &'static str,
+    submitted: Instant,
+    committed: bool,
+    /// Only withdrawals stay pending once verified, waiting for their Rootstock transaction.
+    verified: bool,
+    eth_tx_hash: Option<String>,
+}
+
+/// Stage an operation reached according to the server.
+enum Progress {
+    Pending,
+    Committed,
+    Verified,
+    Rejected(String),
+}
+
+impl Progress {
+    fn of_tx(info: &TransactionInfo) -> Self {
+        if info.executed && info.success == Some(false) {
+            return Progress::Rejected(
+                info.fail_reason
+                    .clone()
+                    .unwrap_or_else(|| "unknown".to_string()),
+            );
+        }
+        Self::of_block(info.executed, info.is_verified())
+    }
+
+    fn of_ethop(info: &EthOpInfo) -> Self {
+        Self::of_block(info.executed, info.is_verified())
+    }
+
+    fn of_block(executed: bool, verified: bool) -> Self {
+        match (executed, verified) {
+            (_, true) => Progress::Verified,
+            (true, false) => Progress::Committed,
+            (false, false) => Progress::Pending,
+        }
+    }
+}
+
+/// Follows submitted operations until they are verified, rejected or time out.
+///
+/// `send_tx` only tells that the server accepted a transaction. The tracker polls
+/// `tx_info` (`ethop_info` for priority operations) and reports every stage reached to
+/// the run recorder, so records move from submitted to committed and verified and the
+/// report shows how long each stage took.
+///
+/// With a [`StatusFeed`] the stages of the operations it covers arrive as notifications
+/// instead, and only the others are polled.
+///
+/// Withdrawals are followed further: `get_eth_tx_for_withdrawal` is polled until the
+/// server sent the Rootstock transaction and, when L1 receipts are available, until that
+/// transaction was mined.
+pub struct ConfirmationTracker<S> {
+    source: Arc<S>,
+    config: ConfirmationConfig,
+    recorder: Arc<RunRecorder>,
+    metrics: Arc<Metrics>,
+    l1: Option<Arc<dyn L1Receipts>>,
+    pending: Mutex<Vec<PendingOp>>,
+    store: Option<Mutex<PendingStore>>,
+    events: Option<Arc<dyn StatusFeed>>,
+    audit: Option<Arc<ConfirmationAudit>>,
+    listeners: Vec<Arc<dyn ConfirmationListener>>,
+}
+
+/// Latest notified status per operation.
+#[derive(Default)]
+struct Notified {
+    txs: HashMap<TxHash, TransactionInfo>,
+    ethops: HashMap<u32, EthOpInfo>,
+}
+
+impl Notified {
+    /// Keeps the most advanced status, notifications of one operation may arrive in any order.
+    fn collect(events: Vec<StatusEvent>) -> Self {
+        let mut notified = Self::default();
+        for event in events {
+            match event {
+                StatusEvent::Tx(tx_hash, info) => {
+                    let known = notified.txs.get(&tx_hash);
+                    if !known.is_some_and(|known| known.is_verified() || !info.executed) {
+                        notified.txs.insert(tx_hash, info);
+                    }
+                }
+                StatusEvent::PriorityOp(serial_id, info) => {
+                    let known = notified.ethops.get(&serial_id);
+                    if !known.is_some_and(|known| known.is_verified() || !info.executed) {
+                        notified.ethops.insert(serial_id, info);
+                    }
+                }
+            }
+        }
+        notified
+    }
+}
+
+impl<S: ConfirmationSource> ConfirmationTracker<S> {
+    pub fn new(
+        source: Arc<S>,
+        config: ConfirmationConfig,
+        recorder: Arc<RunRecorder>,
+        metrics: Arc<Metrics>,
+    ) -> Self {
+        Self {
+            source,
+            config,
+            recorder,
+            metrics,
+            l1: None,
+            pending: Mutex::new(Vec::new()),
+            store: None,
+            events: None,
+            audit: None,
+            listeners: Vec::new(),
+        }
+    }
+
+    /// Tracker of the `[network.confirmation]` section, keeping the pending operations in its
+    /// `store_file` when one is set.
+    pub fn from_config(
+        source: Arc<S>,
+        config: ConfirmationConfig,
+        recorder: Arc<RunRecorder>,
+        metrics: Arc<Metrics>,
+    ) -> Result<Self, PendingStoreError> {
+        let store = match &config.store_file {
+            Some(path) => Some(PendingStore::open(paths::expand_home(path))?),
+            None => None,
+        };
+        let tracker = Self::new(source, config, recorder, metrics);
+        Ok(match store {
+            Some(store) => tracker.with_store(store),
+            None => tracker,
+        })
+    }
+
+    /// Waits for the Rootstock transactions of withdrawals to be mined as well.
+    pub fn with_l1_receipts(mut self, l1: Arc<dyn L1Receipts>) -> Self {
+        self.l1 = Some(l1);
+        self
+    }
+
+    /// Keeps the pending operations in `store` as well, see [`Self::resume_stored`].
+    pub fn with_store(mut self, store: PendingStore) -> Self {
+        self.store = Some(Mutex::new(store));
+        self
+    }
+
+    /// Follows the operations covered by `events` through its notifications instead of polling.
+    pub fn with_events(mut self, events: Arc<dyn StatusFeed>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Reports every confirmation of a transaction to `audit`.
+    pub fn with_audit(mut self, audit: Arc<ConfirmationAudit>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Tells `listener` of every stage a tracked transaction reaches, rejections included.
+    pub fn with_listener(mut self, listener: Arc<dyn ConfirmationListener>) -> Self {
+        self.listeners.push(listener);
+        self
+    }
+
+    /// Starts tracking an operation submitted at `submitted`.
+    pub fn track(&self, op: TrackedOp, tx_type: &'static str, submitted: Instant) {
+        if let Some(store) = &self.store {
+            let stored = StoredOp::new(op, tx_type, submitted);
+            if let Err(err) = store.lock().unwrap().track(stored) {
+                warn!(?op, error = %err, "unable to store pending operation");
+            }
+        }
+        self.push(op, tx_type, submitted);
+    }
+
+    fn push(&self, op: TrackedOp, tx_type: &'static str, submitted: Instant) {
+        if let Some(events) = &self.events {
+            events.subscribe(&op);
+        }
+        self.pending.lock().unwrap().push(PendingOp {
+            op,
+            tx_type,
+            submitted,
+            committed: false,
+            verified: false,
+            eth_tx_hash: None,
+        });
+    }
+
+    /// Number of operations not yet verified, rejected or given up on.
+    pub fn pending(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    /// Hashes of the pending transactions, priority operations have none.
+    pub fn pending_tx_hashes(&self) -> Vec<TxHash> {
+        let pending = self.pending.lock().unwrap();
+        pending
+            .iter()
+            .filter_map(|op| op.op.tx_hash())
+            .copied()
+            .collect()
+    }
+
+    /// Queries the status of every pending operation once.
+    ///
+    /// Operations whose status can not be queried stay pending until they time out. Covered
+    /// operations are not queried, their status is the latest notification if any.
+    pub async fn poll_once(&self) {
+        let notified = Notified::collect(
+            self.events
+                .as_ref()
+                .map(|events| events.take_events())
+                .unwrap_or_default(),
+        );
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        let polled: Vec<TrackedOp> = pending.iter().map(|op| op.op).collect();
+        let mut still_pending = Vec::with_capacity(pending.len());
+
+        for mut op in pending {
+            if op.verified {
+                if !self.poll_l1(&mut op).await {
+                    self.keep_or_give_up(op, &mut still_pending);
+                }
+                continue;
+            }
+            let covered = self
+                .events
+                .as_ref()
+                .is_some_and(|events| events.covers(&op.op));
+            let progress = match op.op {
+                TrackedOp::Tx(tx_hash) | TrackedOp::Withdrawal(tx_hash) => {
+                    match notified.txs.get(&tx_hash) {
+                        Some(info) => Ok(Progress::of_tx(info)),
+                        None if covered => Ok(Progress::Pending),
+                        None => self
+                            .source
+                            .tx_info(tx_hash)
+                            .await
+                            .map(|info| Progress::of_tx(&info)),
+                    }
+                }
+                TrackedOp::PriorityOp(serial_id) => match notified.ethops.get(&serial_id) {
+                    Some(info) => Ok(Progress::of_ethop(info)),
+                    None if covered => Ok(Progress::Pending),
+                    None => self
+                        .source
+                        .ethop_info(serial_id)
+                        .await
+                        .map(|info| Progress::of_ethop(&info)),
+                },
+            };
+            let latency = op.submitted.elapsed();
+            let tx_hash = op.op.tx_hash();
+
+            match progress.unwrap_or(Progress::Pending) {
+                Progress::Rejected(reason) => {
+                    if let Some(tx_hash) = tx_hash {
+                        self.recorder
+                            .update_status(tx_hash, TxStatus::Rejected, Some(reason));
+                    }
+                    self.notify(&op, TxStatus::Rejected).await;
+                    continue;
+                }
+                Progress::Verified => {
+                    // Both stages were reached since the previous poll.
+                    if !op.committed {
+                        self.record(&op, TxStatus::Committed, latency);
+                        self.notify(&op, TxStatus::Committed).await;
+                    }
+                    self.record(&op, TxStatus::Verified, latency);
+                    self.notify(&op, TxStatus::Verified).await;
+                    if !matches!(op.op, TrackedOp::Withdrawal(_)) || self.poll_l1(&mut op).await {
+                        continue;
+                    }
+                    op.committed = true;
+                    op.verified = true;
+                }
+                Progress::Committed if !op.committed => {
+                    op.committed = true;
+                    self.record(&op, TxStatus::Committed, latency);
+                    self.notify(&op, TxStatus::Committed).await;
+                }
+                Progress::Committed | Progress::Pending => {}
+            }
+            self.keep_or_give_up(op, &mut still_pending);
+        }
+
+        self.forget_finished(&polled, &still_pending);
+        self.pending.lock().unwrap().extend(still_pending);
+    }
+
+    /// Removes the polled operations that are no longer pending from the store and drops
+    /// their notifications.
+    fn forget_finished(&self, polled: &[TrackedOp], still_pending: &[PendingOp]) {
+        let kept: HashSet<TrackedOp> = still_pending.iter().map(|op| op.op).collect();
+        let finished = polled.iter().filter(|op| !kept.contains(op));
+        let mut store = self.store.as_ref().map(|store| store.lock().unwrap());
+        for op in finished {
+            if let Some(events) = &self.events {
+                events.forget(op);
+            }
+            if let Some(Err(err)) = store.as_mut().map(|store| store.complete(op)) {
+                warn!(?op, error = %err, "unable to remove finished operation from the store");
+            }
+        }
+    }
+
+    fn keep_or_give_up(&self, op: PendingOp, still_pending: &mut Vec<PendingOp>) {
+        if op.submitted.elapsed() >= self.config.timeout_for(op.tx_type) {
+            self.metrics
+                .increment(CONFIRMATION_TIMEOUTS_METRIC, &[("type", op.tx_type)]);
+            self.recorder.record_confirmation_timeout(op.tx_type);
+            return;
+        }
+        still_pending.push(op);
+    }
+
+    /// Follows a verified withdrawal on Rootstock, returning whether it reached its last stage.
+    async fn poll_l1(&self, op: &mut PendingOp) -> bool {
+        let TrackedOp::Withdrawal(tx_hash) = op.op else {
+            return true;
+        };
+        if op.eth_tx_hash.is_none() {
+            let Ok(Some(eth_tx_hash)) = self.source.eth_tx_for_withdrawal(tx_hash).await else {
+                return false;
+            };
+            let latency = op.submitted.elapsed();
+            self.observe(op.tx_type, WithdrawalStage::L1Sent.name(), latency);
+            self.recorder.with_withdrawals(|withdrawals| {
+                withdrawals.record_eth_tx(tx_hash, op.tx_type, eth_tx_hash.clone(), latency)
+            });
+            op.eth_tx_hash = Some(eth_tx_hash);
+        }
+
+        let Some(l1) = &self.l1 else {
+            return true;
+        };
+        let eth_tx_hash = op.eth_tx_hash.as_deref().unwrap_or_default();
+        let Ok(l1_hash) = H256::from_str(eth_tx_hash) else {
+            warn!(%tx_hash, eth_tx_hash, "withdrawal sent in a malformed Rootstock transaction");
+            return true;
+        };
+        let Ok(Some(success)) = l1.receipt_status(l1_hash).await else {
+            return false;
+        };
+        if !success {
+            warn!(%tx_hash, eth_tx_hash, "Rootstock transaction of a withdrawal reverted");
+        }
+        let latency = op.submitted.elapsed();
+        self.observe(op.tx_type, WithdrawalStage::L1Confirmed.name(), latency);
+        self.recorder.with_withdrawals(|withdrawals| {
+            withdrawals.record_receipt(tx_hash, op.tx_type, success, latency)
+        });
+        true
+    }
+
+    fn observe(&self, tx_type: &'static str, stage: &'static str, latency: Duration) {
+        self.metrics.observe(
+            CONFIRMATION_LATENCY_METRIC,
+            &[("type", tx_type), ("stage", stage)],
+            latency,
+        );
+    }
+
+    fn record(&self, op: &PendingOp, status: TxStatus, latency: Duration) {
+        let tx_type = op.tx_type;
+        let tx_hash = op.op.tx_hash();
+        let stage = match status {
+            TxStatus::Committed => WithdrawalStage::Committed,
+            _ => WithdrawalStage::Verified,
+        };
+        if let TrackedOp::Withdrawal(tx_hash) = op.op {
+            self.recorder.with_withdrawals(|withdrawals| {
+                withdrawals.record_stage(tx_hash, tx_type, stage, latency)
+            });
+        }
+        if let (Some(audit), Some(tx_hash)) = (&self.audit, tx_hash) {
+            audit.received(*tx_hash);
+        }
+        let stage = stage.name();
+        self.observe(tx_type, stage, latency);
+        self.recorder
+            .record_confirmation(tx_type, tx_hash, status, latency);
+        if let Some(slo) = self.config.slo_for(tx_type, status) {
+            if latency > slo {
+                self.metrics.increment(
+                    CONFIRMATION_SLO_BREACHES_METRIC,
+                    &[("type", tx_type), ("stage", stage)],
+                );
+                self.recorder.record_slo_breach(tx_type, status);
+            }
+        }
+    }
+
+    async fn notify(&self, op: &PendingOp, status: TxStatus) {
+        if let Some(tx_hash) = op.op.tx_hash() {
+            for listener in &self.listeners {
+                listener.reached(*tx_hash, status).await;
+            }
+        }
+    }
+
+    /// Polls at the configured interval until no operation is pending, right away when a
+    /// notification arrives.
+    pub async fn run_until_settled(&self) {
+        while self.pending() > 0 {
+            self.poll_once().await;
+            if self.pending() == 0 {
+                break;
+            }
+            self.next_poll().await;
+        }
+    }
+
+    /// Polls like [`Self::run_until_settled`] but never returns, following the operations
+    /// while the run is still submitting them.
+    pub async fn run(&self) {
+        loop {
+            self.poll_once().await;
+            self.next_poll().await;
+        }
+    }
+
+    async fn next_poll(&self) {
+        let interval = Duration::from_millis(self.config.poll_interval_ms);
+        match &self.events {
+            Some(events) => {
+                tokio::select! {
+                    _ = events.arrived() => {}
+                    _ = tokio::time::sleep(interval) => {}
+                }
+            }
+            None => tokio::time::sleep(interval).await,
+        }
+    }
+
+    /// Operations still pending, to be written into a checkpoint.
+    pub fn checkpoint(&self) -> Vec<PendingCheckpoint> {
+        self.pending
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|op| PendingCheckpoint {
+                op: op.op,
+                tx_type: op.tx_type.to_string(),
+                pending_ms: op.submitted.elapsed().as_millis() as u64,
+            })
+            .collect()
+    }
+
+    /// Resumes tracking the operations of a checkpoint under the `resumed` type.
+    pub fn restore(&self, pending: &[PendingCheckpoint]) {
+        let now = Instant::now();
+        for op in pending {
+            self.track(op.op, RESUMED_TX_TYPE, now);
+        }
+    }
+
+    /// Resumes tracking the operations a previous run left in the store under the `resumed`
+    /// type, returning how many there were.
+    ///
+    /// Unlike a checkpoint the store is written as the run goes, so it also covers runs that
+    /// crashed. Timeouts keep counting from the original submission.
+    pub fn resume_stored(&self) -> usize {
+        let Some(store) = &self.store else {
+            return 0;
+        };
+        let stored = store.lock().unwrap().pending();
+        let now = Instant::now();
+        for op in &stored {
+            let submitted = now.checked_sub(op.age()).unwrap_or(now);
+            self.push(op.op, RESUMED_TX_TYPE, submitted);
+        }
+        stored.len()
+    }
+}
+
+impl<S: ConfirmationSource> SubmissionObserver for ConfirmationTracker<S> {
+    fn observe(&self, submission: &Submission) {
+        if let Some(op) = submission.op {
+            self.track(op, submission.tx_type, submission.started);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::metrics::MetricKey;
+    use crate::report::latency::TxTiming;
+    use crate::rollup::provider::ClientError;
+    use crate::rollup::types::BlockInfo;
+
+    /// Server answering with a scripted sequence of states per operation.
+    #[derive(Default)]
+    struct ScriptedSource {
+        txs: Mutex<HashMap<TxHash, Vec<TransactionInfo>>>,
+        ethops: Mutex<HashMap<u32, Vec<EthOpInfo>>>,
+        withdrawals: Mutex<HashMap<TxHash, Vec<Option<String>>>>,
+    }
+
+    fn next_state<T: Clone>(states: &mut Vec<T>) -> T {
+        if states.len() > 1 {
+            states.remove(0)
+        } else {
+            states[0].clone()
+        }
+    }
+
+    fn block(verified: bool) -> Option<BlockInfo> {
+        Some(BlockInfo {
+            block_number: 1,
+            committed: true,
+            verified,
+        })
+    }
+
+    #[async_trait]
+    impl ConfirmationSource for ScriptedSource {
+        async fn tx_info(&self, tx_hash: TxHash) -> ResponseResult<TransactionInfo> {
+            let mut txs = self.txs.lock().unwrap();
+            let states = txs.get_mut(&tx_hash).ok_or(ClientError::IncorrectInput)?;
+            Ok(next_state(states))
+        }
+
+        async fn ethop_info(&self, serial_id: u32) -> ResponseResult<EthOpInfo> {
+            let mut ethops = self.ethops.lock().unwrap();
+            let states = ethops
+                .get_mut(&serial_id)
+                .ok_or(ClientError::IncorrectInput)?;
+            Ok(next_state(states))
+        }
+
+        async fn eth_tx_for_withdrawal(&self, tx_hash: TxHash) -> ResponseResult<Option<String>> {
+            let mut withdrawals = self.withdrawals.lock().unwrap();
+            let states = withdrawals
+                .get_mut(&tx_hash)
+                .ok_or(ClientError::IncorrectInput)?;
+            Ok(next_state(states))
+        }
+    }
+
+    /// Rootstock node mining every transaction on the second receipt lookup, reverting `reverted`.
+    struct ScriptedL1 {
+        reverted: H256,
+        lookups: Mutex<HashMap<H256, usize>>,
+    }
+
+    #[async_trait]
+    impl L1Receipts for ScriptedL1 {
+        async fn receipt_status(&self, tx_hash: H256) -> Result<Option<bool>, String> {
+            let mut lookups = self.lookups.lock().unwrap();
+            let lookup = lookups.entry(tx_hash).or_default();
+            *lookup += 1;
+            Ok((*lookup > 1).then_some(tx_hash != self.reverted))
+        }
+    }
+
+    /// Tests that records follow committed and verified blocks, rejections and timeouts.
+    #[tokio::test]
+    async fn test_confirmation_tracking() {
+        let tx_info = |executed, success, verified| TransactionInfo {
+            executed,
+            success,
+            fail_reason: (success == Some(false)).then(|| "Nonce mismatch".to_string()),
+            block: executed.then(|| block(verified)).flatten(),
+        };
+        let verified_hash = TxHash { data: [1; 32] };
+        let rejected_hash = TxHash { data: [2; 32] };
+        let unknown_hash = TxHash { data: [3; 32] };
+        let source = ScriptedSource::default();
+        source.txs.lock().unwrap().extend([
+            (
+                verified_hash,
+                vec![
+                    tx_info(false, None, false),
+                    tx_info(true, Some(true), false),
+                    tx_info(true, Some(true), true),
+                ],
+            ),
+            (rejected_hash, vec![tx_info(true, Some(false), false)]),
+        ]);
+        source.ethops.lock().unwrap().insert(
+            7,
+            vec![EthOpInfo {
+                executed: true,
+                block: block(true),
+            }],
+        );
+
+        let recorder = Arc::new(RunRecorder::new());
+        let metrics = Arc::new(Metrics::new());
+        let now = Instant::now();
+        let timing = TxTiming {
+            intended_start: now,
+            actual_start: now,
+            completed: now,
+        };
+        for tx_hash in [verified_hash, rejected_hash, unknown_hash] {
+            recorder.record_tx(
+                "transfer",
+                Some(tx_hash),
+                None,
+                &timing,
+                TxStatus::Submitted,
+            );
+        }
+
+        // Transfers are given up on right away, deposits miss their verify objective.
+        let sla = |timeout_secs, verify_slo_ms| ConfirmationSla {
+            timeout_secs,
+            verify_slo_ms,
+            ..Default::default()
+        };
+        let config = ConfirmationConfig {
+            poll_interval_ms: 1,
+            timeout_secs: 60,
+            sla: HashMap::from([
+                ("transfer".to_string(), sla(Some(0), None)),
+                ("deposit".to_string(), sla(None, Some(0))),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(config.timeout_for("withdraw"), Duration::from_secs(60));
+        assert_eq!(config.slo_for("transfer", TxStatus::Verified), None);
+        let mut patient = config.clone();
+        patient.sla.remove("transfer");
+        let tracker =
+            ConfirmationTracker::new(Arc::new(source), patient, recorder.clone(), metrics.clone());
+        for tx_hash in [verified_hash, rejected_hash] {
+            tracker.track(TrackedOp::Tx(tx_hash), "transfer", now);
+        }
+        tracker.track(TrackedOp::PriorityOp(7), "deposit", now);
+        tracker.run_until_settled().await;
+
+        let gives_up = ConfirmationTracker::new(
+            Arc::new(ScriptedSource::default()),
+            config,
+            recorder.clone(),
+            metrics.clone(),
+        );
+        gives_up.track(TrackedOp::Tx(unknown_hash), "transfer", now);
+        gives_up.poll_once().await;
+        assert_eq!(gives_up.pending(), 0);
+        assert_eq!(
+            metrics.counter(CONFIRMATION_TIMEOUTS_METRIC, &[("type", "transfer")]),
+            1
+        );
+
+        let snapshot = recorder.snapshot(&metrics, Vec::new());
+        let statuses: Vec<TxStatus> = snapshot.records.iter().map(|r| r.status).collect();
+        assert_eq!(
+            statuses,
+            vec![TxStatus::Verified, TxStatus::Rejected, TxStatus::Submitted]
+        );
+        let verified = &snapshot.records[0];
+        assert!(verified.commit_latency_ms.is_some());
+        assert!(verified.verify_latency_ms >= verified.commit_latency_ms);
+        assert_eq!(
+            snapshot.records[1].fail_reason.as_deref(),
+            Some("Nonce mismatch")
+        );
+
+        let rows = &snapshot.confirmation_latency;
+        assert_eq!(
+            rows.iter()
+                .map(|row| (row.tx_type.as_str(), row.committed, row.verified))
+                .collect::<Vec<_>>(),
+            vec![("deposit", 1, 1), ("transfer", 1, 1)]
+        );
+        assert_eq!(
+            rows.iter()
+                .map(|row| (row.timed_out, row.verify_slo_breaches))
+                .collect::<Vec<_>>(),
+            vec![(0, 1), (1, 0)]
+        );
+        assert_eq!(
+            metrics.counter(
+                CONFIRMATION_SLO_BREACHES_METRIC,
+                &[("type", "deposit"), ("stage", "verified")]
+            ),
+            1
+        );
+    }
+
+    /// Tests that withdrawals are followed past verification until their Rootstock receipt.
+    #[tokio::test]
+    async fn test_withdrawal_lifecycle() {
+        let verified = TransactionInfo {
+            executed: true,
+            success: Some(true),
+            fail_reason: None,
+            block: block(true),
+        };
+        let paid_out = TxHash { data: [1; 32] };
+        let reverted = TxHash { data: [2; 32] };
+        let eth_tx = |byte: u8| format!("{:?}", H256::repeat_byte(byte));
+        let source = ScriptedSource::default();
+        source.txs.lock().unwrap().extend([
+            (paid_out, vec![verified.clone()]),
+            (reverted, vec![verified]),
+        ]);
+        source.withdrawals.lock().unwrap().extend([
+            (paid_out, vec![None, Some(eth_tx(0xaa))]),
+            (reverted, vec![Some(eth_tx(0xbb))]),
+        ]);
+
+        let recorder = Arc::new(RunRecorder::new());
+        let metrics = Arc::new(Metrics::new());
+        let config = ConfirmationConfig {
+            poll_interval_ms: 1,
+            ..Default::default()
+        };
+        let l1 = ScriptedL1 {
+            reverted: H256::repeat_byte(0xbb),
+            lookups: Mutex::new(HashMap::new()),
+        };
+        let tracker =
+            ConfirmationTracker::new(Arc::new(source), config, recorder.clone(), metrics.clone())
+                .with_l1_receipts(Arc::new(l1));
+        let now = Instant::now();
+        for tx_hash in [paid_out, reverted] {
+            tracker.track(TrackedOp::Withdrawal(tx_hash), "withdraw", now);
+        }
+        tracker.poll_once().await;
+        assert_eq!(tracker.pending(), 2);
+        tracker.run_until_settled().await;
+
+        let summary = recorder.with_withdrawals(|withdrawals| withdrawals.summary());
+        assert_eq!(
+            summary
+                .rows
+                .iter()
+                .map(|row| (row.stage, row.reached))
+                .collect::<Vec<_>>(),
+            vec![
+                (WithdrawalStage::Committed, 2),
+                (WithdrawalStage::Verified, 2),
+                (WithdrawalStage::L1Sent, 2),
+                (WithdrawalStage::L1Confirmed, 2),
+            ]
+        );
+        assert_eq!(summary.l1_failed, 1);
+        assert_eq!(summary.withdrawals[0].eth_tx_hash, Some(eth_tx(0xaa)));
+        let stages = &summary.withdrawals[0].stages;
+        assert!(stages[&WithdrawalStage::L1Confirmed] >= stages[&WithdrawalStage::Verified]);
+    }
+
+    /// Tests that a restarted tracker resumes the operations its predecessor left in the store.
+    #[tokio::test]
+    async fn test_stored_pending_resume() {
+        let path =
+            std::env::temp_dir().join(format!("confirmation-store-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let tx_info = |executed, verified| TransactionInfo {
+            executed,
+            success: executed.then_some(true),
+            fail_reason: None,
+            block: executed.then(|| block(verified)).flatten(),
+        };
+        let (verified_hash, pending_hash) = (TxHash { data: [1; 32] }, TxHash { data: [2; 32] });
+        let source = Arc::new(ScriptedSource::default());
+        source.txs.lock().unwrap().extend([
+            (verified_hash, vec![tx_info(true, true)]),
+            (pending_hash, vec![tx_info(false, false)]),
+        ]);
+        let tracker = |store| {
+            ConfirmationTracker::new(
+                source.clone(),
+                ConfirmationConfig::default(),
+                Arc::new(RunRecorder::new()),
+                Arc::new(Metrics::new()),
+            )
+            .with_store(store)
+        };
+
+        let crashed = tracker(PendingStore::open(&path).unwrap());
+        let submitted = Instant::now() - Duration::from_secs(30);
+        crashed.track(TrackedOp::Tx(verified_hash), "transfer", submitted);
+        crashed.track(TrackedOp::Tx(pending_hash), "transfer", submitted);
+        crashed.poll_once().await;
+        assert_eq!(crashed.pending(), 1);
+        drop(crashed);
+
+        let restarted = tracker(PendingStore::open(&path).unwrap());
+        assert_eq!(restarted.resume_stored(), 1);
+        let checkpoint = restarted.checkpoint();
+        assert_eq!(checkpoint[0].op, TrackedOp::Tx(pending_hash));
+        assert_eq!(checkpoint[0].tx_type, RESUMED_TX_TYPE);
+        assert!(checkpoint[0].pending_ms >= 30_000);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    /// Tests that a tracker built from the configuration keeps its operations in `store_file`.
+    #[tokio::test]
+    async fn test_tracker_from_config() {
+        let path =
+            std::env::temp_dir().join(format!("confirmation-config-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let config = ConfirmationConfig {
+            store_file: Some(path.clone()),
+            ..Default::default()
+        };
+        let tracker = |config: &ConfirmationConfig| {
+            ConfirmationTracker::from_config(
+                Arc::new(ScriptedSource::default()),
+                config.clone(),
+                Arc::new(RunRecorder::new()),
+                Arc::new(Metrics::new()),
+            )
+            .unwrap()
+        };
+
+        let first = tracker(&config);
+        assert_eq!(first.resume_stored(), 0);
+        first.track(TrackedOp::PriorityOp(7), "deposit", Instant::now());
+        drop(first);
+        assert!(path.exists());
+
+        let restarted = tracker(&config);
+        assert_eq!(restarted.resume_stored(), 1);
+        assert_eq!(restarted.pending(), 1);
+        // Without a store file nothing is carried over.
+        assert_eq!(tracker(&ConfirmationConfig::default()).resume_stored(), 0);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    /// Feed covering every operation but priority operations, notified by the test.
+    #[derive(Default)]
+    struct ScriptedFeed {
+        events: Mutex<Vec<StatusEvent>>,
+        forgotten: Mutex<Vec<TrackedOp>>,
+        arrived: tokio::sync::Notify,
+    }
+
+    #[async_trait]
+    impl StatusFeed for ScriptedFeed {
+        fn subscribe(&self, _op: &TrackedOp) {}
+
+        fn forget(&self, op: &TrackedOp) {
+            self.forgotten.lock().unwrap().push(*op);
+        }
+
+        fn covers(&self, op: &TrackedOp) -> bool {
+            !matches!(op, TrackedOp::PriorityOp(_))
+        }
+
+        fn take_events(&self) -> Vec<StatusEvent> {
+            std::mem::take(&mut self.events.lock().unwrap())
+        }
+
+        async fn arrived(&self) {
+            self.arrived.notified().await
+        }
+    }
+
+    /// Tests that covered operations advance on notifications only and wake the tracker up.
+    #[tokio::test]
+    async fn test_notified_confirmations() {
+        let tx_hash = TxHash { data: [1; 32] };
+        let tx_info = |verified| TransactionInfo {
+            executed: true,
+            success: Some(true),
+            fail_reason: None,
+            block: block(verified),
+        };
+        // The transaction is unknown to the server, it only settles through notifications.
+        let source = ScriptedSource::default();
+        source.ethops.lock().unwrap().insert(
+            7,
+            vec![EthOpInfo {
+                executed: true,
+                block: block(true),
+            }],
+        );
+        let feed = Arc::new(ScriptedFeed::default());
+        let recorder = Arc::new(RunRecorder::new());
+        let metrics = Arc::new(Metrics::new());
+        let config = ConfirmationConfig {
+            poll_interval_ms: 60_000,
+            ..Default::default()
+        };
+        let tracker = Arc::new(
+            ConfirmationTracker::new(Arc::new(source), config, recorder, metrics.clone())
+                .with_events(feed.clone()),
+        );
+        tracker.track(TrackedOp::Tx(tx_hash), "transfer", Instant::now());
+        tracker.track(TrackedOp::PriorityOp(7), "deposit", Instant::now());
+        tracker.poll_once().await;
+        assert_eq!(tracker.pending(), 1);
+        assert_eq!(*feed.forgotten.lock().unwrap(), [TrackedOp::PriorityOp(7)]);
+
+        let settled = tokio::spawn({
+            let tracker = tracker.clone();
+            async move { tracker.run_until_settled().await }
+        });
+        // Out of order, the commit must not undo the verification.
+        feed.events.lock().unwrap().extend([
+            StatusEvent::Tx(tx_hash, tx_info(true)),
+            StatusEvent::Tx(tx_hash, tx_info(false)),
+        ]);
+        feed.arrived.notify_one();
+        tokio::time::timeout(Duration::from_secs(5), settled)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(feed.forgotten.lock().unwrap().len(), 2);
+        let key = |stage| {
+            MetricKey::new(
+                CONFIRMATION_LATENCY_METRIC,
+                &[("type", "transfer"), ("stage", stage)],
+            )
+        };
+        let histograms = metrics.histograms();
+        assert_eq!(histograms[&key("committed")].count, 1);
+        assert_eq!(histograms[&key("verified")].count, 1);
+    }
+
+    /// Tests that the audit only misses confirmations the tracker never received.
+    #[tokio::test]
+    async fn test_confirmation_audit() {
+        let committed = TransactionInfo {
+            executed: true,
+            success: Some(true),
+            fail_reason: None,
+            block: block(false),
+        };
+        let (received, missed) = (TxHash { data: [1; 32] }, TxHash { data: [2; 32] });
+        let source = ScriptedSource::default();
+        source.txs.lock().unwrap().insert(received, vec![committed]);
+        let audit = Arc::new(ConfirmationAudit::new());
+        let tracker = ConfirmationTracker::new(
+            Arc::new(source),
+            ConfirmationConfig::default(),
+            Arc::new(RunRecorder::new()),
+            Arc::new(Metrics::new()),
+        )
+        .with_audit(audit.clone());
+        tracker.track(TrackedOp::Tx(received), "Transfer", Instant::now());
+        tracker.track(TrackedOp::Tx(missed), "Transfer", Instant::now());
+        tracker.track(TrackedOp::PriorityOp(7), "Deposit", Instant::now());
+
+        let mut in_flight = tracker.pending_tx_hashes();
+        in_flight.sort();
+        assert_eq!(in_flight, vec![received, missed]);
+        audit.restart(in_flight);
+        tracker.poll_once().await;
+
+        assert_eq!(audit.missed(&[received, missed]), vec![missed]);
+    }
+}
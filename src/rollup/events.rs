@@ -0,0 +1,409 @@
+//! Status notifications of submitted operations pushed by the rollup server over WebSocket.
+//!
+//! The JSON-RPC API of v0.1 accepts `tx_subscribe` and `ethop_subscribe` requests on its
+//! WebSocket endpoint and sends one notification per subscription once the operation is
+//! committed or verified, right away when it already was. Operations followed that way no
+//! longer have to be polled, which removes most of the requests of the confirmation
+//! tracker during high TPS runs.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use thiserror::Error;
+use tokio::sync::{mpsc, Notify};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::Connector;
+use tracing::warn;
+
+use super::confirmation::TrackedOp;
+use super::types::{EthOpInfo, TransactionInfo, TxHash};
+use crate::scenario::reconnect_storm::Reconnectable;
+use crate::tls::{self, TlsPins};
+
+/// Stages a subscription is made for, each notified once.
+const ACTIONS: [&str; 2] = ["COMMIT", "VERIFY"];
+
+#[derive(Debug, Error)]
+pub enum EventError {
+    #[error("Unable to connect to {0}: {1}")]
+    Connect(String, String),
+}
+
+/// Status of an operation as notified by the server.
+#[derive(Debug, Clone)]
+pub enum StatusEvent {
+    Tx(TxHash, TransactionInfo),
+    PriorityOp(u32, EthOpInfo),
+}
+
+/// Source of status notifications the confirmation tracker consults before polling.
+#[async_trait]
+pub trait StatusFeed: Send + Sync {
+    /// Asks for the notifications of `op`.
+    fn subscribe(&self, op: &TrackedOp);
+
+    /// Drops the notifications of an operation that is no longer tracked.
+    fn forget(&self, op: &TrackedOp);
+
+    /// Whether notifications of `op` are expected, so it need not be polled.
+    fn covers(&self, op: &TrackedOp) -> bool;
+
+    /// Notifications received since the previous call.
+    fn take_events(&self) -> Vec<StatusEvent>;
+
+    /// Waits until a notification arrives.
+    async fn arrived(&self);
+}
+
+/// Operation a subscription is made for; withdrawals are followed by their L2 hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum SubscriptionKey {
+    Tx(TxHash),
+    PriorityOp(u32),
+}
+
+impl SubscriptionKey {
+    fn of(op: &TrackedOp) -> Self {
+        match *op {
+            TrackedOp::Tx(tx_hash) | TrackedOp::Withdrawal(tx_hash) => SubscriptionKey::Tx(tx_hash),
+            TrackedOp::PriorityOp(serial_id) => SubscriptionKey::PriorityOp(serial_id),
+        }
+    }
+
+    fn method(&self, action: &str) -> String {
+        let kind = match self {
+            SubscriptionKey::Tx(_) => "tx",
+            SubscriptionKey::PriorityOp(_) => "ethop",
+        };
+        format!("{kind}_{action}")
+    }
+
+    fn param(&self) -> Value {
+        match self {
+            SubscriptionKey::Tx(tx_hash) => json!(tx_hash.to_string()),
+            SubscriptionKey::PriorityOp(serial_id) => json!(serial_id),
+        }
+    }
+
+    fn event(&self, result: Value) -> Result<StatusEvent, serde_json::Error> {
+        Ok(match *self {
+            SubscriptionKey::Tx(tx_hash) => {
+                StatusEvent::Tx(tx_hash, serde_json::from_value(result)?)
+            }
+            SubscriptionKey::PriorityOp(serial_id) => {
+                StatusEvent::PriorityOp(serial_id, serde_json::from_value(result)?)
+            }
+        })
+    }
+}
+
+#[derive(Default)]
+struct State {
+    /// Sender of the messages to the server, none while disconnected.
+    outgoing: Option<mpsc::UnboundedSender<Message>>,
+    next_id: u64,
+    /// Subscribe requests awaiting the id of their subscription.
+    requests: HashMap<u64, SubscriptionKey>,
+    /// Subscriptions by the id the server assigned to them.
+    subscriptions: HashMap<String, SubscriptionKey>,
+    /// Operations followed through notifications, subscribed again after a reconnect.
+    wanted: HashSet<SubscriptionKey>,
+    events: Vec<StatusEvent>,
+}
+
+impl State {
+    fn send(&mut self, method: String, params: Value) -> Option<u64> {
+        let outgoing = self.outgoing.as_ref()?;
+        self.next_id += 1;
+        let request =
+            json!({ "jsonrpc": "2.0", "id": self.next_id, "method": method, "params": params });
+        outgoing.send(Message::Text(request.to_string())).ok()?;
+        Some(self.next_id)
+    }
+
+    fn request_subscriptions(&mut self, key: SubscriptionKey) {
+        for action in ACTIONS {
+            let params = json!([key.param(), action]);
+            if let Some(id) = self.send(key.method("subscribe"), params) {
+                self.requests.insert(id, key);
+            }
+        }
+    }
+
+    fn disconnected(&mut self) {
+        self.outgoing = None;
+        self.requests.clear();
+        self.subscriptions.clear();
+    }
+
+    /// Handles a response to a subscribe request or a notification.
+    fn handle(&mut self, message: Value) -> bool {
+        if let Some(id) = message["id"].as_u64() {
+            let Some(key) = self.requests.remove(&id) else {
+                return false;
+            };
+            if let Some(error) = message.get("error").filter(|error| !error.is_null()) {
+                // Polled from now on.
+                warn!(?key, %error, "subscription refused by the server");
+                self.wanted.remove(&key);
+            } else if self.wanted.contains(&key) {
+                self.subscriptions
+                    .insert(message["result"].to_string(), key);
+            }
+            return false;
+        }
+        let params = &message["params"];
+        let Some(&key) = self.subscriptions.get(&params["subscription"].to_string()) else {
+            return false;
+        };
+        match key.event(params["result"].clone()) {
+            Ok(event) => {
+                self.events.push(event);
+                true
+            }
+            Err(err) => {
+                warn!(?key, error = %err, "malformed notification");
+                false
+            }
+        }
+    }
+}
+
+/// `StatusFeed` subscribing to the WebSocket endpoint of the JSON-RPC API.
+///
+/// Operations stay covered while the connection is up and their subscriptions were not
+/// refused; the tracker polls them otherwise. A dropped connection is re-established with
+/// [`Reconnectable::reconnect`], which subscribes to every operation still tracked again.
+pub struct EventListener {
+    url: String,
+    pins: TlsPins,
+    state: Arc<Mutex<State>>,
+    arrived: Arc<Notify>,
+    tasks: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl EventListener {
+    /// Connects to `url`, a `ws://` or `wss://` endpoint, enforcing `pins` on the latter.
+    pub async fn connect(url: &str, pins: &TlsPins) -> Result<Self, EventError> {
+        let listener = Self {
+            url: url.to_string(),
+            pins: pins.clone(),
+            state: Arc::new(Mutex::new(State::default())),
+            arrived: Arc::new(Notify::new()),
+            tasks: Mutex::new(Vec::new()),
+        };
+        listener.open().await?;
+        Ok(listener)
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.state.lock().unwrap().outgoing.is_some()
+    }
+
+    async fn open(&self) -> Result<(), EventError> {
+        let connector = Connector::Rustls(Arc::new(tls::client_config(&self.pins)));
+        let (stream, _) = tokio_tungstenite::connect_async_tls_with_config(
+            &self.url,
+            None,
+            false,
+            Some(connector),
+        )
+        .await
+        .map_err(|err| EventError::Connect(self.url.clone(), err.to_string()))?;
+        let (mut sink, mut stream) = stream.split();
+        let (outgoing, mut messages) = mpsc::unbounded_channel();
+
+        let writer = tokio::spawn(async move {
+            while let Some(message) = messages.recv().await {
+                if sink.send(message).await.is_err() {
+                    break;
+                }
+            }
+        });
+        let state = self.state.clone();
+        let arrived = self.arrived.clone();
+        let reader = tokio::spawn(async move {
+            while let Some(Ok(message)) = stream.next().await {
+                let Message::Text(text) = message else {
+                    continue;
+                };
+                let Ok(message) = serde_json::from_str(&text) else {
+                    warn!(text, "malformed message on the event stream");
+                    continue;
+                };
+                if state.lock().unwrap().handle(message) {
+                    arrived.notify_one();
+                }
+            }
+            warn!("event stream closed, confirmations are polled until it reconnects");
+            state.lock().unwrap().disconnected();
+        });
+
+        let mut state = self.state.lock().unwrap();
+        state.outgoing = Some(outgoing);
+        for key in state.wanted.clone() {
+            state.request_subscriptions(key);
+        }
+        *self.tasks.lock().unwrap() = vec![writer, reader];
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StatusFeed for EventListener {
+    fn subscribe(&self, op: &TrackedOp) {
+        let key = SubscriptionKey::of(op);
+        let mut state = self.state.lock().unwrap();
+        if state.wanted.insert(key) {
+            state.request_subscriptions(key);
+        }
+    }
+
+    fn forget(&self, op: &TrackedOp) {
+        let key = SubscriptionKey::of(op);
+        let mut state = self.state.lock().unwrap();
+        state.wanted.remove(&key);
+        let ids: Vec<String> = state
+            .subscriptions
+            .iter()
+            .filter(|(_, subscribed)| **subscribed == key)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in ids {
+            state.subscriptions.remove(&id);
+            if let Ok(id) = serde_json::from_str::<Value>(&id) {
+                state.send(key.method("unsubscribe"), json!([id]));
+            }
+        }
+    }
+
+    fn covers(&self, op: &TrackedOp) -> bool {
+        let state = self.state.lock().unwrap();
+        state.outgoing.is_some() && state.wanted.contains(&SubscriptionKey::of(op))
+    }
+
+    fn take_events(&self) -> Vec<StatusEvent> {
+        std::mem::take(&mut self.state.lock().unwrap().events)
+    }
+
+    async fn arrived(&self) {
+        self.arrived.notified().await
+    }
+}
+
+#[async_trait]
+impl Reconnectable for EventListener {
+    fn name(&self) -> String {
+        format!("events {}", self.url)
+    }
+
+    async fn disconnect(&self) {
+        for task in self.tasks.lock().unwrap().drain(..) {
+            task.abort();
+        }
+        self.state.lock().unwrap().disconnected();
+    }
+
+    async fn reconnect(&self) -> Result<(), String> {
+        self.disconnect().await;
+        self.open().await.map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Accepts subscriptions to transactions, notifying their commit right away, and refuses
+    /// the ones to priority operations.
+    async fn serve(listener: TcpListener) {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                while let Some(Ok(Message::Text(text))) = ws.next().await {
+                    let request: Value = serde_json::from_str(&text).unwrap();
+                    let id = request["id"].as_u64().unwrap();
+                    let mut replies = Vec::new();
+                    match request["method"].as_str().unwrap() {
+                        "ethop_subscribe" => replies.push(json!({
+                            "jsonrpc": "2.0",
+                            "id": id,
+                            "error": { "code": -32601, "message": "Method not found" }
+                        })),
+                        "tx_subscribe" => {
+                            replies.push(json!({ "jsonrpc": "2.0", "id": id, "result": format!("sub-{id}") }));
+                            if request["params"][1] == "COMMIT" {
+                                replies.push(json!({
+                                    "jsonrpc": "2.0",
+                                    "method": "tx_subscribe",
+                                    "params": {
+                                        "subscription": format!("sub-{id}"),
+                                        "result": {
+                                            "executed": true,
+                                            "success": true,
+                                            "failReason": null,
+                                            "block": { "blockNumber": 7, "committed": true, "verified": false }
+                                        }
+                                    }
+                                }));
+                            }
+                        }
+                        _ => {}
+                    }
+                    for reply in replies {
+                        ws.send(Message::Text(reply.to_string())).await.unwrap();
+                    }
+                }
+            });
+        }
+    }
+
+    /// Tests subscriptions, notifications, refused subscriptions and reconnects.
+    #[tokio::test]
+    async fn test_event_listener() {
+        let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", server.local_addr().unwrap());
+        tokio::spawn(serve(server));
+
+        let listener = EventListener::connect(&url, &TlsPins::new()).await.unwrap();
+        let tx = TrackedOp::Tx(TxHash { data: [1; 32] });
+        let deposit = TrackedOp::PriorityOp(3);
+        listener.subscribe(&tx);
+        listener.subscribe(&deposit);
+        assert!(listener.covers(&tx) && listener.covers(&deposit));
+
+        tokio::time::timeout(Duration::from_secs(5), listener.arrived())
+            .await
+            .unwrap();
+        let events = listener.take_events();
+        assert!(matches!(
+            events.as_slice(),
+            [StatusEvent::Tx(tx_hash, info)] if *tx_hash == TxHash { data: [1; 32] } && info.executed
+        ));
+        // The refused subscription falls back to polling.
+        while listener.covers(&deposit) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(listener.covers(&tx));
+
+        listener.disconnect().await;
+        assert!(!listener.is_connected() && !listener.covers(&tx));
+        listener.reconnect().await.unwrap();
+        assert!(listener.covers(&tx));
+        tokio::time::timeout(Duration::from_secs(5), listener.arrived())
+            .await
+            .unwrap();
+        assert_eq!(listener.take_events().len(), 1);
+
+        listener.forget(&tx);
+        assert!(!listener.covers(&tx));
+    }
+}
@@ -0,0 +1,447 @@
+//! `Provider` spreading requests over several rollup servers behind a load balancer.
+//!
+//! Requests go round-robin to the endpoints of `network.rollup_urls`. An endpoint whose
+//! calls keep failing with network errors or timeouts is taken out of the rotation for
+//! `retry_after_secs`, and a call it fails is sent to the next endpoint instead. Refused
+//! requests are answers of a healthy server and are returned as they are.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use num::BigUint;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use super::http::HttpProvider;
+use super::network::Network;
+use super::provider::{ClientError, Provider, ProviderMethod, ResponseResult};
+use super::types::tx::{PackedEthSignature, ZkSyncTx};
+use super::types::{
+    AccountInfo, AccountTx, Address, ContractAddress, EthOpInfo, Fee, Paginated, PaginationQuery,
+    TokenLike, Tokens, TransactionInfo, TxFeeTypes, TxHash,
+};
+use crate::config::NetworkConfig;
+use crate::metrics::Metrics;
+use crate::scenario::reconnect_storm::Reconnectable;
+use crate::tagging::RunTag;
+
+/// Counter of calls answered by another endpoint than the one first chosen, labelled by method.
+pub const FAILOVERS_METRIC: &str = "provider_failovers_total";
+/// Counter of endpoints taken out of the rotation, labelled by endpoint.
+pub const ENDPOINT_DOWN_METRIC: &str = "provider_endpoint_down_total";
+
+/// Health tracking of the rollup endpoints, configured in the `[network.failover]` section.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FailoverConfig {
+    /// Consecutive failed calls after which an endpoint is taken out of the rotation.
+    #[serde(default = "FailoverConfig::default_max_failures")]
+    pub max_failures: u32,
+    /// How long an unhealthy endpoint stays out of the rotation before it is tried again.
+    #[serde(default = "FailoverConfig::default_retry_after_secs")]
+    pub retry_after_secs: u64,
+}
+
+impl FailoverConfig {
+    fn default_max_failures() -> u32 {
+        3
+    }
+
+    fn default_retry_after_secs() -> u64 {
+        30
+    }
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            max_failures: Self::default_max_failures(),
+            retry_after_secs: Self::default_retry_after_secs(),
+        }
+    }
+}
+
+/// Calls and health of one endpoint, for the logs and reports of a run.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointStatus {
+    pub url: String,
+    pub healthy: bool,
+    pub calls: u64,
+    pub failures: u64,
+}
+
+#[derive(Debug, Default)]
+struct EndpointHealth {
+    calls: u64,
+    failures: u64,
+    consecutive_failures: u32,
+    down_until: Option<Instant>,
+}
+
+impl EndpointHealth {
+    fn is_available(&self, now: Instant) -> bool {
+        !matches!(self.down_until, Some(until) if now < until)
+    }
+}
+
+struct Endpoint<P> {
+    url: String,
+    provider: P,
+    health: Mutex<EndpointHealth>,
+}
+
+impl<P> Endpoint<P> {
+    fn record_success(&self) {
+        let mut health = self.health.lock().unwrap();
+        health.calls += 1;
+        health.consecutive_failures = 0;
+        health.down_until = None;
+    }
+
+    /// Counts a failed call, returns whether it took the endpoint out of the rotation.
+    fn record_failure(&self, config: &FailoverConfig) -> bool {
+        let mut health = self.health.lock().unwrap();
+        health.calls += 1;
+        health.failures += 1;
+        health.consecutive_failures += 1;
+        if health.consecutive_failures < config.max_failures {
+            return false;
+        }
+        let was_healthy = health.down_until.is_none();
+        health.down_until = Some(Instant::now() + Duration::from_secs(config.retry_after_secs));
+        was_healthy
+    }
+}
+
+/// `Provider` sending each call to the next healthy endpoint of the rotation.
+///
+/// With every endpoint out of the rotation the unhealthy ones are still tried, so a run
+/// reports the errors of the servers rather than failing on its own.
+pub struct FailoverProvider<P> {
+    endpoints: Vec<Endpoint<P>>,
+    next: AtomicUsize,
+    config: FailoverConfig,
+    metrics: Arc<Metrics>,
+}
+
+impl FailoverProvider<HttpProvider> {
+    /// HTTP provider per configured rollup server.
+    pub fn from_config(config: &NetworkConfig, metrics: Arc<Metrics>) -> Self {
+        let endpoints = config
+            .rollup_urls()
+            .into_iter()
+            .map(|url| {
+                let provider = HttpProvider::new(config, metrics.clone()).with_url(url);
+                (url.to_string(), provider)
+            })
+            .collect();
+        Self::new(endpoints, config.failover.clone(), metrics)
+    }
+
+    /// Tags the requests to every endpoint with the identifier of the run.
+    pub fn with_run_tag(mut self, tag: &RunTag) -> Self {
+        self.endpoints = std::mem::take(&mut self.endpoints)
+            .into_iter()
+            .map(|endpoint| Endpoint {
+                provider: endpoint.provider.with_run_tag(tag),
+                ..endpoint
+            })
+            .collect();
+        self
+    }
+}
+
+impl<P: Provider + Sync> FailoverProvider<P> {
+    /// Rotates over the providers in the given order, keyed by their URL in logs and metrics.
+    ///
+    /// # Panics
+    ///
+    /// Panics when no endpoint is given.
+    pub fn new(endpoints: Vec<(String, P)>, config: FailoverConfig, metrics: Arc<Metrics>) -> Self {
+        assert!(
+            !endpoints.is_empty(),
+            "at least one rollup endpoint is required"
+        );
+        Self {
+            endpoints: endpoints
+                .into_iter()
+                .map(|(url, provider)| Endpoint {
+                    url,
+                    provider,
+                    health: Mutex::new(EndpointHealth::default()),
+                })
+                .collect(),
+            next: AtomicUsize::new(0),
+            config,
+            metrics,
+        }
+    }
+
+    pub fn status(&self) -> Vec<EndpointStatus> {
+        let now = Instant::now();
+        self.endpoints
+            .iter()
+            .map(|endpoint| {
+                let health = endpoint.health.lock().unwrap();
+                EndpointStatus {
+                    url: endpoint.url.clone(),
+                    healthy: health.is_available(now),
+                    calls: health.calls,
+                    failures: health.failures,
+                }
+            })
+            .collect()
+    }
+
+    /// Endpoints in the order the next call tries them: the healthy ones starting at the
+    /// next of the rotation, then the ones taken out of it.
+    fn rotation(&self) -> Vec<&Endpoint<P>> {
+        let count = self.endpoints.len();
+        let start = self.next.fetch_add(1, Ordering::Relaxed) % count;
+        let now = Instant::now();
+        let (mut order, down): (Vec<_>, Vec<_>) = (0..count)
+            .map(|offset| &self.endpoints[(start + offset) % count])
+            .partition(|endpoint| endpoint.health.lock().unwrap().is_available(now));
+        order.extend(down);
+        order
+    }
+
+    async fn route<'a, T, F, Fut>(&'a self, method: ProviderMethod, call: F) -> ResponseResult<T>
+    where
+        F: Fn(&'a P) -> Fut,
+        Fut: Future<Output = ResponseResult<T>> + 'a,
+    {
+        let mut last_error = ClientError::Other;
+        for (attempt, endpoint) in self.rotation().into_iter().enumerate() {
+            match call(&endpoint.provider).await {
+                Err(err) if err.is_backpressure() => {
+                    if endpoint.record_failure(&self.config) {
+                        warn!(
+                            "Rollup endpoint {} is unhealthy, leaving it out for {}s: {}",
+                            endpoint.url, self.config.retry_after_secs, err
+                        );
+                        self.metrics
+                            .increment(ENDPOINT_DOWN_METRIC, &[("endpoint", &endpoint.url)]);
+                    }
+                    last_error = err;
+                }
+                result => {
+                    let recovered = endpoint.health.lock().unwrap().down_until.is_some();
+                    endpoint.record_success();
+                    if recovered {
+                        info!("Rollup endpoint {} is back", endpoint.url);
+                    }
+                    if attempt > 0 {
+                        self.metrics
+                            .increment(FAILOVERS_METRIC, &[("method", method.name())]);
+                    }
+                    return result;
+                }
+            }
+        }
+        Err(last_error)
+    }
+}
+
+#[async_trait]
+impl<P: Provider + Send + Sync> Provider for FailoverProvider<P> {
+    async fn account_info(&self, address: Address) -> ResponseResult<AccountInfo> {
+        self.route(ProviderMethod::AccountInfo, |provider| {
+            provider.account_info(address)
+        })
+        .await
+    }
+
+    async fn account_txs(
+        &self,
+        address: Address,
+        query: PaginationQuery,
+    ) -> ResponseResult<Paginated<AccountTx>> {
+        self.route(ProviderMethod::AccountTxs, |provider| {
+            provider.account_txs(address, query)
+        })
+        .await
+    }
+
+    async fn tokens(&self) -> ResponseResult<Tokens> {
+        self.route(ProviderMethod::Tokens, |provider| provider.tokens())
+            .await
+    }
+
+    async fn tx_info(&self, tx_hash: TxHash) -> ResponseResult<TransactionInfo> {
+        self.route(ProviderMethod::TxInfo, |provider| provider.tx_info(tx_hash))
+            .await
+    }
+
+    async fn get_tx_fee(
+        &self,
+        tx_type: TxFeeTypes,
+        address: Address,
+        token: impl Into<TokenLike> + Send + 'async_trait,
+    ) -> ResponseResult<Fee> {
+        let token = token.into();
+        self.route(ProviderMethod::GetTxFee, |provider| {
+            provider.get_tx_fee(tx_type, address, token.clone())
+        })
+        .await
+    }
+
+    async fn get_txs_batch_fee(
+        &self,
+        tx_types: Vec<TxFeeTypes>,
+        addresses: Vec<Address>,
+        token: impl Into<TokenLike> + Send + 'async_trait,
+    ) -> ResponseResult<BigUint> {
+        let token = token.into();
+        self.route(ProviderMethod::GetTxsBatchFee, |provider| {
+            provider.get_txs_batch_fee(tx_types.clone(), addresses.clone(), token.clone())
+        })
+        .await
+    }
+
+    async fn ethop_info(&self, serial_id: u32) -> ResponseResult<EthOpInfo> {
+        self.route(ProviderMethod::EthOpInfo, |provider| {
+            provider.ethop_info(serial_id)
+        })
+        .await
+    }
+
+    async fn get_eth_tx_for_withdrawal(
+        &self,
+        withdrawal_hash: TxHash,
+    ) -> ResponseResult<Option<String>> {
+        self.route(ProviderMethod::GetEthTxForWithdrawal, |provider| {
+            provider.get_eth_tx_for_withdrawal(withdrawal_hash)
+        })
+        .await
+    }
+
+    async fn contract_address(&self) -> ResponseResult<ContractAddress> {
+        self.route(ProviderMethod::ContractAddress, |provider| {
+            provider.contract_address()
+        })
+        .await
+    }
+
+    /// A transaction sent again after a timeout has the same hash, the servers behind the
+    /// load balancer share their mempool and refuse the copy of an accepted one.
+    async fn send_tx(
+        &self,
+        tx: ZkSyncTx,
+        eth_signature: Option<PackedEthSignature>,
+    ) -> ResponseResult<TxHash> {
+        self.route(ProviderMethod::SendTx, |provider| {
+            provider.send_tx(tx.clone(), eth_signature)
+        })
+        .await
+    }
+
+    async fn send_txs_batch(
+        &self,
+        txs_signed: Vec<(ZkSyncTx, Option<PackedEthSignature>)>,
+        eth_signature: Option<PackedEthSignature>,
+    ) -> ResponseResult<Vec<TxHash>> {
+        self.route(ProviderMethod::SendTxsBatch, |provider| {
+            provider.send_txs_batch(txs_signed.clone(), eth_signature)
+        })
+        .await
+    }
+
+    fn network(&self) -> Network {
+        self.endpoints[0].provider.network()
+    }
+}
+
+#[async_trait]
+impl<P: Reconnectable> Reconnectable for FailoverProvider<P> {
+    fn name(&self) -> String {
+        let names: Vec<_> = self.endpoints.iter().map(|e| e.provider.name()).collect();
+        names.join(", ")
+    }
+
+    async fn disconnect(&self) {
+        for endpoint in &self.endpoints {
+            endpoint.provider.disconnect().await;
+        }
+    }
+
+    /// Back once every endpoint reconnected, the first error otherwise.
+    async fn reconnect(&self) -> Result<(), String> {
+        let mut result = Ok(());
+        for endpoint in &self.endpoints {
+            if let Err(err) = endpoint.provider.reconnect().await {
+                result = result.and(Err(err));
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rollup::mock::{MethodBehavior, MockProvider};
+
+    fn failing() -> MockProvider {
+        MockProvider::new().with_default_behavior(MethodBehavior {
+            failure_rate: 1.0,
+            ..Default::default()
+        })
+    }
+
+    /// Tests round-robin over healthy endpoints, failover away from a failing one and its
+    /// removal from the rotation once it failed `max_failures` times in a row.
+    #[tokio::test]
+    async fn test_failover_provider() {
+        let metrics = Arc::new(Metrics::new());
+        let config = FailoverConfig {
+            max_failures: 2,
+            retry_after_secs: 3_600,
+        };
+        let provider = FailoverProvider::new(
+            vec![
+                ("a".to_string(), MockProvider::new()),
+                ("b".to_string(), failing()),
+                ("c".to_string(), MockProvider::new()),
+            ],
+            config,
+            metrics.clone(),
+        );
+        for _ in 0..9 {
+            provider.tokens().await.unwrap();
+        }
+        let calls = |status: &[EndpointStatus]| -> Vec<(u64, u64, bool)> {
+            status
+                .iter()
+                .map(|endpoint| (endpoint.calls, endpoint.failures, endpoint.healthy))
+                .collect()
+        };
+        // b fails the second and fifth calls over to c, then stays out of the rotation.
+        assert_eq!(
+            calls(&provider.status()),
+            [(3, 0, true), (2, 2, false), (6, 0, true)]
+        );
+        assert_eq!(
+            metrics.counter(FAILOVERS_METRIC, &[("method", "tokens")]),
+            2
+        );
+        assert_eq!(
+            metrics.counter(ENDPOINT_DOWN_METRIC, &[("endpoint", "b")]),
+            1
+        );
+
+        let down = FailoverProvider::new(
+            vec![("a".to_string(), failing()), ("b".to_string(), failing())],
+            FailoverConfig::default(),
+            metrics,
+        );
+        assert!(matches!(
+            down.tokens().await,
+            Err(ClientError::NetworkError(_))
+        ));
+        assert_eq!(calls(&down.status()), [(1, 1, true), (1, 1, true)]);
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use ethers::providers::Middleware;
+use serde::Deserialize;
+
+use super::types::{Fee, TokenLike, TxFeeTypes, U256};
+use crate::metrics::Metrics;
+
+/// Counter of fee cache flushes caused by L1 gas price movement.
+pub const INVALIDATIONS_METRIC: &str = "fee_cache_invalidations_total";
+
+/// Caching of quoted fees, configured in the `[network.fee_cache]` section.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeeCacheConfig {
+    /// How long a quoted fee is reused when the gas price does not move.
+    #[serde(default = "FeeCacheConfig::default_ttl_secs")]
+    pub ttl_secs: u64,
+    /// Relative L1 gas price change, in percent, flushing all cached fees.
+    #[serde(default = "FeeCacheConfig::default_gas_price_threshold_percent")]
+    pub gas_price_threshold_percent: u64,
+    /// How often the L1 gas price is polled.
+    #[serde(default = "FeeCacheConfig::default_gas_price_poll_secs")]
+    pub gas_price_poll_secs: u64,
+}
+
+impl FeeCacheConfig {
+    fn default_ttl_secs() -> u64 {
+        60
+    }
+
+    fn default_gas_price_threshold_percent() -> u64 {
+        10
+    }
+
+    fn default_gas_price_poll_secs() -> u64 {
+        15
+    }
+}
+
+impl Default for FeeCacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: Self::default_ttl_secs(),
+            gas_price_threshold_percent: Self::default_gas_price_threshold_percent(),
+            gas_price_poll_secs: Self::default_gas_price_poll_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    fees: HashMap<(TxFeeTypes, TokenLike), (Fee, Instant)>,
+    /// Gas price the cached fees were quoted at.
+    reference_gas_price: Option<U256>,
+}
+
+/// Fees quoted by the server keyed by transaction type and token.
+///
+/// Fees mostly follow the L1 gas price, so instead of re-quoting every transaction
+/// all entries are dropped at once when the gas price moves beyond the threshold.
+#[derive(Debug)]
+pub struct FeeCache {
+    config: FeeCacheConfig,
+    state: Mutex<CacheState>,
+}
+
+impl FeeCache {
+    pub fn new(config: FeeCacheConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    pub fn get(&self, tx_type: TxFeeTypes, token: &TokenLike) -> Option<Fee> {
+        let ttl = Duration::from_secs(self.config.ttl_secs);
+        let state = self.state.lock().unwrap();
+        state
+            .fees
+            .get(&(tx_type, token.clone()))
+            .filter(|(_, quoted_at)| quoted_at.elapsed() < ttl)
+            .map(|(fee, _)| fee.clone())
+    }
+
+    pub fn insert(&self, tx_type: TxFeeTypes, token: TokenLike, fee: Fee) {
+        let mut state = self.state.lock().unwrap();
+        state.fees.insert((tx_type, token), (fee, Instant::now()));
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().fees.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Records the current L1 gas price, flushing the cache when it moved beyond the threshold.
+    ///
+    /// Returns whether the cache was flushed.
+    pub fn observe_gas_price(&self, gas_price: U256) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let reference = match state.reference_gas_price {
+            Some(reference) => reference,
+            None => {
+                state.reference_gas_price = Some(gas_price);
+                return false;
+            }
+        };
+
+        let change = if gas_price > reference {
+            gas_price - reference
+        } else {
+            reference - gas_price
+        };
+        let moved = if reference.is_zero() {
+            !gas_price.is_zero()
+        } else {
+            change * U256::from(100)
+                > reference * U256::from(self.config.gas_price_threshold_percent)
+        };
+        if moved {
+            state.fees.clear();
+            state.reference_gas_price = Some(gas_price);
+        }
+        moved
+    }
+
+    /// Polls the L1 gas price for as long as the cache is alive, counting invalidations.
+    pub async fn watch_gas_price<M: Middleware>(self: Arc<Self>, l1: M, metrics: Arc<Metrics>) {
+        let mut interval =
+            tokio::time::interval(Duration::from_secs(self.config.gas_price_poll_secs.max(1)));
+        loop {
+            interval.tick().await;
+            // A failed poll keeps the current entries, they still expire by TTL.
+            if let Ok(gas_price) = l1.get_gas_price().await {
+                if self.observe_gas_price(gas_price) {
+                    metrics.increment(INVALIDATIONS_METRIC, &[]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use num::BigUint;
+
+    use super::*;
+    use crate::rollup::types::OutputFeeType;
+
+    fn fee(total: u32) -> Fee {
+        Fee {
+            fee_type: OutputFeeType::Transfer,
+            gas_tx_amount: BigUint::default(),
+            gas_price_wei: BigUint::default(),
+            gas_fee: BigUint::default(),
+            zkp_fee: BigUint::default(),
+            total_fee: BigUint::from(total),
+        }
+    }
+
+    /// Tests that only gas price moves beyond the threshold flush the cache.
+    #[test]
+    fn test_gas_price_invalidation() {
+        let cache = FeeCache::new(FeeCacheConfig {
+            ttl_secs: 3_600,
+            gas_price_threshold_percent: 10,
+            gas_price_poll_secs: 1,
+        });
+        let token = TokenLike::from("RBTC");
+
+        assert!(!cache.observe_gas_price(U256::from(100)));
+        cache.insert(TxFeeTypes::Transfer, token.clone(), fee(5));
+        cache.insert(TxFeeTypes::Withdraw, token.clone(), fee(9));
+
+        assert!(!cache.observe_gas_price(U256::from(110)));
+        assert!(!cache.observe_gas_price(U256::from(91)));
+        assert_eq!(
+            cache.get(TxFeeTypes::Transfer, &token).unwrap().total_fee,
+            BigUint::from(5u32)
+        );
+
+        assert!(cache.observe_gas_price(U256::from(89)));
+        assert!(cache.is_empty());
+
+        // The new price becomes the reference for the next comparison.
+        cache.insert(TxFeeTypes::Transfer, token.clone(), fee(4));
+        assert!(!cache.observe_gas_price(U256::from(97)));
+        assert!(cache.get(TxFeeTypes::Transfer, &token).is_some());
+    }
+}
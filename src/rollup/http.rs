@@ -0,0 +1,385 @@
+//! `Provider` talking to the rollup server over HTTP(S).
+//!
+//! The wire format follows `network.api_version`: the JSON-RPC API of v0.1 or the REST
+//! gateway of v0.2, both through the adapters of [`super::adapters`]. Every request is timed
+//! into a histogram labelled by API version and method, so runs against either interface
+//! can be compared call for call.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
+use async_trait::async_trait;
+use hyper::client::HttpConnector;
+use hyper::header::CONTENT_TYPE;
+use hyper::{Body, Client, Method, Request};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use num::BigUint;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::adapters::{adapter_for, ApiAdapter, ApiCall, ApiRequest, ApiVersion, HttpMethod};
+use super::network::Network;
+use super::provider::{ClientError, Provider, ResponseResult};
+use super::timeouts::{with_timeout, TimeoutsConfig};
+use super::types::tx::{PackedEthSignature, TxEthSignature, ZkSyncTx};
+use super::types::{
+    AccountInfo, AccountTx, Address, ContractAddress, EthOpInfo, Fee, Paginated, PaginationQuery,
+    TokenLike, Tokens, TransactionInfo, TxFeeTypes, TxHash,
+};
+use crate::config::NetworkConfig;
+use crate::metrics::Metrics;
+use crate::scenario::reconnect_storm::Reconnectable;
+use crate::tagging::{RunTag, RUN_ID_HEADER};
+use crate::tls;
+
+/// Histogram of the time the rollup server took to answer, labelled by API version and method.
+pub const PROVIDER_LATENCY_METRIC: &str = "provider_request_latency_seconds";
+
+/// `Provider` sending every call to the rollup server of the network in the format of the
+/// configured API.
+pub struct HttpProvider {
+    url: String,
+    network: Network,
+    adapter: Box<dyn ApiAdapter>,
+    connector: HttpsConnector<HttpConnector>,
+    /// Replaced on `disconnect`, which drops the pooled connections.
+    client: RwLock<Client<HttpsConnector<HttpConnector>>>,
+    timeouts: TimeoutsConfig,
+    metrics: Arc<Metrics>,
+    next_id: AtomicU64,
+    run_id: Option<String>,
+}
+
+impl HttpProvider {
+    pub fn new(config: &NetworkConfig, metrics: Arc<Metrics>) -> Self {
+        let connector = HttpsConnectorBuilder::new()
+            .with_tls_config(tls::client_config(&config.tls_pins))
+            .https_or_http()
+            .enable_http1()
+            .build();
+        Self {
+            url: config.rollup_url().trim_end_matches('/').to_string(),
+            network: config.chain,
+            adapter: adapter_for(config.api_version),
+            client: RwLock::new(Client::builder().build(connector.clone())),
+            connector,
+            timeouts: config.timeouts.clone(),
+            metrics,
+            next_id: AtomicU64::new(1),
+            run_id: None,
+        }
+    }
+
+    /// Sends the identifier of the run along with every request, see [`RUN_ID_HEADER`].
+    pub fn with_run_tag(mut self, tag: &RunTag) -> Self {
+        self.run_id = Some(tag.run_id().to_string());
+        self
+    }
+
+    /// Sends the calls to `url` instead of the rollup server of the configuration.
+    pub fn with_url(mut self, url: &str) -> Self {
+        self.url = url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_network(mut self, network: Network) -> Self {
+        self.network = network;
+        self
+    }
+
+    pub fn api_version(&self) -> ApiVersion {
+        self.adapter.version()
+    }
+
+    /// Sends the call and returns the payload of the response, timing it per method.
+    async fn call(&self, call: ApiCall) -> ResponseResult<Value> {
+        let method = call.method();
+        let request = self.adapter.request(&call)?;
+        let started = Instant::now();
+        let result = with_timeout(&self.timeouts, &self.metrics, method, self.send(request)).await;
+        self.metrics.observe(
+            PROVIDER_LATENCY_METRIC,
+            &[
+                ("api", self.api_version().label()),
+                ("method", method.name()),
+            ],
+            started.elapsed(),
+        );
+        result
+    }
+
+    async fn send(&self, request: ApiRequest) -> ResponseResult<Value> {
+        let (method, uri, body) = match request {
+            ApiRequest::JsonRpc { method, params } => {
+                let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                let body =
+                    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+                (Method::POST, self.url.clone(), Some(body))
+            }
+            ApiRequest::Rest { method, path, body } => {
+                let method = match method {
+                    HttpMethod::Get => Method::GET,
+                    HttpMethod::Post => Method::POST,
+                };
+                (method, format!("{}{}", self.url, path), body)
+            }
+        };
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(CONTENT_TYPE, "application/json");
+        if let Some(run_id) = &self.run_id {
+            request = request.header(RUN_ID_HEADER, run_id);
+        }
+        let request = request
+            .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+            .map_err(|err| ClientError::NetworkError(err.to_string()))?;
+        let client = self.client.read().unwrap().clone();
+        let response = client
+            .request(request)
+            .await
+            .map_err(|err| ClientError::NetworkError(err.to_string()))?;
+        let status = response.status();
+        let bytes = hyper::body::to_bytes(response.into_body())
+            .await
+            .map_err(|err| ClientError::NetworkError(err.to_string()))?;
+        let body: Value = serde_json::from_slice(&bytes).map_err(|err| {
+            ClientError::MalformedResponse(format!("HTTP {}: {}", status.as_u16(), err))
+        })?;
+        self.adapter.unwrap_response(body)
+    }
+}
+
+fn to_value(value: impl Serialize) -> ResponseResult<Value> {
+    serde_json::to_value(value).map_err(|err| ClientError::MalformedResponse(err.to_string()))
+}
+
+fn eth_signature_value(signature: Option<PackedEthSignature>) -> ResponseResult<Option<Value>> {
+    signature
+        .map(TxEthSignature::from)
+        .map(to_value)
+        .transpose()
+}
+
+#[async_trait]
+impl Provider for HttpProvider {
+    async fn account_info(&self, address: Address) -> ResponseResult<AccountInfo> {
+        let response = self.call(ApiCall::AccountInfo(address)).await?;
+        self.adapter.parse_account_info(response)
+    }
+
+    async fn account_txs(
+        &self,
+        address: Address,
+        query: PaginationQuery,
+    ) -> ResponseResult<Paginated<AccountTx>> {
+        let response = self.call(ApiCall::AccountTxs { address, query }).await?;
+        self.adapter.parse_account_txs(response)
+    }
+
+    async fn tokens(&self) -> ResponseResult<Tokens> {
+        let response = self.call(ApiCall::Tokens).await?;
+        self.adapter.parse_tokens(response)
+    }
+
+    async fn tx_info(&self, tx_hash: TxHash) -> ResponseResult<TransactionInfo> {
+        let response = self.call(ApiCall::TxInfo(tx_hash)).await?;
+        self.adapter.parse_tx_info(response)
+    }
+
+    async fn get_tx_fee(
+        &self,
+        tx_type: TxFeeTypes,
+        address: Address,
+        token: impl Into<TokenLike> + Send + 'async_trait,
+    ) -> ResponseResult<Fee> {
+        let call = ApiCall::TxFee {
+            tx_type,
+            address,
+            token: token.into(),
+        };
+        let response = self.call(call).await?;
+        self.adapter.parse_tx_fee(tx_type, response)
+    }
+
+    async fn get_txs_batch_fee(
+        &self,
+        tx_types: Vec<TxFeeTypes>,
+        addresses: Vec<Address>,
+        token: impl Into<TokenLike> + Send + 'async_trait,
+    ) -> ResponseResult<BigUint> {
+        let call = ApiCall::TxsBatchFee {
+            tx_types,
+            addresses,
+            token: token.into(),
+        };
+        let response = self.call(call).await?;
+        self.adapter.parse_txs_batch_fee(response)
+    }
+
+    async fn ethop_info(&self, serial_id: u32) -> ResponseResult<EthOpInfo> {
+        let response = self.call(ApiCall::EthOpInfo(serial_id)).await?;
+        self.adapter.parse_ethop_info(response)
+    }
+
+    async fn get_eth_tx_for_withdrawal(
+        &self,
+        withdrawal_hash: TxHash,
+    ) -> ResponseResult<Option<String>> {
+        let response = self
+            .call(ApiCall::EthTxForWithdrawal(withdrawal_hash))
+            .await?;
+        self.adapter.parse_eth_tx_for_withdrawal(response)
+    }
+
+    async fn contract_address(&self) -> ResponseResult<ContractAddress> {
+        let response = self.call(ApiCall::ContractAddress).await?;
+        self.adapter.parse_contract_address(response)
+    }
+
+    async fn send_tx(
+        &self,
+        tx: ZkSyncTx,
+        eth_signature: Option<PackedEthSignature>,
+    ) -> ResponseResult<TxHash> {
+        let call = ApiCall::SendTx {
+            tx: to_value(tx)?,
+            eth_signature: eth_signature_value(eth_signature)?,
+        };
+        let response = self.call(call).await?;
+        self.adapter.parse_tx_hash(response)
+    }
+
+    async fn send_txs_batch(
+        &self,
+        txs_signed: Vec<(ZkSyncTx, Option<PackedEthSignature>)>,
+        eth_signature: Option<PackedEthSignature>,
+    ) -> ResponseResult<Vec<TxHash>> {
+        let txs = txs_signed
+            .into_iter()
+            .map(|(tx, signature)| Ok((to_value(tx)?, eth_signature_value(signature)?)))
+            .collect::<ResponseResult<Vec<_>>>()?;
+        let call = ApiCall::SendTxsBatch {
+            txs,
+            eth_signature: eth_signature_value(eth_signature)?,
+        };
+        let response = self.call(call).await?;
+        self.adapter.parse_tx_hashes(response)
+    }
+
+    fn network(&self) -> Network {
+        self.network
+    }
+}
+
+#[async_trait]
+impl Reconnectable for HttpProvider {
+    fn name(&self) -> String {
+        format!("http {}", self.url)
+    }
+
+    /// Drops the connection pool, requests in flight finish on the connections they hold.
+    async fn disconnect(&self) {
+        *self.client.write().unwrap() = Client::builder().build(self.connector.clone());
+    }
+
+    /// Opens a new connection with a cheap request.
+    async fn reconnect(&self) -> Result<(), String> {
+        self.contract_address()
+            .await
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Response, Server};
+
+    use super::*;
+    use crate::config::Config;
+    use crate::metrics::MetricKey;
+
+    /// Answers `contract_address` in the format of both APIs, and fails everything else.
+    async fn serve(request: Request<Body>) -> Result<Response<Body>, Infallible> {
+        let path = request.uri().path().to_string();
+        let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+        let contract =
+            json!({ "contract": Address::repeat_byte(1), "govContract": Address::zero() });
+        let response = match path.as_str() {
+            "/" => {
+                let request: Value = serde_json::from_slice(&body).unwrap();
+                match request["method"].as_str() {
+                    Some("contract_address") => json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "result": { "mainContract": "0x01", "govContract": "0x02" }
+                    }),
+                    _ => json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "error": { "code": -32601, "message": "Method not found" }
+                    }),
+                }
+            }
+            "/api/v0.2/config" => json!({ "status": "success", "result": contract, "error": null }),
+            _ => json!({ "status": "error", "result": null, "error": { "message": "Not found" } }),
+        };
+        Ok(Response::new(Body::from(response.to_string())))
+    }
+
+    /// Tests that both API versions are spoken over HTTP, with their latencies kept apart.
+    #[tokio::test]
+    async fn test_http_provider() {
+        let service = make_service_fn(|_| async { Ok::<_, Infallible>(service_fn(serve)) });
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(service);
+        let address = server.local_addr();
+        tokio::spawn(server);
+
+        let metrics = Arc::new(Metrics::new());
+        let mut config = Config::default().network;
+        config.rollup_url = Some(format!("http://{address}/"));
+        let json_rpc = HttpProvider::new(&config, metrics.clone());
+        assert_eq!(
+            json_rpc.contract_address().await.unwrap().main_contract,
+            "0x01"
+        );
+        assert_eq!(
+            json_rpc.tokens().await,
+            Err(ClientError::NetworkError(
+                "Method not found (code -32601)".to_string()
+            ))
+        );
+        assert!(matches!(
+            json_rpc
+                .account_txs(Address::zero(), PaginationQuery::latest(10))
+                .await,
+            Err(ClientError::UnsupportedMethod(_))
+        ));
+
+        config.api_version = ApiVersion::V02;
+        let rest = HttpProvider::new(&config, metrics.clone());
+        assert_eq!(
+            rest.contract_address().await.unwrap().main_contract,
+            format!("{:?}", Address::repeat_byte(1))
+        );
+        assert_eq!(
+            rest.tokens().await,
+            Err(ClientError::NetworkError("Not found".to_string()))
+        );
+
+        let histograms = metrics.histograms();
+        for api in ["v0.1", "v0.2"] {
+            let key = MetricKey::new(
+                PROVIDER_LATENCY_METRIC,
+                &[("api", api), ("method", "contract_address")],
+            );
+            assert_eq!(histograms[&key].count, 1, "{api}");
+        }
+    }
+}
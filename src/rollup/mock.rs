@@ -0,0 +1,503 @@
+//! In-process stand-in for the rollup server, behind dry runs and the tests of the engine.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use num::BigUint;
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use super::network::Network;
+use super::provider::{ClientError, Provider, ProviderMethod, ResponseResult};
+use super::types::pagination::{PaginationInfo, TxHistoryCursor};
+use super::types::tx::{PackedEthSignature, ZkSyncTx};
+use super::types::{
+    AccountId, AccountInfo, AccountTx, Address, BatchFee, BlockInfo, BlockNumber, ContractAddress,
+    EthOpInfo, Fee, OutputFeeType, Paginated, PaginationQuery, Token, TokenId, TokenKind,
+    TokenLike, Tokens, TransactionInfo, TxFeeTypes, TxHash,
+};
+use crate::rng::StreamRng;
+use crate::scenario::reconnect_storm::Reconnectable;
+
+/// Latency and failure rate of a provider method.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MethodBehavior {
+    pub latency: Duration,
+    /// Share of the calls failing with a network error, from 0 to 1.
+    pub failure_rate: f64,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    calls: HashMap<ProviderMethod, usize>,
+    scripts: HashMap<ProviderMethod, VecDeque<ResponseResult<Value>>>,
+    accounts: HashMap<Address, AccountId>,
+    submitted: Vec<(TxHash, ZkSyncTx)>,
+}
+
+/// `Provider` answering every request in-process, for dry runs and tests of the engine
+/// without a node.
+///
+/// Without further setup every account exists, every fee is `fee`, and every submitted
+/// transaction is accepted, immediately verified and listed in its sender's history. Methods can be slowed down and made
+/// to fail at random, and responses queued with [`MockProvider::script`] are returned by
+/// the next calls of their method before falling back to the defaults.
+pub struct MockProvider {
+    network: Network,
+    tokens: Tokens,
+    fee: BigUint,
+    default_behavior: MethodBehavior,
+    behaviors: HashMap<ProviderMethod, MethodBehavior>,
+    rng: Mutex<StreamRng>,
+    state: Mutex<MockState>,
+}
+
+impl Default for MockProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockProvider {
+    pub fn new() -> Self {
+        let rbtc = Token::new(TokenId(0), Address::zero(), "RBTC", 18, TokenKind::None);
+        Self {
+            network: Network::default(),
+            tokens: Tokens::from([(rbtc.symbol.clone(), rbtc)]),
+            fee: BigUint::from(1_000u32),
+            default_behavior: MethodBehavior::default(),
+            behaviors: HashMap::new(),
+            rng: Mutex::new(StreamRng::seed_from_u64(rand::random())),
+            state: Mutex::default(),
+        }
+    }
+
+    pub fn with_network(mut self, network: Network) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Replaces the supported tokens, RBTC only by default.
+    pub fn with_tokens(mut self, tokens: impl IntoIterator<Item = Token>) -> Self {
+        self.tokens = tokens
+            .into_iter()
+            .map(|token| (token.symbol.clone(), token))
+            .collect();
+        self
+    }
+
+    /// Fee quoted for every transaction, batches pay it per transaction.
+    pub fn with_fee(mut self, fee: BigUint) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    /// Behavior of the methods without one of their own.
+    pub fn with_default_behavior(mut self, behavior: MethodBehavior) -> Self {
+        self.default_behavior = behavior;
+        self
+    }
+
+    pub fn with_behavior(mut self, method: ProviderMethod, behavior: MethodBehavior) -> Self {
+        self.behaviors.insert(method, behavior);
+        self
+    }
+
+    /// Seeds the draws of the failure rates, so failing calls are the same in every run.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Mutex::new(StreamRng::seed_from_u64(seed));
+        self
+    }
+
+    /// Queues the response of a future call of `method`, as the JSON the server would send.
+    pub fn script(&self, method: ProviderMethod, response: ResponseResult<Value>) {
+        self.state
+            .lock()
+            .unwrap()
+            .scripts
+            .entry(method)
+            .or_default()
+            .push_back(response);
+    }
+
+    /// Number of calls of `method` so far, failed ones included.
+    pub fn calls(&self, method: ProviderMethod) -> usize {
+        self.state
+            .lock()
+            .unwrap()
+            .calls
+            .get(&method)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Transactions accepted so far, in order of submission.
+    pub fn submitted(&self) -> Vec<(TxHash, ZkSyncTx)> {
+        self.state.lock().unwrap().submitted.clone()
+    }
+
+    /// Applies the behavior of `method`, returning the scripted response if one is queued.
+    async fn call(&self, method: ProviderMethod) -> ResponseResult<Option<Value>> {
+        let behavior = self
+            .behaviors
+            .get(&method)
+            .copied()
+            .unwrap_or(self.default_behavior);
+        let scripted = {
+            let mut state = self.state.lock().unwrap();
+            *state.calls.entry(method).or_default() += 1;
+            state.scripts.get_mut(&method).and_then(VecDeque::pop_front)
+        };
+        if !behavior.latency.is_zero() {
+            tokio::time::sleep(behavior.latency).await;
+        }
+        if let Some(response) = scripted {
+            return response.map(Some);
+        }
+        if behavior.failure_rate > 0.0
+            && self
+                .rng
+                .lock()
+                .unwrap()
+                .gen_bool(behavior.failure_rate.min(1.0))
+        {
+            return Err(ClientError::NetworkError(format!(
+                "injected {} failure",
+                method.name()
+            )));
+        }
+        Ok(None)
+    }
+
+    /// Decodes a scripted response into the result type of the method.
+    fn decode<T: DeserializeOwned>(value: Value) -> ResponseResult<T> {
+        serde_json::from_value(value).map_err(|err| ClientError::MalformedResponse(err.to_string()))
+    }
+
+    fn verified_block() -> Option<BlockInfo> {
+        Some(BlockInfo {
+            block_number: 1,
+            committed: true,
+            verified: true,
+        })
+    }
+
+    /// Account that signed `tx`, forced exits only name their initiator by id.
+    fn sender(state: &MockState, tx: &ZkSyncTx) -> Option<Address> {
+        match tx {
+            ZkSyncTx::Transfer(tx) => Some(tx.from),
+            ZkSyncTx::Withdraw(tx) => Some(tx.from),
+            ZkSyncTx::ChangePubKey(tx) => Some(tx.account),
+            ZkSyncTx::MintNFT(tx) => Some(tx.creator_address),
+            ZkSyncTx::WithdrawNFT(tx) => Some(tx.from),
+            ZkSyncTx::ForcedExit(tx) => state
+                .accounts
+                .iter()
+                .find(|(_, id)| **id == tx.initiator_account_id)
+                .map(|(address, _)| *address),
+        }
+    }
+
+    fn accept(&self, tx: ZkSyncTx) -> ResponseResult<TxHash> {
+        let bytes = serde_json::to_vec(&tx).map_err(|_| ClientError::IncorrectInput)?;
+        let tx_hash = TxHash {
+            data: Sha256::digest(bytes).into(),
+        };
+        self.state.lock().unwrap().submitted.push((tx_hash, tx));
+        Ok(tx_hash)
+    }
+}
+
+#[async_trait]
+impl Provider for MockProvider {
+    async fn account_info(&self, address: Address) -> ResponseResult<AccountInfo> {
+        if let Some(value) = self.call(ProviderMethod::AccountInfo).await? {
+            return Self::decode(value);
+        }
+        let id = {
+            let mut state = self.state.lock().unwrap();
+            let next_id = AccountId(state.accounts.len() as u32 + 1);
+            *state.accounts.entry(address).or_insert(next_id)
+        };
+        Ok(AccountInfo {
+            address,
+            id: Some(id),
+            depositing: Default::default(),
+            committed: Default::default(),
+            verified: Default::default(),
+        })
+    }
+
+    async fn account_txs(
+        &self,
+        address: Address,
+        query: PaginationQuery,
+    ) -> ResponseResult<Paginated<AccountTx>> {
+        if let Some(value) = self.call(ProviderMethod::AccountTxs).await? {
+            return Self::decode(value);
+        }
+        let state = self.state.lock().unwrap();
+        // Newest first, the order of the `older` direction.
+        let history: Vec<TxHash> = state
+            .submitted
+            .iter()
+            .rev()
+            .filter(|(_, tx)| Self::sender(&state, tx) == Some(address))
+            .map(|(tx_hash, _)| *tx_hash)
+            .collect();
+        let start = match query.from {
+            TxHistoryCursor::Latest => 0,
+            TxHistoryCursor::Tx(cursor) => history
+                .iter()
+                .position(|tx_hash| *tx_hash == cursor)
+                .unwrap_or(history.len()),
+        };
+        let list = history
+            .iter()
+            .skip(start)
+            .take(query.limit as usize)
+            .map(|tx_hash| AccountTx {
+                tx_hash: *tx_hash,
+                block_number: Self::verified_block()
+                    .map(|block| BlockNumber(block.block_number as u32)),
+                status: "finalized".to_string(),
+                fail_reason: None,
+                created_at: None,
+            })
+            .collect();
+        Ok(Paginated {
+            list,
+            pagination: PaginationInfo {
+                from: query.from,
+                limit: query.limit,
+                direction: query.direction,
+                count: history.len() as u32,
+            },
+        })
+    }
+
+    async fn tokens(&self) -> ResponseResult<Tokens> {
+        if let Some(value) = self.call(ProviderMethod::Tokens).await? {
+            return Self::decode(value);
+        }
+        Ok(self.tokens.clone())
+    }
+
+    async fn tx_info(&self, tx_hash: TxHash) -> ResponseResult<TransactionInfo> {
+        if let Some(value) = self.call(ProviderMethod::TxInfo).await? {
+            return Self::decode(value);
+        }
+        let known = self
+            .state
+            .lock()
+            .unwrap()
+            .submitted
+            .iter()
+            .any(|(submitted, _)| *submitted == tx_hash);
+        Ok(TransactionInfo {
+            executed: known,
+            success: known.then_some(true),
+            fail_reason: None,
+            block: known.then(Self::verified_block).flatten(),
+        })
+    }
+
+    async fn get_tx_fee(
+        &self,
+        tx_type: TxFeeTypes,
+        _address: Address,
+        _token: impl Into<TokenLike> + Send + 'async_trait,
+    ) -> ResponseResult<Fee> {
+        if let Some(value) = self.call(ProviderMethod::GetTxFee).await? {
+            return Self::decode(value);
+        }
+        let fee_type = match tx_type {
+            TxFeeTypes::Withdraw => OutputFeeType::Withdraw,
+            TxFeeTypes::FastWithdraw => OutputFeeType::FastWithdraw,
+            TxFeeTypes::Transfer => OutputFeeType::Transfer,
+            TxFeeTypes::ChangePubKey(auth) => OutputFeeType::ChangePubKey(auth),
+            TxFeeTypes::MintNFT => OutputFeeType::MintNFT,
+            TxFeeTypes::WithdrawNFT => OutputFeeType::WithdrawNFT,
+            TxFeeTypes::FastWithdrawNFT => OutputFeeType::FastWithdrawNFT,
+        };
+        Ok(Fee {
+            fee_type,
+            gas_tx_amount: BigUint::default(),
+            gas_price_wei: BigUint::default(),
+            gas_fee: self.fee.clone(),
+            zkp_fee: BigUint::default(),
+            total_fee: self.fee.clone(),
+        })
+    }
+
+    async fn get_txs_batch_fee(
+        &self,
+        tx_types: Vec<TxFeeTypes>,
+        _addresses: Vec<Address>,
+        _token: impl Into<TokenLike> + Send + 'async_trait,
+    ) -> ResponseResult<BigUint> {
+        if let Some(value) = self.call(ProviderMethod::GetTxsBatchFee).await? {
+            return Ok(Self::decode::<BatchFee>(value)?.total_fee);
+        }
+        Ok(&self.fee * tx_types.len())
+    }
+
+    async fn ethop_info(&self, _serial_id: u32) -> ResponseResult<EthOpInfo> {
+        if let Some(value) = self.call(ProviderMethod::EthOpInfo).await? {
+            return Self::decode(value);
+        }
+        Ok(EthOpInfo {
+            executed: true,
+            block: Self::verified_block(),
+        })
+    }
+
+    async fn get_eth_tx_for_withdrawal(
+        &self,
+        withdrawal_hash: TxHash,
+    ) -> ResponseResult<Option<String>> {
+        if let Some(value) = self.call(ProviderMethod::GetEthTxForWithdrawal).await? {
+            return Self::decode(value);
+        }
+        Ok(Some(format!("0x{}", hex::encode(withdrawal_hash.data))))
+    }
+
+    async fn contract_address(&self) -> ResponseResult<ContractAddress> {
+        if let Some(value) = self.call(ProviderMethod::ContractAddress).await? {
+            return Self::decode(value);
+        }
+        Ok(ContractAddress {
+            main_contract: format!("{:?}", Address::zero()),
+            gov_contract: format!("{:?}", Address::zero()),
+        })
+    }
+
+    async fn send_tx(
+        &self,
+        tx: ZkSyncTx,
+        _eth_signature: Option<PackedEthSignature>,
+    ) -> ResponseResult<TxHash> {
+        if let Some(value) = self.call(ProviderMethod::SendTx).await? {
+            return Self::decode(value);
+        }
+        self.accept(tx)
+    }
+
+    async fn send_txs_batch(
+        &self,
+        txs_signed: Vec<(ZkSyncTx, Option<PackedEthSignature>)>,
+        _eth_signature: Option<PackedEthSignature>,
+    ) -> ResponseResult<Vec<TxHash>> {
+        if let Some(value) = self.call(ProviderMethod::SendTxsBatch).await? {
+            return Self::decode(value);
+        }
+        txs_signed
+            .into_iter()
+            .map(|(tx, _)| self.accept(tx))
+            .collect()
+    }
+
+    fn network(&self) -> Network {
+        self.network
+    }
+}
+
+#[async_trait]
+impl Reconnectable for MockProvider {
+    fn name(&self) -> String {
+        "mock rollup".to_string()
+    }
+
+    /// Nothing to drop, requests never leave the process.
+    async fn disconnect(&self) {}
+
+    async fn reconnect(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use ethers::signers::LocalWallet;
+    use serde_json::json;
+
+    use super::*;
+    use crate::rollup::types::tx::TimeRange;
+    use crate::rollup::types::Nonce;
+    use crate::wallet::Wallet;
+
+    /// Tests default answers, scripted responses taking precedence, latencies and injected failures.
+    #[tokio::test]
+    async fn test_mock_provider() {
+        let provider = MockProvider::new()
+            .with_seed(7)
+            .with_behavior(
+                ProviderMethod::SendTx,
+                MethodBehavior {
+                    latency: Duration::from_millis(20),
+                    failure_rate: 0.0,
+                },
+            )
+            .with_behavior(
+                ProviderMethod::EthOpInfo,
+                MethodBehavior {
+                    latency: Duration::ZERO,
+                    failure_rate: 1.0,
+                },
+            );
+        let mut wallet = Wallet::new(LocalWallet::from_bytes(&[1; 32]).unwrap())
+            .await
+            .unwrap();
+        let info = provider.account_info(wallet.address()).await.unwrap();
+        wallet.set_account_id(info.id.unwrap());
+        let rbtc = provider.tokens().await.unwrap()["RBTC"].clone();
+        let fee = provider
+            .get_tx_fee(TxFeeTypes::Transfer, wallet.address(), &rbtc)
+            .await
+            .unwrap()
+            .total_fee;
+        let (tx, eth_signature) = wallet
+            .sign_transfer(
+                Address::repeat_byte(2),
+                &rbtc,
+                BigUint::from(10u32),
+                fee,
+                Nonce(0),
+                TimeRange::default(),
+            )
+            .await
+            .unwrap();
+
+        provider.script(
+            ProviderMethod::SendTx,
+            Err(ClientError::NetworkError("scripted".to_string())),
+        );
+        assert_eq!(
+            provider.send_tx(tx.clone(), eth_signature).await,
+            Err(ClientError::NetworkError("scripted".to_string()))
+        );
+        let started = Instant::now();
+        let tx_hash = provider.send_tx(tx.clone(), eth_signature).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(20));
+        assert_eq!(provider.submitted(), vec![(tx_hash, tx)]);
+        assert!(provider.tx_info(tx_hash).await.unwrap().is_verified());
+        assert!(!provider.tx_info(TxHash::default()).await.unwrap().executed);
+        assert_eq!(provider.calls(ProviderMethod::SendTx), 2);
+
+        provider.script(ProviderMethod::TxInfo, Ok(json!({ "executed": "yes" })));
+        assert!(matches!(
+            provider.tx_info(tx_hash).await,
+            Err(ClientError::MalformedResponse(_))
+        ));
+        assert!(matches!(
+            provider.ethop_info(1).await,
+            Err(ClientError::NetworkError(_))
+        ));
+    }
+}
@@ -1,3 +1,14 @@
-
+pub mod adapters;
+pub mod confirmation;
+pub mod events;
+pub mod failover;
+pub mod fee_cache;
+pub mod http;
+pub mod mock;
+pub mod network;
+pub mod pending_store;
 pub mod provider;
-pub mod types;
\ No newline at end of file
+pub mod retry;
+pub mod timeouts;
+pub mod tokens;
+pub mod types;
@@ -0,0 +1,106 @@
+//! Rootstock networks the rollup is deployed on.
+//!
+//! A network is selected with `chain` in the `[network]` section or with `--network`. The
+//! presets bring the chain id and the endpoints of their deployment, which `rpc_url` and
+//! `rollup_url` override; a custom network has no endpoints of its own.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+#[error("Unknown network `{0}`, expected mainnet, testnet, localhost or custom:<chain id>")]
+pub struct UnknownNetwork(pub String);
+
+/// Network the simulated traffic is sent to, written as `mainnet`, `testnet`, `localhost`
+/// or `custom:<chain id>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Network {
+    /// RSK mainnet.
+    Mainnet,
+    /// RSK testnet.
+    Testnet,
+    /// Local regtest node and rollup server.
+    #[default]
+    Localhost,
+    /// Any other deployment, reached through the configured endpoints.
+    Custom { chain_id: u64 },
+}
+
+impl Network {
+    pub const PRESETS: [Network; 3] = [Network::Mainnet, Network::Testnet, Network::Localhost];
+
+    pub fn chain_id(self) -> u64 {
+        match self {
+            Network::Mainnet => 30,
+            Network::Testnet => 31,
+            Network::Localhost => 33,
+            Network::Custom { chain_id } => chain_id,
+        }
+    }
+
+    /// JSON-RPC endpoint of a Rootstock node, none for a custom network.
+    pub fn rpc_url(self) -> Option<&'static str> {
+        match self {
+            Network::Mainnet => Some("https://public-node.rsk.co"),
+            Network::Testnet => Some("https://public-node.testnet.rsk.co"),
+            Network::Localhost => Some("http://127.0.0.1:4444"),
+            Network::Custom { .. } => None,
+        }
+    }
+
+    /// API of the rollup server, none for a custom network.
+    pub fn rollup_url(self) -> Option<&'static str> {
+        match self {
+            Network::Mainnet => Some("https://api.rollup.rifcomputing.net"),
+            Network::Testnet => Some("https://api.testnet.rollup.rifcomputing.net"),
+            Network::Localhost => Some("http://127.0.0.1:5454"),
+            Network::Custom { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Network::Mainnet => f.write_str("mainnet"),
+            Network::Testnet => f.write_str("testnet"),
+            Network::Localhost => f.write_str("localhost"),
+            Network::Custom { chain_id } => write!(f, "custom:{chain_id}"),
+        }
+    }
+}
+
+impl FromStr for Network {
+    type Err = UnknownNetwork;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(preset) = Self::PRESETS
+            .into_iter()
+            .find(|preset| preset.to_string().eq_ignore_ascii_case(s))
+        {
+            return Ok(preset);
+        }
+        s.strip_prefix("custom:")
+            .and_then(|chain_id| chain_id.parse().ok())
+            .map(|chain_id| Network::Custom { chain_id })
+            .ok_or_else(|| UnknownNetwork(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for Network {
+    type Error = UnknownNetwork;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Network> for String {
+    fn from(network: Network) -> Self {
+        network.to_string()
+    }
+}
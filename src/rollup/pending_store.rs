@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::confirmation::TrackedOp;
+use crate::paths;
+use crate::report::now_ms;
+
+/// Journal entries at which the store is compacted even when most operations are still pending.
+const COMPACTION_MIN_ENTRIES: usize = 1_024;
+
+#[derive(Debug, Error)]
+pub enum PendingStoreError {
+    #[error("Unable to access pending operation store: {0}")]
+    Io(#[from] io::Error),
+    #[error("Malformed pending operation store entry on line {0}: {1}")]
+    Malformed(usize, String),
+}
+
+/// Operation submitted but not yet confirmed, as kept on disk.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredOp {
+    pub op: TrackedOp,
+    pub tx_type: String,
+    /// Wall clock time of the submission, which survives a restart unlike an `Instant`.
+    pub submitted_at_ms: u64,
+}
+
+impl StoredOp {
+    pub fn new(op: TrackedOp, tx_type: &str, submitted: Instant) -> Self {
+        Self {
+            op,
+            tx_type: tx_type.to_string(),
+            submitted_at_ms: now_ms().saturating_sub(submitted.elapsed().as_millis()) as u64,
+        }
+    }
+
+    /// Time since the operation was submitted.
+    pub fn age(&self) -> Duration {
+        let age_ms = (now_ms() as u64).saturating_sub(self.submitted_at_ms);
+        Duration::from_millis(age_ms)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+enum JournalEntry {
+    Track(StoredOp),
+    Done { op: TrackedOp },
+}
+
+/// Submitted operations awaiting their confirmation, kept on disk so a restarted run keeps
+/// tracking them.
+///
+/// Every change is appended to a JSON lines journal and flushed right away, so at most the
+/// entry being written when the simulator died is lost; a torn last line is ignored when the
+/// store is opened again. The journal is rewritten with only the pending operations when it
+/// is opened and once finished operations make up most of it.
+pub struct PendingStore {
+    path: PathBuf,
+    file: File,
+    ops: HashMap<TrackedOp, StoredOp>,
+    entries: usize,
+}
+
+impl PendingStore {
+    /// Opens the store, loading the operations left pending by a previous run.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, PendingStoreError> {
+        let path = path.as_ref().to_path_buf();
+        let ops = if path.exists() {
+            Self::replay(&path)?
+        } else {
+            HashMap::new()
+        };
+        let mut store = Self {
+            file: paths::open_private(&path, true)?,
+            path,
+            ops,
+            entries: 0,
+        };
+        store.compact()?;
+        Ok(store)
+    }
+
+    fn replay(path: &Path) -> Result<HashMap<TrackedOp, StoredOp>, PendingStoreError> {
+        let lines = BufReader::new(File::open(path)?)
+            .lines()
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut ops = HashMap::new();
+        for (index, line) in lines.iter().enumerate() {
+            match serde_json::from_str(line) {
+                Ok(JournalEntry::Track(op)) => {
+                    ops.insert(op.op, op);
+                }
+                Ok(JournalEntry::Done { op }) => {
+                    ops.remove(&op);
+                }
+                // Written when the previous run died.
+                Err(_) if index + 1 == lines.len() => {}
+                Err(err) => return Err(PendingStoreError::Malformed(index + 1, err.to_string())),
+            }
+        }
+        Ok(ops)
+    }
+
+    /// Operations still pending, oldest first.
+    pub fn pending(&self) -> Vec<StoredOp> {
+        let mut ops: Vec<StoredOp> = self.ops.values().cloned().collect();
+        ops.sort_by_key(|op| op.submitted_at_ms);
+        ops
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    pub fn track(&mut self, op: StoredOp) -> Result<(), PendingStoreError> {
+        self.append(&JournalEntry::Track(op.clone()))?;
+        self.ops.insert(op.op, op);
+        Ok(())
+    }
+
+    /// Forgets an operation that was confirmed, rejected or given up on.
+    pub fn complete(&mut self, op: &TrackedOp) -> Result<(), PendingStoreError> {
+        if self.ops.remove(op).is_none() {
+            return Ok(());
+        }
+        self.append(&JournalEntry::Done { op: *op })?;
+        if self.entries >= COMPACTION_MIN_ENTRIES && self.entries > 2 * self.ops.len() {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    fn append(&mut self, entry: &JournalEntry) -> Result<(), PendingStoreError> {
+        let line = serde_json::to_string(entry)
+            .map_err(|err| PendingStoreError::Malformed(self.entries + 1, err.to_string()))?;
+        writeln!(self.file, "{}", line)?;
+        self.file.flush()?;
+        self.entries += 1;
+        Ok(())
+    }
+
+    /// Rewrites the journal with the pending operations only, replacing the old one at once
+    /// so a crash while compacting leaves either of them intact.
+    fn compact(&mut self) -> Result<(), PendingStoreError> {
+        let mut compacted = self.path.clone().into_os_string();
+        compacted.push(".compacting");
+        let compacted = PathBuf::from(compacted);
+        let mut file = paths::open_private(&compacted, false)?;
+        let pending = self.pending();
+        for op in &pending {
+            let line = serde_json::to_string(&JournalEntry::Track(op.clone()))
+                .map_err(|err| PendingStoreError::Malformed(0, err.to_string()))?;
+            writeln!(file, "{}", line)?;
+        }
+        file.sync_all()?;
+        fs::rename(&compacted, &self.path)?;
+        self.file = paths::open_private(&self.path, true)?;
+        self.entries = pending.len();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rollup::types::TxHash;
+
+    /// Tests that pending operations survive reopening, torn last lines and compaction.
+    #[test]
+    fn test_pending_store() {
+        let path = std::env::temp_dir().join(format!("pending-test-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let tx = |byte: u8| TrackedOp::Tx(TxHash { data: [byte; 32] });
+        let now = Instant::now();
+
+        let mut store = PendingStore::open(&path).unwrap();
+        store.track(StoredOp::new(tx(1), "transfer", now)).unwrap();
+        store
+            .track(StoredOp::new(TrackedOp::PriorityOp(7), "deposit", now))
+            .unwrap();
+        store.track(StoredOp::new(tx(2), "withdraw", now)).unwrap();
+        store.complete(&tx(1)).unwrap();
+        store.complete(&tx(9)).unwrap();
+        drop(store);
+
+        // The simulator died while writing an entry.
+        let mut file = paths::open_private(&path, true).unwrap();
+        write!(file, "{{\"action\":\"done\",\"op\":").unwrap();
+        drop(file);
+
+        let mut store = PendingStore::open(&path).unwrap();
+        let ops: Vec<TrackedOp> = store.pending().iter().map(|op| op.op).collect();
+        assert_eq!(ops.len(), 2);
+        assert!(ops.contains(&TrackedOp::PriorityOp(7)) && ops.contains(&tx(2)));
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
+
+        for byte in 10..=255u8 {
+            store
+                .track(StoredOp::new(tx(byte), "transfer", now))
+                .unwrap();
+        }
+        for _ in 0..4 {
+            for byte in 10..=255u8 {
+                store.complete(&tx(byte)).unwrap();
+                store
+                    .track(StoredOp::new(tx(byte), "transfer", now))
+                    .unwrap();
+            }
+        }
+        assert_eq!(store.len(), 248);
+        let lines = fs::read_to_string(&path).unwrap().lines().count();
+        assert_eq!(lines, store.entries);
+        assert!(lines < COMPACTION_MIN_ENTRIES, "{lines}");
+        drop(store);
+        assert_eq!(PendingStore::open(&path).unwrap().len(), 248);
+
+        fs::write(&path, "not json\n{}\n").unwrap();
+        assert!(matches!(
+            PendingStore::open(&path),
+            Err(PendingStoreError::Malformed(1, _))
+        ));
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -1,7 +1,16 @@
 use async_trait::async_trait;
 use ethers::types::Address;
+use num::BigUint;
+use serde::Deserialize;
 use thiserror::Error;
 
+use super::network::Network;
+use super::types::tx::{PackedEthSignature, ZkSyncTx};
+use super::types::{
+    AccountInfo, AccountTx, ContractAddress, EthOpInfo, Fee, Paginated, PaginationQuery, TokenLike,
+    Tokens, TransactionInfo, TxFeeTypes, TxHash,
+};
+
 #[derive(Debug, Error, PartialEq)]
 pub enum ClientError {
     #[error("Network '{0}' is not supported")]
@@ -17,10 +26,12 @@ pub enum ClientError {
     IncorrectCredentials,
     #[error("Seed too short, must be at least 32 bytes long")]
     SeedTooShort,
-    #[error("Token is not supported by zkSync")]
-    UnknownToken,
+    #[error("Token(s) not supported by zkSync: {0}")]
+    UnknownToken(String),
     #[error("Incorrect address")]
     IncorrectAddress,
+    #[error("Address {0:?} is on the denylist")]
+    DeniedAddress(Address),
 
     #[error("Operation timeout")]
     OperationTimeout,
@@ -28,8 +39,8 @@ pub enum ClientError {
     PollingIntervalIsTooSmall,
 
     #[error("Signing error: {0}")]
-    // SigningError(SignerError),
-    // #[error("Missing required field for a transaction: {0}")]
+    SigningError(String),
+    #[error("Missing required field for a transaction: {0}")]
     MissingRequiredField(String),
 
     #[error("Rootstock private key was not provided for this wallet")]
@@ -41,13 +52,80 @@ pub enum ClientError {
     #[error("Provided function arguments are incorrect")]
     IncorrectInput,
 
+    #[error("Method '{0}' is not supported by the selected API version")]
+    UnsupportedMethod(String),
+
     #[error("Other")]
     Other,
 }
 
+impl ClientError {
+    /// Whether the error tells of an overloaded or unreachable node rather than a refused
+    /// request, so sending slower may help.
+    pub fn is_backpressure(&self) -> bool {
+        matches!(
+            self,
+            ClientError::NetworkError(_)
+                | ClientError::OperationTimeout
+                | ClientError::MalformedResponse(_)
+        )
+    }
+
+    /// Whether `method` may succeed when called again after failing with the error.
+    ///
+    /// A submission that timed out may have been accepted all the same, sending it again
+    /// would be refused as a duplicate, so only the ones that never got through are retried.
+    pub fn is_retryable(&self, method: ProviderMethod) -> bool {
+        match self {
+            ClientError::OperationTimeout => !method.is_submission(),
+            ClientError::NetworkError(_) | ClientError::MalformedResponse(_) => true,
+            _ => false,
+        }
+    }
+}
 
 pub type ResponseResult<T> = Result<T, ClientError>;
 
+/// Methods of the `Provider` trait that result in a request to the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderMethod {
+    AccountInfo,
+    AccountTxs,
+    Tokens,
+    TxInfo,
+    GetTxFee,
+    GetTxsBatchFee,
+    EthOpInfo,
+    GetEthTxForWithdrawal,
+    ContractAddress,
+    SendTx,
+    SendTxsBatch,
+}
+
+impl ProviderMethod {
+    pub fn name(self) -> &'static str {
+        match self {
+            ProviderMethod::AccountInfo => "account_info",
+            ProviderMethod::AccountTxs => "account_txs",
+            ProviderMethod::Tokens => "tokens",
+            ProviderMethod::TxInfo => "tx_info",
+            ProviderMethod::GetTxFee => "get_tx_fee",
+            ProviderMethod::GetTxsBatchFee => "get_txs_batch_fee",
+            ProviderMethod::EthOpInfo => "ethop_info",
+            ProviderMethod::GetEthTxForWithdrawal => "get_eth_tx_for_withdrawal",
+            ProviderMethod::ContractAddress => "contract_address",
+            ProviderMethod::SendTx => "send_tx",
+            ProviderMethod::SendTxsBatch => "send_txs_batch",
+        }
+    }
+
+    /// Whether the method submits transactions rather than reading state.
+    pub fn is_submission(self) -> bool {
+        matches!(self, ProviderMethod::SendTx | ProviderMethod::SendTxsBatch)
+    }
+}
+
 #[async_trait]
 /// `Provider` used to connect to zkSync network in order to send transactions
 /// and retrieve some information from the server about
@@ -56,6 +134,13 @@ pub trait Provider {
     /// Requests and returns information about a ZKSync account given its address.
     async fn account_info(&self, address: Address) -> ResponseResult<AccountInfo>;
 
+    /// Requests a page of the transaction history of a ZKSync account.
+    async fn account_txs(
+        &self,
+        address: Address,
+        query: PaginationQuery,
+    ) -> ResponseResult<Paginated<AccountTx>>;
+
     /// Requests and returns a list of tokens supported by zkSync.
     async fn tokens(&self) -> ResponseResult<Tokens>;
 
@@ -109,4 +194,3 @@ pub trait Provider {
     /// Type of network this provider is allowing access to.
     fn network(&self) -> Network;
 }
-
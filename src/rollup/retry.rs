@@ -0,0 +1,342 @@
+//! `Provider` calling again the requests that failed with a transient error.
+//!
+//! Long simulations see the occasional dropped connection or timed out request. Without
+//! retries each of them aborts a pipeline step and shows up in the report as a failed
+//! transaction, although the server would have accepted it a moment later. Which errors
+//! are worth another attempt is decided by [`ClientError::is_retryable`].
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use num::BigUint;
+use rand::Rng;
+use serde::Deserialize;
+use tracing::debug;
+
+use super::network::Network;
+use super::provider::{Provider, ProviderMethod, ResponseResult};
+use super::types::tx::{PackedEthSignature, ZkSyncTx};
+use super::types::{
+    AccountInfo, AccountTx, Address, ContractAddress, EthOpInfo, Fee, Paginated, PaginationQuery,
+    TokenLike, Tokens, TransactionInfo, TxFeeTypes, TxHash,
+};
+use crate::metrics::Metrics;
+use crate::scenario::reconnect_storm::Reconnectable;
+
+/// Counter of provider calls made again after a transient error, labelled by method.
+pub const RETRIES_METRIC: &str = "provider_retries_total";
+/// Counter of provider calls still failing once all their attempts were used, by method.
+pub const RETRIES_EXHAUSTED_METRIC: &str = "provider_retries_exhausted_total";
+
+/// Retries of transient provider errors, configured in the `[network.retry]` section.
+///
+/// The n-th retry waits `base_delay_ms * 2^(n-1)`, at most `max_delay_ms`, shortened by
+/// a random share of up to `jitter_percent` so the retries of concurrent users spread out.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RetryConfig {
+    /// Attempts per call, the first one included; 1 disables retries.
+    #[serde(default = "RetryConfig::default_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default = "RetryConfig::default_base_delay_ms")]
+    pub base_delay_ms: u64,
+    #[serde(default = "RetryConfig::default_max_delay_ms")]
+    pub max_delay_ms: u64,
+    #[serde(default = "RetryConfig::default_jitter_percent")]
+    pub jitter_percent: u64,
+}
+
+impl RetryConfig {
+    fn default_max_attempts() -> u32 {
+        3
+    }
+
+    fn default_base_delay_ms() -> u64 {
+        200
+    }
+
+    fn default_max_delay_ms() -> u64 {
+        5_000
+    }
+
+    fn default_jitter_percent() -> u64 {
+        20
+    }
+
+    /// Delay before the `retry`-th retry of a call, counting from 1.
+    pub fn delay<R: Rng>(&self, retry: u32, rng: &mut R) -> Duration {
+        let exponential = self.base_delay_ms.saturating_mul(
+            1u64.checked_shl(retry.saturating_sub(1))
+                .unwrap_or(u64::MAX),
+        );
+        let delay = exponential.min(self.max_delay_ms);
+        let jitter = delay * self.jitter_percent.min(100) / 100;
+        let jitter = if jitter == 0 {
+            0
+        } else {
+            rng.gen_range(0..=jitter)
+        };
+        Duration::from_millis(delay - jitter)
+    }
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: Self::default_max_attempts(),
+            base_delay_ms: Self::default_base_delay_ms(),
+            max_delay_ms: Self::default_max_delay_ms(),
+            jitter_percent: Self::default_jitter_percent(),
+        }
+    }
+}
+
+/// `Provider` retrying the calls of the wrapped one that fail with a retryable error.
+pub struct RetryProvider<P> {
+    inner: P,
+    config: RetryConfig,
+    metrics: Arc<Metrics>,
+}
+
+impl<P: Provider + Sync> RetryProvider<P> {
+    pub fn new(inner: P, config: RetryConfig, metrics: Arc<Metrics>) -> Self {
+        Self {
+            inner,
+            config,
+            metrics,
+        }
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    async fn retry<'a, T, F, Fut>(&'a self, method: ProviderMethod, call: F) -> ResponseResult<T>
+    where
+        F: Fn(&'a P) -> Fut,
+        Fut: Future<Output = ResponseResult<T>> + 'a,
+    {
+        let mut attempt = 1;
+        loop {
+            let err = match call(&self.inner).await {
+                Err(err) if err.is_retryable(method) => err,
+                result => return result,
+            };
+            if attempt >= self.config.max_attempts {
+                if attempt > 1 {
+                    self.metrics
+                        .increment(RETRIES_EXHAUSTED_METRIC, &[("method", method.name())]);
+                }
+                return Err(err);
+            }
+            let delay = self.config.delay(attempt, &mut rand::thread_rng());
+            debug!(
+                "Retrying {} in {:?} after attempt {} failed: {}",
+                method.name(),
+                delay,
+                attempt,
+                err
+            );
+            self.metrics
+                .increment(RETRIES_METRIC, &[("method", method.name())]);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[async_trait]
+impl<P: Provider + Send + Sync> Provider for RetryProvider<P> {
+    async fn account_info(&self, address: Address) -> ResponseResult<AccountInfo> {
+        self.retry(ProviderMethod::AccountInfo, |provider| {
+            provider.account_info(address)
+        })
+        .await
+    }
+
+    async fn account_txs(
+        &self,
+        address: Address,
+        query: PaginationQuery,
+    ) -> ResponseResult<Paginated<AccountTx>> {
+        self.retry(ProviderMethod::AccountTxs, |provider| {
+            provider.account_txs(address, query)
+        })
+        .await
+    }
+
+    async fn tokens(&self) -> ResponseResult<Tokens> {
+        self.retry(ProviderMethod::Tokens, |provider| provider.tokens())
+            .await
+    }
+
+    async fn tx_info(&self, tx_hash: TxHash) -> ResponseResult<TransactionInfo> {
+        self.retry(ProviderMethod::TxInfo, |provider| provider.tx_info(tx_hash))
+            .await
+    }
+
+    async fn get_tx_fee(
+        &self,
+        tx_type: TxFeeTypes,
+        address: Address,
+        token: impl Into<TokenLike> + Send + 'async_trait,
+    ) -> ResponseResult<Fee> {
+        let token = token.into();
+        self.retry(ProviderMethod::GetTxFee, |provider| {
+            provider.get_tx_fee(tx_type, address, token.clone())
+        })
+        .await
+    }
+
+    async fn get_txs_batch_fee(
+        &self,
+        tx_types: Vec<TxFeeTypes>,
+        addresses: Vec<Address>,
+        token: impl Into<TokenLike> + Send + 'async_trait,
+    ) -> ResponseResult<BigUint> {
+        let token = token.into();
+        self.retry(ProviderMethod::GetTxsBatchFee, |provider| {
+            provider.get_txs_batch_fee(tx_types.clone(), addresses.clone(), token.clone())
+        })
+        .await
+    }
+
+    async fn ethop_info(&self, serial_id: u32) -> ResponseResult<EthOpInfo> {
+        self.retry(ProviderMethod::EthOpInfo, |provider| {
+            provider.ethop_info(serial_id)
+        })
+        .await
+    }
+
+    async fn get_eth_tx_for_withdrawal(
+        &self,
+        withdrawal_hash: TxHash,
+    ) -> ResponseResult<Option<String>> {
+        self.retry(ProviderMethod::GetEthTxForWithdrawal, |provider| {
+            provider.get_eth_tx_for_withdrawal(withdrawal_hash)
+        })
+        .await
+    }
+
+    async fn contract_address(&self) -> ResponseResult<ContractAddress> {
+        self.retry(ProviderMethod::ContractAddress, |provider| {
+            provider.contract_address()
+        })
+        .await
+    }
+
+    async fn send_tx(
+        &self,
+        tx: ZkSyncTx,
+        eth_signature: Option<PackedEthSignature>,
+    ) -> ResponseResult<TxHash> {
+        self.retry(ProviderMethod::SendTx, |provider| {
+            provider.send_tx(tx.clone(), eth_signature)
+        })
+        .await
+    }
+
+    async fn send_txs_batch(
+        &self,
+        txs_signed: Vec<(ZkSyncTx, Option<PackedEthSignature>)>,
+        eth_signature: Option<PackedEthSignature>,
+    ) -> ResponseResult<Vec<TxHash>> {
+        self.retry(ProviderMethod::SendTxsBatch, |provider| {
+            provider.send_txs_batch(txs_signed.clone(), eth_signature)
+        })
+        .await
+    }
+
+    fn network(&self) -> Network {
+        self.inner.network()
+    }
+}
+
+#[async_trait]
+impl<P: Reconnectable> Reconnectable for RetryProvider<P> {
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    async fn disconnect(&self) {
+        self.inner.disconnect().await
+    }
+
+    async fn reconnect(&self) -> Result<(), String> {
+        self.inner.reconnect().await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::rollup::mock::MockProvider;
+    use crate::rollup::provider::ClientError;
+
+    /// Tests the backoff delays, and that transient errors are retried up to `max_attempts`
+    /// while refused calls and timed out submissions are returned at once.
+    #[tokio::test]
+    async fn test_retry_provider() {
+        let config = RetryConfig {
+            max_attempts: 3,
+            base_delay_ms: 1,
+            max_delay_ms: 3,
+            jitter_percent: 0,
+        };
+        let mut rng = StdRng::seed_from_u64(7);
+        let delays: Vec<u128> = (1..=4)
+            .map(|retry| config.delay(retry, &mut rng).as_millis())
+            .collect();
+        assert_eq!(delays, [1, 2, 3, 3]);
+        let jittered = RetryConfig {
+            base_delay_ms: 1_000,
+            max_delay_ms: 1_000,
+            jitter_percent: 20,
+            ..config.clone()
+        };
+        let delay = jittered.delay(5, &mut rng);
+        assert!(delay >= Duration::from_millis(800) && delay <= Duration::from_secs(1));
+
+        let metrics = Arc::new(Metrics::new());
+        let provider = RetryProvider::new(MockProvider::new(), config, metrics.clone());
+        let mock = provider.inner();
+        let dropped = || Err(ClientError::NetworkError("connection reset".to_string()));
+        mock.script(ProviderMethod::Tokens, dropped());
+        mock.script(ProviderMethod::Tokens, dropped());
+        assert!(provider.tokens().await.is_ok());
+        assert_eq!(mock.calls(ProviderMethod::Tokens), 3);
+
+        for _ in 0..3 {
+            mock.script(ProviderMethod::ContractAddress, dropped());
+        }
+        assert!(provider.contract_address().await.is_err());
+        assert_eq!(mock.calls(ProviderMethod::ContractAddress), 3);
+
+        mock.script(ProviderMethod::TxInfo, Err(ClientError::IncorrectInput));
+        assert!(matches!(
+            provider.tx_info(TxHash::default()).await,
+            Err(ClientError::IncorrectInput)
+        ));
+        assert_eq!(mock.calls(ProviderMethod::TxInfo), 1);
+
+        mock.script(
+            ProviderMethod::SendTxsBatch,
+            Err(ClientError::OperationTimeout),
+        );
+        assert_eq!(
+            provider.send_txs_batch(Vec::new(), None).await,
+            Err(ClientError::OperationTimeout)
+        );
+        assert_eq!(mock.calls(ProviderMethod::SendTxsBatch), 1);
+
+        assert_eq!(metrics.counter(RETRIES_METRIC, &[("method", "tokens")]), 2);
+        assert_eq!(
+            metrics.counter(RETRIES_EXHAUSTED_METRIC, &[("method", "contract_address")]),
+            1
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use super::provider::{ClientError, ProviderMethod, ResponseResult};
+use crate::metrics::Metrics;
+
+/// Counter of provider calls that did not finish in time, labelled by method.
+pub const TIMEOUTS_METRIC: &str = "provider_timeouts_total";
+
+/// Per-method request timeouts, configured in the `[network.timeouts]` section.
+///
+/// ```toml
+/// [network.timeouts]
+/// default_ms = 10000
+/// send_tx = 5000
+/// tokens = 30000
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TimeoutsConfig {
+    /// Timeout in milliseconds for methods without an override, replaces the built-in defaults.
+    #[serde(default)]
+    pub default_ms: Option<u64>,
+    /// Timeouts in milliseconds keyed by provider method name.
+    #[serde(flatten)]
+    pub overrides: HashMap<ProviderMethod, u64>,
+}
+
+impl TimeoutsConfig {
+    pub fn for_method(&self, method: ProviderMethod) -> Duration {
+        self.overrides
+            .get(&method)
+            .or(self.default_ms.as_ref())
+            .map(|ms| Duration::from_millis(*ms))
+            .unwrap_or_else(|| method.default_timeout())
+    }
+}
+
+impl ProviderMethod {
+    /// Built-in timeout used when the method is not configured.
+    pub fn default_timeout(self) -> Duration {
+        match self {
+            ProviderMethod::AccountInfo
+            | ProviderMethod::TxInfo
+            | ProviderMethod::EthOpInfo
+            | ProviderMethod::GetTxFee
+            | ProviderMethod::GetTxsBatchFee => Duration::from_secs(5),
+            ProviderMethod::AccountTxs
+            | ProviderMethod::SendTx
+            | ProviderMethod::GetEthTxForWithdrawal
+            | ProviderMethod::ContractAddress => Duration::from_secs(10),
+            ProviderMethod::Tokens | ProviderMethod::SendTxsBatch => Duration::from_secs(30),
+        }
+    }
+}
+
+/// Runs a provider call under the timeout configured for its method,
+/// counting the calls that time out.
+pub async fn with_timeout<T>(
+    timeouts: &TimeoutsConfig,
+    metrics: &Metrics,
+    method: ProviderMethod,
+    call: impl Future<Output = ResponseResult<T>>,
+) -> ResponseResult<T> {
+    match tokio::time::timeout(timeouts.for_method(method), call).await {
+        Ok(result) => result,
+        Err(_) => {
+            metrics.increment(TIMEOUTS_METRIC, &[("method", method.name())]);
+            Err(ClientError::OperationTimeout)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests that overrides win over the default and timeouts are counted per method.
+    #[tokio::test]
+    async fn test_method_timeouts() {
+        let timeouts: TimeoutsConfig = toml::from_str("default_ms = 1000\nsend_tx = 10").unwrap();
+        assert_eq!(
+            timeouts.for_method(ProviderMethod::SendTx),
+            Duration::from_millis(10)
+        );
+        assert_eq!(
+            timeouts.for_method(ProviderMethod::Tokens),
+            Duration::from_secs(1)
+        );
+
+        let metrics = Metrics::new();
+        let slow = async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Ok(())
+        };
+        let result = with_timeout(&timeouts, &metrics, ProviderMethod::SendTx, slow).await;
+        assert_eq!(result, Err(ClientError::OperationTimeout));
+        assert_eq!(
+            metrics.counter(TIMEOUTS_METRIC, &[("method", "send_tx")]),
+            1
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use serde::Deserialize;
+
+use super::provider::{ClientError, Provider, ResponseResult};
+use super::types::{Token, TokenLike, Tokens};
+
+/// Token of the configured mix with its relative share of generated transactions.
+///
+/// Configured either as a plain token, with a weight of 1, or as a table:
+/// `tokens = ["RBTC", { token = "RDOC", weight = 3 }]`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(from = "TokenEntry")]
+pub struct WeightedToken {
+    pub token: TokenLike,
+    pub weight: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TokenEntry {
+    Plain(TokenLike),
+    Weighted { token: TokenLike, weight: u32 },
+}
+
+impl From<TokenEntry> for WeightedToken {
+    fn from(entry: TokenEntry) -> Self {
+        match entry {
+            TokenEntry::Plain(token) => Self { token, weight: 1 },
+            TokenEntry::Weighted { token, weight } => Self { token, weight },
+        }
+    }
+}
+
+impl From<TokenLike> for WeightedToken {
+    fn from(token: TokenLike) -> Self {
+        Self { token, weight: 1 }
+    }
+}
+
+/// Tokens supported by the rollup, fetched once at startup.
+#[derive(Debug, Clone, Default)]
+pub struct TokenRegistry {
+    tokens: Tokens,
+}
+
+impl TokenRegistry {
+    pub fn new(tokens: Tokens) -> Self {
+        Self { tokens }
+    }
+
+    pub async fn fetch<P: Provider + Sync>(provider: &P) -> ResponseResult<Self> {
+        Ok(Self::new(provider.tokens().await?))
+    }
+
+    pub fn get(&self, token_like: &TokenLike) -> Option<&Token> {
+        find_token(token_like, &self.tokens)
+    }
+
+    pub fn tokens(&self) -> impl Iterator<Item = &Token> {
+        self.tokens.values()
+    }
+
+    pub fn resolve(&self, configured: &[TokenLike]) -> ResponseResult<Vec<Token>> {
+        resolve_tokens(configured, &self.tokens)
+    }
+}
+
+/// Weighted choice among the configured tokens.
+#[derive(Debug, Clone)]
+pub struct TokenMix {
+    tokens: Vec<Token>,
+    weights: WeightedIndex<u32>,
+}
+
+impl TokenMix {
+    /// Resolves the configured tokens, failing on unknown tokens or when every weight is zero.
+    pub fn new(configured: &[WeightedToken], registry: &TokenRegistry) -> ResponseResult<Self> {
+        let token_likes: Vec<TokenLike> =
+            configured.iter().map(|entry| entry.token.clone()).collect();
+        let tokens = registry.resolve(&token_likes)?;
+        let weights = WeightedIndex::new(configured.iter().map(|entry| entry.weight))
+            .map_err(|_| ClientError::IncorrectInput)?;
+        Ok(Self { tokens, weights })
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    /// Picks the token of the next generated transaction.
+    pub fn choose<R: Rng>(&self, rng: &mut R) -> &Token {
+        &self.tokens[self.weights.sample(rng)]
+    }
+}
+
+/// Looks up every configured token in the list supported by the rollup.
+///
+/// Fails with all the tokens that are not listed at once, so a misconfigured
+/// run is stopped at startup instead of when the token is first used.
+pub fn resolve_tokens(configured: &[TokenLike], supported: &Tokens) -> ResponseResult<Vec<Token>> {
+    let mut resolved = Vec::with_capacity(configured.len());
+    let mut unknown = Vec::new();
+    for token_like in configured {
+        match find_token(token_like, supported) {
+            Some(token) => resolved.push(token.clone()),
+            None => unknown.push(token_like.to_string()),
+        }
+    }
+    if !unknown.is_empty() {
+        return Err(ClientError::UnknownToken(unknown.join(", ")));
+    }
+    Ok(resolved)
+}
+
+/// Fetches the supported tokens and resolves the configured ones against them.
+pub async fn whitelist_tokens<P: Provider + Sync>(
+    provider: &P,
+    configured: &[TokenLike],
+) -> ResponseResult<Vec<Token>> {
+    TokenRegistry::fetch(provider).await?.resolve(configured)
+}
+
+fn find_token<'a>(token_like: &TokenLike, supported: &'a Tokens) -> Option<&'a Token> {
+    match token_like {
+        TokenLike::Id(id) => supported.values().find(|token| token.id == *id),
+        TokenLike::Address(address) => supported.values().find(|token| token.address == *address),
+        TokenLike::Symbol(symbol) => supported
+            .values()
+            .find(|token| token.symbol.eq_ignore_ascii_case(symbol)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::rollup::types::{Address, TokenId, TokenKind};
+
+    /// Tests that tokens resolve by id, address or symbol, unknown ones are reported together and weights shape the mix.
+    #[test]
+    fn test_resolve_tokens() {
+        let rdoc_address = Address::from_low_u64_be(0xd0c);
+        let supported: Tokens = [
+            Token::new(TokenId(0), Address::zero(), "RBTC", 18, TokenKind::None),
+            Token::new(TokenId(1), rdoc_address, "RDOC", 18, TokenKind::ERC20),
+        ]
+        .into_iter()
+        .map(|token| (token.symbol.clone(), token))
+        .collect();
+
+        let resolved = resolve_tokens(
+            &[TokenLike::from("rbtc"), TokenLike::from(rdoc_address)],
+            &supported,
+        )
+        .unwrap();
+        assert_eq!(
+            resolved.iter().map(|token| token.id).collect::<Vec<_>>(),
+            vec![TokenId(0), TokenId(1)]
+        );
+
+        let err = resolve_tokens(
+            &[
+                TokenLike::from(TokenId(1)),
+                TokenLike::from("USDT"),
+                TokenLike::from(TokenId(7)),
+            ],
+            &supported,
+        )
+        .unwrap_err();
+        assert_eq!(err, ClientError::UnknownToken("USDT, 7".to_string()));
+
+        #[derive(Deserialize)]
+        struct Mix {
+            tokens: Vec<WeightedToken>,
+        }
+        let mix: Mix =
+            toml::from_str(r#"tokens = ["RBTC", { token = "RDOC", weight = 3 }]"#).unwrap();
+        assert_eq!(mix.tokens[0].weight, 1);
+        let mix = TokenMix::new(&mix.tokens, &TokenRegistry::new(supported)).unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+        let rdoc = (0..4_000)
+            .filter(|_| mix.choose(&mut rng).symbol == "RDOC")
+            .count();
+        assert!((2_800..3_200).contains(&rdoc), "{rdoc}");
+    }
+}
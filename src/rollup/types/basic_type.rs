@@ -1,11 +1,57 @@
+use std::num::ParseIntError;
+
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum BasicTypeError {
+    #[error("Cannot parse value: {0}")]
+    Parse(#[from] ParseIntError),
+    #[error("Value {value} exceeds the maximum of {max}")]
+    OutOfRange { value: u64, max: u64 },
+}
+
 macro_rules! basic_type {
     ($(#[$attr:meta])* $name:ident, $type:ty) => {
+        basic_type!($(#[$attr])* $name, $type, max = <$type>::MAX);
+    };
+    ($(#[$attr:meta])* $name:ident, $type:ty, max = $max:expr) => {
         $(#[$attr])*
         #[derive(
-            Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash, PartialOrd, Ord, Default
+            Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default
         )]
         pub struct $name(pub $type);
 
+        impl $name {
+            /// Largest value accepted by the protocol.
+            pub const MAX: Self = Self($max);
+
+            /// Creates the value, checking that it does not exceed `MAX`.
+            pub fn new_checked(value: $type) -> Result<Self, BasicTypeError> {
+                if value > Self::MAX.0 {
+                    return Err(BasicTypeError::OutOfRange {
+                        value: value as u64,
+                        max: Self::MAX.0 as u64,
+                    });
+                }
+                Ok(Self(value))
+            }
+
+            /// Returns `None` if the result would exceed `MAX`.
+            pub fn checked_add(self, other: $type) -> Option<Self> {
+                self.0.checked_add(other).filter(|value| *value <= Self::MAX.0).map(Self)
+            }
+
+            /// Returns `None` if the result would underflow.
+            pub fn checked_sub(self, other: $type) -> Option<Self> {
+                self.0.checked_sub(other).map(Self)
+            }
+
+            /// Value following this one, `None` once `MAX` is reached.
+            pub fn checked_next(self) -> Option<Self> {
+                self.checked_add(1)
+            }
+        }
+
         impl Deref for $name {
             type Target = $type;
 
@@ -27,11 +73,14 @@ macro_rules! basic_type {
         }
 
         impl FromStr for $name {
-            type Err = ParseIntError;
+            type Err = BasicTypeError;
 
             fn from_str(s: &str) -> Result<Self, Self::Err> {
-                let value = s.parse::<$type>()?;
-                Ok(Self(value))
+                let value = match s.strip_prefix("0x") {
+                    Some(hex) => <$type>::from_str_radix(hex, 16)?,
+                    None => s.parse::<$type>()?,
+                };
+                Self::new_checked(value)
             }
         }
 
@@ -45,7 +94,8 @@ macro_rules! basic_type {
             type Output = Self;
 
             fn add(self, other: $type) -> Self {
-                Self(self.0 + other)
+                self.checked_add(other)
+                    .unwrap_or_else(|| panic!("{} overflow: {} + {}", stringify!($name), self.0, other))
             }
         }
 
@@ -53,8 +103,88 @@ macro_rules! basic_type {
             type Output = Self;
 
             fn sub(self, other: $type) -> Self {
-                Self(self.0 - other)
+                self.checked_sub(other)
+                    .unwrap_or_else(|| panic!("{} underflow: {} - {}", stringify!($name), self.0, other))
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                self.0.serialize(serializer)
+            }
+        }
+
+        /// Accepts a number, a decimal string or a `0x`-prefixed hexadecimal string,
+        /// rejecting values above `MAX`.
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                struct Visitor;
+
+                impl<'de> serde::de::Visitor<'de> for Visitor {
+                    type Value = $name;
+
+                    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                        write!(f, "{} as a number or a numeric string", stringify!($name))
+                    }
+
+                    fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<$name, E> {
+                        let value = <$type>::try_from(value).map_err(E::custom)?;
+                        $name::new_checked(value).map_err(E::custom)
+                    }
+
+                    fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<$name, E> {
+                        let value = u64::try_from(value).map_err(E::custom)?;
+                        self.visit_u64(value)
+                    }
+
+                    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<$name, E> {
+                        value.parse().map_err(E::custom)
+                    }
+                }
+
+                deserializer.deserialize_any(Visitor)
             }
         }
     };
 }
+
+#[cfg(test)]
+mod test {
+    use crate::rollup::types::{AccountId, Nonce};
+
+    use super::*;
+
+    /// Tests that arithmetic and parsing respect the type's maximum.
+    #[test]
+    fn test_basic_type_range_checks() {
+        assert_eq!(Nonce(1).checked_next(), Some(Nonce(2)));
+        assert_eq!(Nonce::MAX.checked_next(), None);
+        assert_eq!(Nonce(0).checked_sub(1), None);
+        assert!(matches!(
+            AccountId::new_checked(AccountId::MAX.0 + 1),
+            Err(BasicTypeError::OutOfRange { .. })
+        ));
+        assert_eq!("0x10".parse::<Nonce>(), Ok(Nonce(16)));
+    }
+
+    /// Tests that values are deserialized from numbers and strings in both radixes.
+    #[test]
+    fn test_basic_type_serde() {
+        let nonce: Nonce = serde_json::from_str("42").unwrap();
+        assert_eq!(nonce, Nonce(42));
+        let nonce: Nonce = serde_json::from_str("\"42\"").unwrap();
+        assert_eq!(nonce, Nonce(42));
+        let nonce: Nonce = serde_json::from_str("\"0x2a\"").unwrap();
+        assert_eq!(nonce, Nonce(42));
+        assert_eq!(serde_json::to_string(&nonce).unwrap(), "42");
+
+        assert!(serde_json::from_str::<Nonce>(&u32::MAX.to_string()).is_err());
+        assert!(serde_json::from_str::<Nonce>("-1").is_err());
+    }
+}
@@ -9,16 +9,22 @@ use serde::{Deserialize, Serialize};
 #[macro_use]
 mod basic_type;
 pub mod serde_wrappers;
+pub mod packing;
+pub mod pagination;
 pub mod pubkey_hash;
+pub mod tx;
+pub mod tx_hash;
 
 use std::fmt;
-use std::num::ParseIntError;
 use std::ops::{Add, Deref, DerefMut, Sub};
 use std::str::FromStr;
 
 pub use ethers::types::{Address, Log, TransactionReceipt, H160, H256, U128, U256};
 
+pub use self::basic_type::BasicTypeError;
+pub use self::pagination::{AccountTx, Paginated, PaginationDirection, PaginationQuery};
 use self::pubkey_hash::PubKeyHash;
+pub use self::tx_hash::TxHash;
 use self::serde_wrappers::{BigUintSerdeWrapper, BigUintSerdeAsRadix10Str};
 
 basic_type!(
@@ -30,7 +36,8 @@ basic_type!(
 basic_type!(
     /// Unique identifier of the account in the zkSync network.
     AccountId,
-    u32
+    u32,
+    max = (1 << 24) - 1
 );
 
 basic_type!(
@@ -41,8 +48,10 @@ basic_type!(
 
 basic_type!(
     /// zkSync account nonce.
+    /// The last nonce value is reserved, an account can never use it.
     Nonce,
-    u32
+    u32,
+    max = u32::MAX - 1
 );
 
 basic_type!(
@@ -179,6 +188,15 @@ pub struct DepositingAccountBalances {
     balances: HashMap<String, DepositingFunds>,
 }
 
+impl DepositingAccountBalances {
+    /// Amounts per token deposited on L1 that the rollup has not accepted yet.
+    pub fn amounts(&self) -> impl Iterator<Item = (&String, &BigUint)> {
+        self.balances
+            .iter()
+            .map(|(token, funds)| (token, &funds.amount))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum BlockStatus {
@@ -248,6 +266,73 @@ pub enum ChangePubKeyFeeType {
     CREATE2,
 }
 
+impl ChangePubKeyFeeType {
+    /// All the ways of authorizing a `ChangePubKey` operation.
+    pub const ALL: [Self; 3] = [Self::Onchain, Self::ECDSA, Self::CREATE2];
+}
+
+/// Type of the transaction fee as it is requested by the user.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TxFeeTypes {
+    /// Fee for the `Withdraw` or `ForcedExit` transaction.
+    Withdraw,
+    /// Fee for the `Withdraw` operation that requires fast processing.
+    FastWithdraw,
+    /// Fee for the `Transfer` operation.
+    Transfer,
+    /// Fee for the `ChangePubKey` operation.
+    ChangePubKey(ChangePubKeyFeeType),
+    /// Fee for the `MintNFT` operation.
+    MintNFT,
+    /// Fee for the `WithdrawNFT` operation.
+    WithdrawNFT,
+    /// Fee for the `WithdrawNFT` operation that requires fast processing.
+    FastWithdrawNFT,
+}
+
+/// Token identifier accepted by the server: numeric id, contract address or symbol.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TokenLike {
+    Id(TokenId),
+    Address(Address),
+    Symbol(String),
+}
+
+impl fmt::Display for TokenLike {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenLike::Id(id) => write!(f, "{}", id),
+            TokenLike::Address(address) => write!(f, "{:?}", address),
+            TokenLike::Symbol(symbol) => write!(f, "{}", symbol),
+        }
+    }
+}
+
+impl From<TokenId> for TokenLike {
+    fn from(id: TokenId) -> Self {
+        Self::Id(id)
+    }
+}
+
+impl From<Address> for TokenLike {
+    fn from(address: Address) -> Self {
+        Self::Address(address)
+    }
+}
+
+impl From<&str> for TokenLike {
+    fn from(symbol: &str) -> Self {
+        Self::Symbol(symbol.to_string())
+    }
+}
+
+impl From<&Token> for TokenLike {
+    fn from(token: &Token) -> Self {
+        Self::Id(token.id)
+    }
+}
+
 /// Type of the fee calculation pattern.
 /// Unlike the `TxFeeTypes`, this enum represents the fee
 /// from the point of zkSync view, rather than from the users
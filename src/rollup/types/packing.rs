@@ -0,0 +1,110 @@
+//! Floating point packing of amounts as they are stored in rollup transactions.
+//!
+//! An amount is packed into `mantissa * 10^exponent` and laid out big endian with
+//! the mantissa in the high bits and the exponent in the low bits. Amounts that
+//! cannot be represented exactly are rejected by the server, so they have to be
+//! rounded down to the closest packable value before signing.
+
+use num::{BigUint, ToPrimitive};
+
+/// Bit width of the exponent of packed token amounts.
+pub const AMOUNT_EXPONENT_BIT_WIDTH: usize = 5;
+/// Bit width of the mantissa of packed token amounts.
+pub const AMOUNT_MANTISSA_BIT_WIDTH: usize = 35;
+/// Bit width of the exponent of packed fees.
+pub const FEE_EXPONENT_BIT_WIDTH: usize = 5;
+/// Bit width of the mantissa of packed fees.
+pub const FEE_MANTISSA_BIT_WIDTH: usize = 11;
+
+/// Splits the number into mantissa and exponent, rounding down when it has too many digits.
+fn to_float(number: &BigUint, exponent_len: usize, mantissa_len: usize) -> Option<(u128, u32)> {
+    let max_mantissa = (1u128 << mantissa_len) - 1;
+    let max_exponent = (1u32 << exponent_len) - 1;
+    let mut mantissa = number.to_u128()?;
+    let mut exponent = 0;
+    while mantissa > max_mantissa {
+        mantissa /= 10;
+        exponent += 1;
+    }
+    (exponent <= max_exponent).then_some((mantissa, exponent))
+}
+
+fn pack(number: &BigUint, exponent_len: usize, mantissa_len: usize) -> Option<Vec<u8>> {
+    let (mantissa, exponent) = to_float(number, exponent_len, mantissa_len)?;
+    let packed = (mantissa << exponent_len) | u128::from(exponent);
+    let len = (exponent_len + mantissa_len) / 8;
+    Some(packed.to_be_bytes()[16 - len..].to_vec())
+}
+
+fn closest_packable(number: &BigUint, exponent_len: usize, mantissa_len: usize) -> Option<BigUint> {
+    let (mantissa, exponent) = to_float(number, exponent_len, mantissa_len)?;
+    Some(BigUint::from(mantissa) * BigUint::from(10u32).pow(exponent))
+}
+
+/// Packs a token amount into 5 bytes, rounding it down to the closest packable value.
+///
+/// Returns `None` when the amount exceeds the largest packable one.
+pub fn pack_token_amount(amount: &BigUint) -> Option<Vec<u8>> {
+    pack(amount, AMOUNT_EXPONENT_BIT_WIDTH, AMOUNT_MANTISSA_BIT_WIDTH)
+}
+
+/// Packs a fee into 2 bytes, rounding it down to the closest packable value.
+///
+/// Returns `None` when the fee exceeds the largest packable one.
+pub fn pack_fee_amount(fee: &BigUint) -> Option<Vec<u8>> {
+    pack(fee, FEE_EXPONENT_BIT_WIDTH, FEE_MANTISSA_BIT_WIDTH)
+}
+
+/// Largest packable token amount not greater than the given one.
+pub fn closest_packable_token_amount(amount: &BigUint) -> Option<BigUint> {
+    closest_packable(amount, AMOUNT_EXPONENT_BIT_WIDTH, AMOUNT_MANTISSA_BIT_WIDTH)
+}
+
+/// Largest packable fee not greater than the given one.
+///
+/// Fees quoted by the server are rounded this way already, a fee computed by the
+/// simulator should rather be rounded up so it still covers the quote.
+pub fn closest_packable_fee_amount(fee: &BigUint) -> Option<BigUint> {
+    closest_packable(fee, FEE_EXPONENT_BIT_WIDTH, FEE_MANTISSA_BIT_WIDTH)
+}
+
+pub fn is_token_amount_packable(amount: &BigUint) -> bool {
+    closest_packable_token_amount(amount).as_ref() == Some(amount)
+}
+
+pub fn is_fee_amount_packable(fee: &BigUint) -> bool {
+    closest_packable_fee_amount(fee).as_ref() == Some(fee)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests the packed layout and rounding of amounts and fees.
+    #[test]
+    fn test_packing() {
+        // 1000 fits the fee mantissa: 1000 << 5 | 0.
+        assert_eq!(
+            pack_fee_amount(&BigUint::from(1_000u32)).unwrap(),
+            vec![0x7d, 0x00]
+        );
+        // 12345 needs one decimal digit less: 1234 << 5 | 1.
+        let fee = BigUint::from(12_345u32);
+        assert_eq!(pack_fee_amount(&fee).unwrap(), vec![0x9a, 0x41]);
+        assert_eq!(
+            closest_packable_fee_amount(&fee).unwrap(),
+            BigUint::from(12_340u32)
+        );
+        assert!(!is_fee_amount_packable(&fee));
+
+        let amount = BigUint::from(10u32).pow(18) * 3u32;
+        assert!(is_token_amount_packable(&amount));
+        let packed = pack_token_amount(&amount).unwrap();
+        assert_eq!(packed.len(), 5);
+        assert_eq!(packed[4] & 0x1f, 8);
+
+        let max_mantissa = BigUint::from((1u64 << 35) - 1);
+        let too_large = (max_mantissa + 1u32) * BigUint::from(10u32).pow(31);
+        assert!(pack_token_amount(&too_large).is_none());
+    }
+}
@@ -0,0 +1,107 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{BlockNumber, TxHash};
+
+/// Direction in which a paginated list is walked from its starting point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PaginationDirection {
+    Newer,
+    Older,
+}
+
+impl fmt::Display for PaginationDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaginationDirection::Newer => write!(f, "newer"),
+            PaginationDirection::Older => write!(f, "older"),
+        }
+    }
+}
+
+/// Starting point of a page of transactions, either the latest one or a given transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TxHistoryCursor {
+    #[serde(with = "latest")]
+    Latest,
+    Tx(TxHash),
+}
+
+impl fmt::Display for TxHistoryCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxHistoryCursor::Latest => write!(f, "latest"),
+            TxHistoryCursor::Tx(tx_hash) => write!(f, "{}", tx_hash),
+        }
+    }
+}
+
+/// (De)serializes the unit variant as the `"latest"` string.
+mod latest {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str("latest")
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<(), D::Error> {
+        match String::deserialize(deserializer)?.as_str() {
+            "latest" => Ok(()),
+            other => Err(D::Error::custom(format!(
+                "expected 'latest', got '{}'",
+                other
+            ))),
+        }
+    }
+}
+
+/// Page of the account transaction history to request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaginationQuery {
+    pub from: TxHistoryCursor,
+    pub limit: u32,
+    pub direction: PaginationDirection,
+}
+
+impl PaginationQuery {
+    /// First page of the whole history, starting from the latest transaction.
+    pub fn latest(limit: u32) -> Self {
+        Self {
+            from: TxHistoryCursor::Latest,
+            limit,
+            direction: PaginationDirection::Older,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaginationInfo {
+    pub from: TxHistoryCursor,
+    pub limit: u32,
+    pub direction: PaginationDirection,
+    /// Total number of items in the list, not only on this page.
+    pub count: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Paginated<T> {
+    pub list: Vec<T>,
+    pub pagination: PaginationInfo,
+}
+
+/// Transaction as listed in the history of an account.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountTx {
+    pub tx_hash: TxHash,
+    pub block_number: Option<BlockNumber>,
+    pub status: String,
+    pub fail_reason: Option<String>,
+    pub created_at: Option<String>,
+}
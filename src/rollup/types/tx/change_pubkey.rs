@@ -0,0 +1,110 @@
+use ethers::utils::keccak256;
+use num::BigUint;
+use serde::{Deserialize, Serialize};
+
+use super::{PackedEthSignature, TimeRange, TxSignature, CURRENT_TX_VERSION};
+use crate::rollup::types::packing::pack_fee_amount;
+use crate::rollup::types::pubkey_hash::PubKeyHash;
+use crate::rollup::types::serde_wrappers::BigUintSerdeAsRadix10Str;
+use crate::rollup::types::{AccountId, Address, ChangePubKeyFeeType, Nonce, TokenId, H256};
+
+/// Proof that the owner of the Rootstock address authorized the new signing key.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ChangePubKeyEthAuthData {
+    /// Authorized beforehand by a `setAuthPubkeyHash` call on the rollup contract.
+    Onchain,
+    /// Authorized by a Rootstock signature of the new key.
+    #[serde(rename_all = "camelCase")]
+    ECDSA {
+        eth_signature: PackedEthSignature,
+        /// Hash of the batch the transaction is part of, zero when sent alone.
+        batch_hash: H256,
+    },
+    /// Authorized by the account address being the `CREATE2` address of a contract whose
+    /// salt commits to the new key.
+    CREATE2(ChangePubKeyCREATE2Data),
+}
+
+/// Deployment of the contract owning a `CREATE2` account.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangePubKeyCREATE2Data {
+    pub creator_address: Address,
+    pub salt_arg: H256,
+    pub code_hash: H256,
+}
+
+impl ChangePubKeyCREATE2Data {
+    /// Address of the contract deployed with the salt `keccak256(salt_arg ++ pub_key_hash)`.
+    pub fn get_address(&self, pub_key_hash: &PubKeyHash) -> Address {
+        let mut salt_preimage = self.salt_arg.as_bytes().to_vec();
+        salt_preimage.extend_from_slice(&pub_key_hash.data);
+        let salt = keccak256(salt_preimage);
+
+        let mut preimage = vec![0xff];
+        preimage.extend_from_slice(self.creator_address.as_bytes());
+        preimage.extend_from_slice(&salt);
+        preimage.extend_from_slice(self.code_hash.as_bytes());
+        Address::from_slice(&keccak256(preimage)[12..])
+    }
+}
+
+impl ChangePubKeyEthAuthData {
+    pub fn fee_type(&self) -> ChangePubKeyFeeType {
+        match self {
+            ChangePubKeyEthAuthData::Onchain => ChangePubKeyFeeType::Onchain,
+            ChangePubKeyEthAuthData::ECDSA { .. } => ChangePubKeyFeeType::ECDSA,
+            ChangePubKeyEthAuthData::CREATE2(_) => ChangePubKeyFeeType::CREATE2,
+        }
+    }
+}
+
+/// Sets the L2 signing key of an account, required before it can send any other transaction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangePubKey {
+    pub account_id: AccountId,
+    pub account: Address,
+    pub new_pk_hash: PubKeyHash,
+    pub fee_token: TokenId,
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    pub fee: BigUint,
+    pub nonce: Nonce,
+    pub signature: TxSignature,
+    pub eth_auth_data: ChangePubKeyEthAuthData,
+    #[serde(flatten)]
+    pub time_range: TimeRange,
+}
+
+impl ChangePubKey {
+    pub const TX_TYPE: u8 = 7;
+
+    /// Bytes signed by the L2 signing key, the fee must be packable.
+    pub fn get_bytes(&self) -> Vec<u8> {
+        let mut out = vec![255 - Self::TX_TYPE, CURRENT_TX_VERSION];
+        out.extend_from_slice(&self.account_id.to_be_bytes());
+        out.extend_from_slice(self.account.as_bytes());
+        out.extend_from_slice(&self.new_pk_hash.data);
+        out.extend_from_slice(&self.fee_token.to_be_bytes());
+        out.extend(pack_fee_amount(&self.fee).expect("fee is checked before signing"));
+        out.extend_from_slice(&self.nonce.to_be_bytes());
+        out.extend_from_slice(&self.time_range.as_be_bytes());
+        out
+    }
+
+    /// Data signed by the Rootstock key to authorize the new signing key with ECDSA.
+    pub fn get_eth_signed_data(
+        new_pk_hash: &PubKeyHash,
+        nonce: Nonce,
+        account_id: AccountId,
+        batch_hash: H256,
+    ) -> Vec<u8> {
+        let mut data = Vec::with_capacity(60);
+        data.extend_from_slice(&new_pk_hash.data);
+        data.extend_from_slice(&nonce.to_be_bytes());
+        data.extend_from_slice(&account_id.to_be_bytes());
+        data.extend_from_slice(batch_hash.as_bytes());
+        data
+    }
+}
@@ -0,0 +1,42 @@
+use num::BigUint;
+use serde::{Deserialize, Serialize};
+
+use super::{TimeRange, TxSignature, CURRENT_TX_VERSION};
+use crate::rollup::types::packing::pack_fee_amount;
+use crate::rollup::types::serde_wrappers::BigUintSerdeAsRadix10Str;
+use crate::rollup::types::{AccountId, Address, Nonce, TokenId};
+
+/// Withdrawal of the whole `token` balance of a target account to its Rootstock address,
+/// initiated and paid for by another account.
+///
+/// The rollup only accepts it for targets that never set a signing key, the initiator's L2
+/// signature is the only one required.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForcedExit {
+    pub initiator_account_id: AccountId,
+    pub target: Address,
+    pub token: TokenId,
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    pub fee: BigUint,
+    pub nonce: Nonce,
+    pub signature: TxSignature,
+    #[serde(flatten)]
+    pub time_range: TimeRange,
+}
+
+impl ForcedExit {
+    pub const TX_TYPE: u8 = 8;
+
+    /// Bytes signed by the L2 signing key of the initiator, the fee must be packable.
+    pub fn get_bytes(&self) -> Vec<u8> {
+        let mut out = vec![255 - Self::TX_TYPE, CURRENT_TX_VERSION];
+        out.extend_from_slice(&self.initiator_account_id.to_be_bytes());
+        out.extend_from_slice(self.target.as_bytes());
+        out.extend_from_slice(&self.token.to_be_bytes());
+        out.extend(pack_fee_amount(&self.fee).expect("fee is checked before signing"));
+        out.extend_from_slice(&self.nonce.to_be_bytes());
+        out.extend_from_slice(&self.time_range.as_be_bytes());
+        out
+    }
+}
@@ -0,0 +1,45 @@
+use num::BigUint;
+use serde::{Deserialize, Serialize};
+
+use super::{eth_message_with_fee_and_nonce, TxSignature, CURRENT_TX_VERSION};
+use crate::rollup::types::packing::pack_fee_amount;
+use crate::rollup::types::serde_wrappers::BigUintSerdeAsRadix10Str;
+use crate::rollup::types::{AccountId, Address, Nonce, TokenId, H256};
+
+/// Minting of an NFT with the given content hash to the recipient.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MintNFT {
+    pub creator_id: AccountId,
+    pub creator_address: Address,
+    pub content_hash: H256,
+    pub recipient: Address,
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    pub fee: BigUint,
+    pub fee_token: TokenId,
+    pub nonce: Nonce,
+    pub signature: TxSignature,
+}
+
+impl MintNFT {
+    pub const TX_TYPE: u8 = 9;
+
+    /// Bytes signed by the L2 signing key, the fee must be packable.
+    pub fn get_bytes(&self) -> Vec<u8> {
+        let mut out = vec![255 - Self::TX_TYPE, CURRENT_TX_VERSION];
+        out.extend_from_slice(&self.creator_id.to_be_bytes());
+        out.extend_from_slice(self.creator_address.as_bytes());
+        out.extend_from_slice(self.content_hash.as_bytes());
+        out.extend_from_slice(self.recipient.as_bytes());
+        out.extend_from_slice(&self.fee_token.to_be_bytes());
+        out.extend(pack_fee_amount(&self.fee).expect("fee is checked before signing"));
+        out.extend_from_slice(&self.nonce.to_be_bytes());
+        out
+    }
+
+    /// Message signed by the Rootstock key of the creator.
+    pub fn get_ethereum_sign_message(&self, fee_token_symbol: &str, decimals: u8) -> String {
+        let message = format!("MintNFT {:?} for: {:?}", self.content_hash, self.recipient);
+        eth_message_with_fee_and_nonce(message, &self.fee, fee_token_symbol, decimals, *self.nonce)
+    }
+}
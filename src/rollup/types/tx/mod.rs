@@ -0,0 +1,140 @@
+//! L2 transactions as they are signed and submitted to the rollup.
+
+use num::{BigUint, Zero};
+use serde::{Deserialize, Serialize};
+
+pub mod change_pubkey;
+pub mod forced_exit;
+pub mod mint_nft;
+pub mod signature;
+pub mod transfer;
+pub mod withdraw;
+pub mod withdraw_nft;
+
+pub use self::change_pubkey::{ChangePubKey, ChangePubKeyCREATE2Data, ChangePubKeyEthAuthData};
+pub use self::forced_exit::ForcedExit;
+pub use self::mint_nft::MintNFT;
+pub use self::signature::{PackedEthSignature, TxEthSignature, TxSignature};
+pub use self::transfer::Transfer;
+pub use self::withdraw::Withdraw;
+pub use self::withdraw_nft::WithdrawNFT;
+
+/// Version byte of the transaction encoding signed by the L2 key.
+pub const CURRENT_TX_VERSION: u8 = 1;
+
+/// Signed L2 transaction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ZkSyncTx {
+    Transfer(Box<Transfer>),
+    Withdraw(Box<Withdraw>),
+    ChangePubKey(Box<ChangePubKey>),
+    MintNFT(Box<MintNFT>),
+    WithdrawNFT(Box<WithdrawNFT>),
+    ForcedExit(Box<ForcedExit>),
+}
+
+impl ZkSyncTx {
+    /// Bytes signed by the L2 signing key.
+    pub fn get_bytes(&self) -> Vec<u8> {
+        match self {
+            ZkSyncTx::Transfer(tx) => tx.get_bytes(),
+            ZkSyncTx::Withdraw(tx) => tx.get_bytes(),
+            ZkSyncTx::ChangePubKey(tx) => tx.get_bytes(),
+            ZkSyncTx::MintNFT(tx) => tx.get_bytes(),
+            ZkSyncTx::WithdrawNFT(tx) => tx.get_bytes(),
+            ZkSyncTx::ForcedExit(tx) => tx.get_bytes(),
+        }
+    }
+
+    /// Signature made by the L2 signing key over `get_bytes`.
+    pub fn signature(&self) -> &TxSignature {
+        match self {
+            ZkSyncTx::Transfer(tx) => &tx.signature,
+            ZkSyncTx::Withdraw(tx) => &tx.signature,
+            ZkSyncTx::ChangePubKey(tx) => &tx.signature,
+            ZkSyncTx::MintNFT(tx) => &tx.signature,
+            ZkSyncTx::WithdrawNFT(tx) => &tx.signature,
+            ZkSyncTx::ForcedExit(tx) => &tx.signature,
+        }
+    }
+
+    pub fn signature_mut(&mut self) -> &mut TxSignature {
+        match self {
+            ZkSyncTx::Transfer(tx) => &mut tx.signature,
+            ZkSyncTx::Withdraw(tx) => &mut tx.signature,
+            ZkSyncTx::ChangePubKey(tx) => &mut tx.signature,
+            ZkSyncTx::MintNFT(tx) => &mut tx.signature,
+            ZkSyncTx::WithdrawNFT(tx) => &mut tx.signature,
+            ZkSyncTx::ForcedExit(tx) => &mut tx.signature,
+        }
+    }
+}
+
+/// Period of time during which a transaction can be executed, in seconds since the epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeRange {
+    pub valid_from: u64,
+    pub valid_until: u64,
+}
+
+impl TimeRange {
+    pub fn new(valid_from: u64, valid_until: u64) -> Self {
+        Self {
+            valid_from,
+            valid_until,
+        }
+    }
+
+    pub fn as_be_bytes(&self) -> [u8; 16] {
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&self.valid_from.to_be_bytes());
+        bytes[8..].copy_from_slice(&self.valid_until.to_be_bytes());
+        bytes
+    }
+}
+
+impl Default for TimeRange {
+    fn default() -> Self {
+        Self::new(0, u64::MAX)
+    }
+}
+
+/// Formats an amount in the token's smallest units as a decimal number of whole tokens.
+pub fn format_units(amount: &BigUint, decimals: u8) -> String {
+    let decimals = decimals as usize;
+    let digits = format!("{:0>width$}", amount.to_string(), width = decimals + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        format!("{}.0", whole)
+    } else {
+        format!("{}.{}", whole, fraction)
+    }
+}
+
+/// Common ending of the messages signed by the Rootstock key: the fee, if any, and the nonce.
+pub(crate) fn eth_message_with_fee_and_nonce(
+    mut message: String,
+    fee: &BigUint,
+    fee_token: &str,
+    decimals: u8,
+    nonce: u32,
+) -> String {
+    if !fee.is_zero() {
+        if !message.is_empty() {
+            message.push('\n');
+        }
+        message.push_str(&format!(
+            "Fee: {} {}",
+            format_units(fee, decimals),
+            fee_token
+        ));
+    }
+    if !message.is_empty() {
+        message.push('\n');
+    }
+    message.push_str(&format!("Nonce: {}", nonce));
+    message
+}
@@ -0,0 +1,102 @@
+use std::fmt;
+use std::str::FromStr;
+
+use ethers::types::Signature;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Length of a packed public key of the L2 signing key.
+pub const PACKED_PUBLIC_KEY_LEN: usize = 32;
+/// Length of a packed musig signature.
+pub const PACKED_SIGNATURE_LEN: usize = 64;
+
+/// Signature of an L2 transaction made with the account's signing key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxSignature {
+    #[serde(with = "hex_bytes")]
+    pub pub_key: [u8; PACKED_PUBLIC_KEY_LEN],
+    #[serde(with = "hex_bytes")]
+    pub signature: [u8; PACKED_SIGNATURE_LEN],
+}
+
+impl Default for TxSignature {
+    fn default() -> Self {
+        Self {
+            pub_key: [0; PACKED_PUBLIC_KEY_LEN],
+            signature: [0; PACKED_SIGNATURE_LEN],
+        }
+    }
+}
+
+/// Fixed size byte arrays (de)serialized as plain hex strings.
+mod hex_bytes {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer, const N: usize>(
+        bytes: &[u8; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<[u8; N], D::Error> {
+        let string = String::deserialize(deserializer)?;
+        let bytes = hex::decode(string.trim_start_matches("0x")).map_err(D::Error::custom)?;
+        bytes
+            .try_into()
+            .map_err(|_| D::Error::custom(format!("expected {} bytes", N)))
+    }
+}
+
+/// Rootstock signature of a transaction message, 65 bytes `r || s || v`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackedEthSignature(pub Signature);
+
+impl PackedEthSignature {
+    pub fn serialize_packed(&self) -> [u8; 65] {
+        self.0.into()
+    }
+}
+
+impl From<Signature> for PackedEthSignature {
+    fn from(signature: Signature) -> Self {
+        Self(signature)
+    }
+}
+
+impl fmt::Display for PackedEthSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", hex::encode(self.serialize_packed()))
+    }
+}
+
+impl Serialize for PackedEthSignature {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for PackedEthSignature {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let string = String::deserialize(deserializer)?;
+        Signature::from_str(&string)
+            .map(Self)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Rootstock signature as the server expects it next to a submitted transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "signature")]
+pub enum TxEthSignature {
+    EthereumSignature(PackedEthSignature),
+}
+
+impl From<PackedEthSignature> for TxEthSignature {
+    fn from(signature: PackedEthSignature) -> Self {
+        Self::EthereumSignature(signature)
+    }
+}
@@ -0,0 +1,60 @@
+use num::{BigUint, Zero};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    eth_message_with_fee_and_nonce, format_units, TimeRange, TxSignature, CURRENT_TX_VERSION,
+};
+use crate::rollup::types::packing::{pack_fee_amount, pack_token_amount};
+use crate::rollup::types::serde_wrappers::BigUintSerdeAsRadix10Str;
+use crate::rollup::types::{AccountId, Address, Nonce, TokenId};
+
+/// Transfer of funds between two L2 accounts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transfer {
+    pub account_id: AccountId,
+    pub from: Address,
+    pub to: Address,
+    pub token: TokenId,
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    pub amount: BigUint,
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    pub fee: BigUint,
+    pub nonce: Nonce,
+    pub signature: TxSignature,
+    #[serde(flatten)]
+    pub time_range: TimeRange,
+}
+
+impl Transfer {
+    pub const TX_TYPE: u8 = 5;
+
+    /// Bytes signed by the L2 signing key, amount and fee must be packable.
+    pub fn get_bytes(&self) -> Vec<u8> {
+        let mut out = vec![255 - Self::TX_TYPE, CURRENT_TX_VERSION];
+        out.extend_from_slice(&self.account_id.to_be_bytes());
+        out.extend_from_slice(self.from.as_bytes());
+        out.extend_from_slice(self.to.as_bytes());
+        out.extend_from_slice(&self.token.to_be_bytes());
+        out.extend(pack_token_amount(&self.amount).expect("amount is checked before signing"));
+        out.extend(pack_fee_amount(&self.fee).expect("fee is checked before signing"));
+        out.extend_from_slice(&self.nonce.to_be_bytes());
+        out.extend_from_slice(&self.time_range.as_be_bytes());
+        out
+    }
+
+    /// Message signed by the Rootstock key of the sender.
+    pub fn get_ethereum_sign_message(&self, token_symbol: &str, decimals: u8) -> String {
+        let message = if self.amount.is_zero() {
+            String::new()
+        } else {
+            format!(
+                "Transfer {} {}\nTo: {:?}",
+                format_units(&self.amount, decimals),
+                token_symbol,
+                self.to
+            )
+        };
+        eth_message_with_fee_and_nonce(message, &self.fee, token_symbol, decimals, *self.nonce)
+    }
+}
@@ -0,0 +1,51 @@
+use num::BigUint;
+use serde::{Deserialize, Serialize};
+
+use super::{eth_message_with_fee_and_nonce, TimeRange, TxSignature, CURRENT_TX_VERSION};
+use crate::rollup::types::packing::pack_fee_amount;
+use crate::rollup::types::serde_wrappers::BigUintSerdeAsRadix10Str;
+use crate::rollup::types::{AccountId, Address, Nonce, TokenId};
+
+/// Withdrawal of an NFT from an L2 account to a Rootstock address, the fee paid in another token.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WithdrawNFT {
+    pub account_id: AccountId,
+    pub from: Address,
+    pub to: Address,
+    pub token: TokenId,
+    pub fee_token: TokenId,
+    #[serde(with = "BigUintSerdeAsRadix10Str")]
+    pub fee: BigUint,
+    pub nonce: Nonce,
+    pub signature: TxSignature,
+    /// Requests the withdrawal to be processed without waiting for the block to fill up.
+    #[serde(default)]
+    pub fast: bool,
+    #[serde(flatten)]
+    pub time_range: TimeRange,
+}
+
+impl WithdrawNFT {
+    pub const TX_TYPE: u8 = 10;
+
+    /// Bytes signed by the L2 signing key, the fee must be packable.
+    pub fn get_bytes(&self) -> Vec<u8> {
+        let mut out = vec![255 - Self::TX_TYPE, CURRENT_TX_VERSION];
+        out.extend_from_slice(&self.account_id.to_be_bytes());
+        out.extend_from_slice(self.from.as_bytes());
+        out.extend_from_slice(self.to.as_bytes());
+        out.extend_from_slice(&self.token.to_be_bytes());
+        out.extend_from_slice(&self.fee_token.to_be_bytes());
+        out.extend(pack_fee_amount(&self.fee).expect("fee is checked before signing"));
+        out.extend_from_slice(&self.nonce.to_be_bytes());
+        out.extend_from_slice(&self.time_range.as_be_bytes());
+        out
+    }
+
+    /// Message signed by the Rootstock key of the account owner.
+    pub fn get_ethereum_sign_message(&self, fee_token_symbol: &str, decimals: u8) -> String {
+        let message = format!("WithdrawNFT {} to: {:?}", *self.token, self.to);
+        eth_message_with_fee_and_nonce(message, &self.fee, fee_token_symbol, decimals, *self.nonce)
+    }
+}
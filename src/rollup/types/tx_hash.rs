@@ -0,0 +1,83 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TxHashDecodeError {
+    #[error("TxHash should start with sync-tx:")]
+    PrefixError,
+    #[error("Cannot decode Hex: {0}")]
+    DecodeHex(#[from] hex::FromHexError),
+    #[error("TxHash size should be equal to 32")]
+    IncorrectHashLength,
+}
+
+/// Hash of the zkSync transaction.
+///
+/// Hexadecimal form of the hash is prepended with the `sync-tx:` prefix.
+#[derive(Copy, Clone, PartialEq, Default, Eq, Hash, PartialOrd, Ord)]
+pub struct TxHash {
+    pub data: [u8; 32],
+}
+
+impl TxHash {
+    /// Reads a transaction hash from its byte sequence representation.
+    ///
+    /// Returns none if the slice length does not match with hash length.
+    pub fn from_slice(slice: &[u8]) -> Option<Self> {
+        let data: [u8; 32] = slice.try_into().ok()?;
+        Some(Self { data })
+    }
+}
+
+impl AsRef<[u8]> for TxHash {
+    fn as_ref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl fmt::Debug for TxHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl fmt::Display for TxHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sync-tx:{}", hex::encode(self.data))
+    }
+}
+
+impl FromStr for TxHash {
+    type Err = TxHashDecodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s
+            .strip_prefix("sync-tx:")
+            .or_else(|| s.strip_prefix("0x"))
+            .ok_or(TxHashDecodeError::PrefixError)?;
+        let bytes = hex::decode(s)?;
+        Self::from_slice(&bytes).ok_or(TxHashDecodeError::IncorrectHashLength)
+    }
+}
+
+impl Serialize for TxHash {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for TxHash {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let string = String::deserialize(deserializer)?;
+        string.parse().map_err(serde::de::Error::custom)
+    }
+}
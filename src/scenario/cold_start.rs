@@ -0,0 +1,457 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::pause::PauseControl;
+use crate::report::latency::Percentiles;
+use crate::report::{now_ms, RunRecorder, TxRecord, TxStatus};
+
+/// Scenario feature measuring how the rollup recovers from a restart, configured in
+/// the `[scenarios.cold_start]` section.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ColdStartConfig {
+    /// Time between two probe transactions while waiting for the restarted node.
+    #[serde(default = "ColdStartConfig::default_probe_interval_ms")]
+    pub probe_interval_ms: u64,
+    /// Longest wait for the first accepted transaction before the scenario fails.
+    #[serde(default = "ColdStartConfig::default_max_wait_secs")]
+    pub max_wait_secs: u64,
+    /// Time between two samples of the backlog of not yet committed transactions.
+    #[serde(default = "ColdStartConfig::default_backlog_interval_ms")]
+    pub backlog_interval_ms: u64,
+    /// How long the backlog is followed after the node is back, unless it drains earlier.
+    #[serde(default = "ColdStartConfig::default_observe_secs")]
+    pub observe_secs: u64,
+    /// Width of the buckets of the latency recovery curve.
+    #[serde(default = "ColdStartConfig::default_bucket_secs")]
+    pub bucket_secs: u64,
+    /// Latency is considered recovered once the median of a bucket is within this
+    /// percentage of the median before the restart.
+    #[serde(default = "ColdStartConfig::default_recovery_threshold_percent")]
+    pub recovery_threshold_percent: f64,
+}
+
+impl ColdStartConfig {
+    fn default_probe_interval_ms() -> u64 {
+        500
+    }
+
+    fn default_max_wait_secs() -> u64 {
+        600
+    }
+
+    fn default_backlog_interval_ms() -> u64 {
+        1_000
+    }
+
+    fn default_observe_secs() -> u64 {
+        600
+    }
+
+    fn default_bucket_secs() -> u64 {
+        10
+    }
+
+    fn default_recovery_threshold_percent() -> f64 {
+        20.0
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ColdStartError {
+    #[error(
+        "No transaction accepted within {max_wait_secs}s of the restart, last error: {last_error}"
+    )]
+    NotAccepting {
+        max_wait_secs: u64,
+        last_error: String,
+    },
+}
+
+/// Rollup node being restarted, as seen by the simulator.
+#[async_trait]
+pub trait RestartTarget: Send + Sync {
+    /// Submits a probe transaction, `Ok` once the node accepted it.
+    async fn probe(&self) -> Result<(), String>;
+
+    /// Number of transactions submitted before or during the restart that are not committed yet.
+    async fn backlog(&self) -> Result<usize, String>;
+}
+
+/// Rollup restarted under the ongoing traffic of the run.
+///
+/// Probes do not send transactions of their own: a probe succeeds once the traffic got a
+/// transaction accepted since the first probe.
+pub struct RunningTraffic {
+    recorder: Arc<RunRecorder>,
+    probing_since_ms: Mutex<Option<u128>>,
+}
+
+impl RunningTraffic {
+    pub fn new(recorder: Arc<RunRecorder>) -> Self {
+        Self {
+            recorder,
+            probing_since_ms: Mutex::new(None),
+        }
+    }
+}
+
+#[async_trait]
+impl RestartTarget for RunningTraffic {
+    async fn probe(&self) -> Result<(), String> {
+        let since_ms = *self
+            .probing_since_ms
+            .lock()
+            .unwrap()
+            .get_or_insert_with(now_ms);
+        match self.recorder.accepted_since(since_ms) {
+            true => Ok(()),
+            false => Err("no transaction accepted since the restart".to_string()),
+        }
+    }
+
+    async fn backlog(&self) -> Result<usize, String> {
+        Ok(self.recorder.uncommitted())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BacklogSample {
+    /// Time since the node accepted the first transaction.
+    pub at_ms: f64,
+    pub backlog: usize,
+}
+
+/// Response times of the transactions submitted within one bucket after the restart.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyBucket {
+    /// Start of the bucket, relative to the restart.
+    pub from_ms: u64,
+    pub count: usize,
+    pub response_time: Percentiles,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColdStartReport {
+    /// Time from resuming after the restart until the node accepted a transaction.
+    pub time_to_first_tx_ms: f64,
+    pub probe_attempts: u32,
+    pub backlog: Vec<BacklogSample>,
+    pub peak_backlog: usize,
+    /// Transactions committed per second between the peak of the backlog and the last sample.
+    pub drain_rate_per_sec: f64,
+    /// Time since the first accepted transaction at which the backlog was empty.
+    pub drained_after_ms: Option<f64>,
+    /// Median response time of the transactions submitted before the restart.
+    pub baseline_p50_ms: f64,
+    pub latency_curve: Vec<LatencyBucket>,
+    /// Start of the first bucket whose median is back within the threshold of the baseline.
+    pub recovered_after_ms: Option<u64>,
+}
+
+/// Restart of the rollup coordinated with the operator through a pause point.
+///
+/// The run halts until the operator restarted the node and resumed it. From then on the
+/// scenario measures how long the node takes to accept a transaction again, how fast the
+/// backlog of transactions submitted around the restart is committed, and how response
+/// times of the ongoing traffic converge back to what they were before the restart.
+pub struct ColdStart {
+    config: ColdStartConfig,
+}
+
+impl ColdStart {
+    pub fn new(config: ColdStartConfig) -> Self {
+        Self { config }
+    }
+
+    /// Waits for the restart, then measures the recovery.
+    ///
+    /// `records` is called once the backlog is followed and must return the records of
+    /// the traffic that kept running during the scenario.
+    pub async fn run<F>(
+        &self,
+        pause: &PauseControl,
+        read_stdin: bool,
+        target: &dyn RestartTarget,
+        records: F,
+    ) -> Result<ColdStartReport, ColdStartError>
+    where
+        F: FnOnce() -> Vec<TxRecord>,
+    {
+        pause.wait("restart the rollup node", read_stdin).await;
+        let restart_at_ms = now_ms();
+
+        let (time_to_first_tx, probe_attempts) = self.wait_first_tx(target).await?;
+        let backlog = self.follow_backlog(target).await;
+        let (baseline_p50_ms, latency_curve) = self.latency_curve(&records(), restart_at_ms);
+
+        let peak = backlog
+            .iter()
+            .copied()
+            .max_by_key(|sample| sample.backlog)
+            .unwrap_or(BacklogSample {
+                at_ms: 0.0,
+                backlog: 0,
+            });
+        let drain_rate_per_sec = match backlog.last() {
+            Some(last) if last.at_ms > peak.at_ms => {
+                peak.backlog.saturating_sub(last.backlog) as f64 * 1000.0
+                    / (last.at_ms - peak.at_ms)
+            }
+            _ => 0.0,
+        };
+        let drained_after_ms = backlog
+            .iter()
+            .find(|sample| sample.backlog == 0)
+            .map(|sample| sample.at_ms);
+        let recovery_limit =
+            baseline_p50_ms * (1.0 + self.config.recovery_threshold_percent / 100.0);
+        let recovered_after_ms = latency_curve
+            .iter()
+            .find(|bucket| bucket.response_time.p50 <= recovery_limit)
+            .map(|bucket| bucket.from_ms);
+
+        Ok(ColdStartReport {
+            time_to_first_tx_ms: time_to_first_tx.as_secs_f64() * 1000.0,
+            probe_attempts,
+            peak_backlog: peak.backlog,
+            backlog,
+            drain_rate_per_sec,
+            drained_after_ms,
+            baseline_p50_ms,
+            latency_curve,
+            recovered_after_ms,
+        })
+    }
+
+    async fn wait_first_tx(
+        &self,
+        target: &dyn RestartTarget,
+    ) -> Result<(Duration, u32), ColdStartError> {
+        let started = Instant::now();
+        let max_wait = Duration::from_secs(self.config.max_wait_secs);
+        let interval = Duration::from_millis(self.config.probe_interval_ms);
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let last_error = match target.probe().await {
+                Ok(()) => return Ok((started.elapsed(), attempts)),
+                Err(err) => err,
+            };
+            if started.elapsed() >= max_wait {
+                return Err(ColdStartError::NotAccepting {
+                    max_wait_secs: self.config.max_wait_secs,
+                    last_error,
+                });
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Samples the backlog until it is empty or the observation window ends.
+    async fn follow_backlog(&self, target: &dyn RestartTarget) -> Vec<BacklogSample> {
+        let started = Instant::now();
+        let observe = Duration::from_secs(self.config.observe_secs);
+        let interval = Duration::from_millis(self.config.backlog_interval_ms);
+        let mut samples = Vec::new();
+        loop {
+            // A failed query leaves a gap in the curve, the node may still be warming up.
+            if let Ok(backlog) = target.backlog().await {
+                samples.push(BacklogSample {
+                    at_ms: started.elapsed().as_secs_f64() * 1000.0,
+                    backlog,
+                });
+                if backlog == 0 {
+                    break;
+                }
+            }
+            if started.elapsed() >= observe {
+                break;
+            }
+            tokio::time::sleep(interval).await;
+        }
+        samples
+    }
+
+    /// Median response time before the restart and the bucketed response times after it.
+    fn latency_curve(
+        &self,
+        records: &[TxRecord],
+        restart_at_ms: u128,
+    ) -> (f64, Vec<LatencyBucket>) {
+        let response_time =
+            |record: &TxRecord| Duration::from_secs_f64(record.response_time_ms / 1000.0);
+        let accepted = records
+            .iter()
+            .filter(|record| record.status != TxStatus::Rejected);
+
+        let mut before = Vec::new();
+        let mut buckets: BTreeMap<u64, Vec<Duration>> = BTreeMap::new();
+        let bucket_ms = (self.config.bucket_secs * 1000).max(1);
+        for record in accepted {
+            if record.submitted_at_ms < restart_at_ms {
+                before.push(response_time(record));
+            } else {
+                let since_restart = (record.submitted_at_ms - restart_at_ms) as u64;
+                buckets
+                    .entry(since_restart / bucket_ms * bucket_ms)
+                    .or_default()
+                    .push(response_time(record));
+            }
+        }
+
+        let curve = buckets
+            .into_iter()
+            .map(|(from_ms, samples)| LatencyBucket {
+                from_ms,
+                count: samples.len(),
+                response_time: Percentiles::from_samples(&samples),
+            })
+            .collect();
+        (Percentiles::from_samples(&before).p50, curve)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::report::latency::TxTiming;
+    use crate::rollup::types::TxHash;
+
+    struct RestartingNode {
+        refusals: Mutex<u32>,
+        backlog: Mutex<Vec<usize>>,
+    }
+
+    #[async_trait]
+    impl RestartTarget for RestartingNode {
+        async fn probe(&self) -> Result<(), String> {
+            let mut refusals = self.refusals.lock().unwrap();
+            if *refusals > 0 {
+                *refusals -= 1;
+                return Err("connection refused".to_string());
+            }
+            Ok(())
+        }
+
+        async fn backlog(&self) -> Result<usize, String> {
+            let mut backlog = self.backlog.lock().unwrap();
+            Ok(if backlog.len() > 1 {
+                backlog.remove(0)
+            } else {
+                backlog[0]
+            })
+        }
+    }
+
+    fn record(submitted_at_ms: u128, response_time_ms: f64) -> TxRecord {
+        TxRecord {
+            tx_hash: None,
+            tx_type: "transfer".to_string(),
+            submitted_at_ms,
+            service_time_ms: response_time_ms,
+            response_time_ms,
+            fee: None,
+            status: TxStatus::Committed,
+            fail_reason: None,
+            commit_latency_ms: None,
+            verify_latency_ms: None,
+        }
+    }
+
+    /// Tests time to the first accepted tx, backlog drain and the latency recovery point.
+    #[tokio::test]
+    async fn test_cold_start_recovery() {
+        let cold_start = ColdStart::new(ColdStartConfig {
+            probe_interval_ms: 1,
+            max_wait_secs: 60,
+            backlog_interval_ms: 1,
+            observe_secs: 60,
+            bucket_secs: 10,
+            recovery_threshold_percent: 20.0,
+        });
+        let node = RestartingNode {
+            refusals: Mutex::new(3),
+            backlog: Mutex::new(vec![40, 60, 30, 0]),
+        };
+        let pause = PauseControl::new();
+        let resumer = pause.clone();
+        tokio::spawn(async move {
+            while !resumer.resume() {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        });
+
+        let report = cold_start
+            .run(&pause, false, &node, || {
+                let restart = now_ms();
+                let mut records: Vec<TxRecord> = (1..=5)
+                    .map(|i| record(restart - 1_000 * i, 100.0))
+                    .collect();
+                records.extend([
+                    record(restart + 1_000, 5_000.0),
+                    record(restart + 12_000, 400.0),
+                    record(restart + 25_000, 110.0),
+                    record(restart + 31_000, 90.0),
+                ]);
+                records
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(report.probe_attempts, 4);
+        assert_eq!(report.peak_backlog, 60);
+        assert_eq!(report.backlog.len(), 4);
+        assert!(report.drained_after_ms.is_some());
+        assert!(report.drain_rate_per_sec > 0.0);
+        assert_eq!(report.baseline_p50_ms, 100.0);
+        assert_eq!(
+            report
+                .latency_curve
+                .iter()
+                .map(|bucket| bucket.from_ms)
+                .collect::<Vec<_>>(),
+            vec![0, 10_000, 20_000, 30_000]
+        );
+        assert_eq!(report.recovered_after_ms, Some(20_000));
+    }
+
+    /// Tests that the traffic probe waits for a transaction accepted after the restart.
+    #[tokio::test]
+    async fn test_running_traffic() {
+        let recorder = Arc::new(RunRecorder::new());
+        let now = Instant::now();
+        let timing = TxTiming {
+            intended_start: now,
+            actual_start: now,
+            completed: now,
+        };
+        let tx_hash = TxHash { data: [1; 32] };
+        recorder.record_tx(
+            "Transfer",
+            Some(tx_hash),
+            None,
+            &timing,
+            TxStatus::Submitted,
+        );
+        let traffic = RunningTraffic::new(recorder.clone());
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert!(traffic.probe().await.is_err());
+        recorder.record_rejected("Transfer", None, &timing, "connection refused".to_string());
+        assert!(traffic.probe().await.is_err());
+        assert_eq!(traffic.backlog().await, Ok(1));
+
+        recorder.record_tx("Transfer", None, None, &timing, TxStatus::Committed);
+        assert!(traffic.probe().await.is_ok());
+        recorder.update_status(&tx_hash, TxStatus::Committed, None);
+        assert_eq!(traffic.backlog().await, Ok(0));
+    }
+}
@@ -0,0 +1,436 @@
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::rollup::types::BlockNumber;
+
+/// Time between two attempts of a transfer rejected while its deposit is pending.
+const RETRY_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Built-in correctness scenario: deposits into accounts while concurrently transferring
+/// out of them amounts that only fit once the deposit is applied.
+#[derive(Debug, Clone, Deserialize)]
+pub struct InterleavingConfig {
+    pub accounts: usize,
+    pub rounds: usize,
+    pub deposit_amount: u64,
+    /// How far below the post-deposit balance the transfer goes, must cover the fee.
+    #[serde(default = "InterleavingConfig::default_headroom")]
+    pub headroom: u64,
+}
+
+impl InterleavingConfig {
+    fn default_headroom() -> u64 {
+        1_000
+    }
+}
+
+/// Deposit and transfer submitted concurrently on the same account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterleavingRound {
+    pub account: usize,
+    /// Committed balance when the round was planned.
+    pub balance_before: u64,
+    pub deposit: u64,
+    pub transfer: u64,
+}
+
+/// Outcome of a single attempt to submit the round's transfer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransferAttempt {
+    Rejected {
+        reason: String,
+        /// Whether the deposit was already reported as applied when the transfer was rejected.
+        deposit_applied: bool,
+    },
+    Included {
+        block: BlockNumber,
+        fee: u64,
+    },
+}
+
+#[derive(Debug, Clone, Default)]
+struct RoundObservation {
+    deposit_block: Option<BlockNumber>,
+    attempts: Vec<TransferAttempt>,
+    final_balance: Option<u64>,
+}
+
+/// Inconsistency between the order the rollup applied operations in and the balances it reported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterleavingAnomaly {
+    /// The transfer was rejected at least once and succeeded on a later attempt.
+    RejectedThenSucceeded { round: usize, rejections: usize },
+    /// The transfer was rejected although the deposit funding it had already been applied.
+    RejectedAfterDeposit { round: usize, reason: String },
+    /// The transfer was included in a block before the deposit it depends on.
+    TransferBeforeDeposit {
+        round: usize,
+        transfer_block: BlockNumber,
+        deposit_block: BlockNumber,
+    },
+    /// The final balance does not match the deposit and transfer that were applied.
+    BalanceMismatch {
+        round: usize,
+        expected: u64,
+        actual: u64,
+    },
+}
+
+impl fmt::Display for InterleavingAnomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterleavingAnomaly::RejectedThenSucceeded { round, rejections } => write!(
+                f,
+                "round {}: transfer rejected {} time(s) before it succeeded",
+                round, rejections
+            ),
+            InterleavingAnomaly::RejectedAfterDeposit { round, reason } => write!(
+                f,
+                "round {}: transfer rejected after the deposit was applied: {}",
+                round, reason
+            ),
+            InterleavingAnomaly::TransferBeforeDeposit {
+                round,
+                transfer_block,
+                deposit_block,
+            } => write!(
+                f,
+                "round {}: transfer in block {} precedes its deposit in block {}",
+                round, transfer_block, deposit_block
+            ),
+            InterleavingAnomaly::BalanceMismatch {
+                round,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "round {}: final balance {} differs from expected {}",
+                round, actual, expected
+            ),
+        }
+    }
+}
+
+/// Accounts the rounds run on, referred to by their index.
+#[async_trait]
+pub trait InterleavingTarget: Send + Sync {
+    /// Committed balance of the account.
+    async fn balance(&self, account: usize) -> Result<u64, String>;
+
+    /// Deposits into the account, returning the block the rollup applied the deposit in.
+    async fn deposit(&self, account: usize, amount: u64) -> Result<BlockNumber, String>;
+
+    /// Transfers out of the account and waits until the transfer is executed, returning its
+    /// block and fee, or why it was rejected.
+    async fn transfer(&self, account: usize, amount: u64) -> Result<(BlockNumber, u64), String>;
+}
+
+pub struct InterleavingScenario {
+    config: InterleavingConfig,
+    rounds: Vec<InterleavingRound>,
+    observations: Vec<RoundObservation>,
+}
+
+impl InterleavingScenario {
+    pub fn new(config: InterleavingConfig) -> Self {
+        Self {
+            config,
+            rounds: Vec::new(),
+            observations: Vec::new(),
+        }
+    }
+
+    /// Whether all configured rounds were planned.
+    pub fn is_done(&self) -> bool {
+        self.rounds.len() >= self.config.rounds
+    }
+
+    /// Plans the next round on the given account and returns its index.
+    pub fn plan_round(&mut self, account: usize, balance: u64) -> usize {
+        let deposit = self.config.deposit_amount;
+        let transfer = (balance + deposit).saturating_sub(self.config.headroom);
+        self.rounds.push(InterleavingRound {
+            account: account % self.config.accounts.max(1),
+            balance_before: balance,
+            deposit,
+            transfer,
+        });
+        self.observations.push(RoundObservation::default());
+        self.rounds.len() - 1
+    }
+
+    pub fn round(&self, round: usize) -> &InterleavingRound {
+        &self.rounds[round]
+    }
+
+    pub fn record_deposit(&mut self, round: usize, block: BlockNumber) {
+        self.observations[round].deposit_block = Some(block);
+    }
+
+    pub fn record_attempt(&mut self, round: usize, attempt: TransferAttempt) {
+        self.observations[round].attempts.push(attempt);
+    }
+
+    pub fn record_final_balance(&mut self, round: usize, balance: u64) {
+        self.observations[round].final_balance = Some(balance);
+    }
+
+    /// Runs all rounds one after the other, cycling over the accounts, and checks them.
+    ///
+    /// A round submits its deposit and transfer at the same time. A transfer rejected while
+    /// the deposit is pending is retried until it succeeds or is rejected once more after the
+    /// deposit was applied.
+    pub async fn run(
+        &mut self,
+        target: &dyn InterleavingTarget,
+    ) -> Result<Vec<InterleavingAnomaly>, String> {
+        let mut account = 0;
+        while !self.is_done() {
+            let balance = target.balance(account).await?;
+            let round = self.plan_round(account, balance);
+            let planned = self.round(round).clone();
+
+            // `None` while the deposit is pending, then whether it was applied.
+            let deposited = Mutex::new(None);
+            let deposit = async {
+                let result = target.deposit(planned.account, planned.deposit).await;
+                *deposited.lock().unwrap() = Some(result.is_ok());
+                result
+            };
+            let transfer = async {
+                let mut attempts = Vec::new();
+                loop {
+                    let result = target.transfer(planned.account, planned.transfer).await;
+                    let deposit_state = *deposited.lock().unwrap();
+                    match result {
+                        Ok((block, fee)) => {
+                            attempts.push(TransferAttempt::Included { block, fee });
+                            return attempts;
+                        }
+                        Err(reason) => attempts.push(TransferAttempt::Rejected {
+                            reason,
+                            deposit_applied: deposit_state == Some(true),
+                        }),
+                    }
+                    if deposit_state.is_some() {
+                        return attempts;
+                    }
+                    tokio::time::sleep(RETRY_INTERVAL).await;
+                }
+            };
+            let (deposit, attempts) = tokio::join!(deposit, transfer);
+
+            if let Ok(block) = deposit {
+                self.record_deposit(round, block);
+            }
+            for attempt in attempts {
+                self.record_attempt(round, attempt);
+            }
+            if let Ok(balance) = target.balance(planned.account).await {
+                self.record_final_balance(round, balance);
+            }
+            account = (account + 1) % self.config.accounts.max(1);
+        }
+        Ok(self.anomalies())
+    }
+
+    /// Checks every round for inconsistent ordering of its deposit and transfer.
+    pub fn anomalies(&self) -> Vec<InterleavingAnomaly> {
+        let mut anomalies = Vec::new();
+        for (index, (round, observed)) in self.rounds.iter().zip(&self.observations).enumerate() {
+            let included = observed.attempts.iter().find_map(|attempt| match attempt {
+                TransferAttempt::Included { block, fee } => Some((*block, *fee)),
+                _ => None,
+            });
+
+            let mut rejections = 0;
+            for attempt in &observed.attempts {
+                if let TransferAttempt::Rejected {
+                    reason,
+                    deposit_applied,
+                } = attempt
+                {
+                    rejections += 1;
+                    if *deposit_applied {
+                        anomalies.push(InterleavingAnomaly::RejectedAfterDeposit {
+                            round: index,
+                            reason: reason.clone(),
+                        });
+                    }
+                }
+            }
+            if rejections > 0 && included.is_some() {
+                anomalies.push(InterleavingAnomaly::RejectedThenSucceeded {
+                    round: index,
+                    rejections,
+                });
+            }
+
+            if let (Some((transfer_block, _)), Some(deposit_block)) =
+                (included, observed.deposit_block)
+            {
+                if transfer_block < deposit_block && round.transfer > round.balance_before {
+                    anomalies.push(InterleavingAnomaly::TransferBeforeDeposit {
+                        round: index,
+                        transfer_block,
+                        deposit_block,
+                    });
+                }
+            }
+
+            if let Some(actual) = observed.final_balance {
+                let mut expected = round.balance_before;
+                if observed.deposit_block.is_some() {
+                    expected += round.deposit;
+                }
+                if let Some((_, fee)) = included {
+                    expected = expected.saturating_sub(round.transfer + fee);
+                }
+                if actual != expected {
+                    anomalies.push(InterleavingAnomaly::BalanceMismatch {
+                        round: index,
+                        expected,
+                        actual,
+                    });
+                }
+            }
+        }
+        anomalies
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Rollup applying deposits in the next block, where transfers that fit the balance land.
+    struct FakeRollup {
+        balances: Mutex<Vec<u64>>,
+        block: Mutex<u32>,
+    }
+
+    #[async_trait]
+    impl InterleavingTarget for FakeRollup {
+        async fn balance(&self, account: usize) -> Result<u64, String> {
+            Ok(self.balances.lock().unwrap()[account])
+        }
+
+        async fn deposit(&self, account: usize, amount: u64) -> Result<BlockNumber, String> {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            self.balances.lock().unwrap()[account] += amount;
+            let mut block = self.block.lock().unwrap();
+            *block += 1;
+            Ok(BlockNumber(*block))
+        }
+
+        async fn transfer(
+            &self,
+            account: usize,
+            amount: u64,
+        ) -> Result<(BlockNumber, u64), String> {
+            let mut balances = self.balances.lock().unwrap();
+            if balances[account] < amount + 5 {
+                return Err("not enough balance".to_string());
+            }
+            balances[account] -= amount + 5;
+            let mut block = self.block.lock().unwrap();
+            *block += 1;
+            Ok((BlockNumber(*block), 5))
+        }
+    }
+
+    /// Tests that a transfer waiting for its deposit is only reported as retried.
+    #[tokio::test]
+    async fn test_interleaving_run() {
+        let mut scenario = InterleavingScenario::new(InterleavingConfig {
+            accounts: 2,
+            rounds: 3,
+            deposit_amount: 500,
+            headroom: 10,
+        });
+        let rollup = FakeRollup {
+            balances: Mutex::new(vec![100, 100]),
+            block: Mutex::new(0),
+        };
+
+        let anomalies = scenario.run(&rollup).await.unwrap();
+
+        assert_eq!(anomalies.len(), 3);
+        assert!(anomalies
+            .iter()
+            .all(|anomaly| matches!(anomaly, InterleavingAnomaly::RejectedThenSucceeded { .. })));
+        assert_eq!(*rollup.balances.lock().unwrap(), vec![5, 5]);
+    }
+
+    /// Tests that retried and misordered transfers are reported.
+    #[test]
+    fn test_interleaving_anomalies() {
+        let mut scenario = InterleavingScenario::new(InterleavingConfig {
+            accounts: 2,
+            rounds: 3,
+            deposit_amount: 500,
+            headroom: 10,
+        });
+
+        // Transfer rejected before the deposit landed, then retried: expected retry only.
+        let retried = scenario.plan_round(0, 100);
+        assert_eq!(scenario.round(retried).transfer, 590);
+        scenario.record_attempt(
+            retried,
+            TransferAttempt::Rejected {
+                reason: "not enough balance".to_string(),
+                deposit_applied: false,
+            },
+        );
+        scenario.record_deposit(retried, BlockNumber(5));
+        scenario.record_attempt(
+            retried,
+            TransferAttempt::Included {
+                block: BlockNumber(6),
+                fee: 5,
+            },
+        );
+        scenario.record_final_balance(retried, 5);
+
+        // Transfer applied in a block before the deposit that funds it.
+        let misordered = scenario.plan_round(1, 100);
+        scenario.record_deposit(misordered, BlockNumber(9));
+        scenario.record_attempt(
+            misordered,
+            TransferAttempt::Included {
+                block: BlockNumber(8),
+                fee: 5,
+            },
+        );
+        scenario.record_final_balance(misordered, 7);
+
+        let consistent = scenario.plan_round(2, 100);
+        assert_eq!(scenario.round(consistent).account, 0);
+        scenario.record_deposit(consistent, BlockNumber(10));
+        assert!(scenario.is_done());
+
+        assert_eq!(
+            scenario.anomalies(),
+            vec![
+                InterleavingAnomaly::RejectedThenSucceeded {
+                    round: 0,
+                    rejections: 1
+                },
+                InterleavingAnomaly::TransferBeforeDeposit {
+                    round: 1,
+                    transfer_block: BlockNumber(8),
+                    deposit_block: BlockNumber(9)
+                },
+                InterleavingAnomaly::BalanceMismatch {
+                    round: 1,
+                    expected: 5,
+                    actual: 7
+                },
+            ]
+        );
+    }
+}
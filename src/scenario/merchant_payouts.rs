@@ -0,0 +1,324 @@
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::report::latency::Percentiles;
+use crate::shutdown::Shutdown;
+
+/// Built-in payment-processor scenario: many payers continuously pay a few merchants,
+/// and every merchant periodically withdraws what it accumulated in a single batch.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MerchantPayoutsConfig {
+    pub payers: usize,
+    pub merchants: usize,
+    pub min_payment: u64,
+    pub max_payment: u64,
+    /// How often each merchant batch-withdraws its accumulated funds.
+    pub payout_interval_secs: u64,
+    /// Merchants holding less than this are skipped until the next payout.
+    #[serde(default)]
+    pub min_payout: u64,
+    /// Maximum number of withdrawals put in a single batch.
+    #[serde(default = "MerchantPayoutsConfig::default_max_batch_size")]
+    pub max_batch_size: usize,
+    /// Maximum amount of a single withdrawal in a payout batch.
+    pub max_withdrawal: u64,
+}
+
+impl MerchantPayoutsConfig {
+    fn default_max_batch_size() -> usize {
+        10
+    }
+}
+
+/// Operation planned by the scenario, accounts are referred to by their index in the role.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MerchantStep {
+    /// Transfer from a payer to a merchant.
+    Payment {
+        payer: usize,
+        merchant: usize,
+        amount: u64,
+    },
+    /// Batch of withdrawals emptying a merchant's accumulated balance.
+    Payout {
+        merchant: usize,
+        withdrawals: Vec<u64>,
+    },
+}
+
+/// Accounts of the scenario, payers and merchants referred to by their index in the role.
+#[async_trait]
+pub trait MerchantAccounts: Send + Sync {
+    /// Transfers `amount` from the payer to the merchant.
+    async fn pay(&self, payer: usize, merchant: usize, amount: u64) -> Result<(), String>;
+
+    /// Sends the withdrawals of the merchant as a single batch.
+    async fn pay_out(&self, merchant: usize, withdrawals: &[u64]) -> Result<(), String>;
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MerchantPayoutsReport {
+    pub payments: usize,
+    pub failed_payments: usize,
+    pub payouts: usize,
+    pub failed_payouts: usize,
+    /// Sum of the withdrawals of the accepted payout batches.
+    pub paid_out: u64,
+    pub payment_response: Percentiles,
+    pub payout_response: Percentiles,
+}
+
+pub struct MerchantPayoutScenario {
+    config: MerchantPayoutsConfig,
+    accumulated: Vec<u64>,
+    last_payout: Duration,
+}
+
+impl MerchantPayoutScenario {
+    pub fn new(config: MerchantPayoutsConfig) -> Self {
+        Self {
+            accumulated: vec![0; config.merchants],
+            config,
+            last_payout: Duration::ZERO,
+        }
+    }
+
+    /// Plans the next payer to merchant transfer.
+    pub fn next_payment<R: Rng>(&mut self, rng: &mut R) -> MerchantStep {
+        let payer = rng.gen_range(0..self.config.payers);
+        let merchant = rng.gen_range(0..self.config.merchants);
+        let amount = rng.gen_range(self.config.min_payment..=self.config.max_payment);
+        self.accumulated[merchant] += amount;
+
+        MerchantStep::Payment {
+            payer,
+            merchant,
+            amount,
+        }
+    }
+
+    /// Plans payout batches of all merchants once the payout interval elapsed since the last one.
+    pub fn payouts_due(&mut self, elapsed: Duration) -> Vec<MerchantStep> {
+        let interval = Duration::from_secs(self.config.payout_interval_secs);
+        if elapsed.saturating_sub(self.last_payout) < interval {
+            return Vec::new();
+        }
+        self.last_payout = elapsed;
+
+        let mut payouts = Vec::new();
+        for (merchant, accumulated) in self.accumulated.iter_mut().enumerate() {
+            if *accumulated == 0 || *accumulated < self.config.min_payout {
+                continue;
+            }
+            let withdrawals = split_payout(
+                accumulated,
+                self.config.max_withdrawal,
+                self.config.max_batch_size,
+            );
+            payouts.push(MerchantStep::Payout {
+                merchant,
+                withdrawals,
+            });
+        }
+        payouts
+    }
+
+    /// Funds received by each merchant and not withdrawn yet.
+    pub fn accumulated(&self) -> &[u64] {
+        &self.accumulated
+    }
+
+    /// Sends `tps` payments per second until `duration` passes or a shutdown is requested,
+    /// paying the merchants out whenever the payout interval elapsed.
+    ///
+    /// Payments and payouts the rollup refused are taken back, so merchants only withdraw
+    /// what they actually received.
+    pub async fn run<R: Rng + Send>(
+        &mut self,
+        accounts: &dyn MerchantAccounts,
+        rng: &mut R,
+        tps: u32,
+        duration: Duration,
+        shutdown: &Shutdown,
+    ) -> MerchantPayoutsReport {
+        let started = Instant::now();
+        let mut ticks = tokio::time::interval(Duration::from_secs(1) / tps.max(1));
+        ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut report = MerchantPayoutsReport::default();
+        let (mut payment_times, mut payout_times) = (Vec::new(), Vec::new());
+        while started.elapsed() < duration && !shutdown.is_requested() {
+            ticks.tick().await;
+            let MerchantStep::Payment {
+                payer,
+                merchant,
+                amount,
+            } = self.next_payment(rng)
+            else {
+                unreachable!("next_payment plans payments");
+            };
+            let sent = Instant::now();
+            match accounts.pay(payer, merchant, amount).await {
+                Ok(()) => {
+                    report.payments += 1;
+                    payment_times.push(sent.elapsed());
+                }
+                Err(_) => {
+                    report.failed_payments += 1;
+                    self.accumulated[merchant] -= amount;
+                }
+            }
+
+            for step in self.payouts_due(started.elapsed()) {
+                let MerchantStep::Payout {
+                    merchant,
+                    withdrawals,
+                } = step
+                else {
+                    continue;
+                };
+                let sent = Instant::now();
+                let total: u64 = withdrawals.iter().sum();
+                match accounts.pay_out(merchant, &withdrawals).await {
+                    Ok(()) => {
+                        report.payouts += 1;
+                        report.paid_out += total;
+                        payout_times.push(sent.elapsed());
+                    }
+                    Err(_) => {
+                        report.failed_payouts += 1;
+                        self.accumulated[merchant] += total;
+                    }
+                }
+            }
+        }
+        report.payment_response = Percentiles::from_samples(&payment_times);
+        report.payout_response = Percentiles::from_samples(&payout_times);
+        report
+    }
+}
+
+/// Splits the accumulated balance into at most `max_batch_size` withdrawals,
+/// leaving whatever does not fit for the next payout.
+fn split_payout(accumulated: &mut u64, max_withdrawal: u64, max_batch_size: usize) -> Vec<u64> {
+    let mut withdrawals = Vec::new();
+    while *accumulated > 0 && withdrawals.len() < max_batch_size.max(1) {
+        let amount = (*accumulated).min(max_withdrawal.max(1));
+        *accumulated -= amount;
+        withdrawals.push(amount);
+    }
+    withdrawals
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    /// Accepts every payout, and the payments of all payers but the first.
+    struct FakeMerchants {
+        received: Mutex<Vec<u64>>,
+        withdrawn: Mutex<Vec<u64>>,
+    }
+
+    #[async_trait]
+    impl MerchantAccounts for FakeMerchants {
+        async fn pay(&self, payer: usize, merchant: usize, amount: u64) -> Result<(), String> {
+            if payer == 0 {
+                return Err("not enough balance".to_string());
+            }
+            self.received.lock().unwrap()[merchant] += amount;
+            Ok(())
+        }
+
+        async fn pay_out(&self, merchant: usize, withdrawals: &[u64]) -> Result<(), String> {
+            self.withdrawn.lock().unwrap()[merchant] += withdrawals.iter().sum::<u64>();
+            Ok(())
+        }
+    }
+
+    /// Tests that merchants withdraw accumulated payments in bounded batches.
+    #[test]
+    fn test_merchant_payouts() {
+        let mut scenario = MerchantPayoutScenario::new(MerchantPayoutsConfig {
+            payers: 50,
+            merchants: 2,
+            min_payment: 10,
+            max_payment: 10,
+            payout_interval_secs: 60,
+            min_payout: 0,
+            max_batch_size: 3,
+            max_withdrawal: 40,
+        });
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..20 {
+            scenario.next_payment(&mut rng);
+        }
+        assert_eq!(scenario.accumulated().iter().sum::<u64>(), 200);
+        assert!(scenario.payouts_due(Duration::from_secs(30)).is_empty());
+
+        let payouts = scenario.payouts_due(Duration::from_secs(60));
+        let withdrawn: u64 = payouts
+            .iter()
+            .map(|step| match step {
+                MerchantStep::Payout { withdrawals, .. } => {
+                    assert!(withdrawals.len() <= 3);
+                    assert!(withdrawals.iter().all(|amount| *amount <= 40));
+                    withdrawals.iter().sum::<u64>()
+                }
+                _ => unreachable!(),
+            })
+            .sum();
+        let left: u64 = scenario.accumulated().iter().sum();
+        assert_eq!(withdrawn + left, 200);
+    }
+
+    /// Tests that merchants only pay out the payments they received.
+    #[tokio::test]
+    async fn test_merchant_payouts_run() {
+        let mut scenario = MerchantPayoutScenario::new(MerchantPayoutsConfig {
+            payers: 3,
+            merchants: 2,
+            min_payment: 1,
+            max_payment: 10,
+            payout_interval_secs: 0,
+            min_payout: 0,
+            max_batch_size: 2,
+            max_withdrawal: 5,
+        });
+        let accounts = FakeMerchants {
+            received: Mutex::new(vec![0; 2]),
+            withdrawn: Mutex::new(vec![0; 2]),
+        };
+        let mut rng = StdRng::seed_from_u64(3);
+        let report = scenario
+            .run(
+                &accounts,
+                &mut rng,
+                200,
+                Duration::from_millis(200),
+                &Shutdown::new(),
+            )
+            .await;
+
+        assert!(report.payments > 0 && report.failed_payments > 0);
+        assert!(report.payouts > 0);
+        assert_eq!(report.failed_payouts, 0);
+        let received = accounts.received.lock().unwrap().clone();
+        let withdrawn = accounts.withdrawn.lock().unwrap().clone();
+        for merchant in 0..2 {
+            assert_eq!(
+                withdrawn[merchant] + scenario.accumulated()[merchant],
+                received[merchant]
+            );
+        }
+        assert_eq!(report.paid_out, withdrawn.iter().sum::<u64>());
+    }
+}
@@ -0,0 +1,27 @@
+use serde::Deserialize;
+
+pub mod cold_start;
+pub mod interleaving;
+pub mod merchant_payouts;
+pub mod nft_interference;
+pub mod pause;
+pub mod reconnect_storm;
+pub mod script;
+pub mod templates;
+pub mod wait_for;
+
+use self::cold_start::ColdStartConfig;
+use self::interleaving::InterleavingConfig;
+use self::merchant_payouts::MerchantPayoutsConfig;
+use self::nft_interference::NftInterferenceConfig;
+use self::reconnect_storm::ReconnectStormConfig;
+
+/// Built-in scenarios enabled in the `[scenarios]` configuration section.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BuiltinScenarios {
+    pub merchant_payouts: Option<MerchantPayoutsConfig>,
+    pub deposit_transfer_interleaving: Option<InterleavingConfig>,
+    pub reconnect_storm: Option<ReconnectStormConfig>,
+    pub cold_start: Option<ColdStartConfig>,
+    pub nft_interference: Option<NftInterferenceConfig>,
+}
@@ -0,0 +1,339 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::report::latency::Percentiles;
+use crate::report::{TxRecord, TxStatus};
+use crate::transaction::TransactionKind;
+
+/// Scenario preset measuring whether heavy NFT minting slows down ordinary transfers,
+/// configured in the `[scenarios.nft_interference]` section.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NftInterferenceConfig {
+    /// Rate of the baseline transfers, the same in every step.
+    #[serde(default = "NftInterferenceConfig::default_transfer_tps")]
+    pub transfer_tps: u32,
+    /// `MintNFT` rates added on top of the transfers, one step each, in order.
+    #[serde(default = "NftInterferenceConfig::default_nft_tps_steps")]
+    pub nft_tps_steps: Vec<u32>,
+    #[serde(default = "NftInterferenceConfig::default_step_secs")]
+    pub step_secs: u64,
+    /// Transfers count as degraded once a step's median latency exceeds the one of the
+    /// first step by this percentage.
+    #[serde(default = "NftInterferenceConfig::default_degradation_threshold_percent")]
+    pub degradation_threshold_percent: f64,
+}
+
+impl NftInterferenceConfig {
+    fn default_transfer_tps() -> u32 {
+        5
+    }
+
+    fn default_nft_tps_steps() -> Vec<u32> {
+        vec![0, 5, 10, 20]
+    }
+
+    fn default_step_secs() -> u64 {
+        120
+    }
+
+    fn default_degradation_threshold_percent() -> f64 {
+        20.0
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum NftInterferenceError {
+    #[error("The scenario has no NFT steps")]
+    NoSteps,
+    #[error("Step at {nft_tps} NFT TPS failed: {reason}")]
+    Step { nft_tps: u32, reason: String },
+}
+
+/// Load the scenario is run with.
+#[async_trait]
+pub trait MixedWorkload: Send {
+    /// Submits `tps` transactions per second of the weighted `mix` for `duration`,
+    /// returning the records of the submitted transactions.
+    async fn run(
+        &mut self,
+        tps: u32,
+        mix: &BTreeMap<TransactionKind, u32>,
+        duration: Duration,
+    ) -> Result<Vec<TxRecord>, String>;
+}
+
+/// Transfer and mint latencies at one NFT rate.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterferenceStep {
+    pub nft_tps: u32,
+    pub transfers: usize,
+    pub mints: usize,
+    pub transfer_response: Percentiles,
+    /// Time until the transfers were committed, which is where block packing shows.
+    pub transfer_commit: Option<Percentiles>,
+    pub mint_response: Percentiles,
+    /// Change of the median transfer latency against the first step, commit latency when known.
+    pub transfer_change_percent: f64,
+    pub degraded: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NftInterferenceReport {
+    pub transfer_tps: u32,
+    pub steps: Vec<InterferenceStep>,
+    /// Least squares slope of the median transfer latency over the NFT rate.
+    pub slope_ms_per_nft_tps: f64,
+    /// Lowest NFT rate at which transfers degraded.
+    pub degraded_from_nft_tps: Option<u32>,
+}
+
+/// Constant transfer load with a growing share of `MintNFT`.
+///
+/// Every step runs the same transfer rate next to more minting, so a change of the
+/// transfer latencies between steps comes from the mints competing for the same blocks
+/// and circuits. Commit latency is preferred over response time when the records carry
+/// it, as it is where packing shows.
+pub struct NftInterference {
+    config: NftInterferenceConfig,
+}
+
+impl NftInterference {
+    pub fn new(config: NftInterferenceConfig) -> Self {
+        Self { config }
+    }
+
+    /// Rate and mix of the step adding `nft_tps` mints to the transfers.
+    pub fn step_load(&self, nft_tps: u32) -> (u32, BTreeMap<TransactionKind, u32>) {
+        let mix = [
+            (TransactionKind::Transfer, self.config.transfer_tps),
+            (TransactionKind::MintNFT, nft_tps),
+        ]
+        .into_iter()
+        .filter(|(_, weight)| *weight > 0)
+        .collect();
+        (self.config.transfer_tps + nft_tps, mix)
+    }
+
+    pub async fn run(
+        &self,
+        workload: &mut dyn MixedWorkload,
+    ) -> Result<NftInterferenceReport, NftInterferenceError> {
+        if self.config.nft_tps_steps.is_empty() {
+            return Err(NftInterferenceError::NoSteps);
+        }
+        let duration = Duration::from_secs(self.config.step_secs);
+        let mut records = Vec::with_capacity(self.config.nft_tps_steps.len());
+        for &nft_tps in &self.config.nft_tps_steps {
+            let (tps, mix) = self.step_load(nft_tps);
+            let step_records = workload
+                .run(tps, &mix, duration)
+                .await
+                .map_err(|reason| NftInterferenceError::Step { nft_tps, reason })?;
+            records.push((nft_tps, step_records));
+        }
+        Ok(self.report(&records))
+    }
+
+    fn report(&self, records: &[(u32, Vec<TxRecord>)]) -> NftInterferenceReport {
+        let mut steps: Vec<InterferenceStep> = records
+            .iter()
+            .map(|(nft_tps, records)| step(*nft_tps, records))
+            .collect();
+        let medians: Vec<(f64, f64)> = steps
+            .iter()
+            .map(|step| (step.nft_tps as f64, transfer_median(step)))
+            .collect();
+        let baseline = medians.first().map_or(0.0, |(_, median)| *median);
+        for (step, (_, median)) in steps.iter_mut().zip(&medians) {
+            if baseline > 0.0 {
+                step.transfer_change_percent = (median - baseline) * 100.0 / baseline;
+            }
+            step.degraded =
+                step.transfer_change_percent > self.config.degradation_threshold_percent;
+        }
+        NftInterferenceReport {
+            transfer_tps: self.config.transfer_tps,
+            slope_ms_per_nft_tps: slope(&medians),
+            degraded_from_nft_tps: steps
+                .iter()
+                .filter(|step| step.degraded)
+                .map(|step| step.nft_tps)
+                .min(),
+            steps,
+        }
+    }
+}
+
+fn step(nft_tps: u32, records: &[TxRecord]) -> InterferenceStep {
+    let accepted = |tx_type: &'static str| {
+        records
+            .iter()
+            .filter(move |record| record.tx_type == tx_type && record.status != TxStatus::Rejected)
+    };
+    let millis = |ms: f64| Duration::from_secs_f64(ms / 1000.0);
+    let transfer_response: Vec<Duration> = accepted("transfer")
+        .map(|record| millis(record.response_time_ms))
+        .collect();
+    let transfer_commit: Vec<Duration> = accepted("transfer")
+        .filter_map(|record| record.commit_latency_ms.map(millis))
+        .collect();
+    let mint_response: Vec<Duration> = accepted("mint_nft")
+        .map(|record| millis(record.response_time_ms))
+        .collect();
+    InterferenceStep {
+        nft_tps,
+        transfers: transfer_response.len(),
+        mints: mint_response.len(),
+        transfer_response: Percentiles::from_samples(&transfer_response),
+        transfer_commit: (!transfer_commit.is_empty())
+            .then(|| Percentiles::from_samples(&transfer_commit)),
+        mint_response: Percentiles::from_samples(&mint_response),
+        transfer_change_percent: 0.0,
+        degraded: false,
+    }
+}
+
+fn transfer_median(step: &InterferenceStep) -> f64 {
+    step.transfer_commit
+        .as_ref()
+        .unwrap_or(&step.transfer_response)
+        .p50
+}
+
+/// Least squares slope of `y` over `x`, 0 when `x` does not vary.
+fn slope(points: &[(f64, f64)]) -> f64 {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let covariance: f64 = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    if variance == 0.0 {
+        0.0
+    } else {
+        covariance / variance
+    }
+}
+
+/// Renders the report as a plain text table.
+pub fn render_table(report: &NftInterferenceReport) -> String {
+    let mut table = format!(
+        "{:>8} {:>10} {:>8} {:>14} {:>14} {:>12} {:>10}\n",
+        "nft_tps", "transfers", "mints", "transfer_p50", "commit_p50", "mint_p50", "change"
+    );
+    for step in &report.steps {
+        let commit = step
+            .transfer_commit
+            .as_ref()
+            .map_or_else(|| "-".to_string(), |commit| format!("{:.1}", commit.p50));
+        let _ = writeln!(
+            table,
+            "{:>8} {:>10} {:>8} {:>14.1} {:>14} {:>12.1} {:>9.1}%{}",
+            step.nft_tps,
+            step.transfers,
+            step.mints,
+            step.transfer_response.p50,
+            commit,
+            step.mint_response.p50,
+            step.transfer_change_percent,
+            if step.degraded { " degraded" } else { "" }
+        );
+    }
+    let _ = writeln!(
+        table,
+        "Transfer latency grows {:.2} ms per NFT TPS; {}",
+        report.slope_ms_per_nft_tps,
+        match report.degraded_from_nft_tps {
+            Some(nft_tps) => format!("degraded from {} NFT TPS", nft_tps),
+            None => "no degradation".to_string(),
+        }
+    );
+    table
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Rollup whose transfer commit latency grows with the share of mints in the blocks.
+    struct PackedBlocks {
+        loads: Vec<(u32, BTreeMap<TransactionKind, u32>)>,
+    }
+
+    #[async_trait]
+    impl MixedWorkload for PackedBlocks {
+        async fn run(
+            &mut self,
+            tps: u32,
+            mix: &BTreeMap<TransactionKind, u32>,
+            _duration: Duration,
+        ) -> Result<Vec<TxRecord>, String> {
+            self.loads.push((tps, mix.clone()));
+            let mints = mix.get(&TransactionKind::MintNFT).copied().unwrap_or(0);
+            let record = |tx_type: &str, commit_latency_ms: f64| TxRecord {
+                tx_hash: None,
+                tx_type: tx_type.to_string(),
+                submitted_at_ms: 0,
+                service_time_ms: 10.0,
+                response_time_ms: 10.0,
+                fee: None,
+                status: TxStatus::Committed,
+                fail_reason: None,
+                commit_latency_ms: Some(commit_latency_ms),
+                verify_latency_ms: None,
+            };
+            let mut records: Vec<TxRecord> = (0..10)
+                .map(|_| record("transfer", 1_000.0 + 30.0 * mints as f64))
+                .collect();
+            records.extend((0..mints).map(|_| record("mint_nft", 2_000.0)));
+            Ok(records)
+        }
+    }
+
+    /// Tests the step loads and spotting the NFT rate transfers degrade from.
+    #[tokio::test]
+    async fn test_nft_interference() {
+        let scenario = NftInterference::new(NftInterferenceConfig {
+            transfer_tps: 5,
+            nft_tps_steps: vec![0, 5, 10],
+            step_secs: 60,
+            degradation_threshold_percent: 20.0,
+        });
+        let mut workload = PackedBlocks { loads: Vec::new() };
+        let report = scenario.run(&mut workload).await.unwrap();
+
+        assert_eq!(workload.loads[0].0, 5);
+        assert_eq!(workload.loads[0].1.len(), 1);
+        assert_eq!(workload.loads[2].0, 15);
+        assert_eq!(workload.loads[2].1[&TransactionKind::MintNFT], 10);
+
+        let changes: Vec<f64> = report
+            .steps
+            .iter()
+            .map(|step| step.transfer_change_percent.round())
+            .collect();
+        assert_eq!(changes, vec![0.0, 15.0, 30.0]);
+        assert_eq!(report.degraded_from_nft_tps, Some(10));
+        assert!((report.slope_ms_per_nft_tps - 30.0).abs() < 1e-9);
+        assert_eq!(report.steps[2].mints, 10);
+        assert!(render_table(&report).contains("degraded from 10 NFT TPS"));
+
+        let empty = NftInterference::new(NftInterferenceConfig {
+            nft_tps_steps: Vec::new(),
+            ..scenario.config
+        });
+        assert_eq!(
+            empty.run(&mut workload).await,
+            Err(NftInterferenceError::NoSteps)
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use std::sync::{Arc, Mutex};
+
+use serde::Deserialize;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::Notify;
+use tracing::info;
+
+/// Pause marker placed between scenario phases, e.g. `pause = "manual"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pause {
+    /// Halt until an operator confirms, either with the Enter key or through the control API.
+    Manual,
+}
+
+#[derive(Debug, Default)]
+struct PauseState {
+    notify: Notify,
+    waiting_at: Mutex<Option<String>>,
+}
+
+/// Handle used to halt a run at a pause point and to release it again.
+///
+/// Clones share the same state, so one copy can wait in the scenario runner while
+/// another one is handed to the control API.
+#[derive(Debug, Clone, Default)]
+pub struct PauseControl {
+    state: Arc<PauseState>,
+}
+
+impl PauseControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Label of the pause point the run is currently halted at.
+    pub fn waiting_at(&self) -> Option<String> {
+        self.state.waiting_at.lock().unwrap().clone()
+    }
+
+    /// Releases the run if it is halted, returns whether it was.
+    pub fn resume(&self) -> bool {
+        let waiting = self.waiting_at().is_some();
+        if waiting {
+            self.state.notify.notify_one();
+        }
+        waiting
+    }
+
+    /// Halts until the operator resumes the run.
+    ///
+    /// When `read_stdin` is set, pressing Enter in the terminal resumes the run as well.
+    pub async fn wait(&self, label: &str, read_stdin: bool) {
+        *self.state.waiting_at.lock().unwrap() = Some(label.to_string());
+        info!(
+            "Paused before '{}': press Enter or call the control API resume endpoint to continue",
+            label
+        );
+
+        let enter_pressed = async {
+            if read_stdin {
+                let mut line = String::new();
+                let _ = BufReader::new(tokio::io::stdin())
+                    .read_line(&mut line)
+                    .await;
+            } else {
+                std::future::pending::<()>().await;
+            }
+        };
+        tokio::select! {
+            _ = self.state.notify.notified() => {}
+            _ = enter_pressed => {}
+        }
+
+        *self.state.waiting_at.lock().unwrap() = None;
+        info!("Resuming after '{}'", label);
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinSet;
+
+use crate::rollup::types::TxHash;
+
+/// Scenario feature dropping every connection of the simulator at once, periodically.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReconnectStormConfig {
+    /// Time between two storms.
+    pub interval_secs: u64,
+    /// How long connections stay down before they are all re-established.
+    #[serde(default)]
+    pub downtime_ms: u64,
+    /// Time given to confirmations of in-flight transactions to arrive after a storm.
+    #[serde(default = "ReconnectStormConfig::default_settle_secs")]
+    pub settle_secs: u64,
+}
+
+impl ReconnectStormConfig {
+    fn default_settle_secs() -> u64 {
+        30
+    }
+}
+
+/// WebSocket subscription or HTTP connection pool that can be dropped and re-established.
+#[async_trait]
+pub trait Reconnectable: Send + Sync {
+    fn name(&self) -> String;
+
+    /// Drops the connection, in-flight requests and subscriptions included.
+    async fn disconnect(&self);
+
+    /// Re-establishes the connection and any subscriptions it carried.
+    async fn reconnect(&self) -> Result<(), String>;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconnectOutcome {
+    pub connection: String,
+    pub reconnect_ms: f64,
+    pub error: Option<String>,
+}
+
+/// Measurements of a single storm.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StormReport {
+    /// Time from dropping the first connection until the last one was back.
+    pub total_ms: f64,
+    pub connections: Vec<ReconnectOutcome>,
+    pub in_flight: usize,
+    /// In-flight transactions confirmed on the server whose confirmation never reached the tracker.
+    pub missed_confirmations: Vec<TxHash>,
+}
+
+impl StormReport {
+    pub fn failed_reconnects(&self) -> usize {
+        self.connections
+            .iter()
+            .filter(|outcome| outcome.error.is_some())
+            .count()
+    }
+}
+
+/// Matches confirmations received by the tracker against the ones the server reports.
+///
+/// Transactions in flight when the storm hits are registered first; the tracker marks
+/// every confirmation it receives and the server state polled after the storm reveals
+/// which confirmations were lost while the connections were down.
+#[derive(Debug, Default)]
+pub struct ConfirmationAudit {
+    state: Mutex<AuditState>,
+}
+
+#[derive(Debug, Default)]
+struct AuditState {
+    expected: HashSet<TxHash>,
+    received: HashSet<TxHash>,
+}
+
+impl ConfirmationAudit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn expect(&self, tx_hashes: impl IntoIterator<Item = TxHash>) {
+        self.state.lock().unwrap().expected.extend(tx_hashes);
+    }
+
+    /// Replaces the expected transactions, confirmations received so far are kept.
+    pub fn restart(&self, tx_hashes: impl IntoIterator<Item = TxHash>) {
+        let mut state = self.state.lock().unwrap();
+        state.expected = tx_hashes.into_iter().collect();
+    }
+
+    /// Called by the tracker whenever it learns about a confirmation.
+    pub fn received(&self, tx_hash: TxHash) {
+        self.state.lock().unwrap().received.insert(tx_hash);
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.state.lock().unwrap().expected.len()
+    }
+
+    /// Expected transactions the server confirmed but the tracker never heard of.
+    pub fn missed(&self, confirmed_on_server: &[TxHash]) -> Vec<TxHash> {
+        let state = self.state.lock().unwrap();
+        let mut missed: Vec<TxHash> = confirmed_on_server
+            .iter()
+            .filter(|tx_hash| state.expected.contains(tx_hash) && !state.received.contains(tx_hash))
+            .copied()
+            .collect();
+        missed.sort();
+        missed.dedup();
+        missed
+    }
+}
+
+pub struct ReconnectStorm {
+    config: ReconnectStormConfig,
+    connections: Vec<Arc<dyn Reconnectable>>,
+}
+
+impl ReconnectStorm {
+    pub fn new(config: ReconnectStormConfig, connections: Vec<Arc<dyn Reconnectable>>) -> Self {
+        Self {
+            config,
+            connections,
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.config.interval_secs)
+    }
+
+    pub fn settle_time(&self) -> Duration {
+        Duration::from_secs(self.config.settle_secs)
+    }
+
+    /// Drops all connections simultaneously, then re-establishes them all at once.
+    ///
+    /// Returns how long the storm took and the outcome of every reconnect.
+    pub async fn unleash(&self) -> (Duration, Vec<ReconnectOutcome>) {
+        let started = Instant::now();
+
+        let mut disconnects = JoinSet::new();
+        for connection in &self.connections {
+            let connection = connection.clone();
+            disconnects.spawn(async move { connection.disconnect().await });
+        }
+        while disconnects.join_next().await.is_some() {}
+
+        tokio::time::sleep(Duration::from_millis(self.config.downtime_ms)).await;
+
+        let mut reconnects = JoinSet::new();
+        for connection in &self.connections {
+            let connection = connection.clone();
+            reconnects.spawn(async move {
+                let reconnect_started = Instant::now();
+                let result = connection.reconnect().await;
+                ReconnectOutcome {
+                    connection: connection.name(),
+                    reconnect_ms: reconnect_started.elapsed().as_secs_f64() * 1000.0,
+                    error: result.err(),
+                }
+            });
+        }
+        let mut outcomes = Vec::with_capacity(self.connections.len());
+        while let Some(joined) = reconnects.join_next().await {
+            outcomes.push(joined.unwrap_or_else(|err| ReconnectOutcome {
+                connection: "unknown".to_string(),
+                reconnect_ms: 0.0,
+                error: Some(err.to_string()),
+            }));
+        }
+        outcomes.sort_by(|a, b| a.connection.cmp(&b.connection));
+
+        (started.elapsed(), outcomes)
+    }
+
+    /// Runs a storm while the given transactions are in flight and reports its effect.
+    ///
+    /// `confirmed_on_server` is queried after the settle time and must return which of
+    /// the in-flight transactions the server has confirmed by then.
+    pub async fn run<F, Fut>(
+        &self,
+        audit: &ConfirmationAudit,
+        confirmed_on_server: F,
+    ) -> StormReport
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Vec<TxHash>>,
+    {
+        let in_flight = audit.in_flight();
+        let (total, connections) = self.unleash().await;
+        tokio::time::sleep(self.settle_time()).await;
+        let confirmed = confirmed_on_server().await;
+
+        StormReport {
+            total_ms: total.as_secs_f64() * 1000.0,
+            connections,
+            in_flight,
+            missed_confirmations: audit.missed(&confirmed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+
+    struct FakeSubscription {
+        name: &'static str,
+        connected: AtomicBool,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl Reconnectable for FakeSubscription {
+        fn name(&self) -> String {
+            self.name.to_string()
+        }
+
+        async fn disconnect(&self) {
+            self.connected.store(false, Ordering::SeqCst);
+        }
+
+        async fn reconnect(&self) -> Result<(), String> {
+            if self.fail {
+                return Err("handshake refused".to_string());
+            }
+            self.connected.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    /// Tests that all connections cycle and confirmations lost during the storm are reported.
+    #[tokio::test]
+    async fn test_reconnect_storm() {
+        let ws = Arc::new(FakeSubscription {
+            name: "ws",
+            connected: AtomicBool::new(true),
+            fail: false,
+        });
+        let http = Arc::new(FakeSubscription {
+            name: "http",
+            connected: AtomicBool::new(true),
+            fail: true,
+        });
+        let storm = ReconnectStorm::new(
+            ReconnectStormConfig {
+                interval_secs: 60,
+                downtime_ms: 1,
+                settle_secs: 0,
+            },
+            vec![ws.clone(), http.clone()],
+        );
+
+        let hashes: Vec<TxHash> = (1..=3u8).map(|byte| TxHash { data: [byte; 32] }).collect();
+        let audit = ConfirmationAudit::new();
+        audit.expect(hashes.clone());
+        audit.received(hashes[0]);
+
+        let report = storm.run(&audit, || async { hashes.clone() }).await;
+
+        assert!(ws.connected.load(Ordering::SeqCst));
+        assert!(!http.connected.load(Ordering::SeqCst));
+        assert_eq!(report.failed_reconnects(), 1);
+        assert_eq!(report.in_flight, 3);
+        assert_eq!(report.missed_confirmations, vec![hashes[1], hashes[2]]);
+    }
+}
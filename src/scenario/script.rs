@@ -0,0 +1,382 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use thiserror::Error;
+use tracing::{info, info_span, Instrument};
+
+use super::pause::{Pause, PauseControl};
+use super::wait_for::{BlockProgress, WaitFor, WaitForError};
+use crate::config::GeneralConfig;
+use crate::engine::{Engine, EngineSummary, TxPipeline};
+use crate::metrics::Metrics;
+use crate::report::RunRecorder;
+
+#[derive(Debug, Error)]
+pub enum ScenarioError {
+    #[error("Unable to read the scenario file: {0}")]
+    Io(#[from] io::Error),
+    #[error("Unable to parse the scenario file: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("Phase {phase}: {reason}")]
+    Invalid { phase: usize, reason: String },
+    #[error("Phase {phase} ({label}) failed: {reason}")]
+    Failed {
+        phase: usize,
+        label: String,
+        reason: String,
+    },
+    #[error("Phase {phase}: {source}")]
+    WaitFor {
+        phase: usize,
+        #[source]
+        source: WaitForError,
+    },
+}
+
+/// Scripted workload run with `--scenario <file>` instead of the single random loop.
+///
+/// Phases run in order. A phase may be preceded by a pause point and followed by a
+/// block finality boundary:
+///
+/// ```toml
+/// name = "payments"
+/// wait_timeout_secs = 1800
+///
+/// [[phase]]
+/// action = "fund"
+/// accounts = 100
+/// wait_for = "verified_block"
+///
+/// [[phase]]
+/// action = "run"
+/// tps = 50
+/// duration_secs = 300
+/// pause = "manual"
+///
+/// [[phase]]
+/// action = "mass_withdraw"
+/// percent = 100
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScenarioFile {
+    pub name: String,
+    /// How often block progress is polled while waiting for a boundary.
+    #[serde(default = "ScenarioFile::default_wait_poll_interval_ms")]
+    pub wait_poll_interval_ms: u64,
+    /// Longest wait for a boundary before the scenario fails.
+    #[serde(default = "ScenarioFile::default_wait_timeout_secs")]
+    pub wait_timeout_secs: u64,
+    #[serde(rename = "phase")]
+    pub phases: Vec<ScenarioPhase>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ScenarioPhase {
+    #[serde(flatten)]
+    pub action: PhaseAction,
+    /// Pause point before the phase starts.
+    #[serde(default)]
+    pub pause: Option<Pause>,
+    /// Boundary awaited after the phase ends, before the next one starts.
+    #[serde(default)]
+    pub wait_for: Option<WaitFor>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum PhaseAction {
+    /// Creates and funds accounts, adding them to the pool used by later phases.
+    Fund { accounts: u32 },
+    /// Submits transactions at a fixed rate.
+    Run { tps: u32, duration_secs: u64 },
+    /// Withdraws the whole balance of the given share of the pool.
+    MassWithdraw {
+        #[serde(default = "PhaseAction::default_percent")]
+        percent: u32,
+    },
+}
+
+impl PhaseAction {
+    fn default_percent() -> u32 {
+        100
+    }
+
+    /// Short description used in progress messages and errors.
+    pub fn label(&self) -> String {
+        match self {
+            PhaseAction::Fund { accounts } => format!("fund {} accounts", accounts),
+            PhaseAction::Run { tps, duration_secs } => {
+                format!("run at {} TPS for {}s", tps, duration_secs)
+            }
+            PhaseAction::MassWithdraw { percent } => {
+                format!("mass withdraw from {}% of accounts", percent)
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            PhaseAction::Fund { accounts: 0 } => Err("accounts must be positive".to_string()),
+            PhaseAction::Run { tps: 0, .. } => Err("tps must be positive".to_string()),
+            PhaseAction::Run {
+                duration_secs: 0, ..
+            } => Err("duration_secs must be positive".to_string()),
+            PhaseAction::MassWithdraw { percent } if *percent == 0 || *percent > 100 => {
+                Err("percent must be between 1 and 100".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl ScenarioFile {
+    fn default_wait_poll_interval_ms() -> u64 {
+        1000
+    }
+
+    fn default_wait_timeout_secs() -> u64 {
+        3600
+    }
+
+    pub fn load_from_file(file_path: impl AsRef<Path>) -> Result<Self, ScenarioError> {
+        let content = fs::read_to_string(file_path)?;
+        Self::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Result<Self, ScenarioError> {
+        let scenario: ScenarioFile = toml::from_str(content)?;
+        scenario.validate()?;
+
+        Ok(scenario)
+    }
+
+    fn validate(&self) -> Result<(), ScenarioError> {
+        if self.phases.is_empty() {
+            return Err(ScenarioError::Invalid {
+                phase: 0,
+                reason: "the scenario has no phases".to_string(),
+            });
+        }
+        for (index, phase) in self.phases.iter().enumerate() {
+            phase
+                .action
+                .validate()
+                .map_err(|reason| ScenarioError::Invalid {
+                    phase: index + 1,
+                    reason,
+                })?;
+        }
+        Ok(())
+    }
+}
+
+/// Carries out the actions of scenario phases.
+#[async_trait]
+pub trait PhaseExecutor: Send {
+    async fn fund(&mut self, accounts: u32) -> Result<(), String>;
+
+    async fn run(&mut self, tps: u32, duration: Duration) -> Result<EngineSummary, String>;
+
+    async fn mass_withdraw(&mut self, percent: u32) -> Result<(), String>;
+}
+
+/// Executor submitting the `run` phases through the simulation engine.
+///
+/// It has no account pool of its own, so scenarios using `fund` or `mass_withdraw` fail.
+pub struct EngineExecutor<'a, P: TxPipeline> {
+    pipeline: Arc<P>,
+    config: &'a GeneralConfig,
+    recorder: Arc<RunRecorder>,
+    metrics: Arc<Metrics>,
+}
+
+impl<'a, P: TxPipeline> EngineExecutor<'a, P> {
+    pub fn new(
+        pipeline: Arc<P>,
+        config: &'a GeneralConfig,
+        recorder: Arc<RunRecorder>,
+        metrics: Arc<Metrics>,
+    ) -> Self {
+        Self {
+            pipeline,
+            config,
+            recorder,
+            metrics,
+        }
+    }
+}
+
+#[async_trait]
+impl<P: TxPipeline> PhaseExecutor for EngineExecutor<'_, P> {
+    async fn fund(&mut self, _accounts: u32) -> Result<(), String> {
+        Err("funding accounts is not supported by the engine executor".to_string())
+    }
+
+    async fn run(&mut self, tps: u32, duration: Duration) -> Result<EngineSummary, String> {
+        let engine = Engine::for_phase(
+            self.pipeline.clone(),
+            self.config,
+            tps,
+            duration,
+            self.recorder.clone(),
+            self.metrics.clone(),
+        );
+        Ok(engine.run().await)
+    }
+
+    async fn mass_withdraw(&mut self, _percent: u32) -> Result<(), String> {
+        Err("mass withdrawals are not supported by the engine executor".to_string())
+    }
+}
+
+/// Runs the phases of a scenario one after another.
+pub struct ScenarioRunner<'a> {
+    pause: PauseControl,
+    progress: &'a dyn BlockProgress,
+}
+
+impl<'a> ScenarioRunner<'a> {
+    pub fn new(pause: PauseControl, progress: &'a dyn BlockProgress) -> Self {
+        Self { pause, progress }
+    }
+
+    /// Runs every phase, stopping at the first one that fails.
+    pub async fn run(
+        &self,
+        scenario: &ScenarioFile,
+        executor: &mut dyn PhaseExecutor,
+    ) -> Result<(), ScenarioError> {
+        let poll_interval = Duration::from_millis(scenario.wait_poll_interval_ms);
+        let wait_timeout = Duration::from_secs(scenario.wait_timeout_secs);
+        for (index, phase) in scenario.phases.iter().enumerate() {
+            let number = index + 1;
+            let label = phase.action.label();
+            if let Some(Pause::Manual) = phase.pause {
+                self.pause.wait(&label, true).await;
+            }
+
+            let span = info_span!("phase", scenario = %scenario.name, number, label = %label);
+            async {
+                info!("phase {}/{}: {}", number, scenario.phases.len(), label);
+                let result = match phase.action {
+                    PhaseAction::Fund { accounts } => executor.fund(accounts).await,
+                    PhaseAction::Run { tps, duration_secs } => executor
+                        .run(tps, Duration::from_secs(duration_secs))
+                        .await
+                        .map(|summary| {
+                            info!(
+                                submitted = summary.submitted,
+                                failed = summary.failed,
+                                "Submitted {} transactions, {} failed ({:.1} TPS)",
+                                summary.submitted,
+                                summary.failed,
+                                summary.achieved_tps()
+                            );
+                        }),
+                    PhaseAction::MassWithdraw { percent } => executor.mass_withdraw(percent).await,
+                };
+                result.map_err(|reason| ScenarioError::Failed {
+                    phase: number,
+                    label: label.clone(),
+                    reason,
+                })?;
+
+                if let Some(wait_for) = phase.wait_for {
+                    info!("Waiting for {}", wait_for);
+                    wait_for
+                        .wait(self.progress, poll_interval, wait_timeout)
+                        .await
+                        .map_err(|source| ScenarioError::WaitFor {
+                            phase: number,
+                            source,
+                        })?;
+                }
+                Ok::<_, ScenarioError>(())
+            }
+            .instrument(span)
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests that phases are parsed in order together with their boundaries and validated.
+    #[test]
+    fn test_parse_scenario_file() {
+        let scenario: ScenarioFile = toml::from_str(
+            r#"
+            name = "payments"
+
+            [[phase]]
+            action = "fund"
+            accounts = 100
+            wait_for = "verified_block"
+
+            [[phase]]
+            action = "run"
+            tps = 50
+            duration_secs = 300
+            pause = "manual"
+
+            [[phase]]
+            action = "mass_withdraw"
+            wait_for = { committed_blocks = 2 }
+            "#,
+        )
+        .unwrap();
+        scenario.validate().unwrap();
+
+        assert_eq!(
+            scenario.phases,
+            vec![
+                ScenarioPhase {
+                    action: PhaseAction::Fund { accounts: 100 },
+                    pause: None,
+                    wait_for: Some(WaitFor::VerifiedBlock),
+                },
+                ScenarioPhase {
+                    action: PhaseAction::Run {
+                        tps: 50,
+                        duration_secs: 300,
+                    },
+                    pause: Some(Pause::Manual),
+                    wait_for: None,
+                },
+                ScenarioPhase {
+                    action: PhaseAction::MassWithdraw { percent: 100 },
+                    pause: None,
+                    wait_for: Some(WaitFor::CommittedBlocks(2)),
+                },
+            ]
+        );
+
+        let invalid: ScenarioFile = toml::from_str(
+            r#"
+            name = "broken"
+
+            [[phase]]
+            action = "fund"
+            accounts = 10
+
+            [[phase]]
+            action = "run"
+            tps = 0
+            duration_secs = 60
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(
+            invalid.validate(),
+            Err(ScenarioError::Invalid { phase: 2, .. })
+        ));
+    }
+}
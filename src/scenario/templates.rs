@@ -0,0 +1,149 @@
+//! Ready-made scenarios embedded in the binary, run with `run --template <name>`.
+//!
+//! A template is a scenario file with a `[settings]` table of configuration values the
+//! scenario needs, e.g. the operation mix of an NFT drop:
+//!
+//! ```toml
+//! [settings.transaction]
+//! mix = { mint_nft = 80, transfer = 20 }
+//! ```
+//!
+//! Settings replace whole keys of their section and are applied before the `RIF_SIM_*`
+//! variables and `--set` flags, which can still adjust them.
+
+use std::io::ErrorKind;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::script::{ScenarioError, ScenarioFile};
+use crate::config::{Config, ConfigError, ConfigOverride, EXAMPLE_CONFIG};
+
+/// Scenario shipped with the binary.
+#[derive(Debug)]
+pub struct Template {
+    pub name: &'static str,
+    source: &'static str,
+}
+
+/// Embeds `scenarios/templates/<name>.toml`.
+macro_rules! template {
+    ($name:literal) => {
+        Template::new(
+            $name,
+            include_str!(concat!("../../scenarios/templates/", $name, ".toml")),
+        )
+    };
+}
+
+/// Templates in the order they are listed in `run --help`.
+pub const TEMPLATES: [Template; 5] = [
+    template!("smoke"),
+    template!("payments"),
+    template!("nft-drop"),
+    template!("mass-exit"),
+    template!("soak-24h"),
+];
+
+#[derive(Deserialize)]
+struct TemplateHeader {
+    description: String,
+    #[serde(default)]
+    settings: toml::Table,
+}
+
+impl Template {
+    const fn new(name: &'static str, source: &'static str) -> Self {
+        Self { name, source }
+    }
+
+    pub fn names() -> Vec<&'static str> {
+        TEMPLATES.iter().map(|template| template.name).collect()
+    }
+
+    pub fn find(name: &str) -> Option<&'static Template> {
+        TEMPLATES.iter().find(|template| template.name == name)
+    }
+
+    fn header(&self) -> TemplateHeader {
+        toml::from_str(self.source).expect("templates are checked by the tests")
+    }
+
+    pub fn description(&self) -> String {
+        self.header().description
+    }
+
+    pub fn scenario(&self) -> Result<ScenarioFile, ScenarioError> {
+        ScenarioFile::parse(self.source)
+    }
+
+    /// Settings of the template as overrides of `<section>.<key>`.
+    pub fn overrides(&self) -> Vec<ConfigOverride> {
+        let mut overrides = Vec::new();
+        for (section, keys) in self.header().settings {
+            let Some(keys) = keys.as_table() else {
+                continue;
+            };
+            for (key, value) in keys {
+                overrides.push(ConfigOverride {
+                    path: format!("{section}.{key}"),
+                    value: value.clone(),
+                });
+            }
+        }
+        overrides
+    }
+
+    /// Loads the configuration file with the settings of the template applied before
+    /// `overrides`, on top of the configuration shipped with the repository when the file
+    /// does not exist.
+    pub fn load_config(
+        &self,
+        file_path: &Path,
+        overrides: &[ConfigOverride],
+    ) -> Result<Config, ConfigError> {
+        let mut layered = self.overrides();
+        layered.extend(overrides.iter().cloned());
+        let config = match Config::load_with_overrides(file_path, &layered) {
+            Err(ConfigError::Io(err)) if err.kind() == ErrorKind::NotFound => {
+                Config::parse_with_overrides(EXAMPLE_CONFIG, "shipped with the binary", &layered)?
+            }
+            loaded => loaded?,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transaction::TransactionKind;
+
+    /// Tests that every template parses, describes itself and applies to the shipped
+    /// configuration, and that later overrides win over its settings.
+    #[test]
+    fn test_templates() {
+        let missing = Path::new("no-such-config.toml");
+        for template in &TEMPLATES {
+            let scenario = template.scenario().unwrap();
+            assert_eq!(scenario.name, template.name);
+            assert!(!template.description().is_empty());
+            assert!(!template.overrides().is_empty(), "{}", template.name);
+            template.load_config(missing, &[]).unwrap();
+        }
+        assert_eq!(Template::names().len(), TEMPLATES.len());
+
+        let nft_drop = Template::find("nft-drop").unwrap();
+        let config = nft_drop.load_config(missing, &[]).unwrap();
+        assert_eq!(config.transaction.mix[&TransactionKind::MintNFT], 80);
+        let config = nft_drop
+            .load_config(
+                missing,
+                &["transaction.mix={ transfer = 1 }".parse().unwrap()],
+            )
+            .unwrap();
+        assert_eq!(config.transaction.mix.len(), 1);
+        assert!(Template::find("nope").is_none());
+    }
+}
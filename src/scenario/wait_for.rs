@@ -0,0 +1,153 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::rollup::provider::{ClientError, ResponseResult};
+use crate::rollup::types::BlockNumber;
+
+/// Block finality boundary placed between scenario phases.
+///
+/// ```toml
+/// wait_for = "verified_block"
+/// wait_for = { committed_blocks = 3 }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WaitFor {
+    /// Until the last block committed when the phase ended is verified.
+    VerifiedBlock,
+    /// Until the given number of further blocks is committed.
+    CommittedBlocks(u32),
+}
+
+impl fmt::Display for WaitFor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WaitFor::VerifiedBlock => write!(f, "verified block"),
+            WaitFor::CommittedBlocks(count) => write!(f, "{} committed block(s)", count),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum WaitForError {
+    #[error("Unable to query block progress: {0}")]
+    Provider(#[from] ClientError),
+    #[error("Gave up waiting for {condition} after {waited:?}")]
+    Timeout {
+        condition: WaitFor,
+        waited: Duration,
+    },
+}
+
+/// Latest blocks of the rollup chain.
+#[async_trait]
+pub trait BlockProgress: Send + Sync {
+    async fn last_committed_block(&self) -> ResponseResult<BlockNumber>;
+
+    async fn last_verified_block(&self) -> ResponseResult<BlockNumber>;
+}
+
+impl WaitFor {
+    /// Blocks until the boundary is reached, polling the chain progress.
+    ///
+    /// Transactions of the previous phase must already be committed when this is called,
+    /// so they are all contained in the last committed block or an earlier one.
+    pub async fn wait(
+        self,
+        progress: &dyn BlockProgress,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> Result<BlockNumber, WaitForError> {
+        let started = Instant::now();
+        let last_committed = progress.last_committed_block().await?;
+        let target = match self {
+            WaitFor::VerifiedBlock => last_committed,
+            WaitFor::CommittedBlocks(count) => BlockNumber(last_committed.0.saturating_add(count)),
+        };
+
+        loop {
+            let reached = match self {
+                WaitFor::VerifiedBlock => progress.last_verified_block().await?,
+                WaitFor::CommittedBlocks(_) => progress.last_committed_block().await?,
+            };
+            if reached >= target {
+                return Ok(reached);
+            }
+            if started.elapsed() >= timeout {
+                return Err(WaitForError::Timeout {
+                    condition: self,
+                    waited: started.elapsed(),
+                });
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// Chain committing a block and verifying one on every poll, verification lagging by 3.
+    struct FakeChain {
+        committed: Mutex<u32>,
+    }
+
+    #[async_trait]
+    impl BlockProgress for FakeChain {
+        async fn last_committed_block(&self) -> ResponseResult<BlockNumber> {
+            let mut committed = self.committed.lock().unwrap();
+            *committed += 1;
+            Ok(BlockNumber(*committed))
+        }
+
+        async fn last_verified_block(&self) -> ResponseResult<BlockNumber> {
+            let mut committed = self.committed.lock().unwrap();
+            *committed += 1;
+            Ok(BlockNumber(committed.saturating_sub(3)))
+        }
+    }
+
+    /// Tests that both boundaries are parsed and waited for.
+    #[tokio::test]
+    async fn test_wait_for_boundaries() {
+        #[derive(Deserialize)]
+        struct Phase {
+            wait_for: WaitFor,
+        }
+        let phase: Phase = toml::from_str("wait_for = \"verified_block\"").unwrap();
+        assert_eq!(phase.wait_for, WaitFor::VerifiedBlock);
+        let phase: Phase = toml::from_str("wait_for = { committed_blocks = 3 }").unwrap();
+        assert_eq!(phase.wait_for, WaitFor::CommittedBlocks(3));
+
+        let poll = Duration::from_millis(1);
+        let timeout = Duration::from_secs(5);
+        let chain = FakeChain {
+            committed: Mutex::new(9),
+        };
+        let reached = WaitFor::VerifiedBlock
+            .wait(&chain, poll, timeout)
+            .await
+            .unwrap();
+        assert_eq!(reached, BlockNumber(10));
+
+        let reached = WaitFor::CommittedBlocks(3)
+            .wait(&chain, poll, timeout)
+            .await
+            .unwrap();
+        assert!(reached >= BlockNumber(17));
+
+        assert!(matches!(
+            WaitFor::VerifiedBlock
+                .wait(&chain, poll, Duration::ZERO)
+                .await,
+            Err(WaitForError::Timeout { .. })
+        ));
+    }
+}
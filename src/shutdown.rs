@@ -0,0 +1,77 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::Notify;
+use tracing::warn;
+
+/// Exit code of a run stopped by a second signal, as a shell reports SIGINT.
+const FORCED_EXIT_CODE: i32 = 130;
+
+/// Request to end the run early, shared by the signal handler and the engine.
+///
+/// The first SIGINT or SIGTERM stops new transactions from being generated; the run then
+/// waits for the ones in flight, writes its report and a checkpoint. A second signal
+/// exits right away.
+#[derive(Debug, Clone, Default)]
+pub struct Shutdown {
+    requested: Arc<AtomicBool>,
+    notify: Arc<Notify>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::Relaxed);
+        self.notify.notify_waiters();
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::Relaxed)
+    }
+
+    /// Resolves once the shutdown is requested, to abandon waits that may never end.
+    pub async fn requested(&self) {
+        loop {
+            let notified = self.notify.notified();
+            if self.is_requested() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Waits for SIGINT or SIGTERM, requesting the shutdown on the first and exiting on the second.
+    pub async fn listen(self) {
+        loop {
+            if wait_for_signal().await.is_err() {
+                warn!("Unable to listen for shutdown signals");
+                return;
+            }
+            if self.is_requested() {
+                warn!("Second shutdown signal received, exiting without a checkpoint");
+                std::process::exit(FORCED_EXIT_CODE);
+            }
+            warn!("Shutdown requested, finishing transactions in flight; signal again to exit now");
+            self.request();
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() -> std::io::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result,
+        _ = terminate.recv() => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() -> std::io::Result<()> {
+    tokio::signal::ctrl_c().await
+}
@@ -0,0 +1,365 @@
+use num::BigUint;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::report::batch_fees::BatchFeeQuote;
+use crate::report::RunRecorder;
+use crate::rollup::provider::{ClientError, Provider};
+use crate::rollup::types::packing::is_fee_amount_packable;
+use crate::rollup::types::tx::TimeRange;
+use crate::rollup::types::{Address, Nonce, Token, TxFeeTypes, TxHash};
+use crate::wallet::{SignedTx, Wallet};
+
+#[derive(Debug, Error)]
+pub enum SponsorError {
+    #[error("{0}")]
+    Client(#[from] ClientError),
+    #[error("A sponsored batch needs at least one transaction")]
+    EmptyBatch,
+    #[error("Sponsor balance {available} does not cover the batch fee {required}")]
+    InsufficientBalance {
+        required: BigUint,
+        available: BigUint,
+    },
+}
+
+/// Fee-sponsored batches, enabled by the `[sponsor]` section.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SponsorConfig {
+    /// Number of sponsored transactions per batch, the sponsor's fee transfer comes on top.
+    #[serde(default = "SponsorConfig::default_batch_size")]
+    pub batch_size: usize,
+    /// Sponsoring stops once paying a batch fee would leave less than this, in the
+    /// smallest units of the fee token.
+    #[serde(default)]
+    pub min_balance: u64,
+    /// Also quotes the sponsored transfers one by one, for the batch fee report.
+    #[serde(default)]
+    pub compare_fees: bool,
+}
+
+impl SponsorConfig {
+    fn default_batch_size() -> usize {
+        10
+    }
+}
+
+impl Default for SponsorConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: Self::default_batch_size(),
+            min_balance: 0,
+            compare_fees: false,
+        }
+    }
+}
+
+/// Zero-fee transfer of a sponsored account.
+pub struct SponsoredTransfer<'a> {
+    pub wallet: &'a Wallet,
+    pub to: Address,
+    pub amount: BigUint,
+    pub nonce: Nonce,
+}
+
+/// Signed batch ready for `Provider::send_txs_batch`, the sponsor's fee transfer comes last.
+#[derive(Debug, Clone)]
+pub struct SponsoredBatch {
+    pub txs: Vec<SignedTx>,
+    pub fee: BigUint,
+}
+
+/// Pays the fees of other accounts' transfers, the way wallet providers cover their users.
+///
+/// Sponsored transfers are signed with a zero fee; the sponsor appends a zero amount
+/// transfer to itself whose fee covers the whole batch. The rollup executes a batch
+/// atomically, so the sponsored transfers are only accepted together with the fee. Only
+/// transfers are sponsored, the protocol charges other operations on their own account.
+pub struct FeeSponsor {
+    wallet: Wallet,
+    nonce: Nonce,
+    balance: BigUint,
+    config: SponsorConfig,
+}
+
+impl FeeSponsor {
+    /// Creates the sponsor with its next nonce and its balance in the fee token.
+    pub fn new(wallet: Wallet, nonce: Nonce, balance: BigUint, config: SponsorConfig) -> Self {
+        Self {
+            wallet,
+            nonce,
+            balance,
+            config,
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        self.wallet.address()
+    }
+
+    pub fn balance(&self) -> &BigUint {
+        &self.balance
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.config.batch_size.max(1)
+    }
+
+    /// Asks the server for the fee of a batch of sponsored transfers to `recipients`, rounded up to a packable fee.
+    ///
+    /// With `compare_fees`, the transfers are also quoted on their own, without the sponsor's
+    /// transfer, and both quotes are recorded for the batch fee report.
+    pub async fn quote_fee<P: Provider + Sync>(
+        &self,
+        provider: &P,
+        recipients: &[Address],
+        token: &Token,
+        recorder: &RunRecorder,
+    ) -> Result<BigUint, SponsorError> {
+        let mut addresses = recipients.to_vec();
+        addresses.push(self.address());
+        let tx_types = vec![TxFeeTypes::Transfer; addresses.len()];
+        let fee = provider
+            .get_txs_batch_fee(tx_types.clone(), addresses, token)
+            .await?;
+        if self.config.compare_fees {
+            let mut individual_fees = BigUint::default();
+            for recipient in recipients {
+                individual_fees += provider
+                    .get_tx_fee(TxFeeTypes::Transfer, *recipient, token)
+                    .await?
+                    .total_fee;
+            }
+            let quote = BatchFeeQuote {
+                token: token.symbol.clone(),
+                tx_types,
+                batch_fee: fee.clone(),
+                individual_fees,
+            };
+            recorder.with_batch_fees(|batch_fees| batch_fees.record(&quote));
+        }
+        round_up_packable_fee(&fee).ok_or(SponsorError::Client(ClientError::NotPackableValue))
+    }
+
+    /// Signs the sponsored transfers with a zero fee and the sponsor's transfer paying `fee`.
+    pub async fn sign_batch(
+        &mut self,
+        transfers: Vec<SponsoredTransfer<'_>>,
+        token: &Token,
+        fee: BigUint,
+        time_range: TimeRange,
+    ) -> Result<SponsoredBatch, SponsorError> {
+        if transfers.is_empty() {
+            return Err(SponsorError::EmptyBatch);
+        }
+        let required = &fee + BigUint::from(self.config.min_balance);
+        if self.balance < required {
+            return Err(SponsorError::InsufficientBalance {
+                required,
+                available: self.balance.clone(),
+            });
+        }
+
+        let mut txs = Vec::with_capacity(transfers.len() + 1);
+        for transfer in transfers {
+            txs.push(
+                transfer
+                    .wallet
+                    .sign_transfer(
+                        transfer.to,
+                        token,
+                        transfer.amount,
+                        BigUint::default(),
+                        transfer.nonce,
+                        time_range,
+                    )
+                    .await?,
+            );
+        }
+        txs.push(
+            self.wallet
+                .sign_transfer(
+                    self.address(),
+                    token,
+                    BigUint::default(),
+                    fee.clone(),
+                    self.nonce,
+                    time_range,
+                )
+                .await?,
+        );
+        self.nonce = self.nonce.checked_next().unwrap_or(self.nonce);
+        Ok(SponsoredBatch { txs, fee })
+    }
+
+    /// Submits a signed batch, charging the fee to the sponsor once the server accepts it.
+    ///
+    /// A rejected batch consumes none of its nonces, so the sponsor's nonce goes back by one.
+    pub async fn submit<P: Provider + Sync>(
+        &mut self,
+        provider: &P,
+        batch: SponsoredBatch,
+        recorder: &RunRecorder,
+    ) -> Result<Vec<TxHash>, SponsorError> {
+        let sponsored = batch.txs.len() - 1;
+        match provider.send_txs_batch(batch.txs, None).await {
+            Ok(hashes) => {
+                self.balance = if self.balance >= batch.fee {
+                    &self.balance - &batch.fee
+                } else {
+                    BigUint::default()
+                };
+                recorder.with_sponsor(|ledger| ledger.record_batch(sponsored, &batch.fee));
+                Ok(hashes)
+            }
+            Err(err) => {
+                self.nonce = Nonce(self.nonce.saturating_sub(1));
+                recorder.with_sponsor(|ledger| ledger.record_rejected());
+                Err(err.into())
+            }
+        }
+    }
+}
+
+/// Smallest packable fee not lower than the given one, so a batch fee still covers the quote.
+pub fn round_up_packable_fee(fee: &BigUint) -> Option<BigUint> {
+    let mut unit = BigUint::from(1u32);
+    for _ in 0..32 {
+        let candidate = (fee + &unit - 1u32) / &unit * &unit;
+        if is_fee_amount_packable(&candidate) {
+            return Some(candidate);
+        }
+        unit *= 10u32;
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::signers::LocalWallet;
+
+    use super::*;
+    use crate::report::units::AmountFormat;
+    use crate::rollup::mock::MockProvider;
+    use crate::rollup::types::tx::ZkSyncTx;
+    use crate::rollup::types::{AccountId, TokenId, TokenKind};
+
+    /// Tests that sponsored transfers are free, the sponsor pays a rounded up fee and its balance is checked.
+    #[tokio::test]
+    async fn test_sponsored_batch() {
+        assert_eq!(
+            round_up_packable_fee(&BigUint::from(123_456u32)),
+            Some(BigUint::from(123_500u32))
+        );
+        assert_eq!(
+            round_up_packable_fee(&BigUint::from(2_047u32)),
+            Some(BigUint::from(2_047u32))
+        );
+
+        let mut wallets = Vec::new();
+        for seed in 1..=3u8 {
+            let mut wallet = Wallet::new(LocalWallet::from_bytes(&[seed; 32]).unwrap())
+                .await
+                .unwrap();
+            wallet.set_account_id(AccountId(seed as u32));
+            wallets.push(wallet);
+        }
+        let sponsor_wallet = wallets.pop().unwrap();
+        let sponsor_address = sponsor_wallet.address();
+        let config = SponsorConfig {
+            batch_size: 2,
+            min_balance: 100,
+            compare_fees: true,
+        };
+        let mut sponsor =
+            FeeSponsor::new(sponsor_wallet, Nonce(5), BigUint::from(1_100u32), config);
+        let token = Token::new(TokenId(0), Address::zero(), "RBTC", 18, TokenKind::None);
+        let recorder = RunRecorder::new();
+        let provider = MockProvider::new().with_fee(BigUint::from(100u32));
+        let recipients: Vec<Address> = wallets.iter().map(Wallet::address).collect();
+        let quoted = sponsor
+            .quote_fee(&provider, &recipients, &token, &recorder)
+            .await
+            .unwrap();
+        assert_eq!(quoted, BigUint::from(300u32));
+        // Two transfers on their own cost less than the batch with the sponsor's transfer.
+        let rows = recorder.with_batch_fees(|batch_fees| batch_fees.rows());
+        assert_eq!(rows[0].avg_individual_fees, BigUint::from(200u32));
+        assert!(rows[0].savings_percent < 0.0);
+
+        let transfers = || {
+            wallets
+                .iter()
+                .map(|wallet| SponsoredTransfer {
+                    wallet,
+                    to: sponsor_address,
+                    amount: BigUint::from(500u32),
+                    nonce: Nonce(0),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let batch = sponsor
+            .sign_batch(
+                transfers(),
+                &token,
+                BigUint::from(1_000u32),
+                TimeRange::default(),
+            )
+            .await
+            .unwrap();
+        let transfers_signed: Vec<_> = batch
+            .txs
+            .iter()
+            .map(|(tx, _)| match tx {
+                ZkSyncTx::Transfer(transfer) => {
+                    (transfer.from, transfer.fee.clone(), transfer.nonce)
+                }
+                _ => panic!("expected transfers"),
+            })
+            .collect();
+        assert_eq!(transfers_signed.len(), 3);
+        assert!(transfers_signed[..2]
+            .iter()
+            .all(|(_, fee, _)| *fee == BigUint::default()));
+        assert_eq!(
+            transfers_signed[2],
+            (sponsor_address, BigUint::from(1_000u32), Nonce(5))
+        );
+
+        assert!(matches!(
+            sponsor
+                .sign_batch(
+                    transfers(),
+                    &token,
+                    BigUint::from(1_010u32),
+                    TimeRange::default()
+                )
+                .await,
+            Err(SponsorError::InsufficientBalance { .. })
+        ));
+        assert!(matches!(
+            sponsor
+                .sign_batch(
+                    Vec::new(),
+                    &token,
+                    BigUint::from(1u32),
+                    TimeRange::default()
+                )
+                .await,
+            Err(SponsorError::EmptyBatch)
+        ));
+
+        recorder.with_sponsor(|ledger| {
+            ledger.set_sponsor(sponsor_address, &token.symbol, BigUint::from(1_100u32));
+            ledger.record_batch(2, &batch.fee);
+            ledger.record_rejected();
+        });
+        let summary = recorder.with_sponsor(|ledger| ledger.summary());
+        assert_eq!(summary.avg_fee_per_tx, BigUint::from(500u32));
+        assert_eq!(summary.remaining_balance, BigUint::from(100u32));
+        assert_eq!(summary.rejected_batches, 1);
+        let table = recorder.with_sponsor(|ledger| ledger.render_table(&AmountFormat::new()));
+        assert!(table.contains("0.000000000000001 RBTC"));
+    }
+}
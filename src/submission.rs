@@ -0,0 +1,1497 @@
+//! Pipeline of real runs: operations generated from the account pool, signed with the
+//! accounts' keys and submitted to the rollup server, deposits through the Rootstock node.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use ethers::types::U256;
+use num::{BigUint, ToPrimitive};
+use tracing::{debug, warn};
+
+use crate::accounts::AccountPool;
+use crate::checkpoint::{CheckpointError, PipelineState};
+use crate::config::{GeneralConfig, NetworkConfig, TransactionConfig};
+use crate::engine::{Engine, TxPipeline};
+use crate::l1::deposit::{CompletedDeposit, DepositError, L1Depositor};
+use crate::l1::node::{L1Node, L1Signer};
+use crate::l1::nonce::L1NonceManager;
+use crate::metrics::Metrics;
+use crate::report::nfts::NftOperation;
+use crate::report::{RunRecorder, TxRecord, TxStatus};
+use crate::rng::{RngStream, RngStreams, StreamRng};
+use crate::rollup::confirmation::{ConfirmationConfig, TrackedOp};
+use crate::rollup::fee_cache::FeeCache;
+use crate::rollup::provider::{ClientError, Provider, ResponseResult};
+use crate::rollup::tokens::{TokenMix, TokenRegistry};
+use crate::rollup::types::tx::TimeRange;
+use crate::rollup::types::{
+    Address, BlockInfo, BlockNumber, ChangePubKeyFeeType, Token, TokenLike, TransactionInfo,
+    TxFeeTypes, TxHash,
+};
+use crate::scenario::interleaving::InterleavingTarget;
+use crate::scenario::merchant_payouts::MerchantAccounts;
+use crate::scenario::nft_interference::MixedWorkload;
+use crate::scenario::wait_for::BlockProgress;
+use crate::sponsor::{
+    round_up_packable_fee, FeeSponsor, SponsorConfig, SponsorError, SponsoredTransfer,
+};
+use crate::tagging::RunTag;
+use crate::transaction::{
+    parse_address, AddressDenylist, Transaction, TransactionKind, TransactionMix,
+};
+use crate::wallet::account_state::AccountGcAction;
+use crate::wallet::batch_nonce::{BatchId, BatchNonceTracker, BatchNonces};
+use crate::wallet::nonce::NonceRejection;
+use crate::wallet::SignedTx;
+
+/// Generated operation ready to be submitted.
+#[derive(Debug, Clone)]
+pub struct RollupTx {
+    pub transaction: Transaction,
+    /// Token of the amount, the fee token for operations that move no funds.
+    pub token: Token,
+    /// Signed L2 transaction, `None` for deposits, which are sent on L1.
+    pub signed: Option<SignedTx>,
+}
+
+/// `TxPipeline` sending the configured mix from the accounts of the pool.
+///
+/// Fees are quoted once per operation type and token and reused while the fee cache keeps
+/// them. A rejected transaction is taken back from the local view of the pool, rewinding
+/// the sender's nonce, and the nonce is reconciled with the server when the rejection says
+/// it is off. The signing key of a `ChangePubKey` is only
+/// marked as set once the confirmation tracker saw it committed. With a fee sponsor, the
+/// transfers planned by the built-in scenarios go out in batches whose fee the sponsor pays.
+/// Planned operations take their nonces from the [`BatchNonceTracker`], which tells of the
+/// batches in flight that a rejected batch of the same sender leaves without valid nonces.
+///
+/// The rollup API has no block endpoint, so block progress is read from the block of the
+/// last accepted transaction: `committed_blocks` boundaries only see blocks the run's own
+/// transactions land in.
+pub struct RollupPipeline<P> {
+    provider: Arc<P>,
+    pool: tokio::sync::Mutex<AccountPool>,
+    config: TransactionConfig,
+    mix: Mutex<TransactionMix>,
+    tokens: TokenRegistry,
+    token_mix: TokenMix,
+    denylist: AddressDenylist,
+    fees: Arc<FeeCache>,
+    rng: Mutex<StreamRng>,
+    confirmation: ConfirmationConfig,
+    l1: Option<L1Node>,
+    /// Nonce managers of the accounts' L1 wallets, created on their first deposit.
+    l1_nonces: Mutex<HashMap<Address, Arc<L1NonceManager<L1Signer>>>>,
+    tag: Option<RunTag>,
+    last_accepted: Mutex<Option<TxHash>>,
+    /// Accepted `ChangePubKey`s not yet committed, by hash.
+    key_changes: Mutex<HashMap<TxHash, Address>>,
+    recorder: Mutex<Option<Arc<RunRecorder>>>,
+    sponsor: Option<tokio::sync::Mutex<FeeSponsor>>,
+    batch_nonces: Mutex<BatchNonceTracker>,
+}
+
+impl<P: Provider + Send + Sync + 'static> RollupPipeline<P> {
+    /// Pipeline sending from the accounts of `pool`, the configured tokens checked against
+    /// the token list of the rollup.
+    pub async fn new(
+        provider: Arc<P>,
+        pool: AccountPool,
+        network: &NetworkConfig,
+        config: &TransactionConfig,
+    ) -> ResponseResult<Self> {
+        let tokens = TokenRegistry::fetch(&*provider).await?;
+        let token_mix = TokenMix::new(&config.tokens, &tokens)?;
+        let contract = provider.contract_address().await?.main_contract;
+        let denylist =
+            AddressDenylist::new(config).with_contract_address(parse_address(&contract)?);
+        Ok(Self {
+            provider,
+            pool: tokio::sync::Mutex::new(pool),
+            config: config.clone(),
+            mix: Mutex::new(TransactionMix::new(&config.mix)?),
+            tokens,
+            token_mix,
+            denylist,
+            fees: Arc::new(FeeCache::new(network.fee_cache.clone())),
+            rng: Mutex::new(StreamRng::seed_from_u64(rand::random())),
+            confirmation: network.confirmation.clone(),
+            l1: None,
+            l1_nonces: Mutex::new(HashMap::new()),
+            tag: None,
+            last_accepted: Mutex::new(None),
+            key_changes: Mutex::new(HashMap::new()),
+            recorder: Mutex::new(None),
+            sponsor: None,
+            batch_nonces: Mutex::new(BatchNonceTracker::new()),
+        })
+    }
+
+    /// Sends deposits through `node`, without it no deposits are generated.
+    pub fn with_l1(mut self, node: L1Node) -> Self {
+        self.l1 = Some(node);
+        self
+    }
+
+    /// Tags the calldata of deposits with the identifier of the run.
+    pub fn with_run_tag(mut self, tag: RunTag) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    /// Pays the fees of planned transfers from the last account of the pool, which is taken
+    /// out of it so generated operations never use the sponsor's nonces.
+    pub fn with_sponsor(mut self, config: SponsorConfig) -> Self {
+        let symbol = self.scenario_token().symbol.clone();
+        if let Some(account) = self.pool.get_mut().take_last() {
+            let balance = account
+                .state
+                .balances
+                .get(&symbol)
+                .cloned()
+                .unwrap_or_default();
+            let nonce = account.state.nonce;
+            self.sponsor = Some(tokio::sync::Mutex::new(FeeSponsor::new(
+                account.wallet,
+                nonce,
+                balance,
+                config,
+            )));
+        }
+        self
+    }
+
+    pub fn provider(&self) -> &Arc<P> {
+        &self.provider
+    }
+
+    /// Reports to the recorder of the engine running the pipeline, if there is one.
+    fn report(&self, f: impl FnOnce(&RunRecorder)) {
+        let recorder = self.recorder.lock().unwrap().clone();
+        if let Some(recorder) = recorder {
+            f(&recorder);
+        }
+    }
+
+    /// Counts the fees of accepted transactions towards the onboarding costs of their senders.
+    fn report_fees(&self, transactions: &[Transaction]) {
+        self.report(|recorder| {
+            recorder.with_onboarding_costs(|costs| {
+                for transaction in transactions {
+                    if let Some(fee) = transaction.fee() {
+                        costs.record_l2_fee(transaction.from(), fee);
+                    }
+                }
+            })
+        });
+    }
+
+    /// Polls `tx_hash` until it is executed, failing with `OperationTimeout` after `timeout`.
+    async fn wait_executed(
+        &self,
+        tx_hash: TxHash,
+        timeout: Duration,
+    ) -> Result<TransactionInfo, ClientError> {
+        let poll_interval = Duration::from_millis(self.confirmation.poll_interval_ms);
+        let polled = tokio::time::timeout(timeout, async {
+            loop {
+                let info = self.provider.tx_info(tx_hash).await?;
+                if info.executed {
+                    return Ok(info);
+                }
+                tokio::time::sleep(poll_interval).await;
+            }
+        });
+        polled.await.map_err(|_| ClientError::OperationTimeout)?
+    }
+
+    /// Replaces the configured mix of the operations generated from now on.
+    pub fn set_mix(&self, mix: &BTreeMap<TransactionKind, u32>) -> ResponseResult<()> {
+        *self.mix.lock().unwrap() = TransactionMix::new(mix)?;
+        Ok(())
+    }
+
+    /// Fee to pay for an operation of `kind` in `token`, `None` for L1 operations.
+    ///
+    /// The generator picks the variant of the operation afterwards, so the most expensive
+    /// one is quoted; the rollup accepts fees above its quote.
+    async fn fee(&self, kind: TransactionKind, token: &Token) -> ResponseResult<Option<BigUint>> {
+        let fee_type = match kind {
+            TransactionKind::Deposit => return Ok(None),
+            TransactionKind::Transfer | TransactionKind::TransferToNew => TxFeeTypes::Transfer,
+            TransactionKind::Withdraw => TxFeeTypes::FastWithdraw,
+            TransactionKind::ForcedExit => TxFeeTypes::Withdraw,
+            TransactionKind::ChangePubKey => TxFeeTypes::ChangePubKey(ChangePubKeyFeeType::ECDSA),
+            TransactionKind::MintNFT => TxFeeTypes::MintNFT,
+            TransactionKind::WithdrawNFT => TxFeeTypes::WithdrawNFT,
+        };
+        let token_like = TokenLike::Id(token.id);
+        let fee = match self.fees.get(fee_type, &token_like) {
+            Some(fee) => fee,
+            None => {
+                // A fresh address is quoted, so transfers pay enough to create the recipient.
+                let fee = self
+                    .provider
+                    .get_tx_fee(fee_type, Address::zero(), token_like.clone())
+                    .await?;
+                self.fees.insert(fee_type, token_like, fee.clone());
+                fee
+            }
+        };
+        round_up_packable_fee(&fee.total_fee)
+            .map(Some)
+            .ok_or(ClientError::NotPackableValue)
+    }
+
+    /// Signs the operation with the sender's keys, `None` for deposits.
+    async fn sign(
+        &self,
+        pool: &AccountPool,
+        transaction: &Transaction,
+        token: &Token,
+    ) -> ResponseResult<Option<SignedTx>> {
+        let wallet = &pool
+            .get(&transaction.from())
+            .ok_or(ClientError::IncorrectAddress)?
+            .wallet;
+        let time_range = TimeRange::default();
+        let signed = match transaction.clone() {
+            Transaction::Deposit { .. } => return Ok(None),
+            Transaction::Transfer {
+                to,
+                amount,
+                fee,
+                nonce,
+                ..
+            }
+            | Transaction::TransferToNew {
+                to,
+                amount,
+                fee,
+                nonce,
+                ..
+            } => {
+                wallet
+                    .sign_transfer(to, token, amount, fee, nonce, time_range)
+                    .await?
+            }
+            Transaction::Withdraw {
+                to,
+                amount,
+                fee,
+                nonce,
+                fast,
+                ..
+            } => {
+                wallet
+                    .sign_withdraw(to, token, amount, fee, nonce, fast, time_range)
+                    .await?
+            }
+            Transaction::ChangePubKey {
+                fee,
+                nonce,
+                auth_type,
+                ..
+            } => {
+                wallet
+                    .sign_change_pub_key(token, fee, nonce, auth_type, time_range)
+                    .await?
+            }
+            Transaction::MintNFT {
+                to,
+                content_hash,
+                fee,
+                nonce,
+                ..
+            } => {
+                wallet
+                    .sign_mint_nft(to, content_hash, token, fee, nonce)
+                    .await?
+            }
+            Transaction::WithdrawNFT {
+                to,
+                token: nft,
+                fee,
+                nonce,
+                ..
+            } => {
+                wallet
+                    .sign_withdraw_nft(to, nft, token, fee, nonce, false, time_range)
+                    .await?
+            }
+            Transaction::ForcedExit { to, fee, nonce, .. } => {
+                wallet
+                    .sign_forced_exit(to, token, fee, nonce, time_range)
+                    .await?
+            }
+        };
+        Ok(Some(signed))
+    }
+
+    /// Deposits from the sender's L1 key and waits until the rollup executed the deposit.
+    async fn deposit(&self, transaction: &Transaction, token: &Token) -> ResponseResult<TxHash> {
+        let Transaction::Deposit {
+            from, to, amount, ..
+        } = transaction
+        else {
+            return Err(ClientError::IncorrectInput);
+        };
+        let completed = self.l1_deposit(*from, *to, token, amount).await?;
+        Ok(TxHash {
+            data: completed.tx_hash.0,
+        })
+    }
+
+    /// Deposits `amount` from the L1 key of `from` to `to`, crediting the local view of `to`
+    /// once the rollup executed it.
+    async fn l1_deposit(
+        &self,
+        from: Address,
+        to: Address,
+        token: &Token,
+        amount: &BigUint,
+    ) -> ResponseResult<CompletedDeposit> {
+        let node = self
+            .l1
+            .as_ref()
+            .ok_or_else(|| ClientError::UnsupportedMethod("deposit".to_string()))?;
+        let (signer, nonces) = {
+            let pool = self.pool.lock().await;
+            let wallet = pool
+                .get(&from)
+                .ok_or(ClientError::IncorrectAddress)?
+                .wallet
+                .eth_signer();
+            let nonces = self
+                .l1_nonces
+                .lock()
+                .unwrap()
+                .entry(from)
+                .or_insert_with(|| Arc::new(node.nonce_manager(wallet)))
+                .clone();
+            (node.signer(wallet), nonces)
+        };
+        let mut depositor = L1Depositor::new(&*self.provider, &signer, node.poll_config().clone())
+            .with_nonce_manager(nonces.as_ref());
+        if let Some(tag) = &self.tag {
+            depositor = depositor.with_run_tag(tag);
+        }
+        let completed = depositor
+            .deposit(token, U256::from_big_endian(&amount.to_bytes_be()), to)
+            .await
+            .map_err(|err| {
+                if let DepositError::Reverted { revert, .. } = &err {
+                    self.report(|recorder| recorder.record_deposit_revert(revert));
+                }
+                match err {
+                    DepositError::Provider(err) => err,
+                    err => ClientError::NetworkError(err.to_string()),
+                }
+            })?;
+        if let Some(account) = self.pool.lock().await.get_mut(&to) {
+            account.state.accept_deposit(&token.symbol, amount);
+        }
+        let (gas_used, gas_price) = completed.gas_cost();
+        self.report(|recorder| {
+            recorder.with_onboarding_costs(|costs| {
+                costs.record_deposit(to, completed.tx_hash, gas_used, gas_price)
+            })
+        });
+        Ok(completed)
+    }
+
+    /// Token the built-in scenarios send, the first configured one.
+    pub fn scenario_token(&self) -> &Token {
+        &self.token_mix.tokens()[0]
+    }
+
+    /// Address of the account at `index` of the pool.
+    pub async fn address_at(&self, index: usize) -> ResponseResult<Address> {
+        let pool = self.pool.lock().await;
+        pool.addresses()
+            .get(index)
+            .copied()
+            .ok_or(ClientError::IncorrectAddress)
+    }
+
+    /// Signs and sends transfers and withdrawals of the scenario token planned by a built-in
+    /// scenario, all from `from` and as one batch when there are several.
+    ///
+    /// Nonce and balances are taken locally right away, like for generated operations.
+    /// With a fee sponsor, transfers go out with a zero fee in the sponsor's batches until
+    /// its balance no longer covers a batch, the rest then pays its own fees.
+    pub async fn send_planned(
+        &self,
+        from: Address,
+        planned: Vec<(TransactionKind, Address, BigUint)>,
+    ) -> ResponseResult<Vec<TxHash>> {
+        let Some(sponsor) = &self.sponsor else {
+            return self.send_paid(from, planned).await;
+        };
+        if planned
+            .iter()
+            .any(|(kind, _, _)| *kind != TransactionKind::Transfer)
+        {
+            return self.send_paid(from, planned).await;
+        }
+        let mut sponsor = sponsor.lock().await;
+        let batch_size = sponsor.batch_size();
+        let mut tx_hashes = Vec::with_capacity(planned.len());
+        for (index, chunk) in planned.chunks(batch_size).enumerate() {
+            match self.send_sponsored(&mut sponsor, from, chunk).await? {
+                Some(sponsored) => tx_hashes.extend(sponsored),
+                None => {
+                    let rest = planned[index * batch_size..].to_vec();
+                    tx_hashes.extend(self.send_paid(from, rest).await?);
+                    break;
+                }
+            }
+        }
+        Ok(tx_hashes)
+    }
+
+    /// Sends planned transfers with a zero fee in one batch, the sponsor's transfer paying for
+    /// it. `None` when the sponsor can no longer pay the batch, nothing was sent then.
+    async fn send_sponsored(
+        &self,
+        sponsor: &mut FeeSponsor,
+        from: Address,
+        planned: &[(TransactionKind, Address, BigUint)],
+    ) -> ResponseResult<Option<Vec<TxHash>>> {
+        let token = self.scenario_token().clone();
+        let recorder = self.recorder.lock().unwrap().clone().unwrap_or_default();
+        let recipients: Vec<Address> = planned.iter().map(|(_, to, _)| *to).collect();
+        let fee = sponsor
+            .quote_fee(&*self.provider, &recipients, &token, &recorder)
+            .await
+            .map_err(sponsor_client_error)?;
+
+        let mut pool = self.pool.lock().await;
+        let nonces = self.reserve_batch(&pool, from, planned.len())?;
+        let account = pool.get(&from).ok_or(ClientError::IncorrectAddress)?;
+        let mut transactions = Vec::with_capacity(planned.len());
+        let mut transfers = Vec::with_capacity(planned.len());
+        for ((_, to, amount), nonce) in planned.iter().zip(nonces.nonces()) {
+            transactions.push(Transaction::Transfer {
+                from,
+                to: *to,
+                token: token.id,
+                amount: amount.clone(),
+                fee: BigUint::default(),
+                nonce,
+            });
+            transfers.push(SponsoredTransfer {
+                wallet: &account.wallet,
+                to: *to,
+                amount: amount.clone(),
+                nonce,
+            });
+        }
+        let batch = match sponsor
+            .sign_batch(transfers, &token, fee, TimeRange::default())
+            .await
+        {
+            Ok(batch) => batch,
+            Err(SponsorError::InsufficientBalance {
+                required,
+                available,
+            }) => {
+                self.settle_batch(nonces.id, false);
+                warn!(
+                    "Sponsor balance {} no longer covers a batch fee of {}, transfers pay their own fees",
+                    available, required
+                );
+                return Ok(None);
+            }
+            Err(err) => {
+                self.settle_batch(nonces.id, false);
+                return Err(sponsor_client_error(err));
+            }
+        };
+        for transaction in &transactions {
+            pool.reserve_nonce(&from, transaction);
+            spend_locally(&mut pool, transaction, &token);
+        }
+        drop(pool);
+
+        let result = sponsor.submit(&*self.provider, batch, &recorder).await;
+        self.settle_batch(nonces.id, result.is_ok());
+        match result {
+            Ok(mut tx_hashes) => {
+                // The sponsor's own transfer comes last.
+                tx_hashes.truncate(transactions.len());
+                if let Some(tx_hash) = tx_hashes.last() {
+                    *self.last_accepted.lock().unwrap() = Some(*tx_hash);
+                }
+                let mut pool = self.pool.lock().await;
+                for transaction in &transactions {
+                    pool.accept(transaction);
+                }
+                Ok(Some(tx_hashes))
+            }
+            Err(err) => {
+                let err = sponsor_client_error(err);
+                self.reject(&transactions, &token, &err).await;
+                Err(err)
+            }
+        }
+    }
+
+    /// Sends planned operations paying their own fees.
+    async fn send_paid(
+        &self,
+        from: Address,
+        planned: Vec<(TransactionKind, Address, BigUint)>,
+    ) -> ResponseResult<Vec<TxHash>> {
+        let token = self.scenario_token().clone();
+        let mut fees = Vec::with_capacity(planned.len());
+        for (kind, _, _) in &planned {
+            fees.push(self.fee(*kind, &token).await?.unwrap_or_default());
+        }
+
+        let mut transactions = Vec::with_capacity(planned.len());
+        let mut signed = Vec::with_capacity(planned.len());
+        let nonces = {
+            let mut pool = self.pool.lock().await;
+            let nonces = self.reserve_batch(&pool, from, planned.len())?;
+            for (((kind, to, amount), fee), nonce) in
+                planned.into_iter().zip(fees).zip(nonces.nonces())
+            {
+                let transaction = match kind {
+                    TransactionKind::Withdraw => Transaction::Withdraw {
+                        from,
+                        to,
+                        token: token.id,
+                        amount,
+                        fee,
+                        nonce,
+                        fast: false,
+                    },
+                    _ => Transaction::Transfer {
+                        from,
+                        to,
+                        token: token.id,
+                        amount,
+                        fee,
+                        nonce,
+                    },
+                };
+                let tx = match self.sign(&pool, &transaction, &token).await {
+                    Ok(tx) => tx,
+                    Err(err) => {
+                        self.settle_batch(nonces.id, false);
+                        return Err(err);
+                    }
+                };
+                pool.reserve_nonce(&from, &transaction);
+                spend_locally(&mut pool, &transaction, &token);
+                signed.extend(tx);
+                transactions.push(transaction);
+            }
+            nonces
+        };
+
+        let result = match signed.len() {
+            1 => {
+                let (tx, eth_signature) = signed.remove(0);
+                self.provider
+                    .send_tx(tx, eth_signature)
+                    .await
+                    .map(|tx_hash| vec![tx_hash])
+            }
+            _ => self.provider.send_txs_batch(signed, None).await,
+        };
+        self.settle_batch(nonces.id, result.is_ok());
+        match &result {
+            Ok(tx_hashes) => {
+                if let Some(tx_hash) = tx_hashes.last() {
+                    *self.last_accepted.lock().unwrap() = Some(*tx_hash);
+                }
+                self.report_fees(&transactions);
+                let mut pool = self.pool.lock().await;
+                for transaction in &transactions {
+                    pool.accept(transaction);
+                }
+            }
+            Err(err) => self.reject(&transactions, &token, err).await,
+        }
+        result
+    }
+
+    /// Reserves consecutive nonces of `from` for `count` planned operations, starting at the
+    /// sender's next nonce in the pool.
+    fn reserve_batch(
+        &self,
+        pool: &AccountPool,
+        from: Address,
+        count: usize,
+    ) -> ResponseResult<BatchNonces> {
+        let nonce = pool.nonce(&from).ok_or(ClientError::IncorrectAddress)?;
+        let mut batches = self.batch_nonces.lock().unwrap();
+        batches.set_nonce(from, nonce);
+        batches
+            .reserve(&vec![from; count])
+            .map_err(|_| ClientError::IncorrectInput)
+    }
+
+    /// Consumes the nonces of an accepted batch or rolls back a refused one, warning of the
+    /// batches in flight that were signed with nonces following the refused ones.
+    fn settle_batch(&self, id: BatchId, accepted: bool) {
+        let mut batches = self.batch_nonces.lock().unwrap();
+        if accepted {
+            batches.confirm(id);
+            return;
+        }
+        let invalidated = batches.reject(id);
+        if !invalidated.is_empty() {
+            warn!(
+                "{} batches in flight were signed after a refused batch of the same sender and will be refused as well",
+                invalidated.len()
+            );
+        }
+    }
+
+    /// Takes transactions the server refused back from the pool, reconciling the sender's
+    /// nonce with the server when it disagreed on it.
+    async fn reject(&self, transactions: &[Transaction], token: &Token, err: &ClientError) {
+        let mut pool = self.pool.lock().await;
+        for transaction in transactions {
+            if pool.reject(transaction, token, err) != Some(NonceRejection::Mismatch) {
+                continue;
+            }
+            let from = transaction.from();
+            if let Err(err) = pool.reconcile_nonce(&*self.provider, from).await {
+                warn!(?from, error = %err, "unable to reconcile nonce");
+            }
+        }
+    }
+
+    /// Block of the last accepted transaction, once it is committed.
+    async fn last_block(&self) -> ResponseResult<Option<BlockInfo>> {
+        let last_accepted = *self.last_accepted.lock().unwrap();
+        let Some(tx_hash) = last_accepted else {
+            return Ok(None);
+        };
+        let info = self.provider.tx_info(tx_hash).await?;
+        Ok(info.block.filter(|block| block.committed))
+    }
+}
+
+#[async_trait]
+impl<P: Provider + Send + Sync + 'static> TxPipeline for RollupPipeline<P> {
+    type Tx = RollupTx;
+
+    async fn prepare(&self) -> Option<RollupTx> {
+        let (kind, token) = {
+            let mut rng = self.rng.lock().unwrap();
+            let kind = self.mix.lock().unwrap().choose(&mut *rng);
+            (kind, self.token_mix.choose(&mut *rng).clone())
+        };
+        if kind == TransactionKind::Deposit && self.l1.is_none() {
+            return None;
+        }
+        let fee = match self.fee(kind, &token).await {
+            Ok(fee) => fee.unwrap_or_default(),
+            Err(err) => {
+                warn!(tx_type = kind.name(), token = %token.symbol, error = %err, "unable to quote fee");
+                return None;
+            }
+        };
+
+        let mut pool = self.pool.lock().await;
+        let transaction = {
+            let mut rng = self.rng.lock().unwrap();
+            let rng = &mut *rng;
+            match kind {
+                TransactionKind::Transfer => {
+                    pool.generate_transfer(rng, &self.config, &self.denylist, &token, fee)
+                }
+                TransactionKind::MintNFT | TransactionKind::WithdrawNFT => {
+                    pool.generate_nft(rng, kind, &self.config, &self.denylist, &token, fee)
+                }
+                TransactionKind::ForcedExit => {
+                    pool.generate_forced_exit(rng, &self.denylist, &token, fee)
+                }
+                _ => pool.generate_own(rng, kind, &self.config, &self.denylist, &token, fee),
+            }
+        }?;
+        match self.sign(&pool, &transaction, &token).await {
+            Ok(signed) => Some(RollupTx {
+                transaction,
+                token,
+                signed,
+            }),
+            Err(err) => {
+                warn!(tx_type = kind.name(), from = ?transaction.from(), error = %err, "unable to sign");
+                pool.reject(&transaction, &token, &err);
+                None
+            }
+        }
+    }
+
+    fn tx_type(tx: &RollupTx) -> &'static str {
+        tx.transaction.kind().name()
+    }
+
+    fn tx_fee(tx: &RollupTx) -> Option<BigUint> {
+        tx.transaction.fee().cloned()
+    }
+
+    fn tx_account(tx: &RollupTx) -> Option<Address> {
+        Some(tx.transaction.from())
+    }
+
+    fn tx_amount(tx: &RollupTx) -> Option<(String, BigUint)> {
+        match &tx.transaction {
+            Transaction::Deposit { amount, .. }
+            | Transaction::Transfer { amount, .. }
+            | Transaction::TransferToNew { amount, .. }
+            | Transaction::Withdraw { amount, .. } => {
+                Some((tx.token.symbol.clone(), amount.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    fn tx_nft(tx: &RollupTx) -> Option<NftOperation> {
+        tx.transaction.nft_operation()
+    }
+
+    fn tx_change_pubkey(tx: &RollupTx) -> Option<ChangePubKeyFeeType> {
+        match tx.transaction {
+            Transaction::ChangePubKey { auth_type, .. } => Some(auth_type),
+            _ => None,
+        }
+    }
+
+    fn tx_copy(tx: &RollupTx) -> Option<RollupTx> {
+        tx.signed.is_some().then(|| tx.clone())
+    }
+
+    fn tx_payload(tx: &RollupTx) -> Option<String> {
+        let (signed, _) = tx.signed.as_ref()?;
+        serde_json::to_string(signed).ok()
+    }
+
+    fn tx_signed(tx: &RollupTx) -> Option<&SignedTx> {
+        tx.signed.as_ref()
+    }
+
+    fn tracked_as(tx: &RollupTx) -> Option<fn(TxHash) -> TrackedOp> {
+        match tx.transaction {
+            // Deposits are only accepted once the rollup executed them.
+            Transaction::Deposit { .. } => None,
+            Transaction::Withdraw { .. } => Some(TrackedOp::Withdrawal),
+            _ => Some(TrackedOp::Tx),
+        }
+    }
+
+    async fn submit(&self, tx: RollupTx) -> Result<TxHash, ClientError> {
+        let RollupTx {
+            transaction,
+            token,
+            signed,
+        } = tx;
+        let result = match signed {
+            Some((signed, eth_signature)) => self.provider.send_tx(signed, eth_signature).await,
+            None => self.deposit(&transaction, &token).await,
+        };
+        match &result {
+            Ok(tx_hash) => {
+                *self.last_accepted.lock().unwrap() = Some(*tx_hash);
+                if let Transaction::ChangePubKey { from, .. } = &transaction {
+                    self.key_changes.lock().unwrap().insert(*tx_hash, *from);
+                }
+                self.report_fees(std::slice::from_ref(&transaction));
+                self.pool.lock().await.accept(&transaction);
+            }
+            Err(err) => {
+                self.reject(std::slice::from_ref(&transaction), &token, err)
+                    .await
+            }
+        }
+        result
+    }
+
+    async fn tx_reached(&self, tx_hash: TxHash, status: TxStatus) {
+        let address = self.key_changes.lock().unwrap().remove(&tx_hash);
+        if let (Some(address), TxStatus::Committed | TxStatus::Verified) = (address, status) {
+            self.pool.lock().await.confirm_signing_key(&address);
+        }
+    }
+
+    async fn wait_confirmed(&self, tx_hash: TxHash, tx_type: &str) -> Result<(), ClientError> {
+        let timeout = self.confirmation.timeout_for(tx_type);
+        let info = self.wait_executed(tx_hash, timeout).await?;
+        if info.success == Some(false) {
+            debug!(%tx_hash, reason = ?info.fail_reason, "transaction failed");
+            return Err(ClientError::Other);
+        }
+        Ok(())
+    }
+
+    /// A re-sync sets the nonces back to the committed ones; transactions accepted but not
+    /// yet in a block make the next ones mismatch, which reconciles them.
+    async fn collect_garbage(&self, action: AccountGcAction, dust_threshold: &BigUint) {
+        let mut pool = self.pool.lock().await;
+        match action {
+            AccountGcAction::Compact => {
+                let stats = pool.compact(dust_threshold);
+                debug!(
+                    balances = stats.balances_removed,
+                    nfts = stats.nfts_removed,
+                    "compacted the accounts"
+                );
+            }
+            AccountGcAction::Resync => match pool.sync(self.provider.as_ref()).await {
+                Ok(drifted) => {
+                    for (address, discrepancies) in drifted {
+                        warn!(?address, ?discrepancies, "local account state drifted");
+                    }
+                }
+                Err(err) => warn!("Unable to re-sync the accounts: {}", err),
+            },
+        }
+    }
+
+    fn seed(&self, streams: &RngStreams) {
+        *self.rng.lock().unwrap() = streams.stream(RngStream::Amounts);
+    }
+
+    fn report_to(&self, recorder: Arc<RunRecorder>) {
+        if let Some(Ok(sponsor)) = self.sponsor.as_ref().map(|sponsor| sponsor.try_lock()) {
+            recorder.with_sponsor(|ledger| {
+                ledger.set_sponsor(
+                    sponsor.address(),
+                    &self.scenario_token().symbol,
+                    sponsor.balance().clone(),
+                )
+            });
+        }
+        *self.recorder.lock().unwrap() = Some(recorder);
+    }
+
+    /// `None` while a transaction is being prepared, the accounts are only consistent
+    /// between two of them.
+    fn checkpoint(&self) -> Option<PipelineState> {
+        let Ok(pool) = self.pool.try_lock() else {
+            warn!("accounts are in use, unable to checkpoint them");
+            return None;
+        };
+        Some(PipelineState {
+            accounts: pool.checkpoint(),
+            pending: Vec::new(),
+        })
+    }
+
+    fn restore(&self, state: &PipelineState) -> Result<(), CheckpointError> {
+        let mut pool = self
+            .pool
+            .try_lock()
+            .map_err(|_| CheckpointError::AccountsBusy)?;
+        pool.restore(&state.accounts)
+    }
+
+    fn tokens(&self) -> Option<TokenRegistry> {
+        Some(self.tokens.clone())
+    }
+
+    fn fee_cache(&self) -> Option<Arc<FeeCache>> {
+        Some(self.fees.clone())
+    }
+}
+
+/// Takes the amount and fee of a planned transfer or withdrawal from the sender's local
+/// balance, crediting the amount of a transfer to a recipient of the pool.
+fn spend_locally(pool: &mut AccountPool, transaction: &Transaction, token: &Token) {
+    let (from, to, amount, fee, credited) = match transaction {
+        Transaction::Transfer {
+            from,
+            to,
+            amount,
+            fee,
+            ..
+        } => (from, to, amount, fee, true),
+        Transaction::Withdraw {
+            from,
+            to,
+            amount,
+            fee,
+            ..
+        } => (from, to, amount, fee, false),
+        _ => return,
+    };
+    if let Some(balance) = pool
+        .get_mut(from)
+        .and_then(|account| account.state.balances.get_mut(&token.symbol))
+    {
+        *balance = if *balance >= amount + fee {
+            &*balance - amount - fee
+        } else {
+            BigUint::default()
+        };
+    }
+    if let (true, Some(recipient)) = (credited, pool.get_mut(to)) {
+        *recipient
+            .state
+            .balances
+            .entry(token.symbol.clone())
+            .or_default() += amount;
+    }
+}
+
+/// Server error behind a failed sponsored batch, the sponsor's own refusals as invalid input.
+fn sponsor_client_error(err: SponsorError) -> ClientError {
+    match err {
+        SponsorError::Client(err) => err,
+        SponsorError::EmptyBatch | SponsorError::InsufficientBalance { .. } => {
+            ClientError::IncorrectInput
+        }
+    }
+}
+
+/// Merchant payouts between the accounts of the pool: the payers first, then the merchants.
+pub struct MerchantPool<'a, P> {
+    pipeline: &'a RollupPipeline<P>,
+    payers: usize,
+}
+
+impl<'a, P> MerchantPool<'a, P> {
+    pub fn new(pipeline: &'a RollupPipeline<P>, payers: usize) -> Self {
+        Self { pipeline, payers }
+    }
+}
+
+#[async_trait]
+impl<'a, P: Provider + Send + Sync + 'static> MerchantAccounts for MerchantPool<'a, P> {
+    async fn pay(&self, payer: usize, merchant: usize, amount: u64) -> Result<(), String> {
+        let from = self.pipeline.address_at(payer).await;
+        let to = self.pipeline.address_at(self.payers + merchant).await;
+        let (from, to) = (
+            from.map_err(|err| err.to_string())?,
+            to.map_err(|err| err.to_string())?,
+        );
+        let transfer = (TransactionKind::Transfer, to, BigUint::from(amount));
+        self.pipeline
+            .send_planned(from, vec![transfer])
+            .await
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+
+    async fn pay_out(&self, merchant: usize, withdrawals: &[u64]) -> Result<(), String> {
+        let merchant = self
+            .pipeline
+            .address_at(self.payers + merchant)
+            .await
+            .map_err(|err| err.to_string())?;
+        let withdrawals = withdrawals
+            .iter()
+            .map(|amount| (TransactionKind::Withdraw, merchant, BigUint::from(*amount)))
+            .collect();
+        self.pipeline
+            .send_planned(merchant, withdrawals)
+            .await
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+}
+
+/// Phases of the engine sending a changing mix through the pipeline.
+pub struct MixedPool<'a, P> {
+    pipeline: Arc<RollupPipeline<P>>,
+    config: &'a GeneralConfig,
+    recorder: Arc<RunRecorder>,
+    metrics: Arc<Metrics>,
+}
+
+impl<'a, P> MixedPool<'a, P> {
+    pub fn new(
+        pipeline: Arc<RollupPipeline<P>>,
+        config: &'a GeneralConfig,
+        recorder: Arc<RunRecorder>,
+        metrics: Arc<Metrics>,
+    ) -> Self {
+        Self {
+            pipeline,
+            config,
+            recorder,
+            metrics,
+        }
+    }
+}
+
+#[async_trait]
+impl<P: Provider + Send + Sync + 'static> MixedWorkload for MixedPool<'_, P> {
+    async fn run(
+        &mut self,
+        tps: u32,
+        mix: &BTreeMap<TransactionKind, u32>,
+        duration: Duration,
+    ) -> Result<Vec<TxRecord>, String> {
+        self.pipeline.set_mix(mix).map_err(|err| err.to_string())?;
+        let recorded = self.recorder.records().len();
+        let engine = Engine::for_phase(
+            self.pipeline.clone(),
+            self.config,
+            tps,
+            duration,
+            self.recorder.clone(),
+            self.metrics.clone(),
+        );
+        engine.run().await;
+        Ok(self.recorder.records().split_off(recorded))
+    }
+}
+
+/// Interleaving rounds on the accounts of the pool, each transferring to the next account.
+pub struct InterleavingPool<'a, P> {
+    pipeline: &'a RollupPipeline<P>,
+}
+
+impl<'a, P> InterleavingPool<'a, P> {
+    pub fn new(pipeline: &'a RollupPipeline<P>) -> Self {
+        Self { pipeline }
+    }
+}
+
+#[async_trait]
+impl<'a, P: Provider + Send + Sync + 'static> InterleavingTarget for InterleavingPool<'a, P> {
+    async fn balance(&self, account: usize) -> Result<u64, String> {
+        let address = self
+            .pipeline
+            .address_at(account)
+            .await
+            .map_err(|err| err.to_string())?;
+        let info = self
+            .pipeline
+            .provider
+            .account_info(address)
+            .await
+            .map_err(|err| err.to_string())?;
+        let balance = info
+            .committed
+            .balances
+            .get(&self.pipeline.scenario_token().symbol)
+            .map(|balance| balance.0.clone())
+            .unwrap_or_default();
+        balance
+            .to_u64()
+            .ok_or_else(|| format!("balance {} does not fit the scenario amounts", balance))
+    }
+
+    async fn deposit(&self, account: usize, amount: u64) -> Result<BlockNumber, String> {
+        let address = self
+            .pipeline
+            .address_at(account)
+            .await
+            .map_err(|err| err.to_string())?;
+        let token = self.pipeline.scenario_token();
+        let completed = self
+            .pipeline
+            .l1_deposit(address, address, token, &BigUint::from(amount))
+            .await
+            .map_err(|err| err.to_string())?;
+        let block = completed.ethop.block.map_or(0, |block| block.block_number);
+        Ok(BlockNumber(block as u32))
+    }
+
+    async fn transfer(&self, account: usize, amount: u64) -> Result<(BlockNumber, u64), String> {
+        let accounts = self.pipeline.pool.lock().await.len();
+        let from = self.pipeline.address_at(account).await;
+        let to = self.pipeline.address_at((account + 1) % accounts).await;
+        let (from, to) = (
+            from.map_err(|err| err.to_string())?,
+            to.map_err(|err| err.to_string())?,
+        );
+        let transfer = (TransactionKind::Transfer, to, BigUint::from(amount));
+        let tx_hash = match self.pipeline.send_planned(from, vec![transfer]).await {
+            Ok(tx_hashes) => tx_hashes[0],
+            Err(err) => return Err(err.to_string()),
+        };
+        let fee = self
+            .pipeline
+            .fee(TransactionKind::Transfer, self.pipeline.scenario_token())
+            .await
+            .map_err(|err| err.to_string())?
+            .unwrap_or_default();
+        let timeout = self
+            .pipeline
+            .confirmation
+            .timeout_for(TransactionKind::Transfer.name());
+        let info = self
+            .pipeline
+            .wait_executed(tx_hash, timeout)
+            .await
+            .map_err(|err| err.to_string())?;
+        if info.success == Some(false) {
+            return Err(info.fail_reason.unwrap_or_default());
+        }
+        let block = info.block.map_or(0, |block| block.block_number);
+        Ok((BlockNumber(block as u32), fee.to_u64().unwrap_or(u64::MAX)))
+    }
+}
+
+#[async_trait]
+impl<P: Provider + Send + Sync + 'static> BlockProgress for RollupPipeline<P> {
+    async fn last_committed_block(&self) -> ResponseResult<BlockNumber> {
+        let block = self.last_block().await?;
+        Ok(BlockNumber(
+            block.map_or(0, |block| block.block_number as u32),
+        ))
+    }
+
+    async fn last_verified_block(&self) -> ResponseResult<BlockNumber> {
+        let block = self.last_block().await?;
+        Ok(BlockNumber(match block {
+            Some(block) if block.verified => block.block_number as u32,
+            Some(block) => (block.block_number as u32).saturating_sub(1),
+            None => 0,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use ethers::signers::LocalWallet;
+    use serde_json::json;
+
+    use super::*;
+    use crate::clock::SimulatedClock;
+    use crate::config::Config;
+    use crate::report::history_check::check_accounts;
+    use crate::rollup::confirmation::{ConfirmationSla, ConfirmationTracker};
+    use crate::rollup::mock::MockProvider;
+    use crate::rollup::provider::ProviderMethod;
+    use crate::rollup::types::tx::ZkSyncTx;
+    use crate::rollup::types::{AccountId, Nonce};
+    use crate::throttler::Throttler;
+    use crate::wallet::Wallet;
+
+    /// Two registered accounts holding RBTC.
+    async fn funded_pool() -> AccountPool {
+        let mut wallets = Vec::new();
+        for seed in 1..=2u8 {
+            let eth_signer = LocalWallet::from_bytes(&[seed; 32]).unwrap();
+            wallets.push(Wallet::new(eth_signer).await.unwrap());
+        }
+        let mut pool = AccountPool::new(wallets);
+        for (id, address) in pool.addresses().into_iter().enumerate() {
+            let account = pool.get_mut(&address).unwrap();
+            account.wallet.set_account_id(AccountId(id as u32 + 1));
+            account
+                .state
+                .balances
+                .insert("RBTC".to_string(), BigUint::from(1_000_000u32));
+        }
+        pool
+    }
+
+    /// Tests that transfers are generated from the pool, signed and submitted to the rollup.
+    #[tokio::test]
+    async fn test_rollup_pipeline_submission() {
+        let pool = funded_pool().await;
+        let mut config = Config::default();
+        config.transaction.mix = [(TransactionKind::Transfer, 1)].into();
+        let provider = Arc::new(MockProvider::new());
+        let pipeline =
+            RollupPipeline::new(provider.clone(), pool, &config.network, &config.transaction)
+                .await
+                .unwrap();
+        pipeline.seed(&RngStreams::new(&config.rng));
+
+        let tx = pipeline.prepare().await.unwrap();
+        assert_eq!(RollupPipeline::<MockProvider>::tx_type(&tx), "transfer");
+        assert_eq!(
+            RollupPipeline::<MockProvider>::tx_fee(&tx),
+            Some(BigUint::from(1_000u32))
+        );
+        assert!(RollupPipeline::<MockProvider>::tx_payload(&tx).is_some());
+        let from = tx.transaction.from();
+        let tx_hash = pipeline.submit(tx).await.unwrap();
+        assert_eq!(provider.submitted()[0].0, tx_hash);
+        // The accepted transfer is listed once in the sender's history.
+        let submitted = [(from, vec![tx_hash])].into();
+        let check = check_accounts(&*provider, &submitted).await.unwrap();
+        assert_eq!(check.checked, 1);
+        assert!(check.is_consistent());
+        pipeline.wait_confirmed(tx_hash, "transfer").await.unwrap();
+        assert_eq!(
+            pipeline.last_verified_block().await.unwrap(),
+            BlockNumber(1)
+        );
+
+        // Without a Rootstock node deposits are not generated.
+        let mut config = Config::default();
+        config.transaction.mix = [(TransactionKind::Deposit, 1)].into();
+        let pool = AccountPool::new(Vec::new());
+        let pipeline = RollupPipeline::new(provider, pool, &config.network, &config.transaction)
+            .await
+            .unwrap();
+        assert!(pipeline.prepare().await.is_none());
+    }
+
+    /// Tests that the closed-loop wait for a confirmation is bounded by the timeout of its type.
+    #[tokio::test]
+    async fn test_wait_confirmed_type_timeout() {
+        let mut config = Config::default();
+        config.transaction.mix = [(TransactionKind::Transfer, 1)].into();
+        config.network.confirmation.poll_interval_ms = 1;
+        config.network.confirmation.timeout_secs = 0;
+        config.network.confirmation.sla.insert(
+            "transfer".to_string(),
+            ConfirmationSla {
+                timeout_secs: Some(60),
+                ..Default::default()
+            },
+        );
+        let provider = Arc::new(MockProvider::new());
+        let pipeline = RollupPipeline::new(
+            provider.clone(),
+            funded_pool().await,
+            &config.network,
+            &config.transaction,
+        )
+        .await
+        .unwrap();
+        pipeline.seed(&RngStreams::new(&config.rng));
+
+        let tx = pipeline.prepare().await.unwrap();
+        let tx_hash = pipeline.submit(tx).await.unwrap();
+        // Still pending at the first poll, past the global timeout.
+        let pending =
+            json!({ "executed": false, "success": null, "failReason": null, "block": null });
+        provider.script(ProviderMethod::TxInfo, Ok(pending.clone()));
+        pipeline.wait_confirmed(tx_hash, "transfer").await.unwrap();
+
+        provider.script(ProviderMethod::TxInfo, Ok(pending));
+        assert!(matches!(
+            pipeline.wait_confirmed(tx_hash, "withdraw").await,
+            Err(ClientError::OperationTimeout)
+        ));
+    }
+
+    /// Tests that the transactions the engine submits are tracked until they are verified.
+    #[tokio::test]
+    async fn test_rollup_pipeline_confirmation() {
+        let mut config = Config::default();
+        config.transaction.mix = [(TransactionKind::Transfer, 1)].into();
+        config.general.tps = 5;
+        config.general.duration_secs = Some(1);
+        config.network.confirmation.poll_interval_ms = 1;
+        let provider = Arc::new(MockProvider::new());
+        let pipeline = RollupPipeline::new(
+            provider.clone(),
+            funded_pool().await,
+            &config.network,
+            &config.transaction,
+        )
+        .await
+        .unwrap();
+        let recorder = Arc::new(RunRecorder::new());
+        let metrics = Arc::new(Metrics::new());
+        let tracker = Arc::new(ConfirmationTracker::new(
+            provider.clone(),
+            config.network.confirmation.clone(),
+            recorder.clone(),
+            metrics.clone(),
+        ));
+        let engine = Engine::with_throttler(
+            Arc::new(pipeline),
+            &config.general,
+            Throttler::with_clock(config.general.tps, SimulatedClock::new()),
+            recorder.clone(),
+            metrics.clone(),
+        )
+        .with_observer(tracker.clone());
+
+        let summary = engine.run().await;
+        assert_eq!(summary.submitted, 5);
+        assert_eq!(tracker.pending(), 5);
+        tracker.run_until_settled().await;
+
+        let snapshot = recorder.snapshot(&metrics, Vec::new());
+        assert_eq!(snapshot.records.len(), 5);
+        assert!(snapshot
+            .records
+            .iter()
+            .all(|record| record.status == TxStatus::Verified));
+    }
+
+    /// Tests that the signing key of a `ChangePubKey` is only set once it was committed.
+    #[tokio::test]
+    async fn test_change_pubkey_confirmation() {
+        let mut config = Config::default();
+        config.transaction.mix = [(TransactionKind::ChangePubKey, 1)].into();
+        config.network.confirmation.poll_interval_ms = 1;
+        let provider = Arc::new(MockProvider::new());
+        let pipeline = Arc::new(
+            RollupPipeline::new(
+                provider.clone(),
+                funded_pool().await,
+                &config.network,
+                &config.transaction,
+            )
+            .await
+            .unwrap(),
+        );
+        pipeline.seed(&RngStreams::new(&config.rng));
+        let tracker = ConfirmationTracker::new(
+            provider,
+            config.network.confirmation.clone(),
+            Arc::new(RunRecorder::new()),
+            Arc::new(Metrics::new()),
+        )
+        .with_listener(pipeline.clone());
+
+        let tx = pipeline.prepare().await.unwrap();
+        let sender = RollupPipeline::<MockProvider>::tx_account(&tx).unwrap();
+        let tx_hash = pipeline.submit(tx).await.unwrap();
+        let pool = pipeline.pool.lock().await;
+        assert!(!pool.get(&sender).unwrap().state.signing_key_set);
+        drop(pool);
+        tracker.track(TrackedOp::Tx(tx_hash), "change_pubkey", Instant::now());
+        tracker.run_until_settled().await;
+        let pool = pipeline.pool.lock().await;
+        assert!(pool.get(&sender).unwrap().state.signing_key_set);
+    }
+
+    /// Tests that each phase sends its own mix and returns only its own records.
+    #[tokio::test]
+    async fn test_mixed_pool() {
+        let config = Config::default();
+        let pipeline = RollupPipeline::new(
+            Arc::new(MockProvider::new()),
+            funded_pool().await,
+            &config.network,
+            &config.transaction,
+        )
+        .await
+        .unwrap();
+        pipeline.seed(&RngStreams::new(&config.rng));
+        let recorder = Arc::new(RunRecorder::new());
+        let mut workload = MixedPool::new(
+            Arc::new(pipeline),
+            &config.general,
+            recorder.clone(),
+            Arc::new(Metrics::new()),
+        );
+
+        let duration = Duration::from_millis(500);
+        let transfers = [(TransactionKind::Transfer, 1)].into();
+        let first = workload.run(10, &transfers, duration).await.unwrap();
+        let mints = [(TransactionKind::MintNFT, 1)].into();
+        let second = workload.run(10, &mints, duration).await.unwrap();
+
+        assert!(!first.is_empty() && !second.is_empty());
+        assert!(first.iter().all(|record| record.tx_type == "transfer"));
+        assert!(second.iter().all(|record| record.tx_type == "mint_nft"));
+        assert_eq!(recorder.records().len(), first.len() + second.len());
+        assert!(workload.run(10, &BTreeMap::new(), duration).await.is_err());
+    }
+
+    /// Tests that merchant payments are single transfers, payouts one batch of
+    /// withdrawals with consecutive nonces, rolled back together when the batch is refused.
+    #[tokio::test]
+    async fn test_merchant_pool() {
+        let pool = funded_pool().await;
+        let [payer, merchant] = [pool.addresses()[0], pool.addresses()[1]];
+        let config = Config::default();
+        let provider = Arc::new(MockProvider::new());
+        let pipeline =
+            RollupPipeline::new(provider.clone(), pool, &config.network, &config.transaction)
+                .await
+                .unwrap();
+        let accounts = MerchantPool::new(&pipeline, 1);
+
+        accounts.pay(0, 0, 5_000).await.unwrap();
+        accounts.pay_out(0, &[2_000, 1_000]).await.unwrap();
+        assert!(accounts.pay(0, 1, 5_000).await.is_err());
+
+        let submitted = provider.submitted();
+        assert_eq!(submitted.len(), 3);
+        assert!(matches!(&submitted[0].1, ZkSyncTx::Transfer(transfer) if transfer.to == merchant));
+        for (index, (_, tx)) in submitted[1..].iter().enumerate() {
+            let ZkSyncTx::Withdraw(withdraw) = tx else {
+                panic!("expected a withdrawal");
+            };
+            assert_eq!(
+                (withdraw.from, withdraw.nonce),
+                (merchant, Nonce(index as u32))
+            );
+        }
+        let pool = pipeline.pool.lock().await;
+        assert_eq!(pool.nonce(&payer), Some(Nonce(1)));
+        assert_eq!(
+            pool.get(&merchant).unwrap().state.balances["RBTC"],
+            BigUint::from(1_000_000u32 + 5_000 - 3_000 - 2_000)
+        );
+        drop(pool);
+
+        // A refused payout takes the merchant back to the first nonce of the batch.
+        provider.script(ProviderMethod::SendTxsBatch, Err(ClientError::Other));
+        assert!(accounts.pay_out(0, &[1_000, 1_000]).await.is_err());
+        assert_eq!(pipeline.pool.lock().await.nonce(&merchant), Some(Nonce(2)));
+        let batch_nonces = pipeline.batch_nonces.lock().unwrap();
+        assert_eq!(batch_nonces.next_nonce(&merchant), Some(Nonce(2)));
+    }
+
+    /// Tests that planned transfers go out fee-free in the sponsor's batches, the sponsor
+    /// being taken out of the pool and charged for them.
+    #[tokio::test]
+    async fn test_sponsored_planned_transfers() {
+        let pool = funded_pool().await;
+        let [payer, sponsor] = [pool.addresses()[0], pool.addresses()[1]];
+        let config = Config::default();
+        let provider = Arc::new(MockProvider::new());
+        let sponsor_config = SponsorConfig {
+            batch_size: 2,
+            ..SponsorConfig::default()
+        };
+        let pipeline =
+            RollupPipeline::new(provider.clone(), pool, &config.network, &config.transaction)
+                .await
+                .unwrap()
+                .with_sponsor(sponsor_config);
+        let recorder = Arc::new(RunRecorder::new());
+        pipeline.report_to(recorder.clone());
+        assert_eq!(pipeline.pool.lock().await.len(), 1);
+
+        let recipient = Address::from_low_u64_be(9);
+        let planned = (1..=3u32)
+            .map(|amount| (TransactionKind::Transfer, recipient, BigUint::from(amount)))
+            .collect();
+        let tx_hashes = pipeline.send_planned(payer, planned).await.unwrap();
+        assert_eq!(tx_hashes.len(), 3);
+
+        // Two batches: two transfers and one, each followed by the sponsor's fee transfer.
+        let transfers: Vec<_> = provider
+            .submitted()
+            .into_iter()
+            .map(|(_, tx)| match tx {
+                ZkSyncTx::Transfer(transfer) => (transfer.from, transfer.fee, transfer.nonce),
+                _ => panic!("expected transfers"),
+            })
+            .collect();
+        assert_eq!(transfers.len(), 5);
+        assert_eq!(transfers[0], (payer, BigUint::default(), Nonce(0)));
+        assert_eq!(transfers[3], (payer, BigUint::default(), Nonce(2)));
+        assert_eq!((transfers[2].0, transfers[2].2), (sponsor, Nonce(0)));
+        assert_eq!((transfers[4].0, transfers[4].2), (sponsor, Nonce(1)));
+        assert!(transfers[4].1 > BigUint::default());
+
+        let summary = recorder.with_sponsor(|ledger| ledger.summary());
+        assert_eq!(summary.sponsor, Some(sponsor));
+        assert_eq!((summary.batches, summary.sponsored_txs), (2, 3));
+        let pool = pipeline.pool.lock().await;
+        assert_eq!(pool.nonce(&payer), Some(Nonce(3)));
+        assert_eq!(
+            pool.get(&payer).unwrap().state.balances["RBTC"],
+            BigUint::from(1_000_000u32 - 6)
+        );
+    }
+}
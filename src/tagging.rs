@@ -0,0 +1,169 @@
+use ethers::types::{Bytes, H256};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+/// Marker preceding the run identifier wherever simulator traffic is tagged.
+pub const TAG_MAGIC: &[u8; 4] = b"rsim";
+
+/// HTTP header carrying the run identifier on every rollup API request.
+pub const RUN_ID_HEADER: &str = "x-simulator-run-id";
+
+/// Tags simulator-originated traffic with the run identifier, using only fields
+/// the protocol accepts arbitrary data in, so operators can filter it from organic traffic:
+///
+/// - every rollup API request carries the `x-simulator-run-id` header,
+/// - L1 deposit calldata gets `rsim` + run id appended after the ABI encoded arguments,
+///   which the contract ignores,
+/// - `MintNFT` content hashes start with `rsim` followed by 8 bytes of the run id hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunTag {
+    run_id: String,
+}
+
+impl RunTag {
+    pub fn new(run_id: &str) -> Self {
+        Self {
+            run_id: run_id.to_string(),
+        }
+    }
+
+    /// Creates a tag with a random run identifier.
+    pub fn generate() -> Self {
+        let id: [u8; 8] = rand::thread_rng().gen();
+        Self::new(&hex::encode(id))
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Short fingerprint of the run identifier fitting in fixed size fields.
+    pub fn fingerprint(&self) -> [u8; 8] {
+        let digest = Sha256::digest(self.run_id.as_bytes());
+        let mut fingerprint = [0u8; 8];
+        fingerprint.copy_from_slice(&digest[..8]);
+        fingerprint
+    }
+
+    /// Appends the tag to ABI encoded calldata.
+    pub fn tag_calldata(&self, calldata: &Bytes) -> Bytes {
+        let mut tagged = calldata.to_vec();
+        tagged.extend_from_slice(TAG_MAGIC);
+        tagged.extend_from_slice(self.run_id.as_bytes());
+        tagged.into()
+    }
+
+    /// Content hash for a minted NFT, recognizable by its prefix and unique per call.
+    pub fn nft_content_hash<R: Rng>(&self, rng: &mut R) -> H256 {
+        let mut hash = [0u8; 32];
+        hash[..4].copy_from_slice(TAG_MAGIC);
+        hash[4..12].copy_from_slice(&self.fingerprint());
+        rng.fill(&mut hash[12..]);
+        H256::from(hash)
+    }
+
+    /// Whether the content hash was produced by this run.
+    pub fn is_own_content_hash(&self, hash: &H256) -> bool {
+        hash.as_bytes()[..4] == TAG_MAGIC[..] && hash.as_bytes()[4..12] == self.fingerprint()
+    }
+}
+
+/// Extracts the run identifier from calldata tagged with `RunTag::tag_calldata`.
+///
+/// `abi_len` is the length of the untagged calldata (selector plus encoded arguments).
+pub fn parse_calldata_tag(calldata: &[u8], abi_len: usize) -> Option<String> {
+    let tag = calldata.get(abi_len..)?.strip_prefix(&TAG_MAGIC[..])?;
+    String::from_utf8(tag.to_vec()).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+
+    use ethers::types::Address;
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Request, Response, Server};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::config::Config;
+    use crate::l1::deposit::deposit_rbtc_calldata;
+    use crate::metrics::Metrics;
+    use crate::rollup::http::HttpProvider;
+    use crate::rollup::provider::Provider;
+    use crate::rollup::types::{Nonce, TokenId};
+    use crate::transaction::{AddressDenylist, Transaction};
+
+    /// Answers `contract_address` with the run id header of the request as main contract.
+    async fn echo_run_id(request: Request<Body>) -> Result<Response<Body>, Infallible> {
+        let run_id = request
+            .headers()
+            .get(RUN_ID_HEADER)
+            .map(|value| value.to_str().unwrap().to_string());
+        let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+        let request: Value = serde_json::from_slice(&body).unwrap();
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "result": { "mainContract": run_id.unwrap_or_default(), "govContract": "0x02" }
+        });
+        Ok(Response::new(Body::from(response.to_string())))
+    }
+
+    /// Tests that API requests, deposit calldata and minted NFTs carry the run tag.
+    #[tokio::test]
+    async fn test_run_tag_marks_traffic() {
+        let tag = RunTag::new("run-42");
+
+        let service = make_service_fn(|_| async { Ok::<_, Infallible>(service_fn(echo_run_id)) });
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(service);
+        let address = server.local_addr();
+        tokio::spawn(server);
+        let mut config = Config::default().network;
+        config.rollup_url = Some(format!("http://{address}/"));
+        let untagged = HttpProvider::new(&config, Arc::new(Metrics::new()));
+        let tagged = HttpProvider::new(&config, Arc::new(Metrics::new())).with_run_tag(&tag);
+        let contract = untagged.contract_address().await.unwrap();
+        assert_eq!(contract.main_contract, "");
+        let contract = tagged.contract_address().await.unwrap();
+        assert_eq!(contract.main_contract, "run-42");
+
+        let calldata = deposit_rbtc_calldata(Address::repeat_byte(1));
+        let tagged_calldata = tag.tag_calldata(&calldata);
+        assert_eq!(&tagged_calldata[..calldata.len()], &calldata[..]);
+        assert_eq!(
+            parse_calldata_tag(&tagged_calldata, calldata.len()).as_deref(),
+            Some("run-42")
+        );
+        assert_eq!(parse_calldata_tag(&calldata, calldata.len()), None);
+
+        let denylist = AddressDenylist::new(&Config::default().transaction);
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut mint = |tag| {
+            let from = Address::repeat_byte(1);
+            let fee = 1u32.into();
+            match Transaction::generate_mint_nft(
+                &mut rng,
+                &denylist,
+                from,
+                &[],
+                TokenId(0),
+                fee,
+                Nonce(0),
+                tag,
+            ) {
+                Transaction::MintNFT { content_hash, .. } => content_hash,
+                _ => unreachable!(),
+            }
+        };
+        let (first, second) = (mint(Some(&tag)), mint(Some(&tag)));
+        assert!(tag.is_own_content_hash(&first) && tag.is_own_content_hash(&second));
+        assert_ne!(first, second);
+        assert!(!RunTag::new("other").is_own_content_hash(&first));
+        assert!(!tag.is_own_content_hash(&mint(None)));
+    }
+}
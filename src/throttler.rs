@@ -1,25 +1,204 @@
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
-pub struct Throttler {
+use crate::clock::{Clock, SystemClock};
+use crate::load_profile::{ConstantLoad, LoadProfile};
+use crate::rate_control::{AdaptiveRate, AdaptiveRateConfig};
+
+/// Step the schedule moves by while the profile asks for no transactions at all.
+const IDLE_STEP: Duration = Duration::from_millis(10);
+
+/// Paces transactions along a load profile, a constant rate unless configured otherwise.
+///
+/// With an adaptive rate the profile is scaled down while the node shows backpressure.
+pub struct Throttler<C: Clock = SystemClock> {
+    clock: C,
     start_time: Instant,
-    transaction_interval: Duration,
+    profile: Box<dyn LoadProfile>,
+    adaptive: Option<Arc<AdaptiveRate>>,
+    /// Offset from the start at which the last transaction was due.
+    last_due: Mutex<Duration>,
 }
 
 impl Throttler {
     pub fn new(tps: u32) -> Self {
-        let transaction_interval = Duration::from_secs_f64(1.0 / tps as f64);
+        Self::with_clock(tps, SystemClock)
+    }
+
+    pub fn with_profile(profile: Box<dyn LoadProfile>) -> Self {
+        Self::with_profile_and_clock(profile, SystemClock)
+    }
+}
+
+impl<C: Clock> Throttler<C> {
+    pub fn with_clock(tps: u32, clock: C) -> Self {
+        let profile = ConstantLoad {
+            tps: tps.max(1) as f64,
+        };
+        Self::with_profile_and_clock(Box::new(profile), clock)
+    }
+
+    pub fn with_profile_and_clock(profile: Box<dyn LoadProfile>, clock: C) -> Self {
         Throttler {
-            start_time: Instant::now(),
-            transaction_interval,
+            start_time: clock.now(),
+            clock,
+            profile,
+            adaptive: None,
+            last_due: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    /// Scales the profile with an AIMD controller fed with the responses of the node.
+    pub fn with_adaptive_rate(mut self, config: AdaptiveRateConfig) -> Self {
+        self.adaptive = Some(Arc::new(AdaptiveRate::new(config)));
+        self
+    }
+
+    /// Controller the responses of the node are reported to, `None` at a fixed rate.
+    pub fn adaptive_rate(&self) -> Option<Arc<AdaptiveRate>> {
+        self.adaptive.clone()
+    }
+
+    /// Offset from the start at which the transaction after one due at `last_due` is due,
+    /// sent at `factor` of the profile rate.
+    ///
+    /// Each interval follows the rate at the moment the previous transaction was due, which
+    /// is accurate as long as the rate changes slowly compared to the interval.
+    fn next_due(&self, last_due: Duration, factor: f64) -> Duration {
+        let mut due = last_due;
+        loop {
+            let tps = self.profile.tps_at(due) * factor;
+            if tps > 0.0 {
+                return due + Duration::from_secs_f64(1.0 / tps);
+            }
+            due += IDLE_STEP;
+        }
+    }
+
+    /// Moment the schedule started, transactions are due at offsets from it.
+    pub fn started_at(&self) -> Instant {
+        self.start_time
+    }
+
+    /// Time passed since the throttler was created.
+    pub fn elapsed(&self) -> Duration {
+        self.clock.elapsed_since(self.start_time)
+    }
+
+    /// Whether a run limited to `duration` is over.
+    pub fn is_finished(&self, duration: Option<Duration>) -> bool {
+        duration.is_some_and(|duration| self.elapsed() >= duration)
+    }
+
+    /// Waits until the next transaction is due according to the schedule and returns the moment it was due.
+    ///
+    /// The moment is used as the intended start of the submission when reporting latency.
+    pub async fn throttle(&self) -> Instant {
+        let due = {
+            let mut last_due = self.last_due.lock().unwrap();
+            let factor = self
+                .adaptive
+                .as_ref()
+                .map_or(1.0, |adaptive| adaptive.on_send(self.elapsed()));
+            *last_due = self.next_due(*last_due, factor);
+            *last_due
+        };
+        let scheduled = self.start_time + due;
+
+        let remaining_time = scheduled.saturating_duration_since(self.clock.now());
+        if !remaining_time.is_zero() {
+            self.clock.sleep(remaining_time).await;
+        }
+        scheduled
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::clock::SimulatedClock;
+    use crate::load_profile::{BurstConfig, LoadShape, WithBursts};
+
+    /// Tests that transactions are paced at the configured rate and a slow sender is not delayed further.
+    #[tokio::test]
+    async fn test_throttle_on_simulated_clock() {
+        let clock = SimulatedClock::new();
+        let throttler = Throttler::with_clock(10, clock.clone());
+        let duration = Some(Duration::from_secs(2));
+
+        let mut sent = 0;
+        while !throttler.is_finished(duration) {
+            sent += 1;
+            throttler.throttle().await;
         }
+        assert_eq!(sent, 20);
+        assert_eq!(clock.elapsed(), Duration::from_secs(2));
+
+        // Sending took longer than the interval, the next one is already due.
+        clock.advance(Duration::from_millis(350));
+        throttler.throttle().await;
+        assert_eq!(clock.elapsed(), Duration::from_millis(2350));
+        throttler.throttle().await;
+        throttler.throttle().await;
+        throttler.throttle().await;
+        assert_eq!(clock.elapsed(), Duration::from_millis(2400));
     }
 
-    pub fn throttle(&self) {
-        let elapsed_time = self.start_time.elapsed();
+    /// Tests that a ramp and a burst profile send the expected number of transactions per phase.
+    #[tokio::test]
+    async fn test_load_profiles() {
+        let sent_within = |profile: Box<dyn LoadProfile>, secs: u64| async move {
+            let clock = SimulatedClock::new();
+            let throttler = Throttler::with_profile_and_clock(profile, clock.clone());
+            let duration = Duration::from_secs(secs);
+            let mut sent = 0;
+            while !throttler.is_finished(Some(duration)) {
+                throttler.throttle().await;
+                // The last one may be due after a pause reaching past the end.
+                if clock.elapsed() <= duration {
+                    sent += 1;
+                }
+            }
+            sent
+        };
+
+        // 10 to 30 TPS over 10s averages 20 TPS, then 30 TPS.
+        let ramp = LoadShape::Ramp {
+            start_tps: 10,
+            ramp_secs: 10,
+        };
+        let ramped = sent_within(ramp.profile(30), 10).await;
+        assert!((195..=205).contains(&ramped), "{ramped}");
+        let ramped = sent_within(ramp.profile(30), 12).await;
+        assert!((255..=265).contains(&ramped), "{ramped}");
+
+        // 2s at 50 TPS every 10s, nothing in between.
+        let burst = LoadShape::Burst {
+            base_tps: 0,
+            period_secs: 10,
+            burst_secs: 2,
+        };
+        assert_eq!(sent_within(burst.profile(50), 10).await, 100);
+        assert_eq!(sent_within(burst.profile(50), 21).await, 250);
+
+        // 50 more within 1s every 5s on top of 10 TPS.
+        let bursts = WithBursts {
+            base: Box::new(ConstantLoad { tps: 10.0 }),
+            bursts: BurstConfig {
+                txs: 50,
+                within_ms: 1_000,
+                every_secs: 5,
+            },
+        };
+        let bursty = sent_within(Box::new(bursts), 9).await;
+        assert!((139..=141).contains(&bursty), "{bursty}");
 
-        if elapsed_time < self.transaction_interval {
-            let remaining_time = self.transaction_interval - elapsed_time;
-            std::thread::sleep(remaining_time);
+        let sine = LoadShape::Sine {
+            min_tps: 0,
+            period_secs: 10,
         }
+        .profile(20);
+        assert_eq!(sine.tps_at(Duration::ZERO), 0.0);
+        assert!((sine.tps_at(Duration::from_secs(5)) - 20.0).abs() < 1e-9);
     }
 }
@@ -0,0 +1,289 @@
+//! Certificate pinning of the HTTPS endpoints the simulator talks to.
+//!
+//! Pins are set per host name with `tls_pins` in the `[network]` section. A connection to a
+//! pinned host is refused unless one certificate of the chain it presents, or its public key,
+//! matches one of the pins, on top of the usual validation against the web PKI roots. Hosts
+//! without pins are only validated against the roots.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{Certificate, ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+/// Pins of every pinned host, keyed by host name.
+pub type TlsPins = BTreeMap<String, Vec<TlsPin>>;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TlsPinError {
+    #[error("Malformed TLS pin `{0}`, expected `cert-sha256:<hex>` or `spki-sha256:<hex>`")]
+    Malformed(String),
+    #[error("Certificate of {0} matches none of its pins")]
+    Mismatch(String),
+}
+
+/// SHA-256 digest a certificate presented by a pinned host must match.
+///
+/// Written as `cert-sha256:<hex>` for the digest of a whole DER certificate, or
+/// `spki-sha256:<hex>` for the digest of its DER `SubjectPublicKeyInfo`, which survives
+/// renewals of the certificate keeping the key, e.g.
+/// `openssl x509 -pubkey -noout | openssl pkey -pubin -outform der | sha256sum`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum TlsPin {
+    Certificate([u8; 32]),
+    PublicKey([u8; 32]),
+}
+
+impl TlsPin {
+    /// Whether the DER encoded certificate matches the pin.
+    pub fn matches(&self, certificate: &[u8]) -> bool {
+        match self {
+            TlsPin::Certificate(digest) => Sha256::digest(certificate)[..] == digest[..],
+            TlsPin::PublicKey(digest) => subject_public_key_info(certificate)
+                .is_some_and(|spki| Sha256::digest(spki)[..] == digest[..]),
+        }
+    }
+}
+
+impl FromStr for TlsPin {
+    type Err = TlsPinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let malformed = || TlsPinError::Malformed(s.to_string());
+        let (kind, digest) = s.split_once(':').ok_or_else(malformed)?;
+        let digest: [u8; 32] = hex::decode(digest)
+            .ok()
+            .and_then(|digest| digest.try_into().ok())
+            .ok_or_else(malformed)?;
+        match kind {
+            "cert-sha256" => Ok(TlsPin::Certificate(digest)),
+            "spki-sha256" => Ok(TlsPin::PublicKey(digest)),
+            _ => Err(malformed()),
+        }
+    }
+}
+
+impl TryFrom<String> for TlsPin {
+    type Error = TlsPinError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for TlsPin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TlsPin::Certificate(digest) => write!(f, "cert-sha256:{}", hex::encode(digest)),
+            TlsPin::PublicKey(digest) => write!(f, "spki-sha256:{}", hex::encode(digest)),
+        }
+    }
+}
+
+/// Splits the DER element at the start of `input` into its tag, its content and the rest.
+fn der_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, input) = input.split_first()?;
+    let (&first, mut input) = input.split_first()?;
+    let len = if first < 0x80 {
+        first as usize
+    } else {
+        let octets = (first & 0x7f) as usize;
+        if octets == 0 || octets > 4 || input.len() < octets {
+            return None;
+        }
+        let (len, rest) = input.split_at(octets);
+        input = rest;
+        len.iter()
+            .fold(0, |len, octet| (len << 8) | *octet as usize)
+    };
+    (input.len() >= len).then(|| (tag, &input[..len], &input[len..]))
+}
+
+/// DER encoded `SubjectPublicKeyInfo` of an X.509 certificate, `None` when it is malformed.
+fn subject_public_key_info(certificate: &[u8]) -> Option<&[u8]> {
+    const SEQUENCE: u8 = 0x30;
+    const EXPLICIT_VERSION: u8 = 0xa0;
+
+    let (SEQUENCE, certificate, _) = der_element(certificate)? else {
+        return None;
+    };
+    let (SEQUENCE, mut fields, _) = der_element(certificate)? else {
+        return None;
+    };
+    if fields.first() == Some(&EXPLICIT_VERSION) {
+        fields = der_element(fields)?.2;
+    }
+    // Serial number, signature algorithm, issuer, validity and subject come first.
+    for _ in 0..5 {
+        fields = der_element(fields)?.2;
+    }
+    let (tag, _, rest) = der_element(fields)?;
+    (tag == SEQUENCE).then(|| &fields[..fields.len() - rest.len()])
+}
+
+/// Checks the chain presented by `host` against its pins, any host without pins passes.
+pub fn check_pins<'a>(
+    pins: &TlsPins,
+    host: &str,
+    mut chain: impl Iterator<Item = &'a [u8]>,
+) -> Result<(), TlsPinError> {
+    let Some((_, host_pins)) = pins
+        .iter()
+        .find(|(pinned, _)| pinned.eq_ignore_ascii_case(host))
+    else {
+        return Ok(());
+    };
+    if chain.any(|certificate| host_pins.iter().any(|pin| pin.matches(certificate))) {
+        Ok(())
+    } else {
+        Err(TlsPinError::Mismatch(host.to_string()))
+    }
+}
+
+/// Validates certificates against the web PKI roots, then against the pins of the host.
+struct PinnedVerifier {
+    roots: WebPkiVerifier,
+    pins: TlsPins,
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let verified = self.roots.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            scts,
+            ocsp_response,
+            now,
+        )?;
+        let host = match server_name {
+            ServerName::DnsName(name) => name.as_ref().to_string(),
+            ServerName::IpAddress(address) => address.to_string(),
+            _ => return Ok(verified),
+        };
+        let chain = std::iter::once(end_entity)
+            .chain(intermediates)
+            .map(|certificate| certificate.0.as_slice());
+        check_pins(&self.pins, &host, chain)
+            .map_err(|err| rustls::Error::General(err.to_string()))?;
+        Ok(verified)
+    }
+}
+
+/// TLS configuration of the HTTPS clients, enforcing `pins`.
+pub fn client_config(pins: &TlsPins) -> ClientConfig {
+    let mut roots = RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+        OwnedTrustAnchor::from_subject_spki_name_constraints(
+            anchor.subject,
+            anchor.spki,
+            anchor.name_constraints,
+        )
+    }));
+    let verifier = PinnedVerifier {
+        roots: WebPkiVerifier::new(roots, None),
+        pins: pins.clone(),
+    };
+    ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+        if content.len() < 0x80 {
+            out.push(content.len() as u8);
+        } else {
+            out.extend([0x82, (content.len() >> 8) as u8, content.len() as u8]);
+        }
+        out.extend_from_slice(content);
+        out
+    }
+
+    /// Certificate with the structure of an X.509 one, its fields filled with placeholders.
+    fn certificate(spki: &[u8]) -> Vec<u8> {
+        let name = der(0x30, &der(0x31, &[0xaa; 150]));
+        let tbs = [
+            der(0xa0, &der(0x02, &[2])),
+            der(0x02, &[0x01, 0x23]),
+            der(0x30, &der(0x06, &[1, 2, 3])),
+            name.clone(),
+            der(0x30, &[0x17; 26]),
+            name,
+            spki.to_vec(),
+        ]
+        .concat();
+        let signature = [der(0x30, &der(0x06, &[1, 2, 3])), der(0x03, &[0; 64])].concat();
+        der(0x30, &[der(0x30, &tbs), signature].concat())
+    }
+
+    /// Tests pin parsing, the extraction of the public key and the check of the chain.
+    #[test]
+    fn test_tls_pins() {
+        let spki = der(
+            0x30,
+            &[der(0x30, &[0x06, 0x01, 0x2a]), der(0x03, &[7; 65])].concat(),
+        );
+        let leaf = certificate(&spki);
+        let intermediate = certificate(&der(0x30, &der(0x03, &[9; 65])));
+        assert_eq!(subject_public_key_info(&leaf), Some(spki.as_slice()));
+        assert_eq!(subject_public_key_info(&leaf[..40]), None);
+
+        let key_pin: TlsPin = format!("spki-sha256:{}", hex::encode(Sha256::digest(&spki)))
+            .parse()
+            .unwrap();
+        let cert_pin = TlsPin::Certificate(Sha256::digest(&intermediate).into());
+        assert_eq!(cert_pin.to_string().parse::<TlsPin>(), Ok(cert_pin));
+        assert!(key_pin.matches(&leaf) && !key_pin.matches(&intermediate));
+        assert!(cert_pin.matches(&intermediate) && !cert_pin.matches(&leaf));
+        for malformed in ["sha256:00", "spki-sha256:abcd", "cert-sha256"] {
+            assert!(matches!(
+                malformed.parse::<TlsPin>(),
+                Err(TlsPinError::Malformed(_))
+            ));
+        }
+
+        let pins = TlsPins::from([
+            ("rollup.example.com".to_string(), vec![key_pin]),
+            ("hooks.example.com".to_string(), vec![cert_pin]),
+        ]);
+        let chain = [leaf.as_slice(), intermediate.as_slice()];
+        assert_eq!(
+            check_pins(&pins, "Rollup.Example.com", chain.into_iter()),
+            Ok(())
+        );
+        assert_eq!(
+            check_pins(&pins, "hooks.example.com", chain.into_iter()),
+            Ok(())
+        );
+        assert_eq!(
+            check_pins(&pins, "other.example.com", [].into_iter()),
+            Ok(())
+        );
+        assert_eq!(
+            check_pins(&pins, "hooks.example.com", chain[..1].iter().copied()),
+            Err(TlsPinError::Mismatch("hooks.example.com".to_string()))
+        );
+        client_config(&pins);
+    }
+}
@@ -1,19 +1,847 @@
+use std::collections::{BTreeMap, HashSet};
+
+use ethers::types::Address;
+use ethers::utils::to_checksum;
+use num::BigUint;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::seq::SliceRandom;
 use rand::Rng;
+use serde::Deserialize;
+
+use crate::config::TransactionConfig;
+use crate::report::nfts::NftOperation;
+use crate::rollup::provider::ClientError;
+use crate::rollup::types::{ChangePubKeyFeeType, Nonce, TokenId, TxFeeTypes, H256};
+use crate::tagging::RunTag;
+
+/// Rootstock chain ids whose EIP-1191 checksums are accepted next to EIP-55 ones.
+const RSK_CHAIN_IDS: [u8; 2] = [30, 31];
+
+/// Parses a `0x` prefixed address, verifying its checksum when it is written in mixed case.
+///
+/// Both EIP-55 checksums and the EIP-1191 checksums used by Rootstock wallets are accepted;
+/// all lower or all upper case addresses carry no checksum.
+pub fn parse_address(value: &str) -> Result<Address, ClientError> {
+    let hex = value
+        .strip_prefix("0x")
+        .ok_or(ClientError::IncorrectAddress)?;
+    if hex.len() != 40 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(ClientError::IncorrectAddress);
+    }
+    let address: Address = value.parse().map_err(|_| ClientError::IncorrectAddress)?;
+
+    let is_mixed_case =
+        hex.chars().any(|c| c.is_ascii_lowercase()) && hex.chars().any(|c| c.is_ascii_uppercase());
+    if is_mixed_case {
+        let checksum_matches = std::iter::once(None)
+            .chain(RSK_CHAIN_IDS.into_iter().map(Some))
+            .any(|chain_id| to_checksum(&address, chain_id) == value);
+        if !checksum_matches {
+            return Err(ClientError::IncorrectAddress);
+        }
+    }
+    Ok(address)
+}
+
+/// Addresses the generator must never send funds to.
+///
+/// Besides the configured denylist, the zero address and the rollup contract are never
+/// valid recipients: funds sent there are lost, and the server would reject most of
+/// these transactions only after a network round trip.
+#[derive(Debug, Clone, Default)]
+pub struct AddressDenylist {
+    addresses: HashSet<Address>,
+    contract: Option<Address>,
+}
+
+impl AddressDenylist {
+    pub fn new(config: &TransactionConfig) -> Self {
+        Self {
+            addresses: config.denylist.iter().copied().collect(),
+            contract: None,
+        }
+    }
+
+    /// Rejects the rollup contract as a recipient, as reported by `Provider::contract_address`.
+    pub fn with_contract_address(mut self, contract: Address) -> Self {
+        self.contract = Some(contract);
+        self
+    }
+
+    pub fn contains(&self, address: &Address) -> bool {
+        self.addresses.contains(address)
+    }
+
+    /// Fails if the address is used as a recipient or withdrawal target while it is the
+    /// zero address, the rollup contract or denylisted.
+    pub fn ensure_allowed(&self, address: Address) -> Result<(), ClientError> {
+        if address.is_zero() || self.contract == Some(address) {
+            return Err(ClientError::IncorrectAddress);
+        }
+        if self.contains(&address) {
+            return Err(ClientError::DeniedAddress(address));
+        }
+        Ok(())
+    }
 
-use crate::config::{Config, TransactionConfig};
+    /// Picks a random recipient among the candidates that are allowed.
+    pub fn choose_recipient<R: Rng>(&self, rng: &mut R, candidates: &[Address]) -> Option<Address> {
+        let allowed: Vec<&Address> = candidates
+            .iter()
+            .filter(|address| self.ensure_allowed(**address).is_ok())
+            .collect();
+        allowed.choose(rng).map(|address| **address)
+    }
+}
+
+/// Reason for not generating a withdrawal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WithdrawalSkip {
+    /// The fee is higher than the configured share of the withdrawn amount.
+    FeeTooHigh { fee: BigUint, amount: BigUint },
+    /// The account cannot cover the amount together with the fee.
+    InsufficientBalance { required: BigUint, balance: BigUint },
+}
+
+impl WithdrawalSkip {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WithdrawalSkip::FeeTooHigh { .. } => "fee_too_high",
+            WithdrawalSkip::InsufficientBalance { .. } => "insufficient_balance",
+        }
+    }
+}
+
+/// Checks that a withdrawal is worth generating: its fee must not exceed
+/// `max_fee_percent` of the amount and the balance must cover both.
+pub fn check_withdrawal_fee(
+    amount: &BigUint,
+    fee: &BigUint,
+    balance: &BigUint,
+    max_fee_percent: u32,
+) -> Result<(), WithdrawalSkip> {
+    if fee * 100u32 > amount * max_fee_percent {
+        return Err(WithdrawalSkip::FeeTooHigh {
+            fee: fee.clone(),
+            amount: amount.clone(),
+        });
+    }
+    let required = amount + fee;
+    if &required > balance {
+        return Err(WithdrawalSkip::InsufficientBalance {
+            required,
+            balance: balance.clone(),
+        });
+    }
+    Ok(())
+}
 
-pub struct Transaction {
+/// Kind of a rollup operation generated by the simulator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize)]
+#[serde(try_from = "String")]
+pub enum TransactionKind {
+    Deposit,
+    Transfer,
+    TransferToNew,
+    Withdraw,
+    ChangePubKey,
+    MintNFT,
+    WithdrawNFT,
+    ForcedExit,
+}
+
+impl TransactionKind {
+    pub const ALL: [Self; 8] = [
+        Self::Deposit,
+        Self::Transfer,
+        Self::TransferToNew,
+        Self::Withdraw,
+        Self::ChangePubKey,
+        Self::MintNFT,
+        Self::WithdrawNFT,
+        Self::ForcedExit,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransactionKind::Deposit => "deposit",
+            TransactionKind::Transfer => "transfer",
+            TransactionKind::TransferToNew => "transfer_to_new",
+            TransactionKind::Withdraw => "withdraw",
+            TransactionKind::ChangePubKey => "change_pubkey",
+            TransactionKind::MintNFT => "mint_nft",
+            TransactionKind::WithdrawNFT => "withdraw_nft",
+            TransactionKind::ForcedExit => "forced_exit",
+        }
+    }
+
+    /// Whether the operation is submitted on L1 rather than to the rollup server.
+    pub fn is_priority_op(&self) -> bool {
+        matches!(self, TransactionKind::Deposit)
+    }
+}
+
+impl TryFrom<String> for TransactionKind {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.name() == name)
+            .ok_or_else(|| format!("unknown transaction type `{name}`"))
+    }
+}
+
+/// Weighted choice among the operation types of the configured `mix`.
+#[derive(Debug, Clone)]
+pub struct TransactionMix {
+    kinds: Vec<TransactionKind>,
+    weights: WeightedIndex<u32>,
+}
+
+impl TransactionMix {
+    /// Fails when every weight is zero.
+    pub fn new(mix: &BTreeMap<TransactionKind, u32>) -> Result<Self, ClientError> {
+        let (kinds, weights): (Vec<TransactionKind>, Vec<u32>) =
+            mix.iter().filter(|(_, weight)| **weight > 0).unzip();
+        let weights = WeightedIndex::new(weights).map_err(|_| ClientError::IncorrectInput)?;
+        Ok(Self { kinds, weights })
+    }
+
+    /// Types with a positive weight.
+    pub fn kinds(&self) -> &[TransactionKind] {
+        &self.kinds
+    }
+
+    /// Picks the type of the next generated operation.
+    pub fn choose<R: Rng>(&self, rng: &mut R) -> TransactionKind {
+        self.kinds[self.weights.sample(rng)]
+    }
+}
+
+/// State of the sending account every generated operation type is built from.
+#[derive(Debug, Clone)]
+pub struct GenerationInput<'a> {
+    pub from: Address,
+    /// Existing accounts that may receive funds, NFTs or be forced to exit.
+    pub recipients: &'a [Address],
+    pub token: TokenId,
+    pub fee: BigUint,
+    pub nonce: Nonce,
+    /// Balance of the sender in `token`.
+    pub balance: &'a BigUint,
+    /// NFT owned by the sender, if any.
+    pub nft: Option<TokenId>,
+    /// Tag of the run when its traffic is tagged, marking the content of minted NFTs.
+    pub tag: Option<&'a RunTag>,
+}
+
+/// Rollup operation generated by the simulator, before it is signed.
+///
+/// `Deposit` is an L1 priority operation paid with L1 gas, so it carries no rollup fee or nonce.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transaction {
+    Deposit {
+        from: Address,
+        to: Address,
+        token: TokenId,
+        amount: BigUint,
+    },
+    Transfer {
+        from: Address,
+        to: Address,
+        token: TokenId,
+        amount: BigUint,
+        fee: BigUint,
+        nonce: Nonce,
+    },
+    /// Transfer to an address that has no rollup account yet, creating it.
+    TransferToNew {
+        from: Address,
+        to: Address,
+        token: TokenId,
+        amount: BigUint,
+        fee: BigUint,
+        nonce: Nonce,
+    },
+    Withdraw {
+        from: Address,
+        to: Address,
+        token: TokenId,
+        amount: BigUint,
+        fee: BigUint,
+        nonce: Nonce,
+        fast: bool,
+    },
+    ChangePubKey {
+        from: Address,
+        fee_token: TokenId,
+        fee: BigUint,
+        nonce: Nonce,
+        auth_type: ChangePubKeyFeeType,
+    },
+    MintNFT {
+        from: Address,
+        to: Address,
+        content_hash: H256,
+        fee_token: TokenId,
+        fee: BigUint,
+        nonce: Nonce,
+    },
+    WithdrawNFT {
+        from: Address,
+        to: Address,
+        token: TokenId,
+        fee_token: TokenId,
+        fee: BigUint,
+        nonce: Nonce,
+    },
+    /// Withdrawal of the whole `token` balance of `to`, initiated and paid for by `from`.
+    ForcedExit {
+        from: Address,
+        to: Address,
+        token: TokenId,
+        fee: BigUint,
+        nonce: Nonce,
+    },
 }
 
 impl Transaction {
-    pub fn generate_deposit(config: &Config) -> Self {
-        let TransactionConfig {
-            min_deposit_value, max_deposit_value, ..
-        } = config.transaction;
-        let mut rng = rand::thread_rng();
-        let amount = rng.gen_range(config.transaction.min_deposit_value..= config.transaction.max_deposit_value);
-        
-        Transaction {
+    pub fn kind(&self) -> TransactionKind {
+        match self {
+            Transaction::Deposit { .. } => TransactionKind::Deposit,
+            Transaction::Transfer { .. } => TransactionKind::Transfer,
+            Transaction::TransferToNew { .. } => TransactionKind::TransferToNew,
+            Transaction::Withdraw { .. } => TransactionKind::Withdraw,
+            Transaction::ChangePubKey { .. } => TransactionKind::ChangePubKey,
+            Transaction::MintNFT { .. } => TransactionKind::MintNFT,
+            Transaction::WithdrawNFT { .. } => TransactionKind::WithdrawNFT,
+            Transaction::ForcedExit { .. } => TransactionKind::ForcedExit,
+        }
+    }
+
+    /// Account submitting the operation and paying for it.
+    pub fn from(&self) -> Address {
+        match self {
+            Transaction::Deposit { from, .. }
+            | Transaction::Transfer { from, .. }
+            | Transaction::TransferToNew { from, .. }
+            | Transaction::Withdraw { from, .. }
+            | Transaction::ChangePubKey { from, .. }
+            | Transaction::MintNFT { from, .. }
+            | Transaction::WithdrawNFT { from, .. }
+            | Transaction::ForcedExit { from, .. } => *from,
+        }
+    }
+
+    /// Recipient of the funds, `None` for operations that only affect the sender.
+    pub fn to(&self) -> Option<Address> {
+        match self {
+            Transaction::Deposit { to, .. }
+            | Transaction::Transfer { to, .. }
+            | Transaction::TransferToNew { to, .. }
+            | Transaction::Withdraw { to, .. }
+            | Transaction::MintNFT { to, .. }
+            | Transaction::WithdrawNFT { to, .. }
+            | Transaction::ForcedExit { to, .. } => Some(*to),
+            Transaction::ChangePubKey { .. } => None,
+        }
+    }
+
+    /// Rejects transactions sent from the zero address or to a recipient the denylist
+    /// does not allow, before they are signed and submitted.
+    pub fn validate_addresses(&self, denylist: &AddressDenylist) -> Result<(), ClientError> {
+        if self.from().is_zero() {
+            return Err(ClientError::IncorrectAddress);
+        }
+        match self.to() {
+            Some(to) => denylist.ensure_allowed(to),
+            None => Ok(()),
+        }
+    }
+
+    /// Rollup fee, `None` for L1 operations.
+    pub fn fee(&self) -> Option<&BigUint> {
+        match self {
+            Transaction::Deposit { .. } => None,
+            Transaction::Transfer { fee, .. }
+            | Transaction::TransferToNew { fee, .. }
+            | Transaction::Withdraw { fee, .. }
+            | Transaction::ChangePubKey { fee, .. }
+            | Transaction::MintNFT { fee, .. }
+            | Transaction::WithdrawNFT { fee, .. }
+            | Transaction::ForcedExit { fee, .. } => Some(fee),
+        }
+    }
+
+    /// Rollup nonce of the sender, `None` for L1 operations.
+    pub fn nonce(&self) -> Option<Nonce> {
+        match self {
+            Transaction::Deposit { .. } => None,
+            Transaction::Transfer { nonce, .. }
+            | Transaction::TransferToNew { nonce, .. }
+            | Transaction::Withdraw { nonce, .. }
+            | Transaction::ChangePubKey { nonce, .. }
+            | Transaction::MintNFT { nonce, .. }
+            | Transaction::WithdrawNFT { nonce, .. }
+            | Transaction::ForcedExit { nonce, .. } => Some(*nonce),
+        }
+    }
+
+    pub fn fee_mut(&mut self) -> Option<&mut BigUint> {
+        match self {
+            Transaction::Deposit { .. } => None,
+            Transaction::Transfer { fee, .. }
+            | Transaction::TransferToNew { fee, .. }
+            | Transaction::Withdraw { fee, .. }
+            | Transaction::ChangePubKey { fee, .. }
+            | Transaction::MintNFT { fee, .. }
+            | Transaction::WithdrawNFT { fee, .. }
+            | Transaction::ForcedExit { fee, .. } => Some(fee),
+        }
+    }
+
+    pub fn nonce_mut(&mut self) -> Option<&mut Nonce> {
+        match self {
+            Transaction::Deposit { .. } => None,
+            Transaction::Transfer { nonce, .. }
+            | Transaction::TransferToNew { nonce, .. }
+            | Transaction::Withdraw { nonce, .. }
+            | Transaction::ChangePubKey { nonce, .. }
+            | Transaction::MintNFT { nonce, .. }
+            | Transaction::WithdrawNFT { nonce, .. }
+            | Transaction::ForcedExit { nonce, .. } => Some(nonce),
+        }
+    }
+
+    /// Fee type to request from the server, `None` for L1 operations.
+    ///
+    /// Forced exits are charged as withdrawals, in the exited token.
+    pub fn fee_type(&self) -> Option<TxFeeTypes> {
+        match self {
+            Transaction::Deposit { .. } => None,
+            Transaction::Transfer { .. } | Transaction::TransferToNew { .. } => {
+                Some(TxFeeTypes::Transfer)
+            }
+            Transaction::Withdraw { fast: true, .. } => Some(TxFeeTypes::FastWithdraw),
+            Transaction::Withdraw { .. } | Transaction::ForcedExit { .. } => {
+                Some(TxFeeTypes::Withdraw)
+            }
+            Transaction::ChangePubKey { auth_type, .. } => {
+                Some(TxFeeTypes::ChangePubKey(*auth_type))
+            }
+            Transaction::MintNFT { .. } => Some(TxFeeTypes::MintNFT),
+            Transaction::WithdrawNFT { .. } => Some(TxFeeTypes::WithdrawNFT),
+        }
+    }
+
+    /// NFT minted or withdrawn by the operation, to be tracked per creator.
+    pub fn nft_operation(&self) -> Option<NftOperation> {
+        match self {
+            Transaction::MintNFT {
+                from, content_hash, ..
+            } => Some(NftOperation::Mint {
+                creator: *from,
+                content_hash: *content_hash,
+            }),
+            Transaction::WithdrawNFT { token, .. } => Some(NftOperation::Withdraw(*token)),
+            _ => None,
+        }
+    }
+
+    /// Deposits a random amount between `min_deposit_value` and `max_deposit_value` to the sender's own account.
+    pub fn generate_deposit<R: Rng>(
+        rng: &mut R,
+        config: &TransactionConfig,
+        from: Address,
+        token: TokenId,
+    ) -> Self {
+        Transaction::Deposit {
+            from,
+            to: from,
+            token,
+            amount: random_amount(rng, config.min_deposit_value, config.max_deposit_value),
+        }
+    }
+
+    /// Transfers a random amount to one of the existing accounts, `None` when all of them are denylisted.
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_transfer<R: Rng>(
+        rng: &mut R,
+        config: &TransactionConfig,
+        denylist: &AddressDenylist,
+        from: Address,
+        recipients: &[Address],
+        token: TokenId,
+        fee: BigUint,
+        nonce: Nonce,
+    ) -> Option<Self> {
+        let to = denylist.choose_recipient(rng, recipients)?;
+        Some(Transaction::Transfer {
+            from,
+            to,
+            token,
+            amount: random_amount(rng, config.min_transfer_value, config.max_transfer_value),
+            fee,
+            nonce,
+        })
+    }
+
+    /// Transfers a random amount to a freshly generated address.
+    pub fn generate_transfer_to_new<R: Rng>(
+        rng: &mut R,
+        config: &TransactionConfig,
+        from: Address,
+        token: TokenId,
+        fee: BigUint,
+        nonce: Nonce,
+    ) -> Self {
+        Transaction::TransferToNew {
+            from,
+            to: Address::from(rng.gen::<[u8; 20]>()),
+            token,
+            amount: random_amount(
+                rng,
+                config.min_transfer_to_new_value,
+                config.max_transfer_to_new_value,
+            ),
+            fee,
+            nonce,
+        }
+    }
+
+    /// Withdraws a random amount to the sender's L1 address, unless the fee makes it not worth it.
+    pub fn generate_withdraw<R: Rng>(
+        rng: &mut R,
+        config: &TransactionConfig,
+        from: Address,
+        token: TokenId,
+        fee: BigUint,
+        nonce: Nonce,
+        balance: &BigUint,
+    ) -> Result<Self, WithdrawalSkip> {
+        let amount = random_amount(rng, config.min_withdraw_value, config.max_withdraw_value);
+        check_withdrawal_fee(&amount, &fee, balance, config.max_withdrawal_fee_percent)?;
+        Ok(Transaction::Withdraw {
+            from,
+            to: from,
+            token,
+            amount,
+            fee,
+            nonce,
+            fast: rng.gen_ratio(config.fast_withdraw_percent.min(100), 100),
+        })
+    }
+
+    /// Sets the sender's signing key with an ECDSA authorization, the only one that needs no
+    /// prior L1 transaction; `Onchain` authorizations are left to account activation.
+    pub fn generate_change_pubkey(
+        from: Address,
+        fee_token: TokenId,
+        fee: BigUint,
+        nonce: Nonce,
+    ) -> Self {
+        Transaction::ChangePubKey {
+            from,
+            fee_token,
+            fee,
+            nonce,
+            auth_type: ChangePubKeyFeeType::ECDSA,
+        }
+    }
+
+    /// Mints an NFT with random content to the sender or one of the existing accounts, the
+    /// content hash marked with `tag` when given.
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_mint_nft<R: Rng>(
+        rng: &mut R,
+        denylist: &AddressDenylist,
+        from: Address,
+        recipients: &[Address],
+        fee_token: TokenId,
+        fee: BigUint,
+        nonce: Nonce,
+        tag: Option<&RunTag>,
+    ) -> Self {
+        let to = denylist.choose_recipient(rng, recipients).unwrap_or(from);
+        let content_hash = match tag {
+            Some(tag) => tag.nft_content_hash(rng),
+            None => H256::from(rng.gen::<[u8; 32]>()),
+        };
+        Transaction::MintNFT {
+            from,
+            to,
+            content_hash,
+            fee_token,
+            fee,
+            nonce,
+        }
+    }
+
+    /// Withdraws an NFT owned by the sender to its L1 address.
+    pub fn generate_withdraw_nft(
+        from: Address,
+        token: TokenId,
+        fee_token: TokenId,
+        fee: BigUint,
+        nonce: Nonce,
+    ) -> Self {
+        Transaction::WithdrawNFT {
+            from,
+            to: from,
+            token,
+            fee_token,
+            fee,
+            nonce,
+        }
+    }
+
+    /// Forces the exit of one of the target accounts, `None` when all of them are denylisted.
+    ///
+    /// The rollup only accepts it for targets that never set a signing key.
+    pub fn generate_forced_exit<R: Rng>(
+        rng: &mut R,
+        denylist: &AddressDenylist,
+        from: Address,
+        targets: &[Address],
+        token: TokenId,
+        fee: BigUint,
+        nonce: Nonce,
+    ) -> Option<Self> {
+        let to = denylist.choose_recipient(rng, targets)?;
+        Some(Transaction::ForcedExit {
+            from,
+            to,
+            token,
+            fee,
+            nonce,
+        })
+    }
+
+    /// Generates an operation of the given type, `None` when the sender can not make one
+    /// right now, such as a withdrawal not worth its fee or an NFT withdrawal without NFT.
+    pub fn generate<R: Rng>(
+        rng: &mut R,
+        kind: TransactionKind,
+        config: &TransactionConfig,
+        denylist: &AddressDenylist,
+        input: GenerationInput<'_>,
+    ) -> Option<Self> {
+        let GenerationInput {
+            from,
+            recipients,
+            token,
+            fee,
+            nonce,
+            balance,
+            nft,
+            tag,
+        } = input;
+        match kind {
+            TransactionKind::Deposit => Some(Self::generate_deposit(rng, config, from, token)),
+            TransactionKind::Transfer => {
+                Self::generate_transfer(rng, config, denylist, from, recipients, token, fee, nonce)
+            }
+            TransactionKind::TransferToNew => Some(Self::generate_transfer_to_new(
+                rng, config, from, token, fee, nonce,
+            )),
+            TransactionKind::Withdraw => {
+                Self::generate_withdraw(rng, config, from, token, fee, nonce, balance).ok()
+            }
+            TransactionKind::ChangePubKey => {
+                Some(Self::generate_change_pubkey(from, token, fee, nonce))
+            }
+            TransactionKind::MintNFT => Some(Self::generate_mint_nft(
+                rng, denylist, from, recipients, token, fee, nonce, tag,
+            )),
+            TransactionKind::WithdrawNFT => {
+                Some(Self::generate_withdraw_nft(from, nft?, token, fee, nonce))
+            }
+            TransactionKind::ForcedExit => {
+                Self::generate_forced_exit(rng, denylist, from, recipients, token, fee, nonce)
+            }
+        }
+    }
+}
+
+fn random_amount<R: Rng>(rng: &mut R, min: u32, max: u32) -> BigUint {
+    BigUint::from(rng.gen_range(min..=max.max(min)))
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::config::Config;
+
+    /// Tests that generated amounts stay within the configured range and skip denylisted recipients.
+    #[test]
+    fn test_generators_respect_config() {
+        let mut config = Config::default().transaction;
+        let denied = Address::from_low_u64_be(2);
+        config.denylist = vec![denied];
+        let denylist = AddressDenylist::new(&config);
+        let mut rng = StdRng::seed_from_u64(7);
+        let from = Address::from_low_u64_be(1);
+        let token = TokenId(0);
+
+        for nonce in 0..50 {
+            let tx = Transaction::generate_transfer(
+                &mut rng,
+                &config,
+                &denylist,
+                from,
+                &[denied, Address::from_low_u64_be(3)],
+                token,
+                BigUint::from(1u32),
+                Nonce(nonce),
+            )
+            .unwrap();
+            assert_eq!(tx.to(), Some(Address::from_low_u64_be(3)));
+            assert_eq!(tx.nonce(), Some(Nonce(nonce)));
+            match tx {
+                Transaction::Transfer { amount, .. } => {
+                    assert!(amount >= BigUint::from(config.min_transfer_value));
+                    assert!(amount <= BigUint::from(config.max_transfer_value));
+                }
+                other => panic!("unexpected {:?}", other.kind()),
+            }
+        }
+        assert!(Transaction::generate_transfer(
+            &mut rng,
+            &config,
+            &denylist,
+            from,
+            &[denied],
+            token,
+            BigUint::from(1u32),
+            Nonce(0),
+        )
+        .is_none());
+
+        let deposit = Transaction::generate_deposit(&mut rng, &config, from, token);
+        assert_eq!(deposit.kind(), TransactionKind::Deposit);
+        assert_eq!(deposit.fee(), None);
+
+        let skip = Transaction::generate_withdraw(
+            &mut rng,
+            &config,
+            from,
+            token,
+            BigUint::from(1_000u32),
+            Nonce(0),
+            &BigUint::from(1_000_000u32),
+        )
+        .unwrap_err();
+        assert_eq!(skip.name(), "fee_too_high");
+    }
+
+    /// Tests parsing the configured mix, sampling types by weight and generating each type.
+    #[test]
+    fn test_transaction_mix() {
+        let mix: BTreeMap<TransactionKind, u32> = toml::from_str(
+            "transfer = 70\ndeposit = 10\nwithdraw = 10\nmint_nft = 5\nchange_pubkey = 5\nforced_exit = 0",
+        )
+        .unwrap();
+        assert!(toml::from_str::<BTreeMap<TransactionKind, u32>>("mintNFT = 5").is_err());
+        let mix = TransactionMix::new(&mix).unwrap();
+        assert!(!mix.kinds().contains(&TransactionKind::ForcedExit));
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut counts: BTreeMap<TransactionKind, u32> = BTreeMap::new();
+        for _ in 0..10_000 {
+            *counts.entry(mix.choose(&mut rng)).or_default() += 1;
+        }
+        assert_eq!(counts.len(), 5);
+        assert!((6_800..7_200).contains(&counts[&TransactionKind::Transfer]));
+        assert!((400..600).contains(&counts[&TransactionKind::MintNFT]));
+        assert!(TransactionMix::new(&BTreeMap::from([(TransactionKind::Deposit, 0)])).is_err());
+
+        let config = Config::default().transaction;
+        let denylist = AddressDenylist::new(&config);
+        let balance = BigUint::from(1_000_000u32);
+        let input = GenerationInput {
+            from: Address::from_low_u64_be(1),
+            recipients: &[Address::from_low_u64_be(2)],
+            token: TokenId(0),
+            fee: BigUint::from(1u32),
+            nonce: Nonce(3),
+            balance: &balance,
+            nft: None,
+            tag: None,
+        };
+        for kind in TransactionKind::ALL {
+            let tx = Transaction::generate(&mut rng, kind, &config, &denylist, input.clone());
+            match kind {
+                TransactionKind::WithdrawNFT => assert!(tx.is_none()),
+                _ => assert_eq!(tx.unwrap().kind(), kind),
+            }
+        }
+        let owner = GenerationInput {
+            nft: Some(TokenId(70_000)),
+            ..input
+        };
+        let tx = Transaction::generate(
+            &mut rng,
+            TransactionKind::WithdrawNFT,
+            &config,
+            &denylist,
+            owner,
+        );
+        assert_eq!(tx.unwrap().nonce(), Some(Nonce(3)));
+    }
+
+    /// Tests that zero, contract and badly checksummed addresses are rejected locally.
+    #[test]
+    fn test_address_validation() {
+        let contract = Address::from_low_u64_be(9);
+        let denylist =
+            AddressDenylist::new(&Config::default().transaction).with_contract_address(contract);
+        let from = Address::from_low_u64_be(1);
+        let withdraw = |from, to| Transaction::Withdraw {
+            from,
+            to,
+            token: TokenId(0),
+            amount: BigUint::from(1u32),
+            fee: BigUint::from(1u32),
+            nonce: Nonce(0),
+            fast: false,
+        };
+
+        assert!(withdraw(from, from).validate_addresses(&denylist).is_ok());
+        for (from, to) in [
+            (from, contract),
+            (from, Address::zero()),
+            (Address::zero(), from),
+        ] {
+            assert!(matches!(
+                withdraw(from, to).validate_addresses(&denylist),
+                Err(ClientError::IncorrectAddress)
+            ));
+        }
+        let mut rng = StdRng::seed_from_u64(7);
+        assert_eq!(
+            denylist.choose_recipient(&mut rng, &[Address::zero(), contract]),
+            None
+        );
+
+        let eip55 = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+        let eip1191 = "0x5aaEB6053f3e94c9b9a09f33669435E7ef1bEAeD";
+        assert!(parse_address(eip55).is_ok());
+        assert!(parse_address(eip1191).is_ok());
+        assert!(parse_address(&eip55.to_lowercase()).is_ok());
+        for malformed in [
+            "0x5AAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "5aaeb6053f3e94c9b9a09f33669435e7ef1beaed",
+            "0x5aaeb6053f3e94c9b9a09f33669435e7ef1bea",
+            "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beazz",
+        ] {
+            assert!(
+                matches!(parse_address(malformed), Err(ClientError::IncorrectAddress)),
+                "{malformed}"
+            );
         }
     }
 }
@@ -0,0 +1,423 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use num::{BigUint, Zero};
+use serde::Deserialize;
+
+use crate::rollup::types::pubkey_hash::PubKeyHash;
+use crate::rollup::types::{AccountState, Address, DepositingAccountBalances, Nonce, TokenId, NFT};
+
+/// Compaction of the local account model, configured in the `[account_gc]` section.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AccountGcConfig {
+    /// How often empty balances and transferred-away NFTs are dropped.
+    #[serde(default = "AccountGcConfig::default_compaction_interval_secs")]
+    pub compaction_interval_secs: u64,
+    /// How often the local state is replaced by the server's `account_info`.
+    #[serde(default = "AccountGcConfig::default_resync_interval_secs")]
+    pub resync_interval_secs: u64,
+    /// Balances at or below this amount are considered dust and dropped.
+    #[serde(default)]
+    pub dust_threshold: u64,
+}
+
+impl AccountGcConfig {
+    fn default_compaction_interval_secs() -> u64 {
+        600
+    }
+
+    fn default_resync_interval_secs() -> u64 {
+        3_600
+    }
+}
+
+impl Default for AccountGcConfig {
+    fn default() -> Self {
+        Self {
+            compaction_interval_secs: Self::default_compaction_interval_secs(),
+            resync_interval_secs: Self::default_resync_interval_secs(),
+            dust_threshold: 0,
+        }
+    }
+}
+
+/// NFT known to the local model, kept until compaction once transferred away.
+#[derive(Debug, Clone)]
+pub struct NftEntry {
+    pub nft: NFT,
+    pub transferred_away: bool,
+}
+
+/// Simulator's own view of an account, updated as transactions are sent.
+#[derive(Debug, Clone, Default)]
+pub struct LocalAccount {
+    pub address: Address,
+    pub nonce: Nonce,
+    /// Committed balances, the ones transactions can spend.
+    pub balances: HashMap<String, BigUint>,
+    /// Deposits sent on L1 that the rollup has not accepted yet, kept apart so they are
+    /// neither spent nor counted twice once they show up in the committed balance.
+    pub depositing: HashMap<String, BigUint>,
+    pub nfts: HashMap<TokenId, NftEntry>,
+    /// Whether the account set its signing key, which protects it from forced exits.
+    pub signing_key_set: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompactionStats {
+    pub balances_removed: usize,
+    pub nfts_removed: usize,
+}
+
+/// Difference between the local model and the committed state reported by the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateDiscrepancy {
+    Nonce {
+        local: Nonce,
+        server: Nonce,
+    },
+    Balance {
+        token: String,
+        local: BigUint,
+        server: BigUint,
+    },
+    /// NFT owned according to one side only.
+    Nft {
+        id: TokenId,
+        owned_locally: bool,
+    },
+}
+
+impl LocalAccount {
+    pub fn new(address: Address) -> Self {
+        Self {
+            address,
+            ..Default::default()
+        }
+    }
+
+    /// Records a deposit of `amount` sent on L1, spendable once the rollup accepted it.
+    pub fn record_deposit(&mut self, token: &str, amount: &BigUint) {
+        *self.depositing.entry(token.to_string()).or_default() += amount;
+    }
+
+    /// Moves up to `amount` of a pending deposit of `token` to the committed balance, once
+    /// the rollup accepted the deposit.
+    pub fn accept_deposit(&mut self, token: &str, amount: &BigUint) {
+        let Some(depositing) = self.depositing.get_mut(token) else {
+            return;
+        };
+        let accepted = amount.min(depositing).clone();
+        *depositing -= &accepted;
+        if depositing.is_zero() {
+            self.depositing.remove(token);
+        }
+        *self.balances.entry(token.to_string()).or_default() += accepted;
+    }
+
+    /// Drops up to `amount` of a pending deposit of `token` that failed on L1.
+    pub fn cancel_deposit(&mut self, token: &str, amount: &BigUint) {
+        let Some(depositing) = self.depositing.get_mut(token) else {
+            return;
+        };
+        *depositing -= amount.min(depositing).clone();
+        if depositing.is_zero() {
+            self.depositing.remove(token);
+        }
+    }
+
+    /// NFT the account still owns, the one with the lowest id.
+    pub fn owned_nft(&self) -> Option<TokenId> {
+        self.nfts
+            .iter()
+            .filter(|(_, entry)| !entry.transferred_away)
+            .map(|(id, _)| *id)
+            .min()
+    }
+
+    /// Drops balances at or below the dust threshold and NFTs that were transferred away.
+    pub fn compact(&mut self, dust_threshold: &BigUint) -> CompactionStats {
+        let balances = self.balances.len();
+        let nfts = self.nfts.len();
+        self.balances
+            .retain(|_, balance| *balance > *dust_threshold);
+        self.nfts.retain(|_, entry| !entry.transferred_away);
+        self.balances.shrink_to_fit();
+        self.nfts.shrink_to_fit();
+
+        CompactionStats {
+            balances_removed: balances - self.balances.len(),
+            nfts_removed: nfts - self.nfts.len(),
+        }
+    }
+
+    /// Replaces the local state by the committed state of the server, reporting what differed.
+    ///
+    /// Local pending deposits the server no longer lists as depositing were accepted in the
+    /// meantime, so they are expected in its committed balance; the ones it still lists are not.
+    pub fn resync(
+        &mut self,
+        committed: &AccountState,
+        depositing: &DepositingAccountBalances,
+    ) -> Vec<StateDiscrepancy> {
+        let mut discrepancies = Vec::new();
+        if self.nonce != committed.nonce {
+            discrepancies.push(StateDiscrepancy::Nonce {
+                local: self.nonce,
+                server: committed.nonce,
+            });
+        }
+
+        let zero = BigUint::zero();
+        let server_depositing: HashMap<String, BigUint> = depositing
+            .amounts()
+            .map(|(token, amount)| (token.clone(), amount.clone()))
+            .collect();
+        let mut tokens: Vec<&String> = self
+            .balances
+            .keys()
+            .chain(self.depositing.keys())
+            .chain(committed.balances.keys())
+            .collect();
+        tokens.sort();
+        tokens.dedup();
+        for token in tokens {
+            let pending = self.depositing.get(token).unwrap_or(&zero);
+            let still_pending = server_depositing.get(token).unwrap_or(&zero);
+            let mut local = self.balances.get(token).unwrap_or(&zero).clone();
+            if pending > still_pending {
+                local += pending - still_pending;
+            }
+            let server = committed
+                .balances
+                .get(token)
+                .map(|balance| &balance.0)
+                .unwrap_or(&zero);
+            if local != *server {
+                discrepancies.push(StateDiscrepancy::Balance {
+                    token: token.clone(),
+                    local,
+                    server: server.clone(),
+                });
+            }
+        }
+
+        let owned_locally = |id: &TokenId| {
+            self.nfts
+                .get(id)
+                .is_some_and(|entry| !entry.transferred_away)
+        };
+        let mut nft_ids: Vec<TokenId> = self
+            .nfts
+            .keys()
+            .chain(committed.nfts.keys())
+            .copied()
+            .collect();
+        nft_ids.sort();
+        nft_ids.dedup();
+        for id in nft_ids {
+            let owned_locally = owned_locally(&id);
+            if owned_locally != committed.nfts.contains_key(&id) {
+                discrepancies.push(StateDiscrepancy::Nft { id, owned_locally });
+            }
+        }
+
+        self.nonce = committed.nonce;
+        self.signing_key_set = committed.pub_key_hash != PubKeyHash::zero();
+        self.balances = committed
+            .balances
+            .iter()
+            .map(|(token, balance)| (token.clone(), balance.0.clone()))
+            .collect();
+        self.depositing = server_depositing;
+        self.nfts = committed
+            .nfts
+            .iter()
+            .map(|(id, nft)| {
+                let entry = NftEntry {
+                    nft: nft.clone(),
+                    transferred_away: false,
+                };
+                (*id, entry)
+            })
+            .collect();
+
+        discrepancies
+    }
+}
+
+/// Maintenance of the local model due at some point of the run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountGcAction {
+    Compact,
+    /// Re-sync against `account_info`, which compacts as well.
+    Resync,
+}
+
+/// Decides when the local model of accounts is compacted or re-synced.
+pub struct AccountGcSchedule {
+    config: AccountGcConfig,
+    last_compaction: Duration,
+    last_resync: Duration,
+}
+
+impl AccountGcSchedule {
+    pub fn new(config: AccountGcConfig) -> Self {
+        Self {
+            config,
+            last_compaction: Duration::ZERO,
+            last_resync: Duration::ZERO,
+        }
+    }
+
+    pub fn dust_threshold(&self) -> BigUint {
+        BigUint::from(self.config.dust_threshold)
+    }
+
+    /// Returns the maintenance due after `elapsed` time of the run, if any.
+    pub fn due(&mut self, elapsed: Duration) -> Option<AccountGcAction> {
+        let resync_interval = Duration::from_secs(self.config.resync_interval_secs);
+        let compaction_interval = Duration::from_secs(self.config.compaction_interval_secs);
+        if elapsed.saturating_sub(self.last_resync) >= resync_interval {
+            self.last_resync = elapsed;
+            self.last_compaction = elapsed;
+            Some(AccountGcAction::Resync)
+        } else if elapsed.saturating_sub(self.last_compaction) >= compaction_interval {
+            self.last_compaction = elapsed;
+            Some(AccountGcAction::Compact)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rollup::types::serde_wrappers::BigUintSerdeWrapper;
+    use crate::rollup::types::{AccountId, H256};
+
+    fn nft(id: u32) -> NFT {
+        NFT {
+            id: TokenId(id),
+            symbol: format!("NFT-{}", id),
+            creator_id: AccountId(1),
+            content_hash: H256::zero(),
+        }
+    }
+
+    /// Tests that compaction drops dust and sent NFTs and re-sync reports every difference.
+    #[test]
+    fn test_compact_and_resync() {
+        let mut account = LocalAccount::new(Address::repeat_byte(1));
+        account.nonce = Nonce(4);
+        account
+            .balances
+            .insert("RBTC".to_string(), BigUint::from(1_000u32));
+        account
+            .balances
+            .insert("RIF".to_string(), BigUint::from(3u32));
+        account.balances.insert("DOC".to_string(), BigUint::zero());
+        for id in [70_000, 70_001] {
+            let entry = NftEntry {
+                nft: nft(id),
+                transferred_away: id == 70_001,
+            };
+            account.nfts.insert(TokenId(id), entry);
+        }
+
+        let stats = account.compact(&BigUint::from(5u32));
+        assert_eq!(
+            stats,
+            CompactionStats {
+                balances_removed: 2,
+                nfts_removed: 1
+            }
+        );
+
+        let mut committed = AccountState {
+            nonce: Nonce(5),
+            ..Default::default()
+        };
+        committed.balances.insert(
+            "RBTC".to_string(),
+            BigUintSerdeWrapper(BigUint::from(900u32)),
+        );
+        committed.nfts.insert(TokenId(70_002), nft(70_002));
+
+        let no_deposits = DepositingAccountBalances::default();
+        let discrepancies = account.resync(&committed, &no_deposits);
+        assert_eq!(
+            discrepancies,
+            vec![
+                StateDiscrepancy::Nonce {
+                    local: Nonce(4),
+                    server: Nonce(5)
+                },
+                StateDiscrepancy::Balance {
+                    token: "RBTC".to_string(),
+                    local: BigUint::from(1_000u32),
+                    server: BigUint::from(900u32)
+                },
+                StateDiscrepancy::Nft {
+                    id: TokenId(70_000),
+                    owned_locally: true
+                },
+                StateDiscrepancy::Nft {
+                    id: TokenId(70_002),
+                    owned_locally: false
+                },
+            ]
+        );
+        assert!(account.resync(&committed, &no_deposits).is_empty());
+    }
+
+    /// Tests that pending deposits are not spendable, raise no discrepancy while the server
+    /// still lists them as depositing and are expected in its committed balance afterwards.
+    #[test]
+    fn test_pending_deposits() {
+        let mut account = LocalAccount::new(Address::repeat_byte(1));
+        account
+            .balances
+            .insert("RBTC".to_string(), BigUint::from(10u32));
+        account.record_deposit("RBTC", &BigUint::from(100u32));
+        assert_eq!(account.balances["RBTC"], BigUint::from(10u32));
+
+        let mut committed = AccountState::default();
+        committed.balances.insert(
+            "RBTC".to_string(),
+            BigUintSerdeWrapper(BigUint::from(10u32)),
+        );
+        let depositing: DepositingAccountBalances = serde_json::from_value(serde_json::json!({
+            "balances": { "RBTC": { "amount": "100", "expectedAcceptBlock": 12 } }
+        }))
+        .unwrap();
+        assert!(account.resync(&committed, &depositing).is_empty());
+        assert_eq!(account.depositing["RBTC"], BigUint::from(100u32));
+
+        committed.balances.insert(
+            "RBTC".to_string(),
+            BigUintSerdeWrapper(BigUint::from(110u32)),
+        );
+        let accepted = DepositingAccountBalances::default();
+        assert!(account.resync(&committed, &accepted).is_empty());
+        assert!(account.depositing.is_empty());
+        assert_eq!(account.balances["RBTC"], BigUint::from(110u32));
+
+        // A deposit the server never accepted nor lists shows up as a divergence.
+        account.record_deposit("RBTC", &BigUint::from(5u32));
+        assert_eq!(
+            account.resync(&committed, &accepted),
+            vec![StateDiscrepancy::Balance {
+                token: "RBTC".to_string(),
+                local: BigUint::from(115u32),
+                server: BigUint::from(110u32)
+            }]
+        );
+
+        account.record_deposit("RBTC", &BigUint::from(5u32));
+        account.accept_deposit("RBTC", &BigUint::from(8u32));
+        assert_eq!(account.balances["RBTC"], BigUint::from(115u32));
+        assert!(account.depositing.is_empty());
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::{BTreeSet, HashMap};
+
+use ethers::types::Address;
+use thiserror::Error;
+
+use crate::rollup::types::Nonce;
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum BatchNonceError {
+    #[error("Account {0:?} has no known nonce")]
+    UnknownAccount(Address),
+    #[error("Account {0:?} ran out of nonces")]
+    NoncesExhausted(Address),
+}
+
+/// Identifier of a batch whose nonces are reserved but not yet settled.
+pub type BatchId = u64;
+
+/// Nonces assigned to the members of one batch, in member order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchNonces {
+    pub id: BatchId,
+    pub members: Vec<(Address, Nonce)>,
+}
+
+impl BatchNonces {
+    pub fn nonces(&self) -> impl Iterator<Item = Nonce> + '_ {
+        self.members.iter().map(|(_, nonce)| *nonce)
+    }
+
+    /// First nonce the batch uses for each of its senders.
+    fn first_nonces(&self) -> HashMap<Address, Nonce> {
+        let mut first = HashMap::new();
+        for (sender, nonce) in &self.members {
+            first.entry(*sender).or_insert(*nonce);
+        }
+        first
+    }
+}
+
+#[derive(Debug, Default)]
+struct SenderState {
+    next: Nonce,
+    /// Unsettled batches using nonces of this sender.
+    pending: BTreeSet<BatchId>,
+}
+
+/// Assigns nonces to batches where one sender may appear several times.
+///
+/// The rollup executes a batch atomically, so a rejected batch consumes none of its
+/// nonces and every sender in it goes back to the nonce the batch started at. Batches
+/// reserved after it that share a sender were signed with nonces that are now ahead of
+/// the account and will be rejected as well; they are rolled back together and returned
+/// to the caller to be re-signed.
+#[derive(Debug, Default)]
+pub struct BatchNonceTracker {
+    senders: HashMap<Address, SenderState>,
+    pending: HashMap<BatchId, BatchNonces>,
+    next_id: BatchId,
+}
+
+impl BatchNonceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the nonce the sender's next transaction must use, e.g. the committed nonce from `account_info`.
+    pub fn set_nonce(&mut self, sender: Address, nonce: Nonce) {
+        self.senders.entry(sender).or_default().next = nonce;
+    }
+
+    pub fn next_nonce(&self, sender: &Address) -> Option<Nonce> {
+        self.senders.get(sender).map(|state| state.next)
+    }
+
+    /// Reserves consecutive nonces for every sender of the batch, in the order the senders appear.
+    pub fn reserve(&mut self, senders: &[Address]) -> Result<BatchNonces, BatchNonceError> {
+        let mut next: HashMap<Address, Nonce> = HashMap::new();
+        let mut members = Vec::with_capacity(senders.len());
+        for sender in senders {
+            let nonce = match next.get(sender) {
+                Some(nonce) => *nonce,
+                None => {
+                    self.senders
+                        .get(sender)
+                        .ok_or(BatchNonceError::UnknownAccount(*sender))?
+                        .next
+                }
+            };
+            let following = nonce
+                .checked_next()
+                .ok_or(BatchNonceError::NoncesExhausted(*sender))?;
+            next.insert(*sender, following);
+            members.push((*sender, nonce));
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        for (sender, nonce) in next {
+            let state = self.senders.entry(sender).or_default();
+            state.next = nonce;
+            state.pending.insert(id);
+        }
+        let batch = BatchNonces { id, members };
+        self.pending.insert(id, batch.clone());
+        Ok(batch)
+    }
+
+    /// Marks the batch as accepted, its nonces are consumed for good.
+    pub fn confirm(&mut self, id: BatchId) {
+        if let Some(batch) = self.pending.remove(&id) {
+            for sender in batch.first_nonces().keys() {
+                if let Some(state) = self.senders.get_mut(sender) {
+                    state.pending.remove(&id);
+                }
+            }
+        }
+    }
+
+    /// Rolls back the nonces of a rejected batch and of every later batch depending on them.
+    ///
+    /// Returns the later batches that were rolled back, in reservation order.
+    pub fn reject(&mut self, id: BatchId) -> Vec<BatchNonces> {
+        let mut to_visit = vec![id];
+        let mut rolled_back = BTreeSet::new();
+        while let Some(id) = to_visit.pop() {
+            if !rolled_back.insert(id) {
+                continue;
+            }
+            let Some(batch) = self.pending.get(&id) else {
+                continue;
+            };
+            for sender in batch.first_nonces().keys() {
+                if let Some(state) = self.senders.get(sender) {
+                    to_visit.extend(state.pending.range(id + 1..));
+                }
+            }
+        }
+
+        let mut invalidated = Vec::new();
+        for id in rolled_back.into_iter().rev() {
+            let Some(batch) = self.pending.remove(&id) else {
+                continue;
+            };
+            // Visiting newest first leaves every sender at the oldest rolled back nonce.
+            for (sender, first) in batch.first_nonces() {
+                if let Some(state) = self.senders.get_mut(&sender) {
+                    state.next = first;
+                    state.pending.remove(&id);
+                }
+            }
+            invalidated.push(batch);
+        }
+        invalidated.reverse();
+        invalidated.retain(|batch| batch.id != id);
+        invalidated
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests that repeated senders get consecutive nonces and rejection rolls back dependent batches.
+    #[test]
+    fn test_batch_nonce_rollback() {
+        let alice = Address::from_low_u64_be(1);
+        let bob = Address::from_low_u64_be(2);
+        let carol = Address::from_low_u64_be(3);
+        let dave = Address::from_low_u64_be(4);
+        let mut tracker = BatchNonceTracker::new();
+        tracker.set_nonce(alice, Nonce(5));
+        tracker.set_nonce(bob, Nonce(0));
+        tracker.set_nonce(carol, Nonce(9));
+        tracker.set_nonce(dave, Nonce(1));
+
+        let first = tracker.reserve(&[alice, bob, alice]).unwrap();
+        assert_eq!(
+            first.nonces().collect::<Vec<_>>(),
+            vec![Nonce(5), Nonce(0), Nonce(6)]
+        );
+        // Depends on the first batch through bob, the third one through carol.
+        let second = tracker.reserve(&[bob, carol]).unwrap();
+        let third = tracker.reserve(&[carol, carol]).unwrap();
+        let unrelated = tracker.reserve(&[dave, dave]).unwrap();
+        tracker.confirm(unrelated.id);
+
+        let invalidated = tracker.reject(first.id);
+        assert_eq!(
+            invalidated.iter().map(|batch| batch.id).collect::<Vec<_>>(),
+            vec![second.id, third.id]
+        );
+        assert_eq!(tracker.next_nonce(&bob), Some(Nonce(0)));
+        assert_eq!(tracker.next_nonce(&carol), Some(Nonce(9)));
+        assert_eq!(tracker.next_nonce(&alice), Some(Nonce(5)));
+        assert_eq!(tracker.next_nonce(&dave), Some(Nonce(3)));
+
+        assert_eq!(
+            tracker.reserve(&[carol]).unwrap().members,
+            vec![(carol, Nonce(9))]
+        );
+        assert_eq!(
+            tracker.reserve(&[Address::zero()]),
+            Err(BatchNonceError::UnknownAccount(Address::zero()))
+        );
+    }
+}
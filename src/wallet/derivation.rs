@@ -0,0 +1,121 @@
+use ethers::signers::coins_bip39::English;
+use ethers::signers::{LocalWallet, MnemonicBuilder, Signer, WalletError};
+use ethers::types::Address;
+use serde::Deserialize;
+use thiserror::Error;
+
+/// BIP-44 path used by Rootstock mainnet wallets (coin type 137).
+pub const RSK_MAINNET_PATH: &str = "m/44'/137'/0'/0/{index}";
+/// BIP-44 path used by Rootstock testnet wallets (coin type 37310).
+pub const RSK_TESTNET_PATH: &str = "m/44'/37310'/0'/0/{index}";
+
+/// Placeholder replaced by the account index in derivation path templates.
+const INDEX_PLACEHOLDER: &str = "{index}";
+
+#[derive(Debug, Error)]
+pub enum DerivationError {
+    #[error("Derivation path template '{0}' must contain {{index}}")]
+    MissingIndex(String),
+    #[error("Unable to derive key: {0}")]
+    Wallet(#[from] WalletError),
+}
+
+/// Derivation of simulated account keys from a mnemonic, configured in the `[keys]` section.
+///
+/// Accounts derived this way can be opened in any standard wallet
+/// using the same mnemonic and derivation path.
+#[derive(Debug, Clone, Deserialize)]
+pub struct KeysConfig {
+    /// BIP-39 mnemonic phrase.
+    pub mnemonic: String,
+    /// BIP-44 path template, `{index}` is replaced by the account index.
+    #[serde(default = "KeysConfig::default_derivation_path")]
+    pub derivation_path: String,
+    /// Index of the first derived account.
+    #[serde(default)]
+    pub first_index: u32,
+}
+
+impl KeysConfig {
+    fn default_derivation_path() -> String {
+        RSK_MAINNET_PATH.to_string()
+    }
+}
+
+/// Expands the path template for the given account index.
+pub fn derivation_path(template: &str, index: u32) -> Result<String, DerivationError> {
+    if !template.contains(INDEX_PLACEHOLDER) {
+        return Err(DerivationError::MissingIndex(template.to_string()));
+    }
+    Ok(template.replace(INDEX_PLACEHOLDER, &index.to_string()))
+}
+
+/// Derives the L1 wallet at the given path template and index.
+pub fn derive_wallet(
+    mnemonic: &str,
+    template: &str,
+    index: u32,
+) -> Result<LocalWallet, DerivationError> {
+    let path = derivation_path(template, index)?;
+    let wallet = MnemonicBuilder::<English>::default()
+        .phrase(mnemonic)
+        .derivation_path(&path)?
+        .build()?;
+    Ok(wallet)
+}
+
+/// Derives `count` consecutive account wallets as configured.
+pub fn derive_wallets(
+    config: &KeysConfig,
+    count: u32,
+) -> Result<Vec<LocalWallet>, DerivationError> {
+    (config.first_index..config.first_index + count)
+        .map(|index| derive_wallet(&config.mnemonic, &config.derivation_path, index))
+        .collect()
+}
+
+/// Addresses of the derived wallets, handy for listing accounts without keeping keys around.
+pub fn derive_addresses(config: &KeysConfig, count: u32) -> Result<Vec<Address>, DerivationError> {
+    Ok(derive_wallets(config, count)?
+        .iter()
+        .map(|wallet| wallet.address())
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TEST_MNEMONIC: &str = "test test test test test test test test test test test junk";
+
+    /// Tests derivation against the well known development mnemonic.
+    #[test]
+    fn test_bip44_derivation() {
+        let wallet = derive_wallet(TEST_MNEMONIC, "m/44'/60'/0'/0/{index}", 0).unwrap();
+        assert_eq!(
+            wallet.address(),
+            "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"
+                .parse::<Address>()
+                .unwrap()
+        );
+
+        let config = KeysConfig {
+            mnemonic: TEST_MNEMONIC.to_string(),
+            derivation_path: RSK_TESTNET_PATH.to_string(),
+            first_index: 3,
+        };
+        let addresses = derive_addresses(&config, 2).unwrap();
+        assert_eq!(
+            addresses[0],
+            derive_wallet(TEST_MNEMONIC, RSK_TESTNET_PATH, 3)
+                .unwrap()
+                .address()
+        );
+        assert_ne!(addresses[0], addresses[1]);
+
+        assert!(matches!(
+            derivation_path("m/44'/137'/0'/0/0", 1),
+            Err(DerivationError::MissingIndex(_))
+        ));
+    }
+}
@@ -0,0 +1,209 @@
+use std::fs;
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use ethers::signers::{LocalWallet, Signer, WalletError};
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::paths;
+
+/// Prefix of the keystore files of the accounts, followed by the account index.
+const KEY_FILE_PREFIX: &str = "account-";
+
+#[derive(Debug, Error)]
+pub enum KeystoreError {
+    #[error("Keystore I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Malformed key file: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Unable to read or write account key: {0}")]
+    Key(#[from] WalletError),
+    #[error("keystore.password is required to encrypt the account keys, or opt in to a plain key file with --insecure-plain")]
+    MissingPassword,
+}
+
+/// Keys of randomly generated accounts kept between runs, configured in the `[keystore]`
+/// section, so accounts funded once are reused instead of funded again on every run.
+///
+/// Not needed with `[keys]`, whose accounts are derived from the mnemonic again.
+#[derive(Debug, Clone, Deserialize)]
+pub struct KeystoreConfig {
+    /// Directory of the encrypted keystore files, one per account, or the JSON key file
+    /// with `insecure_plain`. `~` expands to the home directory.
+    pub path: PathBuf,
+    /// Password of the keystore files, better given as `RIF_SIM_KEYSTORE__PASSWORD`.
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Writes the keys unencrypted to a single JSON file readable by its owner only.
+    #[serde(default)]
+    pub insecure_plain: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PlainKey {
+    address: Address,
+    private_key: String,
+}
+
+enum Format {
+    /// Standard Ethereum keystore files, which wallets can import.
+    Encrypted {
+        password: String,
+    },
+    Plain,
+}
+
+/// Account keys stored on disk, in account order.
+pub struct AccountKeystore {
+    path: PathBuf,
+    format: Format,
+}
+
+impl AccountKeystore {
+    pub fn from_config(config: &KeystoreConfig) -> Result<Self, KeystoreError> {
+        let format = match (&config.password, config.insecure_plain) {
+            (_, true) => Format::Plain,
+            (Some(password), false) => Format::Encrypted {
+                password: password.clone(),
+            },
+            (None, false) => return Err(KeystoreError::MissingPassword),
+        };
+        Ok(Self {
+            path: paths::expand_home(&config.path),
+            format,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Keys stored so far, none when the keystore does not exist yet.
+    pub fn load(&self) -> Result<Vec<LocalWallet>, KeystoreError> {
+        match &self.format {
+            Format::Encrypted { password } => {
+                let entries = match fs::read_dir(&self.path) {
+                    Ok(entries) => entries,
+                    Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+                    Err(err) => return Err(err.into()),
+                };
+                let mut files = Vec::new();
+                for entry in entries {
+                    let path = entry?.path();
+                    let is_key = path
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .is_some_and(|name| name.starts_with(KEY_FILE_PREFIX));
+                    if is_key {
+                        files.push(path);
+                    }
+                }
+                // Zero-padded indices sort in account order.
+                files.sort();
+                files
+                    .iter()
+                    .map(|file| Ok(LocalWallet::decrypt_keystore(file, password)?))
+                    .collect()
+            }
+            Format::Plain => {
+                let content = match fs::read_to_string(&self.path) {
+                    Ok(content) => content,
+                    Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+                    Err(err) => return Err(err.into()),
+                };
+                let keys: Vec<PlainKey> = serde_json::from_str(&content)?;
+                keys.iter()
+                    .map(|key| Ok(key.private_key.trim_start_matches("0x").parse()?))
+                    .collect()
+            }
+        }
+    }
+
+    /// Stores `wallets` as the keys of the accounts from index `first` on; the keys before
+    /// it are expected to be stored already.
+    pub fn store(&self, first: usize, wallets: &[LocalWallet]) -> Result<(), KeystoreError> {
+        match &self.format {
+            Format::Encrypted { password } => {
+                fs::create_dir_all(&self.path)?;
+                let mut rng = rand::thread_rng();
+                for (offset, wallet) in wallets.iter().enumerate() {
+                    let name = format!("{KEY_FILE_PREFIX}{:06}.json", first + offset);
+                    LocalWallet::encrypt_keystore(
+                        &self.path,
+                        &mut rng,
+                        wallet.signer().to_bytes(),
+                        password,
+                        Some(&name),
+                    )?;
+                }
+            }
+            Format::Plain => {
+                let keys: Vec<PlainKey> = self
+                    .load()?
+                    .iter()
+                    .take(first)
+                    .chain(wallets)
+                    .map(|wallet| PlainKey {
+                        address: wallet.address(),
+                        private_key: format!("0x{}", hex::encode(wallet.signer().to_bytes())),
+                    })
+                    .collect();
+                let mut file = paths::open_private(&self.path, false)?;
+                file.write_all(serde_json::to_string_pretty(&keys)?.as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests that both formats give back the stored keys in order, appended ones included,
+    /// and that encryption needs a password.
+    #[test]
+    fn test_keystore_round_trip() {
+        let dir = std::env::temp_dir().join(format!("keystore-test-{}", std::process::id()));
+        let wallets: Vec<LocalWallet> = (1..=3u8)
+            .map(|seed| LocalWallet::from_bytes(&[seed; 32]).unwrap())
+            .collect();
+        let addresses = |wallets: &[LocalWallet]| -> Vec<Address> {
+            wallets.iter().map(LocalWallet::address).collect()
+        };
+
+        for insecure_plain in [true, false] {
+            let config = KeystoreConfig {
+                path: dir.join(if insecure_plain { "keys.json" } else { "keys" }),
+                password: Some("secret".to_string()),
+                insecure_plain,
+            };
+            let keystore = AccountKeystore::from_config(&config).unwrap();
+            assert!(keystore.load().unwrap().is_empty());
+            keystore.store(0, &wallets[..2]).unwrap();
+            keystore.store(2, &wallets[2..]).unwrap();
+            assert_eq!(addresses(&keystore.load().unwrap()), addresses(&wallets));
+
+            if !insecure_plain {
+                let wrong = AccountKeystore::from_config(&KeystoreConfig {
+                    password: Some("wrong".to_string()),
+                    ..config.clone()
+                })
+                .unwrap();
+                assert!(matches!(wrong.load(), Err(KeystoreError::Key(_))));
+                let missing = KeystoreConfig {
+                    password: None,
+                    ..config
+                };
+                assert!(matches!(
+                    AccountKeystore::from_config(&missing),
+                    Err(KeystoreError::MissingPassword)
+                ));
+            }
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
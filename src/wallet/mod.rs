@@ -0,0 +1,308 @@
+pub mod account_state;
+pub mod batch_nonce;
+pub mod derivation;
+pub mod keystore;
+pub mod nonce;
+pub mod signing_key;
+
+use ethers::signers::{LocalWallet, Signer};
+use num::BigUint;
+
+use self::signing_key::{SigningKey, SIGNING_KEY_SEED_MESSAGE};
+use crate::rollup::provider::ClientError;
+use crate::rollup::types::packing::{is_fee_amount_packable, is_token_amount_packable};
+use crate::rollup::types::pubkey_hash::PubKeyHash;
+use crate::rollup::types::tx::{
+    ChangePubKey, ChangePubKeyCREATE2Data, ChangePubKeyEthAuthData, ForcedExit, MintNFT,
+    PackedEthSignature, TimeRange, Transfer, TxSignature, Withdraw, WithdrawNFT, ZkSyncTx,
+};
+use crate::rollup::types::{AccountId, Address, ChangePubKeyFeeType, Nonce, Token, TokenId, H256};
+
+/// Signed transaction ready for `Provider::send_tx`.
+pub type SignedTx = (ZkSyncTx, Option<PackedEthSignature>);
+
+/// Keys of a simulated account: the Rootstock key owning it and the L2 signing key derived from it.
+pub struct Wallet {
+    eth_signer: LocalWallet,
+    signing_key: SigningKey,
+    account_id: Option<AccountId>,
+    create2: Option<ChangePubKeyCREATE2Data>,
+}
+
+impl Wallet {
+    /// Creates the wallet, deriving the L2 signing key the same way standard wallets do.
+    pub async fn new(eth_signer: LocalWallet) -> Result<Self, ClientError> {
+        let seed = eth_signer
+            .sign_message(SIGNING_KEY_SEED_MESSAGE)
+            .await
+            .map_err(|err| ClientError::SigningError(err.to_string()))?;
+        let signing_key = SigningKey::from_seed(&seed.to_vec())?;
+
+        Ok(Self {
+            eth_signer,
+            signing_key,
+            account_id: None,
+            create2: None,
+        })
+    }
+
+    /// Turns the wallet into a `CREATE2` account, its rollup address becomes the address of
+    /// the contract deployed with `create2` and the signing key.
+    pub fn set_create2(&mut self, create2: ChangePubKeyCREATE2Data) {
+        self.create2 = Some(create2);
+    }
+
+    /// Creates a wallet with a random Rootstock key.
+    pub async fn random() -> Result<Self, ClientError> {
+        Self::new(LocalWallet::new(&mut rand::thread_rng())).await
+    }
+
+    /// Rollup address of the account, the Rootstock address unless it is a `CREATE2` account.
+    pub fn address(&self) -> Address {
+        match &self.create2 {
+            Some(create2) => create2.get_address(&self.pub_key_hash()),
+            None => self.eth_signer.address(),
+        }
+    }
+
+    /// Rootstock key of the account, which sends its L1 transactions.
+    pub fn eth_signer(&self) -> &LocalWallet {
+        &self.eth_signer
+    }
+
+    pub fn pub_key_hash(&self) -> PubKeyHash {
+        self.signing_key.pub_key_hash()
+    }
+
+    pub fn account_id(&self) -> Option<AccountId> {
+        self.account_id
+    }
+
+    /// Sets the id assigned by the rollup, known once the first deposit or transfer to the account is committed.
+    pub fn set_account_id(&mut self, account_id: AccountId) {
+        self.account_id = Some(account_id);
+    }
+
+    fn require_account_id(&self) -> Result<AccountId, ClientError> {
+        self.account_id
+            .ok_or_else(|| ClientError::MissingRequiredField("account_id".to_string()))
+    }
+
+    async fn eth_sign(&self, message: &[u8]) -> Result<PackedEthSignature, ClientError> {
+        self.eth_signer
+            .sign_message(message)
+            .await
+            .map(PackedEthSignature)
+            .map_err(|err| ClientError::SigningError(err.to_string()))
+    }
+
+    pub async fn sign_transfer(
+        &self,
+        to: Address,
+        token: &Token,
+        amount: BigUint,
+        fee: BigUint,
+        nonce: Nonce,
+        time_range: TimeRange,
+    ) -> Result<SignedTx, ClientError> {
+        if !is_token_amount_packable(&amount) || !is_fee_amount_packable(&fee) {
+            return Err(ClientError::NotPackableValue);
+        }
+        let mut transfer = Transfer {
+            account_id: self.require_account_id()?,
+            from: self.address(),
+            to,
+            token: token.id,
+            amount,
+            fee,
+            nonce,
+            signature: TxSignature::default(),
+            time_range,
+        };
+        transfer.signature = self.signing_key.sign(&transfer.get_bytes());
+        let message = transfer.get_ethereum_sign_message(&token.symbol, token.decimals);
+        let eth_signature = self.eth_sign(message.as_bytes()).await?;
+
+        Ok((ZkSyncTx::Transfer(Box::new(transfer)), Some(eth_signature)))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn sign_withdraw(
+        &self,
+        to: Address,
+        token: &Token,
+        amount: BigUint,
+        fee: BigUint,
+        nonce: Nonce,
+        fast: bool,
+        time_range: TimeRange,
+    ) -> Result<SignedTx, ClientError> {
+        if amount.bits() > 128 || !is_fee_amount_packable(&fee) {
+            return Err(ClientError::NotPackableValue);
+        }
+        let mut withdraw = Withdraw {
+            account_id: self.require_account_id()?,
+            from: self.address(),
+            to,
+            token: token.id,
+            amount,
+            fee,
+            nonce,
+            signature: TxSignature::default(),
+            fast,
+            time_range,
+        };
+        withdraw.signature = self.signing_key.sign(&withdraw.get_bytes());
+        let message = withdraw.get_ethereum_sign_message(&token.symbol, token.decimals);
+        let eth_signature = self.eth_sign(message.as_bytes()).await?;
+
+        Ok((ZkSyncTx::Withdraw(Box::new(withdraw)), Some(eth_signature)))
+    }
+
+    pub async fn sign_mint_nft(
+        &self,
+        recipient: Address,
+        content_hash: H256,
+        fee_token: &Token,
+        fee: BigUint,
+        nonce: Nonce,
+    ) -> Result<SignedTx, ClientError> {
+        if !is_fee_amount_packable(&fee) {
+            return Err(ClientError::NotPackableValue);
+        }
+        let mut mint_nft = MintNFT {
+            creator_id: self.require_account_id()?,
+            creator_address: self.address(),
+            content_hash,
+            recipient,
+            fee,
+            fee_token: fee_token.id,
+            nonce,
+            signature: TxSignature::default(),
+        };
+        mint_nft.signature = self.signing_key.sign(&mint_nft.get_bytes());
+        let message = mint_nft.get_ethereum_sign_message(&fee_token.symbol, fee_token.decimals);
+        let eth_signature = self.eth_sign(message.as_bytes()).await?;
+
+        Ok((ZkSyncTx::MintNFT(Box::new(mint_nft)), Some(eth_signature)))
+    }
+
+    /// Signs the withdrawal of the NFT `token` owned by this account, the fee paid in `fee_token`.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn sign_withdraw_nft(
+        &self,
+        to: Address,
+        token: TokenId,
+        fee_token: &Token,
+        fee: BigUint,
+        nonce: Nonce,
+        fast: bool,
+        time_range: TimeRange,
+    ) -> Result<SignedTx, ClientError> {
+        if !is_fee_amount_packable(&fee) {
+            return Err(ClientError::NotPackableValue);
+        }
+        let mut withdraw_nft = WithdrawNFT {
+            account_id: self.require_account_id()?,
+            from: self.address(),
+            to,
+            token,
+            fee_token: fee_token.id,
+            fee,
+            nonce,
+            signature: TxSignature::default(),
+            fast,
+            time_range,
+        };
+        withdraw_nft.signature = self.signing_key.sign(&withdraw_nft.get_bytes());
+        let message = withdraw_nft.get_ethereum_sign_message(&fee_token.symbol, fee_token.decimals);
+        let eth_signature = self.eth_sign(message.as_bytes()).await?;
+
+        Ok((
+            ZkSyncTx::WithdrawNFT(Box::new(withdraw_nft)),
+            Some(eth_signature),
+        ))
+    }
+
+    /// Signs the forced exit of `target`, which withdraws its whole `token` balance; the fee is
+    /// paid by this account in the same token.
+    ///
+    /// Only the L2 signature is needed, no message is signed with the Rootstock key.
+    pub async fn sign_forced_exit(
+        &self,
+        target: Address,
+        token: &Token,
+        fee: BigUint,
+        nonce: Nonce,
+        time_range: TimeRange,
+    ) -> Result<SignedTx, ClientError> {
+        if !is_fee_amount_packable(&fee) {
+            return Err(ClientError::NotPackableValue);
+        }
+        let mut forced_exit = ForcedExit {
+            initiator_account_id: self.require_account_id()?,
+            target,
+            token: token.id,
+            fee,
+            nonce,
+            signature: TxSignature::default(),
+            time_range,
+        };
+        forced_exit.signature = self.signing_key.sign(&forced_exit.get_bytes());
+
+        Ok((ZkSyncTx::ForcedExit(Box::new(forced_exit)), None))
+    }
+
+    /// Signs the `ChangePubKey` setting this wallet's signing key on the rollup.
+    ///
+    /// `Onchain` authorization requires the key to be registered on the contract beforehand,
+    /// `CREATE2` authorization a wallet turned into a `CREATE2` account with `set_create2`.
+    pub async fn sign_change_pub_key(
+        &self,
+        fee_token: &Token,
+        fee: BigUint,
+        nonce: Nonce,
+        auth_type: ChangePubKeyFeeType,
+        time_range: TimeRange,
+    ) -> Result<SignedTx, ClientError> {
+        if !is_fee_amount_packable(&fee) {
+            return Err(ClientError::NotPackableValue);
+        }
+        let account_id = self.require_account_id()?;
+        let eth_auth_data = match auth_type {
+            ChangePubKeyFeeType::Onchain => ChangePubKeyEthAuthData::Onchain,
+            ChangePubKeyFeeType::ECDSA => {
+                let batch_hash = H256::zero();
+                let data = ChangePubKey::get_eth_signed_data(
+                    &self.pub_key_hash(),
+                    nonce,
+                    account_id,
+                    batch_hash,
+                );
+                ChangePubKeyEthAuthData::ECDSA {
+                    eth_signature: self.eth_sign(&data).await?,
+                    batch_hash,
+                }
+            }
+            ChangePubKeyFeeType::CREATE2 => self
+                .create2
+                .clone()
+                .map(ChangePubKeyEthAuthData::CREATE2)
+                .ok_or_else(|| ClientError::MissingRequiredField("create2".to_string()))?,
+        };
+        let mut change_pub_key = ChangePubKey {
+            account_id,
+            account: self.address(),
+            new_pk_hash: self.pub_key_hash(),
+            fee_token: fee_token.id,
+            fee,
+            nonce,
+            signature: TxSignature::default(),
+            eth_auth_data,
+            time_range,
+        };
+        change_pub_key.signature = self.signing_key.sign(&change_pub_key.get_bytes());
+
+        Ok((ZkSyncTx::ChangePubKey(Box::new(change_pub_key)), None))
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+
+use ethers::types::Address;
+use thiserror::Error;
+
+use crate::rollup::provider::{ClientError, Provider, ResponseResult};
+use crate::rollup::types::Nonce;
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum NonceError {
+    #[error("Account {0:?} has no known nonce")]
+    UnknownAccount(Address),
+    #[error("Account {0:?} ran out of nonces")]
+    NoncesExhausted(Address),
+}
+
+/// Nonce reserved for one transaction, handed back once the server answered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NonceLease {
+    pub address: Address,
+    pub nonce: Nonce,
+    /// Reset of the account the nonce was reserved after, leases of earlier resets are stale.
+    epoch: u64,
+}
+
+/// What a rejected transaction did to the nonces of its account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonceRejection {
+    /// The nonces were already reset after the lease was taken, nothing changed.
+    Stale,
+    /// The nonce was not consumed; the account goes back to it and the `invalidated`
+    /// transactions reserved after it will be rejected as well.
+    RolledBack { invalidated: usize },
+    /// The server disagreed on the nonce, the account must be reconciled with `account_info`.
+    Mismatch,
+}
+
+#[derive(Debug, Default)]
+struct AccountNonces {
+    next: Nonce,
+    in_flight: BTreeSet<Nonce>,
+    /// Highest nonce the server accepted since the last reset.
+    accepted: Option<Nonce>,
+    epoch: u64,
+}
+
+impl AccountNonces {
+    fn reset(&mut self, next: Nonce) -> usize {
+        let invalidated = self.in_flight.len();
+        self.next = next;
+        self.in_flight.clear();
+        self.accepted = None;
+        self.epoch += 1;
+        invalidated
+    }
+}
+
+/// Local nonces of the accounts, shared by the tasks submitting from them.
+///
+/// Every transaction reserves its nonce before it is signed, so concurrent submissions
+/// from one account never sign the same nonce. A rejection that did not consume its nonce
+/// leaves a gap the transactions reserved after it fall into; the account goes back to the
+/// rejected nonce and the answers of the transactions still in flight are ignored. A nonce
+/// mismatch means the local view is off, and only `account_info` can tell the right nonce.
+#[derive(Debug, Default)]
+pub struct NonceManager {
+    accounts: Mutex<HashMap<Address, AccountNonces>>,
+}
+
+impl NonceManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the nonce the account's next transaction must use, e.g. the committed nonce from `account_info`.
+    pub fn set_nonce(&self, address: Address, nonce: Nonce) {
+        let mut accounts = self.accounts.lock().unwrap();
+        accounts.entry(address).or_default().reset(nonce);
+    }
+
+    pub fn next_nonce(&self, address: &Address) -> Option<Nonce> {
+        let accounts = self.accounts.lock().unwrap();
+        accounts.get(address).map(|account| account.next)
+    }
+
+    /// Number of transactions of the account waiting for the server's answer.
+    pub fn in_flight(&self, address: &Address) -> usize {
+        let accounts = self.accounts.lock().unwrap();
+        accounts
+            .get(address)
+            .map_or(0, |account| account.in_flight.len())
+    }
+
+    pub fn reserve(&self, address: Address) -> Result<NonceLease, NonceError> {
+        let mut accounts = self.accounts.lock().unwrap();
+        let account = accounts
+            .get_mut(&address)
+            .ok_or(NonceError::UnknownAccount(address))?;
+        let nonce = account.next;
+        account.next = nonce
+            .checked_next()
+            .ok_or(NonceError::NoncesExhausted(address))?;
+        account.in_flight.insert(nonce);
+        Ok(NonceLease {
+            address,
+            nonce,
+            epoch: account.epoch,
+        })
+    }
+
+    /// Marks the nonce as consumed by a transaction the server accepted.
+    pub fn accepted(&self, lease: NonceLease) {
+        let mut accounts = self.accounts.lock().unwrap();
+        let Some(account) = accounts.get_mut(&lease.address) else {
+            return;
+        };
+        if account.epoch == lease.epoch {
+            account.in_flight.remove(&lease.nonce);
+            account.accepted = account.accepted.max(Some(lease.nonce));
+        }
+    }
+
+    /// Handles a transaction the server rejected with `err`.
+    pub fn rejected(&self, lease: NonceLease, err: &ClientError) -> NonceRejection {
+        let mut accounts = self.accounts.lock().unwrap();
+        let Some(account) = accounts.get_mut(&lease.address) else {
+            return NonceRejection::Stale;
+        };
+        if account.epoch != lease.epoch {
+            return NonceRejection::Stale;
+        }
+        account.in_flight.remove(&lease.nonce);
+        if is_nonce_mismatch(err) {
+            return NonceRejection::Mismatch;
+        }
+        let invalidated = account.reset(lease.nonce);
+        NonceRejection::RolledBack { invalidated }
+    }
+
+    /// Continues from the committed nonce reported by the server.
+    ///
+    /// Accepted transactions are not committed right away, so the account never goes back
+    /// behind the ones accepted since the last reset.
+    pub fn reconcile(&self, address: Address, committed: Nonce) -> Nonce {
+        let mut accounts = self.accounts.lock().unwrap();
+        let account = accounts.entry(address).or_default();
+        let after_accepted = account.accepted.and_then(Nonce::checked_next);
+        let next = after_accepted.map_or(committed, |accepted| accepted.max(committed));
+        account.reset(next);
+        next
+    }
+
+    /// Reconciles the account with the committed nonce from `account_info`.
+    pub async fn reconcile_with<P: Provider + Sync>(
+        &self,
+        provider: &P,
+        address: Address,
+    ) -> ResponseResult<Nonce> {
+        let info = provider.account_info(address).await?;
+        Ok(self.reconcile(address, info.committed.nonce))
+    }
+
+    /// Handles a rejection, reconciling the account with the server on a nonce mismatch.
+    pub async fn recover<P: Provider + Sync>(
+        &self,
+        provider: &P,
+        lease: NonceLease,
+        err: &ClientError,
+    ) -> ResponseResult<NonceRejection> {
+        let rejection = self.rejected(lease, err);
+        if rejection == NonceRejection::Mismatch {
+            self.reconcile_with(provider, lease.address).await?;
+        }
+        Ok(rejection)
+    }
+}
+
+/// Whether the server refused a transaction because of its nonce.
+pub fn is_nonce_mismatch(err: &ClientError) -> bool {
+    err.to_string().to_lowercase().contains("nonce")
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+
+    /// Tests concurrent reservations, rollback of a rejected nonce and reconciliation.
+    #[test]
+    fn test_nonce_manager() {
+        let alice = Address::from_low_u64_be(1);
+        let manager = Arc::new(NonceManager::new());
+        manager.set_nonce(alice, Nonce(10));
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let manager = manager.clone();
+                std::thread::spawn(move || {
+                    (0..25)
+                        .map(|_| manager.reserve(alice).unwrap())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let mut leases: Vec<NonceLease> = threads
+            .into_iter()
+            .flat_map(|thread| thread.join().unwrap())
+            .collect();
+        leases.sort_by_key(|lease| lease.nonce);
+        let nonces: BTreeSet<Nonce> = leases.iter().map(|lease| lease.nonce).collect();
+        assert_eq!(nonces.len(), 100);
+        assert_eq!(manager.next_nonce(&alice), Some(Nonce(110)));
+
+        manager.accepted(leases[0]);
+        let rejection = manager.rejected(leases[1], &ClientError::IncorrectInput);
+        assert_eq!(rejection, NonceRejection::RolledBack { invalidated: 98 });
+        assert_eq!(manager.next_nonce(&alice), Some(Nonce(11)));
+        assert_eq!(
+            manager.rejected(leases[2], &ClientError::IncorrectInput),
+            NonceRejection::Stale
+        );
+
+        let lease = manager.reserve(alice).unwrap();
+        manager.accepted(lease);
+        let mismatch = manager.reserve(alice).unwrap();
+        let err = ClientError::NetworkError("Nonce mismatch".to_string());
+        assert_eq!(manager.rejected(mismatch, &err), NonceRejection::Mismatch);
+        // The server has not committed the accepted nonce 11 yet.
+        assert_eq!(manager.reconcile(alice, Nonce(10)), Nonce(12));
+        assert_eq!(manager.reconcile(alice, Nonce(15)), Nonce(15));
+        assert_eq!(manager.in_flight(&alice), 0);
+    }
+}
@@ -0,0 +1,92 @@
+use sha2::{Digest, Sha256};
+use zksync_crypto::franklin_crypto::alt_babyjubjub::fs::FsRepr;
+use zksync_crypto::franklin_crypto::alt_babyjubjub::FixedGenerators;
+use zksync_crypto::franklin_crypto::bellman::pairing::ff::{PrimeField, PrimeFieldRepr};
+use zksync_crypto::franklin_crypto::eddsa::Seed;
+use zksync_crypto::primitives::rescue_hash_tx_msg;
+use zksync_crypto::{params, priv_key_from_fs, public_key_from_private, Fs, PrivateKey};
+
+use crate::rollup::provider::ClientError;
+use crate::rollup::types::pubkey_hash::PubKeyHash;
+use crate::rollup::types::tx::signature::{PACKED_PUBLIC_KEY_LEN, PACKED_SIGNATURE_LEN};
+use crate::rollup::types::tx::TxSignature;
+
+/// Message whose Rootstock signature seeds the L2 signing key of an account.
+///
+/// Wallets derive the key from the same message, so an account funded by the
+/// simulator can be taken over by the owner of the Rootstock key.
+pub const SIGNING_KEY_SEED_MESSAGE: &str =
+    "Access zkSync account.\n\nOnly sign this message for a trusted client!";
+
+/// L2 signing key of an account, authorized on the rollup by a `ChangePubKey` transaction.
+pub struct SigningKey {
+    private_key: PrivateKey,
+    pub_key_hash: PubKeyHash,
+}
+
+impl SigningKey {
+    /// Derives the key from a seed, normally the Rootstock signature of [`SIGNING_KEY_SEED_MESSAGE`].
+    pub fn from_seed(seed: &[u8]) -> Result<Self, ClientError> {
+        if seed.len() < 32 {
+            return Err(ClientError::SeedTooShort);
+        }
+
+        let mut effective_seed = Sha256::digest(seed).to_vec();
+        let private_key = loop {
+            let raw_private_key = Sha256::digest(&effective_seed).to_vec();
+            let mut fs_repr = FsRepr::default();
+            fs_repr
+                .read_be(&raw_private_key[..])
+                .expect("sha256 output fits the field representation");
+            match Fs::from_repr(fs_repr) {
+                Ok(fs) => break priv_key_from_fs(fs),
+                // Not a valid field element, hash again.
+                Err(_) => effective_seed = raw_private_key,
+            }
+        };
+
+        Ok(Self {
+            pub_key_hash: PubKeyHash::from_privkey(&private_key),
+            private_key,
+        })
+    }
+
+    pub fn pub_key_hash(&self) -> PubKeyHash {
+        self.pub_key_hash
+    }
+
+    /// Signs the encoded transaction bytes with musig over the Rescue hash of the message.
+    pub fn sign(&self, message: &[u8]) -> TxSignature {
+        let hashed_message = rescue_hash_tx_msg(message);
+        let seed = Seed::deterministic_seed(&self.private_key, &hashed_message);
+        let signature = self.private_key.musig_rescue_sign(
+            &hashed_message,
+            &seed,
+            FixedGenerators::SpendingKeyGenerator,
+            &params::RESCUE_PARAMS,
+            &params::JUBJUB_PARAMS,
+        );
+
+        let mut pub_key = Vec::with_capacity(PACKED_PUBLIC_KEY_LEN);
+        public_key_from_private(&self.private_key)
+            .write(&mut pub_key)
+            .expect("writing to a vector never fails");
+        let mut packed_signature = Vec::with_capacity(PACKED_SIGNATURE_LEN);
+        signature
+            .r
+            .write(&mut packed_signature)
+            .expect("writing to a vector never fails");
+        signature
+            .s
+            .into_repr()
+            .write_le(&mut packed_signature)
+            .expect("writing to a vector never fails");
+
+        TxSignature {
+            pub_key: pub_key.try_into().expect("packed public key is 32 bytes"),
+            signature: packed_signature
+                .try_into()
+                .expect("packed signature is 64 bytes"),
+        }
+    }
+}